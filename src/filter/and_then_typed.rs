@@ -0,0 +1,98 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{ready, TryFuture};
+use pin_project::pin_project;
+
+use super::{Filter, FilterBase, Func, Internal};
+use crate::generic::Either;
+use crate::reply::Reply;
+
+#[derive(Clone, Copy, Debug)]
+pub struct AndThenTyped<T, F> {
+    pub(super) filter: T,
+    pub(super) callback: F,
+}
+
+impl<T, F> FilterBase for AndThenTyped<T, F>
+where
+    T: Filter,
+    F: Func<T::Extract> + Clone + Send,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: Reply,
+{
+    type Extract = (Either<(<F::Output as TryFuture>::Ok,), (<F::Output as TryFuture>::Error,)>,);
+    type Error = T::Error;
+    type Future = AndThenTypedFuture<T, F>;
+    #[inline]
+    fn filter(&self, _: Internal) -> Self::Future {
+        AndThenTypedFuture {
+            state: State::First(self.filter.filter(Internal), self.callback.clone()),
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct AndThenTypedFuture<T, F>
+where
+    T: Filter,
+    F: Func<T::Extract>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: Reply,
+{
+    #[pin]
+    state: State<T, F>,
+}
+
+#[pin_project(project = StateProj)]
+enum State<T, F>
+where
+    T: Filter,
+    F: Func<T::Extract>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: Reply,
+{
+    First(#[pin] T::Future, F),
+    Second(#[pin] F::Output),
+    Done,
+}
+
+impl<T, F> Future for AndThenTypedFuture<T, F>
+where
+    T: Filter,
+    F: Func<T::Extract>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: Reply,
+{
+    type Output = Result<
+        (Either<(<F::Output as TryFuture>::Ok,), (<F::Output as TryFuture>::Error,)>,),
+        T::Error,
+    >;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            let pin = self.as_mut().project();
+            let (ex1, second) = match pin.state.project() {
+                StateProj::First(first, second) => match ready!(first.try_poll(cx)) {
+                    Ok(first) => (first, second),
+                    Err(err) => return Poll::Ready(Err(err)),
+                },
+                StateProj::Second(second) => {
+                    let ex3 = match ready!(second.try_poll(cx)) {
+                        Ok(item) => Either::A((item,)),
+                        Err(err) => Either::B((err,)),
+                    };
+                    self.set(AndThenTypedFuture { state: State::Done });
+                    return Poll::Ready(Ok((ex3,)));
+                }
+                StateProj::Done => panic!("polled after complete"),
+            };
+            let fut2 = second.call(ex1);
+            self.set(AndThenTypedFuture {
+                state: State::Second(fut2),
+            });
+        }
+    }
+}
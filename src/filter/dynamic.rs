@@ -0,0 +1,76 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use super::{BoxedFilter, FilterBase, Internal, Tuple};
+use crate::reject::Rejection;
+
+/// A `Filter` that can be replaced while it is served.
+///
+/// Every request runs the `BoxedFilter` current when it arrives, so
+/// replacing it, such as after reloading a configuration or a plugin,
+/// doesn't affect requests already being handled, and doesn't drop any
+/// connection. Clones share the same filter, so one can be served while
+/// another is kept as a handle to replace it.
+///
+/// # Example
+///
+/// ```
+/// use warp::filters::DynamicFilter;
+/// use warp::Filter;
+///
+/// let routes = DynamicFilter::new(warp::any().map(|| "v1").boxed());
+/// let server = warp::serve_dynamic(routes.clone());
+///
+/// // Later on...
+/// routes.replace(warp::any().map(|| "v2").boxed());
+/// ```
+pub struct DynamicFilter<T: Tuple> {
+    current: Arc<ArcSwap<BoxedFilter<T>>>,
+}
+
+impl<T: Tuple + Send> DynamicFilter<T> {
+    /// Create a `DynamicFilter` serving `filter` at first.
+    pub fn new(filter: BoxedFilter<T>) -> DynamicFilter<T> {
+        DynamicFilter {
+            current: Arc::new(ArcSwap::from_pointee(filter)),
+        }
+    }
+
+    /// Replace the filter, for the requests arriving from now on.
+    pub fn replace(&self, filter: BoxedFilter<T>) {
+        self.current.store(Arc::new(filter));
+    }
+
+    /// Get the current filter.
+    pub fn current(&self) -> BoxedFilter<T> {
+        BoxedFilter::clone(&self.current.load())
+    }
+}
+
+impl<T: Tuple> Clone for DynamicFilter<T> {
+    fn clone(&self) -> DynamicFilter<T> {
+        DynamicFilter {
+            current: self.current.clone(),
+        }
+    }
+}
+
+impl<T: Tuple> fmt::Debug for DynamicFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicFilter").finish()
+    }
+}
+
+impl<T: Tuple + Send> FilterBase for DynamicFilter<T> {
+    type Extract = T;
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<T, Rejection>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        self.current.load().filter(Internal)
+    }
+}
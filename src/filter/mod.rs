@@ -1,12 +1,18 @@
 mod and;
 mod and_then;
+mod and_then_typed;
 mod boxed;
+mod dynamic;
 mod map;
 mod map_err;
+mod optional;
 mod or;
 mod or_else;
 mod recover;
+mod recover_scoped;
 pub(crate) mod service;
+mod singleflight;
+mod then;
 mod unify;
 mod untuple_one;
 mod wrap;
@@ -16,21 +22,29 @@ use std::pin::Pin;
 
 use futures::{future, TryFuture, TryFutureExt};
 
+use crate::filters::state::{Provide, State};
 pub(crate) use crate::generic::{one, Combine, Either, Func, HList, One, Tuple};
 use crate::reject::{CombineRejection, IsReject, Rejection};
 use crate::route::{self, Route};
 
 pub(crate) use self::and::And;
-use self::and_then::AndThen;
+pub(crate) use self::and_then::AndThen;
+use self::and_then_typed::AndThenTyped;
 pub use self::boxed::BoxedFilter;
+pub use self::dynamic::DynamicFilter;
 pub(crate) use self::map::Map;
 pub(crate) use self::map_err::MapErr;
+use self::optional::{Optional, OrDefault};
 pub(crate) use self::or::Or;
 use self::or_else::OrElse;
 use self::recover::Recover;
+use self::recover_scoped::RecoverScoped;
+use self::singleflight::SingleFlight;
+use self::then::Then;
 use self::unify::Unify;
 use self::untuple_one::UntupleOne;
-pub(crate) use self::wrap::{Wrap, WrapSealed};
+pub(crate) use self::wrap::WrapSealed;
+pub use self::wrap::{map_request_body, map_response_body, wrap_fn, Wrap};
 
 // A crate-private base trait, allowing the actual `filter` method to change
 // signatures without it being a breaking change.
@@ -145,6 +159,37 @@ pub trait Filter: FilterBase {
         }
     }
 
+    /// Composes a new `Filter` of either this or the other filter, when both
+    /// extract the same type.
+    ///
+    /// This is the same as calling [`or`](Filter::or) followed by
+    /// [`unify`](Filter::unify), so the extracted value is simply the shared
+    /// type, instead of an `Either` of the two.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// // Match either `/:u32` or `/id/:u32`, extracting a `u32` either way.
+    /// let id = warp::path::param::<u32>()
+    ///     .unify_or(warp::path("id").and(warp::path::param::<u32>()))
+    ///     .map(|id: u32| format!("id = {}", id));
+    /// ```
+    fn unify_or<F>(self, other: F) -> Unify<Or<Self, F>>
+    where
+        Self: Filter<Error = Rejection> + Sized,
+        F: Filter<Extract = Self::Extract>,
+        F::Error: CombineRejection<Self::Error>,
+    {
+        Unify {
+            filter: Or {
+                first: self,
+                second: other,
+            },
+        }
+    }
+
     /// Composes this `Filter` with a function receiving the extracted value.
     ///
     ///
@@ -203,6 +248,8 @@ pub trait Filter: FilterBase {
     ///
     /// The `Error` type of the return `Future` needs be a `Rejection`, which
     /// means most futures will need to have their error mapped into one.
+    /// To reply with an application error directly instead, use
+    /// [`and_then_typed`](Filter::and_then_typed).
     ///
     /// # Example
     ///
@@ -231,6 +278,83 @@ pub trait Filter: FilterBase {
         }
     }
 
+    /// Composes this `Filter` with an async function receiving the
+    /// extracted value.
+    ///
+    /// Like [`map`](Filter::map), the function cannot reject the request:
+    /// the output of its future is the extracted value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// async fn greet(name: String) -> String {
+    ///     format!("Hello, {}!", name)
+    /// }
+    ///
+    /// let route = warp::path::param().then(greet);
+    /// ```
+    fn then<F>(self, fun: F) -> Then<Self, F>
+    where
+        Self: Sized,
+        F: Func<Self::Extract> + Clone,
+        F::Output: Future + Send,
+    {
+        Then {
+            filter: self,
+            callback: fun,
+        }
+    }
+
+    /// Composes this `Filter` with a function returning a `TryFuture` whose
+    /// error is a typed [`Reply`](crate::Reply), instead of a `Rejection`.
+    ///
+    /// An error of the returned future is not a rejection: it is not
+    /// combined with other routes or passed to `recover`, but replied as is.
+    /// This lets handlers use their own error enum, without having to find
+    /// it in a `Rejection` later on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::{http::StatusCode, Filter, Reply};
+    ///
+    /// enum ApiError {
+    ///     NotFound,
+    ///     Forbidden,
+    /// }
+    ///
+    /// impl Reply for ApiError {
+    ///     fn into_response(self) -> warp::reply::Response {
+    ///         match self {
+    ///             ApiError::NotFound => StatusCode::NOT_FOUND.into_response(),
+    ///             ApiError::Forbidden => StatusCode::FORBIDDEN.into_response(),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let route = warp::path::param().and_then_typed(|id: u64| async move {
+    ///     match id {
+    ///         0 => Err(ApiError::Forbidden),
+    ///         1 => Ok(format!("Hello #{}", id)),
+    ///         _ => Err(ApiError::NotFound),
+    ///     }
+    /// });
+    /// ```
+    fn and_then_typed<F>(self, fun: F) -> AndThenTyped<Self, F>
+    where
+        Self: Sized,
+        F: Func<Self::Extract> + Clone,
+        F::Output: TryFuture + Send,
+        <F::Output as TryFuture>::Error: crate::reply::Reply,
+    {
+        AndThenTyped {
+            filter: self,
+            callback: fun,
+        }
+    }
+
     /// Compose this `Filter` with a function receiving an error.
     ///
     /// The function should return some `TryFuture` type yielding the
@@ -268,6 +392,51 @@ pub trait Filter: FilterBase {
         }
     }
 
+    /// Like [`recover`](Filter::recover), but only handles rejections from
+    /// requests that are within the scope of this `Filter`.
+    ///
+    /// A request is in scope once this `Filter` has matched part of its path,
+    /// such as a `warp::path("api")` prefix. Rejections of requests that never
+    /// entered the scope are passed on untouched, so sibling routes combined
+    /// with `or` still get a chance to match them. Rejections returned by
+    /// the handler are likewise propagated like any other rejection.
+    ///
+    /// This allows subtrees to have their own error formats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::convert::Infallible;
+    /// use warp::{http::StatusCode, Filter, Rejection};
+    ///
+    /// async fn api_error(err: Rejection) -> Result<impl warp::Reply, Infallible> {
+    ///     let json = warp::reply::json(&format!("{:?}", err));
+    ///     Ok(warp::reply::with_status(json, StatusCode::NOT_FOUND))
+    /// }
+    ///
+    /// let api = warp::path("api")
+    ///     .and(warp::path("users"))
+    ///     .map(|| "users")
+    ///     .recover_scoped(api_error);
+    ///
+    /// // `/web` is not handled by `api_error`, and reaches this route.
+    /// let web = warp::path("web").map(|| "web");
+    ///
+    /// let routes = api.or(web);
+    /// ```
+    fn recover_scoped<F>(self, fun: F) -> RecoverScoped<Self, F>
+    where
+        Self: Filter<Error = Rejection> + Sized,
+        F: Func<Rejection>,
+        F::Output: TryFuture + Send,
+        <F::Output as TryFuture>::Error: IsReject + Into<Rejection>,
+    {
+        RecoverScoped {
+            filter: self,
+            callback: fun,
+        }
+    }
+
     /// Unifies the extracted value of `Filter`s composed with `or`.
     ///
     /// When a `Filter` extracts some `Either<T, T>`, where both sides
@@ -298,6 +467,61 @@ pub trait Filter: FilterBase {
         Unify { filter: self }
     }
 
+    /// Makes a `Filter` extracting a single value optional.
+    ///
+    /// The new filter extracts `Some` value when this one matches, and
+    /// `None` instead of rejecting, which is the same as writing
+    /// `.map(Some).or(warp::any().map(|| None)).unify()`.
+    ///
+    /// Every rejection is turned into `None`, such as an invalid header
+    /// value as well as a missing one. Filters that should reject invalid
+    /// values have their own `optional` variant, like
+    /// [`header::optional`](crate::header::optional).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// let route = warp::path("hello")
+    ///     .and(warp::path::param::<String>().optional())
+    ///     .map(|name: Option<String>| {
+    ///         format!("Hello, {}!", name.as_deref().unwrap_or("World"))
+    ///     });
+    /// ```
+    fn optional<T>(self) -> Optional<Self>
+    where
+        Self: Filter<Extract = (T,)> + Sized,
+    {
+        Optional { filter: self }
+    }
+
+    /// Makes a `Filter` extracting a single value default to
+    /// `T::default()` instead of rejecting.
+    ///
+    /// This is [`optional`](Filter::optional), unwrapped with the default
+    /// value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// let route = warp::header::<u32>("x-page")
+    ///     .or_default()
+    ///     .map(|page: u32| format!("page {}", page));
+    /// ```
+    fn or_default<T>(self) -> OrDefault<Self, T>
+    where
+        Self: Filter<Extract = (T,)> + Sized,
+        T: Default,
+    {
+        Map {
+            filter: Optional { filter: self },
+            callback: Option::unwrap_or_default,
+        }
+    }
+
     /// Convenience method to remove one layer of tupling.
     ///
     /// This is useful for when things like `map` don't return a new value,
@@ -341,6 +565,82 @@ pub trait Filter: FilterBase {
         UntupleOne { filter: self }
     }
 
+    /// Coalesces concurrent identical `GET` and `HEAD` requests into a
+    /// single execution of this filter.
+    ///
+    /// Requests are identical when `key_fn`, called with the URI and headers
+    /// of each request, returns the same key. The first request runs the
+    /// filter, and while it does, identical requests wait for its reply
+    /// instead of running the filter themselves. The reply is then buffered
+    /// and sent to all of them. This protects expensive endpoints, such as
+    /// ones refilling a cache, from a stampede of requests.
+    ///
+    /// If the first request is rejected, or canceled, the waiting requests
+    /// run the filter on their own. Requests with other methods are never
+    /// coalesced.
+    ///
+    /// Since replies are buffered in memory, this shouldn't be used with
+    /// streaming bodies. The key should include whatever the reply depends
+    /// on, such as an `authorization` header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// let report = warp::path!("report" / u32)
+    ///     .map(|year: u32| {
+    ///         // Build an expensive report...
+    ///         format!("report for {}", year)
+    ///     })
+    ///     .singleflight(|uri: &warp::http::Uri, _: &warp::http::HeaderMap| uri.clone());
+    /// ```
+    fn singleflight<F, K>(self, key_fn: F) -> SingleFlight<Self, F, K>
+    where
+        Self: Filter + Sized,
+        Self::Extract: crate::reply::Reply,
+        Self::Error: Into<Rejection>,
+        F: Fn(&http::Uri, &http::HeaderMap) -> K,
+        K: std::hash::Hash + Eq,
+    {
+        SingleFlight {
+            filter: self,
+            key_fn,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Composes this filter with one extracting `state`, as a [`State`], so
+    /// handlers don't need to move a clone of it into every route.
+    ///
+    /// The state is registered for the request too, so filters running later
+    /// can extract it with [`warp::state`](crate::state()).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use warp::state::State;
+    /// use warp::Filter;
+    ///
+    /// let route = warp::any()
+    ///     .with_state(AtomicUsize::new(0))
+    ///     .map(|hits: State<AtomicUsize>| {
+    ///         format!("hit #{}", hits.fetch_add(1, Ordering::SeqCst) + 1)
+    ///     });
+    /// ```
+    ///
+    /// [`State`]: crate::state::State
+    fn with_state<T>(self, state: T) -> And<Self, Provide<T>>
+    where
+        Self: Sized,
+        <Self::Extract as Tuple>::HList: Combine<<(State<T>,) as Tuple>::HList>,
+        T: Send + Sync + 'static,
+        std::convert::Infallible: CombineRejection<Self::Error>,
+    {
+        self.and(crate::state::provide(state))
+    }
+
     /// Wraps the current filter with some wrapper.
     ///
     /// The wrapper may do some preparation work before starting this filter,
@@ -401,10 +701,6 @@ pub trait Filter: FilterBase {
 
 impl<T: FilterBase> Filter for T {}
 
-pub trait FilterClone: Filter + Clone {}
-
-impl<T: Filter + Clone> FilterClone for T {}
-
 fn _assert_object_safe() {
     fn _assert(_f: &dyn Filter<Extract = (), Error = (), Future = future::Ready<()>>) {}
 }
@@ -0,0 +1,63 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{ready, TryFuture};
+use pin_project::pin_project;
+
+use super::{Filter, FilterBase, Internal, Map};
+use crate::route;
+
+pub type OrDefault<F, T> = Map<Optional<F>, fn(Option<T>) -> T>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Optional<F> {
+    pub(super) filter: F,
+}
+
+impl<F, T> FilterBase for Optional<F>
+where
+    F: Filter<Extract = (T,)>,
+{
+    type Extract = (Option<T>,);
+    type Error = Infallible;
+    type Future = OptionalFuture<F::Future>;
+    #[inline]
+    fn filter(&self, _: Internal) -> Self::Future {
+        let idx = route::with(|route| route.matched_path_index());
+        OptionalFuture {
+            inner: self.filter.filter(Internal),
+            original_path_index: idx,
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct OptionalFuture<F> {
+    #[pin]
+    inner: F,
+    original_path_index: usize,
+}
+
+impl<F, T> Future for OptionalFuture<F>
+where
+    F: TryFuture<Ok = (T,)>,
+{
+    type Output = Result<(Option<T>,), Infallible>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let pin = self.project();
+        match ready!(pin.inner.try_poll(cx)) {
+            Ok((ex,)) => Poll::Ready(Ok((Some(ex),))),
+            Err(_) => {
+                // Like `or`, leave the path as it was before the rejection.
+                let idx = *pin.original_path_index;
+                route::with(|route| route.reset_matched_path_index(idx));
+                Poll::Ready(Ok((None,)))
+            }
+        }
+    }
+}
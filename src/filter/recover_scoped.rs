@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{ready, TryFuture};
+use pin_project::pin_project;
+
+use super::{Filter, FilterBase, Func, Internal};
+use crate::generic::Either;
+use crate::reject::{IsReject, Rejection};
+use crate::route;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RecoverScoped<T, F> {
+    pub(super) filter: T,
+    pub(super) callback: F,
+}
+
+impl<T, F> FilterBase for RecoverScoped<T, F>
+where
+    T: Filter<Error = Rejection>,
+    F: Func<Rejection> + Clone + Send,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: IsReject + Into<Rejection>,
+{
+    type Extract = (Either<T::Extract, (<F::Output as TryFuture>::Ok,)>,);
+    type Error = Rejection;
+    type Future = RecoverScopedFuture<T, F>;
+    #[inline]
+    fn filter(&self, _: Internal) -> Self::Future {
+        let idx = route::with(|route| route.matched_path_index());
+        RecoverScopedFuture {
+            state: State::First(self.filter.filter(Internal), self.callback.clone()),
+            original_path_index: PathIndex(idx),
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct RecoverScopedFuture<T, F>
+where
+    T: Filter<Error = Rejection>,
+    F: Func<Rejection>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: IsReject + Into<Rejection>,
+{
+    #[pin]
+    state: State<T, F>,
+    original_path_index: PathIndex,
+}
+
+#[pin_project(project = StateProj)]
+enum State<T, F>
+where
+    T: Filter<Error = Rejection>,
+    F: Func<Rejection>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: IsReject + Into<Rejection>,
+{
+    First(#[pin] T::Future, F),
+    Second(#[pin] F::Output),
+    Done,
+}
+
+#[derive(Copy, Clone)]
+struct PathIndex(usize);
+
+impl PathIndex {
+    fn reset_path(&self) {
+        route::with(|route| route.reset_matched_path_index(self.0));
+    }
+
+    /// Whether the filter matched some of the path before rejecting, meaning
+    /// the request was inside of its scope.
+    fn entered(&self) -> bool {
+        route::with(|route| route.matched_path_index() > self.0)
+    }
+}
+
+impl<T, F> Future for RecoverScopedFuture<T, F>
+where
+    T: Filter<Error = Rejection>,
+    F: Func<Rejection>,
+    F::Output: TryFuture + Send,
+    <F::Output as TryFuture>::Error: IsReject + Into<Rejection>,
+{
+    type Output = Result<(Either<T::Extract, (<F::Output as TryFuture>::Ok,)>,), Rejection>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            let pin = self.as_mut().project();
+            let (err, second) = match pin.state.project() {
+                StateProj::First(first, second) => match ready!(first.try_poll(cx)) {
+                    Ok(ex) => return Poll::Ready(Ok((Either::A(ex),))),
+                    Err(err) => (err, second),
+                },
+                StateProj::Second(second) => {
+                    let ex2 = match ready!(second.try_poll(cx)) {
+                        Ok(ex2) => Ok((Either::B((ex2,)),)),
+                        Err(e) => {
+                            self.original_path_index.reset_path();
+                            Err(e.into())
+                        }
+                    };
+                    self.set(RecoverScopedFuture {
+                        state: State::Done,
+                        ..*self
+                    });
+                    return Poll::Ready(ex2);
+                }
+                StateProj::Done => panic!("polled after complete"),
+            };
+
+            let entered = pin.original_path_index.entered();
+            pin.original_path_index.reset_path();
+            if !entered {
+                self.set(RecoverScopedFuture {
+                    state: State::Done,
+                    ..*self
+                });
+                return Poll::Ready(Err(err));
+            }
+
+            let fut2 = second.call(err);
+            self.set(RecoverScopedFuture {
+                state: State::Second(fut2),
+                ..*self
+            });
+        }
+    }
+}
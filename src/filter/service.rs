@@ -4,10 +4,11 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures::future::TryFuture;
+use futures::future::{self, TryFuture};
 use hyper::service::Service;
 use pin_project::pin_project;
 
+use crate::filters::ext::Connection;
 use crate::reject::IsReject;
 use crate::reply::{Reply, Response};
 use crate::route::{self, Route};
@@ -58,6 +59,51 @@ where
     FilteredService { filter }
 }
 
+/// Convert a `Filter` into a `Service` factory, with per-connection values.
+///
+/// The returned value is a "make service": it is called with each new
+/// connection, such as by `hyper::Server`, and `info` is called with that
+/// connection to produce a value for it. That value can then be extracted by
+/// any request on the connection with [`warp::ext::connection`][connection].
+///
+/// This is useful with a custom accept loop, to pass along things like the
+/// remote address or TLS details of each connection.
+///
+/// # Example
+///
+/// ```
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::net::SocketAddr;
+/// use hyper::server::conn::AddrStream;
+/// use warp::Filter;
+///
+/// let route = warp::ext::connection::<SocketAddr>()
+///     .map(|addr: SocketAddr| format!("Hello, {}!", addr));
+///
+/// let make_svc = warp::service_with_info(route, |conn: &AddrStream| {
+///     conn.remote_addr()
+/// });
+///
+/// hyper::Server::bind(&([127, 0, 0, 1], 3030).into())
+///     .serve(make_svc)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [connection]: crate::filters::ext::connection
+pub fn service_with_info<F, I>(filter: F, info: I) -> MakeServiceWithInfo<F, I>
+where
+    F: Filter + Clone,
+    <F::Future as TryFuture>::Ok: Reply,
+    <F::Future as TryFuture>::Error: IsReject,
+{
+    MakeServiceWithInfo {
+        service: FilteredService { filter },
+        info,
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct FilteredService<F> {
     filter: F,
@@ -75,7 +121,7 @@ where
         req: Request,
         remote_addr: Option<SocketAddr>,
     ) -> FilteredFuture<F::Future> {
-        debug_assert!(!route::is_set(), "nested route::set calls");
+        debug_assert!(route::can_set(), "nested route::set calls");
 
         let route = Route::new(req, remote_addr);
         let fut = route::set(&route, || self.filter.filter(super::Internal));
@@ -121,7 +167,7 @@ where
 
     #[inline]
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        debug_assert!(!route::is_set(), "nested route::set calls");
+        debug_assert!(route::can_set(), "nested route::set calls");
 
         let pin = self.project();
         let fut = pin.future;
@@ -135,3 +181,61 @@ where
         }
     }
 }
+
+#[derive(Copy, Clone, Debug)]
+pub struct MakeServiceWithInfo<F, I> {
+    service: FilteredService<F>,
+    info: I,
+}
+
+impl<'a, F, I, C, T> Service<&'a C> for MakeServiceWithInfo<F, I>
+where
+    F: Filter + Clone,
+    <F::Future as TryFuture>::Ok: Reply,
+    <F::Future as TryFuture>::Error: IsReject,
+    I: Fn(&C) -> T,
+    T: Clone + Send + Sync + 'static,
+{
+    type Response = ServiceWithInfo<F, T>;
+    type Error = Infallible;
+    type Future = future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, conn: &'a C) -> Self::Future {
+        future::ok(ServiceWithInfo {
+            service: self.service.clone(),
+            info: Connection((self.info)(conn)),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ServiceWithInfo<F, T> {
+    service: FilteredService<F>,
+    info: Connection<T>,
+}
+
+impl<F, T> Service<Request> for ServiceWithInfo<F, T>
+where
+    F: Filter,
+    <F::Future as TryFuture>::Ok: Reply,
+    <F::Future as TryFuture>::Error: IsReject,
+    T: Clone + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = FilteredFuture<F::Future>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        req.extensions_mut().insert(self.info.clone());
+        self.service.call_with_addr(req, None)
+    }
+}
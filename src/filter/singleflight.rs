@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use futures::channel::oneshot;
+use http::{HeaderMap, Method, StatusCode, Uri, Version};
+use hyper::Body;
+
+use super::{Filter, FilterBase, Internal};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route;
+
+type Waiters = Vec<oneshot::Sender<Shared>>;
+
+pub struct SingleFlight<T, F, K> {
+    pub(super) filter: T,
+    pub(super) key_fn: F,
+    pub(super) in_flight: Arc<Mutex<HashMap<(Method, K), Waiters>>>,
+}
+
+impl<T, F, K> Clone for SingleFlight<T, F, K>
+where
+    T: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        SingleFlight {
+            filter: self.filter.clone(),
+            key_fn: self.key_fn.clone(),
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+impl<T, F, K> std::fmt::Debug for SingleFlight<T, F, K>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SingleFlight")
+            .field("filter", &self.filter)
+            .finish()
+    }
+}
+
+impl<T, F, K> FilterBase for SingleFlight<T, F, K>
+where
+    T: Filter + Clone + Send + Sync + 'static,
+    T::Extract: Reply,
+    T::Error: Into<Rejection>,
+    T::Future: Send,
+    F: Fn(&Uri, &HeaderMap) -> K + Clone + Send + Sync + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let key = route::with(|route| {
+            let method = route.method();
+            if method == Method::GET || method == Method::HEAD {
+                Some((method.clone(), (self.key_fn)(route.uri(), route.headers())))
+            } else {
+                None
+            }
+        });
+        let key = match key {
+            Some(key) => key,
+            None => return Box::pin(run(self.filter.filter(Internal))),
+        };
+
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(waiters) = in_flight.get_mut(&key) {
+            let (tx, rx) = oneshot::channel();
+            waiters.push(tx);
+            drop(in_flight);
+            let filter = self.filter.clone();
+            return Box::pin(async move {
+                match rx.await {
+                    Ok(shared) => Ok((shared.to_response(),)),
+                    // The leading request was rejected or canceled, so
+                    // handle this one on its own.
+                    Err(_) => run(filter.filter(Internal)).await,
+                }
+            });
+        }
+        in_flight.insert(key.clone(), Vec::new());
+        drop(in_flight);
+
+        let guard = Leader {
+            in_flight: self.in_flight.clone(),
+            key: Some(key),
+        };
+        let fut = self.filter.filter(Internal);
+        Box::pin(async move {
+            let res = run(fut).await;
+            let waiters = guard.finish();
+            let (res,) = res?;
+            let shared = match Shared::buffer(res).await {
+                Ok(shared) => shared,
+                Err(err) => {
+                    log::error!("singleflight failed to buffer response body: {}", err);
+                    let mut res = Response::new(Body::empty());
+                    *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                    return Ok((res,));
+                }
+            };
+            for tx in waiters {
+                let _ = tx.send(shared.clone());
+            }
+            Ok((shared.to_response(),))
+        })
+    }
+}
+
+async fn run<F, T, E>(fut: F) -> Result<(Response,), Rejection>
+where
+    F: Future<Output = Result<T, E>>,
+    T: Reply,
+    E: Into<Rejection>,
+{
+    match fut.await {
+        Ok(reply) => Ok((reply.into_response(),)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Removes the key of the leading request once it completes, handing out
+/// the waiting requests. If the leading request is dropped before, the
+/// waiters are dropped with it, and handle their requests on their own.
+struct Leader<K: Hash + Eq> {
+    in_flight: Arc<Mutex<HashMap<K, Waiters>>>,
+    key: Option<K>,
+}
+
+impl<K: Hash + Eq> Leader<K> {
+    fn finish(mut self) -> Waiters {
+        self.remove()
+    }
+
+    fn remove(&mut self) -> Waiters {
+        match self.key.take() {
+            Some(key) => self
+                .in_flight
+                .lock()
+                .unwrap()
+                .remove(&key)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> Drop for Leader<K> {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// A buffered response, sent to every coalesced request.
+#[derive(Clone)]
+pub(super) struct Shared {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Shared {
+    async fn buffer(res: Response) -> Result<Shared, hyper::Error> {
+        let (parts, body) = res.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        Ok(Shared {
+            status: parts.status,
+            version: parts.version,
+            headers: parts.headers,
+            body,
+        })
+    }
+
+    fn to_response(&self) -> Response {
+        let mut res = Response::new(Body::from(self.body.clone()));
+        *res.status_mut() = self.status;
+        *res.version_mut() = self.version;
+        *res.headers_mut() = self.headers.clone();
+        res
+    }
+}
@@ -1,4 +1,15 @@
-use super::Filter;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{ready, TryFuture};
+use http::header::{HeaderMap, CONTENT_LENGTH};
+use hyper::Body;
+use pin_project::pin_project;
+
+use super::{Filter, FilterBase, Internal};
+use crate::reply::{Reply, Response};
+use crate::route;
 
 pub trait WrapSealed<F: Filter> {
     type Wrapped: Filter;
@@ -17,6 +28,13 @@ where
     }
 }
 
+/// A wrapper that can be applied to a `Filter` with [`Filter::with`].
+///
+/// Wrappers can run code before and after the filter they wrap, such as
+/// [`warp::log`](crate::log()) or [`warp::cors`](crate::cors()). This trait
+/// cannot be implemented directly; use [`wrap_fn`] to build a custom
+/// wrapper, or [`warp::tower::layer`](crate::tower::layer) to use a tower
+/// `Layer` as one.
 pub trait Wrap<F: Filter>: WrapSealed<F> {}
 
 impl<T, F> Wrap<F> for T
@@ -25,3 +43,249 @@ where
     F: Filter,
 {
 }
+
+/// Create a wrapper from a function that receives the wrapped filter.
+///
+/// The function can combine the filter with others that should run before
+/// or after it, and returns the resulting filter.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let api_version = warp::wrap_fn(|filter| {
+///     warp::path("v1").and(filter)
+/// });
+///
+/// let route = warp::path("users")
+///     .map(|| "users")
+///     .with(api_version);
+/// ```
+pub fn wrap_fn<F, T, U>(func: F) -> WrapFn<F>
+where
+    F: Fn(T) -> U,
+    T: Filter,
+    U: Filter,
+{
+    WrapFn { func }
+}
+
+/// A wrapper created with [`wrap_fn`].
+#[derive(Clone, Copy, Debug)]
+pub struct WrapFn<F> {
+    func: F,
+}
+
+impl<F, T, U> WrapSealed<T> for WrapFn<F>
+where
+    F: Fn(T) -> U,
+    T: Filter,
+    U: Filter,
+{
+    type Wrapped = U;
+
+    fn wrap(&self, filter: T) -> Self::Wrapped {
+        (self.func)(filter)
+    }
+}
+
+/// Create a wrapper rewriting the body of requests, before the filter it
+/// wraps reads it.
+///
+/// The function receives the headers and body of the request, and returns
+/// the body to replace it with. The body is a `Stream` of chunks, so the
+/// function can transform it as it is received, such as to redact fields
+/// or capture a copy for auditing, with [`Body::wrap_stream`]. The headers
+/// can be changed too, such as to set the `content-type` of the new body.
+///
+/// Since the new body may have another length, the `content-length` header
+/// of the request is removed. If the body was already taken by another
+/// filter, the function isn't called.
+///
+/// # Example
+///
+/// ```
+/// use futures::StreamExt;
+/// use warp::hyper::Body;
+/// use warp::Filter;
+///
+/// // Count the bytes of request bodies as they are read.
+/// let audit = warp::map_request_body(|_headers, body: Body| {
+///     let mut read = 0;
+///     Body::wrap_stream(body.inspect(move |chunk| {
+///         if let Ok(chunk) = chunk {
+///             read += chunk.len();
+///             log::debug!("read {} bytes of the body", read);
+///         }
+///     }))
+/// });
+///
+/// let route = warp::body::bytes()
+///     .map(|body: bytes::Bytes| format!("received {} bytes", body.len()))
+///     .with(audit);
+/// ```
+pub fn map_request_body<F>(func: F) -> MapRequestBody<F>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Clone + Send,
+{
+    MapRequestBody { func }
+}
+
+/// A wrapper created with [`map_request_body`].
+#[derive(Clone, Copy, Debug)]
+pub struct MapRequestBody<F> {
+    func: F,
+}
+
+impl<F, T> WrapSealed<T> for MapRequestBody<F>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Clone + Send,
+    T: Filter,
+{
+    type Wrapped = WithRequestBody<F, T>;
+
+    fn wrap(&self, filter: T) -> Self::Wrapped {
+        WithRequestBody {
+            func: self.func.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`map_request_body`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithRequestBody<F, T> {
+    func: F,
+    filter: T,
+}
+
+impl<F, T> FilterBase for WithRequestBody<F, T>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Send,
+    T: Filter,
+{
+    type Extract = T::Extract;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        route::with(|route| {
+            if let Some(body) = route.take_body() {
+                let headers = route.headers_mut();
+                headers.remove(CONTENT_LENGTH);
+                let body = (self.func)(headers, body);
+                route.restore_body(body);
+            }
+        });
+        self.filter.filter(Internal)
+    }
+}
+
+/// Create a wrapper rewriting the body of the replies of the filter it
+/// wraps.
+///
+/// The function receives the headers and body of the reply, and returns
+/// the body to replace it with. As with [`map_request_body`], the body is a
+/// `Stream`, so large replies can be transformed without buffering them,
+/// and the `content-length` header is removed.
+///
+/// Rejections are left alone, as they become replies later on.
+///
+/// # Example
+///
+/// ```
+/// use futures::{stream, StreamExt};
+/// use warp::hyper::Body;
+/// use warp::Filter;
+///
+/// // Wrap JSON replies in an envelope.
+/// let envelope = warp::map_response_body(|_headers, body: Body| {
+///     let start = stream::once(async { Ok::<_, warp::hyper::Error>(bytes::Bytes::from("{\"data\":")) });
+///     let end = stream::once(async { Ok::<_, warp::hyper::Error>(bytes::Bytes::from("}")) });
+///     Body::wrap_stream(start.chain(body).chain(end))
+/// });
+///
+/// let route = warp::path("users")
+///     .map(|| warp::reply::json(&["sean"]))
+///     .with(envelope);
+/// ```
+pub fn map_response_body<F>(func: F) -> MapResponseBody<F>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Clone + Send,
+{
+    MapResponseBody { func }
+}
+
+/// A wrapper created with [`map_response_body`].
+#[derive(Clone, Copy, Debug)]
+pub struct MapResponseBody<F> {
+    func: F,
+}
+
+impl<F, T> WrapSealed<T> for MapResponseBody<F>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Clone + Send,
+    T: Filter,
+    T::Extract: Reply,
+{
+    type Wrapped = WithResponseBody<F, T>;
+
+    fn wrap(&self, filter: T) -> Self::Wrapped {
+        WithResponseBody {
+            func: self.func.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`map_response_body`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithResponseBody<F, T> {
+    func: F,
+    filter: T,
+}
+
+impl<F, T> FilterBase for WithResponseBody<F, T>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Clone + Send,
+    T: Filter,
+    T::Extract: Reply,
+{
+    type Extract = (Response,);
+    type Error = T::Error;
+    type Future = WithResponseBodyFuture<F, T::Future>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        WithResponseBodyFuture {
+            func: self.func.clone(),
+            inner: self.filter.filter(Internal),
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct WithResponseBodyFuture<F, U> {
+    func: F,
+    #[pin]
+    inner: U,
+}
+
+impl<F, U> Future for WithResponseBodyFuture<F, U>
+where
+    F: Fn(&mut HeaderMap, Body) -> Body + Send,
+    U: TryFuture,
+    U::Ok: Reply,
+{
+    type Output = Result<(Response,), U::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let pin = self.project();
+        let reply = ready!(pin.inner.try_poll(cx))?;
+        let (mut parts, body) = reply.into_response().into_parts();
+        parts.headers.remove(CONTENT_LENGTH);
+        let body = (pin.func)(&mut parts.headers, body);
+        Poll::Ready(Ok((Response::from_parts(parts, body),)))
+    }
+}
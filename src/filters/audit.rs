@@ -0,0 +1,584 @@
+//! Audit logging
+//!
+//! An audit [`wrap`] records an [`Event`] for every request of the routes it
+//! wraps, replied or rejected: who made it, what it was, and how it was
+//! answered. Events are sent to an async sink, such as a function writing
+//! them to a database or a message queue.
+//!
+//! A [`Policy`] selects what is recorded besides the method, route, status
+//! and actor of each request: headers, query parameters, and the bodies of
+//! requests and responses. Values of sensitive keys, such as the
+//! `Authorization` header or a `password` field, are replaced with
+//! `"[REDACTED]"` before the event is emitted.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::audit::{Event, Policy};
+//! use warp::Filter;
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let policy = Policy::new()
+//!     .route("/accounts/:id")
+//!     .actor(|user: &User| user.0.clone())
+//!     .request_header("x-request-id")
+//!     .request_body(16 * 1024)
+//!     .redact("iban");
+//!
+//! let route = warp::path!("accounts" / u32)
+//!     .and(warp::ext::provide(warp::auth::bearer().map(User)))
+//!     .map(|id| format!("account #{}", id))
+//!     .with(warp::audit::wrap(
+//!         |event: Event| async move {
+//!             println!("{}", event.to_json());
+//!         },
+//!         policy,
+//!     ));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{Extensions, Method, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
+use serde_json::Value;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+const REDACTED: &str = "[REDACTED]";
+
+type Sink = Arc<dyn Fn(Event) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+type Actor = Arc<dyn Fn(&Extensions) -> Option<String> + Send + Sync>;
+type Capture = Arc<dyn Fn(&HeaderMap, &Bytes) -> Option<Value> + Send + Sync>;
+
+/// Create a wrapping filter recording audit events, selected by `policy`,
+/// and sending them to `sink`.
+///
+/// The response is sent once `sink` has handled the event, so no request is
+/// answered without being recorded.
+pub fn wrap<S, Fut>(sink: S, policy: Policy) -> Audit
+where
+    S: Fn(Event) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Audit {
+        sink: Arc::new(move |event| Box::pin(sink(event))),
+        policy: Arc::new(policy),
+    }
+}
+
+/// What is recorded in audit events, for [`wrap`].
+///
+/// By default, an event has the method, path, status and client address of
+/// a request, and the values of the `authorization`, `proxy-authorization`,
+/// `cookie`, `set-cookie` and `password` keys are redacted.
+#[derive(Clone)]
+pub struct Policy {
+    route: Option<Arc<str>>,
+    actor: Option<Actor>,
+    request_headers: Vec<HeaderName>,
+    response_headers: Vec<HeaderName>,
+    query: bool,
+    request_body: Option<u64>,
+    response_body: Option<u64>,
+    capture: Capture,
+    redact: Vec<String>,
+}
+
+impl Policy {
+    /// Creates the default policy.
+    pub fn new() -> Policy {
+        Policy {
+            route: None,
+            actor: None,
+            request_headers: Vec::new(),
+            response_headers: Vec::new(),
+            query: false,
+            request_body: None,
+            response_body: None,
+            capture: Arc::new(capture_body),
+            redact: [
+                "authorization",
+                "proxy-authorization",
+                "cookie",
+                "set-cookie",
+                "password",
+            ]
+            .iter()
+            .map(|key| (*key).to_owned())
+            .collect(),
+        }
+    }
+
+    /// Sets the normalized route of the events, such as the path pattern of
+    /// the wrapped filter, instead of the raw path of each request.
+    pub fn route(mut self, route: impl Into<String>) -> Self {
+        self.route = Some(route.into().into());
+        self
+    }
+
+    /// Records the actor of requests, from the extension of type `T`, such
+    /// as the user set by an authentication filter with
+    /// [`ext::provide`](crate::ext::provide).
+    ///
+    /// The extension is looked up once the wrapped filter has run, so it can
+    /// be set by the wrapped filter itself.
+    pub fn actor<T, F>(mut self, actor: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.actor = Some(Arc::new(move |ext| ext.get::<T>().map(&actor)));
+        self
+    }
+
+    /// Records the request header `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid header name.
+    pub fn request_header(mut self, name: &str) -> Self {
+        self.request_headers.push(header_name(name));
+        self
+    }
+
+    /// Records the response header `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid header name.
+    pub fn response_header(mut self, name: &str) -> Self {
+        self.response_headers.push(header_name(name));
+        self
+    }
+
+    /// Records the query parameters of requests.
+    pub fn query(mut self) -> Self {
+        self.query = true;
+        self
+    }
+
+    /// Records the bodies of requests up to `max` bytes long.
+    ///
+    /// Longer bodies, and bodies of unknown length, aren't recorded, since
+    /// they must be buffered in memory to be recorded.
+    pub fn request_body(mut self, max: u64) -> Self {
+        self.request_body = Some(max);
+        self
+    }
+
+    /// Records the bodies of responses up to `max` bytes long.
+    ///
+    /// Longer bodies, and streamed bodies, aren't recorded.
+    pub fn response_body(mut self, max: u64) -> Self {
+        self.response_body = Some(max);
+        self
+    }
+
+    /// Sets how recorded bodies are turned into values, given the headers
+    /// of their request or response.
+    ///
+    /// By default, JSON bodies are parsed, `application/x-www-form-urlencoded`
+    /// bodies become objects, and other bodies are recorded as text if they
+    /// are UTF-8. Bodies for which `capture` returns `None` aren't recorded.
+    /// The keys of the values are redacted either way.
+    pub fn capture_body<F>(mut self, capture: F) -> Self
+    where
+        F: Fn(&HeaderMap, &Bytes) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.capture = Arc::new(capture);
+        self
+    }
+
+    /// Redacts the values of `key`, compared case insensitively, in
+    /// headers, query parameters and the objects of bodies.
+    pub fn redact(mut self, key: &str) -> Self {
+        self.redact.push(key.to_ascii_lowercase());
+        self
+    }
+
+    fn is_redacted(&self, key: &str) -> bool {
+        self.redact.iter().any(|k| k.eq_ignore_ascii_case(key))
+    }
+
+    fn headers(&self, names: &[HeaderName], headers: &HeaderMap) -> BTreeMap<String, String> {
+        names
+            .iter()
+            .filter_map(|name| {
+                let value = headers.get(name)?;
+                let value = if self.is_redacted(name.as_str()) {
+                    REDACTED.to_owned()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                Some((name.as_str().to_owned(), value))
+            })
+            .collect()
+    }
+
+    fn query_params(&self, query: Option<&str>) -> BTreeMap<String, String> {
+        if !self.query {
+            return BTreeMap::new();
+        }
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query.unwrap_or(""))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| {
+                if self.is_redacted(&key) {
+                    (key, REDACTED.to_owned())
+                } else {
+                    (key, value)
+                }
+            })
+            .collect()
+    }
+
+    fn body(&self, headers: &HeaderMap, body: &Bytes) -> Option<Value> {
+        let mut value = (self.capture)(headers, body)?;
+        self.redact_value(&mut value);
+        Some(value)
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.is_redacted(key) {
+                        *value = Value::String(REDACTED.to_owned());
+                    } else {
+                        self.redact_value(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|v| self.redact_value(v)),
+            _ => (),
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::new()
+    }
+}
+
+impl fmt::Debug for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Policy")
+            .field("route", &self.route)
+            .field("request_headers", &self.request_headers)
+            .field("response_headers", &self.response_headers)
+            .field("query", &self.query)
+            .field("request_body", &self.request_body)
+            .field("response_body", &self.response_body)
+            .field("redact", &self.redact)
+            .finish()
+    }
+}
+
+fn header_name(name: &str) -> HeaderName {
+    HeaderName::from_bytes(name.as_bytes()).expect("illegal header name")
+}
+
+// The default `Policy::capture_body`.
+fn capture_body(headers: &HeaderMap, body: &Bytes) -> Option<Value> {
+    if body.is_empty() {
+        return None;
+    }
+    let mime = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<mime::Mime>().ok());
+    match mime {
+        Some(ref mime) if mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON) => {
+            serde_json::from_slice(body).ok()
+        }
+        Some(ref mime) if *mime == mime::APPLICATION_WWW_FORM_URLENCODED => {
+            serde_urlencoded::from_bytes::<Vec<(String, String)>>(body)
+                .ok()
+                .map(|pairs| {
+                    Value::Object(
+                        pairs
+                            .into_iter()
+                            .map(|(k, v)| (k, Value::String(v)))
+                            .collect(),
+                    )
+                })
+        }
+        _ => std::str::from_utf8(body)
+            .ok()
+            .map(|text| Value::String(text.to_owned())),
+    }
+}
+
+/// Wrapper recording audit events, see [`wrap`].
+#[derive(Clone)]
+pub struct Audit {
+    sink: Sink,
+    policy: Arc<Policy>,
+}
+
+impl fmt::Debug for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Audit")
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<F> WrapSealed<F> for Audit
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithAudit<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithAudit {
+            filter,
+            audit: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`wrap`].
+#[derive(Clone, Debug)]
+pub struct WithAudit<F> {
+    filter: F,
+    audit: Audit,
+}
+
+impl<F> FilterBase for WithAudit<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let timestamp = SystemTime::now();
+        let policy = self.audit.policy.clone();
+        let sink = self.audit.sink.clone();
+        let filter = self.filter.clone();
+        let (mut event, headers, body) = route::with(|route| {
+            let body = match policy.request_body {
+                Some(max) if matches!(content_length(route.headers()), Some(len) if len <= max) => {
+                    route.take_body()
+                }
+                _ => None,
+            };
+            let event = Event {
+                timestamp,
+                method: route.method().clone(),
+                route: match policy.route {
+                    Some(ref name) => name.to_string(),
+                    None => route.full_path().to_owned(),
+                },
+                path: route.full_path().to_owned(),
+                status: StatusCode::OK,
+                actor: None,
+                remote_ip: route.remote_addr().map(|addr| addr.ip()),
+                request_headers: policy.headers(&policy.request_headers, route.headers()),
+                response_headers: BTreeMap::new(),
+                query: policy.query_params(route.query()),
+                request_body: None,
+                response_body: None,
+            };
+            let headers = body.as_ref().map(|_| route.headers().clone());
+            (event, headers, body)
+        });
+
+        Box::pin(async move {
+            if let (Some(headers), Some(body)) = (headers, body) {
+                let bytes = hyper::body::to_bytes(body).await.map_err(|err| {
+                    log::debug!("to_bytes error: {}", err);
+                    crate::reject::known(crate::body::BodyReadError(err))
+                })?;
+                route::with(|route| route.restore_body(Body::from(bytes.clone())));
+                event.request_body = policy.body(&headers, &bytes);
+            }
+
+            let result = filter
+                .filter(Internal)
+                .await
+                .map(Reply::into_response)
+                .map_err(Into::into);
+            event.actor = policy
+                .actor
+                .as_ref()
+                .and_then(|actor| route::with(|route| actor(route.extensions())));
+
+            let result = match result {
+                Ok(res) => {
+                    event.status = res.status();
+                    event.response_headers =
+                        policy.headers(&policy.response_headers, res.headers());
+                    let res = match policy.response_body {
+                        Some(max) => capture_response(res, max, &policy, &mut event).await,
+                        None => res,
+                    };
+                    Ok((res,))
+                }
+                Err(rejection) => {
+                    event.status = rejection.status();
+                    Err(rejection)
+                }
+            };
+            sink(event).await;
+            result
+        })
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+// Buffers the body of `res` into `event`, if it has a known length of at
+// most `max` bytes.
+async fn capture_response(res: Response, max: u64, policy: &Policy, event: &mut Event) -> Response {
+    match res.body().size_hint().exact() {
+        Some(len) if len > 0 && len <= max => (),
+        _ => return res,
+    }
+    let (parts, body) = res.into_parts();
+    match hyper::body::to_bytes(body).await {
+        Ok(bytes) => {
+            event.response_body = policy.body(&parts.headers, &bytes);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(err) => {
+            log::debug!("audit response body error: {}", err);
+            Response::from_parts(parts, Body::empty())
+        }
+    }
+}
+
+/// An audit event, recorded by [`wrap`] for each request.
+#[derive(Clone, Debug)]
+pub struct Event {
+    timestamp: SystemTime,
+    method: Method,
+    route: String,
+    path: String,
+    status: StatusCode,
+    actor: Option<String>,
+    remote_ip: Option<IpAddr>,
+    request_headers: BTreeMap<String, String>,
+    response_headers: BTreeMap<String, String>,
+    query: BTreeMap<String, String>,
+    request_body: Option<Value>,
+    response_body: Option<Value>,
+}
+
+impl Event {
+    /// When the request was received.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The normalized route of the request, or else its path.
+    pub fn route(&self) -> &str {
+        &self.route
+    }
+
+    /// The path of the request.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The status code of the response, or of the rejection.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The actor of the request, if the policy has one and it was found.
+    pub fn actor(&self) -> Option<&str> {
+        self.actor.as_deref()
+    }
+
+    /// The IP address of the client, if known.
+    pub fn remote_ip(&self) -> Option<IpAddr> {
+        self.remote_ip
+    }
+
+    /// The recorded request headers, by name.
+    pub fn request_headers(&self) -> &BTreeMap<String, String> {
+        &self.request_headers
+    }
+
+    /// The recorded response headers, by name.
+    pub fn response_headers(&self) -> &BTreeMap<String, String> {
+        &self.response_headers
+    }
+
+    /// The recorded query parameters, by name.
+    pub fn query(&self) -> &BTreeMap<String, String> {
+        &self.query
+    }
+
+    /// The recorded request body.
+    pub fn request_body(&self) -> Option<&Value> {
+        self.request_body.as_ref()
+    }
+
+    /// The recorded response body.
+    pub fn response_body(&self) -> Option<&Value> {
+        self.response_body.as_ref()
+    }
+
+    /// The event as a JSON object.
+    ///
+    /// The keys are `timestamp`, in milliseconds since the Unix epoch,
+    /// `method`, `route`, `path`, `status`, `actor` and `remote_ip`, and the
+    /// recorded `request_headers`, `response_headers`, `query`,
+    /// `request_body` and `response_body`, unknown values being `null`.
+    pub fn to_json(&self) -> Value {
+        let timestamp = self
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or(0);
+        serde_json::json!({
+            "timestamp": timestamp,
+            "method": self.method.as_str(),
+            "route": self.route,
+            "path": self.path,
+            "status": self.status.as_u16(),
+            "actor": self.actor,
+            "remote_ip": self.remote_ip.map(|ip| ip.to_string()),
+            "request_headers": self.request_headers,
+            "response_headers": self.response_headers,
+            "query": self.query,
+            "request_body": self.request_body,
+            "response_body": self.response_body,
+        })
+    }
+}
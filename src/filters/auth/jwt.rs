@@ -0,0 +1,472 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::header::HeaderValue;
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::Client;
+use hyper_rustls::HttpsConnector;
+pub use jsonwebtoken::Algorithm;
+use jsonwebtoken::{DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{bearer, unauthorized};
+use crate::filter::Filter;
+use crate::reject::Rejection;
+
+// How often an unknown key id may trigger fetching the key set again.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+// The size of the largest key set that is fetched.
+const JWKS_MAX_SIZE: usize = 1024 * 1024;
+
+/// Create a `Filter` that validates a JSON Web Token.
+///
+/// The token is taken from a Bearer `Authorization` header. Its signature
+/// and its `exp` and `nbf` claims are verified, along with the audience and
+/// issuer if `validation` requires them. The claims are then deserialized
+/// into a `C`.
+///
+/// If the token is missing or invalid, this rejects with an
+/// [`Unauthorized`](super::Unauthorized) rejection, which responds with
+/// `401 Unauthorized`.
+///
+/// # Example
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use warp::Filter;
+///
+/// #[derive(Deserialize)]
+/// struct Claims {
+///     sub: String,
+/// }
+///
+/// let validation = warp::auth::JwtValidation::hmac(b"secret")
+///     .issuer("https://auth.example.com")
+///     .audience("my-api");
+///
+/// let route = warp::path("me")
+///     .and(warp::auth::jwt::<Claims>(validation))
+///     .map(|claims: Claims| claims.sub);
+/// ```
+pub fn jwt<C>(validation: JwtValidation) -> impl Filter<Extract = (C,), Error = Rejection> + Clone
+where
+    C: DeserializeOwned + Send + 'static,
+{
+    let validation = Arc::new(validation);
+    bearer().and_then(move |token: String| {
+        let validation = validation.clone();
+        async move { validation.decode(&token).await }
+    })
+}
+
+/// How tokens are validated by the [`jwt`] filter.
+///
+/// A `JwtValidation` is created from the key used to verify signatures,
+/// which also decides the accepted algorithms.
+#[derive(Clone, Debug)]
+pub struct JwtValidation {
+    keys: Keys,
+    validation: Validation,
+}
+
+#[derive(Clone, Debug)]
+enum Keys {
+    Static(Family, DecodingKey<'static>),
+    Jwks {
+        jwks: Arc<Jwks>,
+        ttl: Duration,
+        timeout: Duration,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Family {
+    Hmac,
+    Rsa,
+    Ec,
+}
+
+impl JwtValidation {
+    /// Verify tokens signed with a shared secret, using `HS256`, `HS384` or
+    /// `HS512`.
+    pub fn hmac(secret: &[u8]) -> Self {
+        let key = DecodingKey::from_secret(secret).into_static();
+        JwtValidation::new(Keys::Static(Family::Hmac, key))
+    }
+
+    /// Verify tokens signed with an RSA key, given its PEM encoded public key.
+    ///
+    /// Tokens using `RS256`, `RS384`, `RS512`, `PS256`, `PS384` or `PS512`
+    /// are accepted.
+    pub fn rsa_pem(pem: &[u8]) -> Result<Self, crate::Error> {
+        let key = DecodingKey::from_rsa_pem(pem)
+            .map_err(crate::Error::new)?
+            .into_static();
+        Ok(JwtValidation::new(Keys::Static(Family::Rsa, key)))
+    }
+
+    /// Verify tokens signed with an elliptic curve key, given its PEM encoded
+    /// public key.
+    ///
+    /// Tokens using `ES256` or `ES384` are accepted.
+    pub fn ec_pem(pem: &[u8]) -> Result<Self, crate::Error> {
+        let key = DecodingKey::from_ec_pem(pem)
+            .map_err(crate::Error::new)?
+            .into_static();
+        Ok(JwtValidation::new(Keys::Static(Family::Ec, key)))
+    }
+
+    /// Verify tokens with the keys of a JSON Web Key Set, fetched from `url`.
+    ///
+    /// The key set is fetched when first needed, and cached for 10 minutes,
+    /// or the duration set with [`jwks_ttl`](JwtValidation::jwks_ttl). A
+    /// token with an unknown key id also causes the key set to be fetched
+    /// again. The key set is fetched at most every 30 seconds, by one request
+    /// at a time, and while it is, requests with a key of the cached set
+    /// don't wait for it.
+    ///
+    /// Fetching the key set times out after 10 seconds, or the duration set
+    /// with [`jwks_timeout`](JwtValidation::jwks_timeout), and key sets
+    /// larger than 1 MiB are refused.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `url` is not a valid URL.
+    pub fn jwks(url: &str) -> Self {
+        let url = url
+            .parse()
+            .unwrap_or_else(|_| panic!("illegal JWKS url: {:?}", url));
+        let client = Client::builder().build(HttpsConnector::new());
+        let jwks = Jwks {
+            url,
+            client,
+            cache: Mutex::new(JwksCache::default()),
+            refresh: tokio::sync::Mutex::new(()),
+        };
+        JwtValidation::new(Keys::Jwks {
+            jwks: Arc::new(jwks),
+            ttl: Duration::from_secs(10 * 60),
+            timeout: Duration::from_secs(10),
+        })
+    }
+
+    fn new(keys: Keys) -> Self {
+        let algorithms = match keys {
+            Keys::Static(family, _) => family.algorithms().to_vec(),
+            Keys::Jwks { .. } => [Family::Rsa, Family::Ec]
+                .iter()
+                .flat_map(|family| family.algorithms())
+                .cloned()
+                .collect(),
+        };
+
+        JwtValidation {
+            keys,
+            validation: Validation {
+                algorithms,
+                ..Validation::default()
+            },
+        }
+    }
+
+    /// Only accept tokens using one of these algorithms.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if an algorithm cannot be used with the key.
+    pub fn algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        for &alg in algorithms {
+            let usable = match self.keys {
+                Keys::Static(family, _) => family == Family::of(alg),
+                Keys::Jwks { .. } => Family::of(alg) != Family::Hmac,
+            };
+            assert!(usable, "algorithm {:?} cannot be used with this key", alg);
+        }
+        self.validation.algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Require the `aud` claim to contain `audience`.
+    ///
+    /// Can be called multiple times to accept several audiences.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.validation
+            .aud
+            .get_or_insert_with(Default::default)
+            .insert(audience.to_owned());
+        self
+    }
+
+    /// Require the `iss` claim to equal `issuer`.
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.validation.iss = Some(issuer.to_owned());
+        self
+    }
+
+    /// Allow for clock skew when checking the `exp` and `nbf` claims.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.validation.leeway = leeway.as_secs();
+        self
+    }
+
+    /// How long a fetched JSON Web Key Set is cached.
+    ///
+    /// This has no effect unless the keys come from a key set.
+    pub fn jwks_ttl(mut self, ttl: Duration) -> Self {
+        if let Keys::Jwks {
+            ttl: ref mut jwks_ttl,
+            ..
+        } = self.keys
+        {
+            *jwks_ttl = ttl;
+        }
+        self
+    }
+
+    /// How long fetching the JSON Web Key Set can take.
+    ///
+    /// This has no effect unless the keys come from a key set.
+    pub fn jwks_timeout(mut self, timeout: Duration) -> Self {
+        if let Keys::Jwks {
+            timeout: ref mut jwks_timeout,
+            ..
+        } = self.keys
+        {
+            *jwks_timeout = timeout;
+        }
+        self
+    }
+
+    async fn decode<C: DeserializeOwned>(&self, token: &str) -> Result<C, Rejection> {
+        let header = jsonwebtoken::decode_header(token).map_err(invalid_token)?;
+        let res = match self.keys {
+            Keys::Static(_, ref key) => jsonwebtoken::decode(token, key, &self.validation),
+            Keys::Jwks {
+                ref jwks,
+                ttl,
+                timeout,
+            } => {
+                let key = jwks
+                    .key(header.kid.as_deref(), header.alg, ttl, timeout)
+                    .await
+                    .ok_or_else(|| invalid_token("no matching key"))?;
+                // A key can only be used with the algorithms of its family.
+                let family = Family::of(header.alg);
+                let validation = Validation {
+                    algorithms: self
+                        .validation
+                        .algorithms
+                        .iter()
+                        .filter(|&&alg| Family::of(alg) == family)
+                        .cloned()
+                        .collect(),
+                    ..self.validation.clone()
+                };
+                jsonwebtoken::decode(token, &key, &validation)
+            }
+        };
+
+        res.map(|data| data.claims).map_err(invalid_token)
+    }
+}
+
+impl Family {
+    fn of(alg: Algorithm) -> Family {
+        match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Family::Hmac,
+            Algorithm::ES256 | Algorithm::ES384 => Family::Ec,
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => Family::Rsa,
+        }
+    }
+
+    fn algorithms(self) -> &'static [Algorithm] {
+        match self {
+            Family::Hmac => &[Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+            Family::Rsa => &[
+                Algorithm::RS256,
+                Algorithm::RS384,
+                Algorithm::RS512,
+                Algorithm::PS256,
+                Algorithm::PS384,
+                Algorithm::PS512,
+            ],
+            Family::Ec => &[Algorithm::ES256, Algorithm::ES384],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Jwks {
+    url: http::Uri,
+    client: Client<HttpsConnector<HttpConnector>>,
+    cache: Mutex<JwksCache>,
+    // Held while fetching the key set, so that it is fetched once for the
+    // requests needing it.
+    refresh: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct JwksCache {
+    keys: Vec<Jwk>,
+    fetched: Option<Instant>,
+    attempted: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Jwk {
+    kid: Option<String>,
+    alg: Option<Algorithm>,
+    family: Family,
+    key: DecodingKey<'static>,
+}
+
+impl Jwks {
+    async fn key(
+        &self,
+        kid: Option<&str>,
+        alg: Algorithm,
+        ttl: Duration,
+        timeout: Duration,
+    ) -> Option<DecodingKey<'static>> {
+        let (key, refresh) = self.cache.lock().unwrap().lookup(kid, alg, ttl);
+        if !refresh {
+            return key;
+        }
+        let _refresh = match self.refresh.try_lock() {
+            Ok(guard) => guard,
+            // Another request is fetching the key set.
+            Err(_) if key.is_some() => return key,
+            Err(_) => self.refresh.lock().await,
+        };
+
+        // The key set may have been fetched while waiting.
+        let (key, refresh) = self.cache.lock().unwrap().lookup(kid, alg, ttl);
+        if !refresh {
+            return key;
+        }
+        self.cache.lock().unwrap().attempted = Some(Instant::now());
+        let keys = match tokio::time::timeout(timeout, self.fetch()).await {
+            Ok(Ok(keys)) => keys,
+            Ok(Err(err)) => {
+                log::warn!("fetching JWKS from {} failed: {}", self.url, err);
+                return key;
+            }
+            Err(_) => {
+                log::warn!("fetching JWKS from {} timed out", self.url);
+                return key;
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.keys = keys;
+        cache.fetched = cache.attempted;
+        cache.find(kid, alg).map(|jwk| jwk.key.clone())
+    }
+
+    async fn fetch(&self) -> Result<Vec<Jwk>, crate::Error> {
+        log::debug!("fetching JWKS from {}", self.url);
+        let res = self
+            .client
+            .get(self.url.clone())
+            .await
+            .map_err(crate::Error::new)?;
+        if !res.status().is_success() {
+            return Err(crate::Error::new(format!(
+                "unexpected status: {}",
+                res.status()
+            )));
+        }
+        let mut body = res.into_body();
+        let mut buf = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(crate::Error::new)?;
+            if buf.len() + chunk.len() > JWKS_MAX_SIZE {
+                return Err(crate::Error::new("key set is too large"));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        let set: Value = serde_json::from_slice(&buf).map_err(crate::Error::new)?;
+
+        Ok(set["keys"]
+            .as_array()
+            .map(|keys| keys.iter().filter_map(Jwk::parse).collect())
+            .unwrap_or_default())
+    }
+}
+
+impl JwksCache {
+    // The key of the cached set, if any, and whether to fetch the set again.
+    fn lookup(
+        &self,
+        kid: Option<&str>,
+        alg: Algorithm,
+        ttl: Duration,
+    ) -> (Option<DecodingKey<'static>>, bool) {
+        let key = self.find(kid, alg).map(|jwk| jwk.key.clone());
+        let expired = match self.fetched {
+            Some(at) => at.elapsed() >= ttl,
+            None => true,
+        };
+        let may_refresh = match self.attempted {
+            Some(at) => at.elapsed() >= JWKS_MIN_REFRESH,
+            None => true,
+        };
+        let refresh = may_refresh && (expired || key.is_none());
+        (key, refresh)
+    }
+
+    fn find(&self, kid: Option<&str>, alg: Algorithm) -> Option<&Jwk> {
+        self.keys.iter().find(|jwk| {
+            jwk.family == Family::of(alg)
+                && jwk.alg.unwrap_or(alg) == alg
+                && (kid.is_none() || jwk.kid.as_deref() == kid)
+        })
+    }
+}
+
+impl Jwk {
+    fn parse(jwk: &Value) -> Option<Jwk> {
+        if jwk["use"].as_str().unwrap_or("sig") != "sig" {
+            return None;
+        }
+
+        let (family, key) = match jwk["kty"].as_str()? {
+            "RSA" => {
+                let key = DecodingKey::from_rsa_components(jwk["n"].as_str()?, jwk["e"].as_str()?);
+                (Family::Rsa, key.into_static())
+            }
+            "EC" => {
+                // An uncompressed curve point.
+                let mut point = vec![0x04];
+                point.extend(base64_url(&jwk["x"])?);
+                point.extend(base64_url(&jwk["y"])?);
+                (Family::Ec, DecodingKey::from_ec_der(&point).into_static())
+            }
+            _ => return None,
+        };
+
+        Some(Jwk {
+            kid: jwk["kid"].as_str().map(ToOwned::to_owned),
+            alg: jwk["alg"].as_str().and_then(|alg| alg.parse().ok()),
+            family,
+            key,
+        })
+    }
+}
+
+fn base64_url(value: &Value) -> Option<Vec<u8>> {
+    base64::decode_config(value.as_str()?, base64::URL_SAFE_NO_PAD).ok()
+}
+
+fn invalid_token<E: std::fmt::Display>(err: E) -> Rejection {
+    log::debug!("invalid token: {}", err);
+    unauthorized(HeaderValue::from_static("Bearer error=\"invalid_token\""))
+}
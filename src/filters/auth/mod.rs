@@ -0,0 +1,117 @@
+//! Authentication Filters
+//!
+//! Filters that extract credentials from the `Authorization` header. When
+//! credentials are missing or malformed, they reject with an
+//! [`Unauthorized`] rejection, which responds with `401 Unauthorized` and a
+//! `WWW-Authenticate` challenge.
+//!
+//! Checking the extracted credentials is left to the application, such as
+//! with `and_then`, except for JSON Web Tokens, which [`jwt`] validates when
+//! the `jwt` feature is enabled.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use futures::future;
+use headers::authorization::{Basic, Bearer};
+use headers::{Authorization, HeaderMapExt};
+use http::header::HeaderValue;
+
+use crate::filter::{filter_fn, filter_fn_one, Filter};
+use crate::reject::{self, Rejection};
+
+#[cfg(feature = "jwt")]
+mod jwt;
+
+#[cfg(feature = "jwt")]
+pub use self::jwt::{jwt, Algorithm, JwtValidation};
+
+/// Create a `Filter` that extracts HTTP Basic credentials.
+///
+/// The filter extracts the `(user, password)` pair. If the request has no
+/// Basic credentials, it rejects with a challenge for the given `realm`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let admin = warp::path("admin")
+///     .and(warp::auth::basic("admin area"))
+///     .and_then(|user: String, password: String| async move {
+///         if user == "admin" && password == "hunter2" {
+///             Ok("welcome")
+///         } else {
+///             Err(warp::reject::not_found())
+///         }
+///     });
+/// ```
+///
+/// # Panics
+///
+/// This function panics if `realm` cannot be used in a header value.
+pub fn basic(realm: &str) -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+    let challenge = HeaderValue::from_str(&format!("Basic realm=\"{}\"", realm))
+        .unwrap_or_else(|_| panic!("illegal realm: {:?}", realm));
+
+    filter_fn(move |route| {
+        let credentials = route
+            .headers()
+            .typed_get::<Authorization<Basic>>()
+            .map(|Authorization(basic)| (basic.username().to_owned(), basic.password().to_owned()))
+            .ok_or_else(|| unauthorized(challenge.clone()));
+        future::ready(credentials)
+    })
+}
+
+/// Create a `Filter` that extracts a Bearer token.
+///
+/// The filter extracts the raw token. If the request has no Bearer token, it
+/// rejects with a `Bearer` challenge.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let api = warp::path("api")
+///     .and(warp::auth::bearer())
+///     .map(|token: String| format!("token: {}", token));
+/// ```
+pub fn bearer() -> impl Filter<Extract = (String,), Error = Rejection> + Copy {
+    filter_fn_one(|route| {
+        let token = route
+            .headers()
+            .typed_get::<Authorization<Bearer>>()
+            .map(|Authorization(bearer)| bearer.token().to_owned())
+            .ok_or_else(|| unauthorized(HeaderValue::from_static("Bearer")));
+        future::ready(token)
+    })
+}
+
+fn unauthorized(challenge: HeaderValue) -> Rejection {
+    log::debug!("missing or invalid credentials");
+    reject::known(Unauthorized { challenge })
+}
+
+/// An error used to reject requests without valid credentials.
+#[derive(Debug)]
+pub struct Unauthorized {
+    challenge: HeaderValue,
+}
+
+impl Unauthorized {
+    /// The `WWW-Authenticate` challenge sent with the rejection.
+    pub fn challenge(&self) -> &HeaderValue {
+        &self.challenge
+    }
+}
+
+impl fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Missing or invalid credentials")
+    }
+}
+
+impl StdError for Unauthorized {}
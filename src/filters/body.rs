@@ -7,10 +7,12 @@ use std::fmt;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use bytes::{buf::BufExt, Buf, Bytes};
+use bytes::{buf::BufExt, Buf, Bytes, BytesMut};
 use futures::{future, ready, Stream, TryFutureExt};
 use headers::ContentLength;
 use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use hyper::body::HttpBody;
 use hyper::Body;
 use mime;
 use serde::de::DeserializeOwned;
@@ -19,6 +21,7 @@ use serde_urlencoded;
 
 use crate::filter::{filter_fn, filter_fn_one, Filter, FilterBase};
 use crate::reject::{self, Rejection};
+use crate::route::Route;
 
 type BoxError = Box<dyn StdError + Send + Sync>;
 
@@ -26,11 +29,13 @@ type BoxError = Box<dyn StdError + Send + Sync>;
 //
 // Does not consume any of it.
 pub(crate) fn body() -> impl Filter<Extract = (Body,), Error = Rejection> + Copy {
-    filter_fn_one(|route| {
-        future::ready(route.take_body().ok_or_else(|| {
-            log::error!("request body already taken in previous filter");
-            reject::known(BodyConsumedMultipleTimes { _p: () })
-        }))
+    filter_fn_one(|route| future::ready(take_body(route)))
+}
+
+pub(crate) fn take_body(route: &mut Route) -> Result<Body, Rejection> {
+    route.take_body().ok_or_else(|| {
+        log::error!("request body already taken in previous filter");
+        reject::known(BodyConsumedMultipleTimes { _p: () })
     })
 }
 
@@ -112,6 +117,50 @@ pub fn bytes() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Copy {
     })
 }
 
+/// Returns a `Filter` that matches any request and extracts a `Future` of a
+/// concatenated body, and the trailers sent after it.
+///
+/// Trailers are only received over HTTP/2, such as the status of gRPC-Web
+/// calls. Over HTTP/1, the trailers are always `None`.
+///
+/// Only the trailers of requests are supported: replies can't send
+/// trailers, since the `hyper::Body` of responses can't carry them.
+///
+/// # Warning
+///
+/// This does not have a default size limit, it would be wise to use one to
+/// prevent a overly large request from using too much memory.
+///
+/// # Example
+///
+/// ```
+/// use warp::{http::HeaderMap, Filter};
+///
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::bytes_with_trailers())
+///     .map(|bytes: bytes::Bytes, trailers: Option<HeaderMap>| {
+///         println!("bytes = {:?}, trailers = {:?}", bytes, trailers);
+///     });
+/// ```
+pub fn bytes_with_trailers(
+) -> impl Filter<Extract = (Bytes, Option<HeaderMap>), Error = Rejection> + Copy {
+    body()
+        .and_then(|mut body: hyper::Body| async move {
+            let mut buf = BytesMut::new();
+            while let Some(chunk) = body.data().await {
+                buf.extend_from_slice(&chunk.map_err(read_error)?);
+            }
+            let trailers = body.trailers().await.map_err(read_error)?;
+            Ok::<_, Rejection>((buf.freeze(), trailers))
+        })
+        .untuple_one()
+}
+
+fn read_error(err: hyper::Error) -> Rejection {
+    log::debug!("read body error: {}", err);
+    reject::known(BodyReadError(err))
+}
+
 /// Returns a `Filter` that matches any request and extracts a `Future` of an
 /// aggregated body.
 ///
@@ -181,6 +230,140 @@ pub fn json<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error =
         })
 }
 
+/// Deserialize JSON from a buffer, such as the one extracted by
+/// [`aggregate`].
+///
+/// The buffer is read in place, even when it is made of several
+/// non-contiguous chunks, so unlike [`bytes`] followed by
+/// `serde_json::from_slice`, a large body isn't copied into a single
+/// allocation first. This is what [`json`] does, for routes that need to
+/// look at the request before deciding how to decode the body.
+///
+/// Errors are rejections with a `BodyDeserializeError`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::{Buf, Filter};
+///
+/// let route = warp::body::content_length_limit(1024 * 1024)
+///     .and(warp::header::optional::<String>("x-version"))
+///     .and(warp::body::aggregate())
+///     .and_then(|version: Option<String>, buf| async move {
+///         let map: HashMap<String, String> = warp::body::json_from_buf(buf)?;
+///         Ok::<_, warp::Rejection>(format!("{:?}: {} keys", version, map.len()))
+///     });
+/// ```
+pub fn json_from_buf<T: DeserializeOwned>(buf: impl Buf) -> Result<T, Rejection> {
+    Json::decode(buf).map_err(deserialize_error)
+}
+
+/// Returns a `Filter` that matches any request and extracts a `Future` of a
+/// JSON-decoded body, along with the raw body it was decoded from.
+///
+/// The raw body is needed to check a signature of the request, or to log it
+/// as received, without reading the body twice.
+///
+/// # Warning
+///
+/// This does not have a default size limit, it would be wise to use one to
+/// prevent a overly large request from using too much memory.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::Filter;
+///
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::json_with_raw())
+///     .map(|simple_map: HashMap<String, String>, raw: bytes::Bytes| {
+///         format!("Got a JSON body of {} bytes!", raw.len())
+///     });
+/// ```
+pub fn json_with_raw<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T, Bytes), Error = Rejection> + Copy {
+    is_content_type::<Json>()
+        .and(bytes())
+        .and_then(|raw: Bytes| async move {
+            Json::decode(raw.clone())
+                .map(|value| (value, raw))
+                .map_err(deserialize_error)
+        })
+        .untuple_one()
+}
+
+/// Returns a `Filter` that matches any request and extracts a `Future` of a
+/// MessagePack-decoded body.
+///
+/// The request must have an `application/msgpack` content-type, or one of
+/// its `application/x-msgpack` and `application/vnd.msgpack` aliases.
+///
+/// # Warning
+///
+/// This does not have a default size limit, it would be wise to use one to
+/// prevent a overly large request from using too much memory.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::Filter;
+///
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::msgpack())
+///     .map(|simple_map: HashMap<String, String>| {
+///         "Got a MessagePack body!"
+///     });
+/// ```
+#[cfg(feature = "msgpack")]
+pub fn msgpack<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = Rejection> + Copy
+{
+    is_content_type::<MessagePack>()
+        .and(aggregate())
+        .and_then(|buf| async move {
+            MessagePack::decode(buf).map_err(|err| {
+                log::debug!("request msgpack body error: {}", err);
+                reject::known(BodyDeserializeError { cause: err })
+            })
+        })
+}
+
+/// Returns a `Filter` that matches any request and extracts a `Future` of a
+/// CBOR-decoded body.
+///
+/// The request must have an `application/cbor` content-type.
+///
+/// # Warning
+///
+/// This does not have a default size limit, it would be wise to use one to
+/// prevent a overly large request from using too much memory.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::Filter;
+///
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::cbor())
+///     .map(|simple_map: HashMap<String, String>| {
+///         "Got a CBOR body!"
+///     });
+/// ```
+#[cfg(feature = "cbor")]
+pub fn cbor<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = Rejection> + Copy {
+    is_content_type::<Cbor>()
+        .and(aggregate())
+        .and_then(|buf| async move {
+            Cbor::decode(buf).map_err(|err| {
+                log::debug!("request cbor body error: {}", err);
+                reject::known(BodyDeserializeError { cause: err })
+            })
+        })
+}
+
 /// Returns a `Filter` that matches any request and extracts a
 /// `Future` of a form encoded body.
 ///
@@ -219,7 +402,8 @@ pub fn form<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error =
 // ===== Decoders =====
 
 trait Decode {
-    const MIME: (mime::Name<'static>, mime::Name<'static>);
+    // The accepted media types, as `(type, subtype)`.
+    const MIME: &'static [(&'static str, &'static str)];
     const WITH_NO_CONTENT_TYPE: bool;
 
     fn decode<B: Buf, T: DeserializeOwned>(buf: B) -> Result<T, BoxError>;
@@ -228,7 +412,7 @@ trait Decode {
 struct Json;
 
 impl Decode for Json {
-    const MIME: (mime::Name<'static>, mime::Name<'static>) = (mime::APPLICATION, mime::JSON);
+    const MIME: &'static [(&'static str, &'static str)] = &[("application", "json")];
     const WITH_NO_CONTENT_TYPE: bool = true;
 
     fn decode<B: Buf, T: DeserializeOwned>(buf: B) -> Result<T, BoxError> {
@@ -239,8 +423,8 @@ impl Decode for Json {
 struct Form;
 
 impl Decode for Form {
-    const MIME: (mime::Name<'static>, mime::Name<'static>) =
-        (mime::APPLICATION, mime::WWW_FORM_URLENCODED);
+    const MIME: &'static [(&'static str, &'static str)] =
+        &[("application", "x-www-form-urlencoded")];
     const WITH_NO_CONTENT_TYPE: bool = true;
 
     fn decode<B: Buf, T: DeserializeOwned>(buf: B) -> Result<T, BoxError> {
@@ -248,11 +432,41 @@ impl Decode for Form {
     }
 }
 
+#[cfg(feature = "msgpack")]
+struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Decode for MessagePack {
+    const MIME: &'static [(&'static str, &'static str)] = &[
+        ("application", "msgpack"),
+        ("application", "x-msgpack"),
+        ("application", "vnd.msgpack"),
+    ];
+    const WITH_NO_CONTENT_TYPE: bool = false;
+
+    fn decode<B: Buf, T: DeserializeOwned>(buf: B) -> Result<T, BoxError> {
+        rmp_serde::from_read(buf.reader()).map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "cbor")]
+struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Decode for Cbor {
+    const MIME: &'static [(&'static str, &'static str)] = &[("application", "cbor")];
+    const WITH_NO_CONTENT_TYPE: bool = false;
+
+    fn decode<B: Buf, T: DeserializeOwned>(buf: B) -> Result<T, BoxError> {
+        serde_cbor::from_reader(buf.reader()).map_err(Into::into)
+    }
+}
+
 // Require the `content-type` header to be this type (or, if there's no `content-type`
 // header at all, optimistically hope it's the right type).
 fn is_content_type<D: Decode>() -> impl Filter<Extract = (), Error = Rejection> + Copy {
     filter_fn(move |route| {
-        let (type_, subtype) = D::MIME;
+        let (type_, subtype) = D::MIME[0];
         if let Some(value) = route.headers().get(CONTENT_TYPE) {
             log::trace!("is_content_type {}/{}? {:?}", type_, subtype, value);
             let ct = value
@@ -260,7 +474,10 @@ fn is_content_type<D: Decode>() -> impl Filter<Extract = (), Error = Rejection>
                 .ok()
                 .and_then(|s| s.parse::<mime::Mime>().ok());
             if let Some(ct) = ct {
-                if ct.type_() == type_ && ct.subtype() == subtype {
+                let matches = D::MIME
+                    .iter()
+                    .any(|&(type_, subtype)| ct.type_() == type_ && ct.subtype() == subtype);
+                if matches {
                     future::ok(())
                 } else {
                     log::debug!(
@@ -311,6 +528,11 @@ impl Stream for BodyStream {
 
 // ===== Rejections =====
 
+pub(crate) fn deserialize_error(err: BoxError) -> Rejection {
+    log::debug!("request body deserialize error: {}", err);
+    reject::known(BodyDeserializeError { cause: err })
+}
+
 /// An error used in rejections when deserializing a request body fails.
 #[derive(Debug)]
 pub struct BodyDeserializeError {
@@ -326,7 +548,7 @@ impl fmt::Display for BodyDeserializeError {
 impl StdError for BodyDeserializeError {}
 
 #[derive(Debug)]
-pub(crate) struct BodyReadError(::hyper::Error);
+pub(crate) struct BodyReadError(pub(crate) ::hyper::Error);
 
 impl ::std::fmt::Display for BodyReadError {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
@@ -0,0 +1,583 @@
+//! Response caching
+//!
+//! The [`wrap`] function caches the successful responses to `GET` requests
+//! of a route in a [`CacheStore`], and answers identical requests from the
+//! store until the responses expire, without running the route again.
+//!
+//! Responses are keyed by the path and query of the request, and the values
+//! of the request headers the [`CachePolicy`] varies on, which are added to
+//! the `Vary` header of the responses. Responses aren't cached if:
+//!
+//! - their status isn't successful,
+//! - they have a `Cache-Control` with `no-store`, `no-cache` or `private`,
+//! - they have a `Set-Cookie` header,
+//! - they vary on headers the policy doesn't vary on,
+//! - or their body is larger than the policy allows.
+//!
+//! Requests with an `Authorization` or `Cookie` header bypass the cache,
+//! unless the policy varies on that header, and so do requests with a
+//! `Cache-Control` with `no-store` or `no-cache`.
+//!
+//! The wrapped filter doesn't run on a cache hit, so the filters checking a
+//! request, such as authentication, [`ip::restrict`](crate::ip::restrict)
+//! or CSRF protection, don't run either. They should be outside of the
+//! wrapped filter, or the policy should vary on what they check.
+//!
+//! Bodies without a `Content-Length` are buffered until they are larger
+//! than the policy allows, and the rest of them is then streamed without
+//! being cached.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use warp::cache::{CachePolicy, MemoryStore};
+//! use warp::Filter;
+//!
+//! let policy = CachePolicy::new()
+//!     .ttl(Duration::from_secs(30))
+//!     .vary("accept-language");
+//! let store = MemoryStore::new().max_bytes(16 * 1024 * 1024);
+//!
+//! let route = warp::path("news")
+//!     .map(|| "today's news")
+//!     .with(warp::cache::wrap(policy, store));
+//! ```
+
+use std::cmp;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+use futures::{future, stream, StreamExt};
+use headers::{CacheControl, HeaderMapExt};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Method, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Create a wrapper caching the responses of a route.
+///
+/// See the [module documentation](self) for which responses are cached.
+pub fn wrap<S: CacheStore>(policy: CachePolicy, store: S) -> Cache<S> {
+    Cache {
+        policy: Arc::new(policy),
+        store: Arc::new(store),
+    }
+}
+
+/// The `Future` returned by the methods of a [`CacheStore`].
+pub type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send>>;
+
+/// A place to keep cached responses, such as memory or Redis.
+///
+/// Errors of a store are logged, and the request is handled as if the
+/// response wasn't cached.
+pub trait CacheStore: Send + Sync + 'static {
+    /// Look up the response cached for `key`, if it hasn't expired.
+    fn get(&self, key: &str) -> StoreFuture<Option<CachedResponse>>;
+
+    /// Cache a response for `key`, which expires after `ttl`.
+    fn put(&self, key: &str, response: CachedResponse, ttl: Duration) -> StoreFuture<()>;
+}
+
+/// A response kept in a [`CacheStore`].
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: SystemTime,
+}
+
+impl CachedResponse {
+    /// Create a `CachedResponse`, such as when loading one from storage.
+    pub fn new(
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+        stored_at: SystemTime,
+    ) -> CachedResponse {
+        CachedResponse {
+            status,
+            headers,
+            body,
+            stored_at,
+        }
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The body of the response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// When the response was cached.
+    pub fn stored_at(&self) -> SystemTime {
+        self.stored_at
+    }
+
+    fn to_response(&self) -> Response {
+        let mut res = Response::new(Body::from(self.body.clone()));
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers.clone();
+        let age = SystemTime::now()
+            .duration_since(self.stored_at)
+            .unwrap_or_default()
+            .as_secs();
+        res.headers_mut()
+            .insert(header::AGE, HeaderValue::from(age));
+        res
+    }
+}
+
+/// A [`CacheStore`] that keeps responses in memory.
+///
+/// When the store is full, expired responses are removed first, and then
+/// the oldest ones. By default, the store holds up to 1000 responses and
+/// 64 MiB of bodies.
+#[derive(Clone, Debug)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<Memory>>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Memory {
+    entries: HashMap<String, MemoryEntry>,
+    bytes: usize,
+    seq: u64,
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    response: CachedResponse,
+    expires: Instant,
+    seq: u64,
+}
+
+impl MemoryStore {
+    /// Create an empty `MemoryStore`.
+    pub fn new() -> MemoryStore {
+        MemoryStore {
+            inner: Arc::default(),
+            max_entries: 1000,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+
+    /// Set the maximum number of responses in the store.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Set the maximum total size of the bodies in the store.
+    pub fn max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = max;
+        self
+    }
+
+    /// The number of responses in the store, including expired ones.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether the store has no responses.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> MemoryStore {
+        MemoryStore::new()
+    }
+}
+
+impl Memory {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.response.body.len();
+        }
+    }
+
+    fn evict(&mut self, max_entries: usize, max_bytes: usize) {
+        let now = Instant::now();
+        if self.entries.len() > max_entries || self.bytes > max_bytes {
+            let bytes = &mut self.bytes;
+            self.entries.retain(|_, entry| {
+                let keep = entry.expires > now;
+                if !keep {
+                    *bytes -= entry.response.body.len();
+                }
+                keep
+            });
+        }
+        while self.entries.len() > max_entries || self.bytes > max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.seq)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> StoreFuture<Option<CachedResponse>> {
+        let mut inner = self.inner.lock().unwrap();
+        let response = match inner.entries.get(key) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.response.clone()),
+            Some(_) => {
+                inner.remove(key);
+                None
+            }
+            None => None,
+        };
+        Box::pin(future::ok(response))
+    }
+
+    fn put(&self, key: &str, response: CachedResponse, ttl: Duration) -> StoreFuture<()> {
+        if response.body.len() <= self.max_bytes && self.max_entries > 0 {
+            let mut inner = self.inner.lock().unwrap();
+            inner.remove(key);
+            inner.seq += 1;
+            inner.bytes += response.body.len();
+            let entry = MemoryEntry {
+                response,
+                expires: Instant::now() + ttl,
+                seq: inner.seq,
+            };
+            inner.entries.insert(key.to_owned(), entry);
+            inner.evict(self.max_entries, self.max_bytes);
+        }
+        Box::pin(future::ok(()))
+    }
+}
+
+/// Which responses are cached, and for how long.
+#[derive(Clone, Debug)]
+pub struct CachePolicy {
+    ttl: Duration,
+    max_body_size: usize,
+    vary: Vec<HeaderName>,
+}
+
+impl CachePolicy {
+    /// Create a `CachePolicy` caching responses of up to 1 MiB for 60
+    /// seconds.
+    pub fn new() -> CachePolicy {
+        CachePolicy {
+            ttl: Duration::from_secs(60),
+            max_body_size: 1024 * 1024,
+            vary: Vec::new(),
+        }
+    }
+
+    /// Set how long responses are cached.
+    ///
+    /// A `max-age` or `s-maxage` in the `Cache-Control` of a response can
+    /// shorten this, but not extend it.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the size of the largest body that is cached.
+    pub fn max_body_size(mut self, max: usize) -> Self {
+        self.max_body_size = max;
+        self
+    }
+
+    /// Cache a response for each value of a request header.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `name` is not a legal header name.
+    pub fn vary(mut self, name: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("illegal vary header name: {:?}", name));
+        if !self.vary.contains(&name) {
+            self.vary.push(name);
+        }
+        self
+    }
+
+    fn key(&self, path: &str, headers: &HeaderMap) -> Option<String> {
+        for name in &[header::AUTHORIZATION, header::COOKIE] {
+            if !self.vary.contains(name) && headers.contains_key(name) {
+                return None;
+            }
+        }
+        if let Some(cc) = headers.typed_get::<CacheControl>() {
+            if cc.no_store() || cc.no_cache() {
+                return None;
+            }
+        }
+        let mut key = format!("GET {}", path);
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            for (i, value) in headers.get_all(name).iter().enumerate() {
+                if i > 0 {
+                    key.push(',');
+                }
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+            }
+        }
+        Some(key)
+    }
+
+    // Returns how long a response can be cached, if it can.
+    fn cacheable(&self, res: &Response) -> Option<Duration> {
+        let headers = res.headers();
+        if !res.status().is_success() || headers.contains_key(header::SET_COOKIE) {
+            return None;
+        }
+        let mut ttl = self.ttl;
+        if let Some(cc) = headers.typed_get::<CacheControl>() {
+            if cc.no_store() || cc.no_cache() || cc.private() {
+                return None;
+            }
+            if let Some(max_age) = cc.s_max_age().or_else(|| cc.max_age()) {
+                ttl = cmp::min(ttl, max_age);
+            }
+        }
+        for value in headers.get_all(header::VARY) {
+            let value = value.to_str().ok()?;
+            for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let known = self
+                    .vary
+                    .iter()
+                    .any(|vary| vary.as_str().eq_ignore_ascii_case(name));
+                if !known {
+                    return None;
+                }
+            }
+        }
+        if ttl == Duration::from_secs(0) {
+            return None;
+        }
+        Some(ttl)
+    }
+
+    fn add_vary(&self, headers: &mut HeaderMap) {
+        if self.vary.is_empty() {
+            return;
+        }
+        let mut names = headers
+            .get_all(header::VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        for name in &self.vary {
+            if !names.iter().any(|n| n == name.as_str()) {
+                names.push(name.as_str().to_owned());
+            }
+        }
+        let value = HeaderValue::from_str(&names.join(", ")).expect("header names are legal");
+        headers.insert(header::VARY, value);
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> CachePolicy {
+        CachePolicy::new()
+    }
+}
+
+/// A wrapper caching the responses of a route, created with [`wrap`].
+pub struct Cache<S> {
+    policy: Arc<CachePolicy>,
+    store: Arc<S>,
+}
+
+impl<S> Clone for Cache<S> {
+    fn clone(&self) -> Self {
+        Cache {
+            policy: self.policy.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for Cache<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<S, F> WrapSealed<F> for Cache<S>
+where
+    S: CacheStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithCache<S, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithCache {
+            cache: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with a [`Cache`].
+pub struct WithCache<S, F> {
+    cache: Cache<S>,
+    filter: F,
+}
+
+impl<S, F: Clone> Clone for WithCache<S, F> {
+    fn clone(&self) -> Self {
+        WithCache {
+            cache: self.cache.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for WithCache<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithCache")
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+impl<S, F> FilterBase for WithCache<S, F>
+where
+    S: CacheStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let policy = self.cache.policy.clone();
+        let key = route::with(|route| {
+            if route.method() != Method::GET {
+                return None;
+            }
+            let path = route
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/");
+            policy.key(path, route.headers())
+        });
+        let store = self.cache.store.clone();
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    let reply = fut.await.map_err(Into::into)?;
+                    return Ok((reply.into_response(),));
+                }
+            };
+
+            match store.get(&key).await {
+                Ok(Some(cached)) => {
+                    log::trace!("cache hit for {:?}", key);
+                    return Ok((cached.to_response(),));
+                }
+                Ok(None) => log::trace!("cache miss for {:?}", key),
+                Err(err) => log::error!("cache store error: {}", err),
+            }
+
+            let mut res = fut.await.map_err(Into::into)?.into_response();
+            let ttl = policy.cacheable(&res);
+            policy.add_vary(res.headers_mut());
+            let ttl = match ttl {
+                Some(ttl) => ttl,
+                None => return Ok((res,)),
+            };
+            let too_large = matches!(
+                res.headers().typed_get::<headers::ContentLength>(),
+                Some(len) if len.0 > policy.max_body_size as u64
+            );
+            if too_large {
+                return Ok((res,));
+            }
+
+            let (parts, mut body) = res.into_parts();
+            let mut chunks = Vec::new();
+            let mut len = 0;
+            while let Some(chunk) = body.data().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        log::error!("cache failed to buffer response body: {}", err);
+                        let mut res = Response::new(Body::empty());
+                        *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                        return Ok((res,));
+                    }
+                };
+                len += chunk.len();
+                chunks.push(chunk);
+                if len > policy.max_body_size {
+                    // Too large to be cached, stream what is left.
+                    let head = stream::iter(chunks.into_iter().map(Ok));
+                    let body = Body::wrap_stream(head.chain(body));
+                    return Ok((Response::from_parts(parts, body),));
+                }
+            }
+            let body = match chunks.len() {
+                1 => chunks.pop().expect("one chunk"),
+                _ => Bytes::from(chunks.concat()),
+            };
+            let cached = CachedResponse::new(
+                parts.status,
+                parts.headers.clone(),
+                body.clone(),
+                SystemTime::now(),
+            );
+            if let Err(err) = store.put(&key, cached, ttl).await {
+                log::error!("cache store error: {}", err);
+            }
+            Ok((Response::from_parts(parts, Body::from(body)),))
+        })
+    }
+}
@@ -2,10 +2,13 @@
 //!
 //! Filters that compress the body of a response.
 
-use async_compression::stream::{BrotliEncoder, DeflateEncoder, GzipEncoder};
+use std::sync::Arc;
+
+use async_compression::tokio_02::write::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder};
 use http::header::HeaderValue;
 use hyper::{
-    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    body::HttpBody,
+    header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY},
     Body,
 };
 
@@ -13,33 +16,255 @@ use crate::filter::{Filter, WrapSealed};
 use crate::reject::IsReject;
 use crate::reply::{Reply, Response};
 
-use self::internal::{CompressionProps, WithCompression};
+use self::internal::{CompressionProps, EncodedBody, WithCompression, Writer};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum CompressionAlgo {
     BR,
     DEFLATE,
     GZIP,
+    ZSTD,
+}
+
+impl CompressionAlgo {
+    // In order of preference, when the client accepts several equally.
+    const ALL: [CompressionAlgo; 4] = [
+        CompressionAlgo::BR,
+        CompressionAlgo::ZSTD,
+        CompressionAlgo::GZIP,
+        CompressionAlgo::DEFLATE,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            CompressionAlgo::BR => "br",
+            CompressionAlgo::DEFLATE => "deflate",
+            CompressionAlgo::GZIP => "gzip",
+            CompressionAlgo::ZSTD => "zstd",
+        }
+    }
+
+    fn encode(self, mut props: CompressionProps) -> Response {
+        let level = props.level.into();
+        let writer = match self {
+            CompressionAlgo::BR => {
+                Writer::Br(Box::new(BrotliEncoder::with_quality(Vec::new(), level)))
+            }
+            CompressionAlgo::DEFLATE => {
+                Writer::Deflate(DeflateEncoder::with_quality(Vec::new(), level))
+            }
+            CompressionAlgo::GZIP => Writer::Gzip(GzipEncoder::with_quality(Vec::new(), level)),
+            CompressionAlgo::ZSTD => Writer::Zstd(ZstdEncoder::with_quality(Vec::new(), level)),
+        };
+        let body = Body::wrap_stream(EncodedBody::new(props.body, writer, props.flush));
+        props.head.headers.append(CONTENT_ENCODING, self.into());
+        props.head.headers.remove(CONTENT_LENGTH);
+        Response::from_parts(props.head, body)
+    }
 }
 
 impl From<CompressionAlgo> for HeaderValue {
     #[inline]
     fn from(algo: CompressionAlgo) -> Self {
-        match algo {
-            CompressionAlgo::BR => HeaderValue::from_static("br"),
-            CompressionAlgo::DEFLATE => HeaderValue::from_static("deflate"),
-            CompressionAlgo::GZIP => HeaderValue::from_static("gzip"),
-        }
+        HeaderValue::from_static(algo.name())
     }
 }
 
 /// Compression
-#[derive(Clone, Copy, Debug)]
+///
+/// Responses that already have a `content-encoding` are never compressed
+/// again. Which other responses are compressed can be configured with
+/// [`min_size`](Compression::min_size), [`content_types`](Compression::content_types)
+/// and [`exclude_content_types`](Compression::exclude_content_types).
+#[derive(Clone, Debug)]
 pub struct Compression<F> {
     func: F,
+    options: Arc<Options>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Options {
+    min_size: u64,
+    level: Level,
+    flush: Flush,
+    content_types: Option<Vec<String>>,
+    exclude_content_types: Vec<String>,
+}
+
+/// The level of compression, trading speed for size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Level {
+    /// The fastest compression, usually producing bigger bodies.
+    Fastest,
+    /// The best compression, usually producing the smallest bodies.
+    Best,
+    /// The default level of the encoding.
+    #[default]
+    Default,
+    /// A level specific to the encoding, clamped to its maximum.
+    Precise(u32),
+}
+
+/// When compressed data is flushed to the client.
+///
+/// Compressors hold on to data until they have enough of it to compress well,
+/// which would hold back the events of a stream that is slow to produce them,
+/// such as server-sent events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Flush {
+    /// Flush whenever the body has no more data ready, so slow streams are
+    /// not held back while fast bodies are still compressed well.
+    #[default]
+    WhenIdle,
+    /// Flush after every chunk of the body.
+    EveryChunk,
+    /// Only flush at the end of the body, for the best compression.
+    End,
+}
+
+impl From<Level> for async_compression::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Fastest => async_compression::Level::Fastest,
+            Level::Best => async_compression::Level::Best,
+            Level::Default => async_compression::Level::Default,
+            Level::Precise(level) => async_compression::Level::Precise(level),
+        }
+    }
+}
+
+impl<F> Compression<F> {
+    fn new(func: F) -> Self {
+        Compression {
+            func,
+            options: Arc::new(Options::default()),
+        }
+    }
+
+    /// Only compress responses whose body is known to be at least `bytes`
+    /// long.
+    ///
+    /// Streamed bodies of unknown length are always compressed.
+    pub fn min_size(mut self, bytes: u64) -> Self {
+        Arc::make_mut(&mut self.options).min_size = bytes;
+        self
+    }
+
+    /// Set the level of compression.
+    pub fn level(mut self, level: Level) -> Self {
+        Arc::make_mut(&mut self.options).level = level;
+        self
+    }
+
+    /// Set when compressed data is flushed, [`Flush::WhenIdle`] by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::compression::Flush;
+    /// use warp::Filter;
+    ///
+    /// let events = warp::path("events")
+    ///     .map(|| {
+    ///         let events = futures::stream::iter(vec![
+    ///             Ok::<_, std::convert::Infallible>(warp::sse::data("hello")),
+    ///         ]);
+    ///         warp::sse::reply(events)
+    ///     })
+    ///     .with(warp::compression::gzip().flush(Flush::EveryChunk));
+    /// ```
+    pub fn flush(mut self, flush: Flush) -> Self {
+        Arc::make_mut(&mut self.options).flush = flush;
+        self
+    }
+
+    /// Only compress responses with one of these content types.
+    ///
+    /// Types may end with a wildcard, such as `text/*`. Parameters like
+    /// `charset` are ignored when matching.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// let compression = warp::compression::gzip()
+    ///     .content_types(vec!["text/*", "application/json"])
+    ///     .min_size(1024);
+    ///
+    /// let route = warp::fs::dir("./static").with(compression);
+    /// ```
+    pub fn content_types<I>(mut self, types: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let types = types.into_iter().map(|ty| ty.into().to_ascii_lowercase());
+        Arc::make_mut(&mut self.options)
+            .content_types
+            .get_or_insert_with(Vec::new)
+            .extend(types);
+        self
+    }
+
+    /// Never compress responses with one of these content types, such as
+    /// `image/jpeg` that is compressed already.
+    ///
+    /// Types may end with a wildcard, such as `video/*`.
+    pub fn exclude_content_types<I>(mut self, types: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let types = types.into_iter().map(|ty| ty.into().to_ascii_lowercase());
+        Arc::make_mut(&mut self.options)
+            .exclude_content_types
+            .extend(types);
+        self
+    }
 }
 
-// TODO: The implementation of `gzip()`, `deflate()`, and `brotli()` could be replaced with
-// generics or a macro
+impl Options {
+    fn should_compress(&self, res: &Response) -> bool {
+        let headers = res.headers();
+        match headers.get(CONTENT_ENCODING) {
+            Some(encoding) if encoding != "identity" => return false,
+            _ => (),
+        }
+
+        let len = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse().ok())
+            .or_else(|| res.body().size_hint().exact());
+        if let Some(len) = len {
+            if len < self.min_size {
+                return false;
+            }
+        }
+
+        if self.content_types.is_none() && self.exclude_content_types.is_empty() {
+            return true;
+        }
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|ty| ty.to_str().ok())
+            .and_then(|ty| ty.split(';').next())
+            .map(|ty| ty.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => content_type.starts_with(prefix),
+            None => *pattern == content_type,
+        };
+        if self.exclude_content_types.iter().any(matches) {
+            return false;
+        }
+        match self.content_types {
+            Some(ref types) => types.iter().any(matches),
+            None => true,
+        }
+    }
+}
 
 /// Create a wrapping filter that compresses the Body of a [`Response`](crate::reply::Response)
 /// using gzip, adding `content-encoding: gzip` to the Response's [`HeaderMap`](hyper::HeaderMap)
@@ -55,16 +280,8 @@ pub struct Compression<F> {
 ///     .with(warp::compression::gzip());
 /// ```
 pub fn gzip() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
-    let func = move |mut props: CompressionProps| {
-        let body = Body::wrap_stream(GzipEncoder::new(props.body));
-        props
-            .head
-            .headers
-            .append(CONTENT_ENCODING, CompressionAlgo::GZIP.into());
-        props.head.headers.remove(CONTENT_LENGTH);
-        Response::from_parts(props.head, body)
-    };
-    Compression { func }
+    let func = move |props: CompressionProps| CompressionAlgo::GZIP.encode(props);
+    Compression::new(func)
 }
 
 /// Create a wrapping filter that compresses the Body of a [`Response`](crate::reply::Response)
@@ -81,16 +298,8 @@ pub fn gzip() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
 ///     .with(warp::compression::deflate());
 /// ```
 pub fn deflate() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
-    let func = move |mut props: CompressionProps| {
-        let body = Body::wrap_stream(DeflateEncoder::new(props.body));
-        props
-            .head
-            .headers
-            .append(CONTENT_ENCODING, CompressionAlgo::DEFLATE.into());
-        props.head.headers.remove(CONTENT_LENGTH);
-        Response::from_parts(props.head, body)
-    };
-    Compression { func }
+    let func = move |props: CompressionProps| CompressionAlgo::DEFLATE.encode(props);
+    Compression::new(func)
 }
 
 /// Create a wrapping filter that compresses the Body of a [`Response`](crate::reply::Response)
@@ -107,16 +316,101 @@ pub fn deflate() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
 ///     .with(warp::compression::brotli());
 /// ```
 pub fn brotli() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
+    let func = move |props: CompressionProps| CompressionAlgo::BR.encode(props);
+    Compression::new(func)
+}
+
+/// Create a wrapping filter that compresses the Body of a [`Response`](crate::reply::Response)
+/// using zstd, adding `content-encoding: zstd` to the Response's [`HeaderMap`](hyper::HeaderMap)
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::get()
+///     .and(warp::path::end())
+///     .and(warp::fs::file("./README.md"))
+///     .with(warp::compression::zstd());
+/// ```
+pub fn zstd() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
+    let func = move |props: CompressionProps| CompressionAlgo::ZSTD.encode(props);
+    Compression::new(func)
+}
+
+/// Create a wrapping filter that compresses the Body of a [`Response`](crate::reply::Response)
+/// with the best encoding accepted by the client.
+///
+/// The encoding is chosen from the `q`-values of the request's `Accept-Encoding`
+/// header, preferring brotli, then zstd, gzip and deflate when several are
+/// accepted equally. If the client accepts none of them, the response is not
+/// compressed. `vary: accept-encoding` is added to the Response in all cases.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::get()
+///     .and(warp::path::end())
+///     .and(warp::fs::file("./README.md"))
+///     .with(warp::compression::auto());
+/// ```
+pub fn auto() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
     let func = move |mut props: CompressionProps| {
-        let body = Body::wrap_stream(BrotliEncoder::new(props.body));
         props
             .head
             .headers
-            .append(CONTENT_ENCODING, CompressionAlgo::BR.into());
-        props.head.headers.remove(CONTENT_LENGTH);
-        Response::from_parts(props.head, body)
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+        let algo = props
+            .accept_encoding
+            .as_ref()
+            .and_then(|value| value.to_str().ok())
+            .and_then(negotiate);
+        match algo {
+            Some(algo) => algo.encode(props),
+            None => props.into_response(),
+        }
     };
-    Compression { func }
+    Compression::new(func)
+}
+
+// Pick the supported encoding with the highest `q`-value in an `Accept-Encoding`.
+fn negotiate(accept: &str) -> Option<CompressionAlgo> {
+    let mut best = None;
+    let mut best_q = 0.0;
+    for algo in CompressionAlgo::ALL.iter().copied() {
+        let mut q = None;
+        let mut wildcard = None;
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let coding = parts.next().unwrap_or("").trim();
+            let weight = parts
+                .filter_map(|param| {
+                    let mut kv = param.splitn(2, '=');
+                    let name = kv.next()?.trim();
+                    let value = kv.next()?.trim();
+                    if name.eq_ignore_ascii_case("q") {
+                        value.parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            if coding.eq_ignore_ascii_case(algo.name()) {
+                q = Some(weight);
+            } else if coding == "*" {
+                wildcard = Some(weight);
+            }
+        }
+        let q = q.or(wildcard).unwrap_or(0.0);
+        if q > best_q {
+            best = Some(algo);
+            best_q = q;
+        }
+    }
+    best
 }
 
 impl<FN, F> WrapSealed<F> for Compression<FN>
@@ -141,16 +435,24 @@ mod internal {
     use std::pin::Pin;
     use std::task::{Context, Poll};
 
+    use std::io;
+
+    use async_compression::tokio_02::write::{
+        BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder,
+    };
     use bytes::Bytes;
     use futures::{ready, Stream, TryFuture};
+    use http::header::{HeaderValue, ACCEPT_ENCODING};
     use hyper::Body;
     use pin_project::pin_project;
+    use tokio::io::AsyncWrite;
 
     use crate::filter::{Filter, FilterBase, Internal};
     use crate::reject::IsReject;
     use crate::reply::{Reply, Response};
+    use crate::route;
 
-    use super::Compression;
+    use super::{Compression, Flush, Level};
 
     /// A wrapper around any type that implements [`Stream`](futures::Stream) to be
     /// compatible with async_compression's Stream based encoders
@@ -188,11 +490,153 @@ mod internal {
         }
     }
 
+    // Output is handed to the client once this much of it is ready.
+    const OUTPUT_CHUNK_SIZE: usize = 8 * 1024;
+
+    pub enum Writer {
+        Br(Box<BrotliEncoder<Vec<u8>>>),
+        Deflate(DeflateEncoder<Vec<u8>>),
+        Gzip(GzipEncoder<Vec<u8>>),
+        Zstd(ZstdEncoder<Vec<u8>>),
+    }
+
+    macro_rules! each_writer {
+        ($writer:expr, $w:ident => $e:expr) => {
+            match $writer {
+                Writer::Br($w) => $e,
+                Writer::Deflate($w) => $e,
+                Writer::Gzip($w) => $e,
+                Writer::Zstd($w) => $e,
+            }
+        };
+    }
+
+    impl Writer {
+        // The encoders write into a `Vec`, so they are never pending.
+        fn write_all(&mut self, mut buf: &[u8], cx: &mut Context) -> io::Result<()> {
+            while !buf.is_empty() {
+                let written = each_writer!(self, w => Pin::new(w).poll_write(cx, buf));
+                match written {
+                    Poll::Ready(Ok(0)) => return Err(io::ErrorKind::WriteZero.into()),
+                    Poll::Ready(Ok(n)) => buf = &buf[n..],
+                    Poll::Ready(Err(err)) => return Err(err),
+                    Poll::Pending => unreachable!("writing to a Vec is never pending"),
+                }
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self, cx: &mut Context) -> io::Result<()> {
+            match each_writer!(self, w => Pin::new(w).poll_flush(cx)) {
+                Poll::Ready(res) => res,
+                Poll::Pending => unreachable!("writing to a Vec is never pending"),
+            }
+        }
+
+        fn shutdown(&mut self, cx: &mut Context) -> io::Result<()> {
+            match each_writer!(self, w => Pin::new(w).poll_shutdown(cx)) {
+                Poll::Ready(res) => res,
+                Poll::Pending => unreachable!("writing to a Vec is never pending"),
+            }
+        }
+
+        fn output(&mut self) -> &mut Vec<u8> {
+            each_writer!(self, w => w.get_mut())
+        }
+    }
+
+    /// A body compressed chunk by chunk, without buffering the whole of it.
+    #[pin_project]
+    pub struct EncodedBody<S> {
+        #[pin]
+        body: S,
+        writer: Writer,
+        flush: Flush,
+        // Whether data was written since the last flush.
+        dirty: bool,
+        done: bool,
+    }
+
+    impl<S> EncodedBody<S> {
+        pub(super) fn new(body: S, writer: Writer, flush: Flush) -> Self {
+            EncodedBody {
+                body,
+                writer,
+                flush,
+                dirty: false,
+                done: false,
+            }
+        }
+    }
+
+    impl<S> Stream for EncodedBody<S>
+    where
+        S: Stream<Item = io::Result<Bytes>>,
+    {
+        type Item = io::Result<Bytes>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let mut pin = self.project();
+            loop {
+                if *pin.done {
+                    return Poll::Ready(None);
+                }
+
+                let ended = match pin.body.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        pin.writer.write_all(&chunk, cx)?;
+                        *pin.dirty = true;
+                        if *pin.flush == Flush::EveryChunk {
+                            pin.writer.flush(cx)?;
+                            *pin.dirty = false;
+                        }
+                        false
+                    }
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                    Poll::Ready(None) => {
+                        pin.writer.shutdown(cx)?;
+                        *pin.done = true;
+                        true
+                    }
+                    Poll::Pending => {
+                        if *pin.dirty && *pin.flush == Flush::WhenIdle {
+                            pin.writer.flush(cx)?;
+                            *pin.dirty = false;
+                        }
+                        let output = pin.writer.output();
+                        if output.is_empty() {
+                            return Poll::Pending;
+                        }
+                        let chunk = Bytes::from(std::mem::take(output));
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                };
+
+                let output = pin.writer.output();
+                let ready =
+                    ended || *pin.flush == Flush::EveryChunk || output.len() >= OUTPUT_CHUNK_SIZE;
+                if ready && !output.is_empty() {
+                    let chunk = Bytes::from(std::mem::take(output));
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+            }
+        }
+    }
+
     /// Compression Props
     #[derive(Debug)]
     pub struct CompressionProps {
         pub(super) body: CompressableBody<Body, hyper::Error>,
         pub(super) head: http::response::Parts,
+        pub(super) accept_encoding: Option<HeaderValue>,
+        pub(super) level: Level,
+        pub(super) flush: Flush,
+    }
+
+    impl CompressionProps {
+        pub(super) fn into_response(self) -> Response {
+            Response::from_parts(self.head, self.body.body)
+        }
     }
 
     impl From<http::Response<Body>> for CompressionProps {
@@ -201,6 +645,9 @@ mod internal {
             CompressionProps {
                 body: body.into(),
                 head,
+                accept_encoding: None,
+                level: Level::Default,
+                flush: Flush::WhenIdle,
             }
         }
     }
@@ -216,7 +663,7 @@ mod internal {
     }
 
     #[allow(missing_debug_implementations)]
-    #[derive(Clone, Copy)]
+    #[derive(Clone)]
     pub struct WithCompression<FN, F> {
         pub(super) compress: Compression<FN>,
         pub(super) filter: F,
@@ -234,8 +681,11 @@ mod internal {
         type Future = WithCompressionFuture<FN, F::Future>;
 
         fn filter(&self, _: Internal) -> Self::Future {
+            let accept_encoding =
+                route::with(|route| route.headers().get(ACCEPT_ENCODING).cloned());
             WithCompressionFuture {
                 compress: self.compress.clone(),
+                accept_encoding,
                 future: self.filter.filter(Internal),
             }
         }
@@ -245,6 +695,7 @@ mod internal {
     #[pin_project]
     pub struct WithCompressionFuture<FN, F> {
         compress: Compression<FN>,
+        accept_encoding: Option<HeaderValue>,
         #[pin]
         future: F,
     }
@@ -258,12 +709,21 @@ mod internal {
     {
         type Output = Result<(Compressed,), F::Error>;
 
-        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-            let pin = self.as_mut().project();
+        fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let pin = self.project();
             let result = ready!(pin.future.try_poll(cx));
             match result {
                 Ok(reply) => {
-                    let resp = (self.compress.func)(reply.into_response().into());
+                    let resp = reply.into_response();
+                    let options = &pin.compress.options;
+                    if !options.should_compress(&resp) {
+                        return Poll::Ready(Ok((Compressed(resp),)));
+                    }
+                    let mut props = CompressionProps::from(resp);
+                    props.accept_encoding = pin.accept_encoding.take();
+                    props.level = options.level;
+                    props.flush = options.flush;
+                    let resp = (pin.compress.func)(props);
                     Poll::Ready(Ok((Compressed(resp),)))
                 }
                 Err(reject) => Poll::Ready(Err(reject)),
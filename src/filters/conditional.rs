@@ -0,0 +1,179 @@
+//! Conditional request filters
+//!
+//! Filters extracting the preconditions of a request, to evaluate them
+//! against the current state of a resource before handling the request, as
+//! described by [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-13).
+//!
+//! This prevents lost updates, such as a `PUT` with an `If-Match` header
+//! overwriting a change it hasn't seen, and saves work on `GET` requests for
+//! content a client already has. To only add `ETag` and `Last-Modified` to
+//! a reply and answer `GET` requests, [`reply::conditional`] is simpler.
+//!
+//! [`reply::conditional`]: crate::reply::conditional
+
+use std::convert::Infallible;
+use std::time::SystemTime;
+
+use headers::{
+    ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince, LastModified,
+};
+use http::{Method, StatusCode};
+use hyper::Body;
+
+use crate::filter::{filter_fn_one, Filter, One};
+use crate::reply::Response;
+use crate::route::Route;
+
+/// Creates a `Filter` extracting the [`Preconditions`] of a request.
+///
+/// Headers that can't be parsed are ignored, as RFC 9110 requires.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use warp::conditional::Preconditions;
+/// use warp::Filter;
+///
+/// let route = warp::put()
+///     .and(warp::path!("posts" / u32))
+///     .and(warp::conditional())
+///     .map(|id: u32, preconditions: Preconditions| {
+///         // Look up the current version of the post...
+///         let etag = format!("\"post-{}-{}\"", id, 3);
+///         let updated = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+///         if let Some(res) = preconditions.evaluate(Some(&etag), Some(updated)) {
+///             return res;
+///         }
+///         // Update the post...
+///         warp::reply::Response::new("updated".into())
+///     });
+/// ```
+pub fn conditional() -> impl Filter<Extract = One<Preconditions>, Error = Infallible> + Copy {
+    filter_fn_one(|route| futures::future::ok(Preconditions::of(route)))
+}
+
+/// The preconditions of a request, see [`conditional`].
+#[derive(Clone, Debug)]
+pub struct Preconditions {
+    method: Method,
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+    if_unmodified_since: Option<IfUnmodifiedSince>,
+}
+
+impl Preconditions {
+    pub(crate) fn of(route: &Route) -> Preconditions {
+        let headers = route.headers();
+        Preconditions {
+            method: route.method().clone(),
+            if_match: headers.typed_get(),
+            if_none_match: headers.typed_get(),
+            if_modified_since: headers.typed_get(),
+            if_unmodified_since: headers.typed_get(),
+        }
+    }
+
+    /// Returns `true` if the request has no preconditions.
+    pub fn is_empty(&self) -> bool {
+        self.if_match.is_none()
+            && self.if_none_match.is_none()
+            && self.if_modified_since.is_none()
+            && self.if_unmodified_since.is_none()
+    }
+
+    /// Evaluates the preconditions against an existing resource, with its
+    /// current `etag` and modification time.
+    ///
+    /// Returns the reply to send instead of handling the request, if a
+    /// precondition fails: `304 Not Modified` for a `GET` or `HEAD` of
+    /// content the client already has, and `412 Precondition Failed`
+    /// otherwise. The reply has the `ETag` and `Last-Modified` headers of
+    /// the resource.
+    ///
+    /// The preconditions are evaluated in the order of RFC 9110:
+    /// `If-Match`, else `If-Unmodified-Since`, then `If-None-Match`, else
+    /// `If-Modified-Since`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `etag` is not a legal entity tag, such as
+    /// `"xyzzy"` or `W/"xyzzy"`, quotes included.
+    pub fn evaluate(
+        &self,
+        etag: Option<&str>,
+        last_modified: Option<SystemTime>,
+    ) -> Option<Response> {
+        let etag = etag.map(|etag| {
+            etag.parse::<ETag>()
+                .unwrap_or_else(|_| panic!("illegal ETag: {:?}", etag))
+        });
+        let last_modified = last_modified.map(LastModified::from);
+
+        let status = self.check(etag.as_ref(), last_modified)?;
+        let mut res = Response::new(Body::empty());
+        *res.status_mut() = status;
+        if let Some(etag) = etag {
+            res.headers_mut().typed_insert(etag);
+        }
+        if let Some(last_modified) = last_modified {
+            res.headers_mut().typed_insert(last_modified);
+        }
+        Some(res)
+    }
+
+    /// Evaluates the preconditions against a resource that doesn't exist.
+    ///
+    /// Any `If-Match` fails with `412 Precondition Failed`, while
+    /// `If-None-Match: *` passes, so that a `PUT` with it only creates the
+    /// resource if it doesn't exist yet.
+    pub fn evaluate_missing(&self) -> Option<Response> {
+        self.if_match.as_ref()?;
+        let mut res = Response::new(Body::empty());
+        *res.status_mut() = StatusCode::PRECONDITION_FAILED;
+        Some(res)
+    }
+
+    // The status replacing the reply of an existing resource, if any.
+    pub(crate) fn check(
+        &self,
+        etag: Option<&ETag>,
+        last_modified: Option<LastModified>,
+    ) -> Option<StatusCode> {
+        if let Some(ref if_match) = self.if_match {
+            let passes = match etag {
+                Some(etag) => if_match.precondition_passes(etag),
+                None => if_match.is_any(),
+            };
+            if !passes {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        } else if let (Some(since), Some(modified)) = (self.if_unmodified_since, last_modified) {
+            if !since.precondition_passes(modified.into()) {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+
+        let safe = self.method == Method::GET || self.method == Method::HEAD;
+        if let Some(ref if_none_match) = self.if_none_match {
+            let passes = match etag {
+                Some(etag) => if_none_match.precondition_passes(etag),
+                None => *if_none_match != IfNoneMatch::any(),
+            };
+            if !passes {
+                return Some(if safe {
+                    StatusCode::NOT_MODIFIED
+                } else {
+                    StatusCode::PRECONDITION_FAILED
+                });
+            }
+        } else if let (Some(since), Some(modified)) = (self.if_modified_since, last_modified) {
+            if safe && !since.is_modified(modified.into()) {
+                return Some(StatusCode::NOT_MODIFIED);
+            }
+        }
+
+        None
+    }
+}
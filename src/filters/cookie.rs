@@ -31,3 +31,137 @@ pub fn optional(
     header::optional2()
         .map(move |opt: Option<Cookie>| opt.and_then(|cookie| cookie.get(name).map(String::from)))
 }
+
+/// Creates a `Filter` that requires a signed cookie by name.
+///
+/// The cookie must have been signed with `key`, such as with
+/// [`SetCookie::signed`](crate::reply::SetCookie::signed). If found and the
+/// signature is valid, extracts the value of the cookie, otherwise rejects.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let key = warp::cookie::Key::generate();
+///
+/// let route = warp::cookie::signed("user", key)
+///     .map(|user: String| format!("Hello, {}!", user));
+/// ```
+#[cfg(feature = "secure-cookies")]
+pub fn signed(
+    name: &'static str,
+    key: Key,
+) -> impl Filter<Extract = One<String>, Error = Rejection> + Clone {
+    cookie(name).and_then(move |value: String| {
+        let value = key
+            .verify(name, &value)
+            .ok_or_else(|| crate::reject::missing_cookie(name));
+        future::ready(value)
+    })
+}
+
+/// Creates a `Filter` that requires an encrypted cookie by name.
+///
+/// The cookie must have been encrypted with `key`, such as with
+/// [`SetCookie::private`](crate::reply::SetCookie::private). If found and it
+/// can be decrypted, extracts the value of the cookie, otherwise rejects.
+#[cfg(feature = "secure-cookies")]
+pub fn private(
+    name: &'static str,
+    key: Key,
+) -> impl Filter<Extract = One<String>, Error = Rejection> + Clone {
+    cookie(name).and_then(move |value: String| {
+        let value = key
+            .decrypt(name, &value)
+            .ok_or_else(|| crate::reject::missing_cookie(name));
+        future::ready(value)
+    })
+}
+
+/// A key to sign and encrypt cookies with.
+#[cfg(feature = "secure-cookies")]
+#[derive(Clone)]
+pub struct Key(::cookie::Key);
+
+#[cfg(feature = "secure-cookies")]
+impl Key {
+    /// Create a `Key` from at least 64 bytes of secret key material.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `key` is shorter than 64 bytes.
+    pub fn from_bytes(key: &[u8]) -> Key {
+        assert!(key.len() >= 64, "cookie key must be at least 64 bytes");
+        Key(::cookie::Key::from(key))
+    }
+
+    /// Generate a random `Key`.
+    ///
+    /// Cookies using it can't be read once the server restarts.
+    pub fn generate() -> Key {
+        Key(::cookie::Key::generate())
+    }
+
+    pub(crate) fn sign(&self, name: &str, value: &str) -> String {
+        let mut jar = ::cookie::CookieJar::new();
+        jar.signed(&self.0)
+            .add(::cookie::Cookie::new(name.to_owned(), value.to_owned()));
+        jar.get(name)
+            .expect("signed cookie was just added")
+            .value()
+            .to_owned()
+    }
+
+    pub(crate) fn verify(&self, name: &str, value: &str) -> Option<String> {
+        let mut jar = ::cookie::CookieJar::new();
+        jar.add_original(::cookie::Cookie::new(name.to_owned(), value.to_owned()));
+        let cookie = jar.signed(&self.0).get(name)?;
+        Some(cookie.value().to_owned())
+    }
+
+    pub(crate) fn encrypt(&self, name: &str, value: &str) -> String {
+        let mut jar = ::cookie::CookieJar::new();
+        jar.private(&self.0)
+            .add(::cookie::Cookie::new(name.to_owned(), value.to_owned()));
+        jar.get(name)
+            .expect("private cookie was just added")
+            .value()
+            .to_owned()
+    }
+
+    pub(crate) fn decrypt(&self, name: &str, value: &str) -> Option<String> {
+        let mut jar = ::cookie::CookieJar::new();
+        jar.add_original(::cookie::Cookie::new(name.to_owned(), value.to_owned()));
+        let cookie = jar.private(&self.0).get(name)?;
+        Some(cookie.value().to_owned())
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Key").finish()
+    }
+}
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only send the cookie with same-site requests.
+    Strict,
+    /// Also send the cookie when navigating to the site.
+    Lax,
+    /// Send the cookie with cross-site requests too, which requires `Secure`.
+    None,
+}
+
+impl SameSite {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
@@ -3,6 +3,9 @@
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 
 use headers::{
@@ -19,6 +22,11 @@ use crate::reply::Reply;
 
 use self::internal::{CorsFilter, IntoOrigin, Seconds};
 
+const ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-request-private-network");
+const ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-allow-private-network");
+
 /// Create a wrapping filter that exposes [CORS][] behavior for a wrapped
 /// filter.
 ///
@@ -49,9 +57,13 @@ pub fn cors() -> Builder {
         credentials: false,
         allowed_headers: HashSet::new(),
         exposed_headers: HashSet::new(),
+        expose_any_header: false,
+        reflect_request_headers: false,
+        private_network: false,
         max_age: None,
         methods: HashSet::new(),
         origins: None,
+        origin_fn: None,
     }
 }
 
@@ -67,9 +79,30 @@ pub struct Builder {
     credentials: bool,
     allowed_headers: HashSet<HeaderName>,
     exposed_headers: HashSet<HeaderName>,
+    expose_any_header: bool,
+    reflect_request_headers: bool,
+    private_network: bool,
     max_age: Option<u64>,
     methods: HashSet<http::Method>,
     origins: Option<HashSet<HeaderValue>>,
+    origin_fn: Option<OriginFn>,
+}
+
+type OriginCheck = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+#[derive(Clone)]
+enum OriginFn {
+    Sync(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+    Async(Arc<dyn Fn(String) -> OriginCheck + Send + Sync>),
+}
+
+impl fmt::Debug for OriginFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OriginFn::Sync(_) => f.write_str("OriginFn::Sync"),
+            OriginFn::Async(_) => f.write_str("OriginFn::Async"),
+        }
+    }
 }
 
 impl Builder {
@@ -184,6 +217,36 @@ impl Builder {
         self
     }
 
+    /// Allows any request header, by reflecting the headers asked for in a
+    /// preflight request.
+    ///
+    /// The list of allowed headers is ignored.
+    pub fn reflect_request_headers(mut self) -> Self {
+        self.reflect_request_headers = true;
+        self
+    }
+
+    /// Exposes all headers of the response.
+    ///
+    /// This sends `Access-Control-Expose-Headers: *`, or, since browsers
+    /// don't treat `*` as a wildcard for requests with credentials, the
+    /// names of the response headers when credentials are allowed.
+    pub fn expose_any_header(mut self) -> Self {
+        self.expose_any_header = true;
+        self
+    }
+
+    /// Sets whether to allow requests from public websites to reach this
+    /// server on a private network.
+    ///
+    /// If allowed, preflight requests with
+    /// `Access-Control-Request-Private-Network: true` get an
+    /// `Access-Control-Allow-Private-Network: true` header.
+    pub fn allow_private_network(mut self, allow: bool) -> Self {
+        self.private_network = allow;
+        self
+    }
+
     /// Sets that *any* `Origin` header is allowed.
     ///
     /// # Warning
@@ -192,6 +255,7 @@ impl Builder {
     /// it is usually better to set an explicit list.
     pub fn allow_any_origin(mut self) -> Self {
         self.origins = None;
+        self.origin_fn = None;
         self
     }
 
@@ -229,6 +293,50 @@ impl Builder {
         self
     }
 
+    /// Decide whether an `Origin` is allowed with a function.
+    ///
+    /// The function is called with origins that aren't already in the list
+    /// of allowed origins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let cors = warp::cors()
+    ///     .allow_origin_fn(|origin| origin.ends_with(".hyper.rs"));
+    /// ```
+    pub fn allow_origin_fn<F>(mut self, func: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.origin_fn = Some(OriginFn::Sync(Arc::new(func)));
+        self
+    }
+
+    /// Decide whether an `Origin` is allowed with an async function, such
+    /// as one checking a database.
+    ///
+    /// The function is called with origins that aren't already in the list
+    /// of allowed origins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let cors = warp::cors()
+    ///     .allow_origin_fn_async(|origin| async move {
+    ///         origin == "https://hyper.rs"
+    ///     });
+    /// ```
+    pub fn allow_origin_fn_async<F, Fut>(mut self, func: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.origin_fn = Some(OriginFn::Async(Arc::new(move |origin| {
+            Box::pin(func(origin))
+        })));
+        self
+    }
+
     /// Sets the `Access-Control-Max-Age` header.
     ///
     /// # Example
@@ -344,24 +452,39 @@ struct Configured {
 }
 
 enum Validated {
-    Preflight(HeaderValue),
+    Preflight(HeaderValue, Requested),
     Simple(HeaderValue),
     NotCors,
 }
 
+// What a preflight request asked for, that may be echoed in the response.
+#[derive(Debug)]
+struct Requested {
+    headers: Option<HeaderValue>,
+    private_network: bool,
+}
+
+enum OriginAllowed {
+    Yes,
+    No,
+    Pending(OriginCheck),
+}
+
 impl Configured {
     fn check_request(
         &self,
         method: &http::Method,
         headers: &http::HeaderMap,
-    ) -> Result<Validated, Forbidden> {
+    ) -> Result<(Validated, Option<OriginCheck>), Forbidden> {
         match (headers.get(header::ORIGIN), method) {
             (Some(origin), &http::Method::OPTIONS) => {
                 // OPTIONS requests are preflight CORS requests...
 
-                if !self.is_origin_allowed(origin) {
-                    return Err(Forbidden::OriginNotAllowed);
-                }
+                let pending = match self.is_origin_allowed(origin) {
+                    OriginAllowed::Yes => None,
+                    OriginAllowed::No => return Err(Forbidden::OriginNotAllowed),
+                    OriginAllowed::Pending(check) => Some(check),
+                };
 
                 if let Some(req_method) = headers.get(header::ACCESS_CONTROL_REQUEST_METHOD) {
                     if !self.is_method_allowed(req_method) {
@@ -372,7 +495,8 @@ impl Configured {
                     return Err(Forbidden::MethodNotAllowed);
                 }
 
-                if let Some(req_headers) = headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+                let req_headers = headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS);
+                if let Some(req_headers) = req_headers {
                     let headers = req_headers
                         .to_str()
                         .map_err(|_| Forbidden::HeaderNotAllowed)?;
@@ -383,21 +507,34 @@ impl Configured {
                     }
                 }
 
-                Ok(Validated::Preflight(origin.clone()))
+                let requested = Requested {
+                    headers: req_headers
+                        .filter(|_| self.cors.reflect_request_headers)
+                        .cloned(),
+                    private_network: self.cors.private_network
+                        && headers
+                            .get(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK)
+                            .map(|value| value == "true")
+                            .unwrap_or(false),
+                };
+
+                Ok((Validated::Preflight(origin.clone(), requested), pending))
             }
             (Some(origin), _) => {
                 // Any other method, simply check for a valid origin...
 
                 log::trace!("origin header: {:?}", origin);
-                if self.is_origin_allowed(origin) {
-                    Ok(Validated::Simple(origin.clone()))
-                } else {
-                    Err(Forbidden::OriginNotAllowed)
+                match self.is_origin_allowed(origin) {
+                    OriginAllowed::Yes => Ok((Validated::Simple(origin.clone()), None)),
+                    OriginAllowed::No => Err(Forbidden::OriginNotAllowed),
+                    OriginAllowed::Pending(check) => {
+                        Ok((Validated::Simple(origin.clone()), Some(check)))
+                    }
                 }
             }
             (None, _) => {
                 // No `ORIGIN` header means this isn't CORS!
-                Ok(Validated::NotCors)
+                Ok((Validated::NotCors, None))
             }
         }
     }
@@ -409,44 +546,89 @@ impl Configured {
     }
 
     fn is_header_allowed(&self, header: &str) -> bool {
+        if self.cors.reflect_request_headers {
+            return HeaderName::from_bytes(header.trim().as_bytes()).is_ok();
+        }
         HeaderName::from_bytes(header.as_bytes())
             .map(|header| self.cors.allowed_headers.contains(&header))
             .unwrap_or(false)
     }
 
-    fn is_origin_allowed(&self, origin: &HeaderValue) -> bool {
-        if let Some(ref allowed) = self.cors.origins {
-            allowed.contains(origin)
-        } else {
-            true
+    fn is_origin_allowed(&self, origin: &HeaderValue) -> OriginAllowed {
+        let listed = match self.cors.origins {
+            Some(ref allowed) => allowed.contains(origin),
+            None => self.cors.origin_fn.is_none(),
+        };
+        if listed {
+            return OriginAllowed::Yes;
+        }
+
+        let origin = match (&self.cors.origin_fn, origin.to_str()) {
+            (Some(_), Ok(origin)) => origin,
+            _ => return OriginAllowed::No,
+        };
+        match self.cors.origin_fn {
+            Some(OriginFn::Sync(ref func)) if func(origin) => OriginAllowed::Yes,
+            Some(OriginFn::Async(ref func)) => OriginAllowed::Pending(func(origin.to_owned())),
+            _ => OriginAllowed::No,
         }
     }
 
-    fn append_preflight_headers(&self, headers: &mut http::HeaderMap) {
+    fn append_preflight_headers(&self, headers: &mut http::HeaderMap, requested: Requested) {
         self.append_common_headers(headers);
 
-        headers.typed_insert(self.allowed_headers_header.clone());
+        if self.cors.reflect_request_headers {
+            if let Some(req_headers) = requested.headers {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, req_headers);
+            }
+            headers.append(
+                header::VARY,
+                HeaderValue::from_static("access-control-request-headers"),
+            );
+        } else {
+            headers.typed_insert(self.allowed_headers_header.clone());
+        }
         headers.typed_insert(self.methods_header.clone());
 
+        if requested.private_network {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK,
+                HeaderValue::from_static("true"),
+            );
+        }
+
         if let Some(max_age) = self.cors.max_age {
             headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.into());
         }
     }
 
     fn append_common_headers(&self, headers: &mut http::HeaderMap) {
+        if self.cors.expose_any_header {
+            if !self.cors.credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                    HeaderValue::from_static("*"),
+                );
+            } else if !headers.is_empty() {
+                let names = headers.keys().map(HeaderName::as_str).collect::<Vec<_>>();
+                let exposed = HeaderValue::from_str(&names.join(", "))
+                    .expect("header names are valid header values");
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+            }
+        } else if let Some(expose_headers_header) = &self.expose_headers_header {
+            headers.typed_insert(expose_headers_header.clone())
+        }
         if self.cors.credentials {
             headers.insert(
                 header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
                 HeaderValue::from_static("true"),
             );
         }
-        if let Some(expose_headers_header) = &self.expose_headers_header {
-            headers.typed_insert(expose_headers_header.clone())
-        }
     }
 }
 
 mod internal {
+    use std::fmt;
     use std::future::Future;
     use std::pin::Pin;
     use std::sync::Arc;
@@ -457,7 +639,7 @@ mod internal {
     use http::header;
     use pin_project::pin_project;
 
-    use super::{Configured, CorsForbidden, Validated};
+    use super::{Configured, CorsForbidden, Forbidden, OriginCheck, Requested, Validated};
     use crate::filter::{Filter, FilterBase, Internal, One};
     use crate::generic::Either;
     use crate::reject::{CombineRejection, Rejection};
@@ -471,7 +653,7 @@ mod internal {
 
     impl<F> FilterBase for CorsFilter<F>
     where
-        F: Filter,
+        F: Filter + Clone + Send,
         F::Extract: Send,
         F::Future: Future,
         F::Error: CombineRejection<Rejection>,
@@ -479,29 +661,40 @@ mod internal {
         type Extract =
             One<Either<One<Preflight>, One<Either<One<Wrapped<F::Extract>>, F::Extract>>>>;
         type Error = <F::Error as CombineRejection<Rejection>>::One;
-        type Future = future::Either<
-            future::Ready<Result<Self::Extract, Self::Error>>,
-            WrappedFuture<F::Future>,
-        >;
+        type Future =
+            future::Either<future::Ready<Result<Self::Extract, Self::Error>>, WrappedFuture<F>>;
 
         fn filter(&self, _: Internal) -> Self::Future {
             let validated =
                 route::with(|route| self.config.check_request(route.method(), route.headers()));
 
             match validated {
-                Ok(Validated::Preflight(origin)) => {
+                Ok((validated, Some(check))) => future::Either::Right(WrappedFuture {
+                    pending: Some(Pending {
+                        check,
+                        validated,
+                        config: self.config.clone(),
+                        filter: self.inner.clone(),
+                    }),
+                    inner: None,
+                    wrapped: None,
+                }),
+                Ok((Validated::Preflight(origin, requested), None)) => {
                     let preflight = Preflight {
                         config: self.config.clone(),
                         origin,
+                        requested,
                     };
                     future::Either::Left(future::ok((Either::A((preflight,)),)))
                 }
-                Ok(Validated::Simple(origin)) => future::Either::Right(WrappedFuture {
-                    inner: self.inner.filter(Internal),
+                Ok((Validated::Simple(origin), None)) => future::Either::Right(WrappedFuture {
+                    pending: None,
+                    inner: Some(self.inner.filter(Internal)),
                     wrapped: Some((self.config.clone(), origin)),
                 }),
-                Ok(Validated::NotCors) => future::Either::Right(WrappedFuture {
-                    inner: self.inner.filter(Internal),
+                Ok((Validated::NotCors, None)) => future::Either::Right(WrappedFuture {
+                    pending: None,
+                    inner: Some(self.inner.filter(Internal)),
                     wrapped: None,
                 }),
                 Err(err) => {
@@ -516,12 +709,14 @@ mod internal {
     pub struct Preflight {
         config: Arc<Configured>,
         origin: header::HeaderValue,
+        requested: Requested,
     }
 
     impl crate::reply::Reply for Preflight {
         fn into_response(self) -> crate::reply::Response {
             let mut res = crate::reply::Response::default();
-            self.config.append_preflight_headers(res.headers_mut());
+            self.config
+                .append_preflight_headers(res.headers_mut(), self.requested);
             res.headers_mut()
                 .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, self.origin);
             res
@@ -549,26 +744,78 @@ mod internal {
     }
 
     #[pin_project]
-    #[derive(Debug)]
-    pub struct WrappedFuture<F> {
+    pub struct WrappedFuture<F: FilterBase> {
+        pending: Option<Pending<F>>,
         #[pin]
-        inner: F,
+        inner: Option<F::Future>,
         wrapped: Option<(Arc<Configured>, header::HeaderValue)>,
     }
 
+    // A request waiting for an async `allow_origin_fn_async` decision.
+    struct Pending<F> {
+        check: OriginCheck,
+        validated: Validated,
+        config: Arc<Configured>,
+        filter: F,
+    }
+
+    impl<F: FilterBase> fmt::Debug for WrappedFuture<F> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("WrappedFuture")
+                .field("pending", &self.pending.is_some())
+                .field("wrapped", &self.wrapped)
+                .finish()
+        }
+    }
+
     impl<F> Future for WrappedFuture<F>
     where
-        F: TryFuture,
+        F: Filter,
         F::Error: CombineRejection<Rejection>,
     {
         type Output = Result<
-            One<Either<One<Preflight>, One<Either<One<Wrapped<F::Ok>>, F::Ok>>>>,
+            One<Either<One<Preflight>, One<Either<One<Wrapped<F::Extract>>, F::Extract>>>>,
             <F::Error as CombineRejection<Rejection>>::One,
         >;
 
         fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-            let pin = self.project();
-            match ready!(pin.inner.try_poll(cx)) {
+            let mut pin = self.project();
+            if let Some(pending) = pin.pending.as_mut() {
+                let allowed = ready!(pending.check.as_mut().poll(cx));
+                let Pending {
+                    validated,
+                    config,
+                    filter,
+                    ..
+                } = pin.pending.take().expect("pending was just polled");
+                if !allowed {
+                    let rejection = crate::reject::known(CorsForbidden {
+                        kind: Forbidden::OriginNotAllowed,
+                    });
+                    return Poll::Ready(Err(rejection.into()));
+                }
+                match validated {
+                    Validated::Preflight(origin, requested) => {
+                        let preflight = Preflight {
+                            config,
+                            origin,
+                            requested,
+                        };
+                        return Poll::Ready(Ok((Either::A((preflight,)),)));
+                    }
+                    Validated::Simple(origin) => {
+                        *pin.wrapped = Some((config, origin));
+                    }
+                    Validated::NotCors => (),
+                }
+                pin.inner.set(Some(filter.filter(Internal)));
+            }
+
+            let inner = pin
+                .inner
+                .as_pin_mut()
+                .expect("WrappedFuture polled after completion");
+            match ready!(inner.try_poll(cx)) {
                 Ok(inner) => {
                     let item = if let Some((config, origin)) = pin.wrapped.take() {
                         (Either::A((Wrapped {
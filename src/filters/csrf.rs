@@ -0,0 +1,313 @@
+//! CSRF protection
+//!
+//! The [`Csrf`] wrapper issues a random token to each client, and rejects
+//! state-changing requests (anything but `GET`, `HEAD`, `OPTIONS` and
+//! `TRACE`) that don't send it back in a header. Since other sites can't
+//! read the token, they can't forge such requests.
+//!
+//! The token is kept either in a cookie of its own (the "double submit
+//! cookie" pattern), or in the [session](crate::session) of the request
+//! (the "synchronizer token" pattern).
+//!
+//! Rejections have the status `403 Forbidden`, and can be recognized with
+//! `rejection.find::<InvalidCsrfToken>()`.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::csrf::{Csrf, CsrfToken};
+//! use warp::Filter;
+//!
+//! let form = warp::get()
+//!     .and(warp::csrf::token())
+//!     .map(|token: CsrfToken| format!("<meta name=\"csrf-token\" content=\"{}\">", token));
+//!
+//! // Requires an `x-csrf-token` header matching the token.
+//! let submit = warp::post().map(warp::reply);
+//!
+//! let route = form.or(submit).with(Csrf::double_submit());
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use headers::{Cookie, HeaderMapExt};
+use http::header::{HeaderName, HeaderValue, SET_COOKIE};
+use http::Method;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::filters::cookie::SameSite;
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+/// Extract the [`CsrfToken`] of the request.
+///
+/// The route must be wrapped with [`Csrf`], otherwise this rejects with a
+/// `MissingExtension`.
+pub fn token() -> impl Filter<Extract = (CsrfToken,), Error = Rejection> + Copy {
+    crate::ext::get::<CsrfToken>()
+}
+
+/// The CSRF token of a request, to be sent back with state-changing
+/// requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    /// The token, as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn generate() -> CsrfToken {
+        let bytes: [u8; 32] = rand::random();
+        CsrfToken(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD))
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A wrapper that issues and validates CSRF tokens.
+///
+/// Applied to routes with [`Filter::with`].
+#[derive(Clone, Debug)]
+pub struct Csrf {
+    header: HeaderName,
+    storage: Storage,
+}
+
+#[derive(Clone, Debug)]
+enum Storage {
+    Cookie {
+        name: String,
+        path: String,
+        secure: bool,
+    },
+    #[cfg(feature = "session")]
+    Session { key: String },
+}
+
+impl Csrf {
+    /// Keep the token in a `csrf_token` cookie.
+    ///
+    /// The cookie is `SameSite=Strict`, and readable by scripts so they can
+    /// copy it into the header.
+    pub fn double_submit() -> Csrf {
+        Csrf {
+            header: HeaderName::from_static("x-csrf-token"),
+            storage: Storage::Cookie {
+                name: "csrf_token".to_owned(),
+                path: "/".to_owned(),
+                secure: false,
+            },
+        }
+    }
+
+    /// Keep the token in the session, under the `csrf_token` key.
+    ///
+    /// The route must also be wrapped with
+    /// [`Sessions`](crate::session::Sessions), outside of the `Csrf`
+    /// wrapper.
+    #[cfg(feature = "session")]
+    pub fn session() -> Csrf {
+        Csrf {
+            header: HeaderName::from_static("x-csrf-token"),
+            storage: Storage::Session {
+                key: "csrf_token".to_owned(),
+            },
+        }
+    }
+
+    /// Set the header that must contain the token, `x-csrf-token` by
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `name` is not a legal header name.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("illegal header name: {:?}", name));
+        self
+    }
+
+    /// Set the name of the cookie, or session key, holding the token.
+    pub fn name(mut self, name: &str) -> Self {
+        match self.storage {
+            Storage::Cookie {
+                name: ref mut n, ..
+            } => *n = name.to_owned(),
+            #[cfg(feature = "session")]
+            Storage::Session { ref mut key } => *key = name.to_owned(),
+        }
+        self
+    }
+
+    /// Set the `Path` of the token cookie.
+    ///
+    /// This has no effect when the token is kept in the session.
+    pub fn path(mut self, path: &str) -> Self {
+        if let Storage::Cookie {
+            path: ref mut p, ..
+        } = self.storage
+        {
+            *p = path.to_owned();
+        }
+        self
+    }
+
+    /// Set whether the token cookie is only sent over HTTPS.
+    ///
+    /// This has no effect when the token is kept in the session.
+    pub fn secure(mut self, secure: bool) -> Self {
+        if let Storage::Cookie {
+            secure: ref mut s, ..
+        } = self.storage
+        {
+            *s = secure;
+        }
+        self
+    }
+
+    // Find the current token, or issue one. Returns whether it is new.
+    fn current(&self, route: &route::Route) -> Result<(CsrfToken, bool), Rejection> {
+        match self.storage {
+            Storage::Cookie { ref name, .. } => {
+                let existing = route
+                    .headers()
+                    .typed_get::<Cookie>()
+                    .and_then(|cookie| cookie.get(name).map(|value| CsrfToken(value.to_owned())));
+                Ok(match existing {
+                    Some(token) => (token, false),
+                    None => (CsrfToken::generate(), true),
+                })
+            }
+            #[cfg(feature = "session")]
+            Storage::Session { ref key } => {
+                let session = route
+                    .extensions()
+                    .get::<crate::session::Session>()
+                    .ok_or_else(crate::ext::missing_extension)?;
+                if let Some(token) = session.get::<String>(key) {
+                    return Ok((CsrfToken(token), false));
+                }
+                let token = CsrfToken::generate();
+                session
+                    .insert(key, token.as_str())
+                    .expect("a string can always be serialized");
+                Ok((token, true))
+            }
+        }
+    }
+
+    fn set_cookie(&self, token: &CsrfToken) -> Option<HeaderValue> {
+        match self.storage {
+            Storage::Cookie {
+                ref name,
+                ref path,
+                secure,
+            } => crate::reply::set_cookie(name, token.as_str())
+                .path(path)
+                .same_site(SameSite::Strict)
+                .secure(secure)
+                .to_header_value()
+                .ok(),
+            #[cfg(feature = "session")]
+            Storage::Session { .. } => None,
+        }
+    }
+}
+
+impl<F> WrapSealed<F> for Csrf
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithCsrf<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithCsrf {
+            csrf: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`Csrf`].
+#[derive(Clone, Debug)]
+pub struct WithCsrf<F> {
+    csrf: Csrf,
+    filter: F,
+}
+
+impl<F> FilterBase for WithCsrf<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let checked = route::with(|route| {
+            let (token, issued) = self.csrf.current(route)?;
+            if !is_safe(route.method()) {
+                let valid = !issued
+                    && route
+                        .headers()
+                        .get(&self.csrf.header)
+                        .map(|value| constant_time_eq(value.as_bytes(), token.as_str().as_bytes()))
+                        .unwrap_or(false);
+                if !valid {
+                    log::debug!("csrf token missing or invalid");
+                    return Err(reject::known(InvalidCsrfToken { _p: () }));
+                }
+            }
+            route.extensions_mut().insert(token.clone());
+            Ok((token, issued))
+        });
+        let csrf = self.csrf.clone();
+        let filter = self.filter.clone();
+
+        Box::pin(async move {
+            let (token, issued) = checked?;
+            let reply = filter.filter(Internal).await.map_err(Into::into)?;
+            let mut res = reply.into_response();
+            if issued {
+                if let Some(cookie) = csrf.set_cookie(&token) {
+                    res.headers_mut().append(SET_COOKIE, cookie);
+                }
+            }
+            Ok((res,))
+        })
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+unit_error! {
+    /// An error used to reject requests without a valid CSRF token.
+    pub InvalidCsrfToken: "Missing or invalid CSRF token"
+}
@@ -0,0 +1,331 @@
+//! Deadline Filters
+//!
+//! Services calling each other can share a time budget for a request: the
+//! caller sends how long it is willing to wait in a header, and the callee
+//! stops working on the request once that time is up, passing what is left
+//! of it to the services it calls in turn.
+//!
+//! The [`propagate`] wrapper reads that header, enforces it as a timeout of
+//! the wrapped filter, and makes the [`Deadline`] available to handlers with
+//! [`deadline`](deadline()), so they can pass it on, or watch its
+//! [`CancellationToken`] to stop work running in the background.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use warp::deadline::Deadline;
+//! use warp::Filter;
+//!
+//! let route = warp::path("search")
+//!     .and(warp::deadline::deadline())
+//!     .map(|deadline: Deadline| {
+//!         format!("{}ms left", deadline.remaining().as_millis())
+//!     })
+//!     .with(warp::deadline::propagate().max_timeout(Duration::from_secs(10)));
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future;
+use http::header::{HeaderName, HeaderValue};
+use http::StatusCode;
+use tokio::sync::watch;
+
+use crate::filter::{filter_fn_one, Filter, FilterBase, Internal, WrapSealed};
+use crate::filters::ext::missing_extension;
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route;
+
+/// Create a wrapper enforcing the deadline of requests, read from their
+/// `x-request-timeout` header.
+///
+/// The timeout is a number of milliseconds, or a value with a unit in the
+/// style of the `grpc-timeout` header, such as `250m` or `3S`. If the
+/// wrapped filter hasn't replied once the deadline is reached, its
+/// [`CancellationToken`] is cancelled, and the request is replied to with
+/// `504 Gateway Timeout`.
+///
+/// Requests without a timeout, or with an invalid one, get no deadline,
+/// unless a [`default_timeout`](Propagate::default_timeout) is set.
+pub fn propagate() -> Propagate {
+    Propagate {
+        header: HeaderName::from_static("x-request-timeout"),
+        default_timeout: None,
+        max_timeout: None,
+    }
+}
+
+/// Creates a `Filter` extracting the [`Deadline`] of the request.
+///
+/// If the request has no deadline, this rejects with a `MissingExtension`.
+pub fn deadline() -> impl Filter<Extract = (Deadline,), Error = Rejection> + Copy {
+    filter_fn_one(|route| {
+        let deadline = route
+            .extensions()
+            .get::<Deadline>()
+            .cloned()
+            .ok_or_else(missing_extension);
+        future::ready(deadline)
+    })
+}
+
+/// Creates a `Filter` extracting the [`Deadline`] of the request, if it has
+/// one.
+pub fn optional() -> impl Filter<Extract = (Option<Deadline>,), Error = Infallible> + Copy {
+    filter_fn_one(|route| future::ok(route.extensions().get::<Deadline>().cloned()))
+}
+
+/// Wrapper enforcing deadlines, see [`propagate`].
+#[derive(Clone, Debug)]
+pub struct Propagate {
+    header: HeaderName,
+    default_timeout: Option<Duration>,
+    max_timeout: Option<Duration>,
+}
+
+impl Propagate {
+    /// Read the timeout from the header `name`, instead of
+    /// `x-request-timeout`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `name` isn't a valid header name.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("illegal header name: {:?}", name));
+        self
+    }
+
+    /// Set the timeout of requests without a timeout header.
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Bound the timeouts requested by clients, so they can't hold on to
+    /// resources longer than `timeout`.
+    pub fn max_timeout(mut self, timeout: Duration) -> Self {
+        self.max_timeout = Some(timeout);
+        self
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        let requested = route::with(|route| {
+            let value = route.headers().get(&self.header)?;
+            let timeout = value.to_str().ok().and_then(parse_timeout);
+            if timeout.is_none() {
+                log::debug!("invalid {} header: {:?}", self.header, value);
+            }
+            timeout
+        });
+        let timeout = requested.or(self.default_timeout)?;
+        match self.max_timeout {
+            Some(max) => Some(timeout.min(max)),
+            None => Some(timeout),
+        }
+    }
+}
+
+impl<F> WrapSealed<F> for Propagate
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithDeadline<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithDeadline {
+            filter,
+            propagate: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`propagate`].
+#[derive(Clone, Debug)]
+pub struct WithDeadline<F> {
+    filter: F,
+    propagate: Propagate,
+}
+
+impl<F> FilterBase for WithDeadline<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let timeout = match self.propagate.timeout() {
+            Some(timeout) => timeout,
+            None => {
+                let fut = self.filter.filter(Internal);
+                return Box::pin(async move {
+                    match fut.await {
+                        Ok(reply) => Ok((reply.into_response(),)),
+                        Err(err) => Err(err.into()),
+                    }
+                });
+            }
+        };
+        let deadline = Deadline::new(Instant::now() + timeout);
+        route::with(|route| route.extensions_mut().insert(deadline.clone()));
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let at = tokio::time::Instant::from_std(deadline.instant());
+            match tokio::time::timeout_at(at, fut).await {
+                Ok(Ok(reply)) => Ok((reply.into_response(),)),
+                Ok(Err(err)) => Err(err.into()),
+                Err(_) => {
+                    log::debug!("request deadline of {:?} exceeded", timeout);
+                    deadline.token().cancel();
+                    let mut res = Response::default();
+                    *res.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+                    Ok((res,))
+                }
+            }
+        })
+    }
+}
+
+// Parses a number of milliseconds, or a `grpc-timeout` value: at most 8
+// digits followed by a unit.
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(millis) = value.parse::<u64>() {
+        return Some(Duration::from_millis(millis));
+    }
+    let unit = value.chars().last()?;
+    let amount = &value[..value.len() - unit.len_utf8()];
+    if amount.is_empty() || amount.len() > 8 || !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount = amount.parse::<u64>().ok()?;
+    let timeout = match unit {
+        'H' => Duration::from_secs(amount * 60 * 60),
+        'M' => Duration::from_secs(amount * 60),
+        'S' => Duration::from_secs(amount),
+        'm' => Duration::from_millis(amount),
+        'u' => Duration::from_micros(amount),
+        'n' => Duration::from_nanos(amount),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+/// The point in time by which a request should be replied to.
+#[derive(Clone, Debug)]
+pub struct Deadline {
+    at: Instant,
+    token: CancellationToken,
+}
+
+impl Deadline {
+    /// Create a `Deadline` at the instant `at`.
+    pub fn new(at: Instant) -> Deadline {
+        Deadline {
+            at,
+            token: CancellationToken::new(),
+        }
+    }
+
+    /// The instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// The time left until the deadline, zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.at
+            .checked_duration_since(Instant::now())
+            .unwrap_or_default()
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Duration::from_secs(0)
+    }
+
+    /// The time left, as the value of a timeout header in milliseconds, to
+    /// pass the deadline on to other services.
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from(self.remaining().as_millis() as u64)
+    }
+
+    /// A token cancelled when the request exceeds its deadline.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+/// A token signaling that work on a request should stop.
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+struct TokenInner {
+    tx: watch::Sender<bool>,
+    rx: watch::Receiver<bool>,
+}
+
+impl CancellationToken {
+    /// Create a token that isn't cancelled.
+    pub fn new() -> CancellationToken {
+        let (tx, rx) = watch::channel(false);
+        CancellationToken {
+            inner: Arc::new(TokenInner { tx, rx }),
+        }
+    }
+
+    /// Cancel the token, and its clones.
+    pub fn cancel(&self) {
+        let _ = self.inner.tx.broadcast(true);
+    }
+
+    /// Whether the token is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.rx.borrow()
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        let mut rx = self.inner.rx.clone();
+        loop {
+            if *rx.borrow() {
+                return;
+            }
+            if rx.recv().await.is_none() {
+                return future::pending().await;
+            }
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
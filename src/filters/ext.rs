@@ -1,11 +1,80 @@
 //! Request Extensions
+//!
+//! Extensions are values of any type stored with a request, one per type.
+//! Filters and wraps can [`insert`], [`set`] or [`provide`] them, so filters
+//! running later can [`get`] them, without threading them through every
+//! `and()`.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let auth = warp::auth::bearer()
+//!     .map(|token: String| User(token));
+//!
+//! let route = warp::ext::provide(auth)
+//!     .and(warp::path("me"))
+//!     .and(warp::ext::get::<User>())
+//!     .map(|user: User| user.0);
+//! ```
 
 use std::convert::Infallible;
 
 use futures::future;
 
-use crate::filter::{filter_fn_one, Filter};
+use crate::filter::{filter_fn, filter_fn_one, Filter};
 use crate::reject::{self, Rejection};
+use crate::route;
+
+/// Create a `Filter` that inserts a clone of `value` into the extensions of
+/// every request.
+///
+/// This replaces an extension of the same type set before.
+pub fn insert<T: Clone + Send + Sync + 'static>(
+    value: T,
+) -> impl Filter<Extract = (), Error = Infallible> + Clone {
+    filter_fn(move |route| {
+        route.extensions_mut().insert(value.clone());
+        future::ok(())
+    })
+}
+
+/// Create a `Filter` that inserts the value extracted by `filter` into the
+/// extensions of the request, instead of extracting it.
+///
+/// Requests rejected by `filter` are rejected the same way.
+pub fn provide<F, T>(filter: F) -> impl Filter<Extract = (), Error = F::Error> + Clone
+where
+    F: Filter<Extract = (T,)> + Clone,
+    T: Send + Sync + 'static,
+{
+    filter.map(set::<T>).untuple_one()
+}
+
+/// Insert `value` into the extensions of the request being filtered.
+///
+/// This can be called from handlers, such as the functions given to
+/// [`Filter::map`] or [`Filter::and_then`], and from wraps.
+///
+/// This replaces an extension of the same type set before.
+///
+/// # Panics
+///
+/// This function panics if it is called outside of the filtering of a
+/// request.
+pub fn set<T: Send + Sync + 'static>(value: T) {
+    assert!(
+        route::is_set(),
+        "illegal ext::set() call outside of the filtering of a request"
+    );
+    route::with(|route| {
+        route.extensions_mut().insert(value);
+    });
+}
 
 /// Get a previously set extension of the current route.
 ///
@@ -17,7 +86,7 @@ pub fn get<T: Clone + Send + Sync + 'static>(
             .extensions()
             .get::<T>()
             .cloned()
-            .ok_or_else(|| reject::known(MissingExtension { _p: () }));
+            .ok_or_else(missing_extension);
         future::ready(route)
     })
 }
@@ -30,6 +99,32 @@ pub fn optional<T: Clone + Send + Sync + 'static>(
     filter_fn_one(|route| future::ok(route.extensions().get::<T>().cloned()))
 }
 
+/// Get a per-connection value, provided with
+/// [`warp::service_with_info`](crate::service_with_info).
+///
+/// If no value of this type was provided for the connection, this rejects
+/// with a `MissingExtension`.
+pub fn connection<T: Clone + Send + Sync + 'static>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Copy {
+    filter_fn_one(|route| {
+        let route = route
+            .extensions()
+            .get::<Connection<T>>()
+            .map(|conn| conn.0.clone())
+            .ok_or_else(missing_extension);
+        future::ready(route)
+    })
+}
+
+pub(crate) fn missing_extension() -> Rejection {
+    reject::known(MissingExtension { _p: () })
+}
+
+// Wraps per-connection values, so they don't conflict with other extensions
+// of the same type.
+#[derive(Clone, Debug)]
+pub(crate) struct Connection<T>(pub(crate) T);
+
 unit_error! {
     /// An error used to reject if `get` cannot find the extension.
     pub MissingExtension: "Missing request extension"
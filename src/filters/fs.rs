@@ -1,30 +1,35 @@
 //! File System Filters
 
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::fs::Metadata;
 use std::future::Future;
+use std::hash::Hasher;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use bytes::{Bytes, BytesMut};
 use futures::future::Either;
-use futures::{future, ready, stream, FutureExt, Stream, StreamExt, TryFutureExt};
+use futures::{future, ready, stream, FutureExt, Stream, StreamExt};
 use headers::{
-    AcceptRanges, ContentLength, ContentRange, ContentType, HeaderMapExt, IfModifiedSince, IfRange,
-    IfUnmodifiedSince, LastModified, Range,
+    AcceptRanges, ContentLength, ContentRange, ContentType, ETag, HeaderMapExt, IfModifiedSince,
+    IfNoneMatch, IfRange, IfUnmodifiedSince, LastModified, Range,
 };
-use http::StatusCode;
+use http::header::{HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, VARY};
+use http::{Method, StatusCode};
 use hyper::Body;
 use mime_guess;
 use tokio::fs::File as TkFile;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use urlencoding::decode;
 
-use crate::filter::{Filter, FilterClone, One};
+use crate::filter::{Filter, FilterBase, Internal, One};
+use crate::filters::reply::{strong_etag, Fnv1a};
 use crate::reject::{self, Rejection};
 use crate::reply::{Reply, Response};
 
@@ -44,15 +49,11 @@ use crate::reply::{Reply, Response};
 /// // Always serves this file from the file system.
 /// let route = warp::fs::file("/www/static/app.js");
 /// ```
-pub fn file(path: impl Into<PathBuf>) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
-    let path = Arc::new(path.into());
-    crate::any()
-        .map(move || {
-            log::trace!("file: {:?}", path);
-            ArcPath(path.clone())
-        })
-        .and(conditionals())
-        .and_then(|path, conditionals| file_reply(path, conditionals))
+pub fn file(path: impl Into<PathBuf>) -> ServeFile {
+    ServeFile {
+        path: Arc::new(path.into()),
+        config: Arc::new(Config::default()),
+    }
 }
 
 /// Creates a `Filter` that serves a directory at the base `path` joined
@@ -77,32 +78,677 @@ pub fn file(path: impl Into<PathBuf>) -> impl FilterClone<Extract = One<File>, E
 /// // - `GET /static/app.js` would serve the file `/www/static/app.js`
 /// // - `GET /static/css/app.css` would serve the file `/www/static/css/app.css`
 /// ```
-pub fn dir(path: impl Into<PathBuf>) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
-    let base = Arc::new(path.into());
-    crate::get()
-        .and(path_from_tail(base))
-        .and(conditionals())
-        .and_then(file_reply)
+pub fn dir(path: impl Into<PathBuf>) -> ServeDir {
+    ServeDir {
+        base: Arc::new(path.into()),
+        config: Arc::new(Config::default()),
+        fallback: None,
+        not_found: None,
+        cache_control: Arc::new(Vec::new()),
+        policy: Arc::default(),
+    }
+}
+
+type ServeFuture = Pin<Box<dyn Future<Output = Result<One<File>, Rejection>> + Send>>;
+
+/// A `Filter` serving a single file, created with [`file`].
+#[derive(Clone, Debug)]
+pub struct ServeFile {
+    path: Arc<PathBuf>,
+    config: Arc<Config>,
 }
 
-fn path_from_tail(
+impl FilterBase for ServeFile {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFuture;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let path = self.path.clone();
+        let config = self.config.clone();
+        let filter = crate::any()
+            .map(move || {
+                log::trace!("file: {:?}", path);
+                ArcPath(path.clone())
+            })
+            .and(conditionals())
+            .and_then(move |path, conditionals| {
+                file_reply(path, conditionals, config.clone(), None)
+            });
+        Box::pin(filter.filter(Internal))
+    }
+}
+
+/// A `Filter` serving the files of a directory, created with [`dir`].
+#[derive(Clone, Debug)]
+pub struct ServeDir {
     base: Arc<PathBuf>,
-) -> impl FilterClone<Extract = One<ArcPath>, Error = Rejection> {
-    crate::path::tail().and_then(move |tail: crate::path::Tail| {
-        future::ready(sanitize_path(base.as_ref(), tail.as_str())).and_then(|mut buf| async {
-            let is_dir = tokio::fs::metadata(buf.clone())
-                .await
-                .map(|m| m.is_dir())
-                .unwrap_or(false);
+    config: Arc<Config>,
+    fallback: Option<Arc<PathBuf>>,
+    not_found: Option<Arc<PathBuf>>,
+    cache_control: Arc<Vec<(Glob, HeaderValue)>>,
+    policy: Arc<Policy>,
+}
 
-            if is_dir {
-                log::debug!("dir: appending index.html to directory path");
-                buf.push("index.html");
+// Which files of a directory may be served.
+#[derive(Clone, Debug, Default)]
+struct Policy {
+    deny_escaping_symlinks: bool,
+    deny_dotfiles: bool,
+    extensions: Option<Vec<String>>,
+}
+
+impl Policy {
+    async fn check(&self, base: &Path, path: &Path) -> Result<(), Rejection> {
+        let relative = path.strip_prefix(base).unwrap_or(path);
+        if self.deny_dotfiles {
+            let dotfile = relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+            if dotfile {
+                log::debug!("dir: rejecting dotfile {:?}", relative);
+                return Err(reject::not_found());
             }
-            log::trace!("dir: {:?}", buf);
-            Ok(ArcPath(Arc::new(buf)))
-        })
-    })
+        }
+
+        if let Some(ref extensions) = self.extensions {
+            let allowed = match path.extension() {
+                Some(ext) => extensions
+                    .iter()
+                    .any(|allowed| ext.to_string_lossy().eq_ignore_ascii_case(allowed)),
+                None => false,
+            };
+            if !allowed {
+                log::debug!("dir: rejecting extension of {:?}", relative);
+                return Err(reject::not_found());
+            }
+        }
+
+        self.check_symlinks(base, path).await
+    }
+
+    // Checks the symlinks of a path only, such as of the precompressed
+    // variant of a file, whose extension is not the one of the file.
+    async fn check_symlinks(&self, base: &Path, path: &Path) -> Result<(), Rejection> {
+        if !self.deny_escaping_symlinks {
+            return Ok(());
+        }
+        // A missing file is left to be rejected when opening it.
+        let real = match tokio::fs::canonicalize(path).await {
+            Ok(real) => real,
+            Err(_) => return Ok(()),
+        };
+        let root = tokio::fs::canonicalize(base).await.map_err(|err| {
+            log::warn!("dir: failed to resolve base {:?}: {}", base, err);
+            reject::not_found()
+        })?;
+        if !real.starts_with(&root) {
+            let relative = path.strip_prefix(base).unwrap_or(path);
+            log::warn!(
+                "dir: rejecting symlink out of the directory: {:?}",
+                relative
+            );
+            return Err(reject::not_found());
+        }
+        Ok(())
+    }
+}
+
+impl ServeDir {
+    /// Serve the file at `path`, relative to the directory, for requests
+    /// that don't match any file.
+    ///
+    /// This is meant for single page applications, which handle their
+    /// routes client side: the entrypoint is served with a `200 OK` for
+    /// any path.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::fs::dir("/www/app").fallback("index.html");
+    /// ```
+    pub fn fallback(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fallback = Some(Arc::new(path.into()));
+        self
+    }
+
+    /// Serve the file at `path`, relative to the directory, with a
+    /// `404 Not Found` status for requests that don't match any file,
+    /// instead of rejecting them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::fs::dir("/www/static").not_found("404.html");
+    /// ```
+    pub fn not_found(mut self, path: impl Into<PathBuf>) -> Self {
+        self.not_found = Some(Arc::new(path.into()));
+        self
+    }
+
+    /// Send a `Cache-Control` header with the files matching `glob`.
+    ///
+    /// The glob is matched against the path of a file relative to the
+    /// directory, where `?` matches any character but `/`, `*` any sequence
+    /// of them, and `**` any number of directories. A glob without a `/` is
+    /// matched against the file name only.
+    ///
+    /// When several globs match a file, the first one added wins.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `value` is not a legal header value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::fs::dir("/www/static")
+    ///     .cache_control("index.html", "no-cache")
+    ///     .cache_control("assets/**", "public, max-age=31536000, immutable");
+    /// ```
+    pub fn cache_control(mut self, glob: &str, value: &str) -> Self {
+        let value = HeaderValue::from_str(value)
+            .unwrap_or_else(|_| panic!("illegal Cache-Control value: {:?}", value));
+        Arc::make_mut(&mut self.cache_control).push((Glob(glob.to_owned()), value));
+        self
+    }
+
+    /// Refuse to serve files through symbolic links pointing out of the
+    /// directory.
+    ///
+    /// Links are followed by default.
+    pub fn deny_escaping_symlinks(mut self) -> Self {
+        Arc::make_mut(&mut self.policy).deny_escaping_symlinks = true;
+        self
+    }
+
+    /// Refuse to serve files whose path has a component starting with a
+    /// `.`, such as `.env` or `.git/config`.
+    pub fn deny_dotfiles(mut self) -> Self {
+        Arc::make_mut(&mut self.policy).deny_dotfiles = true;
+        self
+    }
+
+    /// Only serve files with one of the `extensions`, ignoring case.
+    ///
+    /// The `index.html` of directories is only served if `html` is allowed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::fs::dir("/www/static")
+    ///     .deny_dotfiles()
+    ///     .allowed_extensions(&["html", "css", "js"]);
+    /// ```
+    pub fn allowed_extensions<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let extensions = extensions
+            .into_iter()
+            .map(|ext| ext.as_ref().trim_start_matches('.').to_owned())
+            .collect();
+        Arc::make_mut(&mut self.policy).extensions = Some(extensions);
+        self
+    }
+
+    async fn serve(
+        self,
+        tail: crate::path::Tail,
+        conditionals: Conditionals,
+    ) -> Result<File, Rejection> {
+        let method = conditionals.method.clone();
+        let result = match path_from_tail(self.base.clone(), tail.as_str()).await {
+            Ok(path) => match self.policy.check(&self.base, path.as_ref()).await {
+                Ok(()) => self.reply(path, conditionals).await,
+                Err(rej) => Err(rej),
+            },
+            Err(rej) => Err(rej),
+        };
+        let rej = match result {
+            Err(rej) if rej.is_not_found() => rej,
+            result => return result,
+        };
+
+        if let Some(ref fallback) = self.fallback {
+            log::debug!("dir: serving fallback {:?}", fallback);
+            let path = ArcPath(Arc::new(self.base.join(fallback.as_ref())));
+            return self.reply(path, Conditionals::plain(method)).await;
+        }
+        if let Some(ref not_found) = self.not_found {
+            log::debug!("dir: serving not found page {:?}", not_found);
+            let path = ArcPath(Arc::new(self.base.join(not_found.as_ref())));
+            let mut file = self.reply(path, Conditionals::plain(method)).await?;
+            *file.resp.status_mut() = StatusCode::NOT_FOUND;
+            return Ok(file);
+        }
+        Err(rej)
+    }
+
+    async fn reply(&self, path: ArcPath, conditionals: Conditionals) -> Result<File, Rejection> {
+        let cache_control = self.cache_control_for(path.as_ref());
+        let root = Some((self.base.as_path(), self.policy.as_ref()));
+        let mut file = file_reply(path, conditionals, self.config.clone(), root).await?;
+        if let Some(value) = cache_control {
+            file.resp.headers_mut().insert(CACHE_CONTROL, value);
+        }
+        Ok(file)
+    }
+
+    // The `Cache-Control` of the first rule matching a file.
+    fn cache_control_for(&self, path: &Path) -> Option<HeaderValue> {
+        if self.cache_control.is_empty() {
+            return None;
+        }
+        let relative = path
+            .strip_prefix(self.base.as_ref())
+            .ok()?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.cache_control
+            .iter()
+            .find(|(glob, _)| glob.matches(&relative))
+            .map(|(_, value)| value.clone())
+    }
+}
+
+// A path pattern: `?` matches any character but `/`, `*` any sequence of
+// them, and `**` any sequence of characters. Patterns without a `/` are
+// matched against the file name only.
+#[derive(Clone, Debug)]
+struct Glob(String);
+
+impl Glob {
+    fn matches(&self, path: &str) -> bool {
+        let path = if self.0.contains('/') {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        glob_match(self.0.as_bytes(), path.as_bytes())
+    }
+}
+
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern {
+        [] => s.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // zero or more whole segments
+            glob_match(rest, s)
+                || s.iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == b'/' && glob_match(rest, &s[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=s.len()).any(|i| glob_match(rest, &s[i..])),
+        [b'*', rest @ ..] => {
+            let segment = s.iter().position(|&c| c == b'/').unwrap_or(s.len());
+            (0..=segment).any(|i| glob_match(rest, &s[i..]))
+        }
+        [b'?', rest @ ..] => match s {
+            [c, tail @ ..] if *c != b'/' => glob_match(rest, tail),
+            _ => false,
+        },
+        [p, rest @ ..] => match s {
+            [c, tail @ ..] if c == p => glob_match(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+// Options shared by `ServeFile` and `ServeDir`.
+macro_rules! config_methods {
+    ($($ty:ident),*) => {$(
+        impl $ty {
+            /// Set how the `ETag` of files is generated, [`ETagStrategy::Weak`]
+            /// by default.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// use warp::fs::ETagStrategy;
+            ///
+            /// let route = warp::fs::dir("/www/static").etag(ETagStrategy::Strong);
+            /// ```
+            pub fn etag(mut self, etag: ETagStrategy) -> Self {
+                Arc::make_mut(&mut self.config).etag = etag;
+                self
+            }
+
+            /// Serve `foo.js.br` in place of `foo.js` when it exists and the
+            /// client accepts brotli.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// let route = warp::fs::dir("/www/static")
+            ///     .precompressed_br()
+            ///     .precompressed_gzip();
+            /// ```
+            pub fn precompressed_br(self) -> Self {
+                self.precompressed(Precompressed::Br)
+            }
+
+            /// Serve `foo.js.zst` in place of `foo.js` when it exists and the
+            /// client accepts zstd.
+            pub fn precompressed_zstd(self) -> Self {
+                self.precompressed(Precompressed::Zstd)
+            }
+
+            /// Serve `foo.js.gz` in place of `foo.js` when it exists and the
+            /// client accepts gzip.
+            pub fn precompressed_gzip(self) -> Self {
+                self.precompressed(Precompressed::Gzip)
+            }
+
+            fn precompressed(mut self, encoding: Precompressed) -> Self {
+                let config = Arc::make_mut(&mut self.config);
+                if !config.precompressed.contains(&encoding) {
+                    config.precompressed.push(encoding);
+                    config.precompressed.sort();
+                }
+                self
+            }
+        }
+    )*};
+}
+
+config_methods!(ServeFile, ServeDir);
+
+impl FilterBase for ServeDir {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFuture;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let dir = self.clone();
+        let filter = crate::get()
+            .and(crate::path::tail())
+            .and(conditionals())
+            .and_then(move |tail, conditionals| dir.clone().serve(tail, conditionals));
+        Box::pin(filter.filter(Internal))
+    }
+}
+
+/// Creates a `Filter` that serves files embedded in the binary with
+/// [`include_dir`](https://docs.rs/include_dir/0.6), at the request path.
+///
+/// Like [`dir`](dir), it only matches `GET` requests, serves the
+/// `index.html` of directories, and guesses the `Content-Type` from the file
+/// extension. Files get a strong `ETag`, computed from their contents the
+/// first time they are served.
+///
+/// # Example
+///
+/// ```
+/// use include_dir::{include_dir, Dir};
+/// use warp::Filter;
+///
+/// // Relative to the `Cargo.toml` of the crate.
+/// static ASSETS: Dir = include_dir!("examples/dir");
+///
+/// let route = warp::path("static").and(warp::fs::embedded(&ASSETS));
+/// ```
+#[cfg(feature = "embedded")]
+pub fn embedded(dir: &'static include_dir::Dir<'static>) -> ServeEmbedded {
+    ServeEmbedded {
+        dir,
+        etags: Arc::default(),
+    }
+}
+
+/// A `Filter` serving embedded files, created with [`embedded`].
+#[cfg(feature = "embedded")]
+#[derive(Clone)]
+pub struct ServeEmbedded {
+    dir: &'static include_dir::Dir<'static>,
+    etags: Arc<Mutex<HashMap<&'static Path, ETag>>>,
+}
+
+#[cfg(feature = "embedded")]
+impl ServeEmbedded {
+    fn serve(&self, tail: &str, conditionals: Conditionals) -> Result<File, Rejection> {
+        let mut path = sanitize_path("", tail)?;
+        if path.as_os_str().is_empty() || self.dir.get_dir(&path).is_some() {
+            log::debug!("embedded: appending index.html to directory path");
+            path.push("index.html");
+        }
+        let file = self.dir.get_file(&path).ok_or_else(|| {
+            log::debug!("embedded: file not found: {:?}", path);
+            reject::not_found()
+        })?;
+        let contents = file.contents();
+        let len = contents.len() as u64;
+        let etag = self.etag(&file);
+
+        let resp = match conditionals.check(None, etag.as_ref()) {
+            Cond::NoBody(mut resp) => {
+                if resp.status() == StatusCode::NOT_MODIFIED {
+                    if let Some(etag) = etag {
+                        resp.headers_mut().typed_insert(etag);
+                    }
+                }
+                resp
+            }
+            Cond::WithBody(range) => match bytes_range(range, len) {
+                Ok((start, end)) => {
+                    let body = Bytes::from_static(&contents[start as usize..end as usize]);
+                    let mut resp = Response::new(Body::from(body));
+                    if end - start != len {
+                        *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                        resp.headers_mut().typed_insert(
+                            ContentRange::bytes(start..end, len).expect("valid ContentRange"),
+                        );
+                    }
+
+                    let mime = mime_guess::from_path(file.path()).first_or_octet_stream();
+
+                    resp.headers_mut().typed_insert(ContentLength(end - start));
+                    resp.headers_mut().typed_insert(ContentType::from(mime));
+                    resp.headers_mut().typed_insert(AcceptRanges::bytes());
+                    if let Some(etag) = etag {
+                        resp.headers_mut().typed_insert(etag);
+                    }
+                    resp
+                }
+                Err(BadRange) => {
+                    let mut resp = Response::new(Body::empty());
+                    *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+                    resp.headers_mut()
+                        .typed_insert(ContentRange::unsatisfied_bytes(len));
+                    resp
+                }
+            },
+        };
+
+        Ok(File { resp })
+    }
+
+    fn etag(&self, file: &include_dir::File<'static>) -> Option<ETag> {
+        let mut etags = self.etags.lock().unwrap();
+        if let Some(etag) = etags.get(file.path()) {
+            return Some(etag.clone());
+        }
+        let mut hasher = Fnv1a::default();
+        hasher.write(file.contents());
+        let etag = strong_etag(hasher.finish(), file.contents().len() as u64);
+        etags.insert(file.path(), etag.clone());
+        Some(etag)
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl std::fmt::Debug for ServeEmbedded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ServeEmbedded")
+            .field("dir", &self.dir.path())
+            .finish()
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl FilterBase for ServeEmbedded {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFuture;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let embedded = self.clone();
+        let filter = crate::get()
+            .and(crate::path::tail())
+            .and(conditionals())
+            .and_then(move |tail: crate::path::Tail, conditionals| {
+                future::ready(embedded.serve(tail.as_str(), conditionals))
+            });
+        Box::pin(filter.filter(Internal))
+    }
+}
+
+/// How the `ETag` of a file is generated.
+///
+/// `If-None-Match` requests matching the `ETag` get a `304 Not Modified`
+/// response.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ETagStrategy {
+    /// A weak `ETag` made of the modification time and size of the file.
+    #[default]
+    Weak,
+    /// A strong `ETag` made of a hash of the contents of the file.
+    ///
+    /// Hashes are cached until the modification time or size of a file
+    /// changes, but computing one means reading the whole file.
+    Strong,
+    /// No `ETag`.
+    Disabled,
+}
+
+// Strong `ETag`s, with the modification time and size they were hashed at.
+type HashCache = Arc<Mutex<HashMap<PathBuf, (SystemTime, u64, ETag)>>>;
+
+#[derive(Clone, Debug, Default)]
+struct Config {
+    etag: ETagStrategy,
+    hashes: HashCache,
+    // Sorted in order of preference.
+    precompressed: Vec<Precompressed>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Precompressed {
+    Br,
+    Zstd,
+    Gzip,
+}
+
+impl Precompressed {
+    fn coding(self) -> &'static str {
+        match self {
+            Precompressed::Br => "br",
+            Precompressed::Zstd => "zstd",
+            Precompressed::Gzip => "gzip",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Precompressed::Br => "br",
+            Precompressed::Zstd => "zst",
+            Precompressed::Gzip => "gz",
+        }
+    }
+
+    // Whether an `Accept-Encoding` header accepts this encoding.
+    fn is_accepted(self, accept: &str) -> bool {
+        let mut wildcard = false;
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let coding = parts.next().unwrap_or("").trim();
+            let refused = parts.any(|param| {
+                let param = param.trim();
+                let mut kv = param.splitn(2, '=');
+                let name = kv.next().unwrap_or("").trim();
+                let value = kv.next().unwrap_or("").trim();
+                name.eq_ignore_ascii_case("q") && value.parse::<f32>().ok() == Some(0.0)
+            });
+            if coding.eq_ignore_ascii_case(self.coding()) {
+                return !refused;
+            }
+            if coding == "*" {
+                wildcard = !refused;
+            }
+        }
+        wildcard
+    }
+}
+
+impl Config {
+    async fn etag(&self, path: &Path, meta: &Metadata) -> Option<ETag> {
+        let modified = meta.modified().ok()?;
+        match self.etag {
+            ETagStrategy::Disabled => None,
+            ETagStrategy::Weak => {
+                let mtime = modified.duration_since(UNIX_EPOCH).ok()?;
+                format!(
+                    "W/\"{:x}.{:x}-{:x}\"",
+                    mtime.as_secs(),
+                    mtime.subsec_nanos(),
+                    meta.len()
+                )
+                .parse()
+                .ok()
+            }
+            ETagStrategy::Strong => {
+                if let Some((time, len, etag)) = self.hashes.lock().unwrap().get(path) {
+                    if *time == modified && *len == meta.len() {
+                        return Some(etag.clone());
+                    }
+                }
+                let hash = match hash_file(path).await {
+                    Ok(hash) => hash,
+                    Err(err) => {
+                        log::debug!("file hash error: {}", err);
+                        return None;
+                    }
+                };
+                let etag = strong_etag(hash, meta.len());
+                self.hashes
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_owned(), (modified, meta.len(), etag.clone()));
+                Some(etag)
+            }
+        }
+    }
+}
+
+async fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = TkFile::open(path).await?;
+    let mut hasher = Fnv1a::default();
+    let mut buf = vec![0; DEFAULT_READ_BUF_SIZE];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buf[..n]);
+    }
+}
+
+async fn path_from_tail(base: Arc<PathBuf>, tail: &str) -> Result<ArcPath, Rejection> {
+    let mut buf = sanitize_path(base.as_ref(), tail)?;
+    let is_dir = tokio::fs::metadata(buf.clone())
+        .await
+        .map(|m| m.is_dir())
+        .unwrap_or(false);
+
+    if is_dir {
+        log::debug!("dir: appending index.html to directory path");
+        buf.push("index.html");
+    }
+    log::trace!("dir: {:?}", buf);
+    Ok(ArcPath(Arc::new(buf)))
 }
 
 fn sanitize_path(base: impl AsRef<Path>, tail: &str) -> Result<PathBuf, Rejection> {
@@ -132,8 +778,11 @@ fn sanitize_path(base: impl AsRef<Path>, tail: &str) -> Result<PathBuf, Rejectio
 
 #[derive(Debug)]
 struct Conditionals {
+    method: Method,
+    accept_encoding: Option<HeaderValue>,
     if_modified_since: Option<IfModifiedSince>,
     if_unmodified_since: Option<IfUnmodifiedSince>,
+    if_none_match: Option<IfNoneMatch>,
     if_range: Option<IfRange>,
     range: Option<Range>,
 }
@@ -144,7 +793,7 @@ enum Cond {
 }
 
 impl Conditionals {
-    fn check(self, last_modified: Option<LastModified>) -> Cond {
+    fn check(self, last_modified: Option<LastModified>, etag: Option<&ETag>) -> Cond {
         if let Some(since) = self.if_unmodified_since {
             let precondition = last_modified
                 .map(|time| since.precondition_passes(time.into()))
@@ -163,7 +812,25 @@ impl Conditionals {
             }
         }
 
-        if let Some(since) = self.if_modified_since {
+        if let Some(if_none_match) = self.if_none_match {
+            let passes = etag
+                .map(|etag| if_none_match.precondition_passes(etag))
+                .unwrap_or(true);
+            log::trace!(
+                "if-none-match? {:?} vs {:?} = {}",
+                if_none_match,
+                etag,
+                passes
+            );
+            if !passes {
+                let mut res = Response::new(Body::empty());
+                *res.status_mut() = match self.method {
+                    Method::GET | Method::HEAD => StatusCode::NOT_MODIFIED,
+                    _ => StatusCode::PRECONDITION_FAILED,
+                };
+                return Cond::NoBody(res);
+            }
+        } else if let Some(since) = self.if_modified_since {
             log::trace!(
                 "if-modified-since? header = {:?}, file = {:?}",
                 since,
@@ -182,7 +849,7 @@ impl Conditionals {
 
         if let Some(if_range) = self.if_range {
             log::trace!("if-range? {:?} vs {:?}", if_range, last_modified);
-            let can_range = !if_range.is_modified(None, last_modified.as_ref());
+            let can_range = !if_range.is_modified(etag, last_modified.as_ref());
 
             if !can_range {
                 return Cond::WithBody(None);
@@ -191,19 +858,46 @@ impl Conditionals {
 
         Cond::WithBody(self.range)
     }
+
+    // No conditions, for serving a file other than the one requested.
+    fn plain(method: Method) -> Conditionals {
+        Conditionals {
+            method,
+            accept_encoding: None,
+            if_modified_since: None,
+            if_unmodified_since: None,
+            if_none_match: None,
+            if_range: None,
+            range: None,
+        }
+    }
 }
 
 fn conditionals() -> impl Filter<Extract = One<Conditionals>, Error = Infallible> + Copy {
-    crate::header::optional2()
+    crate::method()
+        .and(crate::filters::header::value_optional("accept-encoding"))
+        .and(crate::header::optional2())
+        .and(crate::header::optional2())
         .and(crate::header::optional2())
         .and(crate::header::optional2())
         .and(crate::header::optional2())
         .map(
-            |if_modified_since, if_unmodified_since, if_range, range| Conditionals {
-                if_modified_since,
-                if_unmodified_since,
-                if_range,
-                range,
+            |method,
+             accept_encoding,
+             if_modified_since,
+             if_unmodified_since,
+             if_none_match,
+             if_range,
+             range| {
+                Conditionals {
+                    method,
+                    accept_encoding,
+                    if_modified_since,
+                    if_unmodified_since,
+                    if_none_match,
+                    if_range,
+                    range,
+                }
             },
         )
 }
@@ -230,12 +924,21 @@ impl Reply for File {
     }
 }
 
-fn file_reply(
+// The directory of a file served by `ServeDir`, and its policy.
+type Root<'a> = Option<(&'a Path, &'a Policy)>;
+
+async fn file_reply(
     path: ArcPath,
     conditionals: Conditionals,
-) -> impl Future<Output = Result<File, Rejection>> + Send {
-    TkFile::open(path.clone()).then(move |res| match res {
-        Ok(f) => Either::Left(file_conditional(f, path, conditionals)),
+    config: Arc<Config>,
+    root: Root<'_>,
+) -> Result<File, Rejection> {
+    if let Some((f, encoding)) = open_precompressed(&path, &conditionals, &config, root).await {
+        return file_conditional(f, path, Some(encoding), conditionals, config).await;
+    }
+
+    match TkFile::open(path.clone()).await {
+        Ok(f) => file_conditional(f, path, None, conditionals, config).await,
         Err(err) => {
             let rej = match err.kind() {
                 io::ErrorKind::NotFound => {
@@ -255,9 +958,37 @@ fn file_reply(
                     reject::known(FileOpenError { _p: () })
                 }
             };
-            Either::Right(future::err(rej))
+            Err(rej)
         }
-    })
+    }
+}
+
+// Open the preferred precompressed variant of a file that the client accepts.
+async fn open_precompressed(
+    path: &ArcPath,
+    conditionals: &Conditionals,
+    config: &Config,
+    root: Root<'_>,
+) -> Option<(TkFile, Precompressed)> {
+    let accept = conditionals.accept_encoding.as_ref()?.to_str().ok()?;
+    for &encoding in &config.precompressed {
+        if !encoding.is_accepted(accept) {
+            continue;
+        }
+        let mut encoded = path.as_ref().as_os_str().to_owned();
+        encoded.push(".");
+        encoded.push(encoding.extension());
+        if let Some((base, policy)) = root {
+            if policy.check_symlinks(base, encoded.as_ref()).await.is_err() {
+                continue;
+            }
+        }
+        if let Ok(f) = TkFile::open(&encoded).await {
+            log::trace!("file: serving precompressed {:?}", encoded);
+            return Some((f, encoding));
+        }
+    }
+    None
 }
 
 async fn file_metadata(f: TkFile) -> Result<(TkFile, Metadata), Rejection> {
@@ -270,61 +1001,95 @@ async fn file_metadata(f: TkFile) -> Result<(TkFile, Metadata), Rejection> {
     }
 }
 
-fn file_conditional(
+async fn file_conditional(
     f: TkFile,
     path: ArcPath,
+    encoding: Option<Precompressed>,
     conditionals: Conditionals,
-) -> impl Future<Output = Result<File, Rejection>> + Send {
-    file_metadata(f).map_ok(move |(file, meta)| {
-        let mut len = meta.len();
-        let modified = meta.modified().ok().map(LastModified::from);
-
-        let resp = match conditionals.check(modified) {
-            Cond::NoBody(resp) => resp,
-            Cond::WithBody(range) => {
-                bytes_range(range, len)
-                    .map(|(start, end)| {
-                        let sub_len = end - start;
-                        let buf_size = optimal_buf_size(&meta);
-                        let stream = file_stream(file, buf_size, (start, end));
-                        let body = Body::wrap_stream(stream);
-
-                        let mut resp = Response::new(body);
-
-                        if sub_len != len {
-                            *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
-                            resp.headers_mut().typed_insert(
-                                ContentRange::bytes(start..end, len).expect("valid ContentRange"),
-                            );
-
-                            len = sub_len;
-                        }
-
-                        let mime = mime_guess::from_path(path.as_ref()).first_or_octet_stream();
-
-                        resp.headers_mut().typed_insert(ContentLength(len));
-                        resp.headers_mut().typed_insert(ContentType::from(mime));
-                        resp.headers_mut().typed_insert(AcceptRanges::bytes());
-
-                        if let Some(last_modified) = modified {
-                            resp.headers_mut().typed_insert(last_modified);
-                        }
-
-                        resp
-                    })
-                    .unwrap_or_else(|BadRange| {
-                        // bad byte range
-                        let mut resp = Response::new(Body::empty());
-                        *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
-                        resp.headers_mut()
-                            .typed_insert(ContentRange::unsatisfied_bytes(len));
-                        resp
-                    })
+    config: Arc<Config>,
+) -> Result<File, Rejection> {
+    let (file, meta) = file_metadata(f).await?;
+    let mut len = meta.len();
+    let modified = meta.modified().ok().map(LastModified::from);
+    let etag = match encoding {
+        Some(encoding) => {
+            let mut encoded = path.as_ref().as_os_str().to_owned();
+            encoded.push(".");
+            encoded.push(encoding.extension());
+            config.etag(Path::new(&encoded), &meta).await
+        }
+        None => config.etag(path.as_ref(), &meta).await,
+    };
+
+    let mut resp = match conditionals.check(modified, etag.as_ref()) {
+        Cond::NoBody(mut resp) => {
+            if resp.status() == StatusCode::NOT_MODIFIED {
+                if let Some(etag) = etag {
+                    resp.headers_mut().typed_insert(etag);
+                }
+                if let Some(last_modified) = modified {
+                    resp.headers_mut().typed_insert(last_modified);
+                }
             }
-        };
+            resp
+        }
+        Cond::WithBody(range) => {
+            bytes_range(range, len)
+                .map(|(start, end)| {
+                    let sub_len = end - start;
+                    let buf_size = optimal_buf_size(&meta);
+                    let stream = file_stream(file, buf_size, (start, end));
+                    let body = Body::wrap_stream(stream);
+
+                    let mut resp = Response::new(body);
+
+                    if sub_len != len {
+                        *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                        resp.headers_mut().typed_insert(
+                            ContentRange::bytes(start..end, len).expect("valid ContentRange"),
+                        );
+
+                        len = sub_len;
+                    }
+
+                    let mime = mime_guess::from_path(path.as_ref()).first_or_octet_stream();
+
+                    resp.headers_mut().typed_insert(ContentLength(len));
+                    resp.headers_mut().typed_insert(ContentType::from(mime));
+                    resp.headers_mut().typed_insert(AcceptRanges::bytes());
 
-        File { resp }
-    })
+                    if let Some(last_modified) = modified {
+                        resp.headers_mut().typed_insert(last_modified);
+                    }
+                    if let Some(etag) = etag {
+                        resp.headers_mut().typed_insert(etag);
+                    }
+                    if let Some(encoding) = encoding {
+                        resp.headers_mut().insert(
+                            CONTENT_ENCODING,
+                            HeaderValue::from_static(encoding.coding()),
+                        );
+                    }
+
+                    resp
+                })
+                .unwrap_or_else(|BadRange| {
+                    // bad byte range
+                    let mut resp = Response::new(Body::empty());
+                    *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+                    resp.headers_mut()
+                        .typed_insert(ContentRange::unsatisfied_bytes(len));
+                    resp
+                })
+        }
+    };
+
+    if !config.precompressed.is_empty() {
+        resp.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    Ok(File { resp })
 }
 
 struct BadRange;
@@ -341,6 +1106,16 @@ fn bytes_range(range: Option<Range>, max_len: u64) -> Result<(u64, u64), BadRang
     let ret = range
         .iter()
         .map(|(start, end)| {
+            // A suffix range, such as `bytes=-500`, is the last bytes of the file.
+            if let (Bound::Unbounded, Bound::Included(suffix)) = (start, end) {
+                return if suffix > 0 && max_len > 0 {
+                    Ok((max_len.saturating_sub(suffix), max_len))
+                } else {
+                    log::trace!("unsatisfiable suffix byte range: -{}/{}", suffix, max_len);
+                    Err(BadRange)
+                };
+            }
+
             let start = match start {
                 Bound::Unbounded => 0,
                 Bound::Included(s) => s,
@@ -0,0 +1,299 @@
+//! GeoIP filters.
+//!
+//! A [`GeoIp`] resolves the address of clients against [MaxMind
+//! databases](https://dev.maxmind.com/geoip/docs/databases), such as
+//! GeoLite2 Country and GeoLite2 ASN, and its [`filter`](GeoIp::filter)
+//! extracts the country and autonomous system of the client as a [`Geo`].
+//!
+//! The `Geo` is also stored in the extensions of the request, so filters
+//! running later can get it with [`ext::get`](crate::ext::get), and access
+//! logs with [`Info::geo`](crate::log::Info::geo).
+//!
+//! Lookups are cached in memory, and the databases can be reloaded while
+//! the server runs, such as after they were updated on disk.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use warp::geoip::{Geo, GeoIp};
+//! use warp::Filter;
+//!
+//! let geoip = GeoIp::open("GeoLite2-Country.mmdb")
+//!     .and_then(|geoip| geoip.with_database("GeoLite2-ASN.mmdb"))
+//!     .expect("open GeoIP databases");
+//!
+//! let route = warp::path("where")
+//!     .and(geoip.filter())
+//!     .map(|geo: Geo| format!("{:?} {:?}", geo.country(), geo.asn()));
+//! ```
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use futures::future;
+use maxminddb::{MaxMindDBError, Reader};
+use serde::Deserialize;
+
+use crate::filter::{filter_fn, Filter};
+use crate::filters::uri::Proxies;
+
+/// MaxMind databases resolving the country and autonomous system of IP
+/// addresses.
+///
+/// A `GeoIp` is cheap to clone, and clones share the same databases and
+/// cache.
+#[derive(Clone)]
+pub struct GeoIp {
+    inner: Arc<Inner>,
+    proxies: Arc<Proxies>,
+}
+
+struct Inner {
+    paths: Vec<PathBuf>,
+    readers: ArcSwap<Vec<Reader<Vec<u8>>>>,
+    cache: Mutex<HashMap<IpAddr, Geo>>,
+    cache_size: usize,
+}
+
+impl GeoIp {
+    /// Open the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<GeoIp, GeoIpError> {
+        GeoIp::open_all(vec![path.as_ref().to_owned()], 10_000)
+    }
+
+    /// Add the database at `path`, such as an ASN database besides a
+    /// country database.
+    ///
+    /// Databases are looked up in the order they were added, the first
+    /// having a value winning.
+    pub fn with_database(self, path: impl AsRef<Path>) -> Result<GeoIp, GeoIpError> {
+        let mut paths = self.inner.paths.clone();
+        paths.push(path.as_ref().to_owned());
+        let geoip = GeoIp::open_all(paths, self.inner.cache_size)?;
+        Ok(GeoIp {
+            proxies: self.proxies,
+            ..geoip
+        })
+    }
+
+    /// Set how many addresses are cached, 10,000 by default.
+    ///
+    /// The cache is emptied when it is full.
+    pub fn cache_size(self, size: usize) -> GeoIp {
+        GeoIp {
+            inner: Arc::new(Inner {
+                paths: self.inner.paths.clone(),
+                readers: ArcSwap::new(self.inner.readers.load_full()),
+                cache: Mutex::new(HashMap::new()),
+                cache_size: size,
+            }),
+            proxies: self.proxies,
+        }
+    }
+
+    /// Trust the forwarding headers of requests from the proxy at `addr`,
+    /// to find the address of clients.
+    pub fn trust(mut self, addr: IpAddr) -> Self {
+        Arc::make_mut(&mut self.proxies).addrs.push(addr);
+        self
+    }
+
+    /// Trust the forwarding headers of every request.
+    ///
+    /// This should only be used when the server can only be reached
+    /// through proxies, as anyone could pretend to be a proxy otherwise.
+    pub fn trust_all(mut self) -> Self {
+        Arc::make_mut(&mut self.proxies).all = true;
+        self
+    }
+
+    /// Read the databases again from their files, and empty the cache.
+    ///
+    /// Lookups keep using the previous databases if one can't be read.
+    pub fn reload(&self) -> Result<(), GeoIpError> {
+        let readers = read_all(&self.inner.paths)?;
+        self.inner.readers.store(Arc::new(readers));
+        self.inner.cache.lock().unwrap().clear();
+        Ok(())
+    }
+
+    /// Look up the country and autonomous system of `ip`.
+    pub fn lookup(&self, ip: IpAddr) -> Geo {
+        if let Some(geo) = self.inner.cache.lock().unwrap().get(&ip) {
+            return geo.clone();
+        }
+        let mut geo = Geo {
+            ip: Some(ip),
+            ..Geo::default()
+        };
+        for reader in self.inner.readers.load().iter() {
+            let ip = match (ip, reader.metadata.ip_version) {
+                (IpAddr::V6(v6), 4) => match v6.to_ipv4_mapped() {
+                    Some(v4) => IpAddr::V4(v4),
+                    None => continue,
+                },
+                _ => ip,
+            };
+            match reader.lookup::<Record>(ip) {
+                Ok(record) => geo.merge(record),
+                Err(MaxMindDBError::AddressNotFoundError(_)) => (),
+                Err(err) => log::debug!("geoip lookup error for {}: {}", ip, err),
+            }
+        }
+        let mut cache = self.inner.cache.lock().unwrap();
+        if cache.len() >= self.inner.cache_size {
+            cache.clear();
+        }
+        if self.inner.cache_size > 0 {
+            cache.insert(ip, geo.clone());
+        }
+        geo
+    }
+
+    /// Creates a `Filter` that extracts the [`Geo`] of the client, and
+    /// stores it in the extensions of the request.
+    ///
+    /// It never rejects: requests without a client address, or with an
+    /// address that isn't in the databases, get an empty `Geo`.
+    pub fn filter(&self) -> impl Filter<Extract = (Geo,), Error = Infallible> + Clone {
+        let geoip = self.clone();
+        filter_fn(move |route| {
+            let geo = match geoip.proxies.client_ip(route) {
+                Some(ip) => geoip.lookup(ip),
+                None => Geo::default(),
+            };
+            route.extensions_mut().insert(geo.clone());
+            future::ok((geo,))
+        })
+    }
+
+    fn open_all(paths: Vec<PathBuf>, cache_size: usize) -> Result<GeoIp, GeoIpError> {
+        let readers = read_all(&paths)?;
+        Ok(GeoIp {
+            inner: Arc::new(Inner {
+                paths,
+                readers: ArcSwap::from_pointee(readers),
+                cache: Mutex::new(HashMap::new()),
+                cache_size,
+            }),
+            proxies: Arc::new(Proxies::default()),
+        })
+    }
+}
+
+fn read_all(paths: &[PathBuf]) -> Result<Vec<Reader<Vec<u8>>>, GeoIpError> {
+    paths
+        .iter()
+        .map(|path| {
+            Reader::open_readfile(path).map_err(|err| GeoIpError {
+                path: path.clone(),
+                err,
+            })
+        })
+        .collect()
+}
+
+impl fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GeoIp")
+            .field("paths", &self.inner.paths)
+            .field("cache_size", &self.inner.cache_size)
+            .field("proxies", &self.proxies)
+            .finish()
+    }
+}
+
+// The fields of the records of the GeoIP2 and GeoLite2 Country, City and
+// ASN databases used by `Geo`.
+#[derive(Deserialize)]
+struct Record<'a> {
+    #[serde(borrow)]
+    country: Option<Country<'a>>,
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Country<'a> {
+    iso_code: Option<&'a str>,
+}
+
+/// The country and autonomous system of a client, extracted by
+/// [`GeoIp::filter`].
+///
+/// Fields are `None` when they aren't known, such as the country with only
+/// an ASN database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Geo {
+    ip: Option<IpAddr>,
+    country: Option<String>,
+    asn: Option<u32>,
+    as_organization: Option<String>,
+}
+
+impl Geo {
+    /// The address of the client.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    /// The ISO 3166-1 code of the country of the client, like `FR`.
+    pub fn country(&self) -> Option<&str> {
+        self.country.as_deref()
+    }
+
+    /// The number of the autonomous system of the client.
+    pub fn asn(&self) -> Option<u32> {
+        self.asn
+    }
+
+    /// The organization of the autonomous system of the client.
+    pub fn as_organization(&self) -> Option<&str> {
+        self.as_organization.as_deref()
+    }
+
+    fn merge(&mut self, record: Record) {
+        if self.country.is_none() {
+            self.country = record
+                .country
+                .and_then(|country| country.iso_code)
+                .map(ToOwned::to_owned);
+        }
+        if self.asn.is_none() {
+            self.asn = record.autonomous_system_number;
+        }
+        if self.as_organization.is_none() {
+            self.as_organization = record.autonomous_system_organization.map(ToOwned::to_owned);
+        }
+    }
+}
+
+/// An error opening a GeoIP database.
+#[derive(Debug)]
+pub struct GeoIpError {
+    path: PathBuf,
+    err: MaxMindDBError,
+}
+
+impl fmt::Display for GeoIpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "error opening GeoIP database {}: {}",
+            self.path.display(),
+            self.err
+        )
+    }
+}
+
+impl StdError for GeoIpError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.err)
+    }
+}
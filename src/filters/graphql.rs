@@ -0,0 +1,438 @@
+//! GraphQL
+//!
+//! Helpers to serve a GraphQL schema over HTTP, following the
+//! [GraphQL over HTTP][spec] specification, with any GraphQL engine:
+//!
+//! - [`request`] extracts the GraphQL requests, from the query of a `GET`,
+//!   or the body of a `POST`, which may hold a batch of requests.
+//! - [`reply`] serializes the result of executing them, with the media type
+//!   the client accepts.
+//! - [`subscriptions`] runs subscriptions over a WebSocket, with the
+//!   [`graphql-transport-ws`][ws] protocol.
+//!
+//! # Media types
+//!
+//! Requests are read from `application/json` bodies, the
+//! `application/graphql` body of a query alone is accepted too. Results are
+//! sent as `application/graphql-response+json` to clients accepting it, and
+//! as `application/json` otherwise.
+//!
+//! Engines should refuse to execute mutations of `GET` requests, as they
+//! may be cached or prefetched, see [`GraphQLRequest::is_get`].
+//!
+//! [spec]: https://graphql.github.io/graphql-over-http/draft/
+//! [ws]: https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md
+
+use bytes::Bytes;
+use headers::ContentType;
+use http::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::filter::{filter_fn_one, Filter};
+use crate::filters::body::{self, deserialize_error};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+/// The media type of GraphQL results.
+pub const GRAPHQL_RESPONSE_JSON: &str = "application/graphql-response+json";
+
+/// The media type of a GraphQL query alone, as a request body.
+pub const GRAPHQL: &str = "application/graphql";
+
+/// A GraphQL request.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLRequest {
+    /// The source of the document to execute.
+    pub query: String,
+    /// The name of the operation of the document to execute.
+    #[serde(default)]
+    pub operation_name: Option<String>,
+    /// The values of the variables of the operation.
+    #[serde(default)]
+    pub variables: Option<Map<String, Value>>,
+    /// Extensions of the request, such as persisted queries.
+    #[serde(default)]
+    pub extensions: Option<Map<String, Value>>,
+    #[serde(skip)]
+    get: bool,
+}
+
+impl GraphQLRequest {
+    /// Create a `GraphQLRequest` for a query.
+    pub fn new(query: impl Into<String>) -> GraphQLRequest {
+        GraphQLRequest {
+            query: query.into(),
+            ..GraphQLRequest::default()
+        }
+    }
+
+    /// Whether the request was made with `GET`, in which case it mustn't
+    /// execute a mutation.
+    pub fn is_get(&self) -> bool {
+        self.get
+    }
+}
+
+/// The GraphQL requests of an HTTP request, extracted by [`request`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphQLBatch {
+    /// A single request.
+    Single(GraphQLRequest),
+    /// A batch of requests, sent as a JSON array, to execute in order. The
+    /// reply should be an array of their results.
+    Batch(Vec<GraphQLRequest>),
+}
+
+impl GraphQLBatch {
+    /// Whether this is a batch of requests.
+    pub fn is_batch(&self) -> bool {
+        matches!(self, GraphQLBatch::Batch(_))
+    }
+
+    /// The requests, whether there is one or a batch.
+    pub fn into_requests(self) -> Vec<GraphQLRequest> {
+        match self {
+            GraphQLBatch::Single(req) => vec![req],
+            GraphQLBatch::Batch(reqs) => reqs,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Batch {
+    Single(GraphQLRequest),
+    Batch(Vec<GraphQLRequest>),
+}
+
+// The parameters of a `GET`, where `variables` and `extensions` are JSON.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetParams {
+    query: String,
+    operation_name: Option<String>,
+    variables: Option<String>,
+    extensions: Option<String>,
+}
+
+/// Creates a `Filter` extracting the GraphQL requests of a `GET` or `POST`.
+///
+/// `GET` requests have the `query`, `operationName`, `variables` and
+/// `extensions` parameters in their query string, the last two as JSON.
+/// `POST` requests have a JSON body, with a request object or a batch of
+/// them in an array, or a query alone with the `application/graphql` media
+/// type.
+///
+/// # Example
+///
+/// ```
+/// use warp::graphql::{GraphQLBatch, GraphQLRequest};
+/// use warp::Filter;
+///
+/// fn execute(req: GraphQLRequest) -> serde_json::Value {
+///     // Execute the request with a GraphQL engine...
+///     serde_json::json!({ "data": { "hello": "world" } })
+/// }
+///
+/// let route = warp::path("graphql")
+///     .and(warp::graphql::request())
+///     .map(|batch: GraphQLBatch| match batch {
+///         GraphQLBatch::Single(req) => warp::graphql::reply(&execute(req)),
+///         GraphQLBatch::Batch(reqs) => {
+///             let results = reqs.into_iter().map(execute).collect::<Vec<_>>();
+///             warp::graphql::reply(&results)
+///         }
+///     });
+/// ```
+pub fn request() -> impl Filter<Extract = (GraphQLBatch,), Error = Rejection> + Copy {
+    let get = crate::get().and(filter_fn_one(|route| {
+        let params = serde_urlencoded::from_str::<GetParams>(route.query().unwrap_or(""))
+            .map_err(|err| {
+                log::debug!("graphql query params error: {}", err);
+                reject::invalid_query()
+            })
+            .and_then(|params| {
+                Ok(GraphQLBatch::Single(GraphQLRequest {
+                    query: params.query,
+                    operation_name: params.operation_name,
+                    variables: json_param(params.variables)?,
+                    extensions: json_param(params.extensions)?,
+                    get: true,
+                }))
+            });
+        futures::future::ready(params)
+    }));
+
+    let post = crate::post()
+        .and(crate::header::optional2::<ContentType>())
+        .and(body::bytes())
+        .and_then(
+            |content_type: Option<ContentType>, body: Bytes| async move {
+                let mime = content_type.map(mime::Mime::from);
+                match mime.as_ref().map(|mime| mime.essence_str()) {
+                    Some("application/json") | None => {
+                        match serde_json::from_slice(&body)
+                            .map_err(|err| deserialize_error(err.into()))?
+                        {
+                            Batch::Single(req) => Ok(GraphQLBatch::Single(req)),
+                            Batch::Batch(reqs) => Ok(GraphQLBatch::Batch(reqs)),
+                        }
+                    }
+                    Some(GRAPHQL) => {
+                        let query = String::from_utf8(body.to_vec())
+                            .map_err(|err| deserialize_error(err.into()))?;
+                        Ok(GraphQLBatch::Single(GraphQLRequest::new(query)))
+                    }
+                    Some(_) => Err(reject::unsupported_media_type()),
+                }
+            },
+        );
+
+    get.or(post).unify()
+}
+
+fn json_param(param: Option<String>) -> Result<Option<Map<String, Value>>, Rejection> {
+    match param {
+        Some(ref json) if !json.is_empty() => serde_json::from_str(json).map(Some).map_err(|err| {
+            log::debug!("graphql query param json error: {}", err);
+            reject::invalid_query()
+        }),
+        _ => Ok(None),
+    }
+}
+
+/// Reply with a GraphQL result, or an array of results for a batch.
+///
+/// The reply is `application/graphql-response+json` if the request accepts
+/// it, and `application/json` otherwise. The accepted media types are
+/// looked up when `reply` is called, so it must be called while handling
+/// the request.
+pub fn reply<T: Serialize>(result: &T) -> GraphQLReply {
+    let accepts_graphql = route::is_set()
+        && route::with(|route| {
+            route
+                .headers()
+                .get_all(ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|media| {
+                    let media = media.split(';').next().unwrap_or("").trim();
+                    media.eq_ignore_ascii_case(GRAPHQL_RESPONSE_JSON)
+                })
+        });
+    GraphQLReply {
+        body: serde_json::to_vec(result).map_err(|err| {
+            log::error!("graphql::reply error: {}", err);
+        }),
+        content_type: if accepts_graphql {
+            GRAPHQL_RESPONSE_JSON
+        } else {
+            "application/json"
+        },
+    }
+}
+
+/// A GraphQL result, created with [`reply`].
+#[allow(missing_debug_implementations)]
+pub struct GraphQLReply {
+    body: Result<Vec<u8>, ()>,
+    content_type: &'static str,
+}
+
+impl Reply for GraphQLReply {
+    fn into_response(self) -> Response {
+        match self.body {
+            Ok(body) => {
+                let mut res = Response::new(body.into());
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(self.content_type));
+                res
+            }
+            Err(()) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+/// Creates a `Filter` running GraphQL subscriptions over a WebSocket.
+///
+/// The WebSocket speaks the `graphql-transport-ws` protocol. Each
+/// subscription of the client is handed to `handler`, which returns a
+/// `Stream` of the results to send it, until the stream ends or the client
+/// completes the subscription. The `connection_init` payload of the client
+/// isn't checked, authentication should be done by filters before this one.
+///
+/// # Example
+///
+/// ```
+/// use futures::stream;
+/// use warp::graphql::GraphQLRequest;
+/// use warp::Filter;
+///
+/// let route = warp::path("graphql").and(warp::graphql::subscriptions(|req: GraphQLRequest| {
+///     // Subscribe with a GraphQL engine...
+///     stream::iter(vec![serde_json::json!({ "data": { "tick": 1 } })])
+/// }));
+/// ```
+#[cfg(feature = "websocket")]
+pub fn subscriptions<F, S>(
+    handler: F,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    F: Fn(GraphQLRequest) -> S + Clone + Send + Sync + 'static,
+    S: futures::Stream<Item = Value> + Send + 'static,
+{
+    crate::ws()
+        .and(crate::header::optional::<String>("sec-websocket-protocol"))
+        .map(move |ws: crate::ws::Ws, protocols: Option<String>| {
+            let handler = handler.clone();
+            let reply = ws.on_upgrade(move |socket| ws_protocol::serve(socket, handler));
+            let requested = matches!(
+                protocols,
+                Some(ref protocols) if protocols
+                    .split(',')
+                    .any(|protocol| protocol.trim() == ws_protocol::PROTOCOL)
+            );
+            let mut res = reply.into_response();
+            if requested {
+                res.headers_mut().insert(
+                    "sec-websocket-protocol",
+                    HeaderValue::from_static(ws_protocol::PROTOCOL),
+                );
+            }
+            res
+        })
+}
+
+#[cfg(feature = "websocket")]
+mod ws_protocol {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use futures::channel::oneshot;
+    use futures::{FutureExt, Stream, StreamExt};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use tokio::sync::mpsc;
+
+    use super::GraphQLRequest;
+    use crate::ws::{Message, WebSocket};
+
+    pub(super) const PROTOCOL: &str = "graphql-transport-ws";
+
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum ClientMessage {
+        ConnectionInit,
+        Ping,
+        Pong,
+        Subscribe { id: String, payload: GraphQLRequest },
+        Complete { id: String },
+    }
+
+    type Active = Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>;
+
+    pub(super) async fn serve<F, S>(socket: WebSocket, handler: F)
+    where
+        F: Fn(GraphQLRequest) -> S,
+        S: Stream<Item = Value> + Send + 'static,
+    {
+        let (ws_tx, mut ws_rx) = socket.split();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::task::spawn(rx.map(Ok).forward(ws_tx).map(|result| {
+            if let Err(err) = result {
+                log::debug!("graphql websocket send error: {}", err);
+            }
+        }));
+
+        let active = Active::default();
+        let mut acknowledged = false;
+        while let Some(msg) = ws_rx.next().await {
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(err) => {
+                    log::debug!("graphql websocket error: {}", err);
+                    break;
+                }
+            };
+            if msg.is_close() {
+                break;
+            }
+            if msg.is_ping() || msg.is_pong() {
+                continue;
+            }
+            let msg = match msg.to_str().ok().map(serde_json::from_str) {
+                Some(Ok(msg)) => msg,
+                _ => {
+                    let _ = tx.send(Message::close_with(4400u16, "Invalid message received"));
+                    break;
+                }
+            };
+            match msg {
+                ClientMessage::ConnectionInit if acknowledged => {
+                    let _ = tx.send(Message::close_with(
+                        4429u16,
+                        "Too many initialisation requests",
+                    ));
+                    break;
+                }
+                ClientMessage::ConnectionInit => {
+                    acknowledged = true;
+                    send(&tx, json!({ "type": "connection_ack" }));
+                }
+                ClientMessage::Ping => send(&tx, json!({ "type": "pong" })),
+                ClientMessage::Pong => {}
+                ClientMessage::Subscribe { .. } if !acknowledged => {
+                    let _ = tx.send(Message::close_with(4401u16, "Unauthorized"));
+                    break;
+                }
+                ClientMessage::Subscribe { id, payload } => {
+                    let (cancel, canceled) = oneshot::channel();
+                    {
+                        let mut active = active.lock().unwrap();
+                        if active.contains_key(&id) {
+                            let reason = format!("Subscriber for {} already exists", id);
+                            let _ = tx.send(Message::close_with(4409u16, reason));
+                            break;
+                        }
+                        active.insert(id.clone(), cancel);
+                    }
+                    let results = handler(payload).take_until(canceled);
+                    tokio::task::spawn(subscribe(id, results, tx.clone(), active.clone()));
+                }
+                ClientMessage::Complete { id } => {
+                    // Dropping the sender cancels the subscription.
+                    active.lock().unwrap().remove(&id);
+                }
+            }
+        }
+
+        active.lock().unwrap().clear();
+    }
+
+    async fn subscribe<S>(
+        id: String,
+        results: S,
+        tx: mpsc::UnboundedSender<Message>,
+        active: Active,
+    ) where
+        S: Stream<Item = Value>,
+    {
+        futures::pin_mut!(results);
+        while let Some(result) = results.next().await {
+            send(&tx, json!({ "type": "next", "id": id, "payload": result }));
+        }
+        // Only complete subscriptions the client didn't complete itself.
+        if active.lock().unwrap().remove(&id).is_some() {
+            send(&tx, json!({ "type": "complete", "id": id }));
+        }
+    }
+
+    fn send(tx: &mpsc::UnboundedSender<Message>, msg: Value) {
+        let _ = tx.send(Message::text(msg.to_string()));
+    }
+}
@@ -0,0 +1,211 @@
+//! gRPC-Web
+//!
+//! Browsers can't make the HTTP/2 requests with trailers that gRPC needs,
+//! so [gRPC-Web][spec] frames the trailers in the response body instead.
+//! The [`grpc_web`] filter translates gRPC-Web requests for a gRPC service,
+//! such as a [tonic][tonic] server, and its responses back, so browsers can
+//! reach the service on the same listener as warp filters.
+//!
+//! Only the binary format, `application/grpc-web` and
+//! `application/grpc-web+proto`, is supported, not `application/grpc-web-text`.
+//!
+//! Browsers also need CORS to call a service on another origin, with the
+//! `grpc-status` and `grpc-message` headers exposed, which
+//! [`warp::cors`](crate::cors()) can add.
+//!
+//! [spec]: https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md
+//! [tonic]: https://docs.rs/tonic
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::future;
+use futures::stream;
+use http::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, TE};
+use http::{Method, Version};
+use hyper::body::HttpBody;
+use hyper::Body;
+use tower_service::Service;
+
+use crate::filter::{FilterBase, Internal};
+use crate::filters::tower::{into_request, service_error};
+use crate::reject::{self, Rejection};
+use crate::reply::Response;
+use crate::route;
+use crate::Request;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Create a `Filter` bridging gRPC-Web requests to a gRPC `Service`.
+///
+/// As with [`service_filter`](crate::service_filter), the path matched so
+/// far is stripped from the URI of the request handed to the service, so it
+/// can be mounted under a path prefix.
+///
+/// Requests that aren't a `POST` with a gRPC-Web `content-type` are
+/// rejected, so other filters can still handle them. If the service returns
+/// an error, the request is rejected with a
+/// [`ServiceError`](crate::tower::ServiceError).
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // Any gRPC `Service`, such as a tonic server...
+/// let greeter = tower::service_fn(|_req: warp::http::Request<warp::hyper::Body>| async {
+///     let res = warp::http::Response::builder()
+///         .header("content-type", "application/grpc")
+///         .header("grpc-status", "0")
+///         .body(warp::hyper::Body::empty());
+///     res
+/// });
+///
+/// let route = warp::path("grpc").and(warp::grpc_web(greeter));
+/// ```
+pub fn grpc_web<S, B>(service: S) -> GrpcWeb<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    GrpcWeb { service }
+}
+
+/// A filter bridging gRPC-Web to a gRPC `Service`, created with
+/// [`grpc_web`].
+#[derive(Clone, Copy)]
+pub struct GrpcWeb<S> {
+    service: S,
+}
+
+impl<S> fmt::Debug for GrpcWeb<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GrpcWeb").finish()
+    }
+}
+
+impl<S, B> FilterBase for GrpcWeb<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let req = route::with(|route| {
+            if route.method() != Method::POST {
+                return Err(reject::method_not_allowed(&[Method::POST]));
+            }
+            let content_type = route
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(grpc_content_type)
+                .ok_or_else(reject::unsupported_media_type)?;
+            let headers = route.headers_mut();
+            headers.insert(CONTENT_TYPE, content_type);
+            headers.insert(TE, HeaderValue::from_static("trailers"));
+            headers.remove(CONTENT_LENGTH);
+            let mut req = into_request(route, true)?;
+            *req.version_mut() = Version::HTTP_2;
+            Ok(req)
+        });
+        let mut service = self.service.clone();
+        Box::pin(async move {
+            let req = req?;
+            future::poll_fn(|cx| service.poll_ready(cx))
+                .await
+                .map_err(service_error)?;
+            let res = service.call(req).await.map_err(service_error)?;
+
+            let (mut parts, body) = res.into_parts();
+            let content_type = parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(grpc_web_content_type)
+                .unwrap_or_else(|| HeaderValue::from_static("application/grpc-web+proto"));
+            parts.headers.insert(CONTENT_TYPE, content_type);
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.version = Version::default();
+            Ok((Response::from_parts(parts, encode_body(body)),))
+        })
+    }
+}
+
+// `application/grpc-web+proto` to `application/grpc+proto`.
+fn grpc_content_type(content_type: &str) -> Option<HeaderValue> {
+    let suffix = strip_prefix(content_type, "application/grpc-web")?;
+    if !(suffix.is_empty() || suffix.starts_with('+') || suffix.starts_with(';')) {
+        return None;
+    }
+    HeaderValue::from_str(&format!("application/grpc{}", suffix)).ok()
+}
+
+// `application/grpc+proto` to `application/grpc-web+proto`.
+fn grpc_web_content_type(content_type: &str) -> Option<HeaderValue> {
+    let suffix = strip_prefix(content_type, "application/grpc")?;
+    HeaderValue::from_str(&format!("application/grpc-web{}", suffix)).ok()
+}
+
+fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    if s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+// Streams the messages of a gRPC body, followed by its trailers in a
+// trailers frame.
+fn encode_body<B>(body: B) -> Body
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    let body = Box::pin(body);
+    Body::wrap_stream(stream::unfold(Some(body), |body| async move {
+        let mut body = body?;
+        let data: Option<Result<B::Data, BoxError>> =
+            body.data().await.map(|data| data.map_err(Into::into));
+        match data {
+            Some(Ok(mut data)) => Some((Ok(data.to_bytes()), Some(body))),
+            Some(Err(err)) => Some((Err(err), None)),
+            None => match body.trailers().await.map_err(Into::<BoxError>::into) {
+                Ok(Some(trailers)) => Some((Ok(trailers_frame(&trailers)), None)),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            },
+        }
+    }))
+}
+
+fn trailers_frame(trailers: &HeaderMap) -> Bytes {
+    let mut block = Vec::new();
+    for (name, value) in trailers {
+        block.extend_from_slice(name.as_str().as_bytes());
+        block.push(b':');
+        block.extend_from_slice(value.as_bytes());
+        block.extend_from_slice(b"\r\n");
+    }
+    let mut frame = BytesMut::with_capacity(5 + block.len());
+    // The most significant bit flags a trailers frame.
+    frame.put_u8(0x80);
+    frame.put_u32(block.len() as u32);
+    frame.put_slice(&block);
+    frame.freeze()
+}
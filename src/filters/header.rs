@@ -3,14 +3,17 @@
 //! These filters are used to interact with the Request HTTP headers. Some
 //! of them, like `exact` and `exact_ignore_case`, are just predicates,
 //! they don't extract any values. The `header` filter allows parsing
-//! a type from any header.
+//! a type from any header, `typed_all` several headers into a struct, and
+//! `user_agent_parsed` the browser, operating system and bots of clients.
 use std::convert::Infallible;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use futures::future;
 use headers::{Header, HeaderMapExt};
 use http::header::HeaderValue;
 use http::HeaderMap;
+use serde::de::DeserializeOwned;
 
 use crate::filter::{filter_fn, filter_fn_one, Filter, One};
 use crate::reject::{self, Rejection};
@@ -97,6 +100,48 @@ where
     })
 }
 
+/// Create a `Filter` that extracts several headers into a struct `T`.
+///
+/// The fields of `T` are deserialized with `serde` from the headers of the
+/// same name, so `#[serde(rename_all = "kebab-case")]` maps a field such as
+/// `request_id` to the `request-id` header. Each field parses from the text
+/// of its header like with [`header`](header()), and:
+///
+/// - `Option` fields are `None` when their header is missing, as are
+///   `#[serde(default)]` fields their default, while other missing headers
+///   reject the request.
+/// - `Vec` fields collect all the values of a header, including the comma
+///   separated ones.
+///
+/// If a header is missing or can't be parsed, the request is rejected with
+/// `400 Bad Request`, naming the header like [`header`](header()) does.
+///
+/// # Example
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use warp::Filter;
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "kebab-case")]
+/// struct Client {
+///     x_api_key: String,
+///     x_api_version: Option<u32>,
+///     #[serde(default)]
+///     accept_language: Vec<String>,
+/// }
+///
+/// let route = warp::header::typed_all()
+///     .map(|client: Client| format!("version {:?}", client.x_api_version));
+/// ```
+pub fn typed_all<T: DeserializeOwned + Send + 'static>(
+) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
+    filter_fn_one(|route| {
+        log::trace!("typed_all()");
+        future::ready(de::from_headers(route.headers()))
+    })
+}
+
 pub(crate) fn optional2<T>() -> impl Filter<Extract = One<Option<T>>, Error = Infallible> + Copy
 where
     T: Header + Send + 'static,
@@ -104,6 +149,12 @@ where
     filter_fn_one(move |route| future::ready(Ok(route.headers().typed_get())))
 }
 
+pub(crate) fn value_optional(
+    name: &'static str,
+) -> impl Filter<Extract = One<Option<HeaderValue>>, Error = Infallible> + Copy {
+    filter_fn_one(move |route| future::ok(route.headers().get(name).cloned()))
+}
+
 /* TODO
 pub fn exact2<T>(header: T) -> impl FilterClone<Extract=(), Error=Rejection>
 where
@@ -228,3 +279,596 @@ pub fn value(
 pub fn headers_cloned() -> impl Filter<Extract = One<HeaderMap>, Error = Infallible> + Copy {
     filter_fn_one(|route| future::ok(route.headers().clone()))
 }
+
+/// Create a `Filter` that extracts the best of the `supported` locales for
+/// the `Accept-Language` header.
+///
+/// The language ranges of the header are tried by descending `q`-value,
+/// following the "Lookup" scheme of [RFC 4647]: a range such as `de-CH-1996`
+/// matches a supported `de-CH-1996`, else `de-CH`, else `de`, ignoring case.
+/// Ranges with a `q`-value of `0` are skipped.
+///
+/// The first supported locale is the fallback, extracted if the header is
+/// missing or nothing in it matches. The locale is extracted as it is
+/// spelled in `supported`.
+///
+/// [RFC 4647]: https://tools.ietf.org/html/rfc4647#section-3.4
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::header::accept_language(&["en", "fr", "pt-BR"])
+///     .map(|locale: String| match locale.as_str() {
+///         "fr" => "Bonjour",
+///         "pt-BR" => "Olá",
+///         _ => "Hello",
+///     });
+/// ```
+///
+/// # Panics
+///
+/// Panics if `supported` is empty.
+pub fn accept_language<I>(
+    supported: I,
+) -> impl Filter<Extract = One<String>, Error = Infallible> + Clone
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let supported: Arc<[String]> = supported
+        .into_iter()
+        .map(|locale| locale.as_ref().to_owned())
+        .collect::<Vec<_>>()
+        .into();
+    assert!(
+        !supported.is_empty(),
+        "illegal accept_language: no supported locales"
+    );
+    filter_fn(move |route| {
+        log::trace!("accept_language({:?})", supported);
+        let locale = route
+            .headers()
+            .get(http::header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|accept| lookup_language(accept, &supported))
+            .unwrap_or(&supported[0]);
+        future::ok((locale.clone(),))
+    })
+}
+
+// The supported locale matched first by the ranges of an `Accept-Language`,
+// tried by descending `q`-value.
+fn lookup_language<'a>(accept: &str, supported: &'a [String]) -> Option<&'a String> {
+    let mut ranges = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let range = parts.next()?.trim();
+            let q = parts
+                .filter_map(|param| {
+                    let mut kv = param.splitn(2, '=');
+                    let name = kv.next()?.trim();
+                    let value = kv.next()?.trim();
+                    if name.eq_ignore_ascii_case("q") {
+                        value.parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            if range.is_empty() || range == "*" || q.is_nan() || q <= 0.0 {
+                None
+            } else {
+                Some((range, q))
+            }
+        })
+        .collect::<Vec<_>>();
+    // A stable sort, so that ranges of equal `q`-values keep their order.
+    ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    for (range, _) in ranges {
+        let mut range = range;
+        loop {
+            if let Some(locale) = supported
+                .iter()
+                .find(|locale| locale.eq_ignore_ascii_case(range))
+            {
+                return Some(locale);
+            }
+            match range.rfind('-') {
+                Some(i) => {
+                    range = &range[..i];
+                    // A single letter subtag, such as the `x` of private use,
+                    // is dropped along with the subtag following it.
+                    if range.len() >= 2 && range.as_bytes()[range.len() - 2] == b'-' {
+                        range = &range[..range.len() - 2];
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+    None
+}
+
+/// Create a `Filter` that extracts the `User-Agent` of the request, parsed
+/// by the [`DefaultParser`].
+///
+/// Requests without a `User-Agent` extract an empty [`UserAgent`].
+///
+/// # Example
+///
+/// ```
+/// use warp::header::UserAgent;
+/// use warp::Filter;
+///
+/// let route = warp::header::user_agent_parsed()
+///     .map(|ua: UserAgent| {
+///         if ua.is_bot() {
+///             "hello, robot".to_owned()
+///         } else {
+///             format!("hello, {} user", ua.browser().unwrap_or("unknown"))
+///         }
+///     });
+/// ```
+pub fn user_agent_parsed() -> impl Filter<Extract = One<UserAgent>, Error = Infallible> + Clone {
+    user_agent_parsed_with(DefaultParser)
+}
+
+/// Create a `Filter` that extracts the `User-Agent` of the request, parsed
+/// by `parser`.
+///
+/// Requests without a `User-Agent` are parsed as an empty string.
+///
+/// # Example
+///
+/// ```
+/// use warp::header::{DefaultParser, UserAgentParser};
+///
+/// // Also flags the uptime checks of a monitoring service as bots.
+/// let ua = warp::header::user_agent_parsed_with(|raw: &str| {
+///     let ua = DefaultParser.parse(raw);
+///     let bot = ua.is_bot() || raw.starts_with("Pingdom");
+///     ua.with_bot(bot)
+/// });
+/// ```
+pub fn user_agent_parsed_with<P>(
+    parser: P,
+) -> impl Filter<Extract = One<UserAgent>, Error = Infallible> + Clone
+where
+    P: UserAgentParser + 'static,
+{
+    let parser = Arc::new(parser);
+    filter_fn(move |route| {
+        let raw = route
+            .headers()
+            .get(http::header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        future::ok((parser.parse(raw),))
+    })
+}
+
+/// A `User-Agent` parsed into the browser and operating system of a client,
+/// extracted by [`user_agent_parsed`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserAgent {
+    raw: String,
+    browser: Option<String>,
+    version: Option<String>,
+    os: Option<String>,
+    bot: bool,
+}
+
+impl UserAgent {
+    /// Create a `UserAgent` from the `raw` header, with nothing known about
+    /// it, for parsers to fill in.
+    pub fn new(raw: impl Into<String>) -> UserAgent {
+        UserAgent {
+            raw: raw.into(),
+            ..UserAgent::default()
+        }
+    }
+
+    /// Set the browser, or other client, and its version.
+    pub fn with_browser(mut self, name: impl Into<String>, version: Option<&str>) -> Self {
+        self.browser = Some(name.into());
+        self.version = version.map(ToOwned::to_owned);
+        self
+    }
+
+    /// Set the operating system.
+    pub fn with_os(mut self, name: impl Into<String>) -> Self {
+        self.os = Some(name.into());
+        self
+    }
+
+    /// Set whether the client is automated.
+    pub fn with_bot(mut self, bot: bool) -> Self {
+        self.bot = bot;
+        self
+    }
+
+    /// The raw `User-Agent` header.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The name of the browser or other client, like `Firefox` or `curl`.
+    pub fn browser(&self) -> Option<&str> {
+        self.browser.as_deref()
+    }
+
+    /// The version of the browser or other client, like `115.0`.
+    pub fn browser_version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The name of the operating system, like `Windows` or `iOS`.
+    pub fn os(&self) -> Option<&str> {
+        self.os.as_deref()
+    }
+
+    /// Whether the client is automated, such as a crawler, a command line
+    /// tool, an HTTP library or a headless browser.
+    pub fn is_bot(&self) -> bool {
+        self.bot
+    }
+}
+
+/// A parser of `User-Agent` headers, for [`user_agent_parsed_with`].
+///
+/// It is implemented by functions from the raw header to a [`UserAgent`],
+/// so a parser can wrap the [`DefaultParser`], or a dedicated library.
+pub trait UserAgentParser: Send + Sync {
+    /// Parse the raw `User-Agent` header, which may be empty.
+    fn parse(&self, raw: &str) -> UserAgent;
+}
+
+impl<F> UserAgentParser for F
+where
+    F: Fn(&str) -> UserAgent + Send + Sync,
+{
+    fn parse(&self, raw: &str) -> UserAgent {
+        self(raw)
+    }
+}
+
+/// A parser recognizing the common browsers, operating systems and bots
+/// from the tokens of their `User-Agent`.
+///
+/// Clients identifying as something else than a browser, like
+/// `curl/8.0.1`, get the name and version of their first product token.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultParser;
+
+// Product tokens of browsers, checked in order since browsers also claim to
+// be the browsers they derive from.
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("EdgA/", "Edge"),
+    ("EdgiOS/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("HeadlessChrome/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Version/", "Safari"),
+];
+
+const SYSTEMS: &[(&str, &str)] = &[
+    ("Windows", "Windows"),
+    ("Android", "Android"),
+    ("iPhone", "iOS"),
+    ("iPad", "iOS"),
+    ("iPod", "iOS"),
+    ("CrOS", "Chrome OS"),
+    ("Mac OS X", "macOS"),
+    ("Macintosh", "macOS"),
+    ("Linux", "Linux"),
+];
+
+// Substrings of the lowercased `User-Agent` of automated clients.
+const BOTS: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "headless",
+    "curl/",
+    "wget/",
+    "python-",
+    "go-http-client",
+    "okhttp",
+    "java/",
+    "httpclient",
+    "facebookexternalhit",
+    "preview",
+];
+
+impl UserAgentParser for DefaultParser {
+    fn parse(&self, raw: &str) -> UserAgent {
+        let mut ua = UserAgent::new(raw);
+        let lower = raw.to_ascii_lowercase();
+        ua.bot = BOTS.iter().any(|bot| lower.contains(bot));
+
+        let browser = BROWSERS
+            .iter()
+            .filter(|(token, _)| *token != "Version/" || raw.contains("Safari/"))
+            .find_map(|(token, name)| {
+                let start = raw.find(token)? + token.len();
+                Some((*name, product_version(&raw[start..])))
+            });
+        let browser = browser.or_else(|| {
+            // The first product token, if it isn't a browser's `Mozilla/5.0`.
+            let product = raw.split_whitespace().next()?;
+            let mut product = product.splitn(2, '/');
+            let name = product.next()?;
+            if name.is_empty() || name == "Mozilla" {
+                return None;
+            }
+            Some((name, product.next().and_then(product_version)))
+        });
+        if let Some((name, version)) = browser {
+            ua = ua.with_browser(name, version);
+        }
+
+        if let Some((_, os)) = SYSTEMS.iter().find(|(token, _)| raw.contains(token)) {
+            ua = ua.with_os(*os);
+        }
+        ua
+    }
+}
+
+// The version at the start of `s`, as in `115.0 Safari/537.36`.
+fn product_version(s: &str) -> Option<&str> {
+    let end = s
+        .find(|c: char| c.is_whitespace() || c == ';' || c == ')')
+        .unwrap_or(s.len());
+    Some(&s[..end]).filter(|version| !version.is_empty())
+}
+
+// A `serde` deserializer of the fields of a struct from headers.
+mod de {
+    use std::fmt;
+
+    use http::HeaderMap;
+    use serde::de::{
+        self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+    };
+
+    use crate::reject::{self, Rejection};
+
+    pub(super) fn from_headers<T: DeserializeOwned>(headers: &HeaderMap) -> Result<T, Rejection> {
+        let mut name = "";
+        T::deserialize(Headers {
+            headers,
+            name: &mut name,
+        })
+        .map_err(|err| {
+            log::debug!("failed to deserialize headers: {}", err);
+            match err {
+                Error::Missing(name) => reject::missing_header(name),
+                Error::Invalid(name) => reject::invalid_header(name),
+                Error::Custom(_) => reject::invalid_header(name),
+            }
+        })
+    }
+
+    #[derive(Debug)]
+    pub(super) enum Error {
+        Missing(&'static str),
+        Invalid(&'static str),
+        Custom(String),
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Error::Missing(name) => write!(f, "missing header {:?}", name),
+                Error::Invalid(name) => write!(f, "invalid header {:?}", name),
+                Error::Custom(msg) => f.write_str(msg),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl de::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Self {
+            Error::Custom(msg.to_string())
+        }
+
+        fn missing_field(field: &'static str) -> Self {
+            Error::Missing(field)
+        }
+    }
+
+    struct Headers<'a, 'n> {
+        headers: &'a HeaderMap,
+        // The struct name, used for errors of no header in particular.
+        name: &'n mut &'static str,
+    }
+
+    impl<'de, 'a: 'de, 'n> de::Deserializer<'de> for Headers<'a, 'n> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+            Err(de::Error::custom(
+                "headers can only be deserialized into a struct",
+            ))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            *self.name = name;
+            visitor.visit_map(Fields {
+                headers: self.headers,
+                fields: fields.iter(),
+                current: None,
+            })
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    // The fields of a struct whose header is present.
+    struct Fields<'a> {
+        headers: &'a HeaderMap,
+        fields: std::slice::Iter<'static, &'static str>,
+        current: Option<&'static str>,
+    }
+
+    impl<'de, 'a: 'de> MapAccess<'de> for Fields<'a> {
+        type Error = Error;
+
+        fn next_key_seed<K: DeserializeSeed<'de>>(
+            &mut self,
+            seed: K,
+        ) -> Result<Option<K::Value>, Error> {
+            for &field in &mut self.fields {
+                if self.headers.contains_key(field) {
+                    self.current = Some(field);
+                    return seed.deserialize(field.into_deserializer()).map(Some);
+                }
+            }
+            Ok(None)
+        }
+
+        fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+            let name = self.current.take().expect("next_value before next_key");
+            let values = self
+                .headers
+                .get_all(name)
+                .iter()
+                .map(|value| value.to_str().map_err(|_| Error::Invalid(name)))
+                .collect::<Result<_, _>>()?;
+            seed.deserialize(Value { name, values })
+                .map_err(|err| match err {
+                    Error::Custom(_) => Error::Invalid(name),
+                    err => err,
+                })
+        }
+    }
+
+    // The values of a header.
+    struct Value<'a> {
+        name: &'static str,
+        values: Vec<&'a str>,
+    }
+
+    impl<'a> Value<'a> {
+        fn str(&self) -> Result<&'a str, Error> {
+            self.values
+                .first()
+                .copied()
+                .ok_or(Error::Missing(self.name))
+        }
+    }
+
+    macro_rules! deserialize_parsed {
+        ($($method:ident => $visit:ident,)*) => {
+            $(
+                fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                    let value = self
+                        .str()?
+                        .trim()
+                        .parse()
+                        .map_err(|_| Error::Invalid(self.name))?;
+                    visitor.$visit(value)
+                }
+            )*
+        };
+    }
+
+    impl<'de, 'a: 'de> de::Deserializer<'de> for Value<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_borrowed_str(self.str()?)
+        }
+
+        deserialize_parsed! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_some(self)
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let name = self.name;
+            let items = self
+                .values
+                .iter()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Item { name, item });
+            visitor.visit_seq(de::value::SeqDeserializer::new(items))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_enum(self.str()?.trim().into_deserializer())
+        }
+
+        serde::forward_to_deserialize_any! {
+            i128 u128 str string bytes byte_buf unit unit_struct tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    // An item of a comma separated header.
+    struct Item<'a> {
+        name: &'static str,
+        item: &'a str,
+    }
+
+    impl<'de, 'a: 'de> IntoDeserializer<'de, Error> for Item<'a> {
+        type Deserializer = Value<'a>;
+
+        fn into_deserializer(self) -> Value<'a> {
+            Value {
+                name: self.name,
+                values: vec![self.item],
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Health checks
+//!
+//! Filters replying to liveness and readiness probes, such as the ones of a
+//! load balancer or an orchestrator:
+//!
+//! - [`live`] replies to `GET /health/live` as long as the server is up.
+//! - [`ready`] replies to `GET /health/ready` once all its [`Checks`] pass,
+//!   such as a database ping, with a JSON report of each check.
+//!
+//! These are plain filters, so they can be combined with the other routes of
+//! an application, and wrapped like them. Since they are not part of the API
+//! of the application, documentation generated from the routes should leave
+//! them out.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//! use warp::health::Checks;
+//!
+//! let checks = Checks::new()
+//!     .check("db", || async {
+//!         // Ping the database...
+//!         Ok::<_, std::io::Error>(())
+//!     });
+//!
+//! let health = warp::health::live().or(warp::health::ready(checks));
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::future;
+use http::StatusCode;
+use serde_json::{json, Map, Value};
+
+use crate::filter::Filter;
+use crate::reject::Rejection;
+use crate::reply::{self, Reply, Response};
+
+type Check =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync>;
+
+/// Creates a `Filter` replying to `GET /health/live` with `200 OK`.
+///
+/// The body is the JSON object `{"status": "ok"}`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let routes = warp::health::live().or(warp::any().map(|| "hello"));
+/// ```
+pub fn live() -> impl Filter<Extract = (Response,), Error = Rejection> + Copy {
+    crate::path("health")
+        .and(crate::path("live"))
+        .and(crate::path::end())
+        .and(crate::get())
+        .map(|| reply::json(&json!({ "status": "ok" })).into_response())
+}
+
+/// Creates a `Filter` replying to `GET /health/ready` with the report of the
+/// readiness `checks`.
+///
+/// The checks run concurrently on each request. The reply is `200 OK` if
+/// they all pass, and `503 Service Unavailable` otherwise, with a JSON body
+/// such as:
+///
+/// ```json
+/// {
+///     "status": "unavailable",
+///     "checks": {
+///         "db": { "status": "ok" },
+///         "queue": { "status": "error", "error": "too many pending jobs" }
+///     }
+/// }
+/// ```
+pub fn ready(checks: Checks) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    crate::path("health")
+        .and(crate::path("ready"))
+        .and(crate::path::end())
+        .and(crate::get())
+        .and_then(move || {
+            let checks = checks.clone();
+            async move { Ok::<_, Infallible>(checks.report().await) }
+        })
+}
+
+/// A set of named readiness checks, see [`ready`].
+#[derive(Clone, Default)]
+pub struct Checks {
+    checks: Vec<(String, Check)>,
+}
+
+impl Checks {
+    /// Creates an empty set of checks, which is always ready.
+    pub fn new() -> Checks {
+        Checks::default()
+    }
+
+    /// Adds a check named `name`.
+    ///
+    /// The check fails if its future resolves to an error, which is included
+    /// in the report.
+    pub fn check<F, Fut, E>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        let check: Check = Arc::new(move || {
+            let fut = check();
+            Box::pin(async move { fut.await.map_err(|err| err.to_string()) })
+        });
+        self.checks.push((name.into(), check));
+        self
+    }
+
+    async fn report(&self) -> Response {
+        let results = future::join_all(self.checks.iter().map(|(_, check)| check())).await;
+
+        let mut ready = true;
+        let mut checks = Map::new();
+        for ((name, _), result) in self.checks.iter().zip(results) {
+            let value = match result {
+                Ok(()) => json!({ "status": "ok" }),
+                Err(err) => {
+                    ready = false;
+                    json!({ "status": "error", "error": err })
+                }
+            };
+            checks.insert(name.clone(), value);
+        }
+
+        let (status, code) = if ready {
+            ("ok", StatusCode::OK)
+        } else {
+            ("unavailable", StatusCode::SERVICE_UNAVAILABLE)
+        };
+        let body = json!({ "status": status, "checks": Value::Object(checks) });
+        reply::with_status(reply::json(&body), code).into_response()
+    }
+}
+
+impl fmt::Debug for Checks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.checks.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
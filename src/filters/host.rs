@@ -0,0 +1,182 @@
+//! Host ("authority") filters.
+//!
+//! These filters match the host of the request, taken from the authority of
+//! its URI, or else its `Host` header. They allow a single server to dispatch
+//! requests to virtual hosts, such as tenants with their own subdomain.
+//!
+//! Host patterns are either a name, like `example.com`, or a wildcard, like
+//! `*.example.com`, where the `*` matches one or more labels. Names are
+//! compared case-insensitively. A pattern without a port matches any port,
+//! while a pattern with a port, like `example.com:8080`, only matches that
+//! port.
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::future;
+pub use http::uri::Authority;
+
+use crate::filter::{filter_fn, filter_fn_one, Filter, One};
+use crate::reject::{self, Rejection};
+use crate::route::Route;
+
+/// Creates a `Filter` that extracts the host of the request, if it has one.
+///
+/// Rejects with `400 Bad Request` if the `Host` header isn't a valid
+/// authority.
+///
+/// # Example
+///
+/// ```
+/// use warp::{host::Authority, Filter};
+///
+/// let route = warp::host::optional()
+///     .map(|authority: Option<Authority>| {
+///         match authority {
+///             Some(a) => format!("{} is currently not at home", a.host()),
+///             None => "please state who you're trying to reach".to_owned(),
+///         }
+///     });
+/// ```
+pub fn optional() -> impl Filter<Extract = One<Option<Authority>>, Error = Rejection> + Copy {
+    filter_fn_one(|route| future::ready(authority(route)))
+}
+
+/// Creates a `Filter` that requires the host of the request to match a
+/// pattern, otherwise rejecting with `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let api = warp::host::exact("api.example.com").map(|| "api");
+/// let tenants = warp::host::exact("*.example.com").map(|| "some tenant");
+///
+/// let routes = api.or(tenants);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the pattern isn't a valid host pattern.
+pub fn exact(pattern: &str) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let pattern = Arc::new(HostPattern::parse(pattern));
+    filter_fn(move |route| {
+        log::trace!("host::exact({:?})", pattern.raw);
+        let res = authority(route).and_then(|authority| match authority {
+            Some(ref authority) if pattern.matches(authority).is_some() => Ok(()),
+            _ => Err(reject::not_found()),
+        });
+        future::ready(res)
+    })
+}
+
+/// Creates a `Filter` that requires the host of the request to match a
+/// wildcard pattern, and extracts the part matched by the wildcard.
+///
+/// The subdomain is lowercased. Requests that don't match are rejected with
+/// `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // `acme.example.com` extracts `"acme"`
+/// let route = warp::host::subdomain("*.example.com")
+///     .map(|tenant: String| format!("Hello, {}!", tenant));
+/// ```
+///
+/// # Panics
+///
+/// Panics if the pattern isn't a valid host pattern starting with `*.`.
+pub fn subdomain(pattern: &str) -> impl Filter<Extract = One<String>, Error = Rejection> + Clone {
+    let pattern = HostPattern::parse(pattern);
+    assert!(
+        pattern.wildcard,
+        "illegal subdomain pattern, must start with \"*.\": {:?}",
+        pattern.raw
+    );
+    let pattern = Arc::new(pattern);
+    filter_fn(move |route| {
+        log::trace!("host::subdomain({:?})", pattern.raw);
+        let res = authority(route).and_then(|authority| {
+            authority
+                .as_ref()
+                .and_then(|authority| pattern.matches(authority))
+                .map(|subdomain| (subdomain,))
+                .ok_or_else(reject::not_found)
+        });
+        future::ready(res)
+    })
+}
+
+fn authority(route: &Route) -> Result<Option<Authority>, Rejection> {
+    if let Some(authority) = route.uri().authority() {
+        return Ok(Some(authority.clone()));
+    }
+    match route.headers().get(http::header::HOST) {
+        Some(value) => Authority::try_from(value.as_bytes())
+            .map(Some)
+            .map_err(|_| reject::invalid_header("host")),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug)]
+struct HostPattern {
+    raw: String,
+    wildcard: bool,
+    // The lowercased name, without the wildcard or a trailing dot.
+    name: String,
+    port: Option<u16>,
+}
+
+impl HostPattern {
+    fn parse(raw: &str) -> HostPattern {
+        let authority =
+            Authority::from_str(raw).unwrap_or_else(|_| panic!("illegal host pattern: {:?}", raw));
+        let host = authority.host().trim_end_matches('.').to_ascii_lowercase();
+        let (wildcard, name) = match host.strip_prefix("*.") {
+            Some(name) => (true, name.to_owned()),
+            None => (false, host),
+        };
+        assert!(
+            !name.is_empty() && !name.contains('*'),
+            "illegal host pattern: {:?}",
+            raw
+        );
+        HostPattern {
+            raw: raw.to_owned(),
+            wildcard,
+            name,
+            port: authority.port_u16(),
+        }
+    }
+
+    // Returns the part matched by the wildcard, or an empty string for
+    // patterns without one.
+    fn matches(&self, authority: &Authority) -> Option<String> {
+        if self.port.is_some() && authority.port_u16() != self.port {
+            return None;
+        }
+        let host = authority.host().trim_end_matches('.');
+        if !self.wildcard {
+            return if host.eq_ignore_ascii_case(&self.name) {
+                Some(String::new())
+            } else {
+                None
+            };
+        }
+
+        let split = host.len().checked_sub(self.name.len() + 1)?;
+        if split == 0
+            || !host.is_char_boundary(split)
+            || !host[split + 1..].eq_ignore_ascii_case(&self.name)
+            || host.as_bytes()[split] != b'.'
+        {
+            return None;
+        }
+        Some(host[..split].to_ascii_lowercase())
+    }
+}
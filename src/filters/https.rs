@@ -0,0 +1,196 @@
+//! HTTPS filters.
+
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::{HeaderValue, LOCATION, STRICT_TRANSPORT_SECURITY};
+use http::uri::Scheme;
+use http::StatusCode;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::filters::uri::Proxies;
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+/// Create a wrapping filter that moves clients to HTTPS.
+///
+/// Plain HTTP requests are redirected with `301 Moved Permanently` to the
+/// same URL with the `https` scheme, on the default port, without running
+/// the wrapped filter. Responses to HTTPS requests get a
+/// `Strict-Transport-Security` header, so browsers keep using HTTPS.
+///
+/// Behind a proxy terminating TLS, the scheme is read from the `Forwarded`
+/// or `X-Forwarded-Proto` header of trusted proxies, as with
+/// [`warp::uri::absolute`](crate::uri::absolute).
+///
+/// # Example
+///
+/// ```
+/// use std::net::Ipv4Addr;
+/// use std::time::Duration;
+/// use warp::Filter;
+///
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(
+///         warp::https::enforce()
+///             .trust(Ipv4Addr::LOCALHOST.into())
+///             .max_age(Duration::from_secs(2 * 365 * 24 * 60 * 60))
+///             .include_subdomains(),
+///     );
+/// ```
+pub fn enforce() -> Https {
+    Https {
+        proxies: Arc::new(Proxies::default()),
+        max_age: Duration::from_secs(365 * 24 * 60 * 60),
+        include_subdomains: false,
+        preload: false,
+    }
+}
+
+/// Wrapper moving clients to HTTPS, see [`enforce`].
+#[derive(Clone, Debug)]
+pub struct Https {
+    proxies: Arc<Proxies>,
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Https {
+    /// Trust the `Forwarded` and `X-Forwarded-Proto` headers of requests
+    /// from the proxy at `addr`.
+    pub fn trust(mut self, addr: IpAddr) -> Self {
+        Arc::make_mut(&mut self.proxies).addrs.push(addr);
+        self
+    }
+
+    /// Trust the `Forwarded` and `X-Forwarded-Proto` headers of every
+    /// request.
+    ///
+    /// This should only be used when the server can only be reached
+    /// through proxies.
+    pub fn trust_all(mut self) -> Self {
+        Arc::make_mut(&mut self.proxies).all = true;
+        self
+    }
+
+    /// Consider requests without a forwarded scheme as HTTPS, for a server
+    /// with TLS.
+    pub fn tls(mut self) -> Self {
+        Arc::make_mut(&mut self.proxies).scheme = Scheme::HTTPS;
+        self
+    }
+
+    /// Sets how long browsers should keep using HTTPS, the `max-age` of the
+    /// `Strict-Transport-Security` header.
+    ///
+    /// Defaults to 1 year.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Apply the `Strict-Transport-Security` header to subdomains too.
+    pub fn include_subdomains(mut self) -> Self {
+        self.include_subdomains = true;
+        self
+    }
+
+    /// Add the `preload` directive to the `Strict-Transport-Security`
+    /// header, to be added to the preload lists of browsers.
+    pub fn preload(mut self) -> Self {
+        self.preload = true;
+        self
+    }
+
+    fn hsts(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        HeaderValue::from_str(&value).expect("hsts is a valid HeaderValue")
+    }
+
+    // The redirect of a plain HTTP request, or `None` for HTTPS requests.
+    fn redirect(&self) -> Result<Option<Response>, Rejection> {
+        route::with(|route| {
+            if self.proxies.scheme(route)? == Scheme::HTTPS {
+                return Ok(None);
+            }
+            let (_, authority) = self.proxies.origin(route)?;
+            let path = route
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/");
+            let location = format!("https://{}{}", authority.host(), path);
+            let location =
+                HeaderValue::from_str(&location).map_err(|_| reject::invalid_header("host"))?;
+            let mut res = Response::default();
+            *res.status_mut() = StatusCode::MOVED_PERMANENTLY;
+            res.headers_mut().insert(LOCATION, location);
+            Ok(Some(res))
+        })
+    }
+}
+
+impl<F> WrapSealed<F> for Https
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithHttps<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithHttps {
+            filter,
+            https: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`enforce`].
+#[derive(Clone, Debug)]
+pub struct WithHttps<F> {
+    filter: F,
+    https: Https,
+}
+
+impl<F> FilterBase for WithHttps<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        match self.https.redirect() {
+            Ok(Some(res)) => return Box::pin(async move { Ok((res,)) }),
+            Ok(None) => (),
+            Err(err) => return Box::pin(async move { Err(err) }),
+        }
+        let hsts = self.https.hsts();
+        let fut = self.filter.filter(Internal);
+        Box::pin(async move {
+            let mut res = fut.await.map_err(Into::into)?.into_response();
+            res.headers_mut()
+                .entry(STRICT_TRANSPORT_SECURITY)
+                .or_insert(hsts);
+            Ok((res,))
+        })
+    }
+}
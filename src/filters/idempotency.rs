@@ -0,0 +1,529 @@
+//! Idempotency keys
+//!
+//! Clients retrying a request that must not be applied twice, such as a
+//! payment, send the same `Idempotency-Key` header with each attempt. The
+//! [`Idempotency`] wrapper handles the first request with a key, keeps its
+//! successful response in an [`IdempotencyStore`], and replays that response
+//! for the retries instead of handling them again:
+//!
+//! - A retry arriving while the first request is still being handled gets a
+//!   `409 Conflict`.
+//! - A key reused for another method, path or body gets a
+//!   `422 Unprocessable Entity`.
+//! - Unsuccessful responses aren't kept, and neither are the responses of
+//!   requests dropped before completing, such as when the client
+//!   disconnected, so the request can be retried.
+//!
+//! Replayed responses have an `Idempotent-Replayed: true` header.
+//!
+//! Keys must be scoped per principal. Retries are answered from the store
+//! without running the wrapped filter, so its authentication doesn't run
+//! either, and a client reusing the key of another one gets the response of
+//! the other client. Clients should send keys that can't be guessed, such
+//! as random UUIDs, and servers with several principals should only accept
+//! keys including the principal, checked by a filter before the wrapper.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::idempotency::{Idempotency, MemoryStore};
+//! use warp::Filter;
+//!
+//! let payments = warp::post()
+//!     .and(warp::path("payments"))
+//!     .map(|| "charged")
+//!     .with(Idempotency::new(MemoryStore::new()));
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hasher;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+use futures::future;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::StatusCode;
+use hyper::body::HttpBody;
+use hyper::Body;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::filters::reply::Fnv1a;
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The `Future` returned by the methods of an [`IdempotencyStore`].
+pub type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send>>;
+
+/// A place to keep the responses of idempotent requests.
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Look up a key, reserving it if it's unknown or expired.
+    ///
+    /// The reservation should expire after `ttl`, in case the request is
+    /// never completed.
+    fn begin(&self, key: &str, ttl: Duration) -> StoreFuture<Lookup>;
+
+    /// Keep the response of the request reserving `key`, for `ttl`.
+    fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) -> StoreFuture<()>;
+
+    /// Release the reservation of `key`, after the request failed.
+    fn abort(&self, key: &str) -> StoreFuture<()>;
+}
+
+/// The state of a key in an [`IdempotencyStore`].
+#[derive(Clone, Debug)]
+pub enum Lookup {
+    /// The key was unknown, and is now reserved.
+    New,
+    /// A request with the key is being handled.
+    InFlight,
+    /// A request with the key was handled, with this response.
+    Completed(StoredResponse),
+}
+
+/// A response kept in an [`IdempotencyStore`].
+#[derive(Clone, Debug)]
+pub struct StoredResponse {
+    fingerprint: String,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    /// Create a `StoredResponse`, such as when loading one from storage.
+    pub fn new(
+        fingerprint: String,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StoredResponse {
+        StoredResponse {
+            fingerprint,
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// The method, path and body hash of the request, which retries must
+    /// match.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The body of the response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    fn replay(&self) -> Response {
+        let mut res = Response::new(Body::from(self.body.clone()));
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers.clone();
+        res.headers_mut().insert(
+            HeaderName::from_static("idempotent-replayed"),
+            HeaderValue::from_static("true"),
+        );
+        res
+    }
+}
+
+/// An [`IdempotencyStore`] that keeps responses in memory.
+///
+/// Responses are lost when the server restarts, and aren't shared between
+/// instances of a server.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+// A response, or `None` while a request is being handled, and its expiry.
+type Entry = (Option<StoredResponse>, Instant);
+
+impl MemoryStore {
+    /// Create an empty `MemoryStore`.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// The number of keys in the store, including expired ones.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the store has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl IdempotencyStore for MemoryStore {
+    fn begin(&self, key: &str, ttl: Duration) -> StoreFuture<Lookup> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, expires)| *expires > now);
+        let lookup = match entries.get(key) {
+            Some((Some(response), _)) => Lookup::Completed(response.clone()),
+            Some((None, _)) => Lookup::InFlight,
+            None => {
+                entries.insert(key.to_owned(), (None, now + ttl));
+                Lookup::New
+            }
+        };
+        Box::pin(future::ok(lookup))
+    }
+
+    fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) -> StoreFuture<()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), (Some(response), Instant::now() + ttl));
+        Box::pin(future::ok(()))
+    }
+
+    fn abort(&self, key: &str) -> StoreFuture<()> {
+        self.entries.lock().unwrap().remove(key);
+        Box::pin(future::ok(()))
+    }
+}
+
+/// A wrapper replaying the responses of requests with an idempotency key.
+///
+/// Created with [`Idempotency::new`], and applied to routes with
+/// [`Filter::with`].
+pub struct Idempotency<S> {
+    store: Arc<S>,
+    header: &'static str,
+    ttl: Duration,
+    required: bool,
+    max_body_size: u64,
+}
+
+impl<S: IdempotencyStore> Idempotency<S> {
+    /// Create an `Idempotency` wrapper with the given store.
+    ///
+    /// The key is read from the `idempotency-key` header, which is optional,
+    /// responses are kept for 24 hours, and bodies of requests with a key
+    /// can be up to 1 MiB.
+    pub fn new(store: S) -> Idempotency<S> {
+        Idempotency {
+            store: Arc::new(store),
+            header: "idempotency-key",
+            ttl: Duration::from_secs(24 * 60 * 60),
+            required: false,
+            max_body_size: 1024 * 1024,
+        }
+    }
+
+    /// Set the name of the header with the key.
+    pub fn header(mut self, name: &'static str) -> Self {
+        self.header = name;
+        self
+    }
+
+    /// Set how long responses are kept, and keys reserved by requests that
+    /// never complete.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set whether requests without a key are rejected, with a
+    /// `MissingHeader`.
+    ///
+    /// Otherwise, they are handled as usual.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Set the size of the largest body of a request with a key.
+    ///
+    /// The bodies of requests with a key are buffered to be hashed, and
+    /// larger ones are rejected with a `PayloadTooLarge`.
+    pub fn max_body_size(mut self, max: u64) -> Self {
+        self.max_body_size = max;
+        self
+    }
+}
+
+impl<S> Clone for Idempotency<S> {
+    fn clone(&self) -> Self {
+        Idempotency {
+            store: self.store.clone(),
+            header: self.header,
+            ttl: self.ttl,
+            required: self.required,
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+impl<S> fmt::Debug for Idempotency<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Idempotency")
+            .field("header", &self.header)
+            .field("ttl", &self.ttl)
+            .field("required", &self.required)
+            .field("max_body_size", &self.max_body_size)
+            .finish()
+    }
+}
+
+impl<S, F> WrapSealed<F> for Idempotency<S>
+where
+    S: IdempotencyStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithIdempotency<S, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithIdempotency {
+            idempotency: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`Idempotency`].
+pub struct WithIdempotency<S, F> {
+    idempotency: Idempotency<S>,
+    filter: F,
+}
+
+impl<S, F: Clone> Clone for WithIdempotency<S, F> {
+    fn clone(&self) -> Self {
+        WithIdempotency {
+            idempotency: self.idempotency.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for WithIdempotency<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithIdempotency")
+            .field("idempotency", &self.idempotency)
+            .finish()
+    }
+}
+
+impl<S, F> FilterBase for WithIdempotency<S, F>
+where
+    S: IdempotencyStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let header = self.idempotency.header;
+        let request = route::with(|route| {
+            let key = match route.headers().get(header) {
+                Some(value) => match value.to_str() {
+                    Ok(key) if !key.is_empty() && key.len() <= 255 => Some(key.to_owned()),
+                    _ => return Err(reject::invalid_header(header)),
+                },
+                None => None,
+            };
+            let fingerprint = format!(
+                "{} {}",
+                route.method(),
+                route
+                    .uri()
+                    .path_and_query()
+                    .map(|path| path.as_str())
+                    .unwrap_or("/")
+            );
+            Ok((key, fingerprint))
+        });
+        let idempotency = self.idempotency.clone();
+        let filter = self.filter.clone();
+
+        Box::pin(async move {
+            let (key, fingerprint) = request?;
+            let key = match key {
+                Some(key) => key,
+                None if idempotency.required => return Err(reject::missing_header(header)),
+                None => {
+                    let reply = filter.filter(Internal).await.map_err(Into::into)?;
+                    return Ok((reply.into_response(),));
+                }
+            };
+            let hash = hash_body(idempotency.max_body_size).await?;
+            let fingerprint = format!("{} {:016x}", fingerprint, hash);
+
+            let store = &idempotency.store;
+            match store
+                .begin(&key, idempotency.ttl)
+                .await
+                .map_err(idempotency_error)?
+            {
+                Lookup::New => {}
+                Lookup::InFlight => {
+                    log::debug!("idempotency key {:?} is in flight", key);
+                    return Ok((status(StatusCode::CONFLICT),));
+                }
+                Lookup::Completed(stored) => {
+                    if stored.fingerprint != fingerprint {
+                        log::debug!(
+                            "idempotency key {:?} reused for {:?}, was {:?}",
+                            key,
+                            fingerprint,
+                            stored.fingerprint
+                        );
+                        return Ok((status(StatusCode::UNPROCESSABLE_ENTITY),));
+                    }
+                    return Ok((stored.replay(),));
+                }
+            }
+            let reservation = Reservation {
+                store: store.clone(),
+                key: Some(key),
+            };
+
+            let res = match filter.filter(Internal).await {
+                Ok(reply) => reply.into_response(),
+                Err(err) => {
+                    reservation.abort().await?;
+                    return Err(err.into());
+                }
+            };
+            if !res.status().is_success() {
+                reservation.abort().await?;
+                return Ok((res,));
+            }
+
+            let (parts, body) = res.into_parts();
+            let body = match hyper::body::to_bytes(body).await {
+                Ok(body) => body,
+                Err(err) => {
+                    reservation.abort().await?;
+                    return Err(idempotency_error(err.into()));
+                }
+            };
+            let stored = StoredResponse::new(
+                fingerprint,
+                parts.status,
+                parts.headers.clone(),
+                body.clone(),
+            );
+            reservation.complete(stored, idempotency.ttl).await?;
+            Ok((Response::from_parts(parts, Body::from(body)),))
+        })
+    }
+}
+
+// Buffers the body of the request to hash it, and puts it back for the
+// wrapped filter.
+async fn hash_body(max_body_size: u64) -> Result<u64, Rejection> {
+    let mut hasher = Fnv1a::default();
+    let body = route::with(|route| route.take_body());
+    if let Some(mut body) = body {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|err| {
+                log::debug!("to_bytes error: {}", err);
+                reject::known(crate::body::BodyReadError(err))
+            })?;
+            if (buf.len() + chunk.len()) as u64 > max_body_size {
+                log::debug!("idempotent request body is larger than {}", max_body_size);
+                return Err(reject::payload_too_large());
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        hasher.write(&buf);
+        route::with(|route| route.restore_body(Body::from(buf.freeze())));
+    }
+    Ok(hasher.finish())
+}
+
+// The reservation of a key, released if the request is dropped before it
+// completes, such as when the client disconnects.
+struct Reservation<S: IdempotencyStore> {
+    store: Arc<S>,
+    key: Option<String>,
+}
+
+impl<S: IdempotencyStore> Reservation<S> {
+    async fn complete(mut self, response: StoredResponse, ttl: Duration) -> Result<(), Rejection> {
+        let key = self.key.take().expect("key is reserved");
+        self.store
+            .complete(&key, response, ttl)
+            .await
+            .map_err(idempotency_error)
+    }
+
+    async fn abort(mut self) -> Result<(), Rejection> {
+        let key = self.key.take().expect("key is reserved");
+        self.store.abort(&key).await.map_err(idempotency_error)
+    }
+}
+
+impl<S: IdempotencyStore> Drop for Reservation<S> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            log::debug!("idempotency key {:?} released by a dropped request", key);
+            let abort = self.store.abort(&key);
+            tokio::spawn(async move {
+                if let Err(err) = abort.await {
+                    log::error!("idempotency store error: {}", err);
+                }
+            });
+        }
+    }
+}
+
+fn status(status: StatusCode) -> Response {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = status;
+    res
+}
+
+fn idempotency_error(err: BoxError) -> Rejection {
+    log::error!("idempotency store error: {}", err);
+    reject::known(IdempotencyError { cause: err })
+}
+
+/// An error used in rejections when an [`IdempotencyStore`] fails.
+#[derive(Debug)]
+pub struct IdempotencyError {
+    cause: BoxError,
+}
+
+impl fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Idempotency store error: {}", self.cause)
+    }
+}
+
+impl StdError for IdempotencyError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.cause)
+    }
+}
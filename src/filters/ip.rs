@@ -0,0 +1,215 @@
+//! IP address filters.
+//!
+//! [`restrict`] lets through the requests of clients in allowed networks,
+//! and rejects the others with `403 Forbidden`, such as an admin route only
+//! reachable from an office network, or a denylist of abusive clients.
+//!
+//! Networks are addresses like `10.1.2.3`, or CIDR blocks like `10.0.0.0/8`
+//! or `2001:db8::/32`. Behind a reverse proxy, the address of the client is
+//! read from the `Forwarded` or `X-Forwarded-For` header of the requests
+//! coming from trusted proxies, as with [`uri::absolute`](crate::uri::absolute).
+//!
+//! The networks can be changed while the server runs, through a [`Handle`].
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use futures::future;
+
+use crate::filter::{FilterBase, Internal};
+use crate::filters::uri::Proxies;
+use crate::filters::when::{in_net, parse_net};
+use crate::reject::{self, Rejection};
+use crate::route;
+
+/// Creates a `Filter` that lets through requests from clients in the
+/// networks of `allow`, but not in those of `deny`, and rejects the others
+/// with `403 Forbidden`.
+///
+/// An empty `allow` allows every client not denied. Requests without a
+/// client address, like those of `warp::test` by default, are only let
+/// through if `allow` is empty.
+///
+/// It extracts nothing.
+///
+/// # Panics
+///
+/// This function panics if a network isn't a valid address or CIDR block.
+///
+/// # Example
+///
+/// ```
+/// use std::net::Ipv4Addr;
+/// use warp::Filter;
+///
+/// let restrict = warp::ip::restrict(&["10.0.0.0/8"], &["10.6.6.0/24"])
+///     .trust(Ipv4Addr::LOCALHOST.into());
+/// let handle = restrict.handle();
+///
+/// let admin = warp::path("admin").and(restrict).map(|| "admin");
+///
+/// // Later on...
+/// handle.deny("10.7.7.7").unwrap();
+/// ```
+pub fn restrict(allow: &[&str], deny: &[&str]) -> Restrict {
+    let rules =
+        Rules::parse(allow, deny).unwrap_or_else(|err| panic!("illegal network: {:?}", err.0));
+    Restrict {
+        rules: Handle {
+            rules: Arc::new(ArcSwap::from_pointee(rules)),
+        },
+        proxies: Arc::new(Proxies::default()),
+    }
+}
+
+/// A `Filter` restricting the clients of routes, created with [`restrict`].
+#[derive(Clone, Debug)]
+pub struct Restrict {
+    rules: Handle,
+    proxies: Arc<Proxies>,
+}
+
+impl Restrict {
+    /// Trust the forwarding headers of requests from the proxy at `addr`.
+    pub fn trust(mut self, addr: IpAddr) -> Self {
+        Arc::make_mut(&mut self.proxies).addrs.push(addr);
+        self
+    }
+
+    /// Trust the forwarding headers of every request.
+    ///
+    /// This should only be used when the server can only be reached
+    /// through proxies, as anyone could pretend to be a proxy otherwise.
+    pub fn trust_all(mut self) -> Self {
+        Arc::make_mut(&mut self.proxies).all = true;
+        self
+    }
+
+    /// A handle to change the networks of this filter, and of its clones.
+    pub fn handle(&self) -> Handle {
+        self.rules.clone()
+    }
+}
+
+impl FilterBase for Restrict {
+    type Extract = ();
+    type Error = Rejection;
+    type Future = future::Ready<Result<Self::Extract, Self::Error>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let ip = route::with(|route| self.proxies.client_ip(route));
+        if self.rules.rules.load().allows(ip) {
+            future::ok(())
+        } else {
+            log::debug!("ip::restrict: forbidden client {:?}", ip);
+            future::err(reject::known(IpForbidden { _p: () }))
+        }
+    }
+}
+
+/// A handle changing the networks of a [`restrict`] filter while it is
+/// served.
+///
+/// Changes apply to the requests arriving from then on.
+#[derive(Clone)]
+pub struct Handle {
+    rules: Arc<ArcSwap<Rules>>,
+}
+
+impl Handle {
+    /// Replace the allowed and denied networks.
+    ///
+    /// Nothing is changed if a network is invalid.
+    pub fn set(&self, allow: &[&str], deny: &[&str]) -> Result<(), InvalidNetwork> {
+        let rules = Rules::parse(allow, deny)?;
+        self.rules.store(Arc::new(rules));
+        Ok(())
+    }
+
+    /// Add a network to the allowed networks.
+    pub fn allow(&self, net: &str) -> Result<(), InvalidNetwork> {
+        let net = Rules::parse_net(net)?;
+        self.rules.rcu(|rules| {
+            let mut rules = Rules::clone(rules);
+            rules.allow.push(net);
+            rules
+        });
+        Ok(())
+    }
+
+    /// Add a network to the denied networks.
+    pub fn deny(&self, net: &str) -> Result<(), InvalidNetwork> {
+        let net = Rules::parse_net(net)?;
+        self.rules.rcu(|rules| {
+            let mut rules = Rules::clone(rules);
+            rules.deny.push(net);
+            rules
+        });
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules = self.rules.load();
+        f.debug_struct("Handle")
+            .field("allow", &rules.allow)
+            .field("deny", &rules.deny)
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rules {
+    allow: Vec<(IpAddr, u8)>,
+    deny: Vec<(IpAddr, u8)>,
+}
+
+impl Rules {
+    fn parse(allow: &[&str], deny: &[&str]) -> Result<Rules, InvalidNetwork> {
+        let parse_all = |nets: &[&str]| {
+            nets.iter()
+                .map(|net| Rules::parse_net(net))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Rules {
+            allow: parse_all(allow)?,
+            deny: parse_all(deny)?,
+        })
+    }
+
+    fn parse_net(net: &str) -> Result<(IpAddr, u8), InvalidNetwork> {
+        parse_net(net).ok_or_else(|| InvalidNetwork(net.to_owned()))
+    }
+
+    fn allows(&self, ip: Option<IpAddr>) -> bool {
+        let ip = match ip {
+            Some(ip) => ip,
+            None => return self.allow.is_empty(),
+        };
+        let matches = |nets: &[(IpAddr, u8)]| nets.iter().any(|&(net, p)| in_net(ip, net, p));
+        !matches(&self.deny) && (self.allow.is_empty() || matches(&self.allow))
+    }
+}
+
+/// An error changing the networks of a [`Handle`], with a network that
+/// isn't a valid address or CIDR block.
+#[derive(Debug)]
+pub struct InvalidNetwork(String);
+
+impl fmt::Display for InvalidNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid network: {:?}", self.0)
+    }
+}
+
+impl StdError for InvalidNetwork {}
+
+unit_error! {
+    /// An error used to reject requests of clients that aren't allowed by
+    /// [`restrict`].
+    pub IpForbidden: "Client address forbidden"
+}
@@ -0,0 +1,586 @@
+//! Limit Filters
+//!
+//! Filters bounding the work a server takes on, so that a slow downstream
+//! service can't pile up requests until the runtime is exhausted, and a
+//! single client can't take more than its share.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use http::StatusCode;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+
+/// Create a wrapping filter that bounds the number of requests in flight to
+/// `max`.
+///
+/// Requests arriving while `max` requests are already being handled are
+/// shed: they are replied to right away with `503 Service Unavailable` and
+/// a `Retry-After` header, without running the wrapped filter.
+///
+/// The limit is shared by every filter wrapped with the same `Concurrency`,
+/// or a clone of it. Wrapping all the routes of a server bounds them
+/// globally, while wrapping a single route only bounds that route.
+///
+/// Since every request reaching the wrapped filter takes a slot, the limit
+/// is best applied after the path of a route has been matched.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let slow = warp::path("reports")
+///     .and(warp::any().map(|| "report").with(warp::limit::concurrency(64)));
+///
+/// let routes = slow.or(warp::any().map(|| "hello"));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `max` is `0`.
+pub fn concurrency(max: usize) -> Concurrency {
+    assert!(max > 0, "illegal concurrency limit: 0");
+    Concurrency {
+        max,
+        in_flight: Arc::new(AtomicUsize::new(0)),
+        retry_after: Duration::from_secs(1),
+    }
+}
+
+/// Wrapper bounding the requests in flight, see [`concurrency`].
+#[derive(Clone)]
+pub struct Concurrency {
+    max: usize,
+    in_flight: Arc<AtomicUsize>,
+    retry_after: Duration,
+}
+
+impl Concurrency {
+    /// Sets the delay advertised in the `Retry-After` header of shed
+    /// requests, rounded up to whole seconds.
+    ///
+    /// Defaults to 1 second.
+    pub fn retry_after(mut self, delay: Duration) -> Self {
+        self.retry_after = delay;
+        self
+    }
+
+    /// The number of requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    fn acquire(&self) -> Option<Permit> {
+        let mut current = self.in_flight.load(Ordering::Acquire);
+        loop {
+            if current >= self.max {
+                return None;
+            }
+            match self.in_flight.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    return Some(Permit {
+                        in_flight: self.in_flight.clone(),
+                    })
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn shed(&self) -> Response {
+        let mut secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs += 1;
+        }
+        let mut res = Response::default();
+        *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(secs.max(1)));
+        res
+    }
+}
+
+impl fmt::Debug for Concurrency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Concurrency")
+            .field("max", &self.max)
+            .field("in_flight", &self.in_flight())
+            .field("retry_after", &self.retry_after)
+            .finish()
+    }
+}
+
+// A slot of a `Concurrency`, released when dropped.
+struct Permit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<F> WrapSealed<F> for Concurrency
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithConcurrency<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithConcurrency {
+            filter,
+            concurrency: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`concurrency`].
+#[derive(Clone, Debug)]
+pub struct WithConcurrency<F> {
+    filter: F,
+    concurrency: Concurrency,
+}
+
+impl<F> FilterBase for WithConcurrency<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let permit = match self.concurrency.acquire() {
+            Some(permit) => permit,
+            None => {
+                log::debug!(
+                    "concurrency limit of {} reached, shedding request",
+                    self.concurrency.max
+                );
+                let res = self.concurrency.shed();
+                return Box::pin(async move { Ok((res,)) });
+            }
+        };
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let result = fut.await;
+            drop(permit);
+            result
+                .map(|reply| (reply.into_response(),))
+                .map_err(Into::into)
+        })
+    }
+}
+
+/// Create a wrapping filter limiting the requests of each principal, such
+/// as a user or an API key, to the [`Quota`] of its plan.
+///
+/// The principal is extracted by `key`, which runs before the wrapped
+/// filter: requests it rejects are rejected the same way, without being
+/// counted. `quotas` gives the quota of each principal, and principals
+/// without a quota aren't limited.
+///
+/// Requests are counted in fixed windows starting with the first request of
+/// a principal, or with the first request after its quota changed. Once a
+/// principal has made the `limit` requests of its window, its requests are
+/// rejected with a [`TooManyRequests`] until the window ends, without
+/// running the wrapped filter. It is replied to with `429 Too Many
+/// Requests` and a `Retry-After` header.
+///
+/// Only requests replied by the wrapped filter are counted: the requests it
+/// rejects, such as those for another path, are given back to the quota.
+/// Since the rejection of limited principals is preferred over `404 Not
+/// Found`, routes combined with `or` still fall through to the next route
+/// when a throttled route doesn't match, but a throttled route matching
+/// the request is replied to with `429`.
+///
+/// Responses to limited principals, and their `429` rejections, have the
+/// headers:
+///
+/// - `X-RateLimit-Limit`: the number of requests allowed per window,
+/// - `X-RateLimit-Remaining`: the number of requests left in the window,
+/// - `X-RateLimit-Reset`: the number of seconds until the window ends.
+///
+/// The counts are shared by every filter wrapped with the same `Throttle`,
+/// or a clone of it.
+///
+/// # Example
+///
+/// ```
+/// use warp::limit::Quota;
+/// use warp::Filter;
+///
+/// #[derive(Clone, PartialEq, Eq, Hash)]
+/// struct ApiKey(String);
+///
+/// let key = warp::header::<String>("x-api-key").map(ApiKey);
+/// let quotas = |key: &ApiKey| {
+///     if key.0.starts_with("pro_") {
+///         Some(Quota::per_minute(1_000))
+///     } else {
+///         Some(Quota::per_minute(60))
+///     }
+/// };
+///
+/// let search = warp::path("search")
+///     .map(|| "results")
+///     .with(warp::limit::throttle(key, quotas));
+/// let health = warp::path("health").map(|| "ok");
+///
+/// // `/health` isn't limited, nor counted against quotas.
+/// let routes = search.or(health);
+/// ```
+pub fn throttle<K, Q, P>(key: K, quotas: Q) -> Throttle<K, Q, P>
+where
+    K: Filter<Extract = (P,)>,
+    Q: Quotas<P>,
+    P: Hash + Eq + Clone,
+{
+    Throttle {
+        key,
+        quotas: Arc::new(quotas),
+        windows: Arc::new(Mutex::new(Windows {
+            by_principal: HashMap::new(),
+            checks: 0,
+        })),
+    }
+}
+
+/// A number of requests allowed per window of time, for [`throttle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    limit: u64,
+    window: Duration,
+}
+
+impl Quota {
+    /// A quota of `limit` requests per `window`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new(limit: u64, window: Duration) -> Quota {
+        assert!(window > Duration::from_secs(0), "illegal quota window: 0");
+        Quota { limit, window }
+    }
+
+    /// A quota of `limit` requests per second.
+    pub fn per_second(limit: u64) -> Quota {
+        Quota::new(limit, Duration::from_secs(1))
+    }
+
+    /// A quota of `limit` requests per minute.
+    pub fn per_minute(limit: u64) -> Quota {
+        Quota::new(limit, Duration::from_secs(60))
+    }
+
+    /// A quota of `limit` requests per hour.
+    pub fn per_hour(limit: u64) -> Quota {
+        Quota::new(limit, Duration::from_secs(60 * 60))
+    }
+
+    /// The number of requests allowed per window.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// The length of a window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+/// The quotas of principals, usually by the tier of their plan, for
+/// [`throttle`].
+///
+/// It is implemented by functions from a principal to its quota.
+pub trait Quotas<P>: Send + Sync + 'static {
+    /// The quota of `principal`, or `None` if it isn't limited.
+    fn quota(&self, principal: &P) -> Option<Quota>;
+}
+
+impl<P, F> Quotas<P> for F
+where
+    F: Fn(&P) -> Option<Quota> + Send + Sync + 'static,
+{
+    fn quota(&self, principal: &P) -> Option<Quota> {
+        self(principal)
+    }
+}
+
+/// Wrapper limiting the requests of principals, see [`throttle`].
+pub struct Throttle<K, Q, P> {
+    key: K,
+    quotas: Arc<Q>,
+    windows: Arc<Mutex<Windows<P>>>,
+}
+
+struct Windows<P> {
+    by_principal: HashMap<P, Window>,
+    // Checks since the ended windows were last removed.
+    checks: u64,
+}
+
+struct Window {
+    quota: Quota,
+    ends: Instant,
+    count: u64,
+}
+
+// The outcome of counting a request against a quota.
+struct Usage {
+    allowed: bool,
+    limit: u64,
+    remaining: u64,
+    ends: Instant,
+    reset: Duration,
+}
+
+impl<K, Q, P> Throttle<K, Q, P>
+where
+    P: Hash + Eq,
+{
+    // Counts a request of `principal`, if its quota allows it.
+    fn check(&self, principal: P, quota: Quota) -> Usage {
+        let now = tokio::time::Instant::now().into_std();
+        let mut windows = self.windows.lock().unwrap();
+        windows.checks += 1;
+        if windows.checks >= 1024 {
+            windows.checks = 0;
+            windows.by_principal.retain(|_, window| window.ends > now);
+        }
+        let new_window = || Window {
+            quota,
+            ends: now + quota.window,
+            count: 0,
+        };
+        let window = windows
+            .by_principal
+            .entry(principal)
+            .or_insert_with(new_window);
+        // A window ends early when the quota changes, such as when the plan
+        // of the principal was downgraded.
+        if window.ends <= now || window.quota != quota {
+            *window = new_window();
+        }
+        let allowed = window.count < quota.limit;
+        if allowed {
+            window.count += 1;
+        }
+        Usage {
+            allowed,
+            limit: quota.limit,
+            remaining: quota.limit.saturating_sub(window.count),
+            ends: window.ends,
+            reset: window.ends - now,
+        }
+    }
+
+    // Gives back a request counted by `check`, unless its window ended.
+    fn refund(&self, principal: &P, usage: &Usage) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some(window) = windows.by_principal.get_mut(principal) {
+            if window.ends == usage.ends {
+                window.count = window.count.saturating_sub(1);
+            }
+        }
+    }
+}
+
+impl Usage {
+    fn apply(&self, res: &mut Response) {
+        rate_limit_headers(
+            res.headers_mut(),
+            self.limit,
+            self.remaining,
+            reset_secs(self.reset),
+        );
+    }
+}
+
+// The seconds until a window ends, rounded up.
+fn reset_secs(reset: Duration) -> u64 {
+    let mut secs = reset.as_secs();
+    if reset.subsec_nanos() > 0 {
+        secs += 1;
+    }
+    secs
+}
+
+fn rate_limit_headers(headers: &mut HeaderMap, limit: u64, remaining: u64, reset: u64) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+}
+
+impl<K: Clone, Q, P> Clone for Throttle<K, Q, P> {
+    fn clone(&self) -> Self {
+        Throttle {
+            key: self.key.clone(),
+            quotas: self.quotas.clone(),
+            windows: self.windows.clone(),
+        }
+    }
+}
+
+impl<K, Q, P> fmt::Debug for Throttle<K, Q, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field(
+                "principals",
+                &self.windows.lock().unwrap().by_principal.len(),
+            )
+            .finish()
+    }
+}
+
+impl<F, K, Q, P> WrapSealed<F> for Throttle<K, Q, P>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+    K: Filter<Extract = (P,)> + Clone + Send + Sync + 'static,
+    K::Error: Into<Rejection>,
+    K::Future: Send,
+    Q: Quotas<P>,
+    P: Hash + Eq + Clone + Send + 'static,
+{
+    type Wrapped = WithThrottle<F, K, Q, P>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithThrottle {
+            filter,
+            throttle: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`throttle`].
+#[derive(Debug)]
+pub struct WithThrottle<F, K, Q, P> {
+    filter: F,
+    throttle: Throttle<K, Q, P>,
+}
+
+impl<F: Clone, K: Clone, Q, P> Clone for WithThrottle<F, K, Q, P> {
+    fn clone(&self) -> Self {
+        WithThrottle {
+            filter: self.filter.clone(),
+            throttle: self.throttle.clone(),
+        }
+    }
+}
+
+impl<F, K, Q, P> FilterBase for WithThrottle<F, K, Q, P>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+    K: Filter<Extract = (P,)> + Clone + Send + Sync + 'static,
+    K::Error: Into<Rejection>,
+    K::Future: Send,
+    Q: Quotas<P>,
+    P: Hash + Eq + Clone + Send + 'static,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let key = self.throttle.key.filter(Internal);
+        let throttle = self.throttle.clone();
+        let filter = self.filter.clone();
+
+        Box::pin(async move {
+            let (principal,) = key.await.map_err(Into::into)?;
+            let usage = match throttle.quotas.quota(&principal) {
+                Some(quota) => throttle.check(principal.clone(), quota),
+                None => {
+                    let res = filter.filter(Internal).await.map_err(Into::into)?;
+                    return Ok((res.into_response(),));
+                }
+            };
+            if !usage.allowed {
+                log::debug!("throttle: quota of {} requests exhausted", usage.limit);
+                return Err(crate::reject::known(TooManyRequests {
+                    limit: usage.limit,
+                    reset: usage.reset,
+                }));
+            }
+            match filter.filter(Internal).await {
+                Ok(res) => {
+                    let mut res = res.into_response();
+                    usage.apply(&mut res);
+                    Ok((res,))
+                }
+                Err(err) => {
+                    throttle.refund(&principal, &usage);
+                    Err(err.into())
+                }
+            }
+        })
+    }
+}
+
+/// An error used to reject the requests of principals that exhausted their
+/// quota, with [`throttle`].
+///
+/// It is replied to with `429 Too Many Requests`, and the `Retry-After` and
+/// `X-RateLimit-*` headers.
+#[derive(Debug)]
+pub struct TooManyRequests {
+    limit: u64,
+    reset: Duration,
+}
+
+impl TooManyRequests {
+    /// The number of requests allowed per window.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// The time until the window ends, when requests are allowed again.
+    pub fn retry_after(&self) -> Duration {
+        self.reset
+    }
+
+    pub(crate) fn headers(&self, headers: &mut HeaderMap) {
+        let reset = reset_secs(self.reset);
+        rate_limit_headers(headers, self.limit, 0, reset);
+        headers.insert(RETRY_AFTER, HeaderValue::from(reset.max(1)));
+    }
+}
+
+impl fmt::Display for TooManyRequests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Too many requests")
+    }
+}
+
+impl StdError for TooManyRequests {}
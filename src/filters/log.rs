@@ -1,15 +1,20 @@
 //! Logger Filters
 
 use std::fmt;
-use std::net::SocketAddr;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use http::header::HeaderName;
 use http::{self, header, StatusCode};
+use hyper::body::HttpBody;
 
-use crate::filter::{Filter, WrapSealed};
-use crate::reject::IsReject;
-use crate::reply::Reply;
-use crate::route::Route;
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
 
 use self::internal::WithLog;
 
@@ -37,10 +42,10 @@ pub fn log(name: &'static str) -> Log<impl Fn(Info) + Copy> {
         log::info!(
             target: name,
             "{} \"{} {} {:?}\" {} \"{}\" \"{}\" {:?}",
-            OptFmt(info.route.remote_addr()),
+            OptFmt(info.remote_addr()),
             info.method(),
             info.path(),
-            info.route.version(),
+            info.version(),
             info.status().as_u16(),
             OptFmt(info.referer()),
             OptFmt(info.user_agent()),
@@ -86,14 +91,55 @@ pub struct Log<F> {
 /// Information about the request/response that can be used to prepare log lines.
 #[allow(missing_debug_implementations)]
 pub struct Info<'a> {
-    route: &'a Route,
+    request: Request<'a>,
     start: Instant,
     status: StatusCode,
+    body_size: Option<u64>,
+}
+
+// The request of an `Info`, which is owned by `Parts` if the log line is
+// deferred until the response body has been streamed.
+enum Request<'a> {
+    Route(&'a Route),
+    Parts(&'a Parts),
+}
+
+struct Parts {
+    remote_addr: Option<SocketAddr>,
+    method: http::Method,
+    path: String,
+    version: http::Version,
+    headers: http::HeaderMap,
+    pattern: Option<String>,
+    #[cfg(feature = "geoip")]
+    geo: Option<crate::geoip::Geo>,
+}
+
+impl Parts {
+    fn new(route: &Route) -> Parts {
+        Parts {
+            remote_addr: route.remote_addr(),
+            method: route.method().clone(),
+            path: route.full_path().to_owned(),
+            version: route.version(),
+            headers: route.headers().clone(),
+            pattern: route_pattern(route).map(ToOwned::to_owned),
+            #[cfg(feature = "geoip")]
+            geo: route.extensions().get().cloned(),
+        }
+    }
+}
+
+fn route_pattern(route: &Route) -> Option<&str> {
+    route
+        .extensions()
+        .get::<crate::router::Params>()
+        .and_then(|params| params.pattern())
 }
 
 impl<FN, F> WrapSealed<F> for Log<FN>
 where
-    FN: Fn(Info) + Clone + Send,
+    FN: Fn(Info) + Clone + Send + 'static,
     F: Filter + Clone + Send,
     F::Extract: Reply,
     F::Error: IsReject,
@@ -111,22 +157,53 @@ where
 impl<'a> Info<'a> {
     /// View the remote `SocketAddr` of the request.
     pub fn remote_addr(&self) -> Option<SocketAddr> {
-        self.route.remote_addr()
+        match self.request {
+            Request::Route(route) => route.remote_addr(),
+            Request::Parts(parts) => parts.remote_addr,
+        }
     }
 
     /// View the `http::Method` of the request.
     pub fn method(&self) -> &http::Method {
-        self.route.method()
+        match self.request {
+            Request::Route(route) => route.method(),
+            Request::Parts(parts) => &parts.method,
+        }
     }
 
     /// View the URI path of the request.
     pub fn path(&self) -> &str {
-        self.route.full_path()
+        match self.request {
+            Request::Route(route) => route.full_path(),
+            Request::Parts(parts) => &parts.path,
+        }
     }
 
     /// View the `http::Version` of the request.
     pub fn version(&self) -> http::Version {
-        self.route.version()
+        match self.request {
+            Request::Route(route) => route.version(),
+            Request::Parts(parts) => parts.version,
+        }
+    }
+
+    /// View the pattern of the route that matched the request, such as
+    /// `/hello/:name`, if it was dispatched by a [`Router`](crate::router::Router).
+    pub fn route_pattern(&self) -> Option<&str> {
+        match self.request {
+            Request::Route(route) => route_pattern(route),
+            Request::Parts(parts) => parts.pattern.as_deref(),
+        }
+    }
+
+    /// View the country and autonomous system of the client, if they were
+    /// looked up by a [`GeoIp`](crate::geoip::GeoIp) filter.
+    #[cfg(feature = "geoip")]
+    pub fn geo(&self) -> Option<&crate::geoip::Geo> {
+        match self.request {
+            Request::Route(route) => route.extensions().get(),
+            Request::Parts(parts) => parts.geo.as_ref(),
+        }
     }
 
     /// View the `http::StatusCode` of the response.
@@ -134,38 +211,302 @@ impl<'a> Info<'a> {
         self.status
     }
 
+    /// View the size in bytes of the response body.
+    ///
+    /// If the size isn't known ahead of time, the bytes are counted as the
+    /// body is streamed, and the log function is only called once the body
+    /// has been written, or dropped early. This is `None` for rejections.
+    pub fn body_size(&self) -> Option<u64> {
+        self.body_size
+    }
+
     /// View the referer of the request.
     pub fn referer(&self) -> Option<&str> {
-        self.route
-            .headers()
+        self.request_headers()
             .get(header::REFERER)
             .and_then(|v| v.to_str().ok())
     }
 
     /// View the user agent of the request.
     pub fn user_agent(&self) -> Option<&str> {
-        self.route
-            .headers()
+        self.request_headers()
             .get(header::USER_AGENT)
             .and_then(|v| v.to_str().ok())
     }
 
     /// View the `Duration` that elapsed for the request.
+    ///
+    /// For bodies streamed before logging, this includes the time it took to
+    /// write the body.
     pub fn elapsed(&self) -> Duration {
         tokio::time::Instant::now().into_std() - self.start
     }
 
     /// View the host of the request
     pub fn host(&self) -> Option<&str> {
-        self.route
-            .headers()
+        self.request_headers()
             .get(header::HOST)
             .and_then(|v| v.to_str().ok())
     }
 
     /// Access the full headers of the request
     pub fn request_headers(&self) -> &http::HeaderMap {
-        self.route.headers()
+        match self.request {
+            Request::Route(route) => route.headers(),
+            Request::Parts(parts) => &parts.headers,
+        }
+    }
+}
+
+/// Create a wrapping filter that logs a structured record of each request.
+///
+/// By default, records are emitted as `tracing` events at the `INFO` level
+/// with the `warp::access` target. Each event has the fields of the record,
+/// and the record as JSON as its message. Use
+/// [`sink`](Structured::sink) to send records somewhere else instead.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path!("users" / u32)
+///     .map(|id| format!("user #{}", id))
+///     .with(warp::log::structured().route("/users/:id"));
+///
+/// let custom = warp::any()
+///     .map(warp::reply)
+///     .with(warp::log::structured().sink(|record| {
+///         println!("{}", record.to_json());
+///     }));
+/// ```
+pub fn structured() -> Structured {
+    Structured {
+        route: None,
+        request_id_header: HeaderName::from_static("x-request-id"),
+        sink: None,
+    }
+}
+
+type Sink = Arc<dyn Fn(&Record) + Send + Sync>;
+
+/// A wrapping filter logging [`Record`]s, see [`structured`].
+#[derive(Clone)]
+pub struct Structured {
+    route: Option<Arc<str>>,
+    request_id_header: HeaderName,
+    sink: Option<Sink>,
+}
+
+impl Structured {
+    /// Sets the normalized route of the records, such as the path pattern
+    /// of the wrapped filter, instead of the raw path of each request.
+    pub fn route(mut self, route: impl Into<String>) -> Self {
+        self.route = Some(route.into().into());
+        self
+    }
+
+    /// Sets the header holding the id of the request.
+    ///
+    /// Defaults to `x-request-id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid header name.
+    pub fn request_id_header(mut self, name: &str) -> Self {
+        self.request_id_header =
+            HeaderName::from_bytes(name.as_bytes()).expect("illegal header name");
+        self
+    }
+
+    /// Sends the records to a function, instead of `tracing` events.
+    pub fn sink<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&Record) + Send + Sync + 'static,
+    {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    fn emit(&self, record: &Record) {
+        match self.sink {
+            Some(ref sink) => sink(record),
+            None => tracing::info!(
+                target: "warp::access",
+                method = %record.method,
+                route = %record.route,
+                status = record.status.as_u16(),
+                latency_ms = record.latency_ms(),
+                bytes = record.bytes,
+                remote_ip = record.remote_ip.map(tracing::field::display),
+                request_id = record.request_id.as_deref(),
+                "{}",
+                record.to_json(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for Structured {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Structured")
+            .field("route", &self.route)
+            .field("request_id_header", &self.request_id_header)
+            .finish()
+    }
+}
+
+impl<F> WrapSealed<F> for Structured
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithStructured<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithStructured {
+            filter,
+            structured: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`structured`].
+#[derive(Clone, Debug)]
+pub struct WithStructured<F> {
+    filter: F,
+    structured: Structured,
+}
+
+impl<F> FilterBase for WithStructured<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let started = tokio::time::Instant::now().into_std();
+        let structured = self.structured.clone();
+        let (method, route, remote_ip, request_id) = route::with(|route| {
+            let request_id = route
+                .headers()
+                .get(&structured.request_id_header)
+                .and_then(|v| v.to_str().ok())
+                .map(ToOwned::to_owned);
+            let path = match structured.route {
+                Some(ref name) => name.to_string(),
+                None => route.full_path().to_owned(),
+            };
+            (
+                route.method().clone(),
+                path,
+                route.remote_addr().map(|addr| addr.ip()),
+                request_id,
+            )
+        });
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let result = fut.await.map(Reply::into_response).map_err(Into::into);
+            let (status, bytes) = match result {
+                Ok(ref res) => {
+                    let bytes = res
+                        .headers()
+                        .get(header::CONTENT_LENGTH)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse().ok())
+                        .or_else(|| res.body().size_hint().exact());
+                    (res.status(), bytes)
+                }
+                Err(ref rejection) => (rejection.status(), None),
+            };
+            structured.emit(&Record {
+                method,
+                route,
+                status,
+                latency: tokio::time::Instant::now().into_std() - started,
+                bytes,
+                remote_ip,
+                request_id,
+            });
+            result.map(|res| (res,))
+        })
+    }
+}
+
+/// A structured record of a request, logged by [`structured`].
+#[derive(Clone, Debug)]
+pub struct Record {
+    method: http::Method,
+    route: String,
+    status: StatusCode,
+    latency: Duration,
+    bytes: Option<u64>,
+    remote_ip: Option<IpAddr>,
+    request_id: Option<String>,
+}
+
+impl Record {
+    /// The method of the request.
+    pub fn method(&self) -> &http::Method {
+        &self.method
+    }
+
+    /// The normalized route of the request, or else its path.
+    pub fn route(&self) -> &str {
+        &self.route
+    }
+
+    /// The status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The time it took to reply.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// The length of the response body, if known.
+    pub fn bytes(&self) -> Option<u64> {
+        self.bytes
+    }
+
+    /// The IP address of the client, if known.
+    pub fn remote_ip(&self) -> Option<IpAddr> {
+        self.remote_ip
+    }
+
+    /// The id of the request, from its request id header.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// The record as a JSON object.
+    ///
+    /// The keys are `method`, `route`, `status`, `latency_ms`, `bytes`,
+    /// `remote_ip` and `request_id`, the last three being `null` if unknown.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "method": self.method.as_str(),
+            "route": self.route,
+            "status": self.status.as_u16(),
+            "latency_ms": self.latency_ms(),
+            "bytes": self.bytes,
+            "remote_ip": self.remote_ip.map(|ip| ip.to_string()),
+            "request_id": self.request_id,
+        })
+    }
+
+    fn latency_ms(&self) -> f64 {
+        self.latency.as_secs_f64() * 1000.0
     }
 }
 
@@ -187,10 +528,13 @@ mod internal {
     use std::task::{Context, Poll};
     use std::time::Instant;
 
-    use futures::{ready, TryFuture};
+    use futures::{ready, Stream, TryFuture};
+    use http::StatusCode;
+    use hyper::body::{Bytes, HttpBody};
+    use hyper::Body;
     use pin_project::pin_project;
 
-    use super::{Info, Log};
+    use super::{Info, Log, Parts, Request};
     use crate::filter::{Filter, FilterBase, Internal};
     use crate::reject::IsReject;
     use crate::reply::{Reply, Response};
@@ -215,7 +559,7 @@ mod internal {
 
     impl<FN, F> FilterBase for WithLog<FN, F>
     where
-        FN: Fn(Info) + Clone + Send,
+        FN: Fn(Info) + Clone + Send + 'static,
         F: Filter + Clone + Send,
         F::Extract: Reply,
         F::Error: IsReject,
@@ -245,7 +589,7 @@ mod internal {
 
     impl<FN, F> Future for WithLogFuture<FN, F>
     where
-        FN: Fn(Info),
+        FN: Fn(Info) + Clone + Send + 'static,
         F: TryFuture,
         F::Ok: Reply,
         F::Error: IsReject,
@@ -254,27 +598,101 @@ mod internal {
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
             let pin = self.as_mut().project();
-            let (result, status) = match ready!(pin.future.try_poll(cx)) {
+            let (result, status, body_size) = match ready!(pin.future.try_poll(cx)) {
                 Ok(reply) => {
                     let resp = reply.into_response();
                     let status = resp.status();
-                    (Poll::Ready(Ok((Logged(resp),))), status)
+                    match HttpBody::size_hint(resp.body()).exact() {
+                        Some(size) => (Ok(resp), status, Some(size)),
+                        None => {
+                            // The log line waits for the body to be counted.
+                            let pending = route::with(|route| Pending {
+                                log: self.log.clone(),
+                                parts: Parts::new(route),
+                                start: self.started,
+                                status,
+                                bytes: 0,
+                            });
+                            let (head, body) = resp.into_parts();
+                            let body = Body::wrap_stream(Counted {
+                                body,
+                                pending: Some(pending),
+                            });
+                            return Poll::Ready(Ok((Logged(Response::from_parts(head, body)),)));
+                        }
+                    }
                 }
                 Err(reject) => {
                     let status = reject.status();
-                    (Poll::Ready(Err(reject)), status)
+                    (Err(reject), status, None)
                 }
             };
 
             route::with(|route| {
                 (self.log.func)(Info {
-                    route,
+                    request: Request::Route(route),
                     start: self.started,
                     status,
+                    body_size,
                 });
             });
 
-            result
+            Poll::Ready(result.map(|resp| (Logged(resp),)))
+        }
+    }
+
+    // A response body counting the bytes written, that logs once it ends,
+    // or is dropped.
+    #[pin_project]
+    struct Counted<FN: Fn(Info)> {
+        #[pin]
+        body: Body,
+        pending: Option<Pending<FN>>,
+    }
+
+    impl<FN> Stream for Counted<FN>
+    where
+        FN: Fn(Info),
+    {
+        type Item = Result<Bytes, hyper::Error>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+            let pin = self.project();
+            let item = ready!(pin.body.poll_data(cx));
+            match item {
+                Some(Ok(ref chunk)) => {
+                    if let Some(ref mut pending) = pin.pending {
+                        pending.bytes += chunk.len() as u64;
+                    }
+                }
+                Some(Err(_)) => {}
+                None => {
+                    pin.pending.take();
+                }
+            }
+            Poll::Ready(item)
+        }
+    }
+
+    struct Pending<FN: Fn(Info)> {
+        log: Log<FN>,
+        parts: Parts,
+        start: Instant,
+        status: StatusCode,
+        bytes: u64,
+    }
+
+    impl<FN> Drop for Pending<FN>
+    where
+        FN: Fn(Info),
+    {
+        fn drop(&mut self) {
+            (self.log.func)(Info {
+                request: Request::Parts(&self.parts),
+                start: self.start,
+                status: self.status,
+                body_size: Some(self.bytes),
+            });
         }
     }
 }
@@ -0,0 +1,164 @@
+//! Maintenance Mode
+//!
+//! A [`Maintenance`] switch can be turned on and off while the server runs,
+//! to drain some routes, such as during a migration of the data they use,
+//! without restarting the process.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::{HeaderValue, RETRY_AFTER};
+use http::StatusCode;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+
+/// Create a maintenance switch, off at first.
+///
+/// Wrapping routes with the switch, or clones of it, puts them in
+/// maintenance while it's on: requests reaching them are replied to right
+/// away with `503 Service Unavailable` and a `Retry-After` header, without
+/// running the wrapped filter. Requests already being handled complete.
+///
+/// Like other wrappers, it's best applied after the path of a route has been
+/// matched, so unrelated requests keep being rejected with a `404`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let reports = warp::maintenance::switch();
+///
+/// let routes = warp::path("reports")
+///     .and(warp::any().map(|| "report").with(reports.clone()))
+///     .or(warp::any().map(|| "hello"));
+///
+/// // From an admin endpoint or a signal handler...
+/// reports.enable();
+/// ```
+pub fn switch() -> Maintenance {
+    Maintenance {
+        enabled: Arc::new(AtomicBool::new(false)),
+        retry_after: Duration::from_secs(60),
+    }
+}
+
+/// Wrapper and handle of a maintenance switch, see [`switch`].
+#[derive(Clone)]
+pub struct Maintenance {
+    enabled: Arc<AtomicBool>,
+    retry_after: Duration,
+}
+
+impl Maintenance {
+    /// Sets the delay advertised in the `Retry-After` header, rounded up to
+    /// whole seconds.
+    ///
+    /// Defaults to 60 seconds.
+    pub fn retry_after(mut self, delay: Duration) -> Self {
+        self.retry_after = delay;
+        self
+    }
+
+    /// Put the wrapped routes in maintenance.
+    pub fn enable(&self) {
+        self.set(true);
+    }
+
+    /// Serve the wrapped routes again.
+    pub fn disable(&self) {
+        self.set(false);
+    }
+
+    /// Turn the switch on or off.
+    pub fn set(&self, enabled: bool) {
+        let was_enabled = self.enabled.swap(enabled, Ordering::AcqRel);
+        if was_enabled != enabled {
+            log::info!(
+                "maintenance {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+    }
+
+    /// Whether the wrapped routes are in maintenance.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    fn unavailable(&self) -> Response {
+        let mut secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs += 1;
+        }
+        let mut res = Response::default();
+        *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(secs.max(1)));
+        res
+    }
+}
+
+impl fmt::Debug for Maintenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Maintenance")
+            .field("enabled", &self.is_enabled())
+            .field("retry_after", &self.retry_after)
+            .finish()
+    }
+}
+
+impl<F> WrapSealed<F> for Maintenance
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithMaintenance<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithMaintenance {
+            filter,
+            maintenance: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with a maintenance [`switch`].
+#[derive(Clone, Debug)]
+pub struct WithMaintenance<F> {
+    filter: F,
+    maintenance: Maintenance,
+}
+
+impl<F> FilterBase for WithMaintenance<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        if self.maintenance.is_enabled() {
+            let res = self.maintenance.unavailable();
+            return Box::pin(async move { Ok((res,)) });
+        }
+        let fut = self.filter.filter(Internal);
+        Box::pin(async move {
+            fut.await
+                .map(|reply| (reply.into_response(),))
+                .map_err(Into::into)
+        })
+    }
+}
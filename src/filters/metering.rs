@@ -0,0 +1,355 @@
+//! Usage metering
+//!
+//! A metering [`wrap`] reports the [`Usage`] of every request replied by the
+//! routes it wraps to a [`UsageSink`]: the route and principal it was made
+//! for, and the bytes of its request and response bodies. Sinks receive
+//! them in the background, to aggregate them for a billing pipeline, or to
+//! send them to a queue, without delaying responses.
+//!
+//! Usage is reported once the response body has been sent, or the client
+//! went away, so streamed bodies are counted as they were written.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::metering::Usage;
+//! use warp::Filter;
+//!
+//! #[derive(Clone)]
+//! struct Account(String);
+//!
+//! let metering = warp::metering::wrap(|usage: Usage| async move {
+//!     println!(
+//!         "{} {:?}: {} bytes",
+//!         usage.route(),
+//!         usage.principal(),
+//!         usage.response_bytes(),
+//!     );
+//! })
+//! .route("/reports/:id")
+//! .principal(|account: &Account| account.0.clone());
+//!
+//! let route = warp::path!("reports" / u32)
+//!     .and(warp::ext::provide(warp::auth::bearer().map(Account)))
+//!     .map(|id| format!("report #{}", id))
+//!     .with(metering);
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::{ready, Stream};
+use http::{Extensions, Method, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
+use pin_project::pin_project;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route;
+
+type Principal = Arc<dyn Fn(&Extensions) -> Option<String> + Send + Sync>;
+
+/// Create a wrapping filter reporting the usage of each replied request to
+/// `sink`.
+///
+/// Requests rejected by the wrapped filter aren't reported, since another
+/// route may still handle them.
+pub fn wrap<S>(sink: S) -> Metering<S>
+where
+    S: UsageSink,
+{
+    Metering {
+        sink: Arc::new(sink),
+        route: None,
+        principal: None,
+    }
+}
+
+/// A receiver of the [`Usage`] of requests, for [`wrap`].
+///
+/// It is implemented by functions taking a `Usage`, and returning a future.
+pub trait UsageSink: Send + Sync + 'static {
+    /// The future handling a `Usage`.
+    type Future: Future<Output = ()> + Send + 'static;
+
+    /// Handle the usage of a request.
+    ///
+    /// The returned future is spawned, so it doesn't delay responses.
+    fn record(&self, usage: Usage) -> Self::Future;
+}
+
+impl<F, Fut> UsageSink for F
+where
+    F: Fn(Usage) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    type Future = Fut;
+
+    fn record(&self, usage: Usage) -> Self::Future {
+        self(usage)
+    }
+}
+
+/// Wrapper reporting the usage of requests, see [`wrap`].
+pub struct Metering<S> {
+    sink: Arc<S>,
+    route: Option<Arc<str>>,
+    principal: Option<Principal>,
+}
+
+impl<S> Metering<S> {
+    /// Names the wrapped route in reports, such as with its path pattern.
+    ///
+    /// By default, the path of each request is reported.
+    pub fn route(mut self, name: impl Into<String>) -> Self {
+        self.route = Some(name.into().into());
+        self
+    }
+
+    /// Reports the principal of requests, from the extension of type `T`,
+    /// such as one inserted by [`ext::provide`](crate::ext::provide).
+    ///
+    /// It is read once the wrapped filter has run, so extensions inserted
+    /// by the wrapped filter are found.
+    pub fn principal<T, F>(mut self, principal: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.principal = Some(Arc::new(move |ext| ext.get::<T>().map(&principal)));
+        self
+    }
+}
+
+impl<S> Clone for Metering<S> {
+    fn clone(&self) -> Self {
+        Metering {
+            sink: self.sink.clone(),
+            route: self.route.clone(),
+            principal: self.principal.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for Metering<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metering")
+            .field("route", &self.route)
+            .finish()
+    }
+}
+
+impl<F, S> WrapSealed<F> for Metering<S>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+    S: UsageSink,
+{
+    type Wrapped = WithMetering<F, S>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithMetering {
+            filter,
+            metering: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`wrap`].
+#[derive(Debug)]
+pub struct WithMetering<F, S> {
+    filter: F,
+    metering: Metering<S>,
+}
+
+impl<F: Clone, S> Clone for WithMetering<F, S> {
+    fn clone(&self) -> Self {
+        WithMetering {
+            filter: self.filter.clone(),
+            metering: self.metering.clone(),
+        }
+    }
+}
+
+impl<F, S> FilterBase for WithMetering<F, S>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+    S: UsageSink,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let metering = self.metering.clone();
+        let filter = self.filter.clone();
+        let request_bytes = Arc::new(AtomicU64::new(0));
+        let (method, route) = route::with(|route| {
+            if let Some(body) = route.take_body() {
+                route.restore_body(Body::wrap_stream(Counted {
+                    body,
+                    bytes: request_bytes.clone(),
+                }));
+            }
+            let name = match metering.route {
+                Some(ref name) => name.to_string(),
+                None => route.full_path().to_owned(),
+            };
+            (route.method().clone(), name)
+        });
+
+        Box::pin(async move {
+            let res = filter
+                .filter(Internal)
+                .await
+                .map_err(Into::into)?
+                .into_response();
+            let principal = metering
+                .principal
+                .as_ref()
+                .and_then(|principal| route::with(|route| principal(route.extensions())));
+
+            let (parts, body) = res.into_parts();
+            let response_bytes = Arc::new(AtomicU64::new(0));
+            let pending = Pending {
+                sink: metering.sink,
+                usage: Some(Usage {
+                    method,
+                    route,
+                    principal,
+                    status: parts.status,
+                    request_bytes: 0,
+                    response_bytes: 0,
+                }),
+                request_bytes,
+                response_bytes: response_bytes.clone(),
+            };
+            let body = Body::wrap_stream(Reporting {
+                body: Counted {
+                    body,
+                    bytes: response_bytes,
+                },
+                pending: Some(pending),
+            });
+            Ok((Response::from_parts(parts, body),))
+        })
+    }
+}
+
+// A body counting the bytes read from it.
+#[pin_project]
+struct Counted {
+    #[pin]
+    body: Body,
+    bytes: Arc<AtomicU64>,
+}
+
+impl Stream for Counted {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let pin = self.project();
+        let item = ready!(pin.body.poll_data(cx));
+        if let Some(Ok(ref chunk)) = item {
+            pin.bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        Poll::Ready(item)
+    }
+}
+
+// A response body that reports the usage of the request once it ends, or
+// is dropped.
+#[pin_project]
+struct Reporting<S: UsageSink> {
+    #[pin]
+    body: Counted,
+    pending: Option<Pending<S>>,
+}
+
+impl<S: UsageSink> Stream for Reporting<S> {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let pin = self.project();
+        let item = ready!(pin.body.poll_next(cx));
+        if item.is_none() {
+            pin.pending.take();
+        }
+        Poll::Ready(item)
+    }
+}
+
+struct Pending<S: UsageSink> {
+    sink: Arc<S>,
+    usage: Option<Usage>,
+    request_bytes: Arc<AtomicU64>,
+    response_bytes: Arc<AtomicU64>,
+}
+
+impl<S: UsageSink> Drop for Pending<S> {
+    fn drop(&mut self) {
+        if let Some(mut usage) = self.usage.take() {
+            usage.request_bytes = self.request_bytes.load(Ordering::Relaxed);
+            usage.response_bytes = self.response_bytes.load(Ordering::Relaxed);
+            tokio::spawn(self.sink.record(usage));
+        }
+    }
+}
+
+/// The usage of a request, reported by [`wrap`].
+///
+/// Each `Usage` is a single request, which sinks count by route and
+/// principal.
+#[derive(Clone, Debug)]
+pub struct Usage {
+    method: Method,
+    route: String,
+    principal: Option<String>,
+    status: StatusCode,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+impl Usage {
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The name of the route, or the path of the request.
+    pub fn route(&self) -> &str {
+        &self.route
+    }
+
+    /// The principal of the request, if the wrap has one and it was found.
+    pub fn principal(&self) -> Option<&str> {
+        self.principal.as_deref()
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The number of bytes of the request body read by the server.
+    pub fn request_bytes(&self) -> u64 {
+        self.request_bytes
+    }
+
+    /// The number of bytes of the response body sent to the client.
+    pub fn response_bytes(&self) -> u64 {
+        self.response_bytes
+    }
+}
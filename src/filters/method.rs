@@ -6,12 +6,20 @@
 //!
 //! There is also [`warp::method()`](method), which never rejects
 //! a request, and just extracts the method to be used in your filter chains.
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+
 use futures::future;
-use http::Method;
+use http::header::{HeaderValue, ALLOW, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{Method, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
 
-use crate::filter::{filter_fn, filter_fn_one, Filter, One};
-use crate::reject::Rejection;
-use std::convert::Infallible;
+use crate::filter::{filter_fn, filter_fn_one, Filter, FilterBase, Internal, One, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
 
 /// Create a `Filter` that requires the request method to be `GET`.
 ///
@@ -135,11 +143,383 @@ where
         if route.method() == method {
             future::ok(())
         } else {
-            future::err(crate::reject::method_not_allowed())
+            future::err(crate::reject::method_not_allowed(std::slice::from_ref(
+                method,
+            )))
         }
     })
 }
 
+/// Create a wrapping filter that answers `HEAD` requests with the `GET`
+/// routes of the wrapped filter.
+///
+/// A `HEAD` request is first given to the wrapped filter as is, so routes
+/// handling `HEAD` explicitly still take precedence. If it is rejected with
+/// `405 Method Not Allowed`, it is run again as a `GET` request, and the
+/// body of the response is discarded. Its headers are kept, and a
+/// `Content-Length` is added if the size of the body was known.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // `HEAD /hello` replies with the headers of `GET /hello`.
+/// let route = warp::path("hello")
+///     .and(warp::get())
+///     .map(|| "Hello, World!")
+///     .with(warp::filters::method::auto_head());
+/// ```
+pub fn auto_head() -> AutoHead {
+    AutoHead { _p: () }
+}
+
+/// Wrapper answering `HEAD` requests with `GET` routes, see [`auto_head`].
+#[derive(Clone, Copy, Debug)]
+pub struct AutoHead {
+    _p: (),
+}
+
+impl<F> WrapSealed<F> for AutoHead
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithAutoHead<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithAutoHead { filter }
+    }
+}
+
+/// A filter wrapped with [`auto_head`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithAutoHead<F> {
+    filter: F,
+}
+
+impl<F> FilterBase for WithAutoHead<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let (is_head, idx) =
+            route::with(|route| (route.method() == Method::HEAD, route.matched_path_index()));
+        let filter = self.filter.clone();
+        let first = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let err = match first.await {
+                Ok(reply) => return Ok((reply.into_response(),)),
+                Err(err) => err.into(),
+            };
+            if !is_head || err.status() != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(err);
+            }
+
+            log::trace!("method::auto_head: retrying HEAD as GET");
+            route::with(|route| {
+                route.reset_matched_path_index(idx);
+                route.set_method(Method::GET);
+            });
+            let result = filter.filter(Internal).await;
+            route::with(|route| route.set_method(Method::HEAD));
+
+            let mut res = result.map_err(Into::into)?.into_response();
+            if !res.headers().contains_key(CONTENT_LENGTH) {
+                if let Some(len) = res.body().size_hint().exact() {
+                    res.headers_mut()
+                        .insert(CONTENT_LENGTH, HeaderValue::from(len));
+                }
+            }
+            *res.body_mut() = Body::empty();
+            Ok((res,))
+        })
+    }
+}
+
+/// Create a wrapping filter that answers `OPTIONS` requests with the
+/// methods allowed by the wrapped filter.
+///
+/// An `OPTIONS` request is first given to the wrapped filter as is, so
+/// routes handling `OPTIONS` explicitly still take precedence. If it is
+/// rejected with `405 Method Not Allowed`, the reply is a `204 No Content`
+/// with an `Allow` header listing the methods of the method filters that
+/// rejected it, and `OPTIONS`. Handlers are never run for this.
+///
+/// The allowed methods are only known for method filters that come after
+/// the path filters of a route, such as `warp::path("a").and(warp::get())`,
+/// and only the standard methods are listed, not extension methods.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // `OPTIONS /hello` replies with `Allow: GET, POST, OPTIONS`.
+/// let hello = warp::path("hello");
+/// let route = hello.and(warp::get()).map(|| "Hello, World!")
+///     .or(hello.and(warp::post()).map(|| "Thanks!"))
+///     .with(warp::filters::method::auto_options());
+/// ```
+pub fn auto_options() -> AutoOptions {
+    AutoOptions { _p: () }
+}
+
+/// Wrapper answering `OPTIONS` requests, see [`auto_options`].
+#[derive(Clone, Copy, Debug)]
+pub struct AutoOptions {
+    _p: (),
+}
+
+impl<F> WrapSealed<F> for AutoOptions
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithAutoOptions<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithAutoOptions { filter }
+    }
+}
+
+/// A filter wrapped with [`auto_options`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithAutoOptions<F> {
+    filter: F,
+}
+
+impl<F> FilterBase for WithAutoOptions<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let is_options = route::with(|route| route.method() == Method::OPTIONS);
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let err: Rejection = match fut.await {
+                Ok(reply) => return Ok((reply.into_response(),)),
+                Err(err) => err.into(),
+            };
+            if !is_options || err.status() != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(err);
+            }
+
+            let mut allowed = err.allowed_methods();
+            allowed.push(Method::OPTIONS);
+            log::trace!("method::auto_options: allowed {:?}", allowed);
+            let mut res = Response::default();
+            *res.status_mut() = StatusCode::NO_CONTENT;
+            res.headers_mut().insert(ALLOW, allow_header(&allowed));
+            Ok((res,))
+        })
+    }
+}
+
+/// Create a wrapping filter that overrides the method of `POST` requests
+/// with the `X-HTTP-Method-Override` header.
+///
+/// This helps clients behind proxies that only allow `GET` and `POST`. The
+/// overridden method is the one seen by the method filters of the wrapped
+/// filter. A header that isn't a valid method rejects the request with
+/// `400 Bad Request`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // `POST /item` with `X-HTTP-Method-Override: DELETE` is routed here.
+/// let route = warp::path("item")
+///     .and(warp::delete())
+///     .map(warp::reply)
+///     .with(warp::filters::method::override_via_header());
+/// ```
+pub fn override_via_header() -> MethodOverride {
+    MethodOverride {
+        source: OverrideSource::Header,
+    }
+}
+
+/// Create a wrapping filter that overrides the method of `POST` requests
+/// with the `_method` field of an `application/x-www-form-urlencoded` body.
+///
+/// This lets HTML forms, which can only be sent with `GET` or `POST`, reach
+/// other routes. The body is buffered to find the field, and is still
+/// available to the wrapped filter. Fields that aren't a valid method are
+/// ignored.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::Filter;
+///
+/// // `<form method="post"><input type="hidden" name="_method" value="PUT">`
+/// let route = warp::path("item")
+///     .and(warp::put())
+///     .and(warp::body::form())
+///     .map(|form: HashMap<String, String>| format!("{:?}", form))
+///     .with(warp::filters::method::override_via_form());
+/// ```
+pub fn override_via_form() -> MethodOverride {
+    MethodOverride {
+        source: OverrideSource::Form,
+    }
+}
+
+/// Wrapper overriding the method of `POST` requests, see
+/// [`override_via_header`] and [`override_via_form`].
+#[derive(Clone, Copy, Debug)]
+pub struct MethodOverride {
+    source: OverrideSource,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum OverrideSource {
+    Header,
+    Form,
+}
+
+impl<F> WrapSealed<F> for MethodOverride
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithMethodOverride<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithMethodOverride {
+            source: self.source,
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`MethodOverride`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithMethodOverride<F> {
+    source: OverrideSource,
+    filter: F,
+}
+
+impl<F> FilterBase for WithMethodOverride<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let source = self.source;
+        let filter = self.filter.clone();
+        let body = route::with(|route| -> Result<Option<Body>, Rejection> {
+            if route.method() != Method::POST {
+                return Ok(None);
+            }
+            match source {
+                OverrideSource::Header => {
+                    if let Some(value) = route.headers().get(OVERRIDE_HEADER) {
+                        let method = parse_method(value.as_bytes())
+                            .ok_or_else(|| crate::reject::invalid_header(OVERRIDE_HEADER))?;
+                        log::trace!("method::override_via_header: {}", method);
+                        route.set_method(method);
+                    }
+                    Ok(None)
+                }
+                OverrideSource::Form => {
+                    let is_form = route
+                        .headers()
+                        .get(CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| {
+                            value
+                                .split(';')
+                                .next()
+                                .unwrap_or("")
+                                .trim()
+                                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+                        })
+                        .unwrap_or(false);
+                    if is_form {
+                        Ok(route.take_body())
+                    } else {
+                        Ok(None)
+                    }
+                }
+            }
+        });
+
+        Box::pin(async move {
+            if let Some(body) = body? {
+                let bytes = hyper::body::to_bytes(body).await.map_err(|err| {
+                    log::debug!("to_bytes error: {}", err);
+                    crate::reject::known(crate::body::BodyReadError(err))
+                })?;
+                let method = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+                    .ok()
+                    .and_then(|fields| {
+                        fields
+                            .into_iter()
+                            .find(|(name, _)| name == "_method")
+                            .and_then(|(_, value)| parse_method(value.as_bytes()))
+                    });
+                route::with(|route| {
+                    if let Some(method) = method {
+                        log::trace!("method::override_via_form: {}", method);
+                        route.set_method(method);
+                    }
+                    route.restore_body(Body::from(bytes));
+                });
+            }
+
+            let reply = filter.filter(Internal).await.map_err(Into::into)?;
+            Ok((reply.into_response(),))
+        })
+    }
+}
+
+const OVERRIDE_HEADER: &str = "x-http-method-override";
+
+fn parse_method(value: &[u8]) -> Option<Method> {
+    Method::from_bytes(&value.to_ascii_uppercase()).ok()
+}
+
+pub(crate) fn allow_header(methods: &[Method]) -> HeaderValue {
+    let allowed = methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    HeaderValue::from_str(&allowed).expect("methods are valid header values")
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
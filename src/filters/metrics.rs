@@ -0,0 +1,271 @@
+//! Prometheus metrics
+//!
+//! A [`Metrics`] registry records the requests of the routes it wraps, and
+//! renders them in the Prometheus text exposition format:
+//!
+//! - `warp_http_requests_total`, a counter of requests by route, method and
+//!   status class (`2xx`, `4xx`, ...).
+//! - `warp_http_request_duration_seconds`, a histogram of the latency of
+//!   requests by route and method.
+//!
+//! Routes are labeled with a name given when wrapping them, such as their
+//! path pattern, instead of the raw path of each request, which would make
+//! a new series for every id in a path.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! let metrics = warp::metrics::Metrics::new();
+//!
+//! let user = warp::path!("users" / u32)
+//!     .map(|id| format!("user #{}", id))
+//!     .with(metrics.route("/users/:id"));
+//!
+//! // `GET /metrics` replies with the recorded metrics.
+//! let routes = user.or(metrics.exposition());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::StatusCode;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+// The default buckets of the Prometheus clients, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A registry of request metrics.
+///
+/// A `Metrics` is cheap to clone, and all clones share the same metrics.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    series: Arc<Mutex<BTreeMap<(String, String), Series>>>,
+}
+
+#[derive(Default)]
+struct Series {
+    // Requests by status class, from `1xx` to `5xx`.
+    statuses: [u64; 5],
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    /// Creates an empty registry.
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Creates a wrapping filter recording the requests of a route, labeled
+    /// with `name`.
+    ///
+    /// Replies are always recorded. Rejections are only recorded if they are
+    /// not `404 Not Found` or `405 Method Not Allowed`, since those usually
+    /// mean the request was meant for another route.
+    pub fn route(&self, name: impl Into<String>) -> Instrument {
+        Instrument {
+            metrics: self.clone(),
+            name: Arc::new(name.into()),
+        }
+    }
+
+    /// Creates a `Filter` replying to `GET /metrics` with the rendered
+    /// metrics.
+    pub fn exposition(&self) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+        let metrics = self.clone();
+        crate::path("metrics")
+            .and(crate::path::end())
+            .and(crate::get())
+            .map(move || {
+                let mut res = Response::new(metrics.render().into());
+                res.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; version=0.0.4"),
+                );
+                res
+            })
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let series = self.series.lock().expect("metrics lock poisoned");
+        let mut out = String::new();
+
+        out.push_str("# HELP warp_http_requests_total Total number of HTTP requests.\n");
+        out.push_str("# TYPE warp_http_requests_total counter\n");
+        for ((route, method), series) in series.iter() {
+            for (class, &count) in series.statuses.iter().enumerate() {
+                if count > 0 {
+                    let _ = writeln!(
+                        out,
+                        "warp_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}xx\"}} {}",
+                        Escape(route),
+                        Escape(method),
+                        class + 1,
+                        count,
+                    );
+                }
+            }
+        }
+
+        out.push_str(
+            "# HELP warp_http_request_duration_seconds Latency of HTTP requests in seconds.\n",
+        );
+        out.push_str("# TYPE warp_http_request_duration_seconds histogram\n");
+        for ((route, method), series) in series.iter() {
+            let labels = format!("route=\"{}\",method=\"{}\"", Escape(route), Escape(method));
+            let mut cumulative = 0;
+            for (le, count) in BUCKETS.iter().zip(series.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "warp_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, le, cumulative,
+                );
+            }
+            let _ = writeln!(
+                out,
+                "warp_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, series.count,
+            );
+            let _ = writeln!(
+                out,
+                "warp_http_request_duration_seconds_sum{{{}}} {}",
+                labels, series.sum,
+            );
+            let _ = writeln!(
+                out,
+                "warp_http_request_duration_seconds_count{{{}}} {}",
+                labels, series.count,
+            );
+        }
+
+        out
+    }
+
+    fn record(&self, route: &str, method: &str, status: StatusCode, elapsed: Duration) {
+        let mut series = self.series.lock().expect("metrics lock poisoned");
+        let series = series
+            .entry((route.to_owned(), method.to_owned()))
+            .or_default();
+
+        let class = (status.as_u16() / 100) as usize;
+        if (1..=5).contains(&class) {
+            series.statuses[class - 1] += 1;
+        }
+
+        let secs = elapsed.as_secs_f64();
+        if let Some(idx) = BUCKETS.iter().position(|&le| secs <= le) {
+            series.buckets[idx] += 1;
+        }
+        series.sum += secs;
+        series.count += 1;
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metrics").finish()
+    }
+}
+
+// Escapes a label value.
+struct Escape<'a>(&'a str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wrapper recording the requests of a route, see [`Metrics::route`].
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    metrics: Metrics,
+    name: Arc<String>,
+}
+
+impl<F> WrapSealed<F> for Instrument
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = Instrumented<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        Instrumented {
+            instrument: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`Metrics::route`].
+#[derive(Clone, Debug)]
+pub struct Instrumented<F> {
+    instrument: Instrument,
+    filter: F,
+}
+
+impl<F> FilterBase for Instrumented<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let started = Instant::now();
+        let method = route::with(|route| route.method().clone());
+        let instrument = self.instrument.clone();
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let result = fut.await.map(Reply::into_response).map_err(Into::into);
+            let status = match result {
+                Ok(ref res) => Some(res.status()),
+                Err(ref rejection) => match rejection.status() {
+                    StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => None,
+                    status => Some(status),
+                },
+            };
+            if let Some(status) = status {
+                instrument.metrics.record(
+                    &instrument.name,
+                    method.as_str(),
+                    status,
+                    started.elapsed(),
+                );
+            }
+            result.map(|res| (res,))
+        })
+    }
+}
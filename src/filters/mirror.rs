@@ -0,0 +1,273 @@
+//! Request mirroring
+//!
+//! A [`Mirror`] sends a copy of the requests matched by the filter it wraps
+//! to a secondary handler or upstream server, such as a new version of a
+//! service being dark launched, and ignores how it responds. The copies
+//! are sent in the background, once the wrapped filter has replied, so the
+//! primary response isn't delayed by the mirror.
+//!
+//! Mirroring is best effort: when too many copies are already in flight,
+//! or a request body is too large to be buffered, the request isn't
+//! mirrored.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! let search = warp::path("search")
+//!     .map(|| "results")
+//!     .with(warp::mirror::upstream("http://127.0.0.1:3031"));
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::header::{CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use http::uri::{PathAndQuery, Uri};
+use http::Request;
+use hyper::Body;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+
+type Handler =
+    Arc<dyn Fn(Request<Bytes>) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Create a wrapping filter mirroring requests to `handler`.
+///
+/// The handler gets a copy of each request matched by the wrapped filter,
+/// with its body, and runs in its own task.
+///
+/// # Example
+///
+/// ```
+/// use warp::http::Request;
+/// use warp::hyper::body::Bytes;
+/// use warp::Filter;
+///
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(warp::mirror::handler(|req: Request<Bytes>| async move {
+///         println!("shadowed {} {}", req.method(), req.uri());
+///     }));
+/// ```
+pub fn handler<H, Fut>(handler: H) -> Mirror
+where
+    H: Fn(Request<Bytes>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Mirror {
+        handler: Arc::new(move |req| Box::pin(handler(req))),
+        in_flight: Arc::new(AtomicUsize::new(0)),
+        max_in_flight: 64,
+        max_body: 64 * 1024,
+    }
+}
+
+/// Create a wrapping filter mirroring requests to the server at `base`,
+/// like `http://10.0.0.2:8080`.
+///
+/// The copies keep the method, path, query, headers and body of the
+/// requests, but get the `Host` of `base`. Their responses and errors are
+/// ignored.
+///
+/// # Panics
+///
+/// This function panics if `base` isn't an absolute `http` URL.
+pub fn upstream(base: &str) -> Mirror {
+    let base = Uri::try_from(base)
+        .ok()
+        .filter(|base| base.scheme_str() == Some("http") && base.authority().is_some())
+        .unwrap_or_else(|| panic!("illegal mirror upstream: {:?}", base));
+    let client = hyper::Client::new();
+    handler(move |req: Request<Bytes>| {
+        let (mut parts, body) = req.into_parts();
+        let path = parts
+            .uri
+            .path_and_query()
+            .cloned()
+            .unwrap_or_else(|| PathAndQuery::from_static("/"));
+        let uri = Uri::builder()
+            .scheme("http")
+            .authority(base.authority().expect("checked above").clone())
+            .path_and_query(path)
+            .build();
+        let client = client.clone();
+        async move {
+            parts.uri = match uri {
+                Ok(uri) => uri,
+                Err(err) => {
+                    log::debug!("mirror uri error: {}", err);
+                    return;
+                }
+            };
+            parts.headers.remove(HOST);
+            let req = Request::from_parts(parts, Body::from(body));
+            if let Err(err) = client.request(req).await {
+                log::debug!("mirror upstream error: {}", err);
+            }
+        }
+    })
+}
+
+/// Wrapper mirroring requests, see [`handler`] and [`upstream`].
+#[derive(Clone)]
+pub struct Mirror {
+    handler: Handler,
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: usize,
+    max_body: u64,
+}
+
+impl Mirror {
+    /// Sets how many copies can be in flight at once, beyond which requests
+    /// aren't mirrored.
+    ///
+    /// Defaults to 64.
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = max;
+        self
+    }
+
+    /// Sets the largest request body that is buffered to be mirrored, in
+    /// bytes. Requests with larger bodies, or bodies of unknown length,
+    /// aren't mirrored.
+    ///
+    /// Defaults to 64 KiB.
+    pub fn max_body(mut self, max: u64) -> Self {
+        self.max_body = max;
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.in_flight.load(Ordering::Acquire) >= self.max_in_flight
+    }
+
+    // The request without its body, and whether its body must be buffered,
+    // if it can be mirrored.
+    fn capture(&self, route: &Route) -> Option<(Request<()>, bool)> {
+        if self.is_full() {
+            log::debug!("mirror: too many in flight");
+            return None;
+        }
+        let headers = route.headers();
+        let buffer = match headers.get(CONTENT_LENGTH) {
+            Some(len) => match len.to_str().ok().and_then(|len| len.parse::<u64>().ok()) {
+                Some(len) if len <= self.max_body => len > 0,
+                _ => {
+                    log::debug!("mirror: body too large");
+                    return None;
+                }
+            },
+            None if headers.contains_key(TRANSFER_ENCODING) => {
+                log::debug!("mirror: body of unknown length");
+                return None;
+            }
+            None => false,
+        };
+        let mut req = Request::new(());
+        *req.method_mut() = route.method().clone();
+        *req.uri_mut() = route.uri().clone();
+        *req.version_mut() = route.version();
+        *req.headers_mut() = headers.clone();
+        Some((req, buffer))
+    }
+
+    fn send(&self, req: Request<Bytes>) {
+        if self.in_flight.fetch_add(1, Ordering::AcqRel) >= self.max_in_flight {
+            self.in_flight.fetch_sub(1, Ordering::AcqRel);
+            log::debug!("mirror: too many in flight");
+            return;
+        }
+        let fut = (self.handler)(req);
+        let in_flight = self.in_flight.clone();
+        tokio::spawn(async move {
+            fut.await;
+            in_flight.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+}
+
+impl fmt::Debug for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mirror")
+            .field("in_flight", &self.in_flight.load(Ordering::Relaxed))
+            .field("max_in_flight", &self.max_in_flight)
+            .field("max_body", &self.max_body)
+            .finish()
+    }
+}
+
+impl<F> WrapSealed<F> for Mirror
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithMirror<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithMirror {
+            filter,
+            mirror: self.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with a [`Mirror`].
+#[derive(Clone, Debug)]
+pub struct WithMirror<F> {
+    filter: F,
+    mirror: Mirror,
+}
+
+impl<F> FilterBase for WithMirror<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let captured = route::with(|route| {
+            let (req, buffer) = self.mirror.capture(route)?;
+            let body = if buffer { route.take_body() } else { None };
+            Some((req, body))
+        });
+        let filter = self.filter.clone();
+        let mirror = self.mirror.clone();
+        Box::pin(async move {
+            let req = match captured {
+                Some((req, Some(body))) => {
+                    let bytes = hyper::body::to_bytes(body).await.map_err(|err| {
+                        log::debug!("to_bytes error: {}", err);
+                        crate::reject::known(crate::body::BodyReadError(err))
+                    })?;
+                    route::with(|route| route.restore_body(Body::from(bytes.clone())));
+                    Some(req.map(|()| bytes))
+                }
+                Some((req, None)) => Some(req.map(|()| Bytes::new())),
+                None => None,
+            };
+
+            let reply = filter.filter(Internal).await.map_err(Into::into)?;
+            if let Some(req) = req {
+                mirror.send(req);
+            }
+            Ok((reply.into_response(),))
+        })
+    }
+}
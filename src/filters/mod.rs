@@ -5,23 +5,62 @@
 
 pub mod addr;
 pub mod any;
+pub mod audit;
+pub mod auth;
 pub mod body;
+pub mod cache;
 #[cfg(feature = "compression")]
 pub mod compression;
+pub mod conditional;
 pub mod cookie;
 pub mod cors;
+#[cfg(feature = "csrf")]
+pub mod csrf;
+pub mod deadline;
 pub mod ext;
 pub mod fs;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod grpc_web;
 pub mod header;
+pub mod health;
+pub mod host;
+pub mod https;
+pub mod idempotency;
+pub mod ip;
+pub mod limit;
 pub mod log;
+pub mod maintenance;
+pub mod metering;
 pub mod method;
+pub mod metrics;
+pub mod mirror;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+pub mod panic;
 pub mod path;
 pub mod query;
 pub mod reply;
+pub mod router;
+pub mod routes;
+pub mod rpc;
+#[cfg(feature = "session")]
+pub mod session;
+pub mod split;
 pub mod sse;
+pub mod state;
+#[cfg(any(feature = "askama", feature = "tera"))]
+pub mod template;
+pub mod tower;
+pub mod trace;
+pub mod uri;
+pub mod version;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+pub mod when;
 #[cfg(feature = "websocket")]
 pub mod ws;
 
-pub use crate::filter::BoxedFilter;
+pub use crate::filter::{BoxedFilter, DynamicFilter};
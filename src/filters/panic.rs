@@ -0,0 +1,211 @@
+//! Panic filters.
+//!
+//! A panic in a handler unwinds the task serving the connection, which
+//! closes it without a reply. The [`catch`] wrapper catches panics of the
+//! filter it wraps instead, logs them, and replies with a
+//! `500 Internal Server Error`, or the response of a custom handler.
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use http::{Method, StatusCode};
+use pin_project::pin_project;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route;
+
+/// Create a wrapping filter that catches panics of the wrapped filter.
+///
+/// Panics are logged with the method and path of the request, and replied
+/// to with a `500 Internal Server Error`. Use [`Catch::handler`] to reply
+/// differently.
+///
+/// Panics are only caught when the crate is built with `panic = "unwind"`,
+/// the default.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("divide")
+///     .and(warp::path::param::<u32>())
+///     .map(|n: u32| (100 / n).to_string())
+///     .with(warp::panic::catch());
+/// ```
+pub fn catch() -> Catch<fn(&Panic) -> Response> {
+    Catch {
+        handler: internal_server_error,
+    }
+}
+
+fn internal_server_error(_: &Panic) -> Response {
+    let mut res = Response::default();
+    *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    res
+}
+
+/// Wrapper catching panics, see [`catch`].
+#[derive(Clone, Copy, Debug)]
+pub struct Catch<H> {
+    handler: H,
+}
+
+impl<H> Catch<H> {
+    /// Reply to caught panics with the response of `handler`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::http::StatusCode;
+    /// use warp::panic::Panic;
+    /// use warp::{Filter, Reply};
+    ///
+    /// let route = warp::any()
+    ///     .map(|| -> &'static str { panic!("oops") })
+    ///     .with(warp::panic::catch().handler(|panic: &Panic| {
+    ///         warp::reply::with_status(
+    ///             format!("{} {} failed", panic.method(), panic.path()),
+    ///             StatusCode::INTERNAL_SERVER_ERROR,
+    ///         )
+    ///         .into_response()
+    ///     }));
+    /// ```
+    pub fn handler<T>(self, handler: T) -> Catch<T>
+    where
+        T: Fn(&Panic) -> Response + Clone + Send + Sync + 'static,
+    {
+        Catch { handler }
+    }
+}
+
+impl<H, F> WrapSealed<F> for Catch<H>
+where
+    H: Fn(&Panic) -> Response + Clone + Send + Sync + 'static,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithCatch<H, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithCatch {
+            filter,
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+/// A filter wrapped with [`catch`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithCatch<H, F> {
+    filter: F,
+    handler: H,
+}
+
+impl<H, F> FilterBase for WithCatch<H, F>
+where
+    H: Fn(&Panic) -> Response + Clone + Send + Sync + 'static,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let (method, path) =
+            route::with(|route| (route.method().clone(), route.full_path().to_owned()));
+        let handler = self.handler.clone();
+        let on_panic = move |payload: Box<dyn Any + Send>| {
+            let panic = Panic {
+                message: message(&*payload),
+                method,
+                path,
+            };
+            log::error!(
+                "panic handling {} {}: {}",
+                panic.method,
+                panic.path,
+                panic.message.as_deref().unwrap_or("Box<dyn Any>")
+            );
+            Ok((handler(&panic),))
+        };
+
+        let fut = match catch_unwind(AssertUnwindSafe(|| self.filter.filter(Internal))) {
+            Ok(fut) => fut,
+            Err(payload) => {
+                let res = on_panic(payload);
+                return Box::pin(async move { res });
+            }
+        };
+        Box::pin(async move {
+            match (CatchUnwind { fut }).await {
+                Ok(Ok(reply)) => Ok((reply.into_response(),)),
+                Ok(Err(err)) => Err(err.into()),
+                Err(payload) => on_panic(payload),
+            }
+        })
+    }
+}
+
+fn message(payload: &(dyn Any + Send)) -> Option<String> {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        Some((*message).to_owned())
+    } else {
+        payload.downcast_ref::<String>().cloned()
+    }
+}
+
+// Catches panics while polling `fut`.
+#[pin_project]
+struct CatchUnwind<T> {
+    #[pin]
+    fut: T,
+}
+
+impl<T: Future> Future for CatchUnwind<T> {
+    type Output = Result<T::Output, Box<dyn Any + Send>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fut = self.project().fut;
+        match catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// A panic caught by [`catch`], given to its [`handler`](Catch::handler).
+#[derive(Debug)]
+pub struct Panic {
+    message: Option<String>,
+    method: Method,
+    path: String,
+}
+
+impl Panic {
+    /// The message of the panic, if it was a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The path of the request.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
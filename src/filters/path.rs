@@ -125,11 +125,14 @@
 //! with an invalid body for route `/right-path-wrong-body` may try matching against `/wrong-path`
 //! and return the error from `/wrong-path` instead of the correct body-related error.
 
+use std::borrow::Cow;
 use std::convert::Infallible;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 
-use futures::future;
+use futures::future::{self, Either};
+use futures::TryFutureExt;
 use http::uri::PathAndQuery;
 
 use self::internal::Opaque;
@@ -221,6 +224,99 @@ where
     }
 }
 
+/// Mount a filter under a path prefix of one or more segments.
+///
+/// The request path must start with every segment of `prefix`, which are
+/// then matched, before running `filter` on the rest of the path. This is
+/// the same as chaining a [`path`](path()) filter per segment, but keeps a
+/// versioned prefix like `api/v1` in one place.
+///
+/// # Panics
+///
+/// The prefix cannot be empty, or have empty segments.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let users = warp::path!("users" / u32).map(|id| format!("user #{}", id));
+///
+/// // Matches '/api/v1/users/7'
+/// let api = warp::path::mount("api/v1", users);
+/// ```
+pub fn mount<F>(prefix: &str, filter: F) -> Mount<F>
+where
+    F: Filter,
+    F::Extract: Send,
+    F::Error: Into<Rejection>,
+{
+    let prefix = prefix.trim_matches('/');
+    assert!(
+        !prefix.is_empty() && prefix.split('/').all(|seg| !seg.is_empty()),
+        "illegal mount prefix: {:?}",
+        prefix
+    );
+    Mount {
+        prefix: prefix.into(),
+        filter,
+    }
+}
+
+/// A `Filter` mounted under a path prefix, created with [`mount`].
+#[derive(Clone)]
+pub struct Mount<F> {
+    prefix: Arc<str>,
+    filter: F,
+}
+
+impl<F> fmt::Debug for Mount<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mount")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+impl<F> FilterBase for Mount<F>
+where
+    F: Filter,
+    F::Extract: Send,
+    F::Error: Into<Rejection>,
+{
+    type Extract = F::Extract;
+    type Error = Rejection;
+    type Future = Either<
+        future::Ready<Result<Self::Extract, Self::Error>>,
+        future::ErrInto<F::Future, Rejection>,
+    >;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let matched = route::with(|route| {
+            let idx = route.matched_path_index();
+            for p in self.prefix.split('/') {
+                let matched = with_segment(route, |seg| {
+                    log::trace!("{:?}?: {:?}", p, seg);
+                    if seg == p {
+                        Ok(())
+                    } else {
+                        Err(reject::not_found())
+                    }
+                });
+                if let Err(err) = matched {
+                    route.reset_matched_path_index(idx);
+                    return Err(err);
+                }
+            }
+            Ok(())
+        });
+        match matched {
+            Ok(()) => Either::Right(self.filter.filter(Internal).err_into()),
+            Err(err) => Either::Left(future::err(err)),
+        }
+    }
+}
+
 /// Matches the end of a route.
 ///
 /// Note that _not_ including `end()` may result in shorter paths like
@@ -253,6 +349,9 @@ pub fn end() -> impl Filter<Extract = (), Error = Rejection> + Copy {
 ///
 /// If the value could not be parsed, rejects with a `404 Not Found`.
 ///
+/// The segment is parsed as it appears in the URI, without decoding
+/// percent-escapes; see [`param_decoded`] to decode them.
+///
 /// # Example
 ///
 /// ```
@@ -274,6 +373,47 @@ pub fn param<T: FromStr + Send + 'static>(
     })
 }
 
+/// Extract a parameter from a percent-decoded path segment.
+///
+/// This is like [`param`], except that the segment is percent-decoded
+/// before being parsed, so `/caf%C3%A9` extracts a `String` of `café`.
+/// Segments without escapes are parsed in place without allocating.
+///
+/// Since a decoded segment could otherwise escape the directory of a
+/// handler building a file path from it, segments decoding to `.` or
+/// `..`, or containing a `/` or `\` (such as `..%2F..%2Fetc`), are
+/// rejected, as are those decoding to invalid UTF-8. Malformed escapes,
+/// like the `%` of `100%`, are kept as is.
+///
+/// If the value could not be decoded or parsed, rejects with a
+/// `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // GET /tags/caf%C3%A9 replies with "tag café"
+/// let route = warp::path("tags")
+///     .and(warp::path::param_decoded())
+///     .map(|tag: String| format!("tag {}", tag));
+/// ```
+pub fn param_decoded<T: FromStr + Send + 'static>(
+) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
+    filter_segment(|seg| {
+        log::trace!("param_decoded?: {:?}", seg);
+        if seg.is_empty() {
+            return Err(reject::not_found());
+        }
+        let seg = percent_decode(seg).ok_or_else(reject::not_found)?;
+        if seg == "." || seg == ".." || seg.contains(&['/', '\\'][..]) {
+            log::debug!("param_decoded: unsafe segment {:?}", seg);
+            return Err(reject::not_found());
+        }
+        T::from_str(&seg).map(one).map_err(|_| reject::not_found())
+    })
+}
+
 /// Extract the unmatched tail of the path.
 ///
 /// This will return a `Tail`, which allows access to the rest of the path
@@ -461,6 +601,32 @@ fn segment(route: &Route) -> &str {
         .expect("split always has at least 1")
 }
 
+// Percent-decodes a path segment, borrowing it when it has no escapes.
+//
+// Malformed escapes are kept as is, and `None` is returned if the decoded
+// bytes aren't UTF-8.
+fn percent_decode(seg: &str) -> Option<Cow<'_, str>> {
+    if !seg.contains('%') {
+        return Some(Cow::Borrowed(seg));
+    }
+    let hex = |b: Option<&u8>| (*b? as char).to_digit(16).map(|d| d as u8);
+    let bytes = seg.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let (Some(hi), Some(lo)) = (hex(bytes.get(i + 1)), hex(bytes.get(i + 2))) {
+                decoded.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok().map(Cow::Owned)
+}
+
 fn path_and_query(route: &Route) -> PathAndQuery {
     route
         .uri()
@@ -1,10 +1,12 @@
 //! Query Filters
 
+use std::collections::HashMap;
+
 use futures::future;
-use serde::de::DeserializeOwned;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
 use serde_urlencoded;
 
-use crate::filter::{filter_fn_one, Filter, One};
+use crate::filter::{filter_fn, filter_fn_one, Filter, One};
 use crate::reject::{self, Rejection};
 
 /// Creates a `Filter` that decodes query parameters to the type `T`.
@@ -37,3 +39,96 @@ pub fn raw() -> impl Filter<Extract = One<String>, Error = Rejection> + Copy {
         future::ready(route)
     })
 }
+
+/// Creates a `Filter` that decodes the query parameters known to `T`, and
+/// extracts the other ones as a map.
+///
+/// The known parameters are the fields of the struct `T`, by their
+/// serialized names. This suits endpoints taking typed parameters, like
+/// paging, alongside arbitrary ones, like filters on any field. Types that
+/// aren't structs, or have flattened fields, get every parameter, and an
+/// empty map.
+///
+/// If the known parameters cannot be decoded into a `T`, the request is
+/// rejected with a `400 Bad Request`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use serde_derive::Deserialize;
+/// use warp::Filter;
+///
+/// #[derive(Deserialize)]
+/// struct Page {
+///     offset: Option<u32>,
+///     limit: Option<u32>,
+/// }
+///
+/// // GET /items?limit=10&color=red&size=xl
+/// let route = warp::path("items")
+///     .and(warp::query::partial::<Page>())
+///     .map(|page: Page, filters: HashMap<String, String>| {
+///         format!("{} items where {:?}", page.limit.unwrap_or(20), filters)
+///     });
+/// ```
+pub fn partial<T: DeserializeOwned + Send + 'static>(
+) -> impl Filter<Extract = (T, HashMap<String, String>), Error = Rejection> + Copy {
+    filter_fn(|route| {
+        let query_string = route.query().unwrap_or("");
+        future::ready(split_query(query_string).map_err(|e| {
+            log::debug!("failed to decode query string '{}': {:?}", query_string, e);
+            reject::invalid_query()
+        }))
+    })
+}
+
+fn split_query<T: DeserializeOwned>(
+    query_string: &str,
+) -> Result<(T, HashMap<String, String>), serde_urlencoded::de::Error> {
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query_string)?;
+    let (known, rest) = match struct_fields::<T>() {
+        Some(fields) => pairs
+            .into_iter()
+            .partition(|(key, _)| fields.contains(&key.as_str())),
+        None => (pairs, Vec::new()),
+    };
+    // Encoded again, to decode the values as `query` would.
+    let known = serde_urlencoded::to_string(&known).expect("pairs of strings are encodable");
+    let known = serde_urlencoded::from_str(&known)?;
+    Ok((known, rest.into_iter().collect()))
+}
+
+// The field names of `T`, if it is deserialized as a struct.
+fn struct_fields<'de, T: de::Deserialize<'de>>() -> Option<&'static [&'static str]> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldsProbe(&mut fields));
+    fields
+}
+
+// A `Deserializer` only recording the fields of the struct asked for.
+struct FieldsProbe<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de, 'a> Deserializer<'de> for FieldsProbe<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
@@ -19,13 +19,22 @@
 //! Wrapping allows adding in conditional logic *before* the request enters
 //! the inner filter (though the `with::header` wrapper does not).
 
-use std::convert::TryFrom;
+use std::convert::{Infallible, TryFrom};
+use std::hash::Hasher;
 use std::sync::Arc;
+use std::time::Duration;
 
-use http::header::{HeaderMap, HeaderName, HeaderValue};
+use headers::ETag;
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_SECURITY_POLICY, REFERRER_POLICY,
+    X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
 
-use self::sealed::{WithDefaultHeader_, WithHeader_, WithHeaders_};
-use crate::filter::{Filter, Map, WrapSealed};
+use self::sealed::{
+    WithDefaultHeader_, WithEtag_, WithHeader_, WithHeaders_, WithSecurityHeaders_,
+};
+use crate::filter::{AndThen, Filter, Map, WrapSealed};
+use crate::reject::CombineRejection;
 use crate::reply::Reply;
 
 /// Wrap a [`Filter`](crate::Filter) that adds a header to the reply.
@@ -118,6 +127,381 @@ where
     WithDefaultHeader { name, value }
 }
 
+/// Wrap a [`Filter`](crate::Filter) that sets the `Cache-Control` header of
+/// the reply to a policy.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use warp::reply::with::CachePolicy;
+/// use warp::Filter;
+///
+/// // Cache for an hour, in browsers and proxies.
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(warp::reply::with::cache_control(
+///         CachePolicy::max_age(Duration::from_secs(3600)).public(),
+///     ));
+/// ```
+pub fn cache_control(policy: CachePolicy) -> WithHeader {
+    WithHeader {
+        name: CACHE_CONTROL,
+        value: policy.to_header_value(),
+    }
+}
+
+/// A `Cache-Control` policy, for [`cache_control`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CachePolicy {
+    no_store: bool,
+    no_cache: bool,
+    public: bool,
+    private: bool,
+    immutable: bool,
+    must_revalidate: bool,
+    max_age: Option<Duration>,
+    s_max_age: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+}
+
+impl CachePolicy {
+    /// Never store the reply, with `no-store`.
+    pub fn no_store() -> Self {
+        CachePolicy {
+            no_store: true,
+            ..CachePolicy::default()
+        }
+    }
+
+    /// Store the reply, but revalidate it before every use, with `no-cache`.
+    pub fn no_cache() -> Self {
+        CachePolicy {
+            no_cache: true,
+            ..CachePolicy::default()
+        }
+    }
+
+    /// Use the reply without revalidation for `age`, with `max-age`.
+    pub fn max_age(age: Duration) -> Self {
+        CachePolicy {
+            max_age: Some(age),
+            ..CachePolicy::default()
+        }
+    }
+
+    /// Allow shared caches, such as proxies, to store the reply.
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self.private = false;
+        self
+    }
+
+    /// Only allow the browser of the user to store the reply.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self.public = false;
+        self
+    }
+
+    /// Mark the reply as never changing while it is fresh, such as assets
+    /// with a hash in their name.
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// Forbid using the reply once it is stale without revalidating it.
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// Set a different `max-age` for shared caches, with `s-maxage`.
+    pub fn s_max_age(mut self, age: Duration) -> Self {
+        self.s_max_age = Some(age);
+        self
+    }
+
+    /// Allow using the reply for `window` after it is stale, while it is
+    /// revalidated in the background.
+    pub fn stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = Some(window);
+        self
+    }
+
+    fn to_header_value(&self) -> HeaderValue {
+        let mut directives = Vec::new();
+        if self.public {
+            directives.push("public".to_owned());
+        }
+        if self.private {
+            directives.push("private".to_owned());
+        }
+        if self.no_store {
+            directives.push("no-store".to_owned());
+        }
+        if self.no_cache {
+            directives.push("no-cache".to_owned());
+        }
+        if let Some(age) = self.max_age {
+            directives.push(format!("max-age={}", age.as_secs()));
+        }
+        if let Some(age) = self.s_max_age {
+            directives.push(format!("s-maxage={}", age.as_secs()));
+        }
+        if let Some(window) = self.stale_while_revalidate {
+            directives.push(format!("stale-while-revalidate={}", window.as_secs()));
+        }
+        if self.must_revalidate {
+            directives.push("must-revalidate".to_owned());
+        }
+        if self.immutable {
+            directives.push("immutable".to_owned());
+        }
+        HeaderValue::from_str(&directives.join(", ")).expect("directives are valid header values")
+    }
+}
+
+/// Wrap a [`Filter`](crate::Filter) that sets a strong `ETag` on successful
+/// replies to `GET` and `HEAD` requests, from a hash of their body.
+///
+/// When the `If-None-Match` header of the request matches the `ETag`, the
+/// reply is replaced with a `304 Not Modified`, so that clients polling a
+/// resource don't download it again when it hasn't changed. Replies that
+/// already have an `ETag` keep it.
+///
+/// The body is buffered to be hashed, so only bodies of a known length, of
+/// at most 1 MiB, are tagged. Other replies, such as streams, are passed
+/// through unchanged. The hash isn't cryptographic, but is stable between
+/// servers.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("status")
+///     .map(|| warp::reply::json(&["ok"]))
+///     .with(warp::reply::with::etag());
+/// ```
+pub fn etag() -> WithEtag {
+    WithEtag { _p: () }
+}
+
+/// Wrap a [`Filter`](crate::Filter) that sets the security headers of a
+/// [`SecurityPolicy`] on the reply, if they aren't already set.
+///
+/// # Example
+///
+/// ```
+/// use warp::reply::with::{FrameOptions, SecurityPolicy};
+/// use warp::Filter;
+///
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(warp::reply::with::security_headers(
+///         SecurityPolicy::new()
+///             .content_security_policy("img-src", &["'self'", "https://images.example.com"])
+///             .frame_options(FrameOptions::SameOrigin)
+///             .permission("geolocation", &[]),
+///     ));
+/// ```
+pub fn security_headers(policy: SecurityPolicy) -> WithSecurityHeaders {
+    WithSecurityHeaders {
+        headers: Arc::new(policy.to_headers()),
+    }
+}
+
+/// The security headers of replies, for [`security_headers`].
+///
+/// The default policy sets:
+///
+/// - `Content-Security-Policy: default-src 'self'; object-src 'none';
+///   frame-ancestors 'none'`
+/// - `X-Content-Type-Options: nosniff`
+/// - `Referrer-Policy: strict-origin-when-cross-origin`
+/// - `X-Frame-Options: DENY`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecurityPolicy {
+    csp: Vec<(String, Vec<String>)>,
+    referrer_policy: ReferrerPolicy,
+    frame_options: Option<FrameOptions>,
+    permissions: Vec<(String, Vec<String>)>,
+}
+
+/// The values of the `Referrer-Policy` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferrerPolicy {
+    /// `no-referrer`
+    NoReferrer,
+    /// `no-referrer-when-downgrade`
+    NoReferrerWhenDowngrade,
+    /// `origin`
+    Origin,
+    /// `origin-when-cross-origin`
+    OriginWhenCrossOrigin,
+    /// `same-origin`
+    SameOrigin,
+    /// `strict-origin`
+    StrictOrigin,
+    /// `strict-origin-when-cross-origin`
+    StrictOriginWhenCrossOrigin,
+    /// `unsafe-url`
+    UnsafeUrl,
+}
+
+/// The values of the `X-Frame-Options` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// `DENY`, the reply can't be displayed in a frame.
+    Deny,
+    /// `SAMEORIGIN`, the reply can only be displayed in a frame of the same
+    /// origin.
+    SameOrigin,
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        SecurityPolicy::new()
+    }
+}
+
+impl SecurityPolicy {
+    /// Create the default policy.
+    pub fn new() -> Self {
+        SecurityPolicy {
+            csp: vec![
+                ("default-src".to_owned(), vec!["'self'".to_owned()]),
+                ("object-src".to_owned(), vec!["'none'".to_owned()]),
+                ("frame-ancestors".to_owned(), vec!["'none'".to_owned()]),
+            ],
+            referrer_policy: ReferrerPolicy::StrictOriginWhenCrossOrigin,
+            frame_options: Some(FrameOptions::Deny),
+            permissions: Vec::new(),
+        }
+    }
+
+    /// Set a directive of the `Content-Security-Policy` header, replacing the
+    /// sources of the directive if it's already set.
+    ///
+    /// Keywords of sources must be quoted, as in `'self'`.
+    pub fn content_security_policy(mut self, directive: &str, sources: &[&str]) -> Self {
+        let sources = sources.iter().map(|&source| source.to_owned()).collect();
+        match self.csp.iter_mut().find(|(name, _)| name == directive) {
+            Some(entry) => entry.1 = sources,
+            None => self.csp.push((directive.to_owned(), sources)),
+        }
+        self
+    }
+
+    /// Don't set the `Content-Security-Policy` header.
+    pub fn no_content_security_policy(mut self) -> Self {
+        self.csp.clear();
+        self
+    }
+
+    /// Set the `Referrer-Policy` header.
+    pub fn referrer_policy(mut self, policy: ReferrerPolicy) -> Self {
+        self.referrer_policy = policy;
+        self
+    }
+
+    /// Set the `X-Frame-Options` header.
+    pub fn frame_options(mut self, options: FrameOptions) -> Self {
+        self.frame_options = Some(options);
+        self
+    }
+
+    /// Don't set the `X-Frame-Options` header, such as for replies meant to
+    /// be embedded by other sites.
+    pub fn no_frame_options(mut self) -> Self {
+        self.frame_options = None;
+        self
+    }
+
+    /// Allow a feature of the `Permissions-Policy` header to the origins of
+    /// `allowlist`, which can include `self` and `*`. An empty list disables
+    /// the feature.
+    pub fn permission(mut self, feature: &str, allowlist: &[&str]) -> Self {
+        let allowlist = allowlist
+            .iter()
+            .map(|&origin| match origin {
+                "self" | "*" => origin.to_owned(),
+                origin => format!("\"{}\"", origin),
+            })
+            .collect();
+        match self
+            .permissions
+            .iter_mut()
+            .find(|(name, _)| name == feature)
+        {
+            Some(entry) => entry.1 = allowlist,
+            None => self.permissions.push((feature.to_owned(), allowlist)),
+        }
+        self
+    }
+
+    fn to_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if !self.csp.is_empty() {
+            let csp = self
+                .csp
+                .iter()
+                .map(|(directive, sources)| {
+                    let mut directive = directive.clone();
+                    for source in sources {
+                        directive.push(' ');
+                        directive.push_str(source);
+                    }
+                    directive
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            headers.insert(
+                CONTENT_SECURITY_POLICY,
+                HeaderValue::from_str(&csp)
+                    .unwrap_or_else(|_| panic!("illegal Content-Security-Policy: {:?}", csp)),
+            );
+        }
+        headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+        let referrer_policy = match self.referrer_policy {
+            ReferrerPolicy::NoReferrer => "no-referrer",
+            ReferrerPolicy::NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+            ReferrerPolicy::Origin => "origin",
+            ReferrerPolicy::OriginWhenCrossOrigin => "origin-when-cross-origin",
+            ReferrerPolicy::SameOrigin => "same-origin",
+            ReferrerPolicy::StrictOrigin => "strict-origin",
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+            ReferrerPolicy::UnsafeUrl => "unsafe-url",
+        };
+        headers.insert(REFERRER_POLICY, HeaderValue::from_static(referrer_policy));
+        match self.frame_options {
+            Some(FrameOptions::Deny) => {
+                headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+            }
+            Some(FrameOptions::SameOrigin) => {
+                headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+            }
+            None => (),
+        }
+        if !self.permissions.is_empty() {
+            let permissions = self
+                .permissions
+                .iter()
+                .map(|(feature, allowlist)| format!("{}=({})", feature, allowlist.join(" ")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            headers.insert(
+                "permissions-policy",
+                HeaderValue::from_str(&permissions)
+                    .unwrap_or_else(|_| panic!("illegal Permissions-Policy: {:?}", permissions)),
+            );
+        }
+        headers
+    }
+}
+
 /// Wrap a `Filter` to always set a header.
 #[derive(Clone, Debug)]
 pub struct WithHeader {
@@ -177,6 +561,44 @@ where
     }
 }
 
+/// Wrap a `Filter` to set an `ETag` from the body of the reply.
+#[derive(Clone, Debug)]
+pub struct WithEtag {
+    _p: (),
+}
+
+impl<F, R> WrapSealed<F> for WithEtag
+where
+    F: Filter<Extract = (R,)>,
+    R: Reply,
+    Infallible: CombineRejection<F::Error>,
+{
+    type Wrapped = AndThen<F, WithEtag_>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        filter.and_then(WithEtag_ { _p: () })
+    }
+}
+
+/// Wrap a `Filter` to set security headers if they are not already set.
+#[derive(Clone, Debug)]
+pub struct WithSecurityHeaders {
+    headers: Arc<HeaderMap>,
+}
+
+impl<F, R> WrapSealed<F> for WithSecurityHeaders
+where
+    F: Filter<Extract = (R,)>,
+    R: Reply,
+{
+    type Wrapped = Map<F, WithSecurityHeaders_>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        let with = WithSecurityHeaders_ { with: self.clone() };
+        filter.map(with)
+    }
+}
+
 fn assert_name_and_value<K, V>(name: K, value: V) -> (HeaderName, HeaderValue)
 where
     HeaderName: TryFrom<K>,
@@ -195,10 +617,57 @@ where
     (name, value)
 }
 
+// The 64-bit FNV-1a hash, which unlike the hashers of `std` is stable
+// between releases, so the `ETag`s made with it don't change when a server
+// is upgraded.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// The size of the largest body `etag` buffers to hash.
+const ETAG_MAX_BODY_SIZE: u64 = 1024 * 1024;
+
+// A strong `ETag` made of the `Fnv1a` hash and the length of a body.
+pub(crate) fn strong_etag(hash: u64, len: u64) -> ETag {
+    format!("\"{:016x}-{:x}\"", hash, len)
+        .parse()
+        .expect("hex is a legal entity tag")
+}
+
 mod sealed {
-    use super::{WithDefaultHeader, WithHeader, WithHeaders};
+    use std::convert::Infallible;
+    use std::future::Future;
+    use std::hash::Hasher;
+    use std::pin::Pin;
+
+    use headers::{ETag, HeaderMapExt, IfNoneMatch};
+    use http::{header, Method, StatusCode};
+    use hyper::body::HttpBody;
+    use hyper::Body;
+
+    use super::{
+        strong_etag, Fnv1a, WithDefaultHeader, WithHeader, WithHeaders, WithSecurityHeaders,
+        ETAG_MAX_BODY_SIZE,
+    };
     use crate::generic::{Func, One};
-    use crate::reply::{Reply, Reply_};
+    use crate::reply::{Reply, Reply_, Response};
 
     #[derive(Clone)]
     #[allow(missing_debug_implementations)]
@@ -254,4 +723,104 @@ mod sealed {
             Reply_(resp)
         }
     }
+
+    #[derive(Clone)]
+    #[allow(missing_debug_implementations)]
+    pub struct WithSecurityHeaders_ {
+        pub(super) with: WithSecurityHeaders,
+    }
+
+    impl<R: Reply> Func<One<R>> for WithSecurityHeaders_ {
+        type Output = Reply_;
+
+        fn call(&self, args: One<R>) -> Self::Output {
+            let mut resp = args.0.into_response();
+            for (name, value) in &*self.with.headers {
+                resp.headers_mut()
+                    .entry(name)
+                    .or_insert_with(|| value.clone());
+            }
+            Reply_(resp)
+        }
+    }
+
+    #[derive(Clone)]
+    #[allow(missing_debug_implementations)]
+    pub struct WithEtag_ {
+        pub(super) _p: (),
+    }
+
+    impl<R: Reply> Func<One<R>> for WithEtag_ {
+        type Output = Pin<Box<dyn Future<Output = Result<Reply_, Infallible>> + Send>>;
+
+        fn call(&self, args: One<R>) -> Self::Output {
+            let res = args.0.into_response();
+            let request = crate::route::with(|route| {
+                let method = route.method();
+                if method == Method::GET || method == Method::HEAD {
+                    Some(route.headers().typed_get::<IfNoneMatch>())
+                } else {
+                    None
+                }
+            });
+            let if_none_match = match request {
+                Some(if_none_match) if res.status() == StatusCode::OK => if_none_match,
+                _ => return Box::pin(futures::future::ok(Reply_(res))),
+            };
+            // Only bodies of a known, bounded length are buffered to be hashed.
+            let tagged = res.headers().contains_key(header::ETAG);
+            match res.body().size_hint().exact() {
+                _ if tagged => (),
+                Some(len) if len <= ETAG_MAX_BODY_SIZE => (),
+                _ => return Box::pin(futures::future::ok(Reply_(res))),
+            }
+
+            Box::pin(async move {
+                let mut res = res;
+                let etag = match res.headers().typed_get::<ETag>() {
+                    Some(etag) => etag,
+                    None => {
+                        let (parts, body) = res.into_parts();
+                        let body = match hyper::body::to_bytes(body).await {
+                            Ok(body) => body,
+                            Err(err) => {
+                                log::error!("etag failed to buffer response body: {}", err);
+                                let mut res = Response::new(Body::empty());
+                                *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                                return Ok(Reply_(res));
+                            }
+                        };
+                        let mut hasher = Fnv1a::default();
+                        hasher.write(&body);
+                        let etag = strong_etag(hasher.finish(), body.len() as u64);
+                        res = Response::from_parts(parts, Body::from(body));
+                        res.headers_mut().typed_insert(etag.clone());
+                        etag
+                    }
+                };
+
+                match if_none_match {
+                    Some(ref if_none_match) if !if_none_match.precondition_passes(&etag) => {
+                        // Keep the headers a `304` must repeat, from RFC 7232.
+                        let mut not_modified = Response::new(Body::empty());
+                        *not_modified.status_mut() = StatusCode::NOT_MODIFIED;
+                        for name in &[
+                            header::CACHE_CONTROL,
+                            header::CONTENT_LOCATION,
+                            header::DATE,
+                            header::ETAG,
+                            header::EXPIRES,
+                            header::VARY,
+                        ] {
+                            for value in res.headers().get_all(name) {
+                                not_modified.headers_mut().append(name, value.clone());
+                            }
+                        }
+                        Ok(Reply_(not_modified))
+                    }
+                    _ => Ok(Reply_(res)),
+                }
+            })
+        }
+    }
 }
@@ -0,0 +1,502 @@
+//! Runtime Router
+//!
+//! Most warp APIs are built once, at startup, by combining filters with
+//! `and` and `or`. A [`Router`](Router) is instead a table of routes that
+//! can be changed while the server is running, which is useful for things
+//! like plugin systems that mount endpoints after startup.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! let router = warp::router::Router::new();
+//!
+//! // The router is a `Filter`, and can be served like any other.
+//! let routes = warp::path("health")
+//!     .map(warp::reply)
+//!     .or(router.clone());
+//!
+//! // Later, possibly while serving requests...
+//! router.insert("GET", "/hello/:name", warp::router::params().map(|params: warp::router::Params| {
+//!     format!("Hello, {}!", params.get("name").unwrap_or("stranger"))
+//! }));
+//!
+//! router.remove("GET", "/hello/:name");
+//! ```
+
+use std::collections::HashMap;
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use futures::future;
+use http::Method;
+
+use crate::filter::{filter_fn_one, BoxedFilter, Filter, FilterBase, Internal};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+
+/// A table of routes that can be changed at runtime.
+///
+/// Each route is made up of a method, a path pattern, and a handler. The
+/// handler can be any `Filter` that extracts a single [`Reply`](crate::Reply).
+///
+/// Path patterns are made up of segments separated by `/`:
+///
+/// - `users` matches that exact segment.
+/// - `:id` matches any non-empty segment, and records it in the
+///   [`Params`](Params) under the name `id`.
+/// - `*` may only be the last segment, and matches the rest of the path.
+///   The rest of the path is left unmatched, so the handler can keep
+///   routing on it with other path filters.
+///
+/// When several patterns match a request, exact segments are preferred over
+/// parameters, and parameters over wildcards. Finding the route for a request
+/// takes time proportional to the length of its path, not to the number of
+/// routes, so a `Router` is also a good fit for dispatching very large route
+/// sets that would otherwise be a long chain of `or` filters.
+///
+/// A `Router` is cheap to clone, and all clones share the same routes.
+#[derive(Clone)]
+pub struct Router {
+    table: Arc<RwLock<Table>>,
+}
+
+/// Path parameters captured by a [`Router`](Router) pattern.
+///
+/// Use the [`params()`](params) filter to extract these in a handler.
+#[derive(Clone, Debug, Default)]
+pub struct Params {
+    pattern: Option<Arc<str>>,
+    params: Vec<(String, String)>,
+}
+
+// The routes are stored in a trie keyed by path segment, so that finding
+// the route for a request costs about the length of its path, instead of
+// the number of routes.
+#[derive(Default)]
+struct Table {
+    root: Node,
+    len: usize,
+}
+
+#[derive(Default)]
+struct Node {
+    statics: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    // Routes whose pattern ends at this node.
+    routes: Vec<Entry>,
+    // Routes whose pattern ends with a `*` after this node.
+    wildcards: Vec<Entry>,
+}
+
+struct Entry {
+    method: Method,
+    pattern: Pattern,
+    handler: BoxedFilter<(Response,)>,
+}
+
+struct Pattern {
+    raw: Arc<str>,
+    segments: Vec<Segment>,
+    tail: bool,
+}
+
+enum Segment {
+    Static(String),
+    Param(String),
+}
+
+impl Router {
+    /// Create a new `Router` without any routes.
+    pub fn new() -> Router {
+        Router {
+            table: Arc::new(RwLock::new(Table::default())),
+        }
+    }
+
+    /// Add a route to this `Router`.
+    ///
+    /// If a route with the same method and pattern already exists, its
+    /// handler is replaced. Patterns that only differ in the names of
+    /// their parameters are considered the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is not a valid `http::Method`, or if the
+    /// pattern is not valid.
+    pub fn insert<M, F, R>(&self, method: M, pattern: &str, handler: F)
+    where
+        Method: TryFrom<M>,
+        F: Filter<Extract = (R,)> + Send + Sync + 'static,
+        F::Error: Into<Rejection>,
+        R: Reply + 'static,
+    {
+        let method = match TryFrom::try_from(method) {
+            Ok(m) => m,
+            Err(_) => panic!("illegal Method"),
+        };
+        let pattern = Pattern::parse(pattern);
+        let handler = handler.map(Reply::into_response).boxed();
+
+        self.table
+            .write()
+            .expect("router lock poisoned")
+            .insert(Entry {
+                method,
+                pattern,
+                handler,
+            });
+    }
+
+    /// Remove a route from this `Router`.
+    ///
+    /// Returns whether a route with that method and pattern existed.
+    pub fn remove<M>(&self, method: M, pattern: &str) -> bool
+    where
+        Method: TryFrom<M>,
+    {
+        let method: Method = match TryFrom::try_from(method) {
+            Ok(m) => m,
+            Err(_) => return false,
+        };
+        let pattern = Pattern::parse(pattern);
+
+        self.table
+            .write()
+            .expect("router lock poisoned")
+            .remove(&method, &pattern)
+    }
+
+    /// Returns the number of routes in this `Router`.
+    pub fn len(&self) -> usize {
+        self.table.read().expect("router lock poisoned").len
+    }
+
+    /// Returns whether this `Router` has no routes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for Router {
+    fn default() -> Router {
+        Router::new()
+    }
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let table = self.table.read().expect("router lock poisoned");
+        let mut list = f.debug_list();
+        table.root.debug_entries(&mut list);
+        list.finish()
+    }
+}
+
+impl FilterBase for Router {
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<(Response,), Rejection>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let found = route::with(|route| {
+            self.table
+                .read()
+                .expect("router lock poisoned")
+                .lookup(route)
+        });
+
+        match found {
+            Ok(handler) => handler.filter(Internal),
+            Err(rejection) => Box::pin(future::err(rejection)),
+        }
+    }
+}
+
+impl Table {
+    fn insert(&mut self, entry: Entry) {
+        let mut node = &mut self.root;
+        for segment in &entry.pattern.segments {
+            node = match *segment {
+                Segment::Static(ref s) => node.statics.entry(s.clone()).or_default(),
+                Segment::Param(_) => node.param.get_or_insert_with(Default::default),
+            };
+        }
+
+        let entries = if entry.pattern.tail {
+            &mut node.wildcards
+        } else {
+            &mut node.routes
+        };
+
+        if let Some(existing) = entries.iter_mut().find(|e| e.method == entry.method) {
+            *existing = entry;
+        } else {
+            entries.push(entry);
+            self.len += 1;
+        }
+    }
+
+    fn remove(&mut self, method: &Method, pattern: &Pattern) -> bool {
+        let removed = self.root.remove(method, pattern, &pattern.segments);
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    fn lookup(&self, route: &mut Route) -> Result<BoxedFilter<(Response,)>, Rejection> {
+        let mut allowed = Vec::new();
+        let mut values = Vec::new();
+
+        let found = {
+            let path = route.path();
+            self.root
+                .find(path, path, 0, route.method(), &mut values, &mut allowed)
+                .map(|(entry, end)| {
+                    let params = entry
+                        .pattern
+                        .param_names()
+                        .zip(values.iter())
+                        .map(|(name, value)| (name.to_owned(), (*value).to_owned()))
+                        .collect();
+                    let pattern = Some(entry.pattern.raw.clone());
+                    (entry, end, Params { pattern, params })
+                })
+        };
+
+        match found {
+            Some((entry, end, params)) => {
+                log::trace!("router: matched {} {}", entry.method, entry.pattern.raw);
+                if end > 0 {
+                    route.set_unmatched_path(end);
+                }
+                route.extensions_mut().insert(params);
+                Ok(entry.handler.clone())
+            }
+            None if !allowed.is_empty() => Err(reject::method_not_allowed(&allowed)),
+            None => Err(reject::not_found()),
+        }
+    }
+}
+
+impl Node {
+    // Finds the route for the `rest` of the `path`, preferring exact
+    // segments over parameters, and parameters over wildcards.
+    //
+    // Returns the entry and the length of the path that was matched.
+    fn find<'a, 'p>(
+        &'a self,
+        path: &'p str,
+        rest: &'p str,
+        end: usize,
+        method: &Method,
+        values: &mut Vec<&'p str>,
+        allowed: &mut Vec<Method>,
+    ) -> Option<(&'a Entry, usize)> {
+        if rest.is_empty() {
+            if let Some(entry) = pick(&self.routes, method, allowed) {
+                return Some((entry, end));
+            }
+        } else {
+            let (seg, next) = match rest.find('/') {
+                Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+                None => (rest, ""),
+            };
+            let seg_end = path.len() - rest.len() + seg.len();
+
+            if let Some(child) = self.statics.get(seg) {
+                if let Some(found) = child.find(path, next, seg_end, method, values, allowed) {
+                    return Some(found);
+                }
+            }
+
+            if let Some(ref child) = self.param {
+                if !seg.is_empty() {
+                    values.push(seg);
+                    if let Some(found) = child.find(path, next, seg_end, method, values, allowed) {
+                        return Some(found);
+                    }
+                    values.pop();
+                }
+            }
+        }
+
+        pick(&self.wildcards, method, allowed).map(|entry| (entry, end))
+    }
+
+    fn remove(&mut self, method: &Method, pattern: &Pattern, segments: &[Segment]) -> bool {
+        let (segment, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => {
+                let entries = if pattern.tail {
+                    &mut self.wildcards
+                } else {
+                    &mut self.routes
+                };
+                let before = entries.len();
+                entries.retain(|entry| entry.method != *method);
+                return entries.len() != before;
+            }
+        };
+
+        match *segment {
+            Segment::Static(ref s) => {
+                let (removed, empty) = match self.statics.get_mut(s) {
+                    Some(child) => (child.remove(method, pattern, rest), child.is_empty()),
+                    None => return false,
+                };
+                if empty {
+                    self.statics.remove(s);
+                }
+                removed
+            }
+            Segment::Param(_) => {
+                let (removed, empty) = match self.param {
+                    Some(ref mut child) => (child.remove(method, pattern, rest), child.is_empty()),
+                    None => return false,
+                };
+                if empty {
+                    self.param = None;
+                }
+                removed
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.statics.is_empty()
+            && self.param.is_none()
+            && self.routes.is_empty()
+            && self.wildcards.is_empty()
+    }
+
+    fn debug_entries(&self, list: &mut fmt::DebugList<'_, '_>) {
+        for entry in self.routes.iter().chain(self.wildcards.iter()) {
+            list.entry(&format_args!("{} {}", entry.method, entry.pattern.raw));
+        }
+        for child in self.statics.values() {
+            child.debug_entries(list);
+        }
+        if let Some(ref child) = self.param {
+            child.debug_entries(list);
+        }
+    }
+}
+
+// Records the methods of the `entries` in `allowed` if none of them match.
+fn pick<'a>(entries: &'a [Entry], method: &Method, allowed: &mut Vec<Method>) -> Option<&'a Entry> {
+    let found = entries.iter().find(|entry| entry.method == *method);
+    if found.is_none() {
+        for entry in entries {
+            if !allowed.contains(&entry.method) {
+                allowed.push(entry.method.clone());
+            }
+        }
+    }
+    found
+}
+
+impl Pattern {
+    fn parse(raw: &str) -> Pattern {
+        let mut segments = Vec::new();
+        let mut tail = false;
+
+        for seg in raw.split('/').filter(|seg| !seg.is_empty()) {
+            assert!(
+                !tail,
+                "router pattern wildcard must be the last segment: {:?}",
+                raw
+            );
+
+            if seg == "*" {
+                tail = true;
+            } else if let Some(name) = seg.strip_prefix(':') {
+                assert!(
+                    !name.is_empty(),
+                    "router pattern parameters must be named: {:?}",
+                    raw
+                );
+                segments.push(Segment::Param(name.to_owned()));
+            } else {
+                segments.push(Segment::Static(seg.to_owned()));
+            }
+        }
+
+        // Normalize the pattern, so that `insert` and `remove` agree
+        // regardless of leading or trailing slashes.
+        let mut normalized = String::new();
+        for segment in &segments {
+            normalized.push('/');
+            match *segment {
+                Segment::Static(ref s) => normalized.push_str(s),
+                Segment::Param(ref name) => {
+                    normalized.push(':');
+                    normalized.push_str(name);
+                }
+            }
+        }
+        if tail {
+            normalized.push_str("/*");
+        } else if normalized.is_empty() {
+            normalized.push('/');
+        }
+
+        Pattern {
+            raw: normalized.into(),
+            segments,
+            tail,
+        }
+    }
+
+    fn param_names(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match *segment {
+            Segment::Param(ref name) => Some(name.as_str()),
+            Segment::Static(_) => None,
+        })
+    }
+}
+
+impl Params {
+    /// Get the pattern of the route that matched, such as `/hello/:name`.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    /// Get the value of a named parameter.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterate over all captured parameters, in the order they appear in
+    /// the pattern.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+/// Extract the [`Params`](Params) captured by the [`Router`](Router) that
+/// dispatched this request.
+///
+/// If the request wasn't dispatched by a `Router`, the `Params` are empty.
+pub fn params() -> impl Filter<Extract = (Params,), Error = Infallible> + Copy {
+    filter_fn_one(|route| {
+        future::ok(
+            route
+                .extensions()
+                .get::<Params>()
+                .cloned()
+                .unwrap_or_default(),
+        )
+    })
+}
@@ -0,0 +1,83 @@
+//! Route tables
+//!
+//! Every `and` and `or` nests the types of the filters it combines, so a
+//! large API built as one long chain has a type that grows with each route.
+//! Compiling it, and every combinator instantiated along the way, gets
+//! slower and the binary bigger, out of proportion with the number of
+//! routes.
+//!
+//! The [`routes!`](../../macro.routes.html) macro combines routes with `or`
+//! too, but boxes each of them, and the table after each of them, so the
+//! type of the table stays the same however many routes it has. This costs
+//! a dynamic dispatch per route tried on each request.
+
+use crate::filter::{BoxedFilter, Filter};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+
+/// Box a route, so it can be combined with routes of other types.
+///
+/// This is what [`routes!`](../../macro.routes.html) does with each of its
+/// routes, and can be used to return routes from functions without naming
+/// their reply type.
+///
+/// # Example
+///
+/// ```
+/// use warp::filters::BoxedFilter;
+/// use warp::reply::Response;
+/// use warp::Filter;
+///
+/// fn hello() -> BoxedFilter<(Response,)> {
+///     warp::routes::boxed(warp::path("hello").map(|| "Hello, World!"))
+/// }
+/// ```
+pub fn boxed<F, R>(filter: F) -> BoxedFilter<(Response,)>
+where
+    F: Filter<Extract = (R,)> + Send + Sync + 'static,
+    F::Error: Into<Rejection>,
+    R: Reply + 'static,
+{
+    filter.map(Reply::into_response).boxed()
+}
+
+/// Combine routes into a table, trying each of them in order.
+///
+/// This behaves like chaining the routes with `or`, rejecting with the
+/// most relevant rejection when none of them match, but every route is
+/// [boxed](crate::routes::boxed), and the table extracts a
+/// [`Response`](crate::reply::Response), whatever the number of routes.
+///
+/// Routes can be of any `Filter` type extracting a single `Reply`,
+/// including other tables, so a large API can be split into tables of a
+/// few dozen routes each.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let users = warp::routes![
+///     warp::path!("users").map(|| "all users"),
+///     warp::path!("users" / u32).map(|id| format!("user #{}", id)),
+/// ];
+///
+/// let api = warp::routes![
+///     warp::path!("health").map(warp::reply),
+///     users,
+/// ];
+///
+/// warp::serve(api);
+/// ```
+#[macro_export]
+macro_rules! routes {
+    ($first:expr $(, $rest:expr)* $(,)?) => ({
+        let routes = $crate::routes::boxed($first);
+        $(
+            let routes = $crate::Filter::boxed($crate::Filter::unify(
+                $crate::Filter::or(routes, $crate::routes::boxed($rest)),
+            ));
+        )*
+        routes
+    });
+}
@@ -0,0 +1,298 @@
+//! JSON-RPC
+//!
+//! The [`json_rpc`] filter serves a [JSON-RPC 2.0][spec] endpoint: it parses
+//! single and batched requests, dispatches them to the async methods of an
+//! [`RpcRouter`], and replies with their results, or with error objects
+//! following the specification.
+//!
+//! Notifications, requests without an `id`, are run but get no response. A
+//! request, or a batch, of notifications alone gets a `204 No Content`.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::rpc::{RpcError, RpcRouter};
+//! use warp::Filter;
+//!
+//! let router = RpcRouter::new()
+//!     .method("add", |(a, b): (i64, i64)| async move { Ok(a + b) })
+//!     .method("divide", |(a, b): (i64, i64)| async move {
+//!         if b == 0 {
+//!             return Err(RpcError::invalid_params("division by zero"));
+//!         }
+//!         Ok(a / b)
+//!     });
+//!
+//! let route = warp::path("rpc").and(warp::rpc::json_rpc(router));
+//! ```
+//!
+//! [spec]: https://www.jsonrpc.org/specification
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future;
+use http::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::filter::Filter;
+use crate::reject::Rejection;
+use crate::reply::{self, Reply, Response};
+
+type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+type Method = Arc<dyn Fn(Value) -> MethodFuture + Send + Sync>;
+
+/// The methods of a JSON-RPC endpoint, served with [`json_rpc`].
+#[derive(Clone, Default)]
+pub struct RpcRouter {
+    methods: HashMap<String, Method>,
+}
+
+impl RpcRouter {
+    /// Create an `RpcRouter` without methods.
+    pub fn new() -> RpcRouter {
+        RpcRouter::default()
+    }
+
+    /// Register an async method.
+    ///
+    /// The `params` of requests are deserialized into the argument of
+    /// `handler`, from an array or an object, or from `null` when they are
+    /// omitted, so methods without parameters can take `()`. Requests with
+    /// params that can't be deserialized get an "Invalid params" error.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a method with the same name is already
+    /// registered, or if the name starts with `rpc.`, which is reserved.
+    pub fn method<F, P, U, R>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(P) -> U + Send + Sync + 'static,
+        P: DeserializeOwned,
+        U: Future<Output = Result<R, RpcError>> + Send + 'static,
+        R: Serialize,
+    {
+        if name.starts_with("rpc.") {
+            panic!("illegal JSON-RPC method name: {:?} is reserved", name);
+        }
+        let method: Method = Arc::new(move |params: Value| {
+            let params = match serde_json::from_value::<P>(params) {
+                Ok(params) => params,
+                Err(err) => {
+                    return Box::pin(future::err(RpcError::invalid_params(err.to_string())))
+                        as MethodFuture
+                }
+            };
+            let fut = handler(params);
+            Box::pin(async move {
+                let result = fut.await?;
+                serde_json::to_value(result).map_err(|err| {
+                    log::error!("json_rpc result serialize error: {}", err);
+                    RpcError::internal_error()
+                })
+            })
+        });
+        if self.methods.insert(name.to_owned(), method).is_some() {
+            panic!("illegal JSON-RPC method: {:?} is already registered", name);
+        }
+        self
+    }
+
+    async fn call(&self, request: Value) -> Option<Value> {
+        let (id, method, params) = match parse_request(request) {
+            Ok(request) => request,
+            Err(id) => return Some(error_response(id, RpcError::invalid_request())),
+        };
+        let result = match self.methods.get(&method) {
+            Some(handler) => handler(params).await,
+            None => Err(RpcError::method_not_found()),
+        };
+        // Notifications get no response, even on errors.
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(id, err),
+        })
+    }
+}
+
+impl fmt::Debug for RpcRouter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RpcRouter")
+            .field("methods", &self.methods.keys())
+            .finish()
+    }
+}
+
+// Returns the id, if it isn't a notification, method and params of a
+// request, or the id to report an invalid request with.
+fn parse_request(request: Value) -> Result<(Option<Value>, String, Value), Value> {
+    let mut request = match request {
+        Value::Object(request) => request,
+        _ => return Err(Value::Null),
+    };
+    let id = match request.remove("id") {
+        None => None,
+        Some(id @ Value::Null) | Some(id @ Value::Number(_)) | Some(id @ Value::String(_)) => {
+            Some(id)
+        }
+        Some(_) => return Err(Value::Null),
+    };
+    let invalid = || id.clone().unwrap_or(Value::Null);
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid());
+    }
+    let method = match request.remove("method") {
+        Some(Value::String(method)) => method,
+        _ => return Err(invalid()),
+    };
+    let params = match request.remove("params") {
+        None => Value::Null,
+        Some(params @ Value::Array(_)) | Some(params @ Value::Object(_)) => params,
+        Some(_) => return Err(invalid()),
+    };
+    Ok((id, method, params))
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    let mut error = Map::new();
+    error.insert("code".to_owned(), err.code.into());
+    error.insert("message".to_owned(), err.message.into());
+    if let Some(data) = err.data {
+        error.insert("data".to_owned(), data);
+    }
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// Creates a `Filter` serving a JSON-RPC endpoint with the methods of
+/// `router`.
+///
+/// Requests are read from the body of a `POST`. Every response, errors
+/// included, is a `200 OK`, except for notifications.
+pub fn json_rpc(
+    router: RpcRouter,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let router = Arc::new(router);
+    crate::post()
+        .and(crate::body::bytes())
+        .and_then(move |body: Bytes| {
+            let router = router.clone();
+            async move { Ok::<_, Rejection>(dispatch(&router, &body).await) }
+        })
+}
+
+async fn dispatch(router: &RpcRouter, body: &[u8]) -> Response {
+    let request = match serde_json::from_slice::<Value>(body) {
+        Ok(request) => request,
+        Err(err) => {
+            log::debug!("json_rpc parse error: {}", err);
+            return reply::json(&error_response(Value::Null, RpcError::parse_error()))
+                .into_response();
+        }
+    };
+    let response = match request {
+        Value::Array(requests) if requests.is_empty() => {
+            Some(error_response(Value::Null, RpcError::invalid_request()))
+        }
+        Value::Array(requests) => {
+            let responses = future::join_all(requests.into_iter().map(|req| router.call(req)))
+                .await
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            if responses.is_empty() {
+                None
+            } else {
+                Some(Value::Array(responses))
+            }
+        }
+        request => router.call(request).await,
+    };
+    match response {
+        Some(response) => reply::json(&response).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// A JSON-RPC error object, returned by methods that fail.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// Create an error with an application defined code.
+    ///
+    /// Codes from -32768 to -32000 are reserved by the specification.
+    pub fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Add more information about the error.
+    pub fn with_data(mut self, data: Value) -> RpcError {
+        self.data = Some(data);
+        self
+    }
+
+    /// The body of the request isn't valid JSON (-32700).
+    pub fn parse_error() -> RpcError {
+        RpcError::new(-32700, "Parse error")
+    }
+
+    /// The request isn't a valid request object (-32600).
+    pub fn invalid_request() -> RpcError {
+        RpcError::new(-32600, "Invalid Request")
+    }
+
+    /// The method doesn't exist (-32601).
+    pub fn method_not_found() -> RpcError {
+        RpcError::new(-32601, "Method not found")
+    }
+
+    /// The params of the method are invalid (-32602), with a description
+    /// of the problem as data.
+    pub fn invalid_params(reason: impl Into<String>) -> RpcError {
+        RpcError::new(-32602, "Invalid params").with_data(Value::String(reason.into()))
+    }
+
+    /// The method failed unexpectedly (-32603).
+    pub fn internal_error() -> RpcError {
+        RpcError::new(-32603, "Internal error")
+    }
+
+    /// The code of the error.
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+
+    /// The message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The data of the error.
+    pub fn data(&self) -> Option<&Value> {
+        self.data.as_ref()
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl StdError for RpcError {}
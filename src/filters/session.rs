@@ -0,0 +1,621 @@
+//! Sessions
+//!
+//! Cookie-based sessions, made of three parts:
+//!
+//! - A [`SessionStore`] keeps the data of each session. A [`MemoryStore`]
+//!   and a signed [`CookieStore`] are included.
+//! - The [`Sessions`] wrapper loads the session of a request before running
+//!   the wrapped filter, and afterwards persists any changes and sets the
+//!   session cookie.
+//! - The [`session()`] filter extracts the [`Session`] of the request.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::session::{MemoryStore, Session, Sessions};
+//! use warp::Filter;
+//!
+//! let sessions = Sessions::new(MemoryStore::new()).secure(true);
+//!
+//! let visits = warp::path("visits")
+//!     .and(warp::session::session())
+//!     .map(|session: Session| {
+//!         let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+//!         session.insert("visits", visits).unwrap();
+//!         format!("visits: {}", visits)
+//!     })
+//!     .with(sessions);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use ::cookie::{Cookie as SignedCookie, CookieJar, Key};
+use futures::future;
+use headers::{Cookie, HeaderMapExt};
+use http::header::{HeaderValue, SET_COOKIE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The data of a session.
+pub type SessionData = serde_json::Map<String, Value>;
+
+/// The `Future` returned by the methods of a [`SessionStore`].
+pub type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send>>;
+
+/// Extract the [`Session`] of the request.
+///
+/// The route must be wrapped with [`Sessions`], otherwise this rejects with
+/// a `MissingExtension`.
+pub fn session() -> impl Filter<Extract = (Session,), Error = Rejection> + Copy {
+    crate::ext::get::<Session>()
+}
+
+/// A place to keep session data.
+///
+/// Sessions are identified by the value of their cookie, which is decided
+/// by the store.
+pub trait SessionStore: Send + Sync + 'static {
+    /// Load the data of the session with this cookie value.
+    ///
+    /// Returns `None` if there is no such session.
+    fn load(&self, cookie: &str) -> StoreFuture<Option<SessionData>>;
+
+    /// Save the data of a session, and return its cookie value.
+    ///
+    /// `cookie` is the cookie value of the session, if it was loaded from
+    /// this store.
+    fn store(&self, cookie: Option<&str>, data: SessionData) -> StoreFuture<String>;
+
+    /// Remove the session with this cookie value.
+    fn destroy(&self, cookie: &str) -> StoreFuture<()>;
+}
+
+/// A [`SessionStore`] that keeps sessions in memory.
+///
+/// The cookie holds a random session id. Sessions are lost when the server
+/// restarts.
+///
+/// Sessions expire once they haven't been used for 24 hours, or the
+/// duration set with [`ttl`](MemoryStore::ttl), and are then evicted.
+#[derive(Clone, Debug)]
+pub struct MemoryStore {
+    inner: Arc<RwLock<Entries>>,
+    ttl: Duration,
+}
+
+#[derive(Debug)]
+struct Entries {
+    sessions: HashMap<String, Entry>,
+    // When expired sessions were last evicted.
+    evicted: Instant,
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: SessionData,
+    expires: Instant,
+}
+
+impl MemoryStore {
+    /// Create an empty `MemoryStore`.
+    pub fn new() -> MemoryStore {
+        MemoryStore {
+            inner: Arc::new(RwLock::new(Entries {
+                sessions: HashMap::new(),
+                evicted: Instant::now(),
+            })),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Expire sessions once they haven't been used for `ttl`.
+    ///
+    /// With a [`Sessions::max_age`], this should be the same duration, so
+    /// that sessions last as long as their cookie.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The number of sessions in the store.
+    ///
+    /// Expired sessions are counted until they are evicted.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().sessions.len()
+    }
+
+    /// Whether the store has no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> MemoryStore {
+        MemoryStore::new()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, cookie: &str) -> StoreFuture<Option<SessionData>> {
+        let now = Instant::now();
+        let mut inner = self.inner.write().unwrap();
+        let data = match inner.sessions.get_mut(cookie) {
+            Some(entry) if entry.expires > now => {
+                entry.expires = now + self.ttl;
+                Some(entry.data.clone())
+            }
+            Some(_) => {
+                inner.sessions.remove(cookie);
+                None
+            }
+            None => None,
+        };
+        Box::pin(future::ok(data))
+    }
+
+    fn store(&self, cookie: Option<&str>, data: SessionData) -> StoreFuture<String> {
+        let id = cookie.map(ToOwned::to_owned).unwrap_or_else(|| {
+            let id: [u8; 32] = rand::random();
+            base64::encode_config(id, base64::URL_SAFE_NO_PAD)
+        });
+        let now = Instant::now();
+        let mut inner = self.inner.write().unwrap();
+        // Sessions that are never loaded again are evicted every `ttl`.
+        if now.duration_since(inner.evicted) >= self.ttl {
+            inner.sessions.retain(|_, entry| entry.expires > now);
+            inner.evicted = now;
+        }
+        let entry = Entry {
+            data,
+            expires: now + self.ttl,
+        };
+        inner.sessions.insert(id.clone(), entry);
+        Box::pin(future::ok(id))
+    }
+
+    fn destroy(&self, cookie: &str) -> StoreFuture<()> {
+        self.inner.write().unwrap().sessions.remove(cookie);
+        Box::pin(future::ok(()))
+    }
+}
+
+/// A [`SessionStore`] that keeps the session data in the cookie itself.
+///
+/// The cookie is signed, so it cannot be tampered with, but the data can be
+/// read by the client. Cookies are limited in size, so only small sessions
+/// should be kept this way.
+#[derive(Clone)]
+pub struct CookieStore {
+    key: Key,
+}
+
+impl CookieStore {
+    /// Create a `CookieStore` signing with `key`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `key` is shorter than 64 bytes.
+    pub fn new(key: &[u8]) -> CookieStore {
+        assert!(key.len() >= 64, "session key must be at least 64 bytes");
+        CookieStore {
+            key: Key::from(key),
+        }
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CookieStore").finish()
+    }
+}
+
+// Name used for the signature, independent of the configured cookie name.
+const SIGNED_NAME: &str = "session";
+
+impl SessionStore for CookieStore {
+    fn load(&self, cookie: &str) -> StoreFuture<Option<SessionData>> {
+        let mut jar = CookieJar::new();
+        jar.add_original(SignedCookie::new(SIGNED_NAME, cookie.to_owned()));
+        let data = jar
+            .signed(&self.key)
+            .get(SIGNED_NAME)
+            .and_then(|cookie| base64::decode_config(cookie.value(), base64::URL_SAFE_NO_PAD).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok());
+        Box::pin(future::ok(data))
+    }
+
+    fn store(&self, _cookie: Option<&str>, data: SessionData) -> StoreFuture<String> {
+        let json = Value::Object(data).to_string();
+        let value = base64::encode_config(&json, base64::URL_SAFE_NO_PAD);
+        let mut jar = CookieJar::new();
+        jar.signed(&self.key)
+            .add(SignedCookie::new(SIGNED_NAME, value));
+        let signed = jar
+            .get(SIGNED_NAME)
+            .expect("signed cookie was just added")
+            .value()
+            .to_owned();
+        Box::pin(future::ok(signed))
+    }
+
+    fn destroy(&self, _cookie: &str) -> StoreFuture<()> {
+        Box::pin(future::ok(()))
+    }
+}
+
+/// The session of a request.
+///
+/// A `Session` is a cheap handle, and clones refer to the same session.
+/// Changes are persisted by the [`Sessions`] wrapper once the request has
+/// been handled.
+#[derive(Clone, Debug)]
+pub struct Session {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    data: SessionData,
+    cookie: Option<String>,
+    changed: bool,
+    renew: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    fn new(cookie: Option<String>, data: SessionData) -> Session {
+        Session {
+            state: Arc::new(Mutex::new(State {
+                data,
+                cookie,
+                ..State::default()
+            })),
+        }
+    }
+
+    /// Get a value of the session, deserialized as a `T`.
+    ///
+    /// Returns `None` if there is no such value, or it is not a `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let state = self.state.lock().unwrap();
+        let value = state.data.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    /// Set a value of the session.
+    pub fn insert<T: Serialize>(&self, key: &str, value: T) -> Result<(), crate::Error> {
+        let value = serde_json::to_value(value).map_err(crate::Error::new)?;
+        let mut state = self.state.lock().unwrap();
+        state.data.insert(key.to_owned(), value);
+        state.changed = true;
+        Ok(())
+    }
+
+    /// Remove a value of the session.
+    pub fn remove(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.data.remove(key).is_some() {
+            state.changed = true;
+        }
+    }
+
+    /// Remove all values of the session.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.changed = true;
+    }
+
+    /// Give the session a new cookie, keeping its values.
+    ///
+    /// This should be done whenever privileges change, such as when a user
+    /// logs in, to prevent session fixation.
+    pub fn renew(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.renew = true;
+        state.changed = true;
+    }
+
+    /// Remove the session from the store, and its cookie from the client.
+    pub fn destroy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+pub use crate::filters::cookie::SameSite;
+
+/// A wrapper that loads and persists sessions.
+///
+/// Created with [`Sessions::new`], and applied to routes with
+/// [`Filter::with`].
+pub struct Sessions<S> {
+    store: Arc<S>,
+    config: Arc<Config>,
+}
+
+#[derive(Debug)]
+struct Config {
+    name: String,
+    path: String,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    same_site: SameSite,
+    secure: bool,
+    http_only: bool,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    /// Create a `Sessions` wrapper with the given store.
+    ///
+    /// The cookie is named `session`, applies to path `/`, lasts until the
+    /// browser is closed, and is `HttpOnly` and `SameSite=Lax`.
+    pub fn new(store: S) -> Sessions<S> {
+        Sessions {
+            store: Arc::new(store),
+            config: Arc::new(Config {
+                name: "session".to_owned(),
+                path: "/".to_owned(),
+                domain: None,
+                max_age: None,
+                same_site: SameSite::Lax,
+                secure: false,
+                http_only: true,
+            }),
+        }
+    }
+
+    /// Set the name of the session cookie.
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.config_mut().name = name.to_owned();
+        self
+    }
+
+    /// Set the `Path` of the session cookie.
+    pub fn path(mut self, path: &str) -> Self {
+        self.config_mut().path = path.to_owned();
+        self
+    }
+
+    /// Set the `Domain` of the session cookie.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.config_mut().domain = Some(domain.to_owned());
+        self
+    }
+
+    /// Keep the session cookie for `max_age`, instead of until the browser
+    /// is closed.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.config_mut().max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `SameSite` attribute of the session cookie.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.config_mut().same_site = same_site;
+        self
+    }
+
+    /// Set whether the session cookie is only sent over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.config_mut().secure = secure;
+        self
+    }
+
+    /// Set whether the session cookie is hidden from scripts.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.config_mut().http_only = http_only;
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        Arc::get_mut(&mut self.config).expect("Sessions is configured before being cloned")
+    }
+}
+
+impl<S> Clone for Sessions<S> {
+    fn clone(&self) -> Self {
+        Sessions {
+            store: self.store.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for Sessions<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sessions")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl<S, F> WrapSealed<F> for Sessions<S>
+where
+    S: SessionStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithSessions<S, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithSessions {
+            sessions: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`Sessions`].
+pub struct WithSessions<S, F> {
+    sessions: Sessions<S>,
+    filter: F,
+}
+
+impl<S, F: Clone> Clone for WithSessions<S, F> {
+    fn clone(&self) -> Self {
+        WithSessions {
+            sessions: self.sessions.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for WithSessions<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithSessions")
+            .field("sessions", &self.sessions)
+            .finish()
+    }
+}
+
+impl<S, F> FilterBase for WithSessions<S, F>
+where
+    S: SessionStore,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let cookie = route::with(|route| {
+            route.headers().typed_get::<Cookie>().and_then(|cookie| {
+                cookie
+                    .get(&self.sessions.config.name)
+                    .map(ToOwned::to_owned)
+            })
+        });
+        let sessions = self.sessions.clone();
+        let filter = self.filter.clone();
+
+        Box::pin(async move {
+            let session = match cookie {
+                Some(cookie) => match sessions.store.load(&cookie).await {
+                    Ok(Some(data)) => Session::new(Some(cookie), data),
+                    Ok(None) => Session::new(None, SessionData::new()),
+                    Err(err) => return Err(session_error(err)),
+                },
+                None => Session::new(None, SessionData::new()),
+            };
+            route::with(|route| route.extensions_mut().insert(session.clone()));
+
+            let reply = filter.filter(Internal).await.map_err(Into::into)?;
+            let mut res = reply.into_response();
+
+            if let Some(cookie) = sessions.persist(&session).await? {
+                res.headers_mut().append(SET_COOKIE, cookie);
+            }
+            Ok((res,))
+        })
+    }
+}
+
+impl<S: SessionStore> Sessions<S> {
+    // Save the changes to a session, returning a new `Set-Cookie` if needed.
+    async fn persist(&self, session: &Session) -> Result<Option<HeaderValue>, Rejection> {
+        let (cookie, data, renew, destroyed) = {
+            let mut state = session.state.lock().unwrap();
+            if !state.changed && !state.destroyed {
+                return Ok(None);
+            }
+            let data = std::mem::replace(&mut state.data, SessionData::new());
+            (state.cookie.take(), data, state.renew, state.destroyed)
+        };
+
+        if destroyed || renew {
+            if let Some(ref cookie) = cookie {
+                self.store.destroy(cookie).await.map_err(session_error)?;
+            }
+            if destroyed {
+                return Ok(cookie.map(|_| self.config.removal()));
+            }
+        }
+
+        let cookie = if renew { None } else { cookie };
+        let value = self
+            .store
+            .store(cookie.as_deref(), data)
+            .await
+            .map_err(session_error)?;
+        Ok(Some(self.config.set_cookie(&value)))
+    }
+}
+
+impl Config {
+    fn set_cookie(&self, value: &str) -> HeaderValue {
+        let mut cookie = format!("{}={}", self.name, value);
+        self.push_attributes(&mut cookie, self.max_age.map(|age| age.as_secs()));
+        HeaderValue::from_str(&cookie).expect("session cookie is a valid header value")
+    }
+
+    fn removal(&self) -> HeaderValue {
+        let mut cookie = format!("{}=", self.name);
+        self.push_attributes(&mut cookie, Some(0));
+        HeaderValue::from_str(&cookie).expect("session cookie is a valid header value")
+    }
+
+    fn push_attributes(&self, cookie: &mut String, max_age: Option<u64>) {
+        cookie.push_str("; Path=");
+        cookie.push_str(&self.path);
+        if let Some(ref domain) = self.domain {
+            cookie.push_str("; Domain=");
+            cookie.push_str(domain);
+        }
+        if let Some(max_age) = max_age {
+            cookie.push_str(&format!("; Max-Age={}", max_age));
+        }
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        cookie.push_str("; SameSite=");
+        cookie.push_str(self.same_site.as_str());
+    }
+}
+
+fn session_error(err: BoxError) -> Rejection {
+    log::error!("session store error: {}", err);
+    reject::known(SessionError { cause: err })
+}
+
+/// An error used in rejections when a [`SessionStore`] fails.
+#[derive(Debug)]
+pub struct SessionError {
+    cause: BoxError,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Session store error: {}", self.cause)
+    }
+}
+
+impl StdError for SessionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.cause)
+    }
+}
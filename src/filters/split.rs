@@ -0,0 +1,159 @@
+//! Traffic splitting filters.
+//!
+//! A [`Split`] sends a share of the requests to each of its branches, in
+//! proportion to their weights, such as 95% of the traffic to the current
+//! version of a route, and 5% to a canary release.
+//!
+//! By default, each request is drawn at random. Splits can instead be
+//! sticky, keyed by a header or cookie identifying the client, so that a
+//! client keeps getting the same branch as long as the weights don't
+//! change.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use headers::{Cookie, HeaderMapExt};
+
+use crate::filter::{BoxedFilter, Filter, FilterBase, Internal};
+use crate::reject::Rejection;
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+
+/// Create a traffic split, without branches at first.
+///
+/// Add branches with [`Split::branch`]. A split without branches rejects
+/// every request with `404 Not Found`. The requests sent to a branch that
+/// rejects them are rejected, without trying the other branches.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let search = warp::path("search").and(
+///     warp::split()
+///         .branch(95, warp::any().map(|| "search"))
+///         .branch(5, warp::any().map(|| "new search"))
+///         .sticky_cookie("session"),
+/// );
+/// ```
+pub fn split() -> Split {
+    Split {
+        branches: Arc::new(Vec::new()),
+        key: None,
+    }
+}
+
+/// A `Filter` splitting traffic between routes, created with [`split`].
+#[derive(Clone)]
+pub struct Split {
+    branches: Arc<Vec<(u32, BoxedFilter<(Response,)>)>>,
+    key: Option<Key>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Key {
+    Header(&'static str),
+    Cookie(&'static str),
+}
+
+impl Split {
+    /// Add a branch getting a share of `weight` of the requests.
+    ///
+    /// Weights are relative to the sum of the weights of the split, so
+    /// weights of `95` and `5`, or `19` and `1`, send 5% of the requests to
+    /// the second branch.
+    pub fn branch<F, T>(mut self, weight: u32, route: F) -> Self
+    where
+        F: Filter<Extract = (T,)> + Send + Sync + 'static,
+        F::Error: Into<Rejection>,
+        T: Reply + 'static,
+    {
+        Arc::make_mut(&mut self.branches).push((weight, crate::routes::boxed(route)));
+        self
+    }
+
+    /// Keep sending the requests with the same value of the header `name`
+    /// to the same branch.
+    ///
+    /// Requests without the header are drawn at random.
+    pub fn sticky_header(mut self, name: &'static str) -> Self {
+        self.key = Some(Key::Header(name));
+        self
+    }
+
+    /// Keep sending the requests with the same value of the cookie `name`
+    /// to the same branch.
+    ///
+    /// Requests without the cookie are drawn at random.
+    pub fn sticky_cookie(mut self, name: &'static str) -> Self {
+        self.key = Some(Key::Cookie(name));
+        self
+    }
+
+    // A number in `[0, 1)`, from the key of the request, or else random.
+    fn draw(&self, route: &Route) -> f64 {
+        let mut hasher = DefaultHasher::new();
+        let hashed = match self.key {
+            Some(Key::Header(name)) => route
+                .headers()
+                .get(name)
+                .map(|value| value.as_bytes().hash(&mut hasher))
+                .is_some(),
+            Some(Key::Cookie(name)) => route
+                .headers()
+                .typed_get::<Cookie>()
+                .and_then(|cookie| cookie.get(name).map(|value| value.hash(&mut hasher)))
+                .is_some(),
+            None => false,
+        };
+        if hashed {
+            (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+        } else {
+            crate::filters::when::random_fraction()
+        }
+    }
+
+    fn pick(&self, point: f64) -> Option<&BoxedFilter<(Response,)>> {
+        let total: u64 = self.branches.iter().map(|(w, _)| u64::from(*w)).sum();
+        let mut point = (point * total as f64) as u64;
+        for (weight, branch) in self.branches.iter() {
+            let weight = u64::from(*weight);
+            if point < weight {
+                return Some(branch);
+            }
+            point -= weight;
+        }
+        None
+    }
+}
+
+impl fmt::Debug for Split {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Split")
+            .field(
+                "weights",
+                &self.branches.iter().map(|(w, _)| w).collect::<Vec<_>>(),
+            )
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl FilterBase for Split {
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let point = route::with(|route| self.draw(route));
+        match self.pick(point) {
+            Some(branch) => branch.filter(Internal),
+            None => Box::pin(async { Err(crate::reject::not_found()) }),
+        }
+    }
+}
@@ -427,11 +427,19 @@ where
 }
 
 /// Configure the interval between keep-alive messages, the content
-/// of each message, and the associated stream.
-#[derive(Debug)]
+/// of each message, the lifetime of connections, and the associated stream.
+///
+/// A `KeepAlive` can be configured once, and cloned for each connection to
+/// override some of its settings, such as a shorter interval for clients
+/// behind an aggressive proxy.
+#[derive(Clone, Debug)]
 pub struct KeepAlive {
     comment_text: Cow<'static, str>,
     max_interval: Duration,
+    jitter: Duration,
+    max_lifetime: Option<Duration>,
+    lifetime_jitter: Duration,
+    retry: Duration,
 }
 
 impl KeepAlive {
@@ -451,6 +459,46 @@ impl KeepAlive {
         self
     }
 
+    /// Shorten each interval between keep-alive messages by a random
+    /// duration of up to `jitter`, so that many connections opened at once
+    /// don't send their messages at once.
+    ///
+    /// Default is no jitter.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Close the stream once it has been open for `lifetime`, after sending
+    /// a retry hint to the client.
+    ///
+    /// Clients reconnect after the retry hint, which lets load balancers
+    /// spread long-lived connections over new servers.
+    ///
+    /// Default is no maximum lifetime.
+    pub fn max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Shorten the maximum lifetime by a random duration of up to `jitter`,
+    /// so that connections opened at once aren't closed at once.
+    ///
+    /// Default is no jitter.
+    pub fn lifetime_jitter(mut self, jitter: Duration) -> Self {
+        self.lifetime_jitter = jitter;
+        self
+    }
+
+    /// Customize the retry hint sent before the stream is closed at its
+    /// maximum lifetime, the time clients wait before reconnecting.
+    ///
+    /// Default is 1 second.
+    pub fn retry(mut self, time: Duration) -> Self {
+        self.retry = time;
+        self
+    }
+
     /// Wrap an event stream with keep-alive functionality.
     ///
     /// See [`keep_alive`](keep_alive) for more.
@@ -467,16 +515,29 @@ impl KeepAlive {
         S::Ok: ServerSentEvent + Send,
         S::Error: StdError + Send + Sync + 'static,
     {
-        let alive_timer = time::delay_for(self.max_interval);
+        let alive_timer = time::delay_for(jittered(self.max_interval, self.jitter));
+        let lifetime = self
+            .max_lifetime
+            .map(|lifetime| time::delay_for(jittered(lifetime, self.lifetime_jitter)));
         SseKeepAlive {
             event_stream,
             comment_text: self.comment_text,
             max_interval: self.max_interval,
+            jitter: self.jitter,
             alive_timer,
+            lifetime,
+            retry: self.retry,
+            closed: false,
         }
     }
 }
 
+// Shortens `time` by a random duration of up to `jitter`.
+fn jittered(time: Duration, jitter: Duration) -> Duration {
+    let jitter = jitter.min(time);
+    time - jitter.mul_f64(crate::filters::when::random_fraction())
+}
+
 #[allow(missing_debug_implementations)]
 #[pin_project]
 struct SseKeepAlive<S> {
@@ -484,7 +545,11 @@ struct SseKeepAlive<S> {
     event_stream: S,
     comment_text: Cow<'static, str>,
     max_interval: Duration,
+    jitter: Duration,
     alive_timer: Delay,
+    lifetime: Option<Delay>,
+    retry: Duration,
+    closed: bool,
 }
 
 #[doc(hidden)]
@@ -510,7 +575,11 @@ where
         event_stream,
         comment_text: Cow::Borrowed(""),
         max_interval,
+        jitter: Duration::from_secs(0),
         alive_timer,
+        lifetime: None,
+        retry: Duration::from_secs(1),
+        closed: false,
     }
 }
 
@@ -524,6 +593,10 @@ where
 /// events is 15 seconds. Both may be customized using the builder pattern
 /// as shown below.
 ///
+/// The stream can also be closed after a maximum lifetime, with a retry
+/// hint telling the client when to reconnect, so that load balancers can
+/// rebalance long-lived connections.
+///
 /// ```
 /// use std::time::Duration;
 /// use std::convert::Infallible;
@@ -549,6 +622,9 @@ where
 ///             let stream = warp::sse::keep_alive()
 ///                 .interval(Duration::from_secs(5))
 ///                 .text("thump".to_string())
+///                 .jitter(Duration::from_secs(1))
+///                 .max_lifetime(Duration::from_secs(30 * 60))
+///                 .lifetime_jitter(Duration::from_secs(5 * 60))
 ///                 .stream(event_stream);
 ///             warp::sse::reply(stream)
 ///         });
@@ -560,6 +636,10 @@ pub fn keep_alive() -> KeepAlive {
     KeepAlive {
         comment_text: Cow::Borrowed(""),
         max_interval: Duration::from_secs(15),
+        jitter: Duration::from_secs(0),
+        max_lifetime: None,
+        lifetime_jitter: Duration::from_secs(0),
+        retry: Duration::from_secs(1),
     }
 }
 
@@ -569,25 +649,46 @@ where
     S::Ok: ServerSentEvent,
     S::Error: StdError + Send + Sync + 'static,
 {
-    type Item = Result<EitherServerSentEvent<S::Ok, SseComment<Cow<'static, str>>>, SseError>;
+    type Item = Result<
+        EitherServerSentEvent<
+            S::Ok,
+            EitherServerSentEvent<SseComment<Cow<'static, str>>, SseRetry>,
+        >,
+        SseError,
+    >;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         let mut pin = self.project();
+        if *pin.closed {
+            return Poll::Ready(None);
+        }
+        if let Some(ref mut lifetime) = pin.lifetime {
+            if Pin::new(lifetime).poll(cx).is_ready() {
+                // close the stream, telling the client when to reconnect
+                *pin.closed = true;
+                let retry = SseRetry(*pin.retry);
+                return Poll::Ready(Some(Ok(EitherServerSentEvent::B(
+                    EitherServerSentEvent::B(retry),
+                ))));
+            }
+        }
+        let (interval, jitter) = (*pin.max_interval, *pin.jitter);
+        let next_alive = || tokio::time::Instant::now() + jittered(interval, jitter);
         match pin.event_stream.try_poll_next(cx) {
             Poll::Pending => match Pin::new(&mut pin.alive_timer).poll(cx) {
                 Poll::Pending => Poll::Pending,
                 Poll::Ready(_) => {
                     // restart timer
-                    pin.alive_timer
-                        .reset(tokio::time::Instant::now() + *pin.max_interval);
+                    pin.alive_timer.reset(next_alive());
                     let comment_str = pin.comment_text.clone();
-                    Poll::Ready(Some(Ok(EitherServerSentEvent::B(SseComment(comment_str)))))
+                    Poll::Ready(Some(Ok(EitherServerSentEvent::B(
+                        EitherServerSentEvent::A(SseComment(comment_str)),
+                    ))))
                 }
             },
             Poll::Ready(Some(Ok(event))) => {
                 // restart timer
-                pin.alive_timer
-                    .reset(tokio::time::Instant::now() + *pin.max_interval);
+                pin.alive_timer.reset(next_alive());
                 Poll::Ready(Some(Ok(EitherServerSentEvent::A(event))))
             }
             Poll::Ready(None) => Poll::Ready(None),
@@ -0,0 +1,201 @@
+//! Shared State
+//!
+//! Handlers often need shared state, such as a database pool or a
+//! configuration. Instead of moving a clone of it into every route, with
+//! `warp::any().map(move || db.clone())`, the state can be wrapped in a
+//! [`State`], and extracted with [`Filter::with_state`], or registered once
+//! for all routes with [`provide`] and extracted with [`state`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use std::sync::Mutex;
+//! use warp::state::State;
+//! use warp::Filter;
+//!
+//! type Db = Mutex<HashMap<String, String>>;
+//!
+//! let get = warp::path!("keys" / String)
+//!     .and(warp::state::<Db>())
+//!     .map(|key: String, db: State<Db>| {
+//!         db.lock().unwrap().get(&key).cloned().unwrap_or_default()
+//!     });
+//!
+//! let count = warp::path("count")
+//!     .and(warp::state::<Db>())
+//!     .map(|db: State<Db>| db.lock().unwrap().len().to_string());
+//!
+//! let routes = get
+//!     .or(count)
+//!     .with(warp::state::provide(Db::default()));
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use futures::future;
+
+use crate::filter::{filter_fn_one, Filter, FilterBase, Internal, WrapSealed};
+use crate::filters::ext::missing_extension;
+use crate::reject::Rejection;
+use crate::route;
+
+/// Shared state, cheap to clone, extracted by [`state`] and
+/// [`Filter::with_state`].
+///
+/// It dereferences to the `T` it holds.
+pub struct State<T>(Arc<T>);
+
+impl<T> State<T> {
+    /// Wrap `value` into a `State`.
+    pub fn new(value: T) -> State<T> {
+        State(Arc::new(value))
+    }
+
+    /// Get the `Arc` holding the state.
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        State(self.0.clone())
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<Arc<T>> for State<T> {
+    fn from(value: Arc<T>) -> State<T> {
+        State(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for State<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("State").field(&self.0).finish()
+    }
+}
+
+/// Creates a `Filter` extracting the `State<T>` registered for the request,
+/// with [`provide`] or [`Filter::with_state`].
+///
+/// If no state of this type is registered, this rejects with a
+/// `MissingExtension`, answered with a `500 Internal Server Error`.
+pub fn state<T: Send + Sync + 'static>(
+) -> impl Filter<Extract = (State<T>,), Error = Rejection> + Copy {
+    filter_fn_one(|route| {
+        let state = route
+            .extensions()
+            .get::<State<T>>()
+            .cloned()
+            .ok_or_else(missing_extension);
+        future::ready(state)
+    })
+}
+
+/// Registers `value` as the `State<T>` of requests.
+///
+/// As a `Filter`, this extracts the state, like [`Filter::with_state`]. As a
+/// wrapper, given to [`Filter::with`], the state can be extracted with
+/// [`state`] by any of the wrapped filters.
+pub fn provide<T: Send + Sync + 'static>(value: T) -> Provide<T> {
+    Provide {
+        state: State::new(value),
+    }
+}
+
+/// A `Filter` and wrapper registering a `State`, created with [`provide`].
+#[derive(Debug)]
+pub struct Provide<T> {
+    state: State<T>,
+}
+
+impl<T> Clone for Provide<T> {
+    fn clone(&self) -> Self {
+        Provide {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> Provide<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn register(&self) {
+        let state = self.state.clone();
+        route::with(|route| {
+            route.extensions_mut().insert(state);
+        });
+    }
+}
+
+impl<T> FilterBase for Provide<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Extract = (State<T>,);
+    type Error = std::convert::Infallible;
+    type Future = future::Ready<Result<Self::Extract, Self::Error>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        self.register();
+        future::ok((self.state.clone(),))
+    }
+}
+
+impl<T, F> WrapSealed<F> for Provide<T>
+where
+    T: Send + Sync + 'static,
+    F: Filter + Clone,
+{
+    type Wrapped = WithState<T, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithState {
+            provide: self.clone(),
+            filter,
+        }
+    }
+}
+
+/// A filter wrapped with [`provide`].
+#[derive(Debug)]
+pub struct WithState<T, F> {
+    provide: Provide<T>,
+    filter: F,
+}
+
+impl<T, F: Clone> Clone for WithState<T, F> {
+    fn clone(&self) -> Self {
+        WithState {
+            provide: self.provide.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<T, F> FilterBase for WithState<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Filter,
+{
+    type Extract = F::Extract;
+    type Error = F::Error;
+    type Future = F::Future;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        self.provide.register();
+        self.filter.filter(Internal)
+    }
+}
@@ -0,0 +1,117 @@
+//! Template engine integration
+//!
+//! Render templates into [`html`](crate::reply::html) replies, with the
+//! [askama](https://docs.rs/askama) (feature `askama`) or
+//! [tera](https://docs.rs/tera) (feature `tera`) engines.
+//!
+//! Rendering errors are logged, and turned into rejections with a
+//! `500 Internal Server Error` status. They can be recognized with
+//! `rejection.find::<TemplateError>()`, which tells which template failed.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::reject::{self, Rejection};
+use crate::reply::{html, Html};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Render an askama template.
+///
+/// # Example
+///
+/// ```
+/// use askama::Template;
+/// use warp::Filter;
+///
+/// #[derive(Template)]
+/// #[template(source = "Hello, {{ name }}!", ext = "html")]
+/// struct Hello {
+///     name: String,
+/// }
+///
+/// let route = warp::path!("hello" / String).and_then(|name| async move {
+///     warp::reply::template::askama(&Hello { name })
+/// });
+/// ```
+#[cfg(feature = "askama")]
+pub fn askama<T: askama::Template>(template: &T) -> Result<Html<String>, Rejection> {
+    template
+        .render()
+        .map(html)
+        .map_err(|err| rejection(std::any::type_name::<T>(), err.into()))
+}
+
+/// Render the template `name` of a tera instance, with the `context`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use tera::{Context, Tera};
+/// use warp::Filter;
+///
+/// let mut tera = Tera::default();
+/// tera.add_raw_template("hello.html", "Hello, {{ name }}!").unwrap();
+/// let tera = Arc::new(tera);
+///
+/// let route = warp::path!("hello" / String).and_then(move |name: String| {
+///     let tera = tera.clone();
+///     async move {
+///         let mut context = Context::new();
+///         context.insert("name", &name);
+///         warp::reply::template::tera(&tera, "hello.html", &context)
+///     }
+/// });
+/// ```
+#[cfg(feature = "tera")]
+pub fn tera(
+    tera: &tera::Tera,
+    name: &str,
+    context: &tera::Context,
+) -> Result<Html<String>, Rejection> {
+    tera.render(name, context)
+        .map(html)
+        .map_err(|err| rejection(name, err.into()))
+}
+
+fn rejection(template: &str, cause: BoxError) -> Rejection {
+    let err = TemplateError {
+        template: template.to_owned(),
+        cause,
+    };
+    log::error!("{}", err);
+    reject::known(err)
+}
+
+/// An error used in rejections when rendering a template fails.
+#[derive(Debug)]
+pub struct TemplateError {
+    template: String,
+    cause: BoxError,
+}
+
+impl TemplateError {
+    /// The name of the template that failed to render.
+    ///
+    /// For askama, this is the name of the template type.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Template render error (template={}): {}",
+            self.template, self.cause
+        )
+    }
+}
+
+impl StdError for TemplateError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.cause)
+    }
+}
@@ -0,0 +1,446 @@
+//! Tower interoperability
+//!
+//! Adapters between warp and the [Tower][tower] `Layer` and `Service`
+//! traits. They allow existing tower middleware, such as timeouts or load
+//! shedding, to wrap warp filters, warp wrappers to be used in a tower stack,
+//! and tower services to be mounted next to warp filters.
+//!
+//! [tower]: https://docs.rs/tower
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Buf;
+use futures::future::{self, TryFuture};
+use futures::{stream, TryStreamExt};
+use hyper::body::HttpBody;
+use hyper::Body;
+use pin_project::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::filter::service::FilteredService;
+use crate::filter::{Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{self, IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+use crate::Request;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Use a tower `Layer` to wrap a filter.
+///
+/// The wrapped filter is converted into a `Service`, the layer is applied,
+/// and the resulting service is run as a filter again. Rejections of the
+/// wrapped filter pass through the layer, so the result can still be
+/// combined with `or`. If the layered service itself returns an error, such
+/// as when a timeout elapses, the request is rejected with a
+/// [`ServiceError`].
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use warp::Filter;
+///
+/// let route = warp::path("slow")
+///     .map(warp::reply)
+///     .with(warp::tower::layer(tower::timeout::TimeoutLayer::new(
+///         Duration::from_secs(5),
+///     )));
+/// ```
+pub fn layer<L>(layer: L) -> WithLayer<L> {
+    WithLayer { layer }
+}
+
+/// Use a warp wrapper as a tower `Layer`.
+///
+/// The service being layered is run as a filter with [`service_filter`],
+/// wrapped with `wrapper`, and converted back into a `Service`.
+///
+/// # Example
+///
+/// ```
+/// use tower_layer::Layer;
+/// use warp::Filter;
+///
+/// let layer = warp::tower::wrap_layer(warp::log("example::api"));
+/// let svc = layer.layer(warp::service(warp::any().map(warp::reply)));
+/// ```
+pub fn wrap_layer<W>(wrapper: W) -> WrapLayer<W> {
+    WrapLayer { wrapper }
+}
+
+/// Convert a tower `Service` into a `Filter`.
+///
+/// The request is handed to the service with the path matched so far
+/// stripped from its URI, so a service can be mounted under a path prefix.
+/// The request and response bodies are streamed through. This allows
+/// serving other tower or hyper services, such as a gRPC server, on the same
+/// listener as warp filters.
+///
+/// If the service returns an error, the request is rejected with a
+/// [`ServiceError`].
+///
+/// Note that response trailers are only forwarded when the service responds
+/// with a `hyper::Body`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // Any `Service<Request<Body>>`, including another warp filter...
+/// let legacy = warp::service(warp::path("hello").map(|| "hello from legacy"));
+///
+/// // Responds to `/legacy/hello`.
+/// let route = warp::path("legacy").and(warp::service_filter(legacy));
+/// ```
+pub fn service_filter<S, B>(service: S) -> ServiceFilter<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    ServiceFilter {
+        service,
+        mount: true,
+    }
+}
+
+/// A warp wrapper created with [`layer`].
+#[derive(Clone, Copy)]
+pub struct WithLayer<L> {
+    layer: L,
+}
+
+impl<L> fmt::Debug for WithLayer<L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithLayer").finish()
+    }
+}
+
+impl<L, F> WrapSealed<F> for WithLayer<L>
+where
+    F: Filter + Clone,
+    <F::Future as TryFuture>::Ok: Reply,
+    <F::Future as TryFuture>::Error: Into<Rejection>,
+    L: Layer<FilterService<F>>,
+    L::Service: Service<Request, Response = Response> + Clone + Send + 'static,
+    <L::Service as Service<Request>>::Error: Into<BoxError>,
+    <L::Service as Service<Request>>::Future: Send,
+{
+    type Wrapped = ServiceFilter<L::Service>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        ServiceFilter {
+            service: self.layer.layer(FilterService { filter }),
+            mount: false,
+        }
+    }
+}
+
+/// A tower `Layer` created with [`wrap_layer`].
+#[derive(Clone, Copy)]
+pub struct WrapLayer<W> {
+    wrapper: W,
+}
+
+impl<W> fmt::Debug for WrapLayer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WrapLayer").finish()
+    }
+}
+
+impl<W, S> Layer<S> for WrapLayer<W>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    W: WrapSealed<ServiceFilter<S>>,
+    <<W::Wrapped as FilterBase>::Future as TryFuture>::Ok: Reply,
+    <<W::Wrapped as FilterBase>::Future as TryFuture>::Error: IsReject,
+{
+    type Service = FilteredService<W::Wrapped>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        crate::service(self.wrapper.wrap(service_filter(service)))
+    }
+}
+
+/// A filter that runs a tower `Service`, created with [`service_filter`].
+///
+/// The current request is handed to the service, and its response is
+/// extracted.
+#[derive(Clone, Copy)]
+pub struct ServiceFilter<S> {
+    service: S,
+    mount: bool,
+}
+
+impl<S> fmt::Debug for ServiceFilter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServiceFilter").finish()
+    }
+}
+
+impl<S, B> FilterBase for ServiceFilter<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let mount = self.mount;
+        let req = route::with(|route| into_request(route, mount));
+        let mut service = self.service.clone();
+        Box::pin(async move {
+            let req = req?;
+            // The service may be a warp service, setting the route of the
+            // request it is handed while this one is set.
+            future::poll_fn(|cx| route::nested(|| service.poll_ready(cx)))
+                .await
+                .map_err(service_error)?;
+            let mut res = Nested(route::nested(|| service.call(req)))
+                .await
+                .map_err(service_error)?;
+
+            match res.extensions_mut().remove::<Rejected>() {
+                Some(Rejected {
+                    rejection,
+                    extensions,
+                    body,
+                }) => {
+                    route::with(|route| {
+                        *route.extensions_mut() = extensions;
+                        if let Some(body) = body {
+                            route.restore_body(body);
+                        }
+                    });
+                    Err(rejection)
+                }
+                None => {
+                    let (parts, body) = res.into_parts();
+                    Ok((http::Response::from_parts(parts, into_body(body)),))
+                }
+            }
+        })
+    }
+}
+
+// A future of a service called by a `ServiceFilter`, polled with
+// `route::nested`.
+#[pin_project]
+struct Nested<F>(#[pin] F);
+
+impl<F: Future> Future for Nested<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let fut = self.project().0;
+        route::nested(|| fut.poll(cx))
+    }
+}
+
+/// The `Service` given to a tower `Layer` used with [`layer`].
+///
+/// It runs the wrapped filter on each request it is called with.
+#[derive(Clone, Copy)]
+pub struct FilterService<F> {
+    filter: F,
+}
+
+impl<F> fmt::Debug for FilterService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterService").finish()
+    }
+}
+
+impl<F> Service<Request> for FilterService<F>
+where
+    F: Filter,
+    <F::Future as TryFuture>::Ok: Reply,
+    <F::Future as TryFuture>::Error: Into<Rejection>,
+{
+    type Response = Response;
+    type Error = std::convert::Infallible;
+    type Future = FilterServiceFuture<F::Future>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let origin = req.extensions_mut().remove::<Origin>();
+        let route = Route::new(req, origin.as_ref().and_then(|o| o.remote_addr));
+        if let Some(origin) = origin {
+            let mut route = route.borrow_mut();
+            // Middleware may have rewritten the path, in which case
+            // matching starts over.
+            if route.full_path() == origin.path {
+                route.set_matched_path_index(origin.matched_path_index);
+            }
+        }
+        let future = route::set(&route, || self.filter.filter(Internal));
+        FilterServiceFuture { future, route }
+    }
+}
+
+/// The `Future` returned by a [`FilterService`].
+#[pin_project]
+pub struct FilterServiceFuture<F> {
+    #[pin]
+    future: F,
+    route: RefCell<Route>,
+}
+
+impl<F> fmt::Debug for FilterServiceFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterServiceFuture").finish()
+    }
+}
+
+impl<F> Future for FilterServiceFuture<F>
+where
+    F: TryFuture,
+    F::Ok: Reply,
+    F::Error: Into<Rejection>,
+{
+    type Output = Result<Response, std::convert::Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let pin = self.project();
+        let fut = pin.future;
+        match route::set(pin.route, || fut.try_poll(cx)) {
+            Poll::Ready(Ok(ok)) => Poll::Ready(Ok(ok.into_response())),
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(err)) => {
+                let rejection = err.into();
+                let mut res = rejection.into_response();
+                // Hand the rejection, and what's left of the request, back
+                // to the `ServiceFilter`, so other filters can still try it.
+                let mut route = pin.route.borrow_mut();
+                let rejected = Rejected {
+                    rejection,
+                    extensions: route.take_extensions(),
+                    body: route.take_body(),
+                };
+                res.extensions_mut().insert(rejected);
+                Poll::Ready(Ok(res))
+            }
+        }
+    }
+}
+
+// Carries the state of the outer route into a `FilterService`.
+struct Origin {
+    remote_addr: Option<SocketAddr>,
+    path: String,
+    matched_path_index: usize,
+}
+
+// Carries a rejection out of a `FilterService`, through the layers.
+struct Rejected {
+    rejection: Rejection,
+    extensions: http::Extensions,
+    body: Option<Body>,
+}
+
+pub(crate) fn into_request(route: &mut Route, mount: bool) -> Result<Request, Rejection> {
+    let body = crate::filters::body::take_body(route)?;
+    let mut req = http::Request::new(body);
+    *req.method_mut() = route.method().clone();
+    *req.uri_mut() = if mount {
+        mounted_uri(route)
+    } else {
+        route.uri().clone()
+    };
+    *req.version_mut() = route.version();
+    *req.headers_mut() = route.headers().clone();
+    *req.extensions_mut() = route.take_extensions();
+    req.extensions_mut().insert(Origin {
+        remote_addr: route.remote_addr(),
+        path: route.full_path().to_owned(),
+        matched_path_index: route.matched_path_index(),
+    });
+    Ok(req)
+}
+
+// The request URI, without the part of the path that was already matched.
+fn mounted_uri(route: &Route) -> http::Uri {
+    if !route.full_path().starts_with('/') {
+        return route.uri().clone();
+    }
+
+    let mut path_and_query = format!("/{}", route.path());
+    if let Some(query) = route.query() {
+        path_and_query.push('?');
+        path_and_query.push_str(query);
+    }
+
+    let mut parts = route.uri().clone().into_parts();
+    parts.path_and_query = Some(
+        path_and_query
+            .parse()
+            .expect("unmatched path is a valid path"),
+    );
+    http::Uri::from_parts(parts).expect("mounted uri parts are valid")
+}
+
+fn into_body<B>(body: B) -> Body
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    // Pass a `hyper::Body` through untouched, keeping any trailers.
+    let mut body = Some(body);
+    if let Some(body) = (&mut body as &mut dyn Any).downcast_mut::<Option<Body>>() {
+        return body.take().expect("body is set");
+    }
+
+    let mut body = Box::pin(body.expect("body is set"));
+    Body::wrap_stream(
+        stream::poll_fn(move |cx| body.as_mut().poll_data(cx)).map_ok(|mut data| data.to_bytes()),
+    )
+}
+
+pub(crate) fn service_error<E: Into<BoxError>>(err: E) -> Rejection {
+    let err = err.into();
+    log::debug!("service error: {}", err);
+    reject::known(ServiceError { cause: err })
+}
+
+/// An error used in rejections when a tower `Service` returns an error.
+#[derive(Debug)]
+pub struct ServiceError {
+    cause: BoxError,
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Service error: {}", self.cause)
+    }
+}
+
+impl StdError for ServiceError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.cause)
+    }
+}
@@ -0,0 +1,552 @@
+//! [`tracing`] filters.
+//!
+//! [`tracing`] is a framework for instrumenting Rust programs to
+//! collect scoped, structured, and async-aware diagnostics. This module
+//! provides a set of filters for instrumenting warp applications with
+//! `tracing` spans. The spans have fields following the OpenTelemetry
+//! semantic conventions for HTTP servers, and the status code of the
+//! response is recorded on them once it is known.
+//!
+//! With the `otel` feature, the W3C `traceparent` and `baggage` headers of
+//! requests are extracted as the parent of the spans, using
+//! [`tracing-opentelemetry`], so that traces connect across services.
+//!
+//! [`tracing`]: https://crates.io/crates/tracing
+//! [`tracing-opentelemetry`]: https://crates.io/crates/tracing-opentelemetry
+use std::convert::Infallible;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future;
+use http::header::{HeaderName, HeaderValue};
+use http::{header, HeaderMap, StatusCode};
+use tracing::Span;
+
+use crate::filter::{filter_fn_one, Filter, FilterBase, Internal, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+
+/// Create a wrapping filter that instruments every request with a `tracing`
+/// [`Span`] at the [`INFO`] level.
+///
+/// The span is named `request`, and has the fields:
+///
+/// - `http.request.method`
+/// - `url.path`
+/// - `url.query`, if the request has a query
+/// - `network.protocol.version`
+/// - `client.address`, if the remote address is known
+/// - `user_agent.original`, if the request has a `User-Agent`
+/// - `http.response.status_code`, once the response is known
+/// - `otel.kind`, which is `server`
+/// - `otel.status_code`, which is `error` for `5xx` responses
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(warp::trace::request());
+/// ```
+///
+/// [`Span`]: https://docs.rs/tracing/latest/tracing/#spans
+/// [`INFO`]: https://docs.rs/tracing/latest/tracing/struct.Level.html#associatedconstant.INFO
+pub fn request() -> Trace<impl Fn(Info) -> Span + Clone> {
+    trace(|info: Info| {
+        let span = tracing::info_span!(
+            "request",
+            http.request.method = %info.method(),
+            url.path = %info.path(),
+            url.query = tracing::field::Empty,
+            network.protocol.version = ?info.version(),
+            client.address = tracing::field::Empty,
+            user_agent.original = tracing::field::Empty,
+            http.response.status_code = tracing::field::Empty,
+            otel.kind = "server",
+            otel.status_code = tracing::field::Empty,
+        );
+
+        if let Some(query) = info.query() {
+            span.record("url.query", query);
+        }
+        if let Some(addr) = info.remote_addr() {
+            span.record("client.address", tracing::field::display(addr.ip()));
+        }
+        if let Some(user_agent) = info.user_agent() {
+            span.record("user_agent.original", user_agent);
+        }
+
+        span
+    })
+}
+
+/// Create a wrapping filter that instruments every request with a custom
+/// `tracing` [`Span`] provided by a function.
+///
+/// The status code of the response is recorded in the
+/// `http.response.status_code` and `otel.status_code` fields, if the span
+/// has them.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::any()
+///     .map(warp::reply)
+///     .with(warp::trace(|info| {
+///         // Create a span using tracing macros
+///         tracing::info_span!(
+///             "request",
+///             method = %info.method(),
+///             path = %info.path(),
+///             http.response.status_code = tracing::field::Empty,
+///         )
+///     }));
+/// ```
+///
+/// [`Span`]: https://docs.rs/tracing/latest/tracing/#spans
+pub fn trace<F>(func: F) -> Trace<F>
+where
+    F: Fn(Info) -> Span + Clone,
+{
+    Trace { func }
+}
+
+/// Decorates a [`Filter`](crate::Filter) to create a [`tracing`] [span] for
+/// requests and responses.
+///
+/// [`tracing`]: https://crates.io/crates/tracing
+/// [span]: https://docs.rs/tracing/latest/tracing/#spans
+#[derive(Clone, Copy, Debug)]
+pub struct Trace<F> {
+    func: F,
+}
+
+/// Information about the request, used to create the span of a request.
+#[allow(missing_debug_implementations)]
+pub struct Info<'a> {
+    route: &'a Route,
+}
+
+impl<FN, F> WrapSealed<F> for Trace<FN>
+where
+    FN: Fn(Info) -> Span + Clone + Send + Sync + 'static,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithTrace<FN, F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithTrace {
+            filter,
+            trace: self.clone(),
+        }
+    }
+}
+
+impl<'a> Info<'a> {
+    /// View the remote `SocketAddr` of the request.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.route.remote_addr()
+    }
+
+    /// View the `http::Method` of the request.
+    pub fn method(&self) -> &http::Method {
+        self.route.method()
+    }
+
+    /// View the URI path of the request.
+    pub fn path(&self) -> &str {
+        self.route.full_path()
+    }
+
+    /// View the URI query of the request, if any.
+    pub fn query(&self) -> Option<&str> {
+        self.route.query()
+    }
+
+    /// View the `http::Version` of the request.
+    pub fn version(&self) -> http::Version {
+        self.route.version()
+    }
+
+    /// View the referer of the request.
+    pub fn referer(&self) -> Option<&str> {
+        self.route
+            .headers()
+            .get(header::REFERER)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// View the user agent of the request.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.route
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// View the host of the request.
+    pub fn host(&self) -> Option<&str> {
+        self.route
+            .headers()
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// View the request headers.
+    pub fn request_headers(&self) -> &HeaderMap {
+        self.route.headers()
+    }
+}
+
+/// A filter wrapped with [`Trace`].
+#[derive(Clone, Copy)]
+pub struct WithTrace<FN, F> {
+    filter: F,
+    trace: Trace<FN>,
+}
+
+impl<FN, F> fmt::Debug for WithTrace<FN, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithTrace").finish()
+    }
+}
+
+impl<FN, F> FilterBase for WithTrace<FN, F>
+where
+    FN: Fn(Info) -> Span + Clone + Send + Sync + 'static,
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let span = route::with(|route| {
+            let span = (self.trace.func)(Info { route });
+            #[cfg(feature = "otel")]
+            {
+                use tracing_opentelemetry::OpenTelemetrySpanExt;
+                span.set_parent(otel::extract(route.headers()));
+            }
+            span
+        });
+        let fut = {
+            let _entered = span.enter();
+            self.filter.filter(Internal)
+        };
+
+        let instrumented = span.clone();
+        let traced = async move {
+            let result = fut.await.map(Reply::into_response).map_err(Into::into);
+            let status = match result {
+                Ok(ref res) => res.status(),
+                Err(ref rejection) => rejection.status(),
+            };
+            record_status(&span, status);
+            match result {
+                Ok(ref res) => tracing::debug!(
+                    status = res.status().as_u16(),
+                    "finished processing with success"
+                ),
+                Err(ref rejection) => tracing::debug!(?rejection, "unable to process request"),
+            }
+            result.map(|res| (res,))
+        };
+        Box::pin(tracing::Instrument::instrument(traced, instrumented))
+    }
+}
+
+fn record_status(span: &Span, status: StatusCode) {
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "error");
+    }
+}
+
+/// Create a wrapping filter that adds a `Server-Timing` header to responses.
+///
+/// Handlers record named durations, such as the time spent querying a
+/// database, in the [`Timings`] of the request, extracted with [`timings`].
+/// They are sent in the header along with a `total` duration, so that they
+/// show up in the developer tools of browsers.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+/// use warp::trace::Timings;
+///
+/// let route = warp::trace::timings()
+///     .map(|timings: Timings| {
+///         let db = timings.start("db");
+///         // Query the database...
+///         db.stop();
+///         "hello"
+///     })
+///     .with(warp::trace::server_timing());
+/// ```
+pub fn server_timing() -> ServerTiming {
+    ServerTiming { _p: () }
+}
+
+/// Creates a `Filter` that extracts the [`Timings`] of the request.
+///
+/// If the filter isn't wrapped by [`server_timing`], the timings that are
+/// recorded are discarded.
+pub fn timings() -> impl Filter<Extract = (Timings,), Error = Infallible> + Copy {
+    filter_fn_one(|route| {
+        future::ok(
+            route
+                .extensions()
+                .get::<Timings>()
+                .cloned()
+                .unwrap_or_default(),
+        )
+    })
+}
+
+/// Decorates a [`Filter`](crate::Filter) to add a `Server-Timing` header to
+/// responses, see [`server_timing`].
+#[derive(Clone, Copy, Debug)]
+pub struct ServerTiming {
+    _p: (),
+}
+
+/// The named durations recorded for a request, see [`server_timing`].
+///
+/// Names should be HTTP tokens, such as `db` or `render`.
+#[derive(Clone, Default)]
+pub struct Timings {
+    metrics: Arc<Mutex<Vec<(String, Duration)>>>,
+}
+
+impl Timings {
+    /// Records that `name` took `duration`.
+    pub fn record(&self, name: impl Into<String>, duration: Duration) {
+        self.metrics
+            .lock()
+            .expect("timings lock poisoned")
+            .push((name.into(), duration));
+    }
+
+    /// Starts timing `name`, until the returned [`Timer`] is stopped or
+    /// dropped.
+    pub fn start(&self, name: impl Into<String>) -> Timer {
+        Timer {
+            timings: self.clone(),
+            name: Some(name.into()),
+            started: Instant::now(),
+        }
+    }
+
+    fn header(&self, total: Duration) -> Option<HeaderValue> {
+        let metrics = self.metrics.lock().expect("timings lock poisoned");
+        let mut value = String::new();
+        for (name, duration) in metrics.iter() {
+            let _ = write!(value, "{};dur={:.1}, ", name, millis(*duration));
+        }
+        let _ = write!(value, "total;dur={:.1}", millis(total));
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+impl fmt::Debug for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timings")
+            .field(
+                "metrics",
+                &*self.metrics.lock().expect("timings lock poisoned"),
+            )
+            .finish()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// A running timing, recorded when stopped or dropped, see
+/// [`Timings::start`].
+#[derive(Debug)]
+pub struct Timer {
+    timings: Timings,
+    name: Option<String>,
+    started: Instant,
+}
+
+impl Timer {
+    /// Stops the timer, recording the elapsed time.
+    pub fn stop(self) {}
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            self.timings.record(name, self.started.elapsed());
+        }
+    }
+}
+
+impl<F> WrapSealed<F> for ServerTiming
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Wrapped = WithServerTiming<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithServerTiming { filter }
+    }
+}
+
+/// A filter wrapped with [`server_timing`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithServerTiming<F> {
+    filter: F,
+}
+
+impl<F> FilterBase for WithServerTiming<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: Into<Rejection>,
+    F::Future: Send,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let started = Instant::now();
+        let timings = Timings::default();
+        route::with(|route| route.extensions_mut().insert(timings.clone()));
+        let fut = self.filter.filter(Internal);
+
+        Box::pin(async move {
+            let mut res = fut.await.map_err(Into::into)?.into_response();
+            if let Some(value) = timings.header(started.elapsed()) {
+                res.headers_mut()
+                    .append(HeaderName::from_static("server-timing"), value);
+            }
+            Ok((res,))
+        })
+    }
+}
+
+#[cfg(feature = "otel")]
+pub use self::otel::extract as extract_context;
+
+#[cfg(feature = "otel")]
+mod otel {
+    use http::HeaderMap;
+    use opentelemetry::baggage::BaggageExt;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId};
+    use opentelemetry::{Context, KeyValue};
+
+    /// Extracts the context propagated with the W3C `traceparent`,
+    /// `tracestate` and `baggage` headers.
+    ///
+    /// Headers that are missing or malformed are ignored, and extract an
+    /// empty context.
+    pub fn extract(headers: &HeaderMap) -> Context {
+        let mut cx = Context::new();
+        if let Some(span_context) = traceparent(headers) {
+            cx = cx.with_remote_span_context(span_context);
+        }
+        let baggage = baggage(headers);
+        if !baggage.is_empty() {
+            cx = cx.with_baggage(baggage);
+        }
+        cx
+    }
+
+    // https://www.w3.org/TR/trace-context/#traceparent-header
+    fn traceparent(headers: &HeaderMap) -> Option<SpanContext> {
+        let value = headers.get("traceparent")?.to_str().ok()?.trim();
+        let mut parts = value.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Later versions may add fields, but version 00 has exactly four.
+        if version.len() != 2
+            || version == "ff"
+            || (version == "00" && parts.next().is_some())
+            || trace_id.len() != 32
+            || span_id.len() != 16
+            || flags.len() != 2
+            || !is_lower_hex(version)
+            || !is_lower_hex(trace_id)
+            || !is_lower_hex(span_id)
+            || !is_lower_hex(flags)
+        {
+            return None;
+        }
+
+        let trace_id = TraceId::from_hex(trace_id).ok()?;
+        let span_id = SpanId::from_hex(span_id).ok()?;
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        let trace_state = headers
+            .get("tracestate")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+
+        let span_context = SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::new(flags) & TraceFlags::SAMPLED,
+            true,
+            trace_state,
+        );
+        if span_context.is_valid() {
+            Some(span_context)
+        } else {
+            None
+        }
+    }
+
+    // https://www.w3.org/TR/baggage/#header-content
+    fn baggage(headers: &HeaderMap) -> Vec<KeyValue> {
+        headers
+            .get_all("baggage")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|member| {
+                // Properties after the value are ignored.
+                let pair = member.split(';').next()?;
+                let mut kv = pair.splitn(2, '=');
+                let key = kv.next()?.trim();
+                let value = kv.next()?.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                let key = urlencoding::decode(key).ok()?;
+                let value = urlencoding::decode(value).ok()?;
+                Some(KeyValue::new(key, value))
+            })
+            .collect()
+    }
+
+    fn is_lower_hex(s: &str) -> bool {
+        s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    }
+}
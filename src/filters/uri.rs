@@ -0,0 +1,311 @@
+//! URI filters.
+//!
+//! Behind a reverse proxy, the URI a client requested isn't the one the
+//! server receives: the proxy may terminate TLS, and forward requests to
+//! another host and port. Proxies describe the original request in the
+//! `Forwarded` header, or the `X-Forwarded-Proto`, `X-Forwarded-Host` and
+//! `X-Forwarded-Port` headers, which [`absolute`] uses to reconstruct the
+//! URL visible to clients, to build `Location` headers or OAuth redirect
+//! URIs.
+//!
+//! Since clients can send these headers too, they are only honored on
+//! requests coming from a trusted proxy.
+
+use std::convert::TryFrom;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use futures::future;
+use http::header::HOST;
+use http::uri::{Authority, Scheme, Uri};
+
+use crate::filter::{FilterBase, Internal, One};
+use crate::reject::{self, Rejection};
+use crate::route::{self, Route};
+
+/// Creates a `Filter` that extracts the absolute URL of the request, as
+/// visible to clients.
+///
+/// By default, no proxy is trusted: the URL is made of the `Host` of the
+/// request, with the `http` scheme. Requests without a host are rejected
+/// with a `MissingHeader`.
+///
+/// # Example
+///
+/// ```
+/// use std::net::Ipv4Addr;
+/// use warp::http::Uri;
+/// use warp::Filter;
+///
+/// let route = warp::path("login")
+///     .and(warp::uri::absolute().trust(Ipv4Addr::LOCALHOST.into()))
+///     .map(|url: Uri| format!("redirect_uri={}/callback", url));
+/// ```
+pub fn absolute() -> Absolute {
+    Absolute {
+        proxies: Arc::new(Proxies::default()),
+    }
+}
+
+/// A `Filter` extracting the absolute URL of the request, created with
+/// [`absolute`].
+#[derive(Clone, Debug)]
+pub struct Absolute {
+    proxies: Arc<Proxies>,
+}
+
+impl Absolute {
+    /// Trust the forwarding headers of requests from the proxy at `addr`.
+    pub fn trust(mut self, addr: IpAddr) -> Self {
+        Arc::make_mut(&mut self.proxies).addrs.push(addr);
+        self
+    }
+
+    /// Trust the forwarding headers of every request.
+    ///
+    /// This should only be used when the server can only be reached
+    /// through proxies, as anyone could pretend to be a proxy otherwise.
+    pub fn trust_all(mut self) -> Self {
+        Arc::make_mut(&mut self.proxies).all = true;
+        self
+    }
+
+    /// Set the scheme of requests without a forwarded scheme, `http` by
+    /// default, such as `https` for a server with TLS.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `scheme` isn't a valid scheme.
+    pub fn scheme(mut self, scheme: &str) -> Self {
+        let scheme =
+            Scheme::try_from(scheme).unwrap_or_else(|_| panic!("illegal scheme: {:?}", scheme));
+        Arc::make_mut(&mut self.proxies).scheme = scheme;
+        self
+    }
+}
+
+impl FilterBase for Absolute {
+    type Extract = One<Uri>;
+    type Error = Rejection;
+    type Future = future::Ready<Result<Self::Extract, Self::Error>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let uri = route::with(|route| {
+            let (scheme, authority) = self.proxies.origin(route)?;
+            let path = route
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/");
+            Uri::builder()
+                .scheme(scheme)
+                .authority(authority)
+                .path_and_query(path)
+                .build()
+                .map_err(|_| reject::invalid_header("host"))
+        });
+        future::ready(uri.map(|uri| (uri,)))
+    }
+}
+
+// The proxies whose forwarding headers are trusted.
+#[derive(Clone, Debug)]
+pub(crate) struct Proxies {
+    pub(crate) all: bool,
+    pub(crate) addrs: Vec<IpAddr>,
+    pub(crate) scheme: Scheme,
+}
+
+impl Default for Proxies {
+    fn default() -> Self {
+        Proxies {
+            all: false,
+            addrs: Vec::new(),
+            scheme: Scheme::HTTP,
+        }
+    }
+}
+
+// The original request described by the headers of a proxy.
+#[derive(Default)]
+struct Forwarded {
+    proto: Option<String>,
+    host: Option<String>,
+    port: Option<String>,
+}
+
+impl Proxies {
+    fn trusts(&self, route: &Route) -> bool {
+        self.all
+            || match route.remote_addr() {
+                Some(addr) => self.addrs.contains(&addr.ip()),
+                None => false,
+            }
+    }
+
+    // The scheme of the request, as requested by the client.
+    pub(crate) fn scheme(&self, route: &Route) -> Result<Scheme, Rejection> {
+        self.scheme_of(route, &self.forwarded(route))
+    }
+
+    fn scheme_of(&self, route: &Route, forwarded: &Forwarded) -> Result<Scheme, Rejection> {
+        if let Some(scheme) = route.uri().scheme() {
+            return Ok(scheme.clone());
+        }
+        match forwarded.proto {
+            Some(ref proto) => Scheme::try_from(proto.as_str())
+                .map_err(|_| reject::invalid_header(forwarded_header(route))),
+            None => Ok(self.scheme.clone()),
+        }
+    }
+
+    // The scheme and authority of the request, as requested by the client.
+    pub(crate) fn origin(&self, route: &Route) -> Result<(Scheme, Authority), Rejection> {
+        let forwarded = self.forwarded(route);
+        let scheme = self.scheme_of(route, &forwarded)?;
+        let invalid = || reject::invalid_header(forwarded_header(route));
+
+        let authority = match forwarded.host {
+            Some(host) => Authority::try_from(host.as_str()).map_err(|_| invalid())?,
+            None => match route.uri().authority() {
+                Some(authority) => authority.clone(),
+                None => match route.headers().get(HOST) {
+                    Some(host) => Authority::try_from(host.as_bytes())
+                        .map_err(|_| reject::invalid_header("host"))?,
+                    None => return Err(reject::missing_header("host")),
+                },
+            },
+        };
+        let port = match forwarded.port {
+            Some(port) => Some(port.parse::<u16>().map_err(|_| invalid())?),
+            None => authority.port_u16(),
+        };
+
+        // Leave out the default ports of the scheme.
+        let default_port = match scheme.as_str() {
+            "http" => Some(80),
+            "https" => Some(443),
+            _ => None,
+        };
+        let authority = match port {
+            Some(port) if Some(port) != default_port => {
+                let authority = format!("{}:{}", authority.host(), port);
+                Authority::try_from(authority.as_str()).map_err(|_| invalid())?
+            }
+            _ => Authority::try_from(authority.host()).map_err(|_| invalid())?,
+        };
+        Ok((scheme, authority))
+    }
+
+    // The address of the client, skipping the trusted proxies that forwarded
+    // the request, from the `for` of the `Forwarded` header, or else the
+    // `X-Forwarded-For` header.
+    pub(crate) fn client_ip(&self, route: &Route) -> Option<IpAddr> {
+        let trusted = |ip: Option<IpAddr>| match ip {
+            Some(ip) => self.all || self.addrs.contains(&ip),
+            None => self.all,
+        };
+        let mut client = route.remote_addr().map(|addr| addr.ip());
+        if !trusted(client) {
+            return client;
+        }
+        let headers = route.headers();
+        let mut chain: Vec<Option<IpAddr>> =
+            match headers.get("forwarded").and_then(|v| v.to_str().ok()) {
+                Some(value) => value
+                    .split(',')
+                    .map(|element| {
+                        element.split(';').find_map(|pair| {
+                            let mut pair = pair.splitn(2, '=');
+                            let name = pair.next()?.trim();
+                            if name.eq_ignore_ascii_case("for") {
+                                Some(parse_node(pair.next()?))
+                            } else {
+                                None
+                            }
+                        })?
+                    })
+                    .collect(),
+                None => headers
+                    .get_all("x-forwarded-for")
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .flat_map(|v| v.split(','))
+                    .map(parse_node)
+                    .collect(),
+            };
+        while trusted(client) {
+            match chain.pop() {
+                Some(Some(ip)) => client = Some(ip),
+                // The end of the chain, or an unknown or obfuscated node.
+                _ => break,
+            }
+        }
+        client
+    }
+
+    fn forwarded(&self, route: &Route) -> Forwarded {
+        if !self.trusts(route) {
+            return Forwarded::default();
+        }
+        let headers = route.headers();
+        if let Some(value) = headers.get("forwarded").and_then(|v| v.to_str().ok()) {
+            return parse_forwarded(value);
+        }
+        let first = |name: &str| {
+            let value = headers.get(name)?.to_str().ok()?;
+            let first = value.split(',').next()?.trim();
+            if first.is_empty() {
+                None
+            } else {
+                Some(first.to_owned())
+            }
+        };
+        Forwarded {
+            proto: first("x-forwarded-proto").map(|proto| proto.to_ascii_lowercase()),
+            host: first("x-forwarded-host"),
+            port: first("x-forwarded-port"),
+        }
+    }
+}
+
+fn forwarded_header(route: &Route) -> &'static str {
+    if route.headers().contains_key("forwarded") {
+        "forwarded"
+    } else {
+        "x-forwarded-host"
+    }
+}
+
+// Parses a node of a forwarding header, as in `192.0.2.60`, `192.0.2.60:80`
+// or `"[2001:db8::1]:4711"`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+// Parses the first element of a `Forwarded` header, set by the proxy
+// closest to the client, as in `for=192.0.2.60;proto=https;host=example.com`.
+fn parse_forwarded(value: &str) -> Forwarded {
+    let mut forwarded = Forwarded::default();
+    let element = value.split(',').next().unwrap_or("");
+    for pair in element.split(';') {
+        let mut pair = pair.splitn(2, '=');
+        let name = pair.next().unwrap_or("").trim();
+        let value = match pair.next() {
+            Some(value) => value.trim().trim_matches('"').to_owned(),
+            None => continue,
+        };
+        if name.eq_ignore_ascii_case("proto") {
+            forwarded.proto = Some(value.to_ascii_lowercase());
+        } else if name.eq_ignore_ascii_case("host") {
+            forwarded.host = Some(value);
+        }
+    }
+    forwarded
+}
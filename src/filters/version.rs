@@ -0,0 +1,295 @@
+//! API versioning filters.
+//!
+//! Clients can ask for a version of an API in the path, as in `/v2/users`,
+//! with a vendor media type in the `Accept` header, as in
+//! `application/vnd.example.v2+json`, or with a header of its own. The
+//! filters of this module extract the requested [`Version`], and
+//! [`dispatch`] runs the routes of that version.
+//!
+//! Versions are compared as strings, without the `v` prefix, so `v2` and
+//! `2` are the same version.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! let v1 = warp::path("users").map(|| "users, v1");
+//! let v2 = warp::path("users").map(|| "users, v2");
+//!
+//! // GET /v1/users or GET /v2/users
+//! let api = warp::version::dispatch(warp::version::path())
+//!     .on("1", v1)
+//!     .on("2", v2);
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::future;
+use http::header::ACCEPT;
+
+use crate::filter::{filter_fn, filter_fn_one, BoxedFilter, Filter, FilterBase, Internal, One};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::Route;
+
+/// A version of an API requested by a client.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Version(String);
+
+impl Version {
+    /// Create a version, without the `v` prefix if it has one.
+    pub fn new(version: &str) -> Version {
+        Version(strip_v(version).to_owned())
+    }
+
+    /// The version, without its `v` prefix.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl PartialEq<str> for Version {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == strip_v(other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Version {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+fn strip_v(version: &str) -> &str {
+    match version
+        .strip_prefix('v')
+        .or_else(|| version.strip_prefix('V'))
+    {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => version,
+    }
+}
+
+/// Creates a `Filter` that extracts the version from the current path
+/// segment, like `v2`.
+///
+/// The segment must be a `v` followed by a number, such as `v1` or `v2.1`,
+/// otherwise the request is rejected with `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use warp::version::Version;
+/// use warp::Filter;
+///
+/// // GET /v2/status
+/// let route = warp::version::path()
+///     .and(warp::path("status"))
+///     .map(|version: Version| format!("API {}", version));
+/// ```
+pub fn path() -> impl Filter<Extract = One<Version>, Error = Rejection> + Copy {
+    crate::path::param::<String>().and_then(|seg: String| {
+        let version = match seg.strip_prefix('v') {
+            Some(number) if is_version_number(number) => Ok(Version(number.to_owned())),
+            _ => Err(reject::not_found()),
+        };
+        future::ready(version)
+    })
+}
+
+fn is_version_number(number: &str) -> bool {
+    number.starts_with(|c: char| c.is_ascii_digit())
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Creates a `Filter` that extracts the version from a header, such as
+/// `api-version: 2`.
+///
+/// Rejects with `400 Bad Request` if the header is missing or empty.
+///
+/// # Example
+///
+/// ```
+/// use warp::version::Version;
+/// use warp::Filter;
+///
+/// let route = warp::version::header("api-version")
+///     .map(|version: Version| format!("API {}", version));
+/// ```
+pub fn header(name: &'static str) -> impl Filter<Extract = One<Version>, Error = Rejection> + Copy {
+    filter_fn_one(move |route| {
+        let version = route
+            .headers()
+            .get(name)
+            .ok_or_else(|| reject::missing_header(name))
+            .and_then(|value| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(Version::new)
+                    .ok_or_else(|| reject::invalid_header(name))
+            });
+        future::ready(version)
+    })
+}
+
+/// Creates a `Filter` that extracts the version from a vendor media type of
+/// the `Accept` header.
+///
+/// Both `application/vnd.{vendor}.v2+json` and
+/// `application/vnd.{vendor}+json; version=2` are understood. Requests
+/// without such a media type are rejected with `404 Not Found`, so another
+/// way of finding the version can be tried with `or`.
+///
+/// # Example
+///
+/// ```
+/// use warp::version::Version;
+/// use warp::Filter;
+///
+/// let version = warp::version::accept("example")
+///     .or(warp::any().map(|| Version::new("1")))
+///     .unify();
+/// ```
+pub fn accept(
+    vendor: &'static str,
+) -> impl Filter<Extract = One<Version>, Error = Rejection> + Copy {
+    filter_fn(move |route: &mut Route| {
+        let version = route
+            .headers()
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(|range| accept_version(range, vendor));
+        future::ready(version.map(|v| (v,)).ok_or_else(reject::not_found))
+    })
+}
+
+// The version of a media range, like `application/vnd.example.v2+json`.
+fn accept_version(range: &str, vendor: &str) -> Option<Version> {
+    let mut parts = range.split(';').map(str::trim);
+    let media_type = parts.next()?.strip_prefix("application/vnd.")?;
+    let media_type = media_type.split('+').next()?;
+    let rest = media_type.strip_prefix(vendor)?;
+    if let Some(number) = rest.strip_prefix(".v") {
+        if is_version_number(number) {
+            return Some(Version(number.to_owned()));
+        }
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    parts.find_map(|param| {
+        let mut param = param.splitn(2, '=');
+        let name = param.next()?.trim();
+        let value = param.next()?.trim().trim_matches('"');
+        if name.eq_ignore_ascii_case("version") && !value.is_empty() {
+            Some(Version::new(value))
+        } else {
+            None
+        }
+    })
+}
+
+/// Create a `Filter` running the routes of the version extracted by
+/// `version`.
+///
+/// Routes are declared per version with [`Dispatch::on`]. Requests for a
+/// version without routes are rejected with `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let api = warp::version::dispatch(warp::version::header("api-version"))
+///     .on("1", warp::path("users").map(|| "users, v1"))
+///     .on("2", warp::path("users").map(|| "users, v2"));
+/// ```
+pub fn dispatch<F>(version: F) -> Dispatch<F>
+where
+    F: Filter<Extract = One<Version>, Error = Rejection> + Clone + Send + Sync + 'static,
+{
+    Dispatch {
+        version,
+        routes: Arc::new(Vec::new()),
+    }
+}
+
+/// A `Filter` running the routes of a version, created with [`dispatch`].
+#[derive(Clone)]
+pub struct Dispatch<F> {
+    version: F,
+    routes: Arc<Vec<(Version, BoxedFilter<(Response,)>)>>,
+}
+
+impl<F> Dispatch<F> {
+    /// Run `route` for requests of `version`.
+    ///
+    /// Several routes of a version are tried in order, as with `or`.
+    pub fn on<R, T>(mut self, version: &str, route: R) -> Self
+    where
+        R: Filter<Extract = (T,)> + Send + Sync + 'static,
+        R::Error: Into<Rejection>,
+        T: Reply + 'static,
+    {
+        let version = Version::new(version);
+        let route = crate::routes::boxed(route);
+        let routes = Arc::make_mut(&mut self.routes);
+        match routes.iter_mut().find(|(v, _)| *v == version) {
+            Some((_, existing)) => {
+                *existing = existing.clone().or(route).unify().boxed();
+            }
+            None => routes.push((version, route)),
+        }
+        self
+    }
+
+    /// The versions with routes, in the order they were declared.
+    pub fn versions(&self) -> impl Iterator<Item = &Version> {
+        self.routes.iter().map(|(version, _)| version)
+    }
+}
+
+impl<F> fmt::Debug for Dispatch<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dispatch")
+            .field("versions", &self.versions().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<F> FilterBase for Dispatch<F>
+where
+    F: Filter<Extract = One<Version>, Error = Rejection> + Clone + Send + Sync + 'static,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let version = self.version.filter(Internal);
+        let routes = self.routes.clone();
+        Box::pin(async move {
+            let (version,) = version.await?;
+            log::trace!("version::dispatch: {}", version);
+            match routes.iter().find(|(v, _)| *v == version) {
+                Some((_, route)) => route.filter(Internal).await,
+                None => Err(reject::not_found()),
+            }
+        })
+    }
+}
@@ -0,0 +1,300 @@
+//! Webhook signatures
+//!
+//! Services delivering webhooks sign their requests with a secret shared
+//! with the receiver, so it can check that a request really comes from the
+//! service. The [`verify`] filter checks HMAC-SHA256 signatures over the raw
+//! body of a request, with the [`Scheme`] of GitHub, Stripe or Slack.
+//!
+//! Signatures are compared in constant time, and for the schemes signing a
+//! timestamp with the body, requests older than the tolerance of the scheme
+//! are refused, so captured requests can't be replayed later.
+//!
+//! Requests with a missing signature header are rejected with a
+//! `MissingHeader`. Requests with an invalid signature are rejected with the
+//! status `403 Forbidden`, and can be recognized with
+//! `rejection.find::<InvalidWebhookSignature>()`.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::webhook::{Scheme, Verified};
+//! use warp::Filter;
+//!
+//! let route = warp::path("github")
+//!     .and(warp::body::content_length_limit(1024 * 1024))
+//!     .and(warp::webhook::verify(Scheme::github("my webhook secret")))
+//!     .map(|payload: Verified| {
+//!         let event = payload.json::<serde_json::Value>();
+//!         warp::reply()
+//!     });
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http::HeaderMap;
+use ring::hmac;
+use serde::de::DeserializeOwned;
+
+use crate::filter::Filter;
+use crate::filters::body::deserialize_error;
+use crate::reject::{self, Rejection};
+
+/// How a service signs its webhook requests.
+#[derive(Clone)]
+pub struct Scheme {
+    key: hmac::Key,
+    kind: Kind,
+    tolerance: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    GitHub,
+    Stripe,
+    Slack,
+}
+
+impl Scheme {
+    /// The scheme of GitHub, with a hex signature of the body in the
+    /// `x-hub-signature-256` header, prefixed with `sha256=`.
+    pub fn github(secret: impl AsRef<[u8]>) -> Scheme {
+        Scheme::new(secret, Kind::GitHub)
+    }
+
+    /// The scheme of Stripe, with the timestamp and hex signatures of the
+    /// timestamp and body in the `stripe-signature` header, as in
+    /// `t=1492774577,v1=5257a869...`.
+    pub fn stripe(secret: impl AsRef<[u8]>) -> Scheme {
+        Scheme::new(secret, Kind::Stripe)
+    }
+
+    /// The scheme of Slack, with the timestamp in the
+    /// `x-slack-request-timestamp` header, and a hex signature of the
+    /// timestamp and body in the `x-slack-signature` header, prefixed with
+    /// `v0=`.
+    pub fn slack(secret: impl AsRef<[u8]>) -> Scheme {
+        Scheme::new(secret, Kind::Slack)
+    }
+
+    fn new(secret: impl AsRef<[u8]>, kind: Kind) -> Scheme {
+        Scheme {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref()),
+            kind,
+            tolerance: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Set how old a signed timestamp can be, 5 minutes by default.
+    ///
+    /// GitHub doesn't sign a timestamp, so this has no effect on its
+    /// scheme.
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<Option<SystemTime>, Rejection> {
+        match self.kind {
+            Kind::GitHub => {
+                let name = "x-hub-signature-256";
+                let signature = header(headers, name)?;
+                let signature = signature.strip_prefix("sha256=").ok_or_else(invalid)?;
+                self.check(&[body], &[signature])?;
+                Ok(None)
+            }
+            Kind::Stripe => {
+                let name = "stripe-signature";
+                let value = header(headers, name)?;
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for pair in value.split(',') {
+                    let mut pair = pair.trim().splitn(2, '=');
+                    match (pair.next(), pair.next()) {
+                        (Some("t"), Some(t)) => timestamp = Some(t),
+                        (Some("v1"), Some(signature)) => signatures.push(signature),
+                        _ => (),
+                    }
+                }
+                let timestamp = timestamp.ok_or_else(invalid)?;
+                let time = self.check_timestamp(timestamp)?;
+                self.check(&[timestamp.as_bytes(), b".", body], &signatures)?;
+                Ok(Some(time))
+            }
+            Kind::Slack => {
+                let timestamp = header(headers, "x-slack-request-timestamp")?;
+                let signature = header(headers, "x-slack-signature")?;
+                let signature = signature.strip_prefix("v0=").ok_or_else(invalid)?;
+                let time = self.check_timestamp(timestamp)?;
+                self.check(&[b"v0:", timestamp.as_bytes(), b":", body], &[signature])?;
+                Ok(Some(time))
+            }
+        }
+    }
+
+    fn check_timestamp(&self, timestamp: &str) -> Result<SystemTime, Rejection> {
+        let secs = timestamp.parse::<u64>().map_err(|_| invalid())?;
+        let time = UNIX_EPOCH
+            .checked_add(Duration::from_secs(secs))
+            .ok_or_else(invalid)?;
+        let age = match SystemTime::now().duration_since(time) {
+            Ok(age) => age,
+            // A clock a little behind the one of the service.
+            Err(err) => err.duration(),
+        };
+        if age > self.tolerance {
+            log::debug!("webhook timestamp {} is out of tolerance", timestamp);
+            return Err(invalid());
+        }
+        Ok(time)
+    }
+
+    // Checks that one of the hex `signatures` is the signature of the
+    // concatenated `parts`.
+    fn check(&self, parts: &[&[u8]], signatures: &[&str]) -> Result<(), Rejection> {
+        let mut ctx = hmac::Context::with_key(&self.key);
+        for part in parts {
+            ctx.update(part);
+        }
+        let expected = ctx.sign();
+        let valid = signatures
+            .iter()
+            .any(|signature| match hex_decode(signature) {
+                Some(signature) => {
+                    ring::constant_time::verify_slices_are_equal(expected.as_ref(), &signature)
+                        .is_ok()
+                }
+                None => false,
+            });
+        if valid {
+            Ok(())
+        } else {
+            log::debug!("webhook signature is invalid");
+            Err(invalid())
+        }
+    }
+}
+
+impl std::fmt::Debug for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Scheme")
+            .field("kind", &self.kind)
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, Rejection> {
+    match headers.get(name) {
+        Some(value) => value.to_str().map_err(|_| reject::invalid_header(name)),
+        None => Err(reject::missing_header(name)),
+    }
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => Some((hex_digit(*hi)? << 4) | hex_digit(*lo)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn invalid() -> Rejection {
+    reject::known(InvalidWebhookSignature { _p: () })
+}
+
+/// The body of a request with a valid signature, extracted by [`verify`].
+#[derive(Clone, Debug)]
+pub struct Verified {
+    body: Bytes,
+    timestamp: Option<SystemTime>,
+}
+
+impl Verified {
+    /// The raw body of the request.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Take the raw body of the request.
+    pub fn into_body(self) -> Bytes {
+        self.body
+    }
+
+    /// The signed timestamp of the request, if the scheme has one.
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
+    /// Deserialize the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// Creates a `Filter` that verifies the signature of a webhook request, and
+/// extracts its body.
+///
+/// The whole body is read to check the signature, so a
+/// [`content_length_limit`](crate::body::content_length_limit) should be
+/// used too.
+pub fn verify(scheme: Scheme) -> impl Filter<Extract = (Verified,), Error = Rejection> + Clone {
+    let scheme = Arc::new(scheme);
+    crate::header::headers_cloned()
+        .and(crate::body::bytes())
+        .and_then(move |headers: HeaderMap, body: Bytes| {
+            let verified = scheme
+                .verify(&headers, &body)
+                .map(|timestamp| Verified { body, timestamp });
+            futures::future::ready(verified)
+        })
+}
+
+/// Creates a `Filter` that verifies the signature of a webhook request, and
+/// extracts its body deserialized as JSON, along with the raw body.
+///
+/// Requests with a body that isn't valid JSON for `T` are rejected with a
+/// `BodyDeserializeError`.
+///
+/// # Example
+///
+/// ```
+/// use bytes::Bytes;
+/// use warp::webhook::Scheme;
+/// use warp::Filter;
+///
+/// let route = warp::path("stripe")
+///     .and(warp::body::content_length_limit(1024 * 1024))
+///     .and(warp::webhook::verify_json(Scheme::stripe("whsec_...")))
+///     .map(|event: serde_json::Value, raw: Bytes| {
+///         warp::reply()
+///     });
+/// ```
+pub fn verify_json<T>(
+    scheme: Scheme,
+) -> impl Filter<Extract = (T, Bytes), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send,
+{
+    verify(scheme)
+        .and_then(|verified: Verified| {
+            let json = verified
+                .json::<T>()
+                .map(|json| (json, verified.into_body()))
+                .map_err(|err| deserialize_error(err.into()));
+            futures::future::ready(json)
+        })
+        .untuple_one()
+}
+
+unit_error! {
+    /// An error used to reject webhook requests without a valid signature.
+    pub InvalidWebhookSignature: "Invalid webhook signature"
+}
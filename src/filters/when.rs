@@ -0,0 +1,237 @@
+//! Conditional routing filters.
+//!
+//! A [`Guard`] is a predicate on a request, such as the value of a header,
+//! or the address of the client. The [`when`] filter lets requests for which
+//! it holds through, and rejects the others with `404 Not Found`, so routes
+//! can be chosen by something else than their path, as for canary releases
+//! or A/B tests:
+//!
+//! ```
+//! use warp::when::{self, when};
+//! use warp::Filter;
+//!
+//! let canary = when(when::header("x-canary", "1").or(when::percent(5.0)))
+//!     .map(|| "new search");
+//! let stable = warp::any().map(|| "search");
+//!
+//! let search = warp::path("search").and(canary.or(stable));
+//! ```
+//!
+//! Guards can be combined with [`and`](Guard::and), [`or`](Guard::or) and
+//! `!`, and display what they check, such as `(header(x-canary = "1")) or
+//! (percent(5))`, to document the gating of a route.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::net::IpAddr;
+use std::ops::Not;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures::future;
+
+use crate::filter::{FilterBase, Internal};
+use crate::reject::{self, Rejection};
+use crate::route::{self, Route};
+
+/// Creates a `Filter` that rejects the requests for which `guard` doesn't
+/// hold, with `404 Not Found`.
+///
+/// It extracts nothing.
+///
+/// # Example
+///
+/// ```
+/// use warp::when::{self, when};
+/// use warp::Filter;
+///
+/// let internal = when(when::remote_ip("10.0.0.0/8")).map(|| "internal");
+/// ```
+pub fn when(guard: Guard) -> When {
+    When { guard }
+}
+
+/// A `Filter` guarding routes, created with [`when`].
+#[derive(Clone, Debug)]
+pub struct When {
+    guard: Guard,
+}
+
+impl FilterBase for When {
+    type Extract = ();
+    type Error = Rejection;
+    type Future = future::Ready<Result<Self::Extract, Self::Error>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let holds = route::with(|route| self.guard.check(route));
+        log::trace!("when {}?: {}", self.guard, holds);
+        if holds {
+            future::ok(())
+        } else {
+            future::err(reject::not_found())
+        }
+    }
+}
+
+/// A predicate on requests, for [`when`].
+#[derive(Clone)]
+pub struct Guard {
+    check: Arc<dyn Fn(&Route) -> bool + Send + Sync>,
+    description: Arc<str>,
+}
+
+impl Guard {
+    fn new<F>(description: String, check: F) -> Guard
+    where
+        F: Fn(&Route) -> bool + Send + Sync + 'static,
+    {
+        Guard {
+            check: Arc::new(check),
+            description: description.into(),
+        }
+    }
+
+    fn check(&self, route: &Route) -> bool {
+        (self.check)(route)
+    }
+
+    /// A guard holding when both this one and `other` hold.
+    pub fn and(self, other: Guard) -> Guard {
+        Guard::new(format!("({}) and ({})", self, other), move |route| {
+            self.check(route) && other.check(route)
+        })
+    }
+
+    /// A guard holding when this one or `other` holds.
+    pub fn or(self, other: Guard) -> Guard {
+        Guard::new(format!("({}) or ({})", self, other), move |route| {
+            self.check(route) || other.check(route)
+        })
+    }
+}
+
+impl Not for Guard {
+    type Output = Guard;
+
+    fn not(self) -> Guard {
+        Guard::new(format!("not ({})", self), move |route| !self.check(route))
+    }
+}
+
+impl fmt::Display for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+impl fmt::Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Guard").field(&self.description).finish()
+    }
+}
+
+/// A guard holding when the header `name` is `value`.
+pub fn header(name: &'static str, value: &str) -> Guard {
+    let value = value.to_owned();
+    Guard::new(format!("header({} = {:?})", name, value), move |route| {
+        route
+            .headers()
+            .get_all(name)
+            .iter()
+            .any(|v| v.as_bytes() == value.as_bytes())
+    })
+}
+
+/// A guard holding when the request has the header `name`.
+pub fn header_present(name: &'static str) -> Guard {
+    Guard::new(format!("header({})", name), move |route| {
+        route.headers().contains_key(name)
+    })
+}
+
+/// A guard holding when the query parameter `name` is `value`.
+pub fn query(name: &str, value: &str) -> Guard {
+    let (name, value) = (name.to_owned(), value.to_owned());
+    Guard::new(format!("query({} = {:?})", name, value), move |route| {
+        let query = route.query().unwrap_or("");
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .map(|pairs| pairs.iter().any(|(n, v)| *n == name && *v == value))
+            .unwrap_or(false)
+    })
+}
+
+/// A guard holding when the client address is in the network `net`, an
+/// address like `10.1.2.3` or a CIDR block like `10.0.0.0/8`.
+///
+/// Requests without a remote address, like those of `warp::test` by
+/// default, never match.
+///
+/// # Panics
+///
+/// This function panics if `net` isn't a valid address or CIDR block.
+pub fn remote_ip(net: &str) -> Guard {
+    let (addr, prefix) = parse_net(net).unwrap_or_else(|| panic!("illegal network: {:?}", net));
+    Guard::new(format!("remote_ip({})", net), move |route| {
+        match route.remote_addr() {
+            Some(remote) => in_net(remote.ip(), addr, prefix),
+            None => false,
+        }
+    })
+}
+
+pub(crate) fn parse_net(net: &str) -> Option<(IpAddr, u8)> {
+    let mut parts = net.splitn(2, '/');
+    let addr = parts.next()?.parse::<IpAddr>().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match parts.next() {
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max)?,
+        None => max,
+    };
+    Some((addr, prefix))
+}
+
+pub(crate) fn in_net(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V4(_)) => match ip.to_ipv4_mapped() {
+            Some(ip) => in_net(IpAddr::V4(ip), net, prefix),
+            None => false,
+        },
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        (IpAddr::V4(_), IpAddr::V6(_)) => false,
+    }
+}
+
+/// A guard holding for a random `percent` of requests, between 0 and 100.
+///
+/// Each request is drawn independently, so a client may see both sides of
+/// the rollout.
+///
+/// # Panics
+///
+/// This function panics if `percent` isn't between 0 and 100.
+pub fn percent(percent: f64) -> Guard {
+    assert!(
+        (0.0..=100.0).contains(&percent),
+        "illegal percent: {}",
+        percent
+    );
+    Guard::new(format!("percent({})", percent), move |_| {
+        random_fraction() * 100.0 < percent
+    })
+}
+
+// A random number in `[0, 1)`, from the random keys of `RandomState`.
+pub(crate) fn random_fraction() -> f64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -322,6 +322,14 @@ impl Message {
         self.inner.is_pong()
     }
 
+    /// Returns the code and reason of a Close message, if it has them.
+    pub fn close_frame(&self) -> Option<(u16, &str)> {
+        match self.inner {
+            protocol::Message::Close(Some(ref close)) => Some((close.code.into(), &close.reason)),
+            _ => None,
+        }
+    }
+
     /// Try to get a reference to the string text, if this is a Text message.
     pub fn to_str(&self) -> Result<&str, ()> {
         match self.inner {
@@ -90,6 +90,7 @@ mod error;
 mod filter;
 pub mod filters;
 mod generic;
+mod proxy_protocol;
 pub mod redirect;
 pub mod reject;
 pub mod reply;
@@ -102,16 +103,31 @@ mod tls;
 mod transport;
 
 pub use self::error::Error;
-pub use self::filter::Filter;
+pub use self::filter::{map_request_body, map_response_body, wrap_fn, Filter, Wrap};
 // This otherwise shows a big dump of re-exports in the doc homepage,
 // with zero context, so just hide it from the docs. Doc examples
 // on each can show that a convenient import exists.
 #[cfg(feature = "compression")]
 #[doc(hidden)]
 pub use self::filters::compression;
+#[cfg(feature = "csrf")]
+#[doc(hidden)]
+pub use self::filters::csrf;
+#[cfg(feature = "geoip")]
+#[doc(hidden)]
+pub use self::filters::geoip;
+#[cfg(feature = "graphql")]
+#[doc(hidden)]
+pub use self::filters::graphql;
 #[cfg(feature = "multipart")]
 #[doc(hidden)]
 pub use self::filters::multipart;
+#[cfg(feature = "session")]
+#[doc(hidden)]
+pub use self::filters::session;
+#[cfg(feature = "webhook")]
+#[doc(hidden)]
+pub use self::filters::webhook;
 #[cfg(feature = "websocket")]
 #[doc(hidden)]
 pub use self::filters::ws;
@@ -120,29 +136,71 @@ pub use self::filters::{
     addr,
     // any() function
     any::any,
+    audit,
+    auth,
     body,
+    cache,
+    conditional,
+    // conditional() function
+    conditional::conditional,
     cookie,
     // cookie() function
     cookie::cookie,
     cors,
     // cors() function
     cors::cors,
+    deadline,
     ext,
     fs,
+    grpc_web,
+    // grpc_web() function
+    grpc_web::grpc_web,
     header,
     // header() function
     header::header,
+    health,
+    host,
+    https,
+    idempotency,
+    ip,
+    limit,
     log,
     // log() function
     log::log,
+    maintenance,
+    metering,
     method::{delete, get, head, method, options, patch, post, put},
+    metrics,
+    mirror,
+    panic,
     path,
     // path() function and macro
     path::path,
     query,
     // query() function
     query::query,
+    router,
+    // routes! macro
+    routes,
+    rpc,
+    split,
+    // split() function
+    split::split,
     sse,
+    state,
+    // state() function
+    state::state,
+    tower,
+    // service_filter() function
+    tower::service_filter,
+    trace,
+    // trace() function
+    trace::trace,
+    uri,
+    version,
+    when,
+    // when() function
+    when::when,
 };
 // ws() function
 #[cfg(feature = "websocket")]
@@ -157,8 +215,8 @@ pub use self::reject::{reject, Rejection};
 pub use self::reply::{reply, Reply};
 #[cfg(feature = "tls")]
 pub use self::server::TlsServer;
-pub use self::server::{serve, Server};
-pub use self::service::service;
+pub use self::server::{serve, serve_dynamic, Server};
+pub use self::service::{service, service_with_info};
 #[doc(hidden)]
 pub use http;
 #[doc(hidden)]
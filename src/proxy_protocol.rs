@@ -0,0 +1,272 @@
+//! PROXY protocol support for the listener.
+//!
+//! Load balancers forwarding TCP connections, such as HAProxy or AWS NLB,
+//! can prefix them with a header holding the address of the client:
+//! https://www.haproxy.org/download/2.3/doc/proxy-protocol.txt
+//!
+//! Both the human readable version 1 and the binary version 2 of the header
+//! are supported.
+
+use std::future::Future;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::poll_fn;
+use futures::stream::{FuturesUnordered, StreamExt};
+use hyper::server::accept::Accept;
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::transport::Transport;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+// A version 1 header is at most 107 bytes, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+// The headers of clients that are this slow are given up on.
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Handshake = Pin<Box<dyn Future<Output = io::Result<ProxyStream>> + Send>>;
+
+/// Accepts connections, reading their PROXY header if enabled.
+///
+/// Headers are read concurrently, so that a slow connection doesn't hold up
+/// the others. Connections with a missing or invalid header are closed.
+pub(crate) struct ProxyAcceptor {
+    incoming: AddrIncoming,
+    enabled: bool,
+    handshakes: FuturesUnordered<Handshake>,
+}
+
+impl ProxyAcceptor {
+    pub(crate) fn new(incoming: AddrIncoming, enabled: bool) -> ProxyAcceptor {
+        ProxyAcceptor {
+            incoming,
+            enabled,
+            handshakes: FuturesUnordered::new(),
+        }
+    }
+}
+
+impl Accept for ProxyAcceptor {
+    type Conn = ProxyStream;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let pin = self.get_mut();
+        loop {
+            match Pin::new(&mut pin.incoming).poll_accept(cx) {
+                Poll::Ready(Some(Ok(stream))) if !pin.enabled => {
+                    let remote_addr = stream.remote_addr();
+                    return Poll::Ready(Some(Ok(ProxyStream::new(
+                        stream,
+                        remote_addr,
+                        Vec::new(),
+                    ))));
+                }
+                Poll::Ready(Some(Ok(stream))) => {
+                    pin.handshakes.push(Box::pin(handshake(stream)));
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) if pin.handshakes.is_empty() => return Poll::Ready(None),
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
+        loop {
+            match pin.handshakes.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(stream))) => return Poll::Ready(Some(Ok(stream))),
+                Poll::Ready(Some(Err(err))) => {
+                    log::debug!("closing connection, PROXY header error: {}", err);
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+async fn handshake(mut stream: AddrStream) -> io::Result<ProxyStream> {
+    let read = async {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 256];
+        loop {
+            if let Some((addr, len)) = parse(&buf)? {
+                let remote_addr = addr.unwrap_or_else(|| stream.remote_addr());
+                let rest = buf.split_off(len);
+                return Ok((remote_addr, rest));
+            }
+            let n = poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut chunk)).await?;
+            if n == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    };
+    let (remote_addr, rest) = tokio::time::timeout(HEADER_TIMEOUT, read)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out reading header"))??;
+    Ok(ProxyStream::new(stream, remote_addr, rest))
+}
+
+/// Parses a PROXY header at the start of `buf`.
+///
+/// Returns `None` if more bytes are needed, or else the address of the
+/// client, if the header has one, and the length of the header.
+fn parse(buf: &[u8]) -> io::Result<Option<(Option<SocketAddr>, usize)>> {
+    let n = buf.len().min(V2_SIGNATURE.len());
+    if buf[..n] == V2_SIGNATURE[..n] {
+        return parse_v2(buf);
+    }
+    let n = buf.len().min(V1_PREFIX.len());
+    if buf[..n] == V1_PREFIX[..n] {
+        return parse_v1(buf);
+    }
+    Err(invalid("missing header"))
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Option<(Option<SocketAddr>, usize)>> {
+    let end = match buf
+        .windows(2)
+        .take(V1_MAX_LEN - 1)
+        .position(|w| w == b"\r\n")
+    {
+        Some(end) => end,
+        None if buf.len() >= V1_MAX_LEN => return Err(invalid("header too long")),
+        None => return Ok(None),
+    };
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..end]).map_err(|_| invalid("not utf-8"))?;
+    let mut parts = line.split(' ');
+    let addr = match parts.next() {
+        Some("UNKNOWN") => None,
+        Some(proto @ "TCP4") | Some(proto @ "TCP6") => {
+            let mut next = || parts.next().ok_or_else(|| invalid("missing field"));
+            let src: IpAddr = next()?.parse().map_err(|_| invalid("bad address"))?;
+            let _dst: IpAddr = next()?.parse().map_err(|_| invalid("bad address"))?;
+            let port: u16 = next()?.parse().map_err(|_| invalid("bad port"))?;
+            let _dst_port: u16 = next()?.parse().map_err(|_| invalid("bad port"))?;
+            if src.is_ipv4() != (proto == "TCP4") || parts.next().is_some() {
+                return Err(invalid("bad address"));
+            }
+            Some(SocketAddr::new(src, port))
+        }
+        _ => return Err(invalid("unknown protocol")),
+    };
+    Ok(Some((addr, end + 2)))
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Option<(Option<SocketAddr>, usize)>> {
+    if buf.len() < 16 {
+        return Ok(None);
+    }
+    let version = buf[12] >> 4;
+    let command = buf[12] & 0x0f;
+    let family = buf[13] >> 4;
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if version != 2 {
+        return Err(invalid("unknown version"));
+    }
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let addrs = &buf[16..len];
+    let addr = match (command, family) {
+        // LOCAL connections, such as health checks of the proxy itself.
+        (0, _) => None,
+        (1, 1) if addrs.len() >= 12 => {
+            let ip = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            Some(SocketAddr::new(ip.into(), port))
+        }
+        (1, 2) if addrs.len() >= 36 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&addrs[..16]);
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+        }
+        (1, 1) | (1, 2) => return Err(invalid("addresses too short")),
+        // Unspecified or unix socket addresses.
+        (1, _) => None,
+        _ => return Err(invalid("unknown command")),
+    };
+    Ok(Some((addr, len)))
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A connection whose client address may come from a PROXY header.
+pub(crate) struct ProxyStream {
+    io: AddrStream,
+    remote_addr: SocketAddr,
+    // Bytes read past the header, served before reading more.
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl ProxyStream {
+    fn new(io: AddrStream, remote_addr: SocketAddr, buf: Vec<u8>) -> ProxyStream {
+        ProxyStream {
+            io,
+            remote_addr,
+            buf,
+            pos: 0,
+        }
+    }
+}
+
+impl Transport for ProxyStream {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        Some(self.remote_addr)
+    }
+}
+
+impl AsyncRead for ProxyStream {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [MaybeUninit<u8>]) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let pin = self.get_mut();
+        if pin.pos < pin.buf.len() {
+            let n = (pin.buf.len() - pin.pos).min(buf.len());
+            buf[..n].copy_from_slice(&pin.buf[pin.pos..pin.pos + n]);
+            pin.pos += n;
+            if pin.pos == pin.buf.len() {
+                pin.buf = Vec::new();
+                pin.pos = 0;
+            }
+            return Poll::Ready(Ok(n));
+        }
+        Pin::new(&mut pin.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProxyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
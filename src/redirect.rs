@@ -2,8 +2,15 @@
 //!
 //! The types in this module are helpers that implement [`Reply`](Reply), and easy
 //! to use in order to setup redirects.
+//!
+//! Besides a [`Uri`](http::Uri), the location can be given as a string. A
+//! string that starts with neither a `/` nor a scheme is relative, and is
+//! resolved against the path matched so far by the route, so
+//! `warp::path("admin").map(|| warp::redirect::temporary("dashboard"))`
+//! redirects to `/admin/dashboard`.
 
-use http::{header, StatusCode};
+use bytes::BytesMut;
+use http::{header, header::HeaderValue, StatusCode};
 
 use self::sealed::AsLocation;
 use crate::reply::{self, Reply};
@@ -48,12 +55,151 @@ pub fn temporary(uri: impl AsLocation) -> impl Reply {
     )
 }
 
+/// A `308` permanent redirect to a different location.
+///
+/// Unlike [`redirect`](redirect), clients must repeat the request with the
+/// same method and body, so this is suitable for `POST` endpoints.
+///
+/// # Example
+///
+/// ```
+/// use warp::{http::Uri, Filter};
+///
+/// let route = warp::path("v1")
+///     .and(warp::post())
+///     .map(|| {
+///         warp::redirect::permanent(Uri::from_static("/v2"))
+///     });
+/// ```
+pub fn permanent(uri: impl AsLocation) -> impl Reply {
+    reply::with_header(
+        StatusCode::PERMANENT_REDIRECT,
+        header::LOCATION,
+        uri.header_value(),
+    )
+}
+
+/// A `307` temporary redirect that keeps the query string of the request.
+///
+/// If the location already has a query, the request's query is appended to
+/// it.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // `/search?q=warp` redirects to `/v2/search?q=warp`
+/// let route = warp::path("search")
+///     .map(|| {
+///         warp::redirect::temporary_preserve_query("/v2/search")
+///     });
+/// ```
+pub fn temporary_preserve_query(uri: impl AsLocation) -> impl Reply {
+    reply::with_header(
+        StatusCode::TEMPORARY_REDIRECT,
+        header::LOCATION,
+        preserve_query(uri.header_value()),
+    )
+}
+
+/// A `308` permanent redirect that keeps the query string of the request.
+///
+/// If the location already has a query, the request's query is appended to
+/// it.
+pub fn permanent_preserve_query(uri: impl AsLocation) -> impl Reply {
+    reply::with_header(
+        StatusCode::PERMANENT_REDIRECT,
+        header::LOCATION,
+        preserve_query(uri.header_value()),
+    )
+}
+
+fn preserve_query(location: HeaderValue) -> HeaderValue {
+    if !crate::route::is_set() {
+        return location;
+    }
+    let query = match crate::route::with(|route| route.query().map(ToOwned::to_owned)) {
+        Some(query) if !query.is_empty() => query,
+        _ => return location,
+    };
+
+    let location = location.as_bytes();
+    let (location, fragment) = match location.iter().position(|&b| b == b'#') {
+        Some(idx) => location.split_at(idx),
+        None => (location, &b""[..]),
+    };
+    let sep: &[u8] = if !location.contains(&b'?') {
+        b"?"
+    } else if location.ends_with(b"?") || location.ends_with(b"&") {
+        b""
+    } else {
+        b"&"
+    };
+
+    let mut bytes = BytesMut::with_capacity(location.len() + query.len() + fragment.len() + 1);
+    bytes.extend_from_slice(location);
+    bytes.extend_from_slice(sep);
+    bytes.extend_from_slice(query.as_bytes());
+    bytes.extend_from_slice(fragment);
+    HeaderValue::from_maybe_shared(bytes.freeze()).expect("query is a valid HeaderValue")
+}
+
+fn resolve(location: &str) -> String {
+    let is_absolute = location.starts_with('/')
+        || location
+            .find(':')
+            .map(|idx| {
+                location[..idx]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+            })
+            .unwrap_or(false);
+    if is_absolute || !crate::route::is_set() {
+        return location.to_owned();
+    }
+
+    let mut segments = crate::route::with(|route| {
+        let matched = &route.full_path()[..route.matched_path_index()];
+        matched
+            .split('/')
+            .filter(|seg| !seg.is_empty())
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>()
+    });
+
+    let (path, rest) = match location.find(['?', '#']) {
+        Some(idx) => location.split_at(idx),
+        None => (location, ""),
+    };
+    for seg in path.split('/') {
+        match seg {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            seg => segments.push(seg.to_owned()),
+        }
+    }
+
+    let mut resolved = String::with_capacity(location.len() + 1);
+    for seg in &segments {
+        resolved.push('/');
+        resolved.push_str(seg);
+    }
+    if resolved.is_empty() || path.ends_with('/') {
+        resolved.push('/');
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
 mod sealed {
     use bytes::Bytes;
     use http::{header::HeaderValue, Uri};
 
     // These sealed traits are to allow adding possibly new impls so other
-    // arguments could be accepted, like maybe just `warp::redirect("/v2")`.
+    // arguments could be accepted.
     pub trait AsLocation: Sealed {}
     pub trait Sealed {
         fn header_value(self) -> HeaderValue;
@@ -67,4 +213,22 @@ mod sealed {
             HeaderValue::from_maybe_shared(bytes).expect("Uri is a valid HeaderValue")
         }
     }
+
+    impl AsLocation for &'static str {}
+
+    impl Sealed for &'static str {
+        fn header_value(self) -> HeaderValue {
+            super::resolve(self).header_value()
+        }
+    }
+
+    impl AsLocation for String {}
+
+    impl Sealed for String {
+        fn header_value(self) -> HeaderValue {
+            let resolved = super::resolve(&self);
+            HeaderValue::from_maybe_shared(Bytes::from(resolved))
+                .expect("illegal redirect location")
+        }
+    }
 }
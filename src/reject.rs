@@ -34,11 +34,13 @@ use std::fmt;
 
 use http::{
     self,
-    header::{HeaderValue, CONTENT_TYPE},
+    header::{HeaderValue, ALLOW, CONTENT_TYPE, WWW_AUTHENTICATE},
     StatusCode,
 };
 use hyper::Body;
 
+use crate::reply::{Problem, Reply};
+
 pub(crate) use self::sealed::{CombineRejection, IsReject};
 
 /// Rejects a request with `404 Not Found`.
@@ -81,8 +83,10 @@ pub(crate) fn missing_cookie(name: &'static str) -> Rejection {
 
 // 405 Method Not Allowed
 #[inline]
-pub(crate) fn method_not_allowed() -> Rejection {
-    known(MethodNotAllowed { _p: () })
+pub(crate) fn method_not_allowed(allowed: &[http::Method]) -> Rejection {
+    Rejection {
+        reason: Reason::MethodNotAllowed(MethodNotAllowed::new(allowed)),
+    }
 }
 
 // 411 Length Required
@@ -116,6 +120,81 @@ pub fn custom<T: Reject>(err: T) -> Rejection {
     Rejection::custom(Box::new(err))
 }
 
+/// Recover from the built-in rejections with
+/// [RFC 7807](https://tools.ietf.org/html/rfc7807) problem documents.
+///
+/// The documents have the `about:blank` type, the reason of the status code
+/// as title, and the message of the rejection as detail. Custom rejections
+/// are passed on, for other [`recover`][] filters to handle.
+///
+/// [`recover`]: ../trait.Filter.html#method.recover
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("hello")
+///     .map(|| "Hello, World!")
+///     .recover(warp::reject::recover_problem);
+/// ```
+pub async fn recover_problem(err: Rejection) -> Result<crate::reply::Response, Rejection> {
+    let detail = match err.reason {
+        Reason::NotFound => None,
+        Reason::MethodNotAllowed(ref e) => Some(e.to_string()),
+        Reason::Other(ref rejections) => match rejections.preferred_known() {
+            Some(known) => Some(known.to_string()),
+            None => return Err(err),
+        },
+    };
+    let mut problem = Problem::from_status(err.status());
+    if let Some(detail) = detail {
+        problem = problem.detail(detail);
+    }
+
+    // Keep the headers of the plain response, such as `WWW-Authenticate`.
+    let mut res = err.into_response();
+    let (parts, body) = problem.into_response().into_parts();
+    res.headers_mut()
+        .insert(CONTENT_TYPE, parts.headers[CONTENT_TYPE].clone());
+    *res.body_mut() = body;
+    Ok(res)
+}
+
+/// Recover from the built-in rejections with JSON bodies.
+///
+/// The bodies are the payloads of [`Rejection::to_json`]. Custom rejections
+/// are passed on, for other [`recover`][] filters to handle.
+///
+/// [`recover`]: ../trait.Filter.html#method.recover
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("hello")
+///     .map(|| "Hello, World!")
+///     .recover(warp::reject::recover_json);
+/// ```
+pub async fn recover_json(err: Rejection) -> Result<crate::reply::Response, Rejection> {
+    if let Reason::Other(ref rejections) = err.reason {
+        if rejections.preferred_known().is_none() {
+            return Err(err);
+        }
+    }
+
+    // Keep the headers of the plain response, such as `WWW-Authenticate`.
+    let mut res = err.into_response();
+    let (parts, body) = crate::reply::json(&err.to_json())
+        .into_response()
+        .into_parts();
+    res.headers_mut()
+        .insert(CONTENT_TYPE, parts.headers[CONTENT_TYPE].clone());
+    *res.body_mut() = body;
+    Ok(res)
+}
+
 /// Protect against re-rejecting a rejection.
 ///
 /// ```compile_fail
@@ -177,6 +256,9 @@ pub struct Rejection {
 
 enum Reason {
     NotFound,
+    // Kept inline, so that the method filters of a large route tree don't
+    // allocate for every request of another method.
+    MethodNotAllowed(MethodNotAllowed),
     Other(Box<Rejections>),
 }
 
@@ -254,10 +336,71 @@ enum_known! {
     BodyReadError(crate::body::BodyReadError),
     BodyDeserializeError(crate::body::BodyDeserializeError),
     CorsForbidden(crate::cors::CorsForbidden),
+    IpForbidden(crate::ip::IpForbidden),
+    TooManyRequests(crate::limit::TooManyRequests),
     #[cfg(feature = "websocket")]
     MissingConnectionUpgrade(crate::ws::MissingConnectionUpgrade),
     MissingExtension(crate::ext::MissingExtension),
     BodyConsumedMultipleTimes(crate::body::BodyConsumedMultipleTimes),
+    ServiceError(crate::tower::ServiceError),
+    Unauthorized(crate::auth::Unauthorized),
+    IdempotencyError(crate::idempotency::IdempotencyError),
+    #[cfg(feature = "session")]
+    SessionError(crate::session::SessionError),
+    #[cfg(feature = "csrf")]
+    InvalidCsrfToken(crate::csrf::InvalidCsrfToken),
+    #[cfg(any(feature = "askama", feature = "tera"))]
+    TemplateError(crate::reply::template::TemplateError),
+    #[cfg(feature = "webhook")]
+    InvalidWebhookSignature(crate::webhook::InvalidWebhookSignature),
+}
+
+impl Known {
+    fn code(&self) -> &'static str {
+        match *self {
+            Known::MethodNotAllowed(_) => "method_not_allowed",
+            Known::InvalidHeader(_) => "invalid_header",
+            Known::MissingHeader(_) => "missing_header",
+            Known::MissingCookie(_) => "missing_cookie",
+            Known::InvalidQuery(_) => "invalid_query",
+            Known::LengthRequired(_) => "length_required",
+            Known::PayloadTooLarge(_) => "payload_too_large",
+            Known::UnsupportedMediaType(_) => "unsupported_media_type",
+            Known::FileOpenError(_) => "file_open_error",
+            Known::FilePermissionError(_) => "file_permission_error",
+            Known::BodyReadError(_) => "body_read_error",
+            Known::BodyDeserializeError(_) => "body_deserialize_error",
+            Known::CorsForbidden(_) => "cors_forbidden",
+            Known::IpForbidden(_) => "ip_forbidden",
+            Known::TooManyRequests(_) => "too_many_requests",
+            #[cfg(feature = "websocket")]
+            Known::MissingConnectionUpgrade(_) => "missing_connection_upgrade",
+            Known::MissingExtension(_) => "missing_extension",
+            Known::BodyConsumedMultipleTimes(_) => "body_consumed_multiple_times",
+            Known::ServiceError(_) => "service_error",
+            Known::Unauthorized(_) => "unauthorized",
+            Known::IdempotencyError(_) => "idempotency_error",
+            #[cfg(feature = "session")]
+            Known::SessionError(_) => "session_error",
+            #[cfg(feature = "csrf")]
+            Known::InvalidCsrfToken(_) => "invalid_csrf_token",
+            #[cfg(any(feature = "askama", feature = "tera"))]
+            Known::TemplateError(_) => "template_error",
+            #[cfg(feature = "webhook")]
+            Known::InvalidWebhookSignature(_) => "invalid_webhook_signature",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match *self {
+            Known::InvalidHeader(ref e) => Some(serde_json::json!({ "header": e.name() })),
+            Known::MissingHeader(ref e) => Some(serde_json::json!({ "header": e.name() })),
+            Known::MissingCookie(ref e) => Some(serde_json::json!({ "cookie": e.name() })),
+            #[cfg(any(feature = "askama", feature = "tera"))]
+            Known::TemplateError(ref e) => Some(serde_json::json!({ "template": e.template() })),
+            _ => None,
+        }
+    }
 }
 
 impl Rejection {
@@ -293,10 +436,58 @@ impl Rejection {
     /// }
     /// ```
     pub fn find<T: 'static>(&self) -> Option<&T> {
-        if let Reason::Other(ref rejections) = self.reason {
-            return rejections.find();
+        match self.reason {
+            Reason::NotFound => None,
+            Reason::MethodNotAllowed(ref e) => (e as &dyn Any).downcast_ref(),
+            Reason::Other(ref rejections) => rejections.find(),
+        }
+    }
+
+    /// Describes this `Rejection` as a machine-readable JSON object.
+    ///
+    /// The object has a stable snake case `code` identifying the rejection,
+    /// a human-readable `message`, and `details` specific to the rejection,
+    /// such as the name of a missing header, or `null`. Custom rejections
+    /// have the code `"unhandled_rejection"`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let rejection = warp::reject();
+    ///
+    /// assert_eq!(rejection.to_json()["code"], "not_found");
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        let (code, message, details) = match self.reason {
+            Reason::NotFound => ("not_found", "Not Found".to_owned(), None),
+            Reason::MethodNotAllowed(ref e) => ("method_not_allowed", e.to_string(), None),
+            Reason::Other(ref rejections) => match rejections.preferred_known() {
+                Some(known) => (known.code(), known.to_string(), known.details()),
+                None => (
+                    "unhandled_rejection",
+                    "Unhandled rejection".to_owned(),
+                    None,
+                ),
+            },
+        };
+        serde_json::json!({
+            "code": code,
+            "message": message,
+            "details": details,
+        })
+    }
+
+    // The methods of all the `MethodNotAllowed` rejections.
+    pub(crate) fn allowed_methods(&self) -> Vec<http::Method> {
+        match self.reason {
+            Reason::NotFound => Vec::new(),
+            Reason::MethodNotAllowed(ref e) => e.allowed(),
+            Reason::Other(ref rejections) => {
+                let mut allowed = MethodNotAllowed { allowed: 0 };
+                rejections.allowed_methods(&mut allowed);
+                allowed.allowed()
+            }
         }
-        None
     }
 
     /// Returns true if this Rejection was made via `warp::reject::not_found`.
@@ -338,6 +529,7 @@ impl IsReject for Rejection {
     fn status(&self) -> StatusCode {
         match self.reason {
             Reason::NotFound => StatusCode::NOT_FOUND,
+            Reason::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
             Reason::Other(ref other) => other.status(),
         }
     }
@@ -349,7 +541,30 @@ impl IsReject for Rejection {
                 *res.status_mut() = StatusCode::NOT_FOUND;
                 res
             }
-            Reason::Other(ref other) => other.into_response(),
+            Reason::MethodNotAllowed(ref e) => {
+                let mut res = http::Response::new(Body::from(e.to_string()));
+                *res.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+                res.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                if e.allowed != 0 {
+                    res.headers_mut()
+                        .insert(ALLOW, crate::filters::method::allow_header(&e.allowed()));
+                }
+                res
+            }
+            Reason::Other(ref other) => {
+                let mut res = other.into_response();
+                if res.status() == StatusCode::METHOD_NOT_ALLOWED {
+                    let allowed = self.allowed_methods();
+                    if !allowed.is_empty() {
+                        res.headers_mut()
+                            .insert(ALLOW, crate::filters::method::allow_header(&allowed));
+                    }
+                }
+                res
+            }
         }
     }
 }
@@ -364,6 +579,7 @@ impl fmt::Debug for Reason {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Reason::NotFound => f.write_str("NotFound"),
+            Reason::MethodNotAllowed(ref e) => fmt::Debug::fmt(e, f),
             Reason::Other(ref other) => match **other {
                 Rejections::Known(ref e) => fmt::Debug::fmt(e, f),
                 Rejections::Custom(ref e) => fmt::Debug::fmt(e, f),
@@ -385,6 +601,7 @@ impl Rejections {
         match *self {
             Rejections::Known(ref k) => match *k {
                 Known::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+                Known::Unauthorized(_) => StatusCode::UNAUTHORIZED,
                 Known::InvalidHeader(_)
                 | Known::MissingHeader(_)
                 | Known::MissingCookie(_)
@@ -394,12 +611,25 @@ impl Rejections {
                 #[cfg(feature = "websocket")]
                 Known::MissingConnectionUpgrade(_) => StatusCode::BAD_REQUEST,
                 Known::LengthRequired(_) => StatusCode::LENGTH_REQUIRED,
+                Known::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
                 Known::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
                 Known::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
-                Known::FilePermissionError(_) | Known::CorsForbidden(_) => StatusCode::FORBIDDEN,
+                Known::FilePermissionError(_) | Known::CorsForbidden(_) | Known::IpForbidden(_) => {
+                    StatusCode::FORBIDDEN
+                }
                 Known::FileOpenError(_)
                 | Known::MissingExtension(_)
-                | Known::BodyConsumedMultipleTimes(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                | Known::BodyConsumedMultipleTimes(_)
+                | Known::ServiceError(_)
+                | Known::IdempotencyError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                #[cfg(feature = "session")]
+                Known::SessionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                #[cfg(feature = "csrf")]
+                Known::InvalidCsrfToken(_) => StatusCode::FORBIDDEN,
+                #[cfg(any(feature = "askama", feature = "tera"))]
+                Known::TemplateError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                #[cfg(feature = "webhook")]
+                Known::InvalidWebhookSignature(_) => StatusCode::FORBIDDEN,
             },
             Rejections::Custom(..) => StatusCode::INTERNAL_SERVER_ERROR,
             Rejections::Combined(ref a, ref b) => preferred(a, b).status(),
@@ -415,6 +645,13 @@ impl Rejections {
                     CONTENT_TYPE,
                     HeaderValue::from_static("text/plain; charset=utf-8"),
                 );
+                if let Known::Unauthorized(ref e) = *e {
+                    res.headers_mut()
+                        .insert(WWW_AUTHENTICATE, e.challenge().clone());
+                }
+                if let Known::TooManyRequests(ref e) = *e {
+                    e.headers(res.headers_mut());
+                }
                 res
             }
             Rejections::Custom(ref e) => {
@@ -435,6 +672,26 @@ impl Rejections {
         }
     }
 
+    // The built-in rejection that would be turned into a response, if any.
+    fn preferred_known(&self) -> Option<&Known> {
+        match *self {
+            Rejections::Known(ref k) => Some(k),
+            Rejections::Custom(..) => None,
+            Rejections::Combined(ref a, ref b) => preferred(a, b).preferred_known(),
+        }
+    }
+
+    fn allowed_methods(&self, methods: &mut MethodNotAllowed) {
+        match *self {
+            Rejections::Known(Known::MethodNotAllowed(ref e)) => methods.allowed |= e.allowed,
+            Rejections::Known(_) | Rejections::Custom(..) => {}
+            Rejections::Combined(ref a, ref b) => {
+                a.allowed_methods(methods);
+                b.allowed_methods(methods);
+            }
+        }
+    }
+
     fn find<T: 'static>(&self) -> Option<&T> {
         match *self {
             Rejections::Known(ref e) => e.inner_as_any().downcast_ref(),
@@ -480,11 +737,63 @@ unit_error! {
     pub InvalidQuery: "Invalid query string"
 }
 
-unit_error! {
-    /// HTTP method not allowed
-    pub MethodNotAllowed: "HTTP method not allowed"
+// The standard methods, in the order they are listed in an `Allow` header.
+static METHODS: [http::Method; 9] = [
+    http::Method::GET,
+    http::Method::HEAD,
+    http::Method::POST,
+    http::Method::PUT,
+    http::Method::DELETE,
+    http::Method::CONNECT,
+    http::Method::OPTIONS,
+    http::Method::TRACE,
+    http::Method::PATCH,
+];
+
+/// HTTP method not allowed
+#[derive(Clone, Copy)]
+pub struct MethodNotAllowed {
+    // A bit for each of the `METHODS` allowed.
+    allowed: u16,
 }
 
+impl MethodNotAllowed {
+    // Extension methods aren't kept, since they would need an allocation.
+    fn new(methods: &[http::Method]) -> MethodNotAllowed {
+        let allowed = methods
+            .iter()
+            .filter_map(|method| METHODS.iter().position(|m| m == method))
+            .fold(0, |allowed, i| allowed | 1 << i);
+        MethodNotAllowed { allowed }
+    }
+
+    /// Retrieve the standard methods that would have been allowed instead
+    pub fn allowed(&self) -> Vec<http::Method> {
+        METHODS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.allowed & 1 << i != 0)
+            .map(|(_, method)| method.clone())
+            .collect()
+    }
+}
+
+impl fmt::Debug for MethodNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MethodNotAllowed")
+            .field("allowed", &self.allowed())
+            .finish()
+    }
+}
+
+impl ::std::fmt::Display for MethodNotAllowed {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("HTTP method not allowed")
+    }
+}
+
+impl StdError for MethodNotAllowed {}
+
 unit_error! {
     /// A content-length header is required
     pub LengthRequired: "A content-length header is required"
@@ -564,7 +873,7 @@ impl ::std::fmt::Display for MissingCookie {
 impl StdError for MissingCookie {}
 
 mod sealed {
-    use super::{Reason, Rejection, Rejections};
+    use super::{Known, MethodNotAllowed, Reason, Rejection, Rejections};
     use http::StatusCode;
     use std::convert::Infallible;
     use std::fmt;
@@ -621,11 +930,28 @@ mod sealed {
                 (Reason::Other(left), Reason::Other(right)) => {
                     Reason::Other(Box::new(Rejections::Combined(left, right)))
                 }
+                (Reason::MethodNotAllowed(left), Reason::MethodNotAllowed(right)) => {
+                    Reason::MethodNotAllowed(MethodNotAllowed {
+                        allowed: left.allowed | right.allowed,
+                    })
+                }
+                (Reason::Other(left), Reason::MethodNotAllowed(right)) => {
+                    let right = Box::new(Rejections::Known(Known::MethodNotAllowed(right)));
+                    Reason::Other(Box::new(Rejections::Combined(left, right)))
+                }
+                (Reason::MethodNotAllowed(left), Reason::Other(right)) => {
+                    let left = Box::new(Rejections::Known(Known::MethodNotAllowed(left)));
+                    Reason::Other(Box::new(Rejections::Combined(left, right)))
+                }
                 (Reason::Other(other), Reason::NotFound)
                 | (Reason::NotFound, Reason::Other(other)) => {
                     // ignore the NotFound
                     Reason::Other(other)
                 }
+                (Reason::MethodNotAllowed(other), Reason::NotFound)
+                | (Reason::NotFound, Reason::MethodNotAllowed(other)) => {
+                    Reason::MethodNotAllowed(other)
+                }
                 (Reason::NotFound, Reason::NotFound) => Reason::NotFound,
             };
 
@@ -679,7 +1005,7 @@ mod tests {
     fn rejection_status() {
         assert_eq!(not_found().status(), StatusCode::NOT_FOUND);
         assert_eq!(
-            method_not_allowed().status(),
+            method_not_allowed(&[]).status(),
             StatusCode::METHOD_NOT_ALLOWED
         );
         assert_eq!(length_required().status(), StatusCode::LENGTH_REQUIRED);
@@ -768,7 +1094,7 @@ mod tests {
 
         assert_eq!(rej.find::<Left>(), Some(&Left));
 
-        let rej = rej.combine(method_not_allowed());
+        let rej = rej.combine(method_not_allowed(&[]));
 
         assert_eq!(rej.find::<Left>(), Some(&Left));
         assert!(rej.find::<MethodNotAllowed>().is_some(), "MethodNotAllowed");
@@ -776,9 +1102,10 @@ mod tests {
 
     #[test]
     fn size_of_rejection() {
+        // The tag, and a box or the methods of a `MethodNotAllowed`.
         assert_eq!(
             ::std::mem::size_of::<Rejection>(),
-            ::std::mem::size_of::<usize>(),
+            2 * ::std::mem::size_of::<usize>(),
         );
     }
 
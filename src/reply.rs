@@ -37,19 +37,31 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::error::Error as StdError;
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
-use crate::generic::{Either, One};
-use http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::filters::conditional::Preconditions;
+use crate::filters::cookie::SameSite;
+use crate::generic::{self, One};
+use bytes::BytesMut;
+use futures::{stream, Stream, StreamExt};
+use headers::{ContentLength, ETag, HeaderMapExt, LastModified};
+use http::header::{
+    HeaderName, HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LOCATION,
+    CONTENT_TYPE, DATE, ETAG, EXPIRES, LAST_MODIFIED, LINK, SET_COOKIE, VARY,
+};
 use http::StatusCode;
 use hyper::Body;
 use serde::Serialize;
 use serde_json;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 // This re-export just looks weird in docs...
 pub(crate) use self::sealed::Reply_;
 use self::sealed::{BoxedReply, Internal};
 #[doc(hidden)]
 pub use crate::filters::reply as with;
+#[cfg(any(feature = "askama", feature = "tera"))]
+pub use crate::filters::template;
 
 /// Response type into which types implementing the `Reply` trait are convertable.
 pub type Response = ::http::Response<Body>;
@@ -131,6 +143,429 @@ impl Reply for Json {
     }
 }
 
+/// Convert the value into a `Reply` with the value encoded as MessagePack,
+/// with the `content-type` set to `application/msgpack`.
+///
+/// Structs are encoded as maps, with their field names.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("ids")
+///     .map(|| {
+///         let our_ids = vec![1, 3, 7, 13];
+///         warp::reply::msgpack(&our_ids)
+///     });
+/// ```
+///
+/// # Note
+///
+/// If a type fails to be serialized, the error is logged at the `error`
+/// level, and the returned `impl Reply` will be an empty
+/// `500 Internal Server Error` response.
+#[cfg(feature = "msgpack")]
+pub fn msgpack<T>(val: &T) -> MessagePack
+where
+    T: Serialize,
+{
+    MessagePack {
+        inner: Format::MessagePack.to_vec(val).map_err(|err| {
+            log::error!("reply::msgpack error: {}", err);
+        }),
+    }
+}
+
+/// A MessagePack formatted reply.
+#[cfg(feature = "msgpack")]
+#[allow(missing_debug_implementations)]
+pub struct MessagePack {
+    inner: Result<Vec<u8>, ()>,
+}
+
+#[cfg(feature = "msgpack")]
+impl Reply for MessagePack {
+    #[inline]
+    fn into_response(self) -> Response {
+        encoded_response(self.inner, Format::MessagePack)
+    }
+}
+
+/// Convert the value into a `Reply` with the value encoded as CBOR, with the
+/// `content-type` set to `application/cbor`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("ids")
+///     .map(|| {
+///         let our_ids = vec![1, 3, 7, 13];
+///         warp::reply::cbor(&our_ids)
+///     });
+/// ```
+///
+/// # Note
+///
+/// If a type fails to be serialized, the error is logged at the `error`
+/// level, and the returned `impl Reply` will be an empty
+/// `500 Internal Server Error` response.
+#[cfg(feature = "cbor")]
+pub fn cbor<T>(val: &T) -> Cbor
+where
+    T: Serialize,
+{
+    Cbor {
+        inner: Format::Cbor.to_vec(val).map_err(|err| {
+            log::error!("reply::cbor error: {}", err);
+        }),
+    }
+}
+
+/// A CBOR formatted reply.
+#[cfg(feature = "cbor")]
+#[allow(missing_debug_implementations)]
+pub struct Cbor {
+    inner: Result<Vec<u8>, ()>,
+}
+
+#[cfg(feature = "cbor")]
+impl Reply for Cbor {
+    #[inline]
+    fn into_response(self) -> Response {
+        encoded_response(self.inner, Format::Cbor)
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn encoded_response(body: Result<Vec<u8>, ()>, format: Format) -> Response {
+    match body {
+        Ok(body) => {
+            let mut res = Response::new(body.into());
+            res.headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static(format.media_type()));
+            res
+        }
+        Err(()) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Reply with a stream of values, each encoded as JSON on its own line, with
+/// the `content-type` set to `application/x-ndjson`.
+///
+/// The values are sent as they come, and the stream is only polled when the
+/// client is ready to receive more, so large exports are never buffered.
+///
+/// # Example
+///
+/// ```
+/// use futures::stream;
+/// use warp::Filter;
+///
+/// // GET /ids returns the lines `1`, `3`, `7` and `13`.
+/// let route = warp::path("ids")
+///     .map(|| {
+///         let our_ids = stream::iter(vec![1, 3, 7, 13]);
+///         warp::reply::ndjson(our_ids)
+///     });
+/// ```
+///
+/// # Note
+///
+/// If a value fails to be serialized, the error is logged at the `error`
+/// level, and the response body is aborted.
+pub fn ndjson<S>(stream: S) -> Ndjson<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    Ndjson { stream }
+}
+
+/// A newline delimited JSON reply.
+#[allow(missing_debug_implementations)]
+pub struct Ndjson<S> {
+    stream: S,
+}
+
+impl<S> Reply for Ndjson<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn into_response(self) -> Response {
+        let lines = self.stream.map(|item| {
+            let mut line = serde_json::to_vec(&item).map_err(|err| {
+                log::error!("reply::ndjson error: {}", err);
+                err
+            })?;
+            line.push(b'\n');
+            Ok::<_, serde_json::Error>(line)
+        });
+        let mut res = Response::new(Body::wrap_stream(lines));
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        res
+    }
+}
+
+/// Reply with a stream of records encoded as CSV, with the `content-type`
+/// set to `text/csv; charset=utf-8`.
+///
+/// The first row is a header with the field names of the first record, when
+/// records are structs or maps. Like with [`ndjson`], records are sent as
+/// they come; an iterator can be turned into a stream with
+/// `futures::stream::iter`.
+///
+/// # Example
+///
+/// ```
+/// use futures::stream;
+/// use serde_derive::Serialize;
+/// use warp::Filter;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u32,
+///     name: &'static str,
+/// }
+///
+/// // GET /users.csv downloads a `users.csv` file with an `id,name` header.
+/// let route = warp::path("users.csv")
+///     .map(|| {
+///         let users = stream::iter(vec![User { id: 1, name: "alice" }]);
+///         warp::reply::csv(users).filename("users.csv")
+///     });
+/// ```
+///
+/// # Note
+///
+/// If a record fails to be serialized, the error is logged at the `error`
+/// level, and the response body is aborted.
+#[cfg(feature = "csv")]
+pub fn csv<S>(stream: S) -> Csv<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    Csv {
+        stream,
+        filename: None,
+    }
+}
+
+/// A CSV reply, created with [`csv`].
+#[cfg(feature = "csv")]
+#[allow(missing_debug_implementations)]
+pub struct Csv<S> {
+    stream: S,
+    filename: Option<String>,
+}
+
+#[cfg(feature = "csv")]
+impl<S> Csv<S> {
+    /// Have the client download the reply as a file named `filename`, with
+    /// a `Content-Disposition` header.
+    pub fn filename(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_owned());
+        self
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<S> Reply for Csv<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn into_response(self) -> Response {
+        let buf = CsvBuffer::default();
+        let mut writer = csv::Writer::from_writer(buf.clone());
+        let rows = self.stream.map(move |record| {
+            writer
+                .serialize(record)
+                .and_then(|()| writer.flush().map_err(Into::into))
+                .map_err(|err| {
+                    log::error!("reply::csv error: {}", err);
+                    err
+                })?;
+            Ok::<_, csv::Error>(buf.take())
+        });
+
+        let mut res = Response::new(Body::wrap_stream(rows));
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/csv; charset=utf-8"),
+        );
+        if let Some(ref filename) = self.filename {
+            res.headers_mut()
+                .insert(CONTENT_DISPOSITION, content_disposition(filename));
+        }
+        res
+    }
+}
+
+// Where the CSV writer encodes records, until they are sent.
+#[cfg(feature = "csv")]
+#[derive(Clone, Default)]
+struct CsvBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(feature = "csv")]
+impl CsvBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl std::io::Write for CsvBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reply with a body streamed from an `AsyncRead`, with the `content-type`
+/// set to `application/octet-stream`.
+///
+/// When the length of the body is known, it is sent as `Content-Length`,
+/// and the reader must provide exactly that many bytes.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("download").and_then(|| async {
+///     let file = tokio::fs::File::open("Cargo.toml")
+///         .await
+///         .map_err(|_| warp::reject::not_found())?;
+///     let len = file.metadata().await.ok().map(|meta| meta.len());
+///     Ok::<_, warp::Rejection>(warp::reply::from_reader(file, len))
+/// });
+/// ```
+pub fn from_reader<R>(reader: R, len: Option<u64>) -> Reader<R>
+where
+    R: AsyncRead + Send + 'static,
+{
+    Reader {
+        reader,
+        len,
+        filename: None,
+    }
+}
+
+/// Reply with a body streamed from an `AsyncRead`, for the client to
+/// download as a file named `filename`.
+///
+/// This is [`from_reader`] with a `Content-Disposition` header, and an
+/// unknown length, which can be set with [`Reader::content_length`].
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("report").map(|| {
+///     let report: &'static [u8] = b"quarterly numbers";
+///     warp::reply::attachment("report.txt", report)
+/// });
+/// ```
+pub fn attachment<R>(filename: &str, reader: R) -> Reader<R>
+where
+    R: AsyncRead + Send + 'static,
+{
+    from_reader(reader, None).filename(filename)
+}
+
+/// A reply streamed from an `AsyncRead`, created with [`from_reader`] or
+/// [`attachment`].
+#[allow(missing_debug_implementations)]
+pub struct Reader<R> {
+    reader: R,
+    len: Option<u64>,
+    filename: Option<String>,
+}
+
+impl<R> Reader<R> {
+    /// Set the length of the body, sent as `Content-Length`.
+    pub fn content_length(mut self, len: u64) -> Self {
+        self.len = Some(len);
+        self
+    }
+
+    /// Have the client download the reply as a file named `filename`, with
+    /// a `Content-Disposition` header.
+    pub fn filename(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_owned());
+        self
+    }
+}
+
+impl<R> Reply for Reader<R>
+where
+    R: AsyncRead + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let reader = Some(Box::pin(self.reader));
+        let chunks = stream::unfold(reader, |reader| async move {
+            let mut reader = reader?;
+            let mut buf = BytesMut::with_capacity(READ_BUF_SIZE);
+            match reader.read_buf(&mut buf).await {
+                Ok(0) => None,
+                Ok(_) => Some((Ok(buf.freeze()), Some(reader))),
+                Err(err) => {
+                    log::debug!("reply::from_reader error: {}", err);
+                    Some((Err(err), None))
+                }
+            }
+        });
+
+        let mut res = Response::new(Body::wrap_stream(chunks));
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        if let Some(len) = self.len {
+            res.headers_mut().typed_insert(ContentLength(len));
+        }
+        if let Some(ref filename) = self.filename {
+            res.headers_mut()
+                .insert(CONTENT_DISPOSITION, content_disposition(filename));
+        }
+        res
+    }
+}
+
+const READ_BUF_SIZE: usize = 8_192;
+
+// An `attachment` disposition, with the filename quoted, and percent-encoded
+// too when it isn't ASCII.
+fn content_disposition(filename: &str) -> HeaderValue {
+    let quoted = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => format!("\\{}", c),
+            c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+            _ => "_".to_owned(),
+        })
+        .collect::<String>();
+    let mut value = format!("attachment; filename=\"{}\"", quoted);
+    if !filename.is_ascii() {
+        value.push_str("; filename*=UTF-8''");
+        value.push_str(&urlencoding::encode(filename));
+    }
+    HeaderValue::from_str(&value).expect("escaped filename is a valid header value")
+}
+
 #[derive(Debug)]
 pub(crate) struct ReplyJsonError;
 
@@ -142,6 +577,242 @@ impl fmt::Display for ReplyJsonError {
 
 impl StdError for ReplyJsonError {}
 
+/// Reply with the value serialized in the format preferred by the `Accept`
+/// header of the request, among the enabled [`Format`]s.
+///
+/// The request is looked up when `negotiate` is called, so it must be called
+/// while handling the request, such as in a `map` or `and_then`. Without an
+/// `Accept` header, the default format is used, JSON unless configured
+/// otherwise. When no format is acceptable, the reply is a
+/// `406 Not Acceptable`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// // GET /ids returns `[1, 3, 7, 13]` as JSON, or in any other enabled
+/// // format the client asks for.
+/// let route = warp::path("ids")
+///     .map(|| warp::reply::negotiate(vec![1, 3, 7, 13]));
+/// ```
+///
+/// # Note
+///
+/// If the value fails to be serialized, the error is logged at the `error`
+/// level, and the returned `impl Reply` will be an empty
+/// `500 Internal Server Error` response.
+pub fn negotiate<T>(val: T) -> Negotiate
+where
+    T: Serialize + Send + 'static,
+{
+    let accept = if crate::route::is_set() {
+        crate::route::with(|route| route.headers().get(ACCEPT).cloned())
+    } else {
+        None
+    };
+    Negotiate {
+        accept,
+        default: Format::Json,
+        serialize: Box::new(move |format| format.to_vec(&val)),
+    }
+}
+
+/// A reply whose format is negotiated with the client, created with
+/// [`negotiate`].
+#[allow(missing_debug_implementations)]
+pub struct Negotiate {
+    accept: Option<HeaderValue>,
+    default: Format,
+    serialize: Box<dyn FnOnce(Format) -> Result<Vec<u8>, SerializeError> + Send>,
+}
+
+type SerializeError = Box<dyn StdError + Send + Sync>;
+
+impl Negotiate {
+    /// Set the format used when the client accepts any of them,
+    /// [`Format::Json`] by default.
+    pub fn default_format(mut self, format: Format) -> Self {
+        self.default = format;
+        self
+    }
+}
+
+impl Reply for Negotiate {
+    fn into_response(self) -> Response {
+        let format = match Format::select(self.accept.as_ref(), self.default) {
+            Some(format) => format,
+            None => {
+                log::debug!("reply::negotiate: no acceptable format");
+                let mut res = StatusCode::NOT_ACCEPTABLE.into_response();
+                res.headers_mut()
+                    .insert(VARY, HeaderValue::from_static("accept"));
+                return res;
+            }
+        };
+        match (self.serialize)(format) {
+            Ok(body) => {
+                let mut res = Response::new(body.into());
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(format.media_type()));
+                res.headers_mut()
+                    .insert(VARY, HeaderValue::from_static("accept"));
+                res
+            }
+            Err(err) => {
+                log::error!("reply::negotiate error: {}", err);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+/// A serialization format of [`negotiate`]d replies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// JSON, as `application/json`.
+    Json,
+    /// MessagePack, as `application/msgpack`.
+    ///
+    /// `application/x-msgpack` and `application/vnd.msgpack` are accepted
+    /// too. Structs are serialized as maps, with their field names.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// CBOR, as `application/cbor`.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// YAML, as `application/yaml`.
+    ///
+    /// `application/x-yaml` and `text/yaml` are accepted too.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Format {
+    // The enabled formats, in order of preference.
+    fn all() -> Vec<Format> {
+        vec![
+            Format::Json,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack,
+            #[cfg(feature = "cbor")]
+            Format::Cbor,
+            #[cfg(feature = "yaml")]
+            Format::Yaml,
+        ]
+    }
+
+    /// The media type of the format, sent as `Content-Type`.
+    pub fn media_type(self) -> &'static str {
+        self.media_types()[0]
+    }
+
+    fn media_types(self) -> &'static [&'static str] {
+        match self {
+            Format::Json => &["application/json"],
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => &[
+                "application/msgpack",
+                "application/x-msgpack",
+                "application/vnd.msgpack",
+            ],
+            #[cfg(feature = "cbor")]
+            Format::Cbor => &["application/cbor"],
+            #[cfg(feature = "yaml")]
+            Format::Yaml => &["application/yaml", "application/x-yaml", "text/yaml"],
+        }
+    }
+
+    pub(crate) fn to_vec<T: Serialize>(self, val: &T) -> Result<Vec<u8>, SerializeError> {
+        match self {
+            Format::Json => Ok(serde_json::to_vec(val)?),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => Ok(rmp_serde::to_vec_named(val)?),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => Ok(serde_cbor::to_vec(val)?),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::to_vec(val)?),
+        }
+    }
+
+    // The best format for an `Accept` header, if any is acceptable.
+    fn select(accept: Option<&HeaderValue>, default: Format) -> Option<Format> {
+        let accept = match accept.and_then(|value| value.to_str().ok()) {
+            Some(accept) if !accept.trim().is_empty() => accept,
+            _ => return Some(default),
+        };
+        let ranges = accept
+            .split(',')
+            .filter_map(parse_media_range)
+            .collect::<Vec<_>>();
+
+        let mut best: Option<(Format, f32)> = None;
+        for format in Some(default).into_iter().chain(Format::all()) {
+            let q = format.quality(&ranges);
+            let better = match best {
+                Some((_, best_q)) => q > best_q,
+                None => q > 0.0,
+            };
+            if better {
+                best = Some((format, q));
+            }
+        }
+        best.map(|(format, _)| format)
+    }
+
+    // The quality of the most specific range matching the format.
+    fn quality(self, ranges: &[(&str, &str, f32)]) -> f32 {
+        let mut quality = 0.0;
+        for media_type in self.media_types() {
+            let mut parts = media_type.splitn(2, '/');
+            let ty = parts.next().unwrap_or("");
+            let subty = parts.next().unwrap_or("");
+            let mut specificity = None;
+            for &(range_ty, range_subty, q) in ranges {
+                let matched = if range_ty == "*" && range_subty == "*" {
+                    Some(0)
+                } else if range_ty.eq_ignore_ascii_case(ty) && range_subty == "*" {
+                    Some(1)
+                } else if range_ty.eq_ignore_ascii_case(ty)
+                    && range_subty.eq_ignore_ascii_case(subty)
+                {
+                    Some(2)
+                } else {
+                    None
+                };
+                match (matched, specificity) {
+                    (Some(m), Some((s, _))) if m <= s => {}
+                    (Some(m), _) => specificity = Some((m, q)),
+                    (None, _) => {}
+                }
+            }
+            if let Some((_, q)) = specificity {
+                if q > quality {
+                    quality = q;
+                }
+            }
+        }
+        quality
+    }
+}
+
+// Parse a media range of an `Accept` header into its type, subtype and
+// quality.
+fn parse_media_range(range: &str) -> Option<(&str, &str, f32)> {
+    let mut params = range.split(';');
+    let mut parts = params.next()?.trim().splitn(2, '/');
+    let ty = parts.next()?.trim();
+    let subty = parts.next()?.trim();
+    let mut q = 1.0;
+    for param in params {
+        let mut kv = param.splitn(2, '=');
+        if kv.next()?.trim().eq_ignore_ascii_case("q") {
+            q = kv.next()?.trim().parse().ok()?;
+        }
+    }
+    Some((ty, subty, q))
+}
+
 /// Reply with a body and `content-type` set to `text/html; charset=utf-8`.
 ///
 /// # Example
@@ -195,6 +866,157 @@ where
     }
 }
 
+/// Reply with an [RFC 7807](https://tools.ietf.org/html/rfc7807) problem
+/// document, with the `content-type` set to `application/problem+json`.
+///
+/// `type_uri` identifies the kind of problem, `title` summarizes it, and
+/// `detail` explains this occurrence of it. To turn rejections into problem
+/// documents, see [`reject::recover_problem`](crate::reject::recover_problem).
+///
+/// # Example
+///
+/// ```
+/// use warp::http::StatusCode;
+/// use warp::Filter;
+///
+/// let route = warp::any().map(|| {
+///     warp::reply::problem(
+///         StatusCode::FORBIDDEN,
+///         "https://example.com/probs/out-of-credit",
+///         "You do not have enough credit.",
+///         "Your current balance is 30, but that costs 50.",
+///     )
+///     .instance("/account/12345/msgs/abc")
+///     .extension("balance", 30)
+/// });
+/// ```
+pub fn problem(status: StatusCode, type_uri: &str, title: &str, detail: &str) -> Problem {
+    Problem {
+        status,
+        type_uri: type_uri.to_owned(),
+        title: title.to_owned(),
+        detail: Some(detail.to_owned()),
+        instance: None,
+        extensions: serde_json::Map::new(),
+    }
+}
+
+/// An [RFC 7807](https://tools.ietf.org/html/rfc7807) problem reply.
+///
+/// The document has the `type`, `title` and `status` members, the `detail`
+/// and `instance` members when known, and any extension members. Its
+/// schema, for API documentation, is returned by [`Problem::schema`].
+#[derive(Clone, Debug)]
+pub struct Problem {
+    status: StatusCode,
+    type_uri: String,
+    title: String,
+    detail: Option<String>,
+    instance: Option<String>,
+    extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Problem {
+    // A problem with no more semantics than its status code.
+    pub(crate) fn from_status(status: StatusCode) -> Problem {
+        Problem {
+            status,
+            type_uri: "about:blank".to_owned(),
+            title: status.canonical_reason().unwrap_or("Unknown").to_owned(),
+            detail: None,
+            instance: None,
+            extensions: serde_json::Map::new(),
+        }
+    }
+
+    pub(crate) fn detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Set the URI identifying this occurrence of the problem.
+    pub fn instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_owned());
+        self
+    }
+
+    /// Add an extension member to the document.
+    ///
+    /// Extensions can't replace the standard members.
+    pub fn extension(mut self, name: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.extensions.insert(name.to_owned(), value.into());
+        self
+    }
+
+    /// The JSON schema of problem documents, as found in the `components`
+    /// of an OpenAPI 3 document.
+    pub fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "description": "A problem details document, as defined by RFC 7807.",
+            "properties": {
+                "type": {
+                    "type": "string",
+                    "format": "uri-reference",
+                    "description": "A URI identifying the problem type.",
+                    "default": "about:blank",
+                },
+                "title": {
+                    "type": "string",
+                    "description": "A short summary of the problem type.",
+                },
+                "status": {
+                    "type": "integer",
+                    "format": "int32",
+                    "minimum": 100,
+                    "maximum": 599,
+                    "description": "The HTTP status code of the response.",
+                },
+                "detail": {
+                    "type": "string",
+                    "description": "An explanation of this occurrence of the problem.",
+                },
+                "instance": {
+                    "type": "string",
+                    "format": "uri-reference",
+                    "description": "A URI identifying this occurrence of the problem.",
+                },
+            },
+            "required": ["type", "title", "status"],
+            "additionalProperties": true,
+        })
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut doc = self.extensions.clone();
+        doc.insert("type".to_owned(), self.type_uri.clone().into());
+        doc.insert("title".to_owned(), self.title.clone().into());
+        doc.insert("status".to_owned(), self.status.as_u16().into());
+        match self.detail {
+            Some(ref detail) => doc.insert("detail".to_owned(), detail.clone().into()),
+            None => doc.remove("detail"),
+        };
+        match self.instance {
+            Some(ref instance) => doc.insert("instance".to_owned(), instance.clone().into()),
+            None => doc.remove("instance"),
+        };
+        serde_json::Value::Object(doc)
+    }
+}
+
+impl Reply for Problem {
+    fn into_response(self) -> Response {
+        let body = self.to_json().to_string();
+        let mut res = Response::new(body.into());
+        *res.status_mut() = self.status;
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        res
+    }
+}
+
 /// Types that can be converted into a `Response`.
 ///
 /// This trait is implemented for the following:
@@ -203,6 +1025,9 @@ where
 /// - `http::Response<impl Into<hyper::Body>>`
 /// - `String`
 /// - `&'static str`
+/// - `Result<impl Reply, impl Reply>`
+/// - `Box<dyn Reply>`
+/// - [`Either`](Either) of two replies
 ///
 /// # Example
 ///
@@ -390,6 +1215,381 @@ impl<T: Reply> Reply for WithHeader<T> {
     }
 }
 
+/// Wrap a reply to answer conditional requests, with the `ETag` and
+/// modification time of its content.
+///
+/// The preconditions of the request are evaluated as with
+/// [`Preconditions::evaluate`](crate::conditional::Preconditions::evaluate):
+/// when the client already has the content, the reply is replaced with a
+/// `304 Not Modified`, and when another precondition fails, with a `412
+/// Precondition Failed`. Only successful replies are replaced.
+///
+/// The request is looked up when `conditional` is called, so it must be
+/// called while handling the request, such as in a `map` or `and_then`.
+///
+/// # Panics
+///
+/// This function panics if `etag` is not a legal entity tag, such as
+/// `"xyzzy"` or `W/"xyzzy"`, quotes included.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use warp::Filter;
+///
+/// let route = warp::path!("posts" / u32).map(|id: u32| {
+///     let updated = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+///     let etag = format!("\"post-{}-{}\"", id, 3);
+///     warp::reply::conditional(Some(&etag), Some(updated), format!("post #{}", id))
+/// });
+/// ```
+pub fn conditional<T: Reply>(
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+    reply: T,
+) -> Conditional<T> {
+    let etag = etag.map(|etag| {
+        etag.parse::<ETag>()
+            .unwrap_or_else(|_| panic!("illegal ETag: {:?}", etag))
+    });
+    let request = if crate::route::is_set() {
+        crate::route::with(|route| Some(Preconditions::of(route)))
+    } else {
+        None
+    };
+    Conditional {
+        reply,
+        etag,
+        last_modified: last_modified.map(LastModified::from),
+        request,
+    }
+}
+
+/// A reply wrapped with [`conditional`].
+#[derive(Debug)]
+pub struct Conditional<T> {
+    reply: T,
+    etag: Option<ETag>,
+    last_modified: Option<LastModified>,
+    request: Option<Preconditions>,
+}
+
+impl<T: Reply> Reply for Conditional<T> {
+    fn into_response(self) -> Response {
+        let mut res = self.reply.into_response();
+        if let Some(etag) = self.etag.clone() {
+            res.headers_mut().typed_insert(etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            res.headers_mut().typed_insert(last_modified);
+        }
+        if !res.status().is_success() {
+            return res;
+        }
+
+        let status = match self.request {
+            Some(ref request) => request.check(self.etag.as_ref(), self.last_modified),
+            None => None,
+        };
+        let status = match status {
+            Some(status) => status,
+            None => return res,
+        };
+
+        let mut replaced = Response::new(Body::empty());
+        *replaced.status_mut() = status;
+        if status == StatusCode::NOT_MODIFIED {
+            // The headers a `304` must repeat, from RFC 7232.
+            for name in &[
+                CACHE_CONTROL,
+                CONTENT_LOCATION,
+                DATE,
+                ETAG,
+                EXPIRES,
+                LAST_MODIFIED,
+                VARY,
+            ] {
+                for value in res.headers().get_all(name) {
+                    replaced.headers_mut().append(name, value.clone());
+                }
+            }
+        }
+        replaced
+    }
+}
+
+/// Build a `Set-Cookie` header, to add to a reply with [`with_cookie`].
+///
+/// By default, the cookie only has the `Path=/` attribute.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use warp::Filter;
+///
+/// let route = warp::any().map(|| {
+///     let cookie = warp::reply::set_cookie("theme", "dark")
+///         .max_age(Duration::from_secs(60 * 60 * 24 * 365))
+///         .http_only(true);
+///     warp::reply::with_cookie(warp::reply(), cookie)
+/// });
+/// ```
+pub fn set_cookie(name: &str, value: &str) -> SetCookie {
+    SetCookie {
+        name: name.to_owned(),
+        value: value.to_owned(),
+        path: Some("/".to_owned()),
+        domain: None,
+        max_age: None,
+        same_site: None,
+        secure: false,
+        http_only: false,
+    }
+}
+
+/// A `Set-Cookie` header, built with [`set_cookie`].
+#[derive(Clone, Debug)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    same_site: Option<SameSite>,
+    secure: bool,
+    http_only: bool,
+}
+
+impl SetCookie {
+    /// Set the `Path` attribute.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Set the `Domain` attribute.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_owned());
+        self
+    }
+
+    /// Set the `Max-Age` attribute, so the cookie outlives the browser
+    /// session.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `SameSite` attribute.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Set whether the cookie is only sent over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set whether the cookie is hidden from scripts.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Remove the cookie from the client, by expiring it.
+    pub fn expire(mut self) -> Self {
+        self.value.clear();
+        self.max_age = Some(Duration::from_secs(0));
+        self
+    }
+
+    /// Sign the value, so it can be read with
+    /// [`warp::cookie::signed`](crate::cookie::signed).
+    ///
+    /// The value can still be read by the client, but not changed.
+    #[cfg(feature = "secure-cookies")]
+    pub fn signed(mut self, key: &crate::cookie::Key) -> Self {
+        self.value = key.sign(&self.name, &self.value);
+        self
+    }
+
+    /// Encrypt the value, so it can be read with
+    /// [`warp::cookie::private`](crate::cookie::private).
+    ///
+    /// The value can be neither read nor changed by the client.
+    #[cfg(feature = "secure-cookies")]
+    pub fn private(mut self, key: &crate::cookie::Key) -> Self {
+        self.value = key.encrypt(&self.name, &self.value);
+        self
+    }
+
+    pub(crate) fn to_header_value(&self) -> Result<HeaderValue, http::Error> {
+        let mut cookie = format!("{}={}", self.name, self.value);
+        if let Some(ref path) = self.path {
+            cookie.push_str("; Path=");
+            cookie.push_str(path);
+        }
+        if let Some(ref domain) = self.domain {
+            cookie.push_str("; Domain=");
+            cookie.push_str(domain);
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.push_str("; SameSite=");
+            cookie.push_str(same_site.as_str());
+        }
+        Ok(HeaderValue::from_str(&cookie)?)
+    }
+}
+
+/// Wrap an `impl Reply` to add a `Set-Cookie` header when rendering.
+///
+/// Unlike [`with_header`], this keeps any cookies already set by `reply`.
+pub fn with_cookie<T: Reply>(reply: T, cookie: SetCookie) -> WithCookie<T> {
+    let header = match cookie.to_header_value() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::error!("with_cookie value error: {}", err);
+            None
+        }
+    };
+
+    WithCookie { header, reply }
+}
+
+/// Wraps an `impl Reply` and adds a `Set-Cookie` header when rendering.
+///
+/// Returned by `warp::reply::with_cookie`.
+#[derive(Debug)]
+pub struct WithCookie<T> {
+    header: Option<HeaderValue>,
+    reply: T,
+}
+
+impl<T: Reply> Reply for WithCookie<T> {
+    fn into_response(self) -> Response {
+        let mut res = self.reply.into_response();
+        if let Some(value) = self.header {
+            res.headers_mut().append(SET_COOKIE, value);
+        }
+        res
+    }
+}
+
+/// Create the `Link` preload headers of a reply, for resources a page will
+/// need.
+///
+/// The headers are added to the response, so that browsers start fetching
+/// or connecting while the page is still loading.
+///
+/// These are the same hints as those of a `103 Early Hints` interim
+/// response, but no interim response is sent: the headers come with the
+/// final response, since the HTTP implementation of warp can't send
+/// interim responses.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("index.html").map(|| {
+///     let links = warp::reply::preload_links()
+///         .preload("/style.css", "style")
+///         .preconnect("https://fonts.example.com");
+///     warp::reply::with_preload_links(warp::reply::html("<html>...</html>"), links)
+/// });
+/// ```
+pub fn preload_links() -> PreloadLinks {
+    PreloadLinks { links: Vec::new() }
+}
+
+/// The `Link` headers hinting at resources a page will need, built with
+/// [`preload_links`].
+#[derive(Clone, Debug, Default)]
+pub struct PreloadLinks {
+    links: Vec<String>,
+}
+
+impl PreloadLinks {
+    /// Hint that the resource at `href` will be needed, as a `destination`
+    /// such as `style`, `script`, `font` or `image`.
+    pub fn preload(self, href: &str, destination: &str) -> Self {
+        let mut link = format!("<{}>; rel=preload; as={}", href, destination);
+        // Fonts are always fetched in CORS mode, and preloading them in
+        // another mode would fetch them twice.
+        if destination == "font" {
+            link.push_str("; crossorigin");
+        }
+        self.link(&link)
+    }
+
+    /// Hint that a connection to the `origin` will be needed.
+    pub fn preconnect(self, origin: &str) -> Self {
+        self.link(&format!("<{}>; rel=preconnect", origin))
+    }
+
+    /// Add a raw `Link` header value, such as `</app.js>; rel=modulepreload`.
+    pub fn link(mut self, link: &str) -> Self {
+        self.links.push(link.to_owned());
+        self
+    }
+
+    /// The `Link` header values.
+    pub fn links(&self) -> impl Iterator<Item = &str> {
+        self.links.iter().map(String::as_str)
+    }
+}
+
+/// Wrap an `impl Reply` to add `Link` preload headers when rendering.
+///
+/// Any `Link` headers already set by `reply` are kept.
+pub fn with_preload_links<T: Reply>(reply: T, links: PreloadLinks) -> WithPreloadLinks<T> {
+    let headers = links
+        .links
+        .iter()
+        .filter_map(|link| match HeaderValue::from_str(link) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                log::error!("with_preload_links value error: {}", err);
+                None
+            }
+        })
+        .collect();
+
+    WithPreloadLinks { headers, reply }
+}
+
+/// Wraps an `impl Reply` and adds `Link` preload headers when rendering.
+///
+/// Returned by `warp::reply::with_preload_links`.
+#[derive(Debug)]
+pub struct WithPreloadLinks<T> {
+    headers: Vec<HeaderValue>,
+    reply: T,
+}
+
+impl<T: Reply> Reply for WithPreloadLinks<T> {
+    fn into_response(self) -> Response {
+        let mut res = self.reply.into_response();
+        for value in self.headers {
+            res.headers_mut().append(LINK, value);
+        }
+        res
+    }
+}
+
 impl<T: Send> Reply for ::http::Response<T>
 where
     Body: From<T>,
@@ -409,18 +1609,24 @@ impl Reply for ::http::StatusCode {
     }
 }
 
-impl<T> Reply for Result<T, ::http::Error>
+impl Reply for ::http::Error {
+    #[inline]
+    fn into_response(self) -> Response {
+        log::error!("reply error: {:?}", self);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+impl<T, E> Reply for Result<T, E>
 where
-    T: Reply + Send,
+    T: Reply,
+    E: Reply,
 {
     #[inline]
     fn into_response(self) -> Response {
         match self {
             Ok(t) => t.into_response(),
-            Err(e) => {
-                log::error!("reply error: {:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
-            }
+            Err(e) => e.into_response(),
         }
     }
 }
@@ -484,7 +1690,7 @@ impl Reply for &'static [u8] {
     }
 }
 
-impl<T, U> Reply for Either<T, U>
+impl<T, U> Reply for generic::Either<T, U>
 where
     T: Reply,
     U: Reply,
@@ -492,8 +1698,51 @@ where
     #[inline]
     fn into_response(self) -> Response {
         match self {
-            Either::A(a) => a.into_response(),
-            Either::B(b) => b.into_response(),
+            generic::Either::A(a) => a.into_response(),
+            generic::Either::B(b) => b.into_response(),
+        }
+    }
+}
+
+/// One of two replies of different types.
+///
+/// A handler returning `impl Reply` must return the same type on every
+/// path, which `Either` provides without boxing the replies or converting
+/// them into responses first.
+///
+/// # Example
+///
+/// ```
+/// use warp::http::StatusCode;
+/// use warp::reply::Either;
+/// use warp::Filter;
+///
+/// let route = warp::path::param().map(|id: u32| {
+///     if id == 0 {
+///         Either::Right(StatusCode::NOT_FOUND)
+///     } else {
+///         Either::Left(format!("item #{}", id))
+///     }
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    /// The first reply type.
+    Left(L),
+    /// The second reply type.
+    Right(R),
+}
+
+impl<L, R> Reply for Either<L, R>
+where
+    L: Reply,
+    R: Reply,
+{
+    #[inline]
+    fn into_response(self) -> Response {
+        match self {
+            Either::Left(l) => l.into_response(),
+            Either::Right(r) => r.into_response(),
         }
     }
 }
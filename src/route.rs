@@ -21,6 +21,32 @@ pub(crate) fn is_set() -> bool {
     ROUTE.is_set()
 }
 
+// The route of the filter calling a service, which may set a route of its
+// own for the request it was handed.
+scoped_thread_local!(static CALLER: *const RefCell<Route>);
+
+// Runs `func`, a call to a service by the filter of the current route,
+// allowing the service to set the route of its own request.
+//
+// This is safe since the scope of `set` restores the route of the caller
+// once the service returns, and the service gets a request of its own,
+// instead of the one of the caller.
+pub(crate) fn nested<F, U>(func: F) -> U
+where
+    F: FnOnce() -> U,
+{
+    let caller = ROUTE.with(|route| route as *const RefCell<Route>);
+    CALLER.set(&caller, func)
+}
+
+// Whether a route can be set: when none is, or when a service is called by
+// the filter of the current route, with `nested`.
+pub(crate) fn can_set() -> bool {
+    !ROUTE.is_set()
+        || (CALLER.is_set()
+            && ROUTE.with(|route| CALLER.with(|caller| std::ptr::eq(route, *caller))))
+}
+
 pub(crate) fn with<F, R>(func: F) -> R
 where
     F: FnOnce(&mut Route) -> R,
@@ -63,10 +89,18 @@ impl Route {
         self.req.method()
     }
 
+    pub(crate) fn set_method(&mut self, method: http::Method) {
+        *self.req.method_mut() = method;
+    }
+
     pub(crate) fn headers(&self) -> &http::HeaderMap {
         self.req.headers()
     }
 
+    pub(crate) fn headers_mut(&mut self) -> &mut http::HeaderMap {
+        self.req.headers_mut()
+    }
+
     pub(crate) fn version(&self) -> http::Version {
         self.req.version()
     }
@@ -75,11 +109,13 @@ impl Route {
         self.req.extensions()
     }
 
-    /*
     pub(crate) fn extensions_mut(&mut self) -> &mut http::Extensions {
         self.req.extensions_mut()
     }
-    */
+
+    pub(crate) fn take_extensions(&mut self) -> http::Extensions {
+        mem::replace(self.req.extensions_mut(), http::Extensions::new())
+    }
 
     pub(crate) fn uri(&self) -> &http::Uri {
         self.req.uri()
@@ -115,6 +151,11 @@ impl Route {
         self.segments_index
     }
 
+    pub(crate) fn set_matched_path_index(&mut self, index: usize) {
+        debug_assert!(index <= self.full_path().len());
+        self.segments_index = index;
+    }
+
     pub(crate) fn reset_matched_path_index(&mut self, index: usize) {
         debug_assert!(
             index <= self.segments_index,
@@ -139,4 +180,9 @@ impl Route {
             BodyState::Taken => None,
         }
     }
+
+    pub(crate) fn restore_body(&mut self, body: Body) {
+        *self.req.body_mut() = body;
+        self.body = BodyState::Ready;
+    }
 }
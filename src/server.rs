@@ -6,6 +6,7 @@ use std::future::Future;
 use std::net::SocketAddr;
 #[cfg(feature = "tls")]
 use std::path::Path;
+use std::time::Duration;
 
 use futures::{future, FutureExt, TryFuture, TryStream, TryStreamExt};
 use hyper::server::conn::AddrIncoming;
@@ -13,7 +14,8 @@ use hyper::service::{make_service_fn, service_fn};
 use hyper::Server as HyperServer;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use crate::filter::Filter;
+use crate::filter::{DynamicFilter, Filter};
+use crate::generic::Tuple;
 use crate::reject::IsReject;
 use crate::reply::Reply;
 use crate::transport::Transport;
@@ -27,17 +29,65 @@ where
 {
     Server {
         pipeline: false,
+        proxy_protocol: false,
+        http2: Http2::default(),
         filter,
     }
 }
 
+/// Create a `Server` with a [`DynamicFilter`], that can be replaced while the
+/// server runs.
+///
+/// Replacing the filter applies to new requests, connections already open
+/// included, without interrupting requests being handled.
+pub fn serve_dynamic<T>(filter: DynamicFilter<T>) -> Server<DynamicFilter<T>>
+where
+    T: Tuple + Reply + Send + 'static,
+{
+    serve(filter)
+}
+
 /// A Warp Server ready to filter requests.
 #[derive(Debug)]
 pub struct Server<F> {
     pipeline: bool,
+    proxy_protocol: bool,
+    http2: Http2,
     filter: F,
 }
 
+// HTTP/2 settings, left to the defaults of hyper when unset.
+#[derive(Clone, Debug, Default)]
+struct Http2 {
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    adaptive_window: bool,
+    max_frame_size: Option<u32>,
+    max_concurrent_streams: Option<u32>,
+    keep_alive_interval: Option<Duration>,
+    keep_alive_timeout: Option<Duration>,
+}
+
+impl Http2 {
+    fn configure<I>(&self, builder: hyper::server::Builder<I>) -> hyper::server::Builder<I> {
+        let mut builder = builder
+            .http2_initial_stream_window_size(self.initial_stream_window_size)
+            .http2_initial_connection_window_size(self.initial_connection_window_size)
+            .http2_max_frame_size(self.max_frame_size)
+            .http2_max_concurrent_streams(self.max_concurrent_streams)
+            .http2_keep_alive_interval(self.keep_alive_interval);
+        // Adaptive windows override the window sizes, so only set it when
+        // asked to.
+        if self.adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+        if let Some(timeout) = self.keep_alive_timeout {
+            builder = builder.http2_keep_alive_timeout(timeout);
+        }
+        builder
+    }
+}
+
 /// A Warp Server ready to filter requests over TLS.
 ///
 /// *This type requires the `"tls"` feature.*
@@ -75,7 +125,10 @@ macro_rules! bind_inner {
     ($this:ident, $addr:expr) => {{
         let service = into_service!($this.filter);
         let (addr, incoming) = addr_incoming!($addr);
-        let srv = HyperServer::builder(incoming)
+        let incoming = crate::proxy_protocol::ProxyAcceptor::new(incoming, $this.proxy_protocol);
+        let srv = $this
+            .http2
+            .configure(HyperServer::builder(incoming))
             .http1_pipeline_flush($this.pipeline)
             .serve(service);
         Ok::<_, hyper::Error>((addr, srv))
@@ -85,7 +138,12 @@ macro_rules! bind_inner {
         let service = into_service!($this.server.filter);
         let (addr, incoming) = addr_incoming!($addr);
         let tls = $this.tls.build()?;
-        let srv = HyperServer::builder(crate::tls::TlsAcceptor::new(tls, incoming))
+        let srv = $this
+            .server
+            .http2
+            .configure(HyperServer::builder(crate::tls::TlsAcceptor::new(
+                tls, incoming,
+            )))
             .http1_pipeline_flush($this.server.pipeline)
             .serve(service);
         Ok::<_, Box<dyn std::error::Error + Send + Sync>>((addr, srv))
@@ -344,14 +402,17 @@ where
         let incoming = incoming.map_ok(crate::transport::LiftIo);
         let service = into_service!(self.filter);
         let pipeline = self.pipeline;
+        let http2 = self.http2;
 
         async move {
-            let srv =
-                HyperServer::builder(hyper::server::accept::from_stream(incoming.into_stream()))
-                    .http1_pipeline_flush(pipeline)
-                    .serve(service)
-                    .with_graceful_shutdown(signal)
-                    .await;
+            let srv = http2
+                .configure(HyperServer::builder(hyper::server::accept::from_stream(
+                    incoming.into_stream(),
+                )))
+                .http1_pipeline_flush(pipeline)
+                .serve(service)
+                .with_graceful_shutdown(signal)
+                .await;
 
             if let Err(err) = srv {
                 log::error!("server error: {}", err);
@@ -367,7 +428,11 @@ where
     {
         let service = into_service!(self.filter);
 
-        let srv = HyperServer::builder(hyper::server::accept::from_stream(incoming.into_stream()))
+        let srv = self
+            .http2
+            .configure(HyperServer::builder(hyper::server::accept::from_stream(
+                incoming.into_stream(),
+            )))
             .http1_pipeline_flush(self.pipeline)
             .serve(service)
             .await;
@@ -377,6 +442,99 @@ where
         }
     }
 
+    /// Expect connections to start with a PROXY protocol header.
+    ///
+    /// Load balancers forwarding TCP connections, such as HAProxy or AWS NLB,
+    /// can send the address of the client in this header. Both versions 1
+    /// and 2 of the protocol are supported, and the address is provided to
+    /// [`addr::remote()`](crate::addr::remote).
+    ///
+    /// Connections without a valid header are closed, so this must only be
+    /// enabled when every connection comes through such a proxy.
+    ///
+    /// This applies to the servers bound by this `Server`, not to streams of
+    /// incoming connections, nor to TLS servers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use warp::Filter;
+    ///
+    /// # async fn run() {
+    /// let routes = warp::addr::remote()
+    ///     .map(|addr: Option<std::net::SocketAddr>| format!("hello {:?}", addr));
+    ///
+    /// warp::serve(routes)
+    ///     .proxy_protocol()
+    ///     .run(([0, 0, 0, 0], 3030))
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn proxy_protocol(mut self) -> Self {
+        self.proxy_protocol = true;
+        self
+    }
+
+    /// Sets the initial flow control window of HTTP/2 streams, in bytes.
+    ///
+    /// Defaults to the HTTP/2 default of 65,535 bytes.
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Sets the initial flow control window of HTTP/2 connections, in bytes.
+    ///
+    /// Defaults to the HTTP/2 default of 65,535 bytes.
+    pub fn http2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Sets whether HTTP/2 flow control windows adapt to the measured
+    /// bandwidth-delay product of connections.
+    ///
+    /// Enabling this overrides the initial window sizes.
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2.adaptive_window = enabled;
+        self
+    }
+
+    /// Sets the largest HTTP/2 frame payload accepted, in bytes.
+    ///
+    /// Defaults to the HTTP/2 default of 16,384 bytes.
+    pub fn http2_max_frame_size(mut self, size: u32) -> Self {
+        self.http2.max_frame_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum number of concurrent streams of HTTP/2 connections.
+    ///
+    /// Defaults to no limit.
+    pub fn http2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.http2.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Sends HTTP/2 pings at this interval, to keep connections alive.
+    ///
+    /// Defaults to not sending pings.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Sets how long to wait for the acknowledgement of a keep-alive ping,
+    /// before closing the connection.
+    ///
+    /// This has no effect unless
+    /// [`http2_keep_alive_interval`](Server::http2_keep_alive_interval) is
+    /// set. Defaults to 20 seconds.
+    pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.http2.keep_alive_timeout = Some(timeout);
+        self
+    }
+
     // Generally shouldn't be used, as it can slow down non-pipelined responses.
     //
     // It's only real use is to make silly pipeline benchmarks look better.
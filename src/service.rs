@@ -1,3 +1,3 @@
 //! Convert `Filter`s into `Service`s
 
-pub use crate::filter::service::service;
+pub use crate::filter::service::{service, service_with_info};
@@ -79,28 +79,42 @@
 //!     assert_eq!(res.body(), "Sum is 3");
 //! }
 //! ```
+//!
+//! The [`ResponseExt`](./trait.ResponseExt.html) trait adds assertions to
+//! these responses, such as comparing a JSON body with an expected value.
+//!
+//! To test behavior only a real server has, such as HTTP/2 or TLS, [`serve`]
+//! runs a filter on an ephemeral port.
+//!
+//! Beyond hand-written cases, [`fuzz`] sends random requests derived from
+//! example paths and bodies, checking the filter never panics nor fails.
 use std::convert::TryFrom;
 use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
 use std::net::SocketAddr;
-#[cfg(feature = "websocket")]
 use std::pin::Pin;
-#[cfg(feature = "websocket")]
 use std::task::{self, Poll};
+use std::time::Duration;
 
 use bytes::Bytes;
 #[cfg(feature = "websocket")]
 use futures::StreamExt;
-use futures::{future, FutureExt, TryFutureExt};
+use futures::{future, FutureExt, Stream, TryFutureExt};
 use http::{
     header::{HeaderName, HeaderValue},
-    Response,
+    Response, StatusCode,
 };
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json;
 #[cfg(feature = "websocket")]
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use hyper::body::HttpBody;
+use tokio::time::{self as time, Delay};
 
 use crate::filter::Filter;
 use crate::reject::IsReject;
@@ -124,6 +138,56 @@ pub fn ws() -> WsBuilder {
     WsBuilder { req: request() }
 }
 
+/// Starts a new test `SseBuilder`.
+pub fn sse() -> SseBuilder {
+    SseBuilder {
+        req: request().header("accept", "text/event-stream"),
+    }
+}
+
+/// Starts a new test `ServeBuilder`, to run `filter` on a real server.
+///
+/// Unlike the other builders of this module, which call the filter
+/// in-process, this binds an ephemeral port of `127.0.0.1`, so that clients
+/// can connect to it over the network, with HTTP/1 or HTTP/2, and optionally
+/// TLS.
+///
+/// # Example
+///
+/// ```no_run
+/// use warp::Filter;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let route = warp::any().map(|| "hello");
+///
+/// let server = warp::test::serve(route).start();
+/// let url = server.url("/");
+/// // Send requests to `url` with any HTTP client...
+///
+/// server.shutdown().await;
+/// # }
+/// ```
+pub fn serve<F>(filter: F) -> ServeBuilder<F> {
+    ServeBuilder {
+        filter,
+        #[cfg(feature = "tls")]
+        tls: false,
+    }
+}
+
+/// Starts a new test `FuzzBuilder`.
+pub fn fuzz() -> FuzzBuilder {
+    FuzzBuilder {
+        seed: None,
+        iterations: 256,
+        paths: Vec::new(),
+        methods: Vec::new(),
+        bodies: Vec::new(),
+        statuses: Vec::new(),
+    }
+}
+
 /// A request builder for testing filters.
 ///
 /// See [module documentation](crate::test) for an overview.
@@ -149,6 +213,103 @@ pub struct WsBuilder {
 pub struct WsClient {
     tx: mpsc::UnboundedSender<crate::ws::Message>,
     rx: mpsc::UnboundedReceiver<Result<crate::ws::Message, crate::error::Error>>,
+    timeout: Duration,
+}
+
+/// A Server-Sent Events builder for testing filters.
+///
+/// See [module documentation](crate::test) for an overview.
+#[must_use = "SseBuilder does nothing on its own"]
+#[derive(Debug)]
+pub struct SseBuilder {
+    req: RequestBuilder,
+}
+
+/// A test client for Server-Sent Events filters.
+///
+/// This is a `Stream` of the events sent by the filter, which errors if no
+/// event is received before the [timeout](SseClient::timeout).
+pub struct SseClient {
+    body: hyper::Body,
+    buf: String,
+    timeout: Duration,
+    delay: Delay,
+}
+
+/// A builder for a [`TestServer`], see [`serve`].
+#[must_use = "ServeBuilder does nothing on its own"]
+#[derive(Debug)]
+pub struct ServeBuilder<F> {
+    filter: F,
+    #[cfg(feature = "tls")]
+    tls: bool,
+}
+
+/// A server running a filter for tests, see [`serve`].
+///
+/// The server is shut down when this is dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    #[cfg(feature = "tls")]
+    cert: Option<self_signed::Certificate>,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+/// A builder of random requests, checking a filter handles them all.
+///
+/// Requests are derived from example paths and bodies of the routes, either
+/// left as they are or mangled: path segments and query parameters are
+/// replaced with edge cases such as huge numbers or percent-encoded bytes,
+/// and bodies are truncated, corrupted or swapped for other content types.
+///
+/// The filter passes if it never panics, and replies to every request with
+/// one of the expected [statuses](FuzzBuilder::status), or with any status
+/// below `500` if none are given.
+///
+/// # Example
+///
+/// ```no_run
+/// use warp::Filter;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let route = warp::path!("todos" / u32)
+///     .and(warp::body::json())
+///     .map(|id: u32, todo: serde_json::Value| warp::reply::json(&todo));
+///
+/// warp::test::fuzz()
+///     .path("/todos/7")
+///     .method("PUT")
+///     .json(&serde_json::json!({ "text": "fuzz" }))
+///     .run(&route)
+///     .await;
+/// # }
+/// ```
+#[must_use = "FuzzBuilder does nothing on its own"]
+#[derive(Debug)]
+pub struct FuzzBuilder {
+    seed: Option<u64>,
+    iterations: usize,
+    paths: Vec<String>,
+    methods: Vec<http::Method>,
+    bodies: Vec<(Bytes, Option<&'static str>)>,
+    statuses: Vec<StatusCode>,
+}
+
+/// An event received by a [`SseClient`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SseEvent {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// An error from Server-Sent Events filter tests.
+#[derive(Debug)]
+pub struct SseError {
+    cause: Box<dyn StdError + Send + Sync>,
 }
 
 /// An error from Websocket filter tests.
@@ -285,6 +446,29 @@ impl RequestBuilder {
             .header("content-type", "application/json")
     }
 
+    /// Set the body of this request to a `multipart/form-data` form.
+    ///
+    /// This sets the `content-type` header, with the boundary of the form.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::test::MultipartForm;
+    ///
+    /// let form = MultipartForm::new()
+    ///     .text("name", "warp")
+    ///     .file("logo", "logo.png", "image/png", &b"\x89PNG"[..]);
+    ///
+    /// let req = warp::test::request()
+    ///     .method("POST")
+    ///     .multipart(form);
+    /// ```
+    pub fn multipart(self, form: MultipartForm) -> Self {
+        let content_type = format!("multipart/form-data; boundary={}", form.boundary);
+        self.body(form.to_bytes())
+            .header("content-type", content_type)
+    }
+
     /// Tries to apply the `Filter` on this request.
     ///
     /// # Example
@@ -404,6 +588,518 @@ impl RequestBuilder {
     }
 }
 
+/// Assertions on the responses returned by [`RequestBuilder::reply`].
+///
+/// Each assertion panics with a descriptive message when it fails, and
+/// otherwise returns the response, so that assertions can be chained.
+///
+/// # Example
+///
+/// ```no_run
+/// use serde_json::json;
+/// use warp::test::ResponseExt;
+/// use warp::Filter;
+///
+/// # async fn run() {
+/// let route = warp::any().map(|| warp::reply::json(&json!({ "id": 7 })));
+///
+/// warp::test::request()
+///     .reply(&route)
+///     .await
+///     .assert_status(200)
+///     .assert_header("content-type", "application/json")
+///     .assert_json(&json!({ "id": 7 }));
+/// # }
+/// ```
+pub trait ResponseExt: sealed::Sealed {
+    /// Asserts the status of the response.
+    #[track_caller]
+    fn assert_status<S>(&self, status: S) -> &Self
+    where
+        StatusCode: TryFrom<S>;
+
+    /// Asserts the response has a header with the value.
+    #[track_caller]
+    fn assert_header<K>(&self, name: K, value: &str) -> &Self
+    where
+        HeaderName: TryFrom<K>;
+
+    /// Asserts the body of the response is the JSON of `expected`.
+    ///
+    /// If it isn't, both are pretty printed along with their differences.
+    #[track_caller]
+    fn assert_json<T: Serialize>(&self, expected: &T) -> &Self;
+
+    /// Deserializes the body of the response from JSON.
+    #[track_caller]
+    fn json_body<T: DeserializeOwned>(&self) -> T;
+}
+
+impl ResponseExt for Response<Bytes> {
+    #[track_caller]
+    fn assert_status<S>(&self, status: S) -> &Self
+    where
+        StatusCode: TryFrom<S>,
+    {
+        let status = StatusCode::try_from(status)
+            .map_err(|_| ())
+            .expect("invalid status code");
+        assert_eq!(
+            self.status(),
+            status,
+            "unexpected status, body: {}",
+            String::from_utf8_lossy(self.body())
+        );
+        self
+    }
+
+    #[track_caller]
+    fn assert_header<K>(&self, name: K, value: &str) -> &Self
+    where
+        HeaderName: TryFrom<K>,
+    {
+        let name: HeaderName = TryFrom::try_from(name)
+            .map_err(|_| ())
+            .expect("invalid header name");
+        let values: Vec<_> = self.headers().get_all(&name).iter().collect();
+        assert!(
+            values.iter().any(|v| v.as_bytes() == value.as_bytes()),
+            "expected header {}: {:?}, found {:?}",
+            name,
+            value,
+            values
+        );
+        self
+    }
+
+    #[track_caller]
+    fn assert_json<T: Serialize>(&self, expected: &T) -> &Self {
+        let actual: serde_json::Value = self.json_body();
+        let expected = serde_json::to_value(expected).expect("expected value must serialize");
+        if actual != expected {
+            let actual = serde_json::to_string_pretty(&actual).expect("json value serializes");
+            let expected = serde_json::to_string_pretty(&expected).expect("json value serializes");
+            panic!(
+                "JSON body doesn't match (-expected +actual):\n{}",
+                diff_lines(&expected, &actual)
+            );
+        }
+        self
+    }
+
+    #[track_caller]
+    fn json_body<T: DeserializeOwned>(&self) -> T {
+        match serde_json::from_slice(self.body()) {
+            Ok(value) => value,
+            Err(err) => panic!(
+                "invalid JSON body: {}\nbody: {}",
+                err,
+                String::from_utf8_lossy(self.body())
+            ),
+        }
+    }
+}
+
+// A line diff of `a` and `b`, from their longest common subsequence.
+fn diff_lines(a: &str, b: &str) -> String {
+    let a: Vec<_> = a.lines().collect();
+    let b: Vec<_> = b.lines().collect();
+    // lcs[i][j] is the length of the LCS of a[i..] and b[j..].
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push_str(&format!(" {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("-{}\n", a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for http::Response<bytes::Bytes> {}
+}
+
+/// A `multipart/form-data` body for testing filters, see
+/// [`RequestBuilder::multipart`].
+#[derive(Clone, Debug)]
+pub struct MultipartForm {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+impl MultipartForm {
+    /// Creates an empty form.
+    pub fn new() -> MultipartForm {
+        MultipartForm {
+            boundary: "warp-test-boundary-7MA4YWxkTrZu0gW".to_owned(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a text field.
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.parts.push(Part {
+            name: name.to_owned(),
+            filename: None,
+            content_type: None,
+            data: value.as_bytes().to_vec(),
+        });
+        self
+    }
+
+    /// Adds a file, with its file name and content type.
+    pub fn file(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: &str,
+        data: impl AsRef<[u8]>,
+    ) -> Self {
+        self.parts.push(Part {
+            name: name.to_owned(),
+            filename: Some(filename.to_owned()),
+            content_type: Some(content_type.to_owned()),
+            data: data.as_ref().to_vec(),
+        });
+        self
+    }
+
+    /// Sets the boundary between the parts of the form.
+    ///
+    /// # Panic
+    ///
+    /// This panics if the boundary is empty or longer than 70 characters.
+    pub fn boundary(mut self, boundary: &str) -> Self {
+        assert!(
+            !boundary.is_empty() && boundary.len() <= 70,
+            "illegal multipart boundary: {:?}",
+            boundary
+        );
+        self.boundary = boundary.to_owned();
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for part in &self.parts {
+            body.extend_from_slice(b"--");
+            body.extend_from_slice(self.boundary.as_bytes());
+            body.extend_from_slice(b"\r\n");
+            let mut disposition = format!("content-disposition: form-data; name=\"{}\"", part.name);
+            if let Some(ref filename) = part.filename {
+                disposition.push_str(&format!("; filename=\"{}\"", filename));
+            }
+            body.extend_from_slice(disposition.as_bytes());
+            body.extend_from_slice(b"\r\n");
+            if let Some(ref content_type) = part.content_type {
+                body.extend_from_slice(format!("content-type: {}\r\n", content_type).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--");
+        body.extend_from_slice(self.boundary.as_bytes());
+        body.extend_from_slice(b"--\r\n");
+        body
+    }
+}
+
+impl Default for MultipartForm {
+    fn default() -> MultipartForm {
+        MultipartForm::new()
+    }
+}
+
+impl SseBuilder {
+    /// Sets the request path of this builder.
+    ///
+    /// The default is not set is `/`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let req = warp::test::sse()
+    ///     .path("/events");
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// This panics if the passed string is not able to be parsed as a valid
+    /// `Uri`.
+    pub fn path(self, p: &str) -> Self {
+        SseBuilder {
+            req: self.req.path(p),
+        }
+    }
+
+    /// Set a header for this request.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let req = warp::test::sse()
+    ///     .header("last-event-id", "42");
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// This panics if the passed strings are not able to be parsed as a valid
+    /// `HeaderName` and `HeaderValue`.
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        HeaderValue: TryFrom<V>,
+    {
+        SseBuilder {
+            req: self.req.header(key, value),
+        }
+    }
+
+    /// Execute this request against the provided filter.
+    ///
+    /// If the filter replies with an event stream, returns a `SseClient`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::convert::Infallible;
+    /// use futures::stream;
+    /// use warp::Filter;
+    /// # async fn run() {
+    ///
+    /// let route = warp::any().map(|| {
+    ///     let events = stream::iter(vec![Ok::<_, Infallible>(warp::sse::data("hello"))]);
+    ///     warp::sse::reply(events)
+    /// });
+    ///
+    /// let mut client = warp::test::sse()
+    ///     .connect(&route)
+    ///     .await
+    ///     .expect("connect");
+    ///
+    /// let event = client.recv().await.expect("event");
+    /// assert_eq!(event.data(), Some("hello"));
+    /// # }
+    /// ```
+    pub async fn connect<F>(self, f: &F) -> Result<SseClient, SseError>
+    where
+        F: Filter + 'static,
+        F::Extract: Reply + Send,
+        F::Error: IsReject + Send,
+    {
+        assert!(!route::is_set(), "nested test filter calls");
+
+        let route = Route::new(self.req.req, self.req.remote_addr);
+        let mut fut = Box::pin(route::set(&route, move || {
+            f.filter(crate::filter::Internal)
+        }));
+        let result = future::poll_fn(move |cx| route::set(&route, || fut.as_mut().poll(cx))).await;
+        let res = match result {
+            Ok(rep) => rep.into_response(),
+            Err(rej) => {
+                log::debug!("rejected: {:?}", rej);
+                rej.into_response()
+            }
+        };
+
+        let is_event_stream = matches!(
+            res.headers().get("content-type"),
+            Some(value) if value.as_bytes().starts_with(b"text/event-stream")
+        );
+        if !res.status().is_success() || !is_event_stream {
+            return Err(SseError::new(format!(
+                "not an event stream: {} {:?}",
+                res.status(),
+                res.headers().get("content-type"),
+            )));
+        }
+
+        let timeout = Duration::from_secs(5);
+        Ok(SseClient {
+            body: res.into_body(),
+            buf: String::new(),
+            timeout,
+            delay: time::delay_for(timeout),
+        })
+    }
+}
+
+impl SseClient {
+    /// Sets how long to wait for each event, before failing.
+    ///
+    /// The default is 5 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.delay = time::delay_for(timeout);
+        self
+    }
+
+    /// Receive an event from the server.
+    pub async fn recv(&mut self) -> Result<SseEvent, SseError> {
+        future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx))
+            .await
+            .unwrap_or_else(|| Err(SseError::new("closed")))
+    }
+
+    /// Assert the server has ended the event stream.
+    pub async fn recv_closed(&mut self) -> Result<(), SseError> {
+        match future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await {
+            Some(Ok(event)) => Err(SseError::new(format!("received event: {:?}", event))),
+            Some(Err(err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    // Parses the next event out of the buffer, skipping comments.
+    fn parse_event(&mut self) -> Option<SseEvent> {
+        loop {
+            let end = self.buf.find("\n\n")?;
+            let block: String = self.buf.drain(..end + 2).collect();
+            let mut event = SseEvent::default();
+            let mut has_field = false;
+            for line in block.lines() {
+                if line.is_empty() || line.starts_with(':') {
+                    continue;
+                }
+                let (field, value) = match line.find(':') {
+                    Some(idx) => {
+                        let value = &line[idx + 1..];
+                        (&line[..idx], value.strip_prefix(' ').unwrap_or(value))
+                    }
+                    None => (line, ""),
+                };
+                has_field = true;
+                match field {
+                    "data" => match event.data {
+                        Some(ref mut data) => {
+                            data.push('\n');
+                            data.push_str(value);
+                        }
+                        None => event.data = Some(value.to_owned()),
+                    },
+                    "event" => event.event = Some(value.to_owned()),
+                    "id" => event.id = Some(value.to_owned()),
+                    "retry" => event.retry = value.parse().ok().map(Duration::from_millis),
+                    _ => {}
+                }
+            }
+            if has_field {
+                return Some(event);
+            }
+        }
+    }
+}
+
+impl Stream for SseClient {
+    type Item = Result<SseEvent, SseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        let client = self.get_mut();
+        loop {
+            if let Some(event) = client.parse_event() {
+                client.delay.reset(time::Instant::now() + client.timeout);
+                return Poll::Ready(Some(Ok(event)));
+            }
+            match Pin::new(&mut client.body).poll_data(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    // Normalize line endings, so events end with "\n\n".
+                    let chunk = String::from_utf8_lossy(&chunk).replace("\r\n", "\n");
+                    client.buf.push_str(&chunk);
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(SseError::new(err)))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => {
+                    return match Pin::new(&mut client.delay).poll(cx) {
+                        Poll::Ready(()) => {
+                            client.delay.reset(time::Instant::now() + client.timeout);
+                            Poll::Ready(Some(Err(SseError::new("timed out waiting for event"))))
+                        }
+                        Poll::Pending => Poll::Pending,
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SseClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SseClient")
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl SseEvent {
+    /// The data of the event, with multiple `data` lines joined by `\n`.
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    /// The name of the event.
+    pub fn event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    /// The id of the event.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The reconnection time of the event.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+}
+
+// ===== impl SseError =====
+
+impl SseError {
+    fn new<E: Into<Box<dyn StdError + Send + Sync>>>(cause: E) -> Self {
+        SseError {
+            cause: cause.into(),
+        }
+    }
+}
+
+impl fmt::Display for SseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "server-sent events error: {}", self.cause)
+    }
+}
+
+impl StdError for SseError {}
+
 #[cfg(feature = "websocket")]
 impl WsBuilder {
     /// Sets the request path of this builder.
@@ -531,10 +1227,18 @@ impl WsBuilder {
             let (tx, rx) = ws.split();
             let write = wr_rx.map(Ok).forward(tx).map(|_| ());
 
+            // The Close message is passed on, so its code can be checked.
             let read = rx
-                .take_while(|result| match result {
-                    Err(_) => future::ready(false),
-                    Ok(m) => future::ready(!m.is_close()),
+                .scan(false, |closed, result| {
+                    let item = match result {
+                        Ok(_) if *closed => None,
+                        Ok(m) => {
+                            *closed = m.is_close();
+                            Some(Ok(m))
+                        }
+                        Err(_) => None,
+                    };
+                    future::ready(item)
                 })
                 .for_each(move |item| {
                     rd_tx.send(item).expect("ws receive error");
@@ -548,6 +1252,7 @@ impl WsBuilder {
             Ok(Ok(())) => Ok(WsClient {
                 tx: wr_tx,
                 rx: rd_rx,
+                timeout: Duration::from_secs(5),
             }),
             Ok(Err(err)) => Err(WsError::new(err)),
             Err(_canceled) => panic!("websocket handshake thread panicked"),
@@ -557,48 +1262,118 @@ impl WsBuilder {
 
 #[cfg(feature = "websocket")]
 impl WsClient {
+    /// Sets how long to wait for each message, before failing.
+    ///
+    /// The default is 5 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     /// Send a "text" websocket message to the server.
     pub async fn send_text(&mut self, text: impl Into<String>) {
         self.send(crate::ws::Message::text(text)).await;
     }
 
+    /// Send a "binary" websocket message to the server.
+    pub async fn send_binary(&mut self, data: impl Into<Vec<u8>>) {
+        self.send(crate::ws::Message::binary(data)).await;
+    }
+
     /// Send a websocket message to the server.
     pub async fn send(&mut self, msg: crate::ws::Message) {
         self.tx.send(msg).unwrap();
     }
 
     /// Receive a websocket message from the server.
+    ///
+    /// Errors if the server closes the connection.
     pub async fn recv(&mut self) -> Result<crate::filters::ws::Message, WsError> {
-        self.rx
-            .next()
-            .await
-            .map(|unbounded_result| unbounded_result.map_err(WsError::new))
-            .unwrap_or_else(|| {
-                // websocket is closed
-                Err(WsError::new("closed"))
-            })
+        match self.next().await? {
+            Some(msg) if msg.is_close() => Err(WsError::new("closed")),
+            Some(msg) => Ok(msg),
+            // websocket is closed
+            None => Err(WsError::new("closed")),
+        }
+    }
+
+    /// Receive a "text" websocket message from the server.
+    ///
+    /// Pings and pongs are skipped, and any other message is an error.
+    pub async fn recv_text(&mut self) -> Result<String, WsError> {
+        let msg = self.recv_data().await?;
+        match msg.to_str() {
+            Ok(text) => Ok(text.to_owned()),
+            Err(()) => Err(WsError::new(format!("expected text, received: {:?}", msg))),
+        }
+    }
+
+    /// Receive a "binary" websocket message from the server.
+    ///
+    /// Pings and pongs are skipped, and any other message is an error.
+    pub async fn recv_binary(&mut self) -> Result<Vec<u8>, WsError> {
+        let msg = self.recv_data().await?;
+        if msg.is_binary() {
+            Ok(msg.into_bytes())
+        } else {
+            Err(WsError::new(format!(
+                "expected binary, received: {:?}",
+                msg
+            )))
+        }
     }
 
     /// Assert the server has closed the connection.
     pub async fn recv_closed(&mut self) -> Result<(), WsError> {
-        self.rx
-            .next()
-            .await
-            .map(|result| match result {
-                Ok(msg) => Err(WsError::new(format!("received message: {:?}", msg))),
-                Err(err) => Err(WsError::new(err)),
-            })
-            .unwrap_or_else(|| {
-                // closed successfully
-                Ok(())
-            })
+        self.recv_close_frame().await.map(|_| ())
+    }
+
+    /// Assert the server has closed the connection, returning the code and
+    /// reason of its Close message, if it has them.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn test(mut client: warp::test::WsClient) {
+    /// let frame = client.recv_close_frame().await.expect("closed");
+    /// assert_eq!(frame, Some((1000, "bye".to_owned())));
+    /// # }
+    /// ```
+    pub async fn recv_close_frame(&mut self) -> Result<Option<(u16, String)>, WsError> {
+        match self.next().await? {
+            Some(msg) if msg.is_close() => Ok(msg
+                .close_frame()
+                .map(|(code, reason)| (code, reason.to_owned()))),
+            Some(msg) => Err(WsError::new(format!("received message: {:?}", msg))),
+            // closed without a Close message
+            None => Ok(None),
+        }
+    }
+
+    async fn recv_data(&mut self) -> Result<crate::filters::ws::Message, WsError> {
+        loop {
+            let msg = self.recv().await?;
+            if !msg.is_ping() && !msg.is_pong() {
+                return Ok(msg);
+            }
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<crate::filters::ws::Message>, WsError> {
+        match time::timeout(self.timeout, self.rx.next()).await {
+            Ok(Some(result)) => result.map(Some).map_err(WsError::new),
+            Ok(None) => Ok(None),
+            Err(_) => Err(WsError::new("timed out waiting for message")),
+        }
     }
 }
 
 #[cfg(feature = "websocket")]
 impl fmt::Debug for WsClient {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("WsClient").finish()
+        f.debug_struct("WsClient")
+            .field("timeout", &self.timeout)
+            .finish()
     }
 }
 
@@ -625,6 +1400,461 @@ impl StdError for WsError {
     }
 }
 
+// ===== impl ServeBuilder =====
+
+impl<F> ServeBuilder<F>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Error: IsReject,
+{
+    /// Serves TLS, with a self-signed certificate generated for `localhost`.
+    ///
+    /// Clients should trust the [certificate](TestServer::certificate_der)
+    /// of the server, and connect to it with the `localhost` server name.
+    /// HTTP/2 and HTTP/1.1 are offered with ALPN.
+    ///
+    /// *This function requires the `"tls"` feature.*
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
+    /// Binds the server and spawns it on the current runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime, or if binding fails.
+    pub fn start(self) -> TestServer {
+        let (tx, rx) = oneshot::channel::<()>();
+        let signal = async move {
+            let _ = rx.await;
+        };
+        let addr = ([127, 0, 0, 1], 0);
+
+        #[cfg(feature = "tls")]
+        {
+            if self.tls {
+                let cert = self_signed::Certificate::generate();
+                let (addr, srv) = crate::serve(self.filter)
+                    .tls()
+                    .key(cert.key_pem())
+                    .cert(cert.pem())
+                    .bind_with_graceful_shutdown(addr, signal);
+                return TestServer {
+                    addr,
+                    cert: Some(cert),
+                    shutdown: Some(tx),
+                    task: Some(tokio::spawn(srv)),
+                };
+            }
+        }
+
+        let (addr, srv) = crate::serve(self.filter).bind_with_graceful_shutdown(addr, signal);
+        TestServer {
+            addr,
+            #[cfg(feature = "tls")]
+            cert: None,
+            shutdown: Some(tx),
+            task: Some(tokio::spawn(srv)),
+        }
+    }
+}
+
+// ===== impl TestServer =====
+
+impl TestServer {
+    /// The address the server is bound to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL of `path` on this server.
+    ///
+    /// The host of TLS servers is `localhost`, the name of their certificate.
+    pub fn url(&self, path: &str) -> String {
+        #[cfg(feature = "tls")]
+        {
+            if self.cert.is_some() {
+                return format!("https://localhost:{}{}", self.addr.port(), path);
+            }
+        }
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// The DER encoded self-signed certificate of a TLS server.
+    ///
+    /// *This function requires the `"tls"` feature.*
+    #[cfg(feature = "tls")]
+    pub fn certificate_der(&self) -> Option<&[u8]> {
+        self.cert.as_ref().map(|cert| cert.der())
+    }
+
+    /// The PEM encoded self-signed certificate of a TLS server.
+    ///
+    /// *This function requires the `"tls"` feature.*
+    #[cfg(feature = "tls")]
+    pub fn certificate_pem(&self) -> Option<&str> {
+        self.cert.as_ref().map(|cert| cert.pem())
+    }
+
+    /// Gracefully shuts down the server, waiting for open connections to
+    /// close.
+    pub async fn shutdown(mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TestServer")
+            .field("addr", &self.addr)
+            .finish()
+    }
+}
+
+// ===== impl FuzzBuilder =====
+
+// Replacements for path segments and query values.
+const FUZZ_VALUES: &[&str] = &[
+    "",
+    "0",
+    "-1",
+    "1.5",
+    "4294967296",
+    "18446744073709551616",
+    "true",
+    "null",
+    "..",
+    "%2e%2e",
+    "%00",
+    "%ff",
+    "%zz",
+    "%C3%A9",
+    "%F0%9F%92%A5",
+    "a%20b",
+    "%2F",
+];
+
+const FUZZ_CONTENT_TYPES: &[&str] = &[
+    "application/json",
+    "application/x-www-form-urlencoded",
+    "text/plain",
+    "multipart/form-data",
+    "application/octet-stream",
+    "not a mime",
+];
+
+impl FuzzBuilder {
+    /// Sets the seed of the random requests.
+    ///
+    /// By default, a new seed is picked on each run. It is included in the
+    /// panic message of failing runs, so that they can be reproduced.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the number of requests to send, `256` by default.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Adds an example path, including any query string, of a route.
+    ///
+    /// Without any, the paths are random.
+    ///
+    /// # Panic
+    ///
+    /// This panics if the passed string is not able to be parsed as a valid
+    /// `Uri`.
+    pub fn path(mut self, p: &str) -> Self {
+        p.parse::<http::Uri>().expect("fuzz path invalid");
+        self.paths.push(p.to_owned());
+        self
+    }
+
+    /// Adds a method of the routes.
+    ///
+    /// Requests mostly use these methods, and some use any other. By
+    /// default, all the common methods are used.
+    ///
+    /// # Panic
+    ///
+    /// This panics if the passed string is not able to be parsed as a valid
+    /// `Method`.
+    pub fn method(mut self, method: &str) -> Self {
+        self.methods.push(method.parse().expect("valid method"));
+        self
+    }
+
+    /// Adds an example body of a route.
+    pub fn body(mut self, body: impl AsRef<[u8]>) -> Self {
+        self.bodies
+            .push((Bytes::copy_from_slice(body.as_ref()), None));
+        self
+    }
+
+    /// Adds an example body of a route by serializing a value into JSON.
+    ///
+    /// Requests using this body as it is have a JSON `content-type`.
+    pub fn json(mut self, val: &impl Serialize) -> Self {
+        let vec = serde_json::to_vec(val).expect("json() must serialize to JSON");
+        self.bodies.push((vec.into(), Some("application/json")));
+        self
+    }
+
+    /// Adds an expected status of the replies.
+    ///
+    /// # Panic
+    ///
+    /// This panics if the passed code is not a valid `StatusCode`.
+    pub fn status(mut self, status: u16) -> Self {
+        self.statuses
+            .push(StatusCode::from_u16(status).expect("valid status code"));
+        self
+    }
+
+    /// Sends the random requests to the filter.
+    ///
+    /// # Panic
+    ///
+    /// This panics, describing the request and the seed of the run, if the
+    /// filter panics or replies with an unexpected status.
+    pub async fn run<F>(self, f: &F)
+    where
+        F: Filter + 'static,
+        F::Extract: Reply + Send,
+        F::Error: IsReject + Send,
+    {
+        let seed = self.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        });
+        let mut rng = FuzzRng::new(seed);
+
+        for i in 0..self.iterations {
+            let (method, path, headers, body) = self.generate(&mut rng);
+            let describe = || {
+                format!(
+                    "fuzz request #{} (seed {}): {} {} {:?} body={:?}",
+                    i,
+                    seed,
+                    method,
+                    path,
+                    headers,
+                    String::from_utf8_lossy(&body),
+                )
+            };
+
+            let mut req = request().method(method.as_str()).path(&path).body(&body);
+            for (name, value) in &headers {
+                req = req.header(*name, value.as_str());
+            }
+
+            let mut fut = Box::pin(req.reply(f));
+            let polled = future::poll_fn(|cx| {
+                match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    fut.as_mut().poll(cx)
+                })) {
+                    Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+                    Ok(Poll::Pending) => Poll::Pending,
+                    Err(_) => Poll::Ready(Err(())),
+                }
+            })
+            .await;
+
+            let res = match polled {
+                Ok(res) => res,
+                Err(()) => panic!("filter panicked on {}", describe()),
+            };
+            let status = res.status();
+            let expected = if self.statuses.is_empty() {
+                !status.is_server_error()
+            } else {
+                self.statuses.contains(&status)
+            };
+            if !expected {
+                panic!("unexpected status {} on {}", status, describe());
+            }
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut FuzzRng,
+    ) -> (http::Method, String, Vec<(&'static str, String)>, Vec<u8>) {
+        let method = if !self.methods.is_empty() && rng.below(4) > 0 {
+            rng.choose(&self.methods).clone()
+        } else {
+            rng.choose(&[
+                http::Method::GET,
+                http::Method::POST,
+                http::Method::PUT,
+                http::Method::PATCH,
+                http::Method::DELETE,
+                http::Method::HEAD,
+                http::Method::OPTIONS,
+            ])
+            .clone()
+        };
+
+        // Examples are sometimes sent as they are, to cover the happy path.
+        let pristine = rng.below(4) == 0;
+
+        let path = if self.paths.is_empty() {
+            let mut path = String::new();
+            for _ in 0..=rng.below(3) {
+                path.push('/');
+                path.push_str(&rng.value());
+            }
+            path
+        } else if pristine {
+            rng.choose(&self.paths).clone()
+        } else {
+            let example = rng.choose(&self.paths);
+            let (path, query) = match example.find('?') {
+                Some(i) => (&example[..i], Some(&example[i + 1..])),
+                None => (&example[..], None),
+            };
+            let mut out = String::new();
+            for segment in path.split('/').skip(1) {
+                out.push('/');
+                if rng.below(3) == 0 {
+                    out.push_str(&rng.value());
+                } else {
+                    out.push_str(segment);
+                }
+            }
+            if out.is_empty() {
+                out.push('/');
+            }
+            if rng.below(8) == 0 {
+                out.push('/');
+                out.push_str(&rng.value());
+            }
+            let mut pairs: Vec<String> = query
+                .map(|q| q.split('&').map(String::from).collect())
+                .unwrap_or_default();
+            for pair in &mut pairs {
+                if rng.below(3) == 0 {
+                    let key = pair.split('=').next().unwrap_or("").to_owned();
+                    *pair = format!("{}={}", key, rng.value());
+                }
+            }
+            if rng.below(4) == 0 {
+                pairs.push(format!("{}={}", rng.value(), rng.value()));
+            }
+            if !pairs.is_empty() {
+                out.push('?');
+                out.push_str(&pairs.join("&"));
+            }
+            out
+        };
+
+        let mut headers = Vec::new();
+        let body = if self.bodies.is_empty() {
+            match rng.below(3) {
+                0 => Vec::new(),
+                _ => rng.bytes(),
+            }
+        } else {
+            let (example, content_type) = rng.choose(&self.bodies);
+            if pristine {
+                if let Some(content_type) = content_type {
+                    headers.push(("content-type", (*content_type).to_owned()));
+                }
+                example.to_vec()
+            } else {
+                let mut body = example.to_vec();
+                match rng.below(4) {
+                    0 => body.truncate(rng.below(body.len() + 1)),
+                    1 if !body.is_empty() => {
+                        let i = rng.below(body.len());
+                        body[i] = rng.next() as u8;
+                    }
+                    2 => body = rng.bytes(),
+                    _ => body.clear(),
+                }
+                body
+            }
+        };
+        if headers.is_empty() && rng.below(2) == 0 {
+            headers.push(("content-type", (*rng.choose(FUZZ_CONTENT_TYPES)).to_owned()));
+        }
+        if rng.below(4) == 0 {
+            headers.push(("accept", (*rng.choose(FUZZ_CONTENT_TYPES)).to_owned()));
+        }
+
+        (method, path, headers, body)
+    }
+}
+
+// A xorshift generator, so that runs can be reproduced from their seed.
+struct FuzzRng(u64);
+
+impl FuzzRng {
+    fn new(seed: u64) -> FuzzRng {
+        // The state must not be zero.
+        FuzzRng(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            0
+        } else {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    // A path segment or query value.
+    fn value(&mut self) -> String {
+        match self.below(4) {
+            0 => self.next().to_string(),
+            1 => {
+                let len = 1 + self.below(300);
+                (0..len)
+                    .map(|_| (b'a' + self.below(26) as u8) as char)
+                    .collect()
+            }
+            _ => (*self.choose(FUZZ_VALUES)).to_owned(),
+        }
+    }
+
+    fn bytes(&mut self) -> Vec<u8> {
+        let len = self.below(512);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
 // ===== impl AddrConnect =====
 
 #[cfg(feature = "websocket")]
@@ -646,6 +1876,172 @@ impl tower_service::Service<::http::Uri> for AddrConnect {
     }
 }
 
+#[cfg(feature = "tls")]
+mod self_signed {
+    //! Generates self-signed certificates for `localhost`.
+    //!
+    //! The certificate is encoded by hand, being small and always the same
+    //! but for its key and serial number.
+
+    use ring::rand::{SecureRandom, SystemRandom};
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    const SEQUENCE: u8 = 0x30;
+    const SET: u8 = 0x31;
+    const INTEGER: u8 = 0x02;
+    const BIT_STRING: u8 = 0x03;
+    const OCTET_STRING: u8 = 0x04;
+    const UTF8_STRING: u8 = 0x0c;
+    const UTC_TIME: u8 = 0x17;
+    const GENERALIZED_TIME: u8 = 0x18;
+
+    // ecdsa-with-SHA256
+    const ECDSA_SHA256: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+    // id-ecPublicKey
+    const EC_PUBLIC_KEY: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+    // prime256v1
+    const P256: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    // commonName
+    const COMMON_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+    // subjectAltName
+    const SUBJECT_ALT_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x1d, 0x11];
+
+    pub(super) struct Certificate {
+        der: Vec<u8>,
+        pem: String,
+        key_pem: String,
+    }
+
+    impl Certificate {
+        pub(super) fn generate() -> Certificate {
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+                .expect("generate key");
+            let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref())
+                .expect("parse generated key");
+
+            let mut serial = [0; 16];
+            rng.fill(&mut serial).expect("generate serial number");
+            // Serial numbers are positive, and have no leading zero byte.
+            serial[0] = serial[0] & 0x7f | 0x01;
+
+            let name = der(
+                SEQUENCE,
+                &der(
+                    SET,
+                    &der(
+                        SEQUENCE,
+                        &[COMMON_NAME, &der(UTF8_STRING, b"localhost")].concat(),
+                    ),
+                ),
+            );
+            let alt_names = der(
+                SEQUENCE,
+                &[der(0x82, b"localhost"), der(0x87, &[127, 0, 0, 1])].concat(),
+            );
+            let extensions = der(
+                0xa3,
+                &der(
+                    SEQUENCE,
+                    &der(
+                        SEQUENCE,
+                        &[SUBJECT_ALT_NAME, &der(OCTET_STRING, &alt_names)].concat(),
+                    ),
+                ),
+            );
+            let public_key = der(
+                SEQUENCE,
+                &[
+                    der(SEQUENCE, &[EC_PUBLIC_KEY, P256].concat()),
+                    bit_string(key.public_key().as_ref()),
+                ]
+                .concat(),
+            );
+            let tbs = der(
+                SEQUENCE,
+                &[
+                    // Version 3
+                    der(0xa0, &der(INTEGER, &[2])),
+                    der(INTEGER, &serial),
+                    der(SEQUENCE, ECDSA_SHA256),
+                    name.clone(),
+                    // Valid from 2000 up to the end of 9999, the latter
+                    // meaning no expiration.
+                    der(
+                        SEQUENCE,
+                        &[
+                            der(UTC_TIME, b"000101000000Z"),
+                            der(GENERALIZED_TIME, b"99991231235959Z"),
+                        ]
+                        .concat(),
+                    ),
+                    name,
+                    public_key,
+                    extensions,
+                ]
+                .concat(),
+            );
+            let signature = key.sign(&rng, &tbs).expect("sign certificate");
+            let cert = der(
+                SEQUENCE,
+                &[
+                    tbs,
+                    der(SEQUENCE, ECDSA_SHA256),
+                    bit_string(signature.as_ref()),
+                ]
+                .concat(),
+            );
+
+            Certificate {
+                pem: pem("CERTIFICATE", &cert),
+                key_pem: pem("PRIVATE KEY", pkcs8.as_ref()),
+                der: cert,
+            }
+        }
+
+        pub(super) fn der(&self) -> &[u8] {
+            &self.der
+        }
+
+        pub(super) fn pem(&self) -> &str {
+            &self.pem
+        }
+
+        pub(super) fn key_pem(&self) -> &str {
+            &self.key_pem
+        }
+    }
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let len = content.len();
+        let mut out = vec![tag];
+        if len < 0x80 {
+            out.push(len as u8);
+        } else if len < 0x100 {
+            out.extend_from_slice(&[0x81, len as u8]);
+        } else {
+            out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn bit_string(bytes: &[u8]) -> Vec<u8> {
+        // No unused bits.
+        der(BIT_STRING, &[&[0], bytes].concat())
+    }
+
+    fn pem(label: &str, der: &[u8]) -> String {
+        let mut pem = format!("-----BEGIN {}-----\n", label);
+        for line in base64::encode(der).as_bytes().chunks(64) {
+            pem.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+            pem.push('\n');
+        }
+        pem.push_str(&format!("-----END {}-----\n", label));
+        pem
+    }
+}
+
 mod inner {
     pub trait OneOrTuple {
         type Output;
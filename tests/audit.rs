@@ -0,0 +1,133 @@
+#![deny(warnings)]
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use warp::audit::{Event, Policy};
+use warp::Filter;
+
+#[derive(Clone)]
+struct User(String);
+
+fn sink() -> (
+    impl Fn(Event) -> futures::future::Ready<()> + Clone,
+    Arc<Mutex<Vec<Event>>>,
+) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    let f = move |event: Event| {
+        sink.lock().unwrap().push(event);
+        futures::future::ready(())
+    };
+    (f, events)
+}
+
+#[tokio::test]
+async fn records_and_redacts() {
+    let _ = pretty_env_logger::try_init();
+
+    let (sink, events) = sink();
+    let policy = Policy::new()
+        .route("/accounts/:id")
+        .actor(|user: &User| user.0.clone())
+        .request_header("x-request-id")
+        .request_header("authorization")
+        .response_header("x-account")
+        .query()
+        .request_body(1024)
+        .response_body(1024)
+        .redact("IBAN");
+    let route = warp::path!("accounts" / u32)
+        .and(warp::ext::provide(warp::auth::bearer().map(User)))
+        .and(warp::body::json())
+        .map(|id: u32, body: serde_json::Value| {
+            let reply = warp::reply::json(&json!({ "id": id, "iban": body["iban"] }));
+            warp::reply::with_header(reply, "x-account", id.to_string())
+        })
+        .with(warp::audit::wrap(sink, policy));
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/accounts/7?token=abc&view=full&password=x")
+        .header("authorization", "Bearer alice")
+        .header("x-request-id", "r1")
+        .json(&json!({ "owner": "alice", "iban": "FR76", "nested": [{ "password": "p" }] }))
+        .remote_addr("10.0.0.1:4000".parse().unwrap())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(res.body()).unwrap(),
+        json!({ "id": 7, "iban": "FR76" })
+    );
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    let mut json = events[0].to_json();
+    assert!(json["timestamp"].as_u64().unwrap() > 0);
+    json.as_object_mut().unwrap().remove("timestamp");
+    assert_eq!(
+        json,
+        json!({
+            "method": "PUT",
+            "route": "/accounts/:id",
+            "path": "/accounts/7",
+            "status": 200,
+            "actor": "alice",
+            "remote_ip": "10.0.0.1",
+            "request_headers": {
+                "authorization": "[REDACTED]",
+                "x-request-id": "r1",
+            },
+            "response_headers": { "x-account": "7" },
+            "query": { "token": "abc", "view": "full", "password": "[REDACTED]" },
+            "request_body": {
+                "owner": "alice",
+                "iban": "[REDACTED]",
+                "nested": [{ "password": "[REDACTED]" }],
+            },
+            "response_body": { "id": 7, "iban": "[REDACTED]" },
+        })
+    );
+}
+
+#[tokio::test]
+async fn records_rejections() {
+    let _ = pretty_env_logger::try_init();
+
+    let (sink, events) = sink();
+    let route = warp::path("admin")
+        .and(warp::auth::bearer())
+        .map(|_| "admin")
+        .with(warp::audit::wrap(sink, Policy::new()));
+
+    let res = warp::test::request().path("/admin").reply(&route).await;
+    assert_eq!(res.status(), 401);
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].route(), "/admin");
+    assert_eq!(events[0].status(), 401);
+    assert_eq!(events[0].actor(), None);
+    assert_eq!(events[0].request_body(), None);
+}
+
+#[tokio::test]
+async fn body_limits_and_capture() {
+    let _ = pretty_env_logger::try_init();
+
+    let (sink, events) = sink();
+    let policy = Policy::new()
+        .request_body(4)
+        .response_body(64)
+        .capture_body(|_, body| Some(json!(body.len())));
+    let route = warp::body::bytes()
+        .map(|body: bytes::Bytes| body.to_vec())
+        .with(warp::audit::wrap(sink, policy));
+
+    let res = warp::test::request().body("hello").reply(&route).await;
+    assert_eq!(res.body(), "hello");
+
+    let events = events.lock().unwrap();
+    assert_eq!(events[0].request_body(), None);
+    assert_eq!(events[0].response_body(), Some(&json!(5)));
+}
@@ -0,0 +1,248 @@
+#![deny(warnings)]
+use warp::Filter;
+
+#[tokio::test]
+async fn basic() {
+    let _ = pretty_env_logger::try_init();
+
+    let auth = warp::auth::basic("test realm");
+
+    // "user:pass"
+    let creds = warp::test::request()
+        .header("authorization", "Basic dXNlcjpwYXNz")
+        .filter(&auth)
+        .await
+        .unwrap();
+    assert_eq!(creds, ("user".to_owned(), "pass".to_owned()));
+
+    let route = auth.map(|user: String, _password: String| user);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(
+        res.headers()["www-authenticate"],
+        "Basic realm=\"test realm\""
+    );
+
+    let res = warp::test::request()
+        .header("authorization", "Bearer dXNlcjpwYXNz")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn bearer() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::auth::bearer();
+
+    let token = warp::test::request()
+        .header("authorization", "Bearer abc.def")
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(token, "abc.def");
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.headers()["www-authenticate"], "Bearer");
+}
+
+#[cfg(feature = "jwt")]
+mod jwt {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_derive::{Deserialize, Serialize};
+    use warp::auth::JwtValidation;
+    use warp::Filter;
+
+    const RSA_N: &str = "r9UZyDBnlHXS3DLskeHjjsiMIauIwyTJw0BM8z0rqdPoIkXLR6QM25Fubh_REbiEGfvUOsuC3ps8RSJTJJkaLWOIh22a8hYrU-IEsXXXD4XVNkRgIXR7NlB81aMyRa8flOA15A1CieXSVqU-Hpsui3GGzASy1CXkJEocttTW4dhaqgiKelSYSMdcOKwJgnHQh2kZ-uUgxZdQSnWuHCEGZdk-CS1enUzi74F5F3HYw4u7c5rSmbu5yTwNw9RSX5khwAEOynnv7JlRS6Qh-F4gtd1WZNmdGxmBZmWfdZgXZbXdKSsZq4i5CavZLrYVmlnUEJtHe_cPqRdIWW63ABznww";
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Claims {
+        sub: String,
+        aud: String,
+        exp: u64,
+    }
+
+    fn claims(aud: &str, exp: Duration) -> Claims {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        Claims {
+            sub: "user".to_owned(),
+            aud: aud.to_owned(),
+            exp: (now + exp).as_secs(),
+        }
+    }
+
+    fn hs256(claims: &Claims, secret: &[u8]) -> String {
+        jsonwebtoken::encode(
+            &Header::default(),
+            claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    fn rs256(claims: &Claims, kid: Option<&str>) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = kid.map(ToOwned::to_owned);
+        let key = EncodingKey::from_rsa_pem(include_bytes!("jwt/rsa.pem")).unwrap();
+        jsonwebtoken::encode(&header, claims, &key).unwrap()
+    }
+
+    async fn status<F>(token: &str, filter: &F) -> u16
+    where
+        F: Filter + 'static,
+        F::Extract: warp::Reply + Send,
+    {
+        warp::test::request()
+            .header("authorization", format!("Bearer {}", token))
+            .reply(filter)
+            .await
+            .status()
+            .as_u16()
+    }
+
+    #[tokio::test]
+    async fn hmac() {
+        let _ = pretty_env_logger::try_init();
+
+        let validation = JwtValidation::hmac(b"secret").audience("api");
+        let route = warp::auth::jwt::<Claims>(validation).map(|claims: Claims| claims.sub);
+
+        let token = hs256(&claims("api", Duration::from_secs(60)), b"secret");
+        let res = warp::test::request()
+            .header("authorization", format!("Bearer {}", token))
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body(), "user");
+
+        let res = warp::test::request().reply(&route).await;
+        assert_eq!(res.status(), 401);
+        assert_eq!(res.headers()["www-authenticate"], "Bearer");
+
+        // wrong secret
+        let token = hs256(&claims("api", Duration::from_secs(60)), b"other");
+        let res = warp::test::request()
+            .header("authorization", format!("Bearer {}", token))
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 401);
+        assert_eq!(
+            res.headers()["www-authenticate"],
+            "Bearer error=\"invalid_token\""
+        );
+
+        // wrong audience
+        let token = hs256(&claims("other", Duration::from_secs(60)), b"secret");
+        assert_eq!(status(&token, &route).await, 401);
+
+        // expired
+        let mut expired = claims("api", Duration::from_secs(0));
+        expired.exp -= 600;
+        let token = hs256(&expired, b"secret");
+        assert_eq!(status(&token, &route).await, 401);
+    }
+
+    #[tokio::test]
+    async fn rsa_pem() {
+        let _ = pretty_env_logger::try_init();
+
+        let public = include_bytes!("jwt/rsa.pub.pem");
+        let validation = JwtValidation::rsa_pem(public).unwrap();
+        let route = warp::auth::jwt::<Claims>(validation).map(|claims: Claims| claims.sub);
+
+        let token = rs256(&claims("api", Duration::from_secs(60)), None);
+        assert_eq!(status(&token, &route).await, 200);
+
+        // the public key can't be used as an HMAC secret
+        let token = hs256(&claims("api", Duration::from_secs(60)), public);
+        assert_eq!(status(&token, &route).await, 401);
+    }
+
+    #[tokio::test]
+    async fn jwks() {
+        let _ = pretty_env_logger::try_init();
+
+        let jwks = warp::path("jwks").map(|| {
+            warp::reply::json(&serde_json::json!({
+                "keys": [{
+                    "kty": "RSA",
+                    "kid": "k1",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "n": RSA_N,
+                    "e": "AQAB",
+                }]
+            }))
+        });
+        let (addr, server) = warp::serve(jwks).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let validation = JwtValidation::jwks(&format!("http://{}/jwks", addr));
+        let route = warp::auth::jwt::<Claims>(validation).map(|claims: Claims| claims.sub);
+
+        let token = rs256(&claims("api", Duration::from_secs(60)), Some("k1"));
+        assert_eq!(status(&token, &route).await, 200);
+
+        let token = rs256(&claims("api", Duration::from_secs(60)), Some("k2"));
+        assert_eq!(status(&token, &route).await, 401);
+    }
+
+    #[tokio::test]
+    async fn jwks_timeout() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Instant;
+
+        let _ = pretty_env_logger::try_init();
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let jwks = warp::path("jwks").and_then(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                tokio::time::delay_for(Duration::from_secs(60)).await;
+                Ok::<_, warp::Rejection>(warp::reply())
+            }
+        });
+        let (addr, server) = warp::serve(jwks).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let validation = JwtValidation::jwks(&format!("http://{}/jwks", addr))
+            .jwks_timeout(Duration::from_millis(100));
+        let route = warp::auth::jwt::<Claims>(validation).map(|claims: Claims| claims.sub);
+
+        // concurrent requests fetch the key set once
+        let token = rs256(&claims("api", Duration::from_secs(60)), Some("k1"));
+        let start = Instant::now();
+        let (a, b) = futures::future::join(status(&token, &route), status(&token, &route)).await;
+        assert_eq!((a, b), (401, 401));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn jwks_too_large() {
+        let _ = pretty_env_logger::try_init();
+
+        let jwks = warp::path("jwks").map(|| {
+            let padding = " ".repeat(2 * 1024 * 1024);
+            format!(
+                r#"{{"keys": [{{"kty": "RSA", "kid": "k1", "n": "{}", "e": "AQAB"}}]}}{}"#,
+                RSA_N, padding
+            )
+        });
+        let (addr, server) = warp::serve(jwks).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let validation = JwtValidation::jwks(&format!("http://{}/jwks", addr));
+        let route = warp::auth::jwt::<Claims>(validation).map(|claims: Claims| claims.sub);
+
+        let token = rs256(&claims("api", Duration::from_secs(60)), Some("k1"));
+        assert_eq!(status(&token, &route).await, 401);
+    }
+}
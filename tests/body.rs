@@ -35,6 +35,88 @@ async fn server_error_if_taking_body_multiple_times() {
     assert_eq!(res.body(), "Request body consumed multiple times");
 }
 
+#[tokio::test]
+async fn bytes_with_trailers() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::body::bytes_with_trailers().map(
+        |bytes: bytes::Bytes, trailers: Option<warp::http::HeaderMap>| {
+            assert_eq!(bytes, "hello");
+            // HTTP/1 requests never have trailers.
+            assert!(trailers.is_none());
+            warp::reply()
+        },
+    );
+
+    let res = warp::test::request().body("hello").reply(&route).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn bytes_with_trailers_http2() {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use warp::http::HeaderMap;
+    use warp::hyper::body::HttpBody;
+
+    // A request body sending trailers after its data.
+    struct Trailing {
+        data: Option<bytes::Bytes>,
+        trailers: Option<HeaderMap>,
+    }
+
+    impl HttpBody for Trailing {
+        type Data = bytes::Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _: &mut Context,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(self.data.take().map(Ok))
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _: &mut Context,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(self.trailers.take()))
+        }
+    }
+
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::body::bytes_with_trailers().map(
+        |bytes: bytes::Bytes, trailers: Option<HeaderMap>| {
+            let checksum = trailers
+                .as_ref()
+                .and_then(|trailers| trailers.get("x-checksum"))
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("none")
+                .to_owned();
+            format!("{:?} {}", bytes, checksum)
+        },
+    );
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let mut trailers = HeaderMap::new();
+    trailers.insert("x-checksum", "abc123".parse().unwrap());
+    let req = warp::http::Request::post(format!("http://{}/", addr))
+        .body(Trailing {
+            data: Some(bytes::Bytes::from("hello")),
+            trailers: Some(trailers),
+        })
+        .unwrap();
+    let client = warp::hyper::Client::builder()
+        .http2_only(true)
+        .build_http::<Trailing>();
+    let res = client.request(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "b\"hello\" abc123");
+}
+
 #[tokio::test]
 async fn content_length_limit() {
     let _ = pretty_env_logger::try_init();
@@ -106,6 +188,48 @@ async fn json_invalid() {
     assert_eq!(&res.body()[..prefix.len()], prefix);
 }
 
+#[tokio::test]
+async fn json_with_raw() {
+    let _ = pretty_env_logger::try_init();
+
+    let json = warp::body::json_with_raw::<Vec<i32>>();
+
+    let (vec, raw) = warp::test::request()
+        .header("content-type", "application/json")
+        .body("[1, 2,\n 3]")
+        .filter(&json)
+        .await
+        .unwrap();
+    assert_eq!(vec, &[1, 2, 3]);
+    assert_eq!(raw, "[1, 2,\n 3]");
+
+    let json = json.map(|vec: Vec<i32>, _| warp::reply::json(&vec));
+    let res = warp::test::request().body("lol#wat").reply(&json).await;
+    assert_eq!(res.status(), 400);
+    let res = warp::test::request()
+        .header("content-type", "text/xml")
+        .body("[3, 2, 1]")
+        .reply(&json)
+        .await;
+    assert_eq!(res.status(), 415);
+}
+
+#[tokio::test]
+async fn json_from_buf() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::body::aggregate().and_then(|buf| async move {
+        warp::body::json_from_buf::<Vec<i32>>(buf).map(|vec| warp::reply::json(&vec))
+    });
+
+    let res = warp::test::request().body("[1, 2, 3]").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "[1,2,3]");
+
+    let res = warp::test::request().body("[1, 2,").reply(&route).await;
+    assert_eq!(res.status(), 400);
+}
+
 #[test]
 fn json_size_of() {
     let json = warp::body::json::<Vec<i32>>();
@@ -182,6 +306,70 @@ async fn form_invalid() {
     assert_eq!(&res.body()[..prefix.len()], prefix);
 }
 
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn msgpack() {
+    let _ = pretty_env_logger::try_init();
+
+    let msgpack = warp::body::msgpack::<Vec<i32>>();
+    let body = rmp_serde::to_vec(&[1, 2, 3]).unwrap();
+
+    for content_type in &["application/msgpack", "application/x-msgpack"] {
+        let req = warp::test::request()
+            .header("content-type", *content_type)
+            .body(body.clone());
+        let vec = req.filter(&msgpack).await.unwrap();
+        assert_eq!(vec, &[1, 2, 3], "{}", content_type);
+    }
+
+    // the content-type is required
+    let res = warp::test::request()
+        .body(body.clone())
+        .reply(&msgpack.map(|_: Vec<i32>| warp::reply()))
+        .await;
+    assert_eq!(res.status(), 415);
+
+    let route = msgpack.map(|vec: Vec<i32>| warp::reply::msgpack(&vec));
+    let res = warp::test::request()
+        .header("content-type", "application/msgpack")
+        .body(body.clone())
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["content-type"], "application/msgpack");
+    assert_eq!(res.body(), &body[..]);
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn cbor() {
+    let _ = pretty_env_logger::try_init();
+
+    let cbor = warp::body::cbor::<Vec<i32>>();
+    let body = serde_cbor::to_vec(&[1, 2, 3]).unwrap();
+
+    let req = warp::test::request()
+        .header("content-type", "application/cbor")
+        .body(body.clone());
+    let vec = req.filter(&cbor).await.unwrap();
+    assert_eq!(vec, &[1, 2, 3]);
+
+    let res = warp::test::request()
+        .header("content-type", "application/cbor")
+        .body("not cbor")
+        .reply(&cbor.map(|_: Vec<i32>| warp::reply()))
+        .await;
+    assert_eq!(res.status(), 400);
+
+    let route = cbor.map(|vec: Vec<i32>| warp::reply::cbor(&vec));
+    let res = warp::test::request()
+        .header("content-type", "application/cbor")
+        .body(body.clone())
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["content-type"], "application/cbor");
+    assert_eq!(res.body(), &body[..]);
+}
+
 #[tokio::test]
 async fn stream() {
     let _ = pretty_env_logger::try_init();
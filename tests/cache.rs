@@ -0,0 +1,215 @@
+#![deny(warnings)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use warp::cache::{CachePolicy, MemoryStore};
+use warp::hyper::body::HttpBody;
+use warp::Filter;
+
+fn news(
+    count: Arc<AtomicUsize>,
+    policy: CachePolicy,
+    store: MemoryStore,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::path!("news" / String)
+        .and(warp::header::optional::<String>("accept-language"))
+        .map(move |topic: String, lang: Option<String>| {
+            let n = count.fetch_add(1, Ordering::SeqCst) + 1;
+            let body = format!("{} #{} {}", topic, n, lang.unwrap_or_default());
+            match topic.as_str() {
+                "private" => Box::new(warp::reply::with_header(body, "cache-control", "private"))
+                    as Box<dyn warp::Reply>,
+                "missing" => Box::new(warp::reply::with_status(
+                    body,
+                    warp::http::StatusCode::NOT_FOUND,
+                )),
+                _ => Box::new(body),
+            }
+        })
+        .with(warp::cache::wrap(policy, store))
+}
+
+#[tokio::test]
+async fn caches_get_responses() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let store = MemoryStore::new();
+    let route = news(count.clone(), CachePolicy::new(), store.clone());
+
+    let res = warp::test::request().path("/news/rust").reply(&route).await;
+    assert_eq!(res.body(), "rust #1 ");
+    assert!(res.headers().get("age").is_none());
+
+    let res = warp::test::request().path("/news/rust").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "rust #1 ");
+    assert_eq!(res.headers()["age"], "0");
+
+    // queries are part of the key
+    let res = warp::test::request()
+        .path("/news/rust?page=2")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "rust #2 ");
+
+    // other methods and authorized requests aren't cached
+    let res = warp::test::request()
+        .method("POST")
+        .path("/news/rust")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "rust #3 ");
+    let res = warp::test::request()
+        .path("/news/rust")
+        .header("authorization", "Bearer abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "rust #4 ");
+    let res = warp::test::request()
+        .path("/news/rust")
+        .header("cache-control", "no-cache")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "rust #5 ");
+
+    // neither are private or unsuccessful responses
+    for n in 6..8 {
+        let res = warp::test::request()
+            .path("/news/private")
+            .reply(&route)
+            .await;
+        assert_eq!(res.body(), &format!("private #{} ", n));
+    }
+    for n in 8..10 {
+        let res = warp::test::request()
+            .path("/news/missing")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.body(), &format!("missing #{} ", n));
+    }
+
+    assert_eq!(store.len(), 2);
+    assert_eq!(count.load(Ordering::SeqCst), 9);
+}
+
+#[tokio::test]
+async fn varies_on_headers() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let policy = CachePolicy::new().vary("accept-language");
+    let route = news(count.clone(), policy, MemoryStore::new());
+
+    for _ in 0..2 {
+        for (lang, n) in &[("en", 1), ("fr", 2)] {
+            let res = warp::test::request()
+                .path("/news/rust")
+                .header("accept-language", *lang)
+                .reply(&route)
+                .await;
+            assert_eq!(res.body(), &format!("rust #{} {}", n, lang));
+            assert_eq!(res.headers()["vary"], "accept-language");
+        }
+    }
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn expires_and_bounds() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let policy = CachePolicy::new().ttl(Duration::from_millis(50));
+    let route = news(count.clone(), policy, MemoryStore::new());
+
+    warp::test::request().path("/news/a").reply(&route).await;
+    let res = warp::test::request().path("/news/a").reply(&route).await;
+    assert_eq!(res.body(), "a #1 ");
+    tokio::time::delay_for(Duration::from_millis(100)).await;
+    let res = warp::test::request().path("/news/a").reply(&route).await;
+    assert_eq!(res.body(), "a #2 ");
+
+    // bodies larger than the policy allows aren't cached
+    let policy = CachePolicy::new().max_body_size(4);
+    let route = news(count.clone(), policy, MemoryStore::new());
+    warp::test::request().path("/news/a").reply(&route).await;
+    let res = warp::test::request().path("/news/a").reply(&route).await;
+    assert_eq!(res.body(), "a #4 ");
+
+    // the store evicts the oldest responses
+    let store = MemoryStore::new().max_entries(2);
+    let route = news(count.clone(), CachePolicy::new(), store.clone());
+    for path in &["/news/a", "/news/b", "/news/c"] {
+        warp::test::request().path(path).reply(&route).await;
+    }
+    assert_eq!(store.len(), 2);
+    let res = warp::test::request().path("/news/c").reply(&route).await;
+    assert_eq!(res.body(), "c #7 ");
+    let res = warp::test::request().path("/news/a").reply(&route).await;
+    assert_eq!(res.body(), "a #8 ");
+}
+
+#[tokio::test]
+async fn bypasses_cookies() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("me")
+        .and(warp::cookie("session"))
+        .map(|session: String| format!("hello {}", session))
+        .with(warp::cache::wrap(CachePolicy::new(), MemoryStore::new()));
+
+    let res = warp::test::request()
+        .path("/me")
+        .header("cookie", "session=alice")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "hello alice");
+    let res = warp::test::request()
+        .path("/me")
+        .header("cookie", "session=bob")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "hello bob");
+    let res = warp::test::request().path("/me").reply(&route).await;
+    assert_eq!(res.status(), 400);
+
+    // unless the policy varies on them
+    let count = Arc::new(AtomicUsize::new(0));
+    let policy = CachePolicy::new().vary("cookie");
+    let route = news(count.clone(), policy, MemoryStore::new());
+    for _ in 0..2 {
+        let res = warp::test::request()
+            .path("/news/rust")
+            .header("cookie", "session=alice")
+            .reply(&route)
+            .await;
+        assert_eq!(res.body(), "rust #1 ");
+    }
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn streams_large_bodies() {
+    let _ = pretty_env_logger::try_init();
+
+    let store = MemoryStore::new();
+    let policy = CachePolicy::new().max_body_size(1024);
+    let route = warp::path("feed")
+        .map(|| warp::reply::ndjson(futures::stream::repeat("an endless feed")))
+        .with(warp::cache::wrap(policy, store.clone()));
+
+    let res = warp::test::request()
+        .path("/feed")
+        .filter(&route)
+        .await
+        .unwrap();
+    let mut body = res.into_body();
+    let mut len = 0;
+    while len < 4096 {
+        len += body.data().await.unwrap().unwrap().len();
+    }
+    assert!(store.is_empty());
+}
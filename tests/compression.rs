@@ -0,0 +1,156 @@
+#![deny(warnings)]
+use warp::Filter;
+
+async fn encoding(accept: Option<&str>) -> Option<String> {
+    let route = warp::any().map(|| "hello").with(warp::compression::auto());
+
+    let mut req = warp::test::request();
+    if let Some(accept) = accept {
+        req = req.header("accept-encoding", accept);
+    }
+    let res = req.reply(&route).await;
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    res.headers()
+        .get("content-encoding")
+        .map(|value| value.to_str().unwrap().to_owned())
+}
+
+#[tokio::test]
+async fn auto_negotiates() {
+    let _ = pretty_env_logger::try_init();
+
+    assert_eq!(encoding(None).await, None);
+    assert_eq!(encoding(Some("identity")).await, None);
+    assert_eq!(encoding(Some("gzip")).await.unwrap(), "gzip");
+    assert_eq!(encoding(Some("gzip, deflate, br")).await.unwrap(), "br");
+    assert_eq!(encoding(Some("gzip, zstd")).await.unwrap(), "zstd");
+    assert_eq!(
+        encoding(Some("br;q=0.5, gzip;q=0.9, deflate"))
+            .await
+            .unwrap(),
+        "deflate"
+    );
+    assert_eq!(encoding(Some("GZIP;Q=0.2, br;q=0")).await.unwrap(), "gzip");
+    assert_eq!(encoding(Some("*;q=0.1, br;q=0")).await.unwrap(), "zstd");
+    assert_eq!(encoding(Some("br;q=0, *;q=0")).await, None);
+}
+
+#[tokio::test]
+async fn zstd() {
+    let route = warp::any().map(|| "hello").with(warp::compression::zstd());
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.headers()["content-encoding"], "zstd");
+    assert!(!res.headers().contains_key("content-length"));
+}
+
+#[tokio::test]
+async fn min_size() {
+    let route = warp::path::param()
+        .map(|len: usize| "a".repeat(len))
+        .with(warp::compression::gzip().min_size(100));
+
+    let res = warp::test::request().path("/99").reply(&route).await;
+    assert!(!res.headers().contains_key("content-encoding"));
+    assert_eq!(res.body().len(), 99);
+
+    let res = warp::test::request().path("/100").reply(&route).await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert!(res.body().len() < 100);
+}
+
+#[tokio::test]
+async fn content_types() {
+    let route = warp::path::param()
+        .map(|ty: String| {
+            let ty = ty.replace('-', "/");
+            warp::reply::with_header("a".repeat(1000), "content-type", ty)
+        })
+        .with(
+            warp::compression::brotli()
+                .level(warp::compression::Level::Best)
+                .content_types(vec!["text/*", "image/*"])
+                .exclude_content_types(vec!["image/jpeg"]),
+        );
+
+    let encoding = |ty: &'static str| {
+        let route = route.clone();
+        async move {
+            let res = warp::test::request()
+                .path(&format!("/{}", ty))
+                .reply(&route)
+                .await;
+            res.headers().contains_key("content-encoding")
+        }
+    };
+
+    assert!(encoding("text-html").await);
+    assert!(encoding("image-svg+xml").await);
+    assert!(!encoding("image-jpeg").await);
+    assert!(!encoding("application-octet-stream").await);
+}
+
+#[tokio::test]
+async fn already_encoded() {
+    let route = warp::any()
+        .map(|| warp::reply::with_header("hello", "content-encoding", "gzip"))
+        .with(warp::compression::zstd());
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert_eq!(res.body(), "hello");
+}
+
+// Poll the body for its next chunk, unless none is ready in time.
+async fn next_chunk(body: &mut warp::hyper::Body) -> Option<warp::hyper::body::Bytes> {
+    use warp::hyper::body::HttpBody;
+
+    let next = tokio::time::timeout(std::time::Duration::from_millis(100), body.data());
+    next.await.ok().flatten().map(Result::unwrap)
+}
+
+#[tokio::test]
+async fn flush() {
+    use std::io::Write;
+    use warp::compression::Flush;
+    use warp::Reply;
+
+    let _ = pretty_env_logger::try_init();
+
+    for &flush in &[Flush::WhenIdle, Flush::EveryChunk, Flush::End] {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Result<&str, warp::Error>>();
+        let rx = std::sync::Arc::new(std::sync::Mutex::new(Some(rx)));
+        let route = warp::any()
+            .map(move || {
+                let body = warp::hyper::Body::wrap_stream(rx.lock().unwrap().take().unwrap());
+                warp::http::Response::new(body)
+            })
+            .with(warp::compression::gzip().flush(flush));
+
+        let mut body = warp::test::request()
+            .filter(&route)
+            .await
+            .unwrap()
+            .into_response()
+            .into_body();
+        let mut decoded = flate2::write::GzDecoder::new(Vec::new());
+
+        tx.send(Ok("data: hello\n\n")).unwrap();
+        match next_chunk(&mut body).await {
+            Some(chunk) => {
+                assert_ne!(flush, Flush::End);
+                decoded.write_all(&chunk).unwrap();
+                decoded.flush().unwrap();
+                assert_eq!(decoded.get_ref(), b"data: hello\n\n");
+            }
+            None => assert_eq!(flush, Flush::End),
+        }
+
+        tx.send(Ok("data: world\n\n")).unwrap();
+        drop(tx);
+        while let Some(chunk) = next_chunk(&mut body).await {
+            decoded.write_all(&chunk).unwrap();
+        }
+        assert_eq!(decoded.finish().unwrap(), b"data: hello\n\ndata: world\n\n");
+    }
+}
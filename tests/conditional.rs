@@ -0,0 +1,288 @@
+#![deny(warnings)]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn updated() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+}
+
+fn route() -> impl Filter<
+    Extract = (warp::reply::Conditional<warp::reply::WithHeader<&'static str>>,),
+    Error = std::convert::Infallible,
+> + Clone {
+    warp::any().map(|| {
+        let reply = warp::reply::with_header("hello", "cache-control", "max-age=60");
+        warp::reply::conditional(Some("\"v1\""), Some(updated()), reply)
+    })
+}
+
+#[tokio::test]
+async fn unconditional() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request().reply(&route()).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["etag"], "\"v1\"");
+    assert_eq!(
+        res.headers()["last-modified"],
+        "Sun, 13 Sep 2020 12:26:40 GMT"
+    );
+    assert_eq!(res.body(), "hello");
+}
+
+#[tokio::test]
+async fn if_none_match() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .header("if-none-match", "\"v0\", W/\"v1\"")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(res.headers()["etag"], "\"v1\"");
+    assert_eq!(res.headers()["cache-control"], "max-age=60");
+    assert_eq!(res.body(), "");
+
+    let res = warp::test::request()
+        .header("if-none-match", "\"v0\"")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 200);
+
+    // takes precedence over if-modified-since
+    let res = warp::test::request()
+        .header("if-none-match", "\"v0\"")
+        .header("if-modified-since", "Sun, 13 Sep 2020 12:26:40 GMT")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("PUT")
+        .header("if-none-match", "*")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 412);
+}
+
+#[tokio::test]
+async fn if_modified_since() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .header("if-modified-since", "Sun, 13 Sep 2020 12:26:40 GMT")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(
+        res.headers()["last-modified"],
+        "Sun, 13 Sep 2020 12:26:40 GMT"
+    );
+
+    let res = warp::test::request()
+        .header("if-modified-since", "Sat, 12 Sep 2020 12:26:40 GMT")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn only_successful_replies() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        let reply = warp::reply::with_status("missing", StatusCode::NOT_FOUND);
+        warp::reply::conditional(Some("\"v1\""), None, reply)
+    });
+    let res = warp::test::request()
+        .header("if-none-match", "\"v1\"")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "missing");
+}
+
+#[tokio::test]
+async fn agrees_with_preconditions() {
+    let _ = pretty_env_logger::try_init();
+
+    // `*` matches any existing resource, even one without an `ETag`
+    let reply = warp::any().map(|| warp::reply::conditional(None, Some(updated()), "hello"));
+    let preconditions =
+        warp::conditional().map(|preconditions: warp::conditional::Preconditions| {
+            preconditions
+                .evaluate(None, Some(updated()))
+                .unwrap_or_else(|| warp::reply::Response::new("hello".into()))
+        });
+    for (method, status) in &[("GET", 304), ("HEAD", 304), ("PUT", 412)] {
+        let res = warp::test::request()
+            .method(method)
+            .header("if-none-match", "*")
+            .reply(&reply)
+            .await;
+        assert_eq!(res.status(), *status, "{}", method);
+        let res = warp::test::request()
+            .method(method)
+            .header("if-none-match", "*")
+            .reply(&preconditions)
+            .await;
+        assert_eq!(res.status(), *status, "{}", method);
+    }
+
+    // `If-Match` is evaluated too
+    let res = warp::test::request()
+        .header("if-match", "\"v0\"")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+fn put_route() -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::any()
+        .and(warp::path::param())
+        .and(warp::conditional())
+        .map(
+            |exists: bool, preconditions: warp::conditional::Preconditions| {
+                let failed = if exists {
+                    preconditions.evaluate(Some("\"v1\""), Some(updated()))
+                } else {
+                    preconditions.evaluate_missing()
+                };
+                failed.unwrap_or_else(|| warp::reply::Response::new("updated".into()))
+            },
+        )
+}
+
+#[tokio::test]
+async fn preconditions_if_match() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-match", "\"v0\", \"v1\"")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "updated");
+
+    // `If-Match` compares strongly.
+    for if_match in &["\"v0\"", "W/\"v1\""] {
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/true")
+            .header("if-match", *if_match)
+            .reply(&put_route())
+            .await;
+        assert_eq!(
+            res.status(),
+            StatusCode::PRECONDITION_FAILED,
+            "{}",
+            if_match
+        );
+        assert_eq!(res.headers()["etag"], "\"v1\"");
+        assert_eq!(res.body(), "");
+    }
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/false")
+        .header("if-match", "*")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn preconditions_if_unmodified_since() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-unmodified-since", "Sun, 13 Sep 2020 12:26:40 GMT")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-unmodified-since", "Sat, 12 Sep 2020 12:26:40 GMT")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    // `If-Match` takes precedence.
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-match", "\"v1\"")
+        .header("if-unmodified-since", "Sat, 12 Sep 2020 12:26:40 GMT")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn preconditions_if_none_match() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .path("/true")
+        .header("if-none-match", "W/\"v1\"")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()["etag"], "\"v1\"");
+    assert_eq!(
+        res.headers()["last-modified"],
+        "Sun, 13 Sep 2020 12:26:40 GMT"
+    );
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-none-match", "*")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    // Create only if missing.
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/false")
+        .header("if-none-match", "*")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn preconditions_if_modified_since() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .path("/true")
+        .header("if-modified-since", "Sun, 13 Sep 2020 12:26:40 GMT")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    // Only for safe methods.
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/true")
+        .header("if-modified-since", "Sun, 13 Sep 2020 12:26:40 GMT")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .path("/true")
+        .reply(&put_route())
+        .await;
+    assert_eq!(res.status(), 200);
+}
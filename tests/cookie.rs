@@ -48,3 +48,80 @@ async fn missing() {
     assert_eq!(res.status(), 400);
     assert_eq!(res.body(), "Missing request cookie \"foo\"");
 }
+
+#[tokio::test]
+async fn set_cookie() {
+    use warp::Filter;
+
+    let route = warp::any().map(|| {
+        let session = warp::reply::set_cookie("session", "abc")
+            .same_site(warp::cookie::SameSite::Strict)
+            .http_only(true);
+        let theme = warp::reply::set_cookie("theme", "").expire();
+        warp::reply::with_cookie(warp::reply::with_cookie(warp::reply(), session), theme)
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    let cookies = res
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        cookies,
+        [
+            "session=abc; Path=/; HttpOnly; SameSite=Strict",
+            "theme=; Path=/; Max-Age=0",
+        ]
+    );
+}
+
+#[cfg(feature = "secure-cookies")]
+mod secure {
+    use warp::cookie::Key;
+    use warp::Filter;
+
+    async fn roundtrip<F>(filter: F, set: warp::reply::SetCookie) -> Option<String>
+    where
+        F: Filter<Extract = (String,), Error = warp::Rejection> + 'static,
+    {
+        let res = warp::test::request()
+            .reply(&warp::any().map(move || warp::reply::with_cookie(warp::reply(), set.clone())))
+            .await;
+        let header = res.headers()["set-cookie"].to_str().unwrap();
+        let pair = header.split(';').next().unwrap().to_owned();
+        warp::test::request()
+            .header("cookie", pair)
+            .filter(&filter)
+            .await
+            .ok()
+    }
+
+    #[tokio::test]
+    async fn signed() {
+        let key = Key::generate();
+        let filter = warp::cookie::signed("user", key.clone());
+
+        let set = warp::reply::set_cookie("user", "sean").signed(&key);
+        assert_eq!(roundtrip(filter.clone(), set).await.unwrap(), "sean");
+
+        let req = warp::test::request().header("cookie", "user=sean");
+        assert!(!req.matches(&filter).await);
+
+        let other = warp::reply::set_cookie("user", "sean").signed(&Key::generate());
+        assert_eq!(roundtrip(filter, other).await, None);
+    }
+
+    #[tokio::test]
+    async fn private() {
+        let key = Key::from_bytes(&[7; 64]);
+        let filter = warp::cookie::private("user", key.clone());
+
+        let set = warp::reply::set_cookie("user", "sean").private(&key);
+        assert_eq!(roundtrip(filter.clone(), set).await.unwrap(), "sean");
+
+        let req = warp::test::request().header("cookie", "user=sean");
+        assert!(!req.matches(&filter).await);
+    }
+}
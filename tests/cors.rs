@@ -52,6 +52,81 @@ async fn origin_not_allowed() {
     assert_eq!(res.status(), 403);
 }
 
+#[tokio::test]
+async fn allow_origin_fn() {
+    let cors = warp::cors()
+        .allow_methods(&[Method::DELETE])
+        .allow_origin("https://hyper.rs")
+        .allow_origin_fn(|origin| origin.ends_with(".warp.rs"));
+
+    let route = warp::any().map(warp::reply).with(cors);
+
+    for origin in &["https://hyper.rs", "https://docs.warp.rs"] {
+        let res = warp::test::request()
+            .method("OPTIONS")
+            .header("origin", *origin)
+            .header("access-control-request-method", "DELETE")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["access-control-allow-origin"], *origin);
+    }
+
+    let res = warp::test::request()
+        .header("origin", "https://warp.rs.evil")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn allow_origin_fn_async() {
+    let cors = warp::cors()
+        .allow_methods(&[Method::DELETE])
+        .allow_origin_fn_async(|origin| async move {
+            tokio::time::delay_for(std::time::Duration::from_millis(1)).await;
+            origin == "https://hyper.rs"
+        });
+
+    let route = warp::path("hello").map(|| "world").with(cors);
+
+    let res = warp::test::request()
+        .path("/hello")
+        .header("origin", "https://hyper.rs")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["access-control-allow-origin"],
+        "https://hyper.rs"
+    );
+    assert_eq!(res.body(), "world");
+
+    // The wrapped filter still runs after the decision.
+    let res = warp::test::request()
+        .path("/bye")
+        .header("origin", "https://hyper.rs")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = warp::test::request()
+        .method("OPTIONS")
+        .header("origin", "https://hyper.rs")
+        .header("access-control-request-method", "DELETE")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("OPTIONS")
+        .header("origin", "https://warp.rs")
+        .header("access-control-request-method", "DELETE")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 403);
+}
+
 #[tokio::test]
 async fn headers_not_exposed() {
     let cors = warp::cors()
@@ -182,3 +257,91 @@ async fn with_log() {
 
     assert_eq!(res.status(), 200);
 }
+
+#[tokio::test]
+async fn reflect_request_headers() {
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_methods(&[Method::POST])
+        .reflect_request_headers();
+
+    let route = warp::any().map(warp::reply).with(cors);
+
+    let res = warp::test::request()
+        .method("OPTIONS")
+        .header("origin", "https://hyper.rs")
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "x-foo, x-bar")
+        .reply(&route)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["access-control-allow-headers"],
+        "x-foo, x-bar"
+    );
+    assert_eq!(res.headers()["vary"], "access-control-request-headers");
+}
+
+#[tokio::test]
+async fn expose_any_header() {
+    let route = warp::any()
+        .map(|| warp::reply::with_header("ok", "x-foo", "bar"))
+        .with(warp::cors().allow_any_origin().expose_any_header());
+
+    let res = warp::test::request()
+        .header("origin", "https://hyper.rs")
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["access-control-expose-headers"], "*");
+
+    let route = warp::any()
+        .map(|| warp::reply::with_header("ok", "x-foo", "bar"))
+        .with(
+            warp::cors()
+                .allow_any_origin()
+                .allow_credentials(true)
+                .expose_any_header(),
+        );
+
+    let res = warp::test::request()
+        .header("origin", "https://hyper.rs")
+        .reply(&route)
+        .await;
+    let exposed = res.headers()["access-control-expose-headers"]
+        .to_str()
+        .unwrap();
+    assert!(
+        exposed.split(", ").any(|name| name == "x-foo"),
+        "{}",
+        exposed
+    );
+}
+
+#[tokio::test]
+async fn allow_private_network() {
+    let preflight = || {
+        warp::test::request()
+            .method("OPTIONS")
+            .header("origin", "https://hyper.rs")
+            .header("access-control-request-method", "GET")
+            .header("access-control-request-private-network", "true")
+    };
+
+    let cors = warp::cors().allow_any_origin().allow_method(Method::GET);
+    let route = warp::any().map(warp::reply).with(cors.clone());
+    let res = preflight().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert!(!res
+        .headers()
+        .contains_key("access-control-allow-private-network"));
+
+    let route = warp::any()
+        .map(warp::reply)
+        .with(cors.allow_private_network(true));
+    let res = preflight().reply(&route).await;
+    assert_eq!(
+        res.headers()["access-control-allow-private-network"],
+        "true"
+    );
+}
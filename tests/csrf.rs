@@ -0,0 +1,134 @@
+#![deny(warnings)]
+use warp::csrf::{Csrf, CsrfToken, InvalidCsrfToken};
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let form = warp::get()
+        .and(warp::csrf::token())
+        .map(|token: CsrfToken| token.to_string());
+    let submit = warp::post().map(|| "submitted");
+    form.or(submit)
+}
+
+#[tokio::test]
+async fn double_submit() {
+    let _ = pretty_env_logger::try_init();
+    let route = routes().with(Csrf::double_submit());
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    let cookie = res.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.ends_with("; Path=/; SameSite=Strict"), "{}", cookie);
+    let token = std::str::from_utf8(res.body()).unwrap().to_owned();
+    assert_eq!(
+        cookie,
+        format!("csrf_token={}; Path=/; SameSite=Strict", token)
+    );
+
+    // An existing token is kept.
+    let res = warp::test::request()
+        .header("cookie", format!("csrf_token={}", token))
+        .reply(&route)
+        .await;
+    assert!(!res.headers().contains_key("set-cookie"));
+    assert_eq!(res.body(), token.as_str());
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", format!("csrf_token={}", token))
+        .header("x-csrf-token", token.as_str())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "submitted");
+}
+
+#[tokio::test]
+async fn rejects_forged() {
+    let _ = pretty_env_logger::try_init();
+    let route = routes().with(Csrf::double_submit());
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", "csrf_token=abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", "csrf_token=abc")
+        .header("x-csrf-token", "abd")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // No token was issued yet, so none can match.
+    let rejection = warp::test::request()
+        .method("POST")
+        .header("x-csrf-token", "")
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(rejection.find::<InvalidCsrfToken>().is_some());
+}
+
+#[tokio::test]
+async fn custom_header() {
+    let route = routes().with(Csrf::double_submit().name("xsrf").header("x-xsrf"));
+
+    let res = warp::test::request()
+        .method("PUT")
+        .header("cookie", "xsrf=abc")
+        .header("x-xsrf", "abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", "xsrf=abc")
+        .header("x-xsrf", "abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+}
+
+#[cfg(feature = "session")]
+#[tokio::test]
+async fn session() {
+    use warp::session::{MemoryStore, Sessions};
+
+    let _ = pretty_env_logger::try_init();
+    let route = routes()
+        .with(Csrf::session())
+        .with(Sessions::new(MemoryStore::new()));
+
+    let res = warp::test::request().reply(&route).await;
+    let cookie = res.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with("session="), "{}", cookie);
+    let session = cookie.split(';').next().unwrap().to_owned();
+    let token = std::str::from_utf8(res.body()).unwrap().to_owned();
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", session.as_str())
+        .header("x-csrf-token", token.as_str())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("cookie", session.as_str())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // The session is required.
+    let res = warp::test::request()
+        .reply(&routes().with(Csrf::session()))
+        .await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,80 @@
+#![deny(warnings)]
+use futures::stream;
+use serde_derive::Serialize;
+use warp::Filter;
+
+#[derive(Serialize)]
+struct User {
+    id: u32,
+    name: &'static str,
+}
+
+#[tokio::test]
+async fn csv() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        let users = stream::iter(vec![
+            User {
+                id: 1,
+                name: "alice",
+            },
+            User {
+                id: 2,
+                name: "bob, \"the builder\"",
+            },
+        ]);
+        warp::reply::csv(users)
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/csv; charset=utf-8");
+    assert!(!res.headers().contains_key("content-disposition"));
+    assert_eq!(
+        res.body(),
+        "id,name\n1,alice\n2,\"bob, \"\"the builder\"\"\"\n"
+    );
+}
+
+#[tokio::test]
+async fn csv_empty() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| warp::reply::csv(stream::iter(Vec::<User>::new())));
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "");
+}
+
+#[tokio::test]
+async fn csv_filename() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        warp::reply::csv(stream::iter(vec![User {
+            id: 1,
+            name: "alice",
+        }]))
+        .filename("users.csv")
+    });
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"users.csv\""
+    );
+
+    let route = warp::any().map(|| {
+        warp::reply::csv(stream::iter(vec![User {
+            id: 1,
+            name: "alice",
+        }]))
+        .filename("r\u{e9}sum\u{e9} \"2020\".csv")
+    });
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"r_sum_ \\\"2020\\\".csv\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%222020%22.csv"
+    );
+}
@@ -0,0 +1,95 @@
+#![deny(warnings)]
+use std::time::Duration;
+
+use warp::deadline::Deadline;
+use warp::Filter;
+
+#[tokio::test]
+async fn enforces_deadline() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path!("sleep" / u64)
+        .and(warp::deadline::deadline())
+        .and_then(|millis: u64, deadline: Deadline| async move {
+            let token = deadline.token().clone();
+            tokio::time::delay_for(Duration::from_millis(millis)).await;
+            Ok::<_, warp::Rejection>(format!("{}", token.is_cancelled()))
+        })
+        .with(warp::deadline::propagate());
+
+    let res = warp::test::request()
+        .path("/sleep/0")
+        .header("x-request-timeout", "1000")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "false");
+
+    let res = warp::test::request()
+        .path("/sleep/1000")
+        .header("x-request-timeout", "20m")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 504);
+
+    // without a deadline, `deadline()` rejects
+    let res = warp::test::request().path("/sleep/0").reply(&route).await;
+    assert_eq!(res.status(), 500);
+}
+
+#[tokio::test]
+async fn default_and_max_timeouts() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::deadline::optional()
+        .map(|deadline: Option<Deadline>| match deadline {
+            Some(deadline) => {
+                let remaining = deadline.remaining();
+                assert!(!deadline.is_expired());
+                format!("{}", remaining.as_millis().div_ceil(1000))
+            }
+            None => "none".to_owned(),
+        })
+        .with(
+            warp::deadline::propagate()
+                .header("grpc-timeout")
+                .default_timeout(Duration::from_secs(5))
+                .max_timeout(Duration::from_secs(30)),
+        );
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "5");
+    let res = warp::test::request()
+        .header("grpc-timeout", "2S")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "2");
+    let res = warp::test::request()
+        .header("grpc-timeout", "1H")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "30");
+    // invalid values fall back to the default
+    let res = warp::test::request()
+        .header("grpc-timeout", "123456789S")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "5");
+
+    let route = warp::deadline::optional()
+        .map(|deadline: Option<Deadline>| format!("{}", deadline.is_some()))
+        .with(warp::deadline::propagate());
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "false");
+}
+
+#[tokio::test]
+async fn cancellation_token() {
+    let token = warp::deadline::CancellationToken::new();
+    let waiter = token.clone();
+    let wait = tokio::spawn(async move { waiter.cancelled().await });
+    assert!(!token.is_cancelled());
+    token.cancel();
+    wait.await.unwrap();
+    assert!(token.is_cancelled());
+}
@@ -0,0 +1,88 @@
+#![deny(warnings)]
+use include_dir::{include_dir, Dir};
+
+static ASSETS: Dir = include_dir!("examples/dir");
+
+#[tokio::test]
+async fn embedded() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::fs::embedded(&ASSETS);
+
+    let res = warp::test::request()
+        .path("/another.html")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/html");
+    assert_eq!(
+        res.body(),
+        &include_bytes!("../examples/dir/another.html")[..]
+    );
+    let etag = res.headers()["etag"].clone();
+
+    // the same tag as the file on disk
+    let file = warp::fs::file("examples/dir/another.html").etag(warp::fs::ETagStrategy::Strong);
+    let res = warp::test::request().reply(&file).await;
+    assert_eq!(res.headers()["etag"], etag);
+
+    let res = warp::test::request()
+        .path("/another.html")
+        .header("if-none-match", etag.clone())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(res.headers()["etag"], etag);
+    assert_eq!(res.body(), "");
+
+    let res = warp::test::request()
+        .path("/another.html")
+        .header("range", "bytes=0-4")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 206);
+    assert_eq!(
+        res.body(),
+        &include_bytes!("../examples/dir/another.html")[..5]
+    );
+}
+
+#[tokio::test]
+async fn embedded_index() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::fs::embedded(&ASSETS);
+
+    let res = warp::test::request().path("/").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.body(),
+        &include_bytes!("../examples/dir/index.html")[..]
+    );
+}
+
+#[tokio::test]
+async fn embedded_not_found() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::fs::embedded(&ASSETS);
+
+    let res = warp::test::request()
+        .path("/missing.html")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = warp::test::request()
+        .path("/../Cargo.toml")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/index.html")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 405);
+}
@@ -26,3 +26,84 @@ async fn get_missing() {
     assert_eq!(res.status(), 500);
     assert_eq!(res.body(), "Missing request extension");
 }
+
+#[tokio::test]
+async fn insert_and_provide() {
+    let route = warp::ext::insert(Ext1(1))
+        .and(warp::ext::get::<Ext1>())
+        .map(|e: Ext1| e.0.to_string());
+    let res = warp::test::request()
+        .extension(Ext1(55))
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "1");
+
+    let user = warp::header::<i32>("x-user").map(Ext1);
+    let route = warp::ext::provide(user)
+        .and(warp::ext::get::<Ext1>())
+        .map(|e: Ext1| e.0.to_string());
+    let res = warp::test::request()
+        .header("x-user", "7")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "7");
+
+    // rejections of the provider are kept
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn set_from_handlers() {
+    let route = warp::any()
+        .and_then(|| async {
+            warp::ext::set(Ext1(3));
+            Ok::<_, warp::Rejection>(())
+        })
+        .untuple_one()
+        .and(warp::ext::get::<Ext1>())
+        .map(|e: Ext1| e.0.to_string());
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "3");
+
+    // and from wraps
+    let route = warp::any()
+        .and(warp::ext::optional::<Ext1>())
+        .map(|e: Option<Ext1>| format!("{:?}", e))
+        .with(warp::wrap_fn(|filter| {
+            warp::any()
+                .map(|| warp::ext::set(Ext1(4)))
+                .untuple_one()
+                .and(filter)
+        }));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "Some(Ext1(4))");
+}
+
+#[test]
+#[should_panic(expected = "outside of the filtering of a request")]
+fn set_outside_of_a_request() {
+    warp::ext::set(Ext1(1));
+}
+
+#[tokio::test]
+async fn connection_info() {
+    use warp::hyper::service::Service;
+
+    let route = warp::ext::connection::<Ext1>().map(|e: Ext1| e.0.to_string());
+    let mut make_svc = warp::service_with_info(route, |conn: &i32| Ext1(*conn));
+
+    let mut svc = make_svc.call(&7).await.unwrap();
+    let req = warp::http::Request::new(warp::hyper::Body::empty());
+    let res = svc.call(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "7");
+
+    // only values given with `service_with_info` are extracted
+    let res = warp::test::request()
+        .extension(Ext1(55))
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 500);
+}
@@ -79,6 +79,59 @@ async fn map() {
     assert_eq!(resp.status(), 200);
 }
 
+#[tokio::test]
+async fn then() {
+    let _ = pretty_env_logger::try_init();
+
+    let double = warp::path::param().then(|n: u32| async move {
+        tokio::time::delay_for(std::time::Duration::from_millis(1)).await;
+        n * 2
+    });
+
+    let req = warp::test::request().path("/21");
+    assert_eq!(req.filter(&double).await.unwrap(), 42);
+
+    // rejections of the filter are kept
+    let req = warp::test::request().path("/nope");
+    assert!(!req.matches(&double).await);
+}
+
+#[tokio::test]
+async fn optional() {
+    let _ = pretty_env_logger::try_init();
+
+    let page = warp::header::<u32>("x-page").optional();
+
+    let req = warp::test::request().header("x-page", "3");
+    assert_eq!(req.filter(&page).await.unwrap(), Some(3));
+
+    let req = warp::test::request();
+    assert_eq!(req.filter(&page).await.unwrap(), None);
+
+    // the path matched before rejecting is given back
+    let id = warp::path("a")
+        .and(warp::path::param::<u32>())
+        .optional()
+        .and(warp::path::tail());
+    let req = warp::test::request().path("/a/b");
+    let (id, tail) = req.filter(&id).await.unwrap();
+    assert_eq!(id, None);
+    assert_eq!(tail.as_str(), "a/b");
+}
+
+#[tokio::test]
+async fn or_default() {
+    let _ = pretty_env_logger::try_init();
+
+    let page = warp::header::<u32>("x-page").or_default();
+
+    let req = warp::test::request().header("x-page", "3");
+    assert_eq!(req.filter(&page).await.unwrap(), 3);
+
+    let req = warp::test::request().header("x-page", "three");
+    assert_eq!(req.filter(&page).await.unwrap(), 0);
+}
+
 #[tokio::test]
 async fn or() {
     let _ = pretty_env_logger::try_init();
@@ -91,6 +144,50 @@ async fn or() {
     let _: Result<_, Infallible> = warp::test::request().filter(&f).await;
 }
 
+#[tokio::test]
+async fn and_then_typed() {
+    let _ = pretty_env_logger::try_init();
+
+    #[derive(Debug)]
+    enum ApiError {
+        Teapot,
+    }
+
+    impl warp::Reply for ApiError {
+        fn into_response(self) -> warp::reply::Response {
+            match self {
+                ApiError::Teapot => {
+                    warp::reply::with_status("short and stout", warp::http::StatusCode::IM_A_TEAPOT)
+                        .into_response()
+                }
+            }
+        }
+    }
+
+    let a = warp::path::param::<u32>().and_then_typed(|n: u32| async move {
+        if n == 418 {
+            Err(ApiError::Teapot)
+        } else {
+            Ok(n.to_string())
+        }
+    });
+    // a typed error is a reply, not a rejection, so `b` is not tried
+    let b = warp::any().map(|| "fallback");
+    let f = a.or(b);
+
+    let resp = warp::test::request().path("/7").reply(&f).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.body(), "7");
+
+    let resp = warp::test::request().path("/418").reply(&f).await;
+    assert_eq!(resp.status(), 418);
+    assert_eq!(resp.body(), "short and stout");
+
+    // rejections of the inner filter still propagate
+    let resp = warp::test::request().path("/nope").reply(&f).await;
+    assert_eq!(resp.body(), "fallback");
+}
+
 #[tokio::test]
 async fn or_else() {
     let _ = pretty_env_logger::try_init();
@@ -133,6 +230,40 @@ async fn recover() {
     let _: Result<_, Infallible> = warp::test::request().filter(&f).await;
 }
 
+#[tokio::test]
+async fn recover_scoped() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = warp::path("api")
+        .and(warp::path("users"))
+        .map(|| "users")
+        .recover_scoped(|_| async move { Ok::<_, Infallible>("api error") });
+    let web = warp::path("web")
+        .map(|| "web")
+        .recover_scoped(|_| async move { Ok::<_, Infallible>("web error") });
+    let rethrow = warp::path("api")
+        .and(warp::path("admin"))
+        .map(|| "admin")
+        .recover_scoped(|err| async move { Err::<String, _>(err) });
+    let f = rethrow.or(api).or(web);
+
+    let resp = warp::test::request().path("/api/users").reply(&f).await;
+    assert_eq!(resp.body(), "users");
+
+    // in scope of `api`, after `rethrow` passed it on
+    let resp = warp::test::request().path("/api/nope").reply(&f).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.body(), "api error");
+
+    // not in scope of `api`, reaches `web`
+    let resp = warp::test::request().path("/web").reply(&f).await;
+    assert_eq!(resp.body(), "web");
+
+    // in no scope at all
+    let resp = warp::test::request().path("/other").reply(&f).await;
+    assert_eq!(resp.status(), 404);
+}
+
 #[tokio::test]
 async fn unify() {
     let _ = pretty_env_logger::try_init();
@@ -146,6 +277,153 @@ async fn unify() {
     assert_eq!(ex, 1);
 }
 
+#[tokio::test]
+async fn unify_or() {
+    let _ = pretty_env_logger::try_init();
+
+    let a = warp::path::param::<u32>();
+    let b = warp::path("id").and(warp::path::param::<u32>());
+    let f = a.unify_or(b);
+
+    let ex = warp::test::request().path("/1").filter(&f).await.unwrap();
+    assert_eq!(ex, 1);
+
+    let ex = warp::test::request()
+        .path("/id/2")
+        .filter(&f)
+        .await
+        .unwrap();
+    assert_eq!(ex, 2);
+
+    // works with multiple extracted values too
+    let a = warp::path::param::<u32>().and(warp::path::param::<String>());
+    let b = warp::any()
+        .map(|| (0u32, String::from("none")))
+        .untuple_one();
+    let f = a.unify_or(b);
+
+    let ex = warp::test::request()
+        .path("/5/five")
+        .filter(&f)
+        .await
+        .unwrap();
+    assert_eq!(ex, (5, String::from("five")));
+
+    let ex = warp::test::request().filter(&f).await.unwrap();
+    assert_eq!(ex, (0, String::from("none")));
+}
+
+#[tokio::test]
+async fn wrap_fn() {
+    let _ = pretty_env_logger::try_init();
+
+    let versioned = warp::wrap_fn(|filter| warp::path("v1").and(filter));
+    let route = warp::path("users").map(|| "users").with(versioned);
+
+    let res = warp::test::request().path("/v1/users").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "users");
+
+    let res = warp::test::request().path("/users").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn map_body() {
+    use futures::StreamExt;
+    use warp::hyper::Body;
+
+    let _ = pretty_env_logger::try_init();
+
+    let upper = |headers: &mut warp::http::HeaderMap, body: Body| {
+        headers.insert("x-upper", warp::http::HeaderValue::from_static("1"));
+        Body::wrap_stream(
+            body.map(|chunk| chunk.map(|chunk| bytes::Bytes::from(chunk.to_ascii_uppercase()))),
+        )
+    };
+
+    let route = warp::header::optional::<String>("x-upper")
+        .and(warp::header::optional::<u64>("content-length"))
+        .and(warp::body::bytes())
+        .map(
+            |upper: Option<String>, len: Option<u64>, body: bytes::Bytes| {
+                format!(
+                    "{:?} {:?} {}",
+                    upper,
+                    len,
+                    std::str::from_utf8(&body).unwrap()
+                )
+            },
+        )
+        .with(warp::map_request_body(upper));
+    let res = warp::test::request()
+        .method("POST")
+        .body("hello")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "Some(\"1\") None HELLO");
+
+    let route = warp::any()
+        .map(|| "hello")
+        .with(warp::map_response_body(upper));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "HELLO");
+    assert_eq!(res.headers()["x-upper"], "1");
+    assert!(res.headers().get("content-length").is_none());
+
+    // rejections aren't replies yet
+    let route = warp::path("a")
+        .map(|| "hello")
+        .with(warp::map_response_body(upper));
+    let res = warp::test::request().path("/b").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn singleflight() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let _ = pretty_env_logger::try_init();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let route = warp::path!("report" / u32)
+        .and_then(move |year: u32| {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                tokio::time::delay_for(Duration::from_millis(50)).await;
+                Ok::<_, Infallible>(format!("{} #{}", year, n))
+            }
+        })
+        .singleflight(|uri: &warp::http::Uri, _: &warp::http::HeaderMap| uri.path().to_owned());
+
+    let req = || warp::test::request().path("/report/2020").reply(&route);
+    let (a, b, c) = tokio::join!(req(), req(), req());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(a.body(), "2020 #1");
+    assert_eq!(b.body(), "2020 #1");
+    assert_eq!(c.body(), "2020 #1");
+
+    // other keys, later requests and other methods aren't coalesced
+    let (a, b) = tokio::join!(
+        warp::test::request().path("/report/2021").reply(&route),
+        warp::test::request()
+            .method("POST")
+            .path("/report/2021")
+            .reply(&route),
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert_ne!(a.body(), b.body());
+
+    let res = req().await;
+    assert_eq!(res.body(), "2020 #4");
+
+    let res = warp::test::request().path("/report/x").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
 #[should_panic]
 #[tokio::test]
 async fn nested() {
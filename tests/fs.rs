@@ -74,6 +74,179 @@ async fn dir_not_found() {
     assert_eq!(res.status(), 404);
 }
 
+#[tokio::test]
+async fn dir_fallback() {
+    let _ = pretty_env_logger::try_init();
+
+    let file = warp::fs::dir("examples").fallback("todos.rs");
+    let contents = fs::read("examples/todos.rs").expect("fs::read");
+
+    // an existing file is served as is
+    let res = warp::test::request().path("/hello.rs").reply(&file).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), &fs::read("examples/hello.rs").unwrap()[..]);
+
+    let res = warp::test::request()
+        .path("/client/side/route")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), &contents[..]);
+}
+
+#[tokio::test]
+async fn dir_not_found_page() {
+    let _ = pretty_env_logger::try_init();
+
+    let file = warp::fs::dir("examples").not_found("hello.rs");
+    let contents = fs::read("examples/hello.rs").expect("fs::read");
+
+    let res = warp::test::request().path("/missing.rs").reply(&file).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), &contents[..]);
+
+    // conditionals don't apply to the not found page
+    let res = warp::test::request()
+        .path("/missing.rs")
+        .header("range", "bytes=0-1")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), &contents[..]);
+
+    let res = warp::test::request()
+        .path("/../Cargo.toml")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), &contents[..]);
+}
+
+#[tokio::test]
+async fn dir_cache_control() {
+    let _ = pretty_env_logger::try_init();
+
+    let file = warp::fs::dir("examples")
+        .cache_control("index.html", "no-cache")
+        .cache_control("dir/**", "max-age=60")
+        .cache_control("*.rs", "max-age=3600");
+
+    let res = warp::test::request().path("/dir").reply(&file).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["cache-control"], "no-cache");
+
+    let res = warp::test::request()
+        .path("/dir/another.html")
+        .reply(&file)
+        .await;
+    assert_eq!(res.headers()["cache-control"], "max-age=60");
+
+    let res = warp::test::request().path("/hello.rs").reply(&file).await;
+    assert_eq!(res.headers()["cache-control"], "max-age=3600");
+
+    let res = warp::test::request().path("/README.md").reply(&file).await;
+    assert_eq!(res.status(), 200);
+    assert!(!res.headers().contains_key("cache-control"));
+}
+
+#[tokio::test]
+async fn dir_policy() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = std::env::temp_dir().join(format!("warp-policy-{}", std::process::id()));
+    fs::create_dir_all(dir.join(".git")).expect("create temp dir");
+    fs::write(dir.join(".env"), "SECRET=1").unwrap();
+    fs::write(dir.join(".git/config"), "[core]").unwrap();
+    fs::write(dir.join("app.JS"), "app").unwrap();
+    fs::write(dir.join("notes.txt"), "notes").unwrap();
+
+    let route = warp::fs::dir(dir.clone())
+        .deny_dotfiles()
+        .allowed_extensions(&["js", ".txt"]);
+
+    for path in &["/.env", "/.git/config", "/%2Eenv", "/%2e%2e/Cargo.toml"] {
+        let res = warp::test::request().path(path).reply(&route).await;
+        assert_eq!(res.status(), 404, "{}", path);
+    }
+
+    let res = warp::test::request().path("/app.JS").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "app");
+
+    let route = warp::fs::dir(dir.clone()).allowed_extensions(&["js"]);
+    let res = warp::test::request().path("/notes.txt").reply(&route).await;
+    assert_eq!(res.status(), 404);
+
+    // without a policy, dotfiles are served
+    let route = warp::fs::dir(dir.clone());
+    let res = warp::test::request().path("/.env").reply(&route).await;
+    assert_eq!(res.status(), 200);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn dir_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let _ = pretty_env_logger::try_init();
+
+    let dir = std::env::temp_dir().join(format!("warp-symlinks-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("root")).expect("create temp dir");
+    fs::write(dir.join("secret.txt"), "secret").unwrap();
+    fs::write(dir.join("root/page.txt"), "page").unwrap();
+    symlink(dir.join("secret.txt"), dir.join("root/escape.txt")).unwrap();
+    symlink(dir.join("root/page.txt"), dir.join("root/inside.txt")).unwrap();
+
+    let route = warp::fs::dir(dir.join("root")).deny_escaping_symlinks();
+
+    let res = warp::test::request()
+        .path("/escape.txt")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = warp::test::request()
+        .path("/inside.txt")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "page");
+
+    let res = warp::test::request()
+        .path("/missing.txt")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    // links are followed by default
+    let route = warp::fs::dir(dir.join("root"));
+    let res = warp::test::request()
+        .path("/escape.txt")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "secret");
+
+    // nor precompressed variants out of the directory
+    symlink(dir.join("secret.txt"), dir.join("root/page.txt.gz")).unwrap();
+    let route = warp::fs::dir(dir.join("root"))
+        .deny_escaping_symlinks()
+        .precompressed_gzip();
+    let res = warp::test::request()
+        .path("/page.txt")
+        .header("accept-encoding", "gzip")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.body(), "page");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[tokio::test]
 async fn dir_bad_path() {
     let _ = pretty_env_logger::try_init();
@@ -137,7 +310,7 @@ async fn not_modified() {
 
     // clearly too old
     let res = warp::test::request()
-        .header("if-modified-since", "Sun, 07 Nov 1994 01:00:00 GMT")
+        .header("if-modified-since", "Mon, 07 Nov 1994 01:00:00 GMT")
         .reply(&file)
         .await;
     assert_eq!(res.status(), 200);
@@ -164,13 +337,145 @@ async fn precondition() {
 
     // clearly too old
     let res = warp::test::request()
-        .header("if-unmodified-since", "Sun, 07 Nov 1994 01:00:00 GMT")
+        .header("if-unmodified-since", "Mon, 07 Nov 1994 01:00:00 GMT")
         .reply(&file)
         .await;
     assert_eq!(res.status(), 412);
     assert_eq!(res.body(), "");
 }
 
+#[tokio::test]
+async fn etag() {
+    let _ = pretty_env_logger::try_init();
+
+    let file = warp::fs::file("README.md");
+    let res = warp::test::request().reply(&file).await;
+    let etag = res.headers()["etag"].clone();
+    assert!(etag.to_str().unwrap().starts_with("W/\""), "{:?}", etag);
+
+    let res = warp::test::request()
+        .header("if-none-match", etag.clone())
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(res.headers()["etag"], etag);
+    assert_eq!(res.body(), "");
+
+    // if-none-match takes precedence over if-modified-since
+    let res = warp::test::request()
+        .header("if-none-match", "\"other\"")
+        .header("if-modified-since", &res.headers()["last-modified"])
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("POST")
+        .header("if-none-match", "*")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 412);
+}
+
+#[tokio::test]
+async fn etag_strategy() {
+    use warp::fs::ETagStrategy;
+    use warp::Filter;
+
+    let _ = pretty_env_logger::try_init();
+
+    let file = warp::fs::file("README.md").etag(ETagStrategy::Strong);
+    let res = warp::test::request().reply(&file).await;
+    let etag = res.headers()["etag"].clone();
+    assert!(etag.to_str().unwrap().starts_with('"'), "{:?}", etag);
+    let res = warp::test::request().reply(&file).await;
+    assert_eq!(res.headers()["etag"], etag);
+
+    // the same hash as the tags of other replies
+    let contents = fs::read("README.md").expect("fs::read README.md");
+    let tagged = warp::any()
+        .map(move || contents.clone())
+        .with(warp::reply::with::etag());
+    let res = warp::test::request().reply(&tagged).await;
+    assert_eq!(res.headers()["etag"], etag);
+
+    // a strong etag allows ranges with if-range
+    let res = warp::test::request()
+        .header("range", "bytes=0-9")
+        .header("if-range", etag.clone())
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 206);
+
+    let dir = warp::fs::dir("examples").etag(ETagStrategy::Disabled);
+    let res = warp::test::request().path("/todos.rs").reply(&dir).await;
+    assert_eq!(res.status(), 200);
+    assert!(!res.headers().contains_key("etag"));
+}
+
+#[tokio::test]
+async fn precompressed() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = std::env::temp_dir().join(format!("warp-precompressed-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("create temp dir");
+    fs::write(dir.join("app.js"), "plain").unwrap();
+    fs::write(dir.join("app.js.gz"), "gzipped").unwrap();
+    fs::write(dir.join("app.js.br"), "brotlied").unwrap();
+    fs::write(dir.join("other.js"), "plain").unwrap();
+
+    let route = warp::fs::dir(dir.clone())
+        .precompressed_gzip()
+        .precompressed_br();
+
+    let res = warp::test::request()
+        .path("/app.js")
+        .header("accept-encoding", "gzip, br")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-encoding"], "br");
+    // the type is still that of the uncompressed file
+    let content_type = res.headers()["content-type"].to_str().unwrap();
+    assert!(content_type.ends_with("/javascript"), "{}", content_type);
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    assert_eq!(res.body(), "brotlied");
+
+    let res = warp::test::request()
+        .path("/app.js")
+        .header("accept-encoding", "gzip, br;q=0")
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert_eq!(res.body(), "gzipped");
+
+    let res = warp::test::request().path("/app.js").reply(&route).await;
+    assert!(!res.headers().contains_key("content-encoding"));
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    assert_eq!(res.body(), "plain");
+
+    // no precompressed variant
+    let res = warp::test::request()
+        .path("/other.js")
+        .header("accept-encoding", "gzip, br")
+        .reply(&route)
+        .await;
+    assert!(!res.headers().contains_key("content-encoding"));
+    assert_eq!(res.body(), "plain");
+
+    // not enabled
+    let res = warp::test::request()
+        .path("/app.js")
+        .header("accept-encoding", "gzip, br")
+        .reply(&warp::fs::dir(dir.clone()))
+        .await;
+    assert!(!res.headers().contains_key("content-encoding"));
+    assert!(!res.headers().contains_key("vary"));
+    assert_eq!(res.body(), "plain");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[tokio::test]
 async fn byte_ranges() {
     let _ = pretty_env_logger::try_init();
@@ -190,6 +495,31 @@ async fn byte_ranges() {
     assert_eq!(res.headers()["content-length"], "101");
     assert_eq!(res.body(), &contents[100..=200]);
 
+    // suffix range
+    let res = warp::test::request()
+        .header("range", "bytes=-100")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 206);
+    assert_eq!(
+        res.headers()["content-range"],
+        format!(
+            "bytes {}-{}/{}",
+            contents.len() - 100,
+            contents.len() - 1,
+            contents.len()
+        )
+    );
+    assert_eq!(res.body(), &contents[contents.len() - 100..]);
+
+    // suffix longer than the file
+    let res = warp::test::request()
+        .header("range", "bytes=-100000")
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), &contents[..]);
+
     // bad range
     let res = warp::test::request()
         .header("range", "bytes=100-10")
@@ -216,10 +546,20 @@ async fn byte_ranges() {
     assert_eq!(res.headers().get("content-length"), None);
     assert_eq!(res.body(), "");
 
+    // if-range matching the file
+    let last_modified = warp::test::request().reply(&file).await.headers()["last-modified"].clone();
+    let res = warp::test::request()
+        .header("range", "bytes=100-200")
+        .header("if-range", last_modified)
+        .reply(&file)
+        .await;
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.body(), &contents[100..=200]);
+
     // if-range too old
     let res = warp::test::request()
         .header("range", "bytes=100-200")
-        .header("if-range", "Sun, 07 Nov 1994 01:00:00 GMT")
+        .header("if-range", "Mon, 07 Nov 1994 01:00:00 GMT")
         .reply(&file)
         .await;
     assert_eq!(res.status(), 200);
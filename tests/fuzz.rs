@@ -0,0 +1,101 @@
+#![deny(warnings)]
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn todos() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("todos" / u32)
+        .and(warp::put())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(warp::body::json())
+        .map(|_id: u32, _query, todo: serde_json::Value| warp::reply::json(&todo))
+}
+
+#[tokio::test]
+async fn fuzz_passes() {
+    let _ = pretty_env_logger::try_init();
+
+    warp::test::fuzz()
+        .seed(7)
+        .iterations(500)
+        .path("/todos/7?verbose=true")
+        .method("PUT")
+        .json(&json!({ "text": "fuzz", "done": false }))
+        .run(&todos())
+        .await;
+}
+
+#[tokio::test]
+async fn fuzz_documented_statuses() {
+    let _ = pretty_env_logger::try_init();
+
+    warp::test::fuzz()
+        .seed(7)
+        .path("/todos/7")
+        .method("PUT")
+        .json(&json!({ "text": "fuzz" }))
+        .status(200)
+        .status(400)
+        .status(404)
+        .status(405)
+        .status(411)
+        .status(413)
+        .status(415)
+        .run(&todos())
+        .await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected status 200")]
+async fn fuzz_undocumented_status() {
+    let _ = pretty_env_logger::try_init();
+
+    warp::test::fuzz()
+        .seed(7)
+        .path("/todos/7")
+        .method("PUT")
+        .json(&json!({ "text": "fuzz" }))
+        .status(400)
+        .run(&todos())
+        .await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "filter panicked")]
+async fn fuzz_catches_panics() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path!("items" / u64).map(|id: u64| {
+        if id > 1_000_000 {
+            panic!("unexpected id");
+        }
+        "item"
+    });
+
+    warp::test::fuzz()
+        .seed(7)
+        .path("/items/1")
+        .run(&route)
+        .await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected status 500")]
+async fn fuzz_catches_server_errors() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path!("items" / String).map(|id: String| {
+        let status = if id.len() > 100 {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        };
+        warp::reply::with_status("item", status)
+    });
+
+    warp::test::fuzz()
+        .seed(7)
+        .path("/items/abc")
+        .run(&route)
+        .await;
+}
@@ -0,0 +1,239 @@
+#![deny(warnings)]
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+
+use warp::geoip::{Geo, GeoIp};
+use warp::Filter;
+
+// An IPv4 network, by its address and prefix length, and its record.
+type Network<'a> = (&'a str, u8, Vec<(&'a str, Value)>);
+
+// Writes a MaxMind DB of IPv4 networks, with records of string and uint32
+// fields, and a `country` map of strings.
+fn mmdb(name: &str, networks: &[Network]) -> PathBuf {
+    enum Rec {
+        Empty,
+        Node(usize),
+        Data(usize),
+    }
+
+    let mut data = Vec::new();
+    let mut nodes = vec![[Rec::Empty, Rec::Empty]];
+    for (net, prefix, fields) in networks {
+        let offset = data.len();
+        encode_map(&mut data, fields);
+        let ip = u32::from(net.parse::<Ipv4Addr>().unwrap());
+        let mut node = 0;
+        for i in 0..*prefix {
+            let bit = ((ip >> (31 - i)) & 1) as usize;
+            if i + 1 == *prefix {
+                nodes[node][bit] = Rec::Data(offset);
+            } else if let Rec::Node(next) = nodes[node][bit] {
+                node = next;
+            } else {
+                nodes.push([Rec::Empty, Rec::Empty]);
+                let next = nodes.len() - 1;
+                nodes[node][bit] = Rec::Node(next);
+                node = next;
+            }
+        }
+    }
+
+    let count = nodes.len();
+    let mut db = Vec::new();
+    for node in &nodes {
+        for rec in node {
+            let value = match *rec {
+                Rec::Empty => count,
+                Rec::Node(n) => n,
+                Rec::Data(offset) => count + 16 + offset,
+            };
+            db.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+        }
+    }
+    db.extend_from_slice(&[0; 16]);
+    db.extend_from_slice(&data);
+    db.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+    encode_map(
+        &mut db,
+        &[
+            ("binary_format_major_version", Value::U16(2)),
+            ("binary_format_minor_version", Value::U16(0)),
+            ("build_epoch", Value::U64(0)),
+            ("database_type", Value::Str("Test")),
+            ("description", Value::Map(vec![])),
+            ("ip_version", Value::U16(4)),
+            ("languages", Value::Array(vec![])),
+            ("node_count", Value::U32(count as u32)),
+            ("record_size", Value::U16(24)),
+        ],
+    );
+
+    let path = std::env::temp_dir().join(format!("warp-{}-{}.mmdb", name, std::process::id()));
+    std::fs::write(&path, db).unwrap();
+    path
+}
+
+enum Value {
+    Str(&'static str),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Map(Vec<(&'static str, Value)>),
+    Array(Vec<Value>),
+}
+
+fn encode_map(out: &mut Vec<u8>, fields: &[(&str, Value)]) {
+    out.push(0xE0 | fields.len() as u8);
+    for (key, value) in fields {
+        encode_str(out, key);
+        encode(out, value);
+    }
+}
+
+fn encode_str(out: &mut Vec<u8>, s: &str) {
+    if s.len() < 29 {
+        out.push(0x40 | s.len() as u8);
+    } else {
+        out.extend_from_slice(&[0x40 | 29, s.len() as u8 - 29]);
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Str(s) => encode_str(out, s),
+        Value::U16(n) => {
+            out.push(0xA0 | 2);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        Value::U32(n) => {
+            out.push(0xC0 | 4);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        Value::U64(n) => {
+            out.extend_from_slice(&[8, 9 - 7]);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        Value::Map(fields) => encode_map(out, fields),
+        Value::Array(values) => {
+            out.extend_from_slice(&[values.len() as u8, 11 - 7]);
+            values.iter().for_each(|v| encode(out, v));
+        }
+    }
+}
+
+fn country(name: &str, code: &'static str) -> PathBuf {
+    mmdb(
+        name,
+        &[(
+            "192.0.2.0",
+            24,
+            vec![("country", Value::Map(vec![("iso_code", Value::Str(code))]))],
+        )],
+    )
+}
+
+#[tokio::test]
+async fn lookup_and_filter() {
+    let _ = pretty_env_logger::try_init();
+
+    let asn = mmdb(
+        "asn",
+        &[
+            (
+                "192.0.2.0",
+                25,
+                vec![
+                    ("autonomous_system_number", Value::U32(64496)),
+                    ("autonomous_system_organization", Value::Str("Example")),
+                ],
+            ),
+            (
+                "198.51.100.0",
+                24,
+                vec![("autonomous_system_number", Value::U32(64497))],
+            ),
+        ],
+    );
+    let geoip = GeoIp::open(country("country", "FR"))
+        .and_then(|geoip| geoip.with_database(&asn))
+        .unwrap()
+        .trust(Ipv4Addr::LOCALHOST.into());
+
+    let geo = geoip.lookup("192.0.2.1".parse().unwrap());
+    assert_eq!(geo.country(), Some("FR"));
+    assert_eq!(geo.asn(), Some(64496));
+    assert_eq!(geo.as_organization(), Some("Example"));
+
+    let geo = geoip.lookup("192.0.2.200".parse().unwrap());
+    assert_eq!(geo.country(), Some("FR"));
+    assert_eq!(geo.asn(), None);
+
+    let geo = geoip.lookup("::ffff:198.51.100.7".parse().unwrap());
+    assert_eq!(geo.country(), None);
+    assert_eq!(geo.asn(), Some(64497));
+
+    let geo = geoip.lookup("203.0.113.1".parse().unwrap());
+    assert_eq!(geo.ip(), Some("203.0.113.1".parse::<IpAddr>().unwrap()));
+    assert_eq!(geo.country(), None);
+    assert_eq!(geo.asn(), None);
+
+    let route = geoip
+        .filter()
+        .and(warp::ext::get::<Geo>())
+        .map(|geo: Geo, stored: Geo| {
+            assert_eq!(geo, stored);
+            geo.country().unwrap_or("-").to_owned()
+        });
+
+    let res = warp::test::request()
+        .remote_addr("127.0.0.1:4000".parse().unwrap())
+        .header("x-forwarded-for", "192.0.2.9")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "FR");
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "-");
+}
+
+#[tokio::test]
+async fn reload() {
+    let path = country("reload", "FR");
+    let geoip = GeoIp::open(&path).unwrap();
+    let ip = "192.0.2.1".parse().unwrap();
+    assert_eq!(geoip.lookup(ip).country(), Some("FR"));
+
+    std::fs::copy(country("reload-de", "DE"), &path).unwrap();
+    // Cached until reloaded.
+    assert_eq!(geoip.lookup(ip).country(), Some("FR"));
+    geoip.reload().unwrap();
+    assert_eq!(geoip.lookup(ip).country(), Some("DE"));
+
+    std::fs::write(&path, b"not a database").unwrap();
+    assert!(geoip.reload().is_err());
+    assert_eq!(geoip.lookup(ip).country(), Some("DE"));
+
+    assert!(GeoIp::open(std::env::temp_dir().join("warp-missing.mmdb")).is_err());
+}
+
+#[tokio::test]
+async fn access_log() {
+    let geoip = GeoIp::open(country("log", "FR")).unwrap();
+    let countries = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let logged = countries.clone();
+    let route = geoip
+        .filter()
+        .map(|_| warp::reply())
+        .with(warp::log::custom(move |info| {
+            let country = info.geo().and_then(|geo| geo.country()).map(String::from);
+            logged.lock().unwrap().push(country);
+        }));
+
+    warp::test::request()
+        .remote_addr("192.0.2.1:4000".parse().unwrap())
+        .reply(&route)
+        .await;
+    assert_eq!(*countries.lock().unwrap(), [Some("FR".to_owned())]);
+}
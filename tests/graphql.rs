@@ -0,0 +1,193 @@
+#![deny(warnings)]
+use serde_json::json;
+use warp::graphql::{GraphQLBatch, GraphQLRequest};
+use warp::Filter;
+
+fn execute(req: GraphQLRequest) -> serde_json::Value {
+    json!({
+        "data": {
+            "query": req.query,
+            "operationName": req.operation_name,
+            "variables": req.variables,
+            "get": req.is_get(),
+        }
+    })
+}
+
+fn schema() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("graphql")
+        .and(warp::graphql::request())
+        .map(|batch: GraphQLBatch| match batch {
+            GraphQLBatch::Single(req) => warp::graphql::reply(&execute(req)),
+            GraphQLBatch::Batch(reqs) => {
+                let results = reqs.into_iter().map(execute).collect::<Vec<_>>();
+                warp::graphql::reply(&results)
+            }
+        })
+}
+
+fn body(res: &warp::http::Response<bytes::Bytes>) -> serde_json::Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[tokio::test]
+async fn get_request() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .path("/graphql?query=%7Bhello%7D&operationName=Hello&variables=%7B%22a%22%3A1%7D")
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(
+        body(&res),
+        json!({
+            "data": {
+                "query": "{hello}",
+                "operationName": "Hello",
+                "variables": { "a": 1 },
+                "get": true,
+            }
+        })
+    );
+
+    let res = warp::test::request()
+        .path("/graphql?operationName=Hello")
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request()
+        .path("/graphql?query=%7Bhello%7D&variables=nope")
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn post_request() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .header("content-type", "application/json")
+        .header(
+            "accept",
+            "application/graphql-response+json, application/json;q=0.9",
+        )
+        .body(r#"{"query":"{hello}","variables":null}"#)
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["content-type"],
+        "application/graphql-response+json"
+    );
+    assert_eq!(body(&res)["data"]["query"], "{hello}");
+    assert_eq!(body(&res)["data"]["get"], false);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .header("content-type", "application/graphql")
+        .body("{hello}")
+        .reply(&schema())
+        .await;
+    assert_eq!(body(&res)["data"]["query"], "{hello}");
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .header("content-type", "application/json")
+        .body(r#"[{"query":"{a}"},{"query":"{b}","operationName":"B"}]"#)
+        .reply(&schema())
+        .await;
+    let results = body(&res);
+    assert_eq!(results[0]["data"]["query"], "{a}");
+    assert_eq!(results[1]["data"]["operationName"], "B");
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .header("content-type", "application/json")
+        .body(r#"{"variables":{}}"#)
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .header("content-type", "text/plain")
+        .body("{hello}")
+        .reply(&schema())
+        .await;
+    assert_eq!(res.status(), 415);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn subscriptions() {
+    use futures::stream;
+
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::graphql::subscriptions(|req: GraphQLRequest| {
+        let query = req.query;
+        stream::iter((1..=2).map(move |n| json!({ "data": { "query": query, "n": n } })))
+    });
+
+    let mut client = warp::test::ws()
+        .header("sec-websocket-protocol", "graphql-transport-ws")
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+
+    client.send_text(r#"{"type":"connection_init"}"#).await;
+    let msg = client.recv_text().await.unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&msg).unwrap(),
+        json!({ "type": "connection_ack" })
+    );
+
+    client.send_text(r#"{"type":"ping"}"#).await;
+    let msg = client.recv_text().await.unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&msg).unwrap(),
+        json!({ "type": "pong" })
+    );
+
+    client
+        .send_text(r#"{"type":"subscribe","id":"1","payload":{"query":"subscription { n }"}}"#)
+        .await;
+    for n in 1..=2 {
+        let msg = client.recv_text().await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&msg).unwrap(),
+            json!({
+                "type": "next",
+                "id": "1",
+                "payload": { "data": { "query": "subscription { n }", "n": n } },
+            })
+        );
+    }
+    let msg = client.recv_text().await.unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&msg).unwrap(),
+        json!({ "type": "complete", "id": "1" })
+    );
+
+    client.send_text("nope").await;
+    let (code, _) = client.recv_close_frame().await.unwrap().unwrap();
+    assert_eq!(code, 4400);
+
+    // subscribing before the connection is acknowledged
+    let mut client = warp::test::ws().handshake(route).await.expect("handshake");
+    client
+        .send_text(r#"{"type":"subscribe","id":"1","payload":{"query":"subscription { n }"}}"#)
+        .await;
+    let (code, _) = client.recv_close_frame().await.unwrap().unwrap();
+    assert_eq!(code, 4401);
+}
@@ -0,0 +1,110 @@
+#![deny(warnings)]
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use warp::http::{self, HeaderMap, HeaderValue};
+use warp::hyper::body::HttpBody;
+use warp::hyper::{self, Body};
+use warp::Filter;
+
+// A gRPC response body, with trailers.
+struct GrpcBody {
+    data: Vec<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl HttpBody for GrpcBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, hyper::Error>>> {
+        if self.data.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(self.data.remove(0))))
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, hyper::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+}
+
+fn greeter() -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    let svc = tower::service_fn(|req: http::Request<Body>| async move {
+        let info = format!(
+            "{} {:?} {:?} {:?}",
+            req.uri(),
+            req.version(),
+            req.headers()["content-type"],
+            req.headers()["te"],
+        );
+        let message = hyper::body::to_bytes(req.into_body()).await?;
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        trailers.insert("grpc-message", HeaderValue::from_static("ok"));
+        let body = GrpcBody {
+            data: vec![info.into(), message],
+            trailers: Some(trailers),
+        };
+        let res = http::Response::builder()
+            .header("content-type", "application/grpc+proto")
+            .body(body)
+            .unwrap();
+        Ok::<_, hyper::Error>(res)
+    });
+    warp::path("grpc").and(warp::grpc_web(svc))
+}
+
+#[tokio::test]
+async fn bridges_requests() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/grpc/helloworld.Greeter/SayHello")
+        .header("content-type", "application/grpc-web+proto")
+        .body("\0\0\0\0\x02hi")
+        .reply(&greeter())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/grpc-web+proto");
+
+    let info = "/helloworld.Greeter/SayHello HTTP/2.0 \"application/grpc+proto\" \"trailers\"";
+    let trailers = "grpc-status:0\r\ngrpc-message:ok\r\n";
+    let mut expected = Vec::new();
+    expected.extend_from_slice(info.as_bytes());
+    expected.extend_from_slice(b"\0\0\0\0\x02hi");
+    expected.push(0x80);
+    expected.extend_from_slice(&(trailers.len() as u32).to_be_bytes());
+    expected.extend_from_slice(trailers.as_bytes());
+    assert_eq!(res.body(), &expected[..]);
+}
+
+#[tokio::test]
+async fn rejects_other_requests() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/grpc/helloworld.Greeter/SayHello")
+        .header("content-type", "application/grpc-web-text")
+        .reply(&greeter())
+        .await;
+    assert_eq!(res.status(), 415);
+
+    let res = warp::test::request()
+        .path("/grpc/helloworld.Greeter/SayHello")
+        .header("content-type", "application/grpc-web")
+        .reply(&greeter())
+        .await;
+    assert_eq!(res.status(), 405);
+}
@@ -1,4 +1,5 @@
 #![deny(warnings)]
+use serde_derive::Deserialize;
 use warp::Filter;
 
 #[tokio::test]
@@ -69,3 +70,200 @@ async fn optional() {
         "invalid optional header still rejects",
     );
 }
+
+#[tokio::test]
+async fn accept_language() {
+    let _ = pretty_env_logger::try_init();
+
+    let locale = warp::header::accept_language(&["en", "fr", "de-CH", "pt-BR"]);
+
+    let cases = &[
+        (None, "en"),
+        (Some(""), "en"),
+        (Some("fr"), "fr"),
+        (Some("FR-ca"), "fr"),
+        (Some("de-CH-1996"), "de-CH"),
+        (Some("de"), "en"),
+        (Some("it, pt-br;q=0.8, fr;q=0.5"), "pt-BR"),
+        (Some("fr;q=0.5, pt-BR;q=0.9"), "pt-BR"),
+        (Some("pt-BR;q=0.5, fr;q=0.5"), "pt-BR"),
+        (Some("fr;q=0, de-CH;q=0.1"), "de-CH"),
+        (Some("fr-x-private"), "fr"),
+        (Some("*"), "en"),
+        (Some("ja;q=oops, fr;q=0.1"), "fr"),
+        (Some("fr;q=NaN"), "en"),
+    ];
+    for &(accept, expected) in cases {
+        let mut req = warp::test::request();
+        if let Some(accept) = accept {
+            req = req.header("accept-language", accept);
+        }
+        let extracted = req.filter(&locale).await.unwrap();
+        assert_eq!(extracted, expected, "accept-language: {:?}", accept);
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Fast,
+    Safe,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct Client {
+    x_api_key: String,
+    x_api_version: Option<u32>,
+    x_mode: Option<Mode>,
+    x_debug: Option<bool>,
+    #[serde(default)]
+    accept_language: Vec<String>,
+}
+
+#[tokio::test]
+async fn typed_all() {
+    let _ = pretty_env_logger::try_init();
+
+    let client = warp::header::typed_all::<Client>();
+
+    let extracted = warp::test::request()
+        .header("x-api-key", "secret")
+        .header("x-api-version", "2")
+        .header("x-mode", "safe")
+        .header("x-debug", "true")
+        .header("accept-language", "en, fr,de")
+        .filter(&client)
+        .await
+        .unwrap();
+    assert_eq!(
+        extracted,
+        Client {
+            x_api_key: "secret".to_owned(),
+            x_api_version: Some(2),
+            x_mode: Some(Mode::Safe),
+            x_debug: Some(true),
+            accept_language: vec!["en".to_owned(), "fr".to_owned(), "de".to_owned()],
+        }
+    );
+
+    let extracted = warp::test::request()
+        .header("x-api-key", "secret")
+        .filter(&client)
+        .await
+        .unwrap();
+    assert_eq!(extracted.x_api_version, None);
+    assert_eq!(extracted.x_mode, None);
+    assert_eq!(extracted.accept_language, Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn typed_all_rejections() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::header::typed_all::<Client>().map(|_| warp::reply());
+
+    let res = warp::test::request()
+        .header("x-api-version", "2")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "Missing request header \"x-api-key\"");
+
+    let res = warp::test::request()
+        .header("x-api-key", "secret")
+        .header("x-api-version", "two")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "Invalid request header \"x-api-version\"");
+
+    let res = warp::test::request()
+        .header("x-api-key", "secret")
+        .header("x-mode", "reckless")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "Invalid request header \"x-mode\"");
+}
+
+#[tokio::test]
+async fn user_agent_parsed() {
+    let _ = pretty_env_logger::try_init();
+
+    let ua = warp::header::user_agent_parsed();
+    let parse = |raw: &'static str| {
+        let ua = ua.clone();
+        async move {
+            warp::test::request()
+                .header("user-agent", raw)
+                .filter(&ua)
+                .await
+                .unwrap()
+        }
+    };
+
+    let firefox =
+        parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/115.0")
+            .await;
+    assert_eq!(firefox.browser(), Some("Firefox"));
+    assert_eq!(firefox.browser_version(), Some("115.0"));
+    assert_eq!(firefox.os(), Some("Windows"));
+    assert!(!firefox.is_bot());
+
+    let safari = parse(
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X) AppleWebKit/605.1.15 \
+         (KHTML, like Gecko) Version/16.5 Mobile/15E148 Safari/604.1",
+    )
+    .await;
+    assert_eq!(safari.browser(), Some("Safari"));
+    assert_eq!(safari.browser_version(), Some("16.5"));
+    assert_eq!(safari.os(), Some("iOS"));
+
+    let edge = parse(
+        "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) \
+         Chrome/114.0.0.0 Mobile Safari/537.36 EdgA/114.0.1823.43",
+    )
+    .await;
+    assert_eq!(edge.browser(), Some("Edge"));
+    assert_eq!(edge.os(), Some("Android"));
+
+    let googlebot =
+        parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)").await;
+    assert!(googlebot.is_bot());
+
+    let curl = parse("curl/8.0.1").await;
+    assert_eq!(curl.browser(), Some("curl"));
+    assert_eq!(curl.browser_version(), Some("8.0.1"));
+    assert_eq!(curl.os(), None);
+    assert!(curl.is_bot());
+    assert_eq!(curl.as_str(), "curl/8.0.1");
+
+    let missing = warp::test::request().filter(&ua).await.unwrap();
+    assert_eq!(missing, warp::header::UserAgent::new(""));
+}
+
+#[tokio::test]
+async fn user_agent_parsed_with() {
+    use warp::header::{DefaultParser, UserAgent, UserAgentParser};
+
+    let ua = warp::header::user_agent_parsed_with(|raw: &str| {
+        let ua = DefaultParser.parse(raw);
+        let bot = ua.is_bot() || raw.starts_with("Pingdom");
+        ua.with_bot(bot)
+    });
+
+    let pingdom = warp::test::request()
+        .header("user-agent", "Pingdom.com_uptime_check/1.0")
+        .filter(&ua)
+        .await
+        .unwrap();
+    assert!(pingdom.is_bot());
+
+    let custom = warp::header::user_agent_parsed_with(|raw: &str| {
+        UserAgent::new(raw).with_browser("Custom", Some("1"))
+    });
+    let ua = warp::test::request().filter(&custom).await.unwrap();
+    assert_eq!(ua.browser(), Some("Custom"));
+    assert_eq!(ua.browser_version(), Some("1"));
+}
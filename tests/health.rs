@@ -0,0 +1,82 @@
+#![deny(warnings)]
+use serde_json::{json, Value};
+use warp::health::Checks;
+
+#[tokio::test]
+async fn live() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::health::live();
+
+    let res = warp::test::request()
+        .path("/health/live")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({ "status": "ok" }));
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/health/live")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 405);
+}
+
+#[tokio::test]
+async fn ready() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::health::ready(
+        Checks::new()
+            .check("db", || async { Ok::<_, String>(()) })
+            .check("queue", || async { Ok::<_, String>(()) }),
+    );
+
+    let res = warp::test::request()
+        .path("/health/ready")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "status": "ok",
+            "checks": {
+                "db": { "status": "ok" },
+                "queue": { "status": "ok" },
+            },
+        })
+    );
+}
+
+#[tokio::test]
+async fn not_ready() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::health::ready(
+        Checks::new()
+            .check("db", || async { Ok::<_, String>(()) })
+            .check("queue", || async { Err("too many pending jobs") }),
+    );
+
+    let res = warp::test::request()
+        .path("/health/ready")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 503);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "status": "unavailable",
+            "checks": {
+                "db": { "status": "ok" },
+                "queue": { "status": "error", "error": "too many pending jobs" },
+            },
+        })
+    );
+}
@@ -0,0 +1,96 @@
+#![deny(warnings)]
+use warp::host::Authority;
+use warp::Filter;
+
+#[tokio::test]
+async fn optional() {
+    let _ = pretty_env_logger::try_init();
+
+    let filter = warp::host::optional();
+
+    let req = warp::test::request().header("host", "example.com:8080");
+    let authority = req.filter(&filter).await.unwrap();
+    assert_eq!(authority, Some(Authority::from_static("example.com:8080")));
+
+    // the authority of the uri takes precedence
+    let req = warp::test::request()
+        .path("http://example.com/")
+        .header("host", "other.example");
+    let authority = req.filter(&filter).await.unwrap();
+    assert_eq!(authority, Some(Authority::from_static("example.com")));
+
+    let req = warp::test::request();
+    assert_eq!(req.filter(&filter).await.unwrap(), None);
+
+    let req = warp::test::request().header("host", "not a host");
+    assert!(req.filter(&filter).await.is_err());
+}
+
+#[tokio::test]
+async fn exact() {
+    let _ = pretty_env_logger::try_init();
+
+    let filter = warp::host::exact("Example.com");
+
+    for host in &["example.com", "EXAMPLE.com:8080", "example.com."] {
+        let req = warp::test::request().header("host", *host);
+        assert!(req.matches(&filter).await, "{}", host);
+    }
+    for host in &["www.example.com", "example.org"] {
+        let req = warp::test::request().header("host", *host);
+        assert!(!req.matches(&filter).await, "{}", host);
+    }
+    assert!(!warp::test::request().matches(&filter).await);
+
+    let filter = warp::host::exact("example.com:8080");
+    let req = warp::test::request().header("host", "example.com:8080");
+    assert!(req.matches(&filter).await);
+    let req = warp::test::request().header("host", "example.com");
+    assert!(!req.matches(&filter).await);
+
+    let filter = warp::host::exact("*.example.com");
+    let req = warp::test::request().header("host", "acme.example.com");
+    assert!(req.matches(&filter).await);
+    let req = warp::test::request().header("host", "example.com");
+    assert!(!req.matches(&filter).await);
+}
+
+#[tokio::test]
+async fn subdomain() {
+    let _ = pretty_env_logger::try_init();
+
+    let tenants =
+        warp::host::subdomain("*.example.com").map(|tenant: String| format!("Hello, {}!", tenant));
+    let apex = warp::host::exact("example.com").map(|| "home");
+    let routes = tenants.or(apex);
+
+    let res = warp::test::request()
+        .header("host", "Acme.Example.com:443")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.body(), "Hello, acme!");
+
+    let res = warp::test::request()
+        .header("host", "eu.acme.example.com")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.body(), "Hello, eu.acme!");
+
+    let res = warp::test::request()
+        .header("host", "example.com")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.body(), "home");
+
+    let res = warp::test::request()
+        .header("host", "badexample.com")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[test]
+#[should_panic(expected = "illegal subdomain pattern")]
+fn subdomain_needs_wildcard() {
+    let _ = warp::host::subdomain("example.com");
+}
@@ -0,0 +1,81 @@
+#![deny(warnings)]
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use warp::Filter;
+
+fn proxy() -> SocketAddr {
+    ([10, 0, 0, 1], 4000).into()
+}
+
+#[tokio::test]
+async fn redirects_http() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(warp::reply).with(warp::https::enforce());
+
+    let res = warp::test::request()
+        .path("/login?next=%2F")
+        .header("host", "example.com:8080")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 301);
+    assert_eq!(
+        res.headers()["location"],
+        "https://example.com/login?next=%2F"
+    );
+    assert!(res.headers().get("strict-transport-security").is_none());
+
+    // forwarded schemes of untrusted clients are ignored
+    let res = warp::test::request()
+        .header("host", "example.com")
+        .header("x-forwarded-proto", "https")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 301);
+    assert_eq!(res.headers()["location"], "https://example.com/");
+}
+
+#[tokio::test]
+async fn hsts() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(warp::reply).with(
+        warp::https::enforce()
+            .trust(proxy().ip())
+            .max_age(Duration::from_secs(600))
+            .include_subdomains()
+            .preload(),
+    );
+
+    let res = warp::test::request()
+        .header("host", "example.com")
+        .header("x-forwarded-proto", "https")
+        .remote_addr(proxy())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["strict-transport-security"],
+        "max-age=600; includeSubDomains; preload"
+    );
+
+    let res = warp::test::request()
+        .header("host", "example.com")
+        .header("forwarded", "for=192.0.2.60;proto=http")
+        .remote_addr(proxy())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 301);
+
+    // servers with TLS only redirect forwarded plain HTTP requests
+    let route = warp::any()
+        .map(warp::reply)
+        .with(warp::https::enforce().tls());
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["strict-transport-security"],
+        "max-age=31536000"
+    );
+}
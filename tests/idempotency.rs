@@ -0,0 +1,229 @@
+#![deny(warnings)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use warp::http::StatusCode;
+use warp::idempotency::{Idempotency, MemoryStore};
+use warp::Filter;
+
+fn charges(
+    count: Arc<AtomicUsize>,
+    store: MemoryStore,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::path!("charges" / u32)
+        .map(move |amount: u32| {
+            let n = count.fetch_add(1, Ordering::SeqCst) + 1;
+            let status = if amount == 0 {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::CREATED
+            };
+            let reply =
+                warp::reply::with_header(format!("charge #{}", n), "x-charge", n.to_string());
+            warp::reply::with_status(reply, status)
+        })
+        .with(Idempotency::new(store))
+}
+
+#[tokio::test]
+async fn replays_response() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let route = charges(count.clone(), MemoryStore::new());
+
+    for _ in 0..2 {
+        let res = warp::test::request()
+            .method("POST")
+            .path("/charges/10")
+            .header("idempotency-key", "abc")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()["x-charge"], "1");
+        assert_eq!(res.body(), "charge #1");
+    }
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/charges/10")
+        .header("idempotency-key", "abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["idempotent-replayed"], "true");
+
+    // Another key, or no key, is handled again.
+    let res = warp::test::request()
+        .method("POST")
+        .path("/charges/10")
+        .header("idempotency-key", "def")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "charge #2");
+    assert!(res.headers().get("idempotent-replayed").is_none());
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/charges/10")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "charge #3");
+}
+
+#[tokio::test]
+async fn key_reused_elsewhere() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = charges(Arc::new(AtomicUsize::new(0)), MemoryStore::new());
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/charges/10")
+        .header("idempotency-key", "abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/charges/20")
+        .header("idempotency-key", "abc")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn failures_not_kept() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let store = MemoryStore::new();
+    let route = charges(count.clone(), store.clone());
+
+    for n in 1..=2 {
+        let res = warp::test::request()
+            .method("POST")
+            .path("/charges/0")
+            .header("idempotency-key", "abc")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.body(), &format!("charge #{}", n));
+    }
+    assert!(store.is_empty());
+}
+
+#[tokio::test]
+async fn in_flight() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any()
+        .and_then(|| async {
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+            Ok::<_, warp::Rejection>("slow")
+        })
+        .with(Idempotency::new(MemoryStore::new()));
+
+    let first = warp::test::request()
+        .method("POST")
+        .header("idempotency-key", "abc")
+        .reply(&route);
+    let second = async {
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        warp::test::request()
+            .method("POST")
+            .header("idempotency-key", "abc")
+            .reply(&route)
+            .await
+    };
+    let (first, second) = futures::future::join(first, second).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn required_and_expiry() {
+    let _ = pretty_env_logger::try_init();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let route = {
+        let count = count.clone();
+        warp::any()
+            .map(move || format!("{}", count.fetch_add(1, Ordering::SeqCst)))
+            .with(
+                Idempotency::new(MemoryStore::new())
+                    .header("x-request-key")
+                    .ttl(Duration::from_millis(50))
+                    .required(true),
+            )
+    };
+
+    let res = warp::test::request().method("POST").reply(&route).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.body(), "Missing request header \"x-request-key\"");
+
+    let req = || {
+        warp::test::request()
+            .method("POST")
+            .header("x-request-key", "abc")
+    };
+    assert_eq!(req().reply(&route).await.body(), "0");
+    assert_eq!(req().reply(&route).await.body(), "0");
+    tokio::time::delay_for(Duration::from_millis(60)).await;
+    assert_eq!(req().reply(&route).await.body(), "1");
+}
+
+#[tokio::test]
+async fn dropped_request_released() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any()
+        .and_then(|| async {
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+            Ok::<_, warp::Rejection>("slow")
+        })
+        .with(Idempotency::new(MemoryStore::new()));
+
+    let req = || {
+        warp::test::request()
+            .method("POST")
+            .header("idempotency-key", "abc")
+    };
+    // The client gives up before the response.
+    let dropped = tokio::time::timeout(Duration::from_millis(10), req().reply(&route)).await;
+    assert!(dropped.is_err());
+    tokio::time::delay_for(Duration::from_millis(10)).await;
+
+    let res = req().reply(&route).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.body(), "slow");
+}
+
+#[tokio::test]
+async fn key_reused_for_another_body() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::body::bytes()
+        .map(|body: bytes::Bytes| body.to_vec())
+        .with(Idempotency::new(MemoryStore::new()).max_body_size(8));
+
+    let req = |body: &'static str| {
+        warp::test::request()
+            .method("POST")
+            .header("idempotency-key", "abc")
+            .body(body)
+    };
+    for _ in 0..2 {
+        let res = req("pay 10").reply(&route).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "pay 10");
+    }
+    let res = req("pay 1000").reply(&route).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = req("pay 100000").reply(&route).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
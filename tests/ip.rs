@@ -0,0 +1,134 @@
+#![deny(warnings)]
+use std::net::Ipv4Addr;
+
+use warp::Filter;
+
+#[tokio::test]
+async fn allow_and_deny() {
+    let _ = pretty_env_logger::try_init();
+
+    let route =
+        warp::ip::restrict(&["10.0.0.0/8", "2001:db8::/32"], &["10.6.6.0/24"]).map(warp::reply);
+
+    let status = |addr: &'static str| {
+        let route = route.clone();
+        async move {
+            warp::test::request()
+                .remote_addr(addr.parse().unwrap())
+                .reply(&route)
+                .await
+                .status()
+        }
+    };
+    assert_eq!(status("10.1.2.3:4000").await, 200);
+    assert_eq!(status("[2001:db8::1]:4000").await, 200);
+    assert_eq!(status("[::ffff:10.1.2.3]:4000").await, 200);
+    assert_eq!(status("10.6.6.6:4000").await, 403);
+    assert_eq!(status("192.168.0.1:4000").await, 403);
+
+    // Without a client address.
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 403);
+
+    let rejection = warp::test::request()
+        .remote_addr("192.168.0.1:4000".parse().unwrap())
+        .filter(&warp::ip::restrict(&["10.0.0.0/8"], &[]))
+        .await
+        .unwrap_err();
+    assert!(rejection.find::<warp::ip::IpForbidden>().is_some());
+}
+
+#[tokio::test]
+async fn deny_only() {
+    let route = warp::ip::restrict(&[], &["192.0.2.1"]).map(warp::reply);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .remote_addr("192.0.2.1:4000".parse().unwrap())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn trusted_proxies() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::ip::restrict(&["203.0.113.0/24"], &[])
+        .trust(Ipv4Addr::LOCALHOST.into())
+        .trust("10.0.0.2".parse().unwrap())
+        .map(warp::reply);
+
+    // The client before the trusted proxies.
+    let res = warp::test::request()
+        .remote_addr("127.0.0.1:4000".parse().unwrap())
+        .header("x-forwarded-for", "198.51.100.1, 203.0.113.7, 10.0.0.2")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .remote_addr("127.0.0.1:4000".parse().unwrap())
+        .header(
+            "forwarded",
+            "for=\"203.0.113.7:1234\";proto=https, for=10.0.0.2",
+        )
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .remote_addr("127.0.0.1:4000".parse().unwrap())
+        .header("x-forwarded-for", "203.0.113.7, 198.51.100.1")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 403);
+
+    // Headers of untrusted clients are ignored.
+    let res = warp::test::request()
+        .remote_addr("198.51.100.1:4000".parse().unwrap())
+        .header("x-forwarded-for", "203.0.113.7")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn handle_updates() {
+    let restrict = warp::ip::restrict(&["10.0.0.0/8"], &[]);
+    let handle = restrict.handle();
+    let route = restrict.map(warp::reply);
+
+    let status = |addr: &'static str| {
+        let route = route.clone();
+        async move {
+            warp::test::request()
+                .remote_addr(addr.parse().unwrap())
+                .reply(&route)
+                .await
+                .status()
+        }
+    };
+    assert_eq!(status("10.1.2.3:4000").await, 200);
+    assert_eq!(status("192.168.0.1:4000").await, 403);
+
+    handle.deny("10.1.2.3").unwrap();
+    handle.allow("192.168.0.0/16").unwrap();
+    assert_eq!(status("10.1.2.3:4000").await, 403);
+    assert_eq!(status("192.168.0.1:4000").await, 200);
+
+    assert!(handle.set(&["not a network"], &[]).is_err());
+    assert!(handle.allow("10.0.0.0/33").is_err());
+    assert_eq!(status("192.168.0.1:4000").await, 200);
+
+    handle.set(&[], &[]).unwrap();
+    assert_eq!(status("10.1.2.3:4000").await, 200);
+}
+
+#[test]
+#[should_panic(expected = "illegal network")]
+fn invalid_network() {
+    warp::ip::restrict(&["10.0.0.0/99"], &[]);
+}
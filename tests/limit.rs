@@ -0,0 +1,219 @@
+#![deny(warnings)]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use warp::limit::Quota;
+use warp::Filter;
+
+#[tokio::test]
+async fn concurrency() {
+    let _ = pretty_env_logger::try_init();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let rx = Arc::new(Mutex::new(Some(rx)));
+    let limit = warp::limit::concurrency(1).retry_after(Duration::from_millis(2500));
+    let route = warp::any()
+        .and_then(move || {
+            let rx = rx.lock().unwrap().take();
+            async move {
+                if let Some(rx) = rx {
+                    let _ = rx.await;
+                }
+                Ok::<_, warp::Rejection>(String::from("done"))
+            }
+        })
+        .with(limit.clone());
+
+    let blocked = tokio::spawn({
+        let route = route.clone();
+        async move { warp::test::request().reply(&route).await }
+    });
+    while limit.in_flight() == 0 {
+        tokio::time::delay_for(Duration::from_millis(1)).await;
+    }
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.headers()["retry-after"], "3");
+
+    tx.send(()).unwrap();
+    let res = blocked.await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(limit.in_flight(), 0);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "done");
+}
+
+#[tokio::test]
+async fn concurrency_releases_on_rejection() {
+    let _ = pretty_env_logger::try_init();
+
+    let limit = warp::limit::concurrency(1);
+    let route = warp::path("hello").map(|| "hello").with(limit.clone());
+
+    let res = warp::test::request().path("/nope").reply(&route).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(limit.in_flight(), 0);
+
+    let res = warp::test::request().path("/hello").reply(&route).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct User(String);
+
+fn user() -> impl Filter<Extract = (User,), Error = warp::Rejection> + Clone {
+    warp::header::<String>("x-user").map(User)
+}
+
+#[tokio::test]
+async fn throttle() {
+    let _ = pretty_env_logger::try_init();
+
+    let quotas = |user: &User| match user.0.as_str() {
+        "admin" => None,
+        "pro" => Some(Quota::per_minute(3)),
+        _ => Some(Quota::per_minute(1)),
+    };
+    let route = warp::any()
+        .map(|| "hello")
+        .with(warp::limit::throttle(user(), quotas));
+
+    for remaining in &["2", "1", "0"] {
+        let res = warp::test::request()
+            .header("x-user", "pro")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["x-ratelimit-limit"], "3");
+        assert_eq!(res.headers()["x-ratelimit-remaining"], *remaining);
+        assert_eq!(res.headers()["x-ratelimit-reset"], "60");
+    }
+    let res = warp::test::request()
+        .header("x-user", "pro")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.headers()["x-ratelimit-limit"], "3");
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+    assert_eq!(res.headers()["retry-after"], "60");
+
+    let rejection = warp::test::request()
+        .header("x-user", "pro")
+        .filter(&route)
+        .await
+        .unwrap_err();
+    let err = rejection
+        .find::<warp::limit::TooManyRequests>()
+        .expect("too many requests");
+    assert_eq!(err.limit(), 3);
+
+    // Principals have their own windows.
+    let res = warp::test::request()
+        .header("x-user", "free")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["x-ratelimit-limit"], "1");
+    let res = warp::test::request()
+        .header("x-user", "free")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 429);
+
+    // Principals without a quota aren't limited.
+    for _ in 0..5 {
+        let res = warp::test::request()
+            .header("x-user", "admin")
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(!res.headers().contains_key("x-ratelimit-limit"));
+    }
+
+    // Rejections of the key filter are kept.
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn throttle_window_resets() {
+    let _ = pretty_env_logger::try_init();
+
+    let quotas = |_: &User| Some(Quota::new(1, Duration::from_millis(50)));
+    let route = warp::any()
+        .map(|| "hello")
+        .with(warp::limit::throttle(user(), quotas));
+    let request = || warp::test::request().header("x-user", "sean");
+
+    assert_eq!(request().reply(&route).await.status(), 200);
+    let res = request().reply(&route).await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.headers()["retry-after"], "1");
+
+    tokio::time::delay_for(Duration::from_millis(60)).await;
+    assert_eq!(request().reply(&route).await.status(), 200);
+}
+
+#[tokio::test]
+async fn throttle_or_fallthrough() {
+    let _ = pretty_env_logger::try_init();
+
+    let quotas = |_: &User| Some(Quota::per_minute(1));
+    let limit = warp::limit::throttle(user(), quotas);
+    let search = warp::path("search").map(|| "results").with(limit.clone());
+    let admin = warp::path("admin").map(|| "admin").with(limit);
+    let health = warp::path("health").map(|| "ok");
+    let routes = search.or(admin).or(health);
+    let request = |path| warp::test::request().path(path).header("x-user", "sean");
+
+    // Misses of throttled routes don't count.
+    for _ in 0..3 {
+        let res = request("/health").reply(&routes).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body(), "ok");
+    }
+    let res = request("/search").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+
+    // Throttled principals still reach the other routes...
+    let res = request("/health").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "ok");
+
+    // ...but not the throttled ones, matched or not.
+    let res = request("/search").reply(&routes).await;
+    assert_eq!(res.status(), 429);
+    let res = request("/admin").reply(&routes).await;
+    assert_eq!(res.status(), 429);
+}
+
+#[tokio::test]
+async fn throttle_quota_lowered() {
+    let _ = pretty_env_logger::try_init();
+
+    let limit = Arc::new(Mutex::new(3));
+    let quota = limit.clone();
+    let quotas = move |_: &User| Some(Quota::per_minute(*quota.lock().unwrap()));
+    let route = warp::any()
+        .map(|| "hello")
+        .with(warp::limit::throttle(user(), quotas));
+    let request = || warp::test::request().header("x-user", "sean");
+
+    for _ in 0..3 {
+        assert_eq!(request().reply(&route).await.status(), 200);
+    }
+    assert_eq!(request().reply(&route).await.status(), 429);
+
+    // A lowered quota starts a new window.
+    *limit.lock().unwrap() = 1;
+    let res = request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["x-ratelimit-limit"], "1");
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+    assert_eq!(request().reply(&route).await.status(), 429);
+}
@@ -0,0 +1,142 @@
+#![deny(warnings)]
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use warp::Filter;
+
+#[tokio::test]
+async fn structured_sink() {
+    let _ = pretty_env_logger::try_init();
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let sink = records.clone();
+    let route = warp::path!("users" / u32)
+        .map(|id| format!("user #{}", id))
+        .with(
+            warp::log::structured()
+                .route("/users/:id")
+                .request_id_header("x-trace")
+                .sink(move |record| sink.lock().unwrap().push(record.clone())),
+        );
+
+    let res = warp::test::request()
+        .path("/users/7")
+        .header("x-trace", "abc")
+        .remote_addr("10.0.0.1:4000".parse().unwrap())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/users/nope")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+
+    let mut json = records[0].to_json();
+    assert!(json["latency_ms"].as_f64().unwrap() >= 0.0);
+    json.as_object_mut().unwrap().remove("latency_ms");
+    assert_eq!(
+        json,
+        json!({
+            "method": "GET",
+            "route": "/users/:id",
+            "status": 200,
+            "bytes": 7,
+            "remote_ip": "10.0.0.1",
+            "request_id": "abc",
+        })
+    );
+
+    assert_eq!(records[1].method(), "POST");
+    assert_eq!(records[1].status(), 404);
+    assert_eq!(records[1].bytes(), None);
+    assert_eq!(records[1].remote_ip(), None);
+    assert_eq!(records[1].request_id(), None);
+}
+
+#[tokio::test]
+async fn structured_default_route() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(warp::reply).with(warp::log::structured());
+    let res = warp::test::request().path("/anything").reply(&route).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn custom_body_size() {
+    let _ = pretty_env_logger::try_init();
+
+    let sizes = Arc::new(Mutex::new(Vec::new()));
+    let log = {
+        let sizes = sizes.clone();
+        warp::log::custom(move |info| {
+            sizes
+                .lock()
+                .unwrap()
+                .push((info.status().as_u16(), info.body_size()));
+        })
+    };
+
+    let sized = warp::path("sized").map(|| "hello");
+    let streamed = warp::path("streamed").map(|| {
+        let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("hello "), Ok("world")];
+        warp::reply::Response::new(warp::hyper::Body::wrap_stream(futures::stream::iter(
+            chunks,
+        )))
+    });
+    let route = sized.or(streamed).with(log);
+
+    let res = warp::test::request().path("/sized").reply(&route).await;
+    assert_eq!(res.body(), "hello");
+
+    let res = warp::test::request().path("/streamed").reply(&route).await;
+    assert_eq!(res.body(), "hello world");
+
+    let res = warp::test::request().path("/nope").reply(&route).await;
+    assert_eq!(res.status(), 404);
+
+    assert_eq!(
+        *sizes.lock().unwrap(),
+        vec![(200, Some(5)), (200, Some(11)), (404, None)]
+    );
+}
+
+#[tokio::test]
+async fn custom_route_pattern() {
+    let _ = pretty_env_logger::try_init();
+
+    let patterns = Arc::new(Mutex::new(Vec::new()));
+    let log = {
+        let patterns = patterns.clone();
+        warp::log::custom(move |info| {
+            patterns
+                .lock()
+                .unwrap()
+                .push(info.route_pattern().map(ToOwned::to_owned));
+        })
+    };
+
+    let router = warp::router::Router::new();
+    router.insert("GET", "/hello/:name", warp::any().map(|| "hello"));
+    let route = router.clone().with(log);
+
+    let res = warp::test::request()
+        .path("/hello/sean")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/bye").reply(&route).await;
+    assert_eq!(res.status(), 404);
+
+    assert_eq!(
+        *patterns.lock().unwrap(),
+        vec![Some("/hello/:name".to_owned()), None]
+    );
+}
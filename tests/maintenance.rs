@@ -0,0 +1,34 @@
+#![deny(warnings)]
+use std::time::Duration;
+
+use warp::Filter;
+
+#[tokio::test]
+async fn switch() {
+    let _ = pretty_env_logger::try_init();
+
+    let reports = warp::maintenance::switch().retry_after(Duration::from_millis(1500));
+    let routes = warp::path("reports")
+        .and(warp::any().map(|| "report").with(reports.clone()))
+        .or(warp::path("hello").map(|| "hello"));
+
+    let res = warp::test::request().path("/reports").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "report");
+
+    reports.enable();
+    assert!(reports.is_enabled());
+    let res = warp::test::request().path("/reports").reply(&routes).await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.headers()["retry-after"], "2");
+
+    // other routes are still served
+    let res = warp::test::request().path("/hello").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    let res = warp::test::request().path("/nope").reply(&routes).await;
+    assert_eq!(res.status(), 404);
+
+    reports.disable();
+    let res = warp::test::request().path("/reports").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,89 @@
+#![deny(warnings)]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use warp::metering::Usage;
+use warp::Filter;
+
+#[derive(Clone)]
+struct User(String);
+
+fn recorder() -> (
+    Arc<Mutex<Vec<Usage>>>,
+    impl Fn(Usage) -> futures::future::Ready<()> + Clone + Send + Sync + 'static,
+) {
+    let usages = Arc::new(Mutex::new(Vec::new()));
+    let recorded = usages.clone();
+    let sink = move |usage| {
+        recorded.lock().unwrap().push(usage);
+        futures::future::ready(())
+    };
+    (usages, sink)
+}
+
+async fn wait(usages: &Mutex<Vec<Usage>>, n: usize) {
+    for _ in 0..100 {
+        if usages.lock().unwrap().len() >= n {
+            return;
+        }
+        tokio::time::delay_for(Duration::from_millis(1)).await;
+    }
+    panic!("usage not reported");
+}
+
+#[tokio::test]
+async fn reports_usage() {
+    let _ = pretty_env_logger::try_init();
+
+    let (usages, sink) = recorder();
+    let route = warp::path!("echo" / u32)
+        .and(warp::ext::provide(warp::header("x-user").map(User)))
+        .and(warp::body::bytes())
+        .map(|_, body: bytes::Bytes| format!("echo: {}", String::from_utf8_lossy(&body)))
+        .with(
+            warp::metering::wrap(sink)
+                .route("/echo/:id")
+                .principal(|user: &User| user.0.clone()),
+        );
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/echo/1")
+        .header("x-user", "sean")
+        .body("hello")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "echo: hello");
+
+    wait(&usages, 1).await;
+    let usages = usages.lock().unwrap();
+    let usage = &usages[0];
+    assert_eq!(usage.method(), "POST");
+    assert_eq!(usage.route(), "/echo/:id");
+    assert_eq!(usage.principal(), Some("sean"));
+    assert_eq!(usage.status(), 200);
+    assert_eq!(usage.request_bytes(), 5);
+    assert_eq!(usage.response_bytes(), 11);
+}
+
+#[tokio::test]
+async fn skips_rejections() {
+    let _ = pretty_env_logger::try_init();
+
+    let (usages, sink) = recorder();
+    let route = warp::path("hello")
+        .map(warp::reply)
+        .with(warp::metering::wrap(sink));
+
+    let res = warp::test::request().path("/nope").reply(&route).await;
+    assert_eq!(res.status(), 404);
+    let res = warp::test::request().path("/hello").reply(&route).await;
+    assert_eq!(res.status(), 200);
+
+    wait(&usages, 1).await;
+    let usages = usages.lock().unwrap();
+    assert_eq!(usages.len(), 1);
+    assert_eq!(usages[0].route(), "/hello");
+    assert_eq!(usages[0].principal(), None);
+    assert_eq!(usages[0].response_bytes(), 0);
+}
@@ -1,6 +1,36 @@
 #![deny(warnings)]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
 use warp::Filter;
 
+// Counts the allocations of the threads counting them.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get().map(|n| n + 1)));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+async fn allocations<F: std::future::Future>(fut: F) -> usize {
+    ALLOCATIONS.with(|n| n.set(Some(0)));
+    fut.await;
+    ALLOCATIONS.with(|n| n.replace(None)).unwrap()
+}
+
 #[tokio::test]
 async fn method() {
     let _ = pretty_env_logger::try_init();
@@ -32,6 +62,25 @@ async fn method_not_allowed_trumps_not_found() {
     assert_eq!(resp.status(), 405);
 }
 
+#[tokio::test]
+async fn method_not_allowed_allow_header() {
+    let _ = pretty_env_logger::try_init();
+    let hello = warp::path("hello");
+    let routes = hello
+        .and(warp::get())
+        .map(warp::reply)
+        .or(hello.and(warp::put()).map(warp::reply))
+        .or(hello.and(warp::get()).map(warp::reply));
+
+    let resp = warp::test::request()
+        .method("DELETE")
+        .path("/hello")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["allow"], "GET, PUT");
+}
+
 #[tokio::test]
 async fn bad_request_trumps_method_not_allowed() {
     let _ = pretty_env_logger::try_init();
@@ -50,3 +99,179 @@ async fn bad_request_trumps_method_not_allowed() {
     // assume POST was the appropriate method.
     assert_eq!(resp.status(), 400);
 }
+
+#[tokio::test]
+async fn auto_head() {
+    let _ = pretty_env_logger::try_init();
+    let hello = warp::path("hello")
+        .and(warp::get())
+        .map(|| warp::reply::with_header("Hello, World!", "x-greeting", "yes"));
+    let explicit = warp::path("explicit")
+        .and(warp::head())
+        .map(|| warp::reply::with_header("", "x-explicit", "yes"));
+    let post = warp::path("post").and(warp::post()).map(warp::reply);
+    let routes = explicit
+        .or(hello)
+        .or(post)
+        .with(warp::filters::method::auto_head());
+
+    let resp = warp::test::request()
+        .method("HEAD")
+        .path("/hello")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-greeting"], "yes");
+    assert_eq!(resp.headers()["content-length"], "13");
+    assert_eq!(resp.body(), "");
+
+    // explicit HEAD routes take precedence
+    let resp = warp::test::request()
+        .method("HEAD")
+        .path("/explicit")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.headers()["x-explicit"], "yes");
+
+    // GET is unchanged
+    let resp = warp::test::request().path("/hello").reply(&routes).await;
+    assert_eq!(resp.body(), "Hello, World!");
+
+    // only GET routes answer HEAD
+    let resp = warp::test::request()
+        .method("HEAD")
+        .path("/post")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 405);
+}
+
+#[tokio::test]
+async fn auto_options() {
+    let _ = pretty_env_logger::try_init();
+    let hello = warp::path("hello");
+    let explicit = warp::path("explicit");
+    let routes = hello
+        .and(warp::get())
+        .map(warp::reply)
+        .or(hello.and(warp::post()).map(warp::reply))
+        .or(explicit
+            .and(warp::options())
+            .map(|| warp::reply::with_header("", "x-explicit", "yes")))
+        .or(explicit.and(warp::delete()).map(warp::reply))
+        .with(warp::filters::method::auto_options());
+
+    let resp = warp::test::request()
+        .method("OPTIONS")
+        .path("/hello")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 204);
+    assert_eq!(resp.headers()["allow"], "GET, POST, OPTIONS");
+
+    // explicit OPTIONS routes take precedence
+    let resp = warp::test::request()
+        .method("OPTIONS")
+        .path("/explicit")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-explicit"], "yes");
+
+    // unknown paths are still not found
+    let resp = warp::test::request()
+        .method("OPTIONS")
+        .path("/nope")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn override_via_header() {
+    let _ = pretty_env_logger::try_init();
+    let item = warp::path("item");
+    let routes = item
+        .and(warp::delete())
+        .map(|| "deleted")
+        .or(item.and(warp::post()).map(|| "posted"))
+        .with(warp::filters::method::override_via_header());
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/item")
+        .header("x-http-method-override", "delete")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.body(), "deleted");
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/item")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.body(), "posted");
+
+    // only POST requests are overridden
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/item")
+        .header("x-http-method-override", "DELETE")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 405);
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/item")
+        .header("x-http-method-override", "NOT A METHOD")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn override_via_form() {
+    let _ = pretty_env_logger::try_init();
+    let routes = warp::path("item")
+        .and(warp::put())
+        .and(warp::body::form())
+        .map(|form: std::collections::HashMap<String, String>| form["name"].clone())
+        .with(warp::filters::method::override_via_form());
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/item")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("_method=PUT&name=warp")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.body(), "warp");
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/item")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("name=warp")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), 405);
+}
+
+#[tokio::test]
+async fn method_not_allowed_does_not_allocate() {
+    let routes = warp::get()
+        .map(warp::reply)
+        .or(warp::put().map(warp::reply))
+        .or(warp::post().map(warp::reply));
+
+    // The same filters run, but `DELETE` is rejected by one more of them.
+    let post = allocations(warp::test::request().method("POST").filter(&routes)).await;
+    let delete = allocations(warp::test::request().method("DELETE").filter(&routes)).await;
+    assert_eq!(delete, post);
+
+    let resp = warp::test::request().method("DELETE").reply(&routes).await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["allow"], "GET, POST, PUT");
+}
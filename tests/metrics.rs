@@ -0,0 +1,63 @@
+#![deny(warnings)]
+use warp::http::StatusCode;
+use warp::Filter;
+
+#[tokio::test]
+async fn records_routes() {
+    let _ = pretty_env_logger::try_init();
+
+    let metrics = warp::metrics::Metrics::new();
+    let user = warp::path!("users" / u32)
+        .and_then(|id: u32| async move {
+            if id == 0 {
+                Err(warp::reject::custom(NoSuchUser))
+            } else {
+                Ok(format!("user #{}", id))
+            }
+        })
+        .with(metrics.route("/users/:id"));
+    let teapot = warp::path("tea")
+        .map(|| warp::reply::with_status("short and stout", StatusCode::IM_A_TEAPOT))
+        .with(metrics.route("/tea"));
+    let routes = user.or(teapot).or(metrics.exposition());
+
+    for path in &["/users/1", "/users/2", "/users/0", "/tea", "/nope"] {
+        warp::test::request().path(path).reply(&routes).await;
+    }
+
+    let res = warp::test::request().path("/metrics").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/plain; version=0.0.4");
+
+    let body = std::str::from_utf8(res.body()).unwrap();
+    let expected = [
+        r#"warp_http_requests_total{route="/tea",method="GET",status="4xx"} 1"#,
+        r#"warp_http_requests_total{route="/users/:id",method="GET",status="2xx"} 2"#,
+        r#"warp_http_requests_total{route="/users/:id",method="GET",status="5xx"} 1"#,
+        r#"warp_http_request_duration_seconds_bucket{route="/users/:id",method="GET",le="+Inf"} 3"#,
+        r#"warp_http_request_duration_seconds_count{route="/tea",method="GET"} 1"#,
+        "# TYPE warp_http_request_duration_seconds histogram",
+    ];
+    for line in &expected {
+        assert!(body.lines().any(|l| l == *line), "{} in:\n{}", line, body);
+    }
+    // unmatched requests aren't recorded for any route
+    assert!(!body.contains("/nope"));
+    assert_eq!(body.matches("requests_total{").count(), 3);
+}
+
+#[derive(Debug)]
+struct NoSuchUser;
+
+impl warp::reject::Reject for NoSuchUser {}
+
+#[tokio::test]
+async fn escapes_labels() {
+    let metrics = warp::metrics::Metrics::new();
+    let route = warp::any().map(warp::reply).with(metrics.route("a\"b\\c"));
+    warp::test::request().reply(&route).await;
+
+    assert!(metrics
+        .render()
+        .contains(r#"{route="a\"b\\c",method="GET",status="2xx"} 1"#));
+}
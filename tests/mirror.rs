@@ -0,0 +1,116 @@
+#![deny(warnings)]
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use warp::http::Request;
+use warp::Filter;
+
+fn channel() -> (
+    warp::mirror::Mirror,
+    mpsc::UnboundedReceiver<Request<Bytes>>,
+) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mirror = warp::mirror::handler(move |req: Request<Bytes>| {
+        let _ = tx.send(req);
+        async {}
+    });
+    (mirror, rx)
+}
+
+#[tokio::test]
+async fn handler() {
+    let _ = pretty_env_logger::try_init();
+
+    let (mirror, mut rx) = channel();
+    let route = warp::path("echo")
+        .and(warp::body::bytes())
+        .map(|body: Bytes| body.to_vec())
+        .with(mirror);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/echo?q=1")
+        .header("x-trace", "abc")
+        .body("hello")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "hello");
+
+    let req = rx.recv().await.expect("mirrored");
+    assert_eq!(req.method(), "POST");
+    assert_eq!(req.uri(), "/echo?q=1");
+    assert_eq!(req.headers()["x-trace"], "abc");
+    assert_eq!(req.body(), "hello");
+
+    // Rejected requests aren't mirrored.
+    let res = warp::test::request().path("/other").reply(&route).await;
+    assert_eq!(res.status(), 404);
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn max_body() {
+    let _ = pretty_env_logger::try_init();
+
+    let (mirror, mut rx) = channel();
+    let route = warp::body::bytes()
+        .map(|body: Bytes| body.to_vec())
+        .with(mirror.max_body(4));
+
+    let res = warp::test::request().body("hello").reply(&route).await;
+    assert_eq!(res.body(), "hello");
+    tokio::time::delay_for(Duration::from_millis(10)).await;
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn max_in_flight() {
+    let _ = pretty_env_logger::try_init();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mirror = warp::mirror::handler(move |req: Request<Bytes>| {
+        let _ = tx.send(req);
+        tokio::time::delay_for(Duration::from_millis(100))
+    })
+    .max_in_flight(1);
+    let route = warp::any().map(warp::reply).with(mirror);
+
+    warp::test::request().path("/1").reply(&route).await;
+    warp::test::request().path("/2").reply(&route).await;
+    tokio::time::delay_for(Duration::from_millis(10)).await;
+
+    assert_eq!(rx.recv().await.unwrap().uri(), "/1");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn upstream() {
+    let _ = pretty_env_logger::try_init();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let shadow = warp::body::bytes().and(warp::path::full()).map(
+        move |body: Bytes, path: warp::path::FullPath| {
+            let _ = tx.send((path.as_str().to_owned(), body));
+            "shadow"
+        },
+    );
+    let (addr, server) = warp::serve(shadow).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let route = warp::any()
+        .map(|| "primary")
+        .with(warp::mirror::upstream(&format!("http://{}", addr)));
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/items/1")
+        .body("data")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "primary");
+
+    let (path, body) = rx.recv().await.expect("mirrored upstream");
+    assert_eq!(path, "/items/1");
+    assert_eq!(body, "data");
+}
@@ -52,3 +52,53 @@ async fn form_fields() {
     assert_eq!(&vec[0].0, "foo");
     assert_eq!(&vec[0].1, b"bar");
 }
+
+#[tokio::test]
+async fn test_form_builder() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = multipart::form().and_then(|form: multipart::FormData| async {
+        form.and_then(|part| {
+            let meta = (
+                part.name().to_owned(),
+                part.filename().map(ToOwned::to_owned),
+                part.content_type().map(ToOwned::to_owned),
+            );
+            part.stream()
+                .try_fold(Vec::new(), |mut vec, data| {
+                    vec.put(data);
+                    async move { Ok(vec) }
+                })
+                .map_ok(move |vec| (meta, vec))
+        })
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| -> warp::Rejection { panic!("multipart error: {:?}", e) })
+    });
+
+    let form = warp::test::MultipartForm::new().text("name", "warp").file(
+        "logo",
+        "logo.png",
+        "image/png",
+        &b"\x89PNG\r\n"[..],
+    );
+    let parts = warp::test::request()
+        .method("POST")
+        .multipart(form)
+        .filter(&route)
+        .await
+        .unwrap();
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].0, ("name".to_owned(), None, None));
+    assert_eq!(parts[0].1, b"warp");
+    assert_eq!(
+        parts[1].0,
+        (
+            "logo".to_owned(),
+            Some("logo.png".to_owned()),
+            Some("image/png".to_owned())
+        )
+    );
+    assert_eq!(parts[1].1, b"\x89PNG\r\n");
+}
@@ -0,0 +1,45 @@
+#![deny(warnings)]
+use futures::{stream, StreamExt};
+use serde_derive::Serialize;
+use warp::Filter;
+
+#[derive(Debug, Serialize)]
+struct Row {
+    id: u32,
+}
+
+#[tokio::test]
+async fn ndjson() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        let rows = stream::iter((1..=3).map(|id| Row { id }));
+        warp::reply::ndjson(rows)
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(res.body(), "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n");
+}
+
+#[tokio::test]
+async fn ndjson_streams() {
+    use warp::Reply;
+
+    let _ = pretty_env_logger::try_init();
+
+    let (mut tx, rx) = tokio::sync::mpsc::channel(1);
+    let mut body = warp::reply::ndjson(rx).into_response().into_body();
+
+    tx.send(Row { id: 1 }).await.unwrap();
+    let line = body.next().await.unwrap().unwrap();
+    assert_eq!(line, "{\"id\":1}\n");
+
+    tx.send(Row { id: 2 }).await.unwrap();
+    let line = body.next().await.unwrap().unwrap();
+    assert_eq!(line, "{\"id\":2}\n");
+
+    drop(tx);
+    assert!(body.next().await.is_none());
+}
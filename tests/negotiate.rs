@@ -0,0 +1,114 @@
+#![deny(warnings)]
+use serde_derive::{Deserialize, Serialize};
+use warp::Filter;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Todo {
+    id: u64,
+    text: String,
+}
+
+fn todo() -> Todo {
+    Todo {
+        id: 1,
+        text: "negotiate".to_owned(),
+    }
+}
+
+fn route(
+) -> impl Filter<Extract = (warp::reply::Negotiate,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(|| warp::reply::negotiate(todo()))
+}
+
+#[tokio::test]
+async fn json() {
+    let _ = pretty_env_logger::try_init();
+
+    for accept in &[
+        None,
+        Some("application/json"),
+        Some("*/*"),
+        Some("application/*"),
+    ] {
+        let mut req = warp::test::request();
+        if let Some(accept) = accept {
+            req = req.header("accept", *accept);
+        }
+        let res = req.reply(&route()).await;
+        assert_eq!(res.status(), 200, "{:?}", accept);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.headers()["vary"], "accept");
+        let body: Todo = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body, todo());
+    }
+}
+
+#[tokio::test]
+async fn not_acceptable() {
+    let _ = pretty_env_logger::try_init();
+
+    for accept in &["text/html", "application/json;q=0", "*/*;q=0"] {
+        let res = warp::test::request()
+            .header("accept", *accept)
+            .reply(&route())
+            .await;
+        assert_eq!(res.status(), 406, "{}", accept);
+        assert_eq!(res.body(), "");
+    }
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn msgpack() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .header("accept", "application/json;q=0.5, application/x-msgpack")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/msgpack");
+    let body: Todo = rmp_serde::from_slice(res.body()).unwrap();
+    assert_eq!(body, todo());
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn cbor() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request()
+        .header("accept", "application/cbor")
+        .reply(&route())
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/cbor");
+    let body: Todo = serde_cbor::from_slice(res.body()).unwrap();
+    assert_eq!(body, todo());
+}
+
+#[cfg(feature = "yaml")]
+#[tokio::test]
+async fn yaml_default() {
+    use warp::reply::Format;
+
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| warp::reply::negotiate(todo()).default_format(Format::Yaml));
+
+    let res = warp::test::request()
+        .header("accept", "*/*")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/yaml");
+    let body: Todo = serde_yaml::from_slice(res.body()).unwrap();
+    assert_eq!(body, todo());
+
+    // an explicit preference wins over the default
+    let res = warp::test::request()
+        .header("accept", "text/yaml;q=0.1, application/json")
+        .reply(&route)
+        .await;
+    assert_eq!(res.headers()["content-type"], "application/json");
+}
@@ -0,0 +1,50 @@
+#![deny(warnings)]
+use warp::http::StatusCode;
+use warp::panic::Panic;
+use warp::{Filter, Reply};
+
+#[tokio::test]
+async fn catch() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path!("divide" / u32)
+        .map(|n: u32| (100 / n).to_string())
+        .with(warp::panic::catch());
+
+    let res = warp::test::request().path("/divide/4").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "25");
+
+    let res = warp::test::request().path("/divide/0").reply(&route).await;
+    assert_eq!(res.status(), 500);
+    assert_eq!(res.body(), "");
+
+    // panics in async handlers are caught too
+    let route = warp::any()
+        .and_then(|| async {
+            tokio::time::delay_for(std::time::Duration::from_millis(1)).await;
+            if true {
+                panic!("async {}", "oops");
+            }
+            Ok::<_, warp::Rejection>("unreachable")
+        })
+        .with(warp::panic::catch().handler(|panic: &Panic| {
+            warp::reply::with_status(
+                format!(
+                    "{} {}: {}",
+                    panic.method(),
+                    panic.path(),
+                    panic.message().unwrap_or("?")
+                ),
+                StatusCode::SERVICE_UNAVAILABLE,
+            )
+            .into_response()
+        }));
+    let res = warp::test::request()
+        .method("POST")
+        .path("/jobs")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.body(), "POST /jobs: async oops");
+}
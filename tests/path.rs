@@ -59,6 +59,71 @@ async fn param() {
     );
 }
 
+#[tokio::test]
+async fn param_not_decoded() {
+    let _ = pretty_env_logger::try_init();
+
+    let s = warp::path::param::<String>();
+    let req = warp::test::request().path("/caf%C3%A9");
+    assert_eq!(req.filter(&s).await.unwrap(), "caf%C3%A9");
+
+    let req = warp::test::request().path("/..%2Fetc");
+    assert_eq!(req.filter(&s).await.unwrap(), "..%2Fetc");
+}
+
+#[tokio::test]
+async fn param_decoded() {
+    let _ = pretty_env_logger::try_init();
+
+    let num = warp::path::param_decoded::<u32>();
+    let req = warp::test::request().path("/%31%32");
+    assert_eq!(req.filter(&num).await.unwrap(), 12);
+
+    let s = warp::path::param_decoded::<String>();
+    let req = warp::test::request().path("/caf%C3%A9%20au%20lait");
+    assert_eq!(req.filter(&s).await.unwrap(), "café au lait");
+
+    // malformed escapes are kept as is
+    let req = warp::test::request().path("/100%");
+    assert_eq!(req.filter(&s).await.unwrap(), "100%");
+
+    // escapes of invalid UTF-8 never match
+    let req = warp::test::request().path("/%FF");
+    assert!(!req.matches(&s).await);
+
+    // decoded separators and dot segments never match
+    for path in &["/..%2Fetc", "/a%2Fb", "/a%5Cb", "/%2E%2E", "/%2e", "/.%2E"] {
+        let req = warp::test::request().path(path);
+        assert!(!req.matches(&s).await, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn mount() {
+    let _ = pretty_env_logger::try_init();
+
+    let users = warp::path!("users" / u32).map(|id| format!("user #{}", id));
+    let api = warp::path::mount("/api/v1/", users).or(warp::path!("api" / ..).map(|| "api"));
+
+    let req = warp::test::request().path("/api/v1/users/7");
+    assert_eq!(req.reply(&api).await.body(), "user #7");
+
+    let req = warp::test::request().path("/api/v2/users/7");
+    assert_eq!(req.reply(&api).await.body(), "api");
+
+    let req = warp::test::request().path("/api/v1");
+    assert_eq!(req.reply(&api).await.body(), "api");
+
+    let req = warp::test::request().path("/api/v1x/users/7");
+    assert_eq!(req.reply(&api).await.body(), "api");
+}
+
+#[test]
+#[should_panic]
+fn mount_empty_segment() {
+    let _ = warp::path::mount("api//v1", warp::any());
+}
+
 #[tokio::test]
 async fn end() {
     let _ = pretty_env_logger::try_init();
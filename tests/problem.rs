@@ -0,0 +1,126 @@
+#![deny(warnings)]
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn body_json(body: &[u8]) -> Value {
+    serde_json::from_slice(body).expect("problem json")
+}
+
+#[tokio::test]
+async fn problem() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        warp::reply::problem(
+            StatusCode::FORBIDDEN,
+            "https://example.com/probs/out-of-credit",
+            "You do not have enough credit.",
+            "Your current balance is 30, but that costs 50.",
+        )
+        .instance("/account/12345/msgs/abc")
+        .extension("balance", 30)
+        .extension("status", "ignored")
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.headers()["content-type"], "application/problem+json");
+    assert_eq!(
+        body_json(res.body()),
+        json!({
+            "type": "https://example.com/probs/out-of-credit",
+            "title": "You do not have enough credit.",
+            "status": 403,
+            "detail": "Your current balance is 30, but that costs 50.",
+            "instance": "/account/12345/msgs/abc",
+            "balance": 30,
+        })
+    );
+}
+
+#[tokio::test]
+async fn recover_not_found() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("hello")
+        .map(warp::reply)
+        .recover(warp::reject::recover_problem);
+
+    let res = warp::test::request().path("/nope").reply(&route).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers()["content-type"], "application/problem+json");
+    assert_eq!(
+        body_json(res.body()),
+        json!({
+            "type": "about:blank",
+            "title": "Not Found",
+            "status": 404,
+        })
+    );
+}
+
+#[tokio::test]
+async fn recover_known() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::post()
+        .map(warp::reply)
+        .recover(warp::reject::recover_problem);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(
+        body_json(res.body()),
+        json!({
+            "type": "about:blank",
+            "title": "Method Not Allowed",
+            "status": 405,
+            "detail": "HTTP method not allowed",
+        })
+    );
+
+    // headers of the rejection are kept
+    let route = warp::auth::basic("test realm")
+        .map(|user: String, _password: String| user)
+        .recover(warp::reject::recover_problem);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.headers()["content-type"], "application/problem+json");
+    assert_eq!(
+        res.headers()["www-authenticate"],
+        "Basic realm=\"test realm\""
+    );
+}
+
+#[tokio::test]
+async fn recover_custom() {
+    #[derive(Debug)]
+    struct Nope;
+
+    impl warp::reject::Reject for Nope {}
+
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any()
+        .and_then(|| async { Err::<String, _>(warp::reject::custom(Nope)) })
+        .recover(warp::reject::recover_problem)
+        .recover(|err: warp::Rejection| async move {
+            match err.find::<Nope>() {
+                Some(Nope) => Ok("custom"),
+                None => Err(err),
+            }
+        });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "custom");
+}
+
+#[test]
+fn schema() {
+    let schema = warp::reply::Problem::schema();
+    assert_eq!(schema["required"], json!(["type", "title", "status"]));
+    assert_eq!(schema["properties"]["status"]["type"], "integer");
+}
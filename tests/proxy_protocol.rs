@@ -0,0 +1,70 @@
+#![deny(warnings)]
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use warp::Filter;
+
+async fn send(addr: SocketAddr, header: &[u8]) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(header).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut res = String::new();
+    let _ = stream.read_to_string(&mut res).await;
+    res
+}
+
+fn server() -> SocketAddr {
+    let route = warp::addr::remote().map(|addr: Option<SocketAddr>| addr.unwrap().to_string());
+    let (addr, server) = warp::serve(route)
+        .proxy_protocol()
+        .bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
+
+#[tokio::test]
+async fn proxy_protocol_v1() {
+    let _ = pretty_env_logger::try_init();
+    let addr = server();
+
+    let res = send(addr, b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200 OK"), "{}", res);
+    assert!(res.ends_with("192.0.2.1:56324"), "{}", res);
+
+    let res = send(addr, b"PROXY TCP6 2001:db8::1 2001:db8::2 8080 443\r\n").await;
+    assert!(res.ends_with("[2001:db8::1]:8080"), "{}", res);
+
+    // The address of the connection is used for unknown protocols.
+    let res = send(addr, b"PROXY UNKNOWN\r\n").await;
+    assert!(res.contains("\r\n\r\n127.0.0.1:"), "{}", res);
+}
+
+#[tokio::test]
+async fn proxy_protocol_v2() {
+    let _ = pretty_env_logger::try_init();
+    let addr = server();
+
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    // Version 2, PROXY command, TCP over IPv4, 12 bytes of addresses.
+    header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+    header.extend_from_slice(&[203, 0, 113, 7, 10, 0, 0, 1]);
+    header.extend_from_slice(&4321u16.to_be_bytes());
+    header.extend_from_slice(&443u16.to_be_bytes());
+
+    let res = send(addr, &header).await;
+    assert!(res.starts_with("HTTP/1.1 200 OK"), "{}", res);
+    assert!(res.ends_with("203.0.113.7:4321"), "{}", res);
+}
+
+#[tokio::test]
+async fn proxy_protocol_missing_header() {
+    let _ = pretty_env_logger::try_init();
+    let addr = server();
+
+    let res = send(addr, b"").await;
+    assert_eq!(res, "");
+}
@@ -128,6 +128,41 @@ struct MyRequiredArgs {
     baz: String,
 }
 
+#[tokio::test]
+async fn partial_query() {
+    let partial = warp::query::partial::<Page>();
+
+    let req = warp::test::request().path("/?limit=10&color=red&size=x%20l");
+
+    let (page, rest) = req.filter(&partial).await.unwrap();
+    assert_eq!(
+        page,
+        Page {
+            offset: None,
+            limit: Some(10)
+        }
+    );
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest["color"], "red");
+    assert_eq!(rest["size"], "x l");
+
+    let req = warp::test::request().path("/?limit=ten&color=red");
+    assert!(!req.matches(&partial).await);
+
+    // Maps take every parameter.
+    let partial = warp::query::partial::<HashMap<String, String>>();
+    let req = warp::test::request().path("/?color=red");
+    let (all, rest) = req.filter(&partial).await.unwrap();
+    assert_eq!(all["color"], "red");
+    assert!(rest.is_empty());
+}
+
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+struct Page {
+    offset: Option<u32>,
+    limit: Option<u32>,
+}
+
 #[tokio::test]
 async fn raw_query() {
     let as_raw = warp::query::raw();
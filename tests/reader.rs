@@ -0,0 +1,45 @@
+#![deny(warnings)]
+use warp::Filter;
+
+#[tokio::test]
+async fn from_reader() {
+    let _ = pretty_env_logger::try_init();
+
+    let contents = std::fs::read("README.md").expect("fs::read README.md");
+    let len = contents.len() as u64;
+    let route = warp::any().and_then(move || async move {
+        let file = tokio::fs::File::open("README.md")
+            .await
+            .map_err(|_| warp::reject::not_found())?;
+        Ok::<_, warp::Rejection>(warp::reply::from_reader(file, Some(len)))
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/octet-stream");
+    assert_eq!(res.headers()["content-length"], len.to_string());
+    assert!(!res.headers().contains_key("content-disposition"));
+    assert_eq!(res.body(), &contents[..]);
+}
+
+#[tokio::test]
+async fn attachment() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| warp::reply::attachment("hello.txt", &b"hello"[..]));
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"hello.txt\""
+    );
+    assert!(!res.headers().contains_key("content-length"));
+    assert_eq!(res.body(), "hello");
+
+    let route =
+        warp::any().map(|| warp::reply::attachment("hello.txt", &b"hello"[..]).content_length(5));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.headers()["content-length"], "5");
+    assert_eq!(res.body(), "hello");
+}
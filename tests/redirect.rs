@@ -11,3 +11,64 @@ async fn redirect_uri() {
     assert_eq!(resp.status(), 301);
     assert_eq!(resp.headers()["location"], "/over-there");
 }
+
+#[tokio::test]
+async fn redirect_permanent_uri() {
+    let over_there = warp::any().map(|| warp::redirect::permanent(Uri::from_static("/v2")));
+
+    let req = warp::test::request().method("POST");
+    let resp = req.reply(&over_there).await;
+
+    assert_eq!(resp.status(), 308);
+    assert_eq!(resp.headers()["location"], "/v2");
+}
+
+#[tokio::test]
+async fn redirect_preserve_query() {
+    let search =
+        warp::path("search").map(|| warp::redirect::temporary_preserve_query("/v2/search"));
+
+    let resp = warp::test::request()
+        .path("/search?q=warp&page=2")
+        .reply(&search)
+        .await;
+    assert_eq!(resp.status(), 307);
+    assert_eq!(resp.headers()["location"], "/v2/search?q=warp&page=2");
+
+    let resp = warp::test::request().path("/search").reply(&search).await;
+    assert_eq!(resp.headers()["location"], "/v2/search");
+
+    let merged = warp::path("search").map(|| {
+        warp::redirect::permanent_preserve_query(String::from("/v2/search?lang=en#results"))
+    });
+    let resp = warp::test::request()
+        .path("/search?q=warp")
+        .reply(&merged)
+        .await;
+    assert_eq!(resp.status(), 308);
+    assert_eq!(
+        resp.headers()["location"],
+        "/v2/search?lang=en&q=warp#results"
+    );
+}
+
+#[tokio::test]
+async fn redirect_relative() {
+    let admin = warp::path("admin")
+        .and(warp::path::end())
+        .map(|| warp::redirect::temporary("dashboard"));
+    let resp = warp::test::request().path("/admin").reply(&admin).await;
+    assert_eq!(resp.headers()["location"], "/admin/dashboard");
+
+    let api = warp::path!("api" / "v1" / ..).map(|| warp::redirect("../v2/"));
+    let resp = warp::test::request()
+        .path("/api/v1/users")
+        .reply(&api)
+        .await;
+    assert_eq!(resp.status(), 301);
+    assert_eq!(resp.headers()["location"], "/api/v2/");
+
+    let absolute = warp::path("old").map(|| warp::redirect("https://example.com/new"));
+    let resp = warp::test::request().path("/old").reply(&absolute).await;
+    assert_eq!(resp.headers()["location"], "https://example.com/new");
+}
@@ -0,0 +1,88 @@
+#![deny(warnings)]
+use serde_json::{json, Value};
+use warp::Filter;
+
+fn body_json(body: &[u8]) -> Value {
+    serde_json::from_slice(body).expect("rejection json")
+}
+
+#[tokio::test]
+async fn not_found() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("hello")
+        .map(warp::reply)
+        .recover(warp::reject::recover_json);
+
+    let res = warp::test::request().path("/nope").reply(&route).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(
+        body_json(res.body()),
+        json!({
+            "code": "not_found",
+            "message": "Not Found",
+            "details": null,
+        })
+    );
+}
+
+#[tokio::test]
+async fn missing_header() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::header::<String>("x-api-key")
+        .map(|_| warp::reply())
+        .recover(warp::reject::recover_json);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(
+        body_json(res.body()),
+        json!({
+            "code": "missing_header",
+            "message": "Missing request header \"x-api-key\"",
+            "details": { "header": "x-api-key" },
+        })
+    );
+}
+
+#[tokio::test]
+async fn preferred_rejection() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::get()
+        .map(warp::reply)
+        .or(warp::post()
+            .and(warp::body::content_length_limit(4))
+            .map(warp::reply))
+        .recover(warp::reject::recover_json);
+
+    let res = warp::test::request()
+        .method("POST")
+        .body("too long")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 413);
+    assert_eq!(body_json(res.body())["code"], "payload_too_large");
+}
+
+#[tokio::test]
+async fn custom_passes_through() {
+    let _ = pretty_env_logger::try_init();
+
+    #[derive(Debug)]
+    struct Nope;
+    impl warp::reject::Reject for Nope {}
+
+    let rejection = warp::reject::custom(Nope);
+    assert_eq!(rejection.to_json()["code"], "unhandled_rejection");
+
+    let route = warp::any()
+        .and_then(|| async { Err::<String, _>(warp::reject::custom(Nope)) })
+        .recover(warp::reject::recover_json);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 500);
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+}
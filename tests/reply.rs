@@ -0,0 +1,64 @@
+#![deny(warnings)]
+use warp::http::StatusCode;
+use warp::reply::Either;
+use warp::{Filter, Reply};
+
+#[tokio::test]
+async fn result() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path::param().map(|id: u32| {
+        if id == 0 {
+            Err(StatusCode::NOT_FOUND)
+        } else {
+            Ok(format!("item #{}", id))
+        }
+    });
+
+    let res = warp::test::request().path("/1").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "item #1");
+
+    let res = warp::test::request().path("/0").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn either() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path::param().map(|id: u32| {
+        if id == 0 {
+            Either::Right(warp::reply::with_status("gone", StatusCode::GONE))
+        } else {
+            Either::Left(warp::reply::json(&id))
+        }
+    });
+
+    let res = warp::test::request().path("/2").reply(&route).await;
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(res.body(), "2");
+
+    let res = warp::test::request().path("/0").reply(&route).await;
+    assert_eq!(res.status(), 410);
+    assert_eq!(res.body(), "gone");
+}
+
+#[tokio::test]
+async fn boxed() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path::param().map(|id: u32| -> Box<dyn Reply> {
+        if id == 0 {
+            Box::new(StatusCode::NO_CONTENT)
+        } else {
+            Box::new(id.to_string())
+        }
+    });
+
+    let res = warp::test::request().path("/3").reply(&route).await;
+    assert_eq!(res.body(), "3");
+
+    let res = warp::test::request().path("/0").reply(&route).await;
+    assert_eq!(res.status(), 204);
+}
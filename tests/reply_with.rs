@@ -62,3 +62,194 @@ async fn default_header() {
 
     assert_eq!(resp.headers()["foo"], "sean", "doesn't replace header");
 }
+
+#[tokio::test]
+async fn cache_control() {
+    use std::time::Duration;
+    use warp::reply::with::CachePolicy;
+
+    let route = warp::any()
+        .map(warp::reply)
+        .with(warp::reply::with::cache_control(
+            CachePolicy::max_age(Duration::from_secs(3600))
+                .public()
+                .stale_while_revalidate(Duration::from_secs(60))
+                .immutable(),
+        ));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(
+        res.headers()["cache-control"],
+        "public, max-age=3600, stale-while-revalidate=60, immutable"
+    );
+
+    let route = warp::any()
+        .map(warp::reply)
+        .with(warp::reply::with::cache_control(
+            CachePolicy::no_store().private(),
+        ));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.headers()["cache-control"], "private, no-store");
+}
+
+#[tokio::test]
+async fn etag() {
+    let route = warp::path::param()
+        .map(|n: u32| {
+            warp::reply::with_header(format!("count: {}", n), "cache-control", "no-cache")
+        })
+        .with(warp::reply::with::etag());
+
+    let res = warp::test::request().path("/1").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "count: 1");
+    let etag = res.headers()["etag"].clone();
+    // tags are stable between releases, made of the FNV-1a hash and length
+    assert_eq!(etag, "\"99af78431907501d-8\"");
+
+    // the same body has the same tag, another body another one
+    let res = warp::test::request().path("/1").reply(&route).await;
+    assert_eq!(res.headers()["etag"], etag);
+    let res = warp::test::request().path("/2").reply(&route).await;
+    assert_ne!(res.headers()["etag"], etag);
+
+    let res = warp::test::request()
+        .path("/1")
+        .header("if-none-match", etag.clone())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(res.body(), "");
+    assert_eq!(res.headers()["etag"], etag);
+    assert_eq!(res.headers()["cache-control"], "no-cache");
+    assert!(res.headers().get("content-type").is_none());
+
+    let res = warp::test::request()
+        .path("/2")
+        .header("if-none-match", etag.clone())
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "count: 2");
+
+    // unsafe methods aren't tagged
+    let res = warp::test::request()
+        .method("POST")
+        .path("/1")
+        .header("if-none-match", etag)
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert!(res.headers().get("etag").is_none());
+}
+
+#[tokio::test]
+async fn etag_unbounded_bodies() {
+    use warp::hyper::body::HttpBody;
+    use warp::Reply;
+
+    // streams are passed through without being buffered
+    let route = warp::any()
+        .map(|| warp::reply::ndjson(futures::stream::repeat("line")))
+        .with(warp::reply::with::etag());
+    let res = warp::test::request().filter(&route).await.unwrap();
+    let mut res = res.into_response();
+    assert!(res.headers().get("etag").is_none());
+    assert!(res.body_mut().data().await.unwrap().is_ok());
+
+    // so are bodies too large to be buffered
+    let route = warp::any()
+        .map(|| vec![0u8; 2 * 1024 * 1024])
+        .with(warp::reply::with::etag());
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body().len(), 2 * 1024 * 1024);
+    assert!(res.headers().get("etag").is_none());
+}
+
+#[tokio::test]
+async fn preload_links() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        let links = warp::reply::preload_links()
+            .preload("/style.css", "style")
+            .preload("/font.woff2", "font")
+            .preconnect("https://cdn.example.com");
+        let reply = warp::reply::with_header("hello", "link", "</next>; rel=prefetch");
+        warp::reply::with_preload_links(reply, links)
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "hello");
+    let links: Vec<_> = res
+        .headers()
+        .get_all("link")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect();
+    assert_eq!(
+        links,
+        [
+            "</next>; rel=prefetch",
+            "</style.css>; rel=preload; as=style",
+            "</font.woff2>; rel=preload; as=font; crossorigin",
+            "<https://cdn.example.com>; rel=preconnect",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn security_headers() {
+    use warp::reply::with::{FrameOptions, ReferrerPolicy, SecurityPolicy};
+
+    let route = warp::any()
+        .map(warp::reply)
+        .with(warp::reply::with::security_headers(SecurityPolicy::new()));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(
+        res.headers()["content-security-policy"],
+        "default-src 'self'; object-src 'none'; frame-ancestors 'none'"
+    );
+    assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+    assert_eq!(
+        res.headers()["referrer-policy"],
+        "strict-origin-when-cross-origin"
+    );
+    assert_eq!(res.headers()["x-frame-options"], "DENY");
+    assert!(res.headers().get("permissions-policy").is_none());
+
+    let policy = SecurityPolicy::new()
+        .content_security_policy("default-src", &["'self'", "https://cdn.example.com"])
+        .content_security_policy("img-src", &["*"])
+        .referrer_policy(ReferrerPolicy::NoReferrer)
+        .no_frame_options()
+        .permission("geolocation", &[])
+        .permission("camera", &["self", "https://meet.example.com"]);
+    // headers already set by the route are kept
+    let route = warp::any()
+        .map(|| warp::reply::with_header(warp::reply(), "x-frame-options", "SAMEORIGIN"))
+        .with(warp::reply::with::security_headers(policy));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(
+        res.headers()["content-security-policy"],
+        "default-src 'self' https://cdn.example.com; object-src 'none'; \
+         frame-ancestors 'none'; img-src *"
+    );
+    assert_eq!(res.headers()["referrer-policy"], "no-referrer");
+    assert_eq!(res.headers()["x-frame-options"], "SAMEORIGIN");
+    assert_eq!(
+        res.headers()["permissions-policy"],
+        "geolocation=(), camera=(self \"https://meet.example.com\")"
+    );
+
+    let route = warp::any()
+        .map(warp::reply)
+        .with(warp::reply::with::security_headers(
+            SecurityPolicy::new()
+                .no_content_security_policy()
+                .frame_options(FrameOptions::SameOrigin),
+        ));
+    let res = warp::test::request().reply(&route).await;
+    assert!(res.headers().get("content-security-policy").is_none());
+    assert_eq!(res.headers()["x-frame-options"], "SAMEORIGIN");
+}
@@ -0,0 +1,204 @@
+#![deny(warnings)]
+use warp::router::{Params, Router};
+use warp::Filter;
+
+#[tokio::test]
+async fn insert_and_remove() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+
+    let res = warp::test::request().path("/hello").reply(&router).await;
+    assert_eq!(res.status(), 404);
+
+    router.insert("GET", "/hello", warp::any().map(|| "hello"));
+    assert_eq!(router.len(), 1);
+
+    let res = warp::test::request().path("/hello").reply(&router).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "hello");
+
+    // replaces the existing handler
+    router.insert("GET", "hello/", warp::any().map(|| "hi"));
+    assert_eq!(router.len(), 1);
+
+    let res = warp::test::request().path("/hello").reply(&router).await;
+    assert_eq!(res.body(), "hi");
+
+    assert!(router.remove("GET", "/hello"));
+    assert!(!router.remove("GET", "/hello"));
+    assert!(router.is_empty());
+
+    let res = warp::test::request().path("/hello").reply(&router).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn params() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    router.insert(
+        "GET",
+        "/users/:id/posts/:post",
+        warp::router::params().map(|params: Params| {
+            format!(
+                "{} {}",
+                params.get("id").unwrap(),
+                params.get("post").unwrap()
+            )
+        }),
+    );
+
+    let res = warp::test::request()
+        .path("/users/7/posts/42")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "7 42");
+
+    let res = warp::test::request()
+        .path("/users/7/posts")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = warp::test::request()
+        .path("/users/7/posts/42/comments")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn method_not_allowed() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    router.insert("POST", "/submit", warp::any().map(warp::reply));
+
+    let res = warp::test::request().path("/submit").reply(&router).await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(res.headers()["allow"], "POST");
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/submit")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    router.insert("PUT", "/submit", warp::any().map(warp::reply));
+    let route = router.clone().with(warp::filters::method::auto_options());
+    let res = warp::test::request()
+        .method("OPTIONS")
+        .path("/submit")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 204);
+    assert_eq!(res.headers()["allow"], "POST, PUT, OPTIONS");
+}
+
+#[tokio::test]
+async fn wildcard_leaves_tail_unmatched() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    router.insert(
+        "GET",
+        "/plugins/*",
+        warp::path("status").and(warp::path::end()).map(|| "ok"),
+    );
+
+    let res = warp::test::request()
+        .path("/plugins/status")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "ok");
+
+    let res = warp::test::request()
+        .path("/plugins/other")
+        .reply(&router)
+        .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn combined_with_or() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    let routes = warp::path("static").map(|| "static").or(router.clone());
+
+    router.insert("GET", "/dynamic", warp::any().map(|| "dynamic"));
+
+    let res = warp::test::request().path("/static").reply(&routes).await;
+    assert_eq!(res.body(), "static");
+
+    let res = warp::test::request().path("/dynamic").reply(&routes).await;
+    assert_eq!(res.body(), "dynamic");
+}
+
+#[tokio::test]
+async fn most_specific_pattern_wins() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    router.insert("GET", "/files/*", warp::any().map(|| "wildcard"));
+    router.insert("GET", "/files/:name", warp::any().map(|| "param"));
+    router.insert("GET", "/files/readme", warp::any().map(|| "static"));
+
+    let res = warp::test::request()
+        .path("/files/readme")
+        .reply(&router)
+        .await;
+    assert_eq!(res.body(), "static");
+
+    let res = warp::test::request()
+        .path("/files/license")
+        .reply(&router)
+        .await;
+    assert_eq!(res.body(), "param");
+
+    let res = warp::test::request()
+        .path("/files/license/full")
+        .reply(&router)
+        .await;
+    assert_eq!(res.body(), "wildcard");
+
+    // falls back to the param when the static branch doesn't match deeper
+    router.insert("GET", "/files/:name/meta", warp::any().map(|| "meta"));
+    let res = warp::test::request()
+        .path("/files/readme/meta")
+        .reply(&router)
+        .await;
+    assert_eq!(res.body(), "meta");
+}
+
+#[tokio::test]
+async fn large_route_set() {
+    let _ = pretty_env_logger::try_init();
+
+    let router = Router::new();
+    for i in 0..1500 {
+        let body = format!("route {}", i);
+        router.insert(
+            "GET",
+            &format!("/api/v1/resource{}/:id", i),
+            warp::any().map(move || body.clone()),
+        );
+    }
+    assert_eq!(router.len(), 1500);
+
+    let res = warp::test::request()
+        .path("/api/v1/resource1234/5")
+        .reply(&router)
+        .await;
+    assert_eq!(res.body(), "route 1234");
+
+    for i in 0..1500 {
+        assert!(router.remove("GET", &format!("/api/v1/resource{}/:id", i)));
+    }
+    assert!(router.is_empty());
+}
@@ -0,0 +1,43 @@
+#![deny(warnings)]
+use warp::http::StatusCode;
+use warp::Filter;
+
+#[tokio::test]
+async fn routes() {
+    let _ = pretty_env_logger::try_init();
+
+    let users = warp::routes![
+        warp::path!("users").map(|| "all users"),
+        warp::path!("users" / u32).map(|id| format!("user #{}", id)),
+    ];
+    let api = warp::routes![
+        warp::path!("health").map(warp::reply),
+        users,
+        warp::path!("teapot").map(|| StatusCode::IM_A_TEAPOT)
+    ];
+
+    let res = warp::test::request().path("/health").reply(&api).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/users/7").reply(&api).await;
+    assert_eq!(res.body(), "user #7");
+
+    let res = warp::test::request().path("/teapot").reply(&api).await;
+    assert_eq!(res.status(), 418);
+
+    let res = warp::test::request().path("/nope").reply(&api).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn routes_rejection() {
+    let _ = pretty_env_logger::try_init();
+
+    // The most relevant rejection is kept, as with `or`.
+    let api = warp::routes![
+        warp::path!("a").and(warp::post()).map(warp::reply),
+        warp::path!("b").map(warp::reply),
+    ];
+    let res = warp::test::request().path("/a").reply(&api).await;
+    assert_eq!(res.status(), 405);
+}
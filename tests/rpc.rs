@@ -0,0 +1,126 @@
+#![deny(warnings)]
+use serde_json::{json, Value};
+use warp::rpc::{RpcError, RpcRouter};
+use warp::Filter;
+
+fn endpoint() -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    let router = RpcRouter::new()
+        .method("subtract", |(a, b): (i64, i64)| async move { Ok(a - b) })
+        .method("ping", |()| async { Ok("pong") })
+        .method("fail", |()| async {
+            Err::<(), _>(RpcError::new(-1, "failed").with_data(json!({ "why": "testing" })))
+        });
+    warp::path("rpc").and(warp::rpc::json_rpc(router))
+}
+
+async fn call(body: &str) -> (u16, Option<Value>) {
+    let res = warp::test::request()
+        .method("POST")
+        .path("/rpc")
+        .body(body)
+        .reply(&endpoint())
+        .await;
+    let body = if res.body().is_empty() {
+        None
+    } else {
+        Some(serde_json::from_slice(res.body()).unwrap())
+    };
+    (res.status().as_u16(), body)
+}
+
+#[tokio::test]
+async fn single_requests() {
+    let _ = pretty_env_logger::try_init();
+
+    let (status, body) =
+        call(r#"{"jsonrpc":"2.0","method":"subtract","params":[42,23],"id":1}"#).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        Some(json!({ "jsonrpc": "2.0", "result": 19, "id": 1 }))
+    );
+
+    let (_, body) = call(r#"{"jsonrpc":"2.0","method":"ping","id":"a"}"#).await;
+    assert_eq!(
+        body,
+        Some(json!({ "jsonrpc": "2.0", "result": "pong", "id": "a" }))
+    );
+
+    // notifications get no response
+    let (status, body) = call(r#"{"jsonrpc":"2.0","method":"subtract","params":[1,2]}"#).await;
+    assert_eq!(status, 204);
+    assert_eq!(body, None);
+}
+
+#[tokio::test]
+async fn errors() {
+    let _ = pretty_env_logger::try_init();
+
+    let (status, body) = call(r#"{"jsonrpc":"2.0","method":"foobar","id":"1"}"#).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        Some(json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": "1",
+        }))
+    );
+
+    let (_, body) = call(r#"{"jsonrpc":"2.0","method":"subtract","params":["a"],"id":2}"#).await;
+    assert_eq!(body.unwrap()["error"]["code"], -32602);
+
+    let (_, body) = call(r#"{"jsonrpc":"2.0","method":"fail","id":3}"#).await;
+    assert_eq!(
+        body.unwrap()["error"],
+        json!({ "code": -1, "message": "failed", "data": { "why": "testing" } })
+    );
+
+    let (_, body) = call(r#"{"jsonrpc":"2.0","method":"foobar,"params":"bar","baz]"#).await;
+    assert_eq!(
+        body,
+        Some(json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32700, "message": "Parse error" },
+            "id": null,
+        }))
+    );
+
+    let (_, body) = call(r#"{"jsonrpc":"2.0","method":1,"params":"bar"}"#).await;
+    assert_eq!(body.unwrap()["error"]["code"], -32600);
+
+    let (_, body) = call("[]").await;
+    assert_eq!(body.unwrap()["error"]["code"], -32600);
+}
+
+#[tokio::test]
+async fn batches() {
+    let _ = pretty_env_logger::try_init();
+
+    let (status, body) = call(
+        r#"[
+            {"jsonrpc":"2.0","method":"subtract","params":[3,1],"id":"1"},
+            {"jsonrpc":"2.0","method":"ping"},
+            1,
+            {"jsonrpc":"2.0","method":"foo.get","id":"5"}
+        ]"#,
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        Some(json!([
+            { "jsonrpc": "2.0", "result": 2, "id": "1" },
+            { "jsonrpc": "2.0", "error": { "code": -32600, "message": "Invalid Request" }, "id": null },
+            { "jsonrpc": "2.0", "error": { "code": -32601, "message": "Method not found" }, "id": "5" },
+        ]))
+    );
+
+    let (status, body) =
+        call(r#"[{"jsonrpc":"2.0","method":"ping"},{"jsonrpc":"2.0","method":"ping"}]"#).await;
+    assert_eq!(status, 204);
+    assert_eq!(body, None);
+
+    let res = warp::test::request().path("/rpc").reply(&endpoint()).await;
+    assert_eq!(res.status(), 405);
+}
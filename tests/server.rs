@@ -0,0 +1,55 @@
+#![deny(warnings)]
+use std::time::Duration;
+
+use warp::hyper::{Client, Version};
+use warp::Filter;
+
+#[tokio::test]
+async fn http2_settings() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| "hello");
+    let (addr, server) = warp::serve(route)
+        .http2_initial_stream_window_size(1024 * 1024)
+        .http2_initial_connection_window_size(4 * 1024 * 1024)
+        .http2_max_frame_size(32 * 1024)
+        .http2_max_concurrent_streams(16)
+        .http2_keep_alive_interval(Duration::from_secs(30))
+        .http2_keep_alive_timeout(Duration::from_secs(5))
+        .bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let client = Client::builder()
+        .http2_only(true)
+        .build_http::<warp::hyper::Body>();
+    let res = client
+        .get(format!("http://{}/", addr).parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.status(), 200);
+    let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "hello");
+}
+
+#[tokio::test]
+async fn serve_dynamic() {
+    let _ = pretty_env_logger::try_init();
+
+    let routes = warp::filters::DynamicFilter::new(warp::any().map(|| "v1").boxed());
+    let (addr, server) = warp::serve_dynamic(routes.clone()).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    // the connection is kept alive by the client, and serves the new routes
+    let client = Client::new();
+    let uri: warp::hyper::Uri = format!("http://{}/", addr).parse().unwrap();
+    for version in &["v1", "v2"] {
+        if *version == "v2" {
+            routes.replace(warp::any().map(|| "v2").boxed());
+        }
+        let res = client.get(uri.clone()).await.unwrap();
+        assert_eq!(res.status(), 200);
+        let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, *version);
+    }
+}
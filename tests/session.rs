@@ -0,0 +1,204 @@
+#![deny(warnings)]
+use warp::session::{CookieStore, MemoryStore, SameSite, Session, Sessions};
+use warp::Filter;
+
+fn counter() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::path("count")
+        .and(warp::session::session())
+        .map(|session: Session| {
+            let count = session.get::<u32>("count").unwrap_or(0) + 1;
+            session.insert("count", count).unwrap();
+            count.to_string()
+        })
+}
+
+fn cookie_value(res: &warp::http::Response<warp::hyper::body::Bytes>) -> String {
+    let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+    set_cookie.split(';').next().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn memory_store() {
+    let _ = pretty_env_logger::try_init();
+
+    let store = MemoryStore::new();
+    let peek = warp::path("peek")
+        .and(warp::session::session())
+        .map(|session: Session| session.get::<u32>("count").unwrap_or(0).to_string());
+    let route = counter().or(peek).with(Sessions::new(store.clone()));
+
+    let res = warp::test::request().path("/count").reply(&route).await;
+    assert_eq!(res.body(), "1");
+    let cookie = cookie_value(&res);
+    assert!(cookie.starts_with("session="));
+    assert_eq!(store.len(), 1);
+
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "2");
+
+    // unchanged sessions don't set the cookie again
+    let res = warp::test::request()
+        .path("/peek")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "2");
+    assert!(res.headers().get("set-cookie").is_none());
+
+    // unknown sessions start over
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", "session=unknown")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "1");
+    assert_ne!(cookie_value(&res), "session=unknown");
+}
+
+#[tokio::test]
+async fn memory_store_ttl() {
+    use std::time::Duration;
+
+    let _ = pretty_env_logger::try_init();
+
+    let store = MemoryStore::new().ttl(Duration::from_millis(200));
+    let route = counter().with(Sessions::new(store.clone()));
+
+    let res = warp::test::request().path("/count").reply(&route).await;
+    let cookie = cookie_value(&res);
+
+    // using the session keeps it alive
+    tokio::time::delay_for(Duration::from_millis(120)).await;
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "2");
+    tokio::time::delay_for(Duration::from_millis(120)).await;
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "3");
+
+    // expired sessions start over
+    tokio::time::delay_for(Duration::from_millis(300)).await;
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "1");
+    assert_ne!(cookie_value(&res), cookie);
+    assert_eq!(store.len(), 1);
+
+    // and sessions that aren't used again are evicted
+    tokio::time::delay_for(Duration::from_millis(300)).await;
+    warp::test::request().path("/count").reply(&route).await;
+    assert_eq!(store.len(), 1);
+}
+
+#[tokio::test]
+async fn destroy_and_renew() {
+    let _ = pretty_env_logger::try_init();
+
+    let store = MemoryStore::new();
+    let logout = warp::path("logout")
+        .and(warp::session::session())
+        .map(|session: Session| {
+            session.destroy();
+            "bye".to_owned()
+        });
+    let login = warp::path("login")
+        .and(warp::session::session())
+        .map(|session: Session| {
+            session.renew();
+            "hi".to_owned()
+        });
+    let route = counter()
+        .or(logout)
+        .unify()
+        .or(login)
+        .unify()
+        .with(Sessions::new(store.clone()));
+
+    let res = warp::test::request().path("/count").reply(&route).await;
+    let cookie = cookie_value(&res);
+
+    let res = warp::test::request()
+        .path("/login")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    let renewed = cookie_value(&res);
+    assert_ne!(renewed, cookie);
+    assert_eq!(store.len(), 1);
+
+    let res = warp::test::request()
+        .path("/logout")
+        .header("cookie", &renewed)
+        .reply(&route)
+        .await;
+    let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+    assert!(set_cookie.starts_with("session=;"));
+    assert!(set_cookie.contains("Max-Age=0"));
+    assert!(store.is_empty());
+}
+
+#[tokio::test]
+async fn cookie_store() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = counter().with(Sessions::new(CookieStore::new(&[7; 64])));
+
+    let res = warp::test::request().path("/count").reply(&route).await;
+    let cookie = cookie_value(&res);
+
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &cookie)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "2");
+
+    // a tampered cookie is ignored
+    let tampered = format!("{}x", cookie);
+    let res = warp::test::request()
+        .path("/count")
+        .header("cookie", &tampered)
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "1");
+}
+
+#[tokio::test]
+async fn cookie_attributes() {
+    let _ = pretty_env_logger::try_init();
+
+    let sessions = Sessions::new(MemoryStore::new())
+        .cookie_name("sid")
+        .path("/app")
+        .max_age(std::time::Duration::from_secs(3600))
+        .same_site(SameSite::Strict)
+        .secure(true);
+    let route = counter().with(sessions);
+
+    let res = warp::test::request().path("/count").reply(&route).await;
+    let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+    assert!(set_cookie.starts_with("sid="));
+    assert!(set_cookie.ends_with("; Path=/app; Max-Age=3600; HttpOnly; Secure; SameSite=Strict"));
+}
+
+#[tokio::test]
+async fn missing_wrapper() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request().path("/count").reply(&counter()).await;
+    assert_eq!(res.status(), 500);
+}
@@ -0,0 +1,76 @@
+#![deny(warnings)]
+use warp::Filter;
+
+#[tokio::test]
+async fn weights() {
+    let _ = pretty_env_logger::try_init();
+
+    let only_b = warp::split()
+        .branch(0, warp::any().map(|| "a"))
+        .branch(1, warp::any().map(|| "b"));
+    for _ in 0..20 {
+        let res = warp::test::request().reply(&only_b).await;
+        assert_eq!(res.body(), "b");
+    }
+
+    let half = warp::split()
+        .branch(1, warp::any().map(|| "a"))
+        .branch(1, warp::any().map(|| "b"));
+    let mut a = 0;
+    for _ in 0..200 {
+        if warp::test::request().reply(&half).await.body() == "a" {
+            a += 1;
+        }
+    }
+    assert!(a > 50 && a < 150, "a {}", a);
+}
+
+#[tokio::test]
+async fn sticky() {
+    let _ = pretty_env_logger::try_init();
+
+    let header = warp::split()
+        .branch(1, warp::any().map(|| "a"))
+        .branch(1, warp::any().map(|| "b"))
+        .sticky_header("x-user");
+    let cookie = warp::split()
+        .branch(1, warp::any().map(|| "a"))
+        .branch(1, warp::any().map(|| "b"))
+        .sticky_cookie("session");
+
+    let mut seen = Vec::new();
+    for user in 0..20 {
+        let user = user.to_string();
+        let req = || warp::test::request().header("x-user", &user);
+        let first = req().reply(&header).await.into_body();
+        for _ in 0..5 {
+            assert_eq!(req().reply(&header).await.body(), &first);
+        }
+        seen.push(first);
+
+        let cookie_header = format!("theme=dark; session={}", user);
+        let req = || warp::test::request().header("cookie", &cookie_header);
+        let first = req().reply(&cookie).await.into_body();
+        for _ in 0..5 {
+            assert_eq!(req().reply(&cookie).await.body(), &first);
+        }
+    }
+    assert!(seen.contains(&"a".into()) && seen.contains(&"b".into()));
+}
+
+#[tokio::test]
+async fn rejections() {
+    let _ = pretty_env_logger::try_init();
+
+    let empty = warp::split();
+    let res = warp::test::request().reply(&empty).await;
+    assert_eq!(res.status(), 404);
+
+    // A branch rejecting a request doesn't fall back to another.
+    let split = warp::split()
+        .branch(1, warp::path("a").map(|| "a"))
+        .sticky_header("x-user");
+    let routes = split.or(warp::any().map(|| "fallback"));
+    let res = warp::test::request().path("/b").reply(&routes).await;
+    assert_eq!(res.body(), "fallback");
+}
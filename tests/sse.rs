@@ -0,0 +1,104 @@
+#![deny(warnings)]
+use std::convert::Infallible;
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use warp::{sse::ServerSentEvent, Filter};
+
+#[tokio::test]
+async fn test_client_events() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("events").map(|| {
+        let events = stream::iter(vec![
+            Ok::<_, Infallible>(warp::sse::data("hello").boxed()),
+            Ok((
+                warp::sse::event("greeting"),
+                warp::sse::id(7),
+                warp::sse::data("line 1\nline 2"),
+            )
+                .boxed()),
+            Ok(warp::sse::retry(Duration::from_millis(1500)).boxed()),
+        ]);
+        warp::sse::reply(events)
+    });
+
+    let mut client = warp::test::sse()
+        .path("/events")
+        .connect(&route)
+        .await
+        .expect("connect");
+
+    let event = client.recv().await.unwrap();
+    assert_eq!(event.data(), Some("hello"));
+    assert_eq!(event.event(), None);
+
+    let event = client.recv().await.unwrap();
+    assert_eq!(event.event(), Some("greeting"));
+    assert_eq!(event.id(), Some("7"));
+    assert_eq!(event.data(), Some("line 1\nline 2"));
+
+    let event = client.recv().await.unwrap();
+    assert_eq!(event.retry(), Some(Duration::from_millis(1500)));
+    assert_eq!(event.data(), None);
+
+    client.recv_closed().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_timeout() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| {
+        let events = stream::pending::<Result<(), Infallible>>()
+            .map(|event| event.map(|_| warp::sse::data("never")));
+        warp::sse::reply(events)
+    });
+
+    let mut client = warp::test::sse()
+        .connect(&route)
+        .await
+        .expect("connect")
+        .timeout(Duration::from_millis(10));
+
+    let err = client.recv().await.unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_client_not_event_stream() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("events").map(warp::reply);
+
+    let err = warp::test::sse()
+        .path("/nope")
+        .connect(&route)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("404"), "{}", err);
+}
+
+#[tokio::test]
+async fn keep_alive_max_lifetime() {
+    let _ = pretty_env_logger::try_init();
+
+    let keep_alive = warp::sse::keep_alive()
+        .interval(Duration::from_millis(5))
+        .jitter(Duration::from_millis(2))
+        .max_lifetime(Duration::from_millis(100));
+    let route = warp::any().map(move || {
+        // Override the retry hint of this connection.
+        let keep_alive = keep_alive.clone().retry(Duration::from_millis(250));
+        let events = stream::pending::<Result<(), Infallible>>()
+            .map(|event| event.map(|_| warp::sse::data("never")));
+        warp::sse::reply(keep_alive.stream(events))
+    });
+
+    let mut client = warp::test::sse().connect(&route).await.expect("connect");
+
+    // Keep-alive comments aren't events, so the retry hint comes first.
+    let event = client.recv().await.unwrap();
+    assert_eq!(event.retry(), Some(Duration::from_millis(250)));
+    client.recv_closed().await.unwrap();
+}
@@ -0,0 +1,54 @@
+#![deny(warnings)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use warp::state::State;
+use warp::Filter;
+
+#[tokio::test]
+async fn with_state() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("hits")
+        .with_state(AtomicUsize::new(0))
+        .map(|hits: State<AtomicUsize>| (hits.fetch_add(1, Ordering::SeqCst) + 1).to_string());
+
+    for n in 1..3 {
+        let res = warp::test::request().path("/hits").reply(&route).await;
+        assert_eq!(res.body(), &n.to_string());
+    }
+
+    // the state is registered for later filters
+    let route = warp::any()
+        .with_state(String::from("config"))
+        .and(warp::state::<String>())
+        .map(|a: State<String>, b: State<String>| format!("{} {}", *a, *b));
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "config config");
+}
+
+#[tokio::test]
+async fn provide() {
+    let _ = pretty_env_logger::try_init();
+
+    let hits = warp::path("hits")
+        .and(warp::state::<AtomicUsize>())
+        .map(|hits: State<AtomicUsize>| (hits.fetch_add(1, Ordering::SeqCst) + 1).to_string());
+    let name = warp::path("name")
+        .and(warp::state::<String>())
+        .map(|name: State<String>| name.to_string());
+    let routes = hits
+        .or(name)
+        .with(warp::state::provide(AtomicUsize::new(10)))
+        .with(warp::state::provide(String::from("warp")));
+
+    let res = warp::test::request().path("/hits").reply(&routes).await;
+    assert_eq!(res.body(), "11");
+    let res = warp::test::request().path("/hits").reply(&routes).await;
+    assert_eq!(res.body(), "12");
+    let res = warp::test::request().path("/name").reply(&routes).await;
+    assert_eq!(res.body(), "warp");
+
+    // missing state is a server error
+    let res = warp::test::request().path("/hits").reply(&hits).await;
+    assert_eq!(res.status(), 500);
+}
@@ -0,0 +1,90 @@
+#![deny(warnings)]
+
+#[cfg(feature = "askama")]
+mod askama {
+    use askama::Template;
+    use warp::Filter;
+
+    #[derive(Template)]
+    #[template(source = "<p>Hello, {{ name }}!</p>", ext = "html")]
+    struct Hello {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn render() {
+        let _ = pretty_env_logger::try_init();
+
+        let route = warp::any().and_then(|| async {
+            let hello = Hello {
+                name: "<b>".to_owned(),
+            };
+            warp::reply::template::askama(&hello)
+        });
+
+        let res = warp::test::request().reply(&route).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+        assert_eq!(res.body(), "<p>Hello, &lt;b&gt;!</p>");
+    }
+}
+
+#[cfg(feature = "tera")]
+mod tera {
+    use std::sync::Arc;
+    use tera::{Context, Tera};
+    use warp::reply::template::TemplateError;
+    use warp::Filter;
+
+    fn tera() -> Arc<Tera> {
+        let mut tera = Tera::default();
+        tera.add_raw_template("hello.html", "<p>Hello, {{ name }}!</p>")
+            .unwrap();
+        Arc::new(tera)
+    }
+
+    #[tokio::test]
+    async fn render() {
+        let _ = pretty_env_logger::try_init();
+
+        let tera = tera();
+        let route = warp::any().and_then(move || {
+            let tera = tera.clone();
+            async move {
+                let mut context = Context::new();
+                context.insert("name", "world");
+                warp::reply::template::tera(&tera, "hello.html", &context)
+            }
+        });
+
+        let res = warp::test::request().reply(&route).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+        assert_eq!(res.body(), "<p>Hello, world!</p>");
+    }
+
+    #[tokio::test]
+    async fn render_error() {
+        let _ = pretty_env_logger::try_init();
+
+        let tera = tera();
+        let render = warp::any().and_then(move || {
+            let tera = tera.clone();
+            async move {
+                // `name` is missing from the context
+                warp::reply::template::tera(&tera, "hello.html", &Context::new())
+            }
+        });
+
+        let err = warp::test::request()
+            .filter(&render)
+            .await
+            .err()
+            .expect("render error");
+        let err = err.find::<TemplateError>().expect("template error");
+        assert_eq!(err.template(), "hello.html");
+
+        let res = warp::test::request().reply(&render).await;
+        assert_eq!(res.status(), 500);
+    }
+}
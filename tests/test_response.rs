@@ -0,0 +1,56 @@
+#![deny(warnings)]
+use serde_derive::Deserialize;
+use serde_json::json;
+use warp::test::ResponseExt;
+use warp::Filter;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct User {
+    id: u32,
+    name: String,
+}
+
+fn route() -> impl Filter<Extract = (warp::reply::Json,), Error = std::convert::Infallible> + Clone
+{
+    warp::any().map(|| warp::reply::json(&json!({ "id": 7, "name": "sean" })))
+}
+
+#[tokio::test]
+async fn assertions() {
+    let _ = pretty_env_logger::try_init();
+
+    let res = warp::test::request().reply(&route()).await;
+    res.assert_status(200)
+        .assert_header("content-type", "application/json")
+        .assert_json(&json!({ "id": 7, "name": "sean" }));
+
+    let user: User = res.json_body();
+    assert_eq!(
+        user,
+        User {
+            id: 7,
+            name: "sean".to_owned()
+        }
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "-  \"name\": \"seanmonstar\"\n+  \"name\": \"sean\"")]
+async fn assert_json_diff() {
+    let res = warp::test::request().reply(&route()).await;
+    res.assert_json(&json!({ "id": 7, "name": "seanmonstar" }));
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected status")]
+async fn assert_status_mismatch() {
+    let res = warp::test::request().reply(&route()).await;
+    res.assert_status(404);
+}
+
+#[tokio::test]
+#[should_panic(expected = "expected header content-type")]
+async fn assert_header_mismatch() {
+    let res = warp::test::request().reply(&route()).await;
+    res.assert_header("content-type", "text/plain");
+}
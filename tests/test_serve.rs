@@ -0,0 +1,52 @@
+#![deny(warnings)]
+use warp::hyper::{body, Client, Version};
+use warp::Filter;
+
+#[tokio::test]
+async fn serve_http1() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("hello").map(|| "world");
+    let server = warp::test::serve(route).start();
+    assert!(server.addr().ip().is_loopback());
+    assert_eq!(
+        server.url("/hello"),
+        format!("http://{}/hello", server.addr())
+    );
+
+    let res = Client::new()
+        .get(server.url("/hello").parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.version(), Version::HTTP_11);
+    assert_eq!(res.status(), 200);
+    assert_eq!(body::to_bytes(res.into_body()).await.unwrap(), "world");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn serve_http2() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::any().map(|| "hello");
+    let server = warp::test::serve(route).start();
+
+    let client = Client::builder()
+        .http2_only(true)
+        .build_http::<warp::hyper::Body>();
+    let res = client.get(server.url("/").parse().unwrap()).await.unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn shutdown_closes_listener() {
+    let _ = pretty_env_logger::try_init();
+
+    let server = warp::test::serve(warp::any().map(warp::reply)).start();
+    let addr = server.addr();
+    server.shutdown().await;
+
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
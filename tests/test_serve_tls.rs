@@ -0,0 +1,69 @@
+#![deny(warnings)]
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{Certificate, ClientConfig, Session};
+use tokio_rustls::webpki::DNSNameRef;
+use tokio_rustls::TlsConnector;
+use warp::Filter;
+
+fn connector(server: &warp::test::TestServer, alpn: &[&[u8]]) -> TlsConnector {
+    let mut config = ClientConfig::new();
+    config
+        .root_store
+        .add(&Certificate(server.certificate_der().unwrap().to_vec()))
+        .unwrap();
+    config.set_protocols(&alpn.iter().map(|p| p.to_vec()).collect::<Vec<_>>());
+    TlsConnector::from(Arc::new(config))
+}
+
+#[tokio::test]
+async fn serve_tls() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::path("hello").map(|| "world");
+    let server = warp::test::serve(route).tls().start();
+    assert_eq!(
+        server.url("/hello"),
+        format!("https://localhost:{}/hello", server.addr().port())
+    );
+    assert!(server
+        .certificate_pem()
+        .unwrap()
+        .starts_with("-----BEGIN CERTIFICATE-----\n"));
+
+    let tcp = TcpStream::connect(server.addr()).await.unwrap();
+    let domain = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let mut tls = connector(&server, &[b"http/1.1"])
+        .connect(domain, tcp)
+        .await
+        .expect("certificate is trusted");
+
+    tls.write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut res = String::new();
+    tls.read_to_string(&mut res).await.unwrap();
+    assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{}", res);
+    assert!(res.ends_with("\r\n\r\nworld"), "{}", res);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn serve_tls_alpn_h2() {
+    let _ = pretty_env_logger::try_init();
+
+    let server = warp::test::serve(warp::any().map(warp::reply))
+        .tls()
+        .start();
+
+    let tcp = TcpStream::connect(server.addr()).await.unwrap();
+    let domain = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let tls = connector(&server, &[b"h2", b"http/1.1"])
+        .connect(domain, tcp)
+        .await
+        .unwrap();
+    assert_eq!(tls.get_ref().1.get_alpn_protocol(), Some(&b"h2"[..]));
+}
@@ -0,0 +1,203 @@
+#![deny(warnings)]
+use std::time::Duration;
+
+use tower::timeout::TimeoutLayer;
+use tower::ServiceExt;
+use tower_layer::{Identity, Layer};
+use warp::{http, hyper, Filter};
+
+#[tokio::test]
+async fn layer_timeout() {
+    let _ = pretty_env_logger::try_init();
+
+    let fast = warp::path("fast").map(warp::reply);
+    let slow = warp::path("slow").and_then(|| async {
+        tokio::time::delay_for(Duration::from_millis(500)).await;
+        Ok::<_, warp::Rejection>(warp::reply())
+    });
+    let routes = fast.or(slow).with(warp::tower::layer(TimeoutLayer::new(
+        Duration::from_millis(50),
+    )));
+
+    let res = warp::test::request().path("/fast").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/slow").reply(&routes).await;
+    assert_eq!(res.status(), 500);
+}
+
+#[tokio::test]
+async fn layer_passes_rejections() {
+    let _ = pretty_env_logger::try_init();
+
+    let a = warp::path("a")
+        .map(|| "a")
+        .with(warp::tower::layer(Identity::new()));
+    let b = warp::path("b")
+        .and(warp::ext::get::<u32>())
+        .and(warp::body::bytes())
+        .map(|ext: u32, body: bytes::Bytes| format!("{} {:?}", ext, body));
+    let routes = a.or(b);
+
+    let res = warp::test::request().path("/a").reply(&routes).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "a");
+
+    // the extensions and body are still there for the other branch
+    let res = warp::test::request()
+        .path("/b")
+        .extension(5u32)
+        .body("hi")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "5 b\"hi\"");
+
+    let res = warp::test::request().path("/c").reply(&routes).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn layer_keeps_matched_path() {
+    let _ = pretty_env_logger::try_init();
+
+    let users = warp::path("users")
+        .and(warp::path::end())
+        .map(|| "users")
+        .with(warp::tower::layer(Identity::new()));
+    let route = warp::path("api").and(users);
+
+    let res = warp::test::request().path("/api/users").reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "users");
+}
+
+#[tokio::test]
+async fn wrap_layer() {
+    let _ = pretty_env_logger::try_init();
+
+    let layer = warp::tower::wrap_layer(warp::wrap_fn(|filter| {
+        warp::header::exact("x-api-key", "secret").and(filter)
+    }));
+    let svc = layer.layer(warp::service(warp::any().map(|| "hello")));
+
+    let req = http::Request::get("/").body(hyper::Body::empty()).unwrap();
+    let res = svc.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    let req = http::Request::get("/")
+        .header("x-api-key", "secret")
+        .body(hyper::Body::empty())
+        .unwrap();
+    let res = svc.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "hello");
+}
+
+#[tokio::test]
+async fn service_filter_mount() {
+    let _ = pretty_env_logger::try_init();
+
+    let svc = tower::service_fn(|req: http::Request<hyper::Body>| async move {
+        let uri = req.uri().to_string();
+        let body = hyper::body::to_bytes(req.into_body()).await?;
+        let res = format!("{} {:?}", uri, body);
+        Ok::<_, hyper::Error>(http::Response::new(hyper::Body::from(res)))
+    });
+    let route = warp::path("mounted").and(warp::service_filter(svc));
+
+    let res = warp::test::request()
+        .path("/mounted/a/b?c=d")
+        .body("hi")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "/a/b?c=d b\"hi\"");
+
+    let res = warp::test::request().path("/mounted").reply(&route).await;
+    assert_eq!(res.body(), "/ b\"\"");
+
+    let res = warp::test::request().path("/other").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn service_filter_other_body() {
+    let _ = pretty_env_logger::try_init();
+
+    let svc = tower::service_fn(|_req: http::Request<hyper::Body>| async {
+        let body = Box::new(hyper::Body::from("boxed"));
+        Ok::<_, hyper::Error>(http::Response::new(body))
+    });
+    let route = warp::service_filter(svc);
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "boxed");
+}
+
+#[tokio::test]
+async fn service_filter_warp_service() {
+    let _ = pretty_env_logger::try_init();
+
+    let legacy = warp::service(warp::path("hello").map(|| "hello"));
+    let route = warp::path("legacy").and(warp::service_filter(legacy));
+
+    let res = warp::test::request()
+        .path("/legacy/hello")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "hello");
+}
+
+#[tokio::test]
+async fn service_filter_error() {
+    let _ = pretty_env_logger::try_init();
+
+    let svc = tower::service_fn(|_req: http::Request<hyper::Body>| async {
+        Err::<http::Response<hyper::Body>, _>("oops")
+    });
+    let route = warp::service_filter(svc).recover(|err: warp::Rejection| async move {
+        match err.find::<warp::tower::ServiceError>() {
+            Some(_) => Ok(http::StatusCode::BAD_GATEWAY),
+            None => Err(err),
+        }
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[cfg(debug_assertions)]
+#[tokio::test]
+#[should_panic(expected = "nested route::set calls")]
+async fn nested_service_outside_service_filter() {
+    async fn call_inner() -> Result<http::StatusCode, warp::Rejection> {
+        let inner = warp::service(warp::any().map(warp::reply));
+        let req = http::Request::get("/").body(hyper::Body::empty()).unwrap();
+        let res = inner.oneshot(req).await.unwrap();
+        Ok(res.status())
+    }
+    let route = warp::any().and_then(call_inner);
+
+    warp::test::request().reply(&route).await;
+}
+
+#[tokio::test]
+async fn service_filter_nested_warp_services() {
+    let _ = pretty_env_logger::try_init();
+
+    // A warp service mounted in a warp service, itself mounted in a filter.
+    let inner = warp::service(warp::path("inner").map(|| "inner"));
+    let middle = warp::service(warp::path("middle").and(warp::service_filter(inner)));
+    let route = warp::path("outer").and(warp::service_filter(middle));
+
+    let res = warp::test::request()
+        .path("/outer/middle/inner")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "inner");
+}
@@ -0,0 +1,155 @@
+#![deny(warnings)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use warp::Filter;
+
+#[tokio::test]
+async fn request() {
+    let _ = pretty_env_logger::try_init();
+
+    let ok = warp::path("ok").map(warp::reply);
+    let err = warp::path("err").and_then(|| async { Err::<String, _>(warp::reject::not_found()) });
+    let route = ok.or(err).with(warp::trace::request());
+
+    let res = warp::test::request()
+        .path("/ok?q=1")
+        .header("user-agent", "warp-test")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/err").reply(&route).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn custom() {
+    let _ = pretty_env_logger::try_init();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    let route = warp::path("hello")
+        .map(|| "Hello")
+        .with(warp::trace(move |info| {
+            assert_eq!(info.method(), "POST");
+            assert_eq!(info.path(), "/hello");
+            assert_eq!(info.query(), Some("name=warp"));
+            counted.fetch_add(1, Ordering::SeqCst);
+            tracing::info_span!("custom", path = %info.path())
+        }));
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/hello?name=warp")
+        .reply(&route)
+        .await;
+    assert_eq!(res.body(), "Hello");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn server_timing() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::trace::timings()
+        .map(|timings: warp::trace::Timings| {
+            timings.record("db", Duration::from_micros(12_340));
+            timings.start("render").stop();
+            "hello"
+        })
+        .with(warp::trace::server_timing());
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "hello");
+
+    let value = res.headers()["server-timing"].to_str().unwrap();
+    let names: Vec<_> = value
+        .split(", ")
+        .map(|metric| metric.split(';').next().unwrap())
+        .collect();
+    assert_eq!(names, ["db", "render", "total"]);
+    assert!(value.starts_with("db;dur=12.3, render;dur="), "{}", value);
+}
+
+#[tokio::test]
+async fn timings_without_server_timing() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::trace::timings().map(|timings: warp::trace::Timings| {
+        timings.record("db", Duration::from_millis(1));
+        "hello"
+    });
+
+    let res = warp::test::request().reply(&route).await;
+    assert_eq!(res.body(), "hello");
+    assert!(!res.headers().contains_key("server-timing"));
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::baggage::BaggageExt;
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+    use warp::http::HeaderMap;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn extract_traceparent() {
+        let cx = warp::trace::extract_context(&headers(&[
+            (
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ),
+            ("tracestate", "vendor=value"),
+        ]));
+        let span = cx.span();
+        let sc = span.span_context();
+        assert!(sc.is_valid());
+        assert!(sc.is_remote());
+        assert!(sc.is_sampled());
+        assert_eq!(
+            sc.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        assert_eq!(sc.trace_state().get("vendor"), Some("value"));
+    }
+
+    #[test]
+    fn extract_invalid_traceparent() {
+        for value in &[
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "garbage",
+        ] {
+            let cx = warp::trace::extract_context(&headers(&[("traceparent", value)]));
+            assert!(!cx.span().span_context().is_valid(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn extract_baggage() {
+        let cx = warp::trace::extract_context(&headers(&[(
+            "baggage",
+            "userId=alice, serverNode=DF%2028;prop=1,=nokey",
+        )]));
+        let baggage = cx.baggage();
+        assert_eq!(baggage.len(), 2);
+        assert_eq!(
+            baggage.get("userId").map(|v| v.as_str().into_owned()),
+            Some("alice".to_owned())
+        );
+        assert_eq!(
+            baggage.get("serverNode").map(|v| v.as_str().into_owned()),
+            Some("DF 28".to_owned())
+        );
+    }
+}
@@ -0,0 +1,100 @@
+#![deny(warnings)]
+use std::net::SocketAddr;
+
+use warp::http::Uri;
+use warp::Filter;
+
+fn proxy() -> SocketAddr {
+    ([10, 0, 0, 1], 4000).into()
+}
+
+#[tokio::test]
+async fn absolute() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::uri::absolute();
+
+    let uri: Uri = warp::test::request()
+        .path("/login?next=%2F")
+        .header("host", "example.com:80")
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(uri, "http://example.com/login?next=%2F");
+
+    // forwarding headers of untrusted clients are ignored
+    let uri = warp::test::request()
+        .path("/login")
+        .header("host", "localhost:3030")
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-host", "evil.example")
+        .remote_addr(proxy())
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(uri, "http://localhost:3030/login");
+
+    let res = warp::test::request()
+        .path("/login")
+        .reply(&route.map(|uri: Uri| uri.to_string()))
+        .await;
+    assert_eq!(res.status(), 400);
+
+    let uri = warp::test::request()
+        .path("/login")
+        .header("host", "example.com")
+        .filter(&warp::uri::absolute().scheme("https"))
+        .await
+        .unwrap();
+    assert_eq!(uri, "https://example.com/login");
+}
+
+#[tokio::test]
+async fn forwarded() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::uri::absolute().trust(proxy().ip());
+
+    let uri = warp::test::request()
+        .path("/login")
+        .header("host", "localhost:3030")
+        .header(
+            "forwarded",
+            "for=192.0.2.60;proto=HTTPS;host=\"example.com\", for=10.0.0.2",
+        )
+        .remote_addr(proxy())
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(uri, "https://example.com/login");
+
+    let uri = warp::test::request()
+        .path("/login")
+        .header("host", "localhost:3030")
+        .header("x-forwarded-proto", "https, http")
+        .header("x-forwarded-host", "example.com")
+        .header("x-forwarded-port", "8443")
+        .remote_addr(proxy())
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(uri, "https://example.com:8443/login");
+
+    let uri = warp::test::request()
+        .path("/")
+        .header("host", "localhost:3030")
+        .header("x-forwarded-proto", "https")
+        .filter(&warp::uri::absolute().trust_all())
+        .await
+        .unwrap();
+    assert_eq!(uri, "https://localhost:3030/");
+
+    let res = warp::test::request()
+        .path("/login")
+        .header("host", "localhost:3030")
+        .header("x-forwarded-port", "https")
+        .remote_addr(proxy())
+        .reply(&route.map(|uri: Uri| uri.to_string()))
+        .await;
+    assert_eq!(res.status(), 400);
+}
@@ -0,0 +1,83 @@
+#![deny(warnings)]
+use warp::version::Version;
+use warp::Filter;
+
+#[tokio::test]
+async fn path() {
+    let _ = pretty_env_logger::try_init();
+
+    let version = warp::version::path();
+
+    let req = warp::test::request().path("/v2/users");
+    assert_eq!(req.filter(&version).await.unwrap(), "2");
+
+    let req = warp::test::request().path("/v2.1");
+    assert_eq!(req.filter(&version).await.unwrap(), Version::new("v2.1"));
+
+    for path in &["/users", "/v", "/vx", "/v2x"] {
+        let req = warp::test::request().path(path);
+        assert!(!req.matches(&version).await, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn header() {
+    let _ = pretty_env_logger::try_init();
+
+    let version = warp::version::header("api-version");
+
+    let req = warp::test::request().header("api-version", "v3");
+    assert_eq!(req.filter(&version).await.unwrap(), "3");
+
+    let req = warp::test::request();
+    let res = req.reply(&version.map(|_| "")).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn accept() {
+    let _ = pretty_env_logger::try_init();
+
+    let version = warp::version::accept("example");
+
+    let req =
+        warp::test::request().header("accept", "text/html, application/vnd.example.v2+json;q=0.9");
+    assert_eq!(req.filter(&version).await.unwrap(), "2");
+
+    let req = warp::test::request().header("accept", "application/vnd.example+json; version=3");
+    assert_eq!(req.filter(&version).await.unwrap(), "3");
+
+    let req = warp::test::request().header("accept", "application/vnd.examples.v2+json");
+    assert!(!req.matches(&version).await);
+
+    let req = warp::test::request().header("accept", "application/json");
+    assert!(!req.matches(&version).await);
+}
+
+#[tokio::test]
+async fn dispatch() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = warp::version::dispatch(warp::version::path())
+        .on("1", warp::path("users").map(|| "users v1"))
+        .on("v2", warp::path("users").map(|| "users v2"))
+        .on("2", warp::path("teams").map(|| "teams v2"));
+
+    let versions = api.versions().map(Version::as_str).collect::<Vec<_>>();
+    assert_eq!(versions, ["1", "2"]);
+
+    let req = warp::test::request().path("/v1/users");
+    assert_eq!(req.reply(&api).await.body(), "users v1");
+
+    let req = warp::test::request().path("/v2/users");
+    assert_eq!(req.reply(&api).await.body(), "users v2");
+
+    let req = warp::test::request().path("/v2/teams");
+    assert_eq!(req.reply(&api).await.body(), "teams v2");
+
+    let req = warp::test::request().path("/v1/teams");
+    assert_eq!(req.reply(&api).await.status(), 404);
+
+    let req = warp::test::request().path("/v3/users");
+    assert_eq!(req.reply(&api).await.status(), 404);
+}
@@ -0,0 +1,166 @@
+#![deny(warnings)]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use ring::hmac;
+use warp::webhook::{InvalidWebhookSignature, Scheme, Verified};
+use warp::Filter;
+
+const SECRET: &str = "It's a Secret to Everybody";
+const BODY: &str = r#"{"action":"opened"}"#;
+
+fn sign(message: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+    hmac::sign(&key, message.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[tokio::test]
+async fn github() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::webhook::verify(Scheme::github(SECRET));
+
+    // the example of the GitHub documentation
+    let verified = warp::test::request()
+        .header(
+            "x-hub-signature-256",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+        )
+        .body("Hello, World!")
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(verified.body(), "Hello, World!");
+    assert_eq!(verified.timestamp(), None);
+
+    let err = warp::test::request()
+        .header("x-hub-signature-256", format!("sha256={}", sign("other")))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(err.find::<InvalidWebhookSignature>().is_some());
+
+    let res = warp::test::request()
+        .header("x-hub-signature-256", "sha256=zz")
+        .body(BODY)
+        .reply(&route.clone().map(|_: Verified| warp::reply()))
+        .await;
+    assert_eq!(res.status(), 403);
+
+    let res = warp::test::request()
+        .body(BODY)
+        .reply(&route.clone().map(|_: Verified| warp::reply()))
+        .await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn stripe() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::webhook::verify_json(Scheme::stripe(SECRET));
+    let t = now();
+    let signature = sign(&format!("{}.{}", t, BODY));
+
+    let (json, raw): (serde_json::Value, Bytes) = warp::test::request()
+        .header(
+            "stripe-signature",
+            format!("t={},v1={},v1={},v0=abc", t, sign("old secret"), signature),
+        )
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(json["action"], "opened");
+    assert_eq!(raw, BODY);
+
+    // an old request can't be replayed
+    let t = now() - 600;
+    let signature = sign(&format!("{}.{}", t, BODY));
+    let err = warp::test::request()
+        .header("stripe-signature", format!("t={},v1={}", t, signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(err.find::<InvalidWebhookSignature>().is_some());
+
+    let route = warp::webhook::verify(Scheme::stripe(SECRET).tolerance(Duration::from_secs(3600)));
+    let verified = warp::test::request()
+        .header("stripe-signature", format!("t={},v1={}", t, signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(
+        verified.timestamp(),
+        Some(UNIX_EPOCH + Duration::from_secs(t))
+    );
+}
+
+#[tokio::test]
+async fn slack() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::webhook::verify(Scheme::slack(SECRET));
+    let t = now();
+    let signature = sign(&format!("v0:{}:{}", t, BODY));
+
+    let verified = warp::test::request()
+        .header("x-slack-request-timestamp", t.to_string())
+        .header("x-slack-signature", format!("v0={}", signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap();
+    assert_eq!(verified.body(), BODY);
+
+    let err = warp::test::request()
+        .header("x-slack-request-timestamp", (t + 1).to_string())
+        .header("x-slack-signature", format!("v0={}", signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(err.find::<InvalidWebhookSignature>().is_some());
+}
+
+#[tokio::test]
+async fn out_of_range_timestamp() {
+    let _ = pretty_env_logger::try_init();
+
+    let t = u64::MAX;
+
+    let route = warp::webhook::verify(Scheme::stripe(SECRET));
+    let signature = sign(&format!("{}.{}", t, BODY));
+    let err = warp::test::request()
+        .header("stripe-signature", format!("t={},v1={}", t, signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(err.find::<InvalidWebhookSignature>().is_some());
+
+    let route = warp::webhook::verify(Scheme::slack(SECRET));
+    let signature = sign(&format!("v0:{}:{}", t, BODY));
+    let err = warp::test::request()
+        .header("x-slack-request-timestamp", t.to_string())
+        .header("x-slack-signature", format!("v0={}", signature))
+        .body(BODY)
+        .filter(&route)
+        .await
+        .unwrap_err();
+    assert!(err.find::<InvalidWebhookSignature>().is_some());
+}
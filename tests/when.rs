@@ -0,0 +1,111 @@
+#![deny(warnings)]
+use warp::when::{self, when};
+use warp::Filter;
+
+#[tokio::test]
+async fn header() {
+    let _ = pretty_env_logger::try_init();
+
+    let canary = when(when::header("x-canary", "1"));
+
+    let req = warp::test::request().header("x-canary", "1");
+    assert!(req.matches(&canary).await);
+
+    let req = warp::test::request().header("x-canary", "0");
+    assert!(!req.matches(&canary).await);
+
+    let present = when(when::header_present("x-canary"));
+    let req = warp::test::request().header("x-canary", "0");
+    assert!(req.matches(&present).await);
+
+    let req = warp::test::request();
+    assert!(!req.matches(&present).await);
+}
+
+#[tokio::test]
+async fn query() {
+    let _ = pretty_env_logger::try_init();
+
+    let beta = when(when::query("beta", "on"));
+
+    let req = warp::test::request().path("/?page=2&beta=on");
+    assert!(req.matches(&beta).await);
+
+    let req = warp::test::request().path("/?beta=off");
+    assert!(!req.matches(&beta).await);
+}
+
+#[tokio::test]
+async fn remote_ip() {
+    let _ = pretty_env_logger::try_init();
+
+    let internal = when(when::remote_ip("10.0.0.0/8"));
+
+    let req = warp::test::request().remote_addr("10.1.2.3:8080".parse().unwrap());
+    assert!(req.matches(&internal).await);
+
+    let req = warp::test::request().remote_addr("[::ffff:10.1.2.3]:8080".parse().unwrap());
+    assert!(req.matches(&internal).await);
+
+    let req = warp::test::request().remote_addr("11.1.2.3:8080".parse().unwrap());
+    assert!(!req.matches(&internal).await);
+
+    let req = warp::test::request();
+    assert!(!req.matches(&internal).await);
+
+    let local = when(when::remote_ip("::1"));
+    let req = warp::test::request().remote_addr("[::1]:8080".parse().unwrap());
+    assert!(req.matches(&local).await);
+}
+
+#[test]
+#[should_panic(expected = "illegal network")]
+fn remote_ip_illegal() {
+    when::remote_ip("10.0.0.0/33");
+}
+
+#[tokio::test]
+async fn percent() {
+    let _ = pretty_env_logger::try_init();
+
+    let all = when(when::percent(100.0));
+    let none = when(when::percent(0.0));
+    let some = when(when::percent(50.0));
+
+    let mut matched = 0;
+    for _ in 0..200 {
+        assert!(warp::test::request().matches(&all).await);
+        assert!(!warp::test::request().matches(&none).await);
+        if warp::test::request().matches(&some).await {
+            matched += 1;
+        }
+    }
+    assert!(matched > 50 && matched < 150, "matched {}", matched);
+}
+
+#[tokio::test]
+async fn combined() {
+    let _ = pretty_env_logger::try_init();
+
+    let guard = when::header("x-canary", "1").or(when::query("canary", "1"));
+    assert_eq!(
+        guard.to_string(),
+        "(header(x-canary = \"1\")) or (query(canary = \"1\"))"
+    );
+
+    let routes = when(guard.clone())
+        .map(|| "canary")
+        .or(when(!guard).map(|| "stable"));
+
+    let req = warp::test::request().path("/?canary=1");
+    assert_eq!(req.reply(&routes).await.body(), "canary");
+
+    let req = warp::test::request().path("/");
+    assert_eq!(req.reply(&routes).await.body(), "stable");
+
+    let both = when(when::header("a", "1").and(when::header("b", "1")));
+    let req = warp::test::request().header("a", "1");
+    assert!(!req.matches(&both).await);
+    let req = warp::test::request().header("a", "1").header("b", "1");
+    assert!(req.matches(&both).await);
+}
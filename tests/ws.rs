@@ -145,6 +145,64 @@ async fn closed() {
     client.recv_closed().await.expect("closed");
 }
 
+#[tokio::test]
+async fn binary_helpers() {
+    let _ = pretty_env_logger::try_init();
+
+    let mut client = warp::test::ws()
+        .handshake(ws_echo())
+        .await
+        .expect("handshake");
+
+    client.send(Message::ping("clt")).await;
+    client.send_binary(&b"bonk"[..]).await;
+    assert_eq!(client.recv_binary().await.expect("recv"), b"bonk");
+
+    client.send_text("hello warp").await;
+    let err = client.recv_binary().await.unwrap_err();
+    assert!(err.to_string().contains("expected binary"), "{}", err);
+}
+
+#[tokio::test]
+async fn close_frame() {
+    let _ = pretty_env_logger::try_init();
+
+    let route = warp::ws().map(|ws: warp::ws::Ws| {
+        ws.on_upgrade(|mut websocket| async move {
+            websocket.send(Message::text("bye")).await.unwrap();
+            websocket
+                .send(Message::close_with(4000u16, "going away"))
+                .await
+                .unwrap();
+        })
+    });
+
+    let mut client = warp::test::ws().handshake(route).await.expect("handshake");
+
+    assert_eq!(client.recv_text().await.expect("recv"), "bye");
+    assert_eq!(
+        client.recv_close_frame().await.expect("closed"),
+        Some((4000, "going away".to_owned()))
+    );
+}
+
+#[tokio::test]
+async fn recv_timeout() {
+    let _ = pretty_env_logger::try_init();
+
+    let mut client = warp::test::ws()
+        .handshake(ws_echo())
+        .await
+        .expect("handshake")
+        .timeout(std::time::Duration::from_millis(50));
+
+    let err = client.recv().await.unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{}", err);
+
+    client.send_text("still there").await;
+    assert_eq!(client.recv_text().await.expect("recv"), "still there");
+}
+
 #[tokio::test]
 async fn limit_message_size() {
     let _ = pretty_env_logger::try_init();